@@ -0,0 +1,149 @@
+//! Key generation, proving and verification times of the RIPEMD-160 Table16 circuit
+//! over the bn256 scalar field, for messages of 1, 2 and 4 blocks.
+//!
+//! Run with `cargo bench --features bench`.
+use std::convert::TryInto;
+
+use bitcoinvm_gadgets::ripemd160::{
+    ref_impl::{
+        constants::{BLOCK_SIZE, BLOCK_SIZE_BYTES},
+        ripemd160::pad_message_bytes,
+    },
+    table16::{BlockWord, Table16Chip, Table16Config},
+    DigestByteOrder, RIPEMD160,
+};
+use criterion::{criterion_group, criterion_main, Criterion};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    dev::MockProver,
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, ConstraintSystem, Error,
+        ProvingKey, VerifyingKey,
+    },
+    poly::kzg::{
+        commitment::{KZGCommitmentScheme, ParamsKZG},
+        multiopen::{ProverGWC, VerifierGWC},
+        strategy::SingleStrategy,
+    },
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+    },
+};
+use rand::rngs::OsRng;
+
+#[derive(Clone)]
+struct RIPEMD160Circuit {
+    data: Vec<[BlockWord; BLOCK_SIZE]>,
+}
+
+impl RIPEMD160Circuit {
+    /// A message of `num_blocks * 64 - 9` bytes, the longest one which is padded to
+    /// `num_blocks` blocks
+    fn new(num_blocks: usize) -> Self {
+        let message = vec![0xab; num_blocks * BLOCK_SIZE_BYTES - 9];
+        let data = pad_message_bytes(message)
+            .into_iter()
+            .map(|block| {
+                let words: Vec<BlockWord> = block
+                    .chunks(4)
+                    .map(|word| BlockWord::from(u32::from_le_bytes(word.try_into().unwrap())))
+                    .collect();
+                words.try_into().unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(data.len(), num_blocks);
+        RIPEMD160Circuit { data }
+    }
+}
+
+impl Circuit<Fr> for RIPEMD160Circuit {
+    type Config = Table16Config<Fr>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        RIPEMD160Circuit {
+            data: vec![[BlockWord::default(); BLOCK_SIZE]; self.data.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        Table16Chip::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+        let table16_chip = Table16Chip::construct(config.clone());
+        Table16Chip::load(config, &mut layouter)?;
+        RIPEMD160::digest(table16_chip, layouter, &self.data, DigestByteOrder::Internal)?;
+        Ok(())
+    }
+}
+
+/// Returns the smallest `k` for which the circuit fits in `2^k` rows. The spread table
+/// alone has `2^16` rows, so `k` is at least 17.
+fn min_k(circuit: &RIPEMD160Circuit) -> u32 {
+    (17..)
+        .find(|&k| MockProver::run(k, circuit, vec![]).map_or(false, |prover| prover.verify().is_ok()))
+        .unwrap()
+}
+
+fn prove(params: &ParamsKZG<Bn256>, pk: &ProvingKey<G1Affine>, circuit: RIPEMD160Circuit) -> Vec<u8> {
+    let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<Bn256>, ProverGWC<'_, Bn256>, _, _, _, _>(
+        params,
+        pk,
+        &[circuit],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    transcript.finalize()
+}
+
+fn verify(params: &ParamsKZG<Bn256>, vk: &VerifyingKey<G1Affine>, proof: &[u8]) {
+    let strategy = SingleStrategy::new(params);
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(proof);
+    assert!(verify_proof::<
+        KZGCommitmentScheme<Bn256>,
+        VerifierGWC<'_, Bn256>,
+        Challenge255<G1Affine>,
+        Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
+        SingleStrategy<'_, Bn256>,
+    >(params, vk, strategy, &[&[]], &mut transcript)
+    .is_ok());
+}
+
+fn bench_ripemd160(c: &mut Criterion) {
+    for num_blocks in [1, 2, 4] {
+        let circuit = RIPEMD160Circuit::new(num_blocks);
+        let k = min_k(&circuit);
+        println!("RIPEMD-160 of {} blocks: minimum k = {}", num_blocks, k);
+
+        let params = ParamsKZG::<Bn256>::setup(k, OsRng);
+        let empty_circuit = circuit.without_witnesses();
+
+        let mut group = c.benchmark_group(format!("ripemd160-{}-blocks", num_blocks));
+        group.sample_size(10);
+
+        group.bench_function("keygen", |b| {
+            b.iter(|| {
+                let vk = keygen_vk(&params, &empty_circuit).expect("keygen_vk should not fail");
+                keygen_pk(&params, vk, &empty_circuit).expect("keygen_pk should not fail")
+            })
+        });
+
+        let vk = keygen_vk(&params, &empty_circuit).expect("keygen_vk should not fail");
+        let pk = keygen_pk(&params, vk, &empty_circuit).expect("keygen_pk should not fail");
+        group.bench_function("prove", |b| b.iter(|| prove(&params, &pk, circuit.clone())));
+
+        let proof = prove(&params, &pk, circuit.clone());
+        println!("RIPEMD-160 of {} blocks: proof size = {} bytes", num_blocks, proof.len());
+        group.bench_function("verify", |b| b.iter(|| verify(&params, pk.get_vk(), &proof)));
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_ripemd160);
+criterion_main!(benches);
@@ -1,6 +1,20 @@
 pub const MAX_SCRIPT_PUBKEY_SIZE : usize = 520;
 pub const MAX_STACK_DEPTH : usize = 33;
+// Maximum number of OP_CHECKSIG opcodes with a valid signature, i.e. of ECDSA verifications
 pub const MAX_CHECKSIG_COUNT: usize = 1;
+// Maximum number of public keys of an OP_CHECKMULTISIG
+pub const MAX_MULTISIG_N: usize = 3;
+// Smallest stack depth of an ExecutionChip. The counts, public keys, signatures and extra item
+// popped by OP_CHECKMULTISIG fit on the stack.
+pub const MIN_STACK_DEPTH: usize = 2 * MAX_MULTISIG_N + 3;
+const _: () = assert!(MIN_STACK_DEPTH <= MAX_STACK_DEPTH);
+// Maximum nesting depth of OP_IF and OP_NOTIF
+pub const MAX_CONDITION_DEPTH: usize = 4;
+// Maximum number of items on the alt stack of OP_TOALTSTACK and OP_FROMALTSTACK
+pub const MAX_ALTSTACK_DEPTH: usize = 4;
+// Entries of the condition stack for branches which are taken and not taken. Unused entries are zero.
+pub const BRANCH_TAKEN: u64 = 1;
+pub const BRANCH_NOT_TAKEN: u64 = 2;
 
 // A stack element is evaluates to true if it consists of non-zero bytes,
 // except when the non-zero bytes encode a negative zero (0x80).
@@ -10,9 +24,15 @@ pub const NEGATIVE_ZERO : u64 = 0x80;
 // So we represent the empty array by the negative zero.
 pub const EMPTY_ARRAY_REPRESENTATION : u64 = NEGATIVE_ZERO;
 
+// Byte length of the items of an initial stack which is not left by a scriptSig. The length of
+// an item cannot be told from its RLC, so it is larger than any number OP_SIZE can push and
+// than any item a hash gadget accepts.
+pub const UNKNOWN_ITEM_LENGTH: u64 = 1 << 40;
+
 // Data push opcodes https://en.bitcoin.it/wiki/Script#Constants
 pub const OP_0: usize                       = 0x00;
 pub const OP_PUSH_NEXT1: usize              = 0x01;
+pub const OP_PUSH_NEXT16: usize             = 0x10;
 pub const OP_PUSH_NEXT75: usize             = 0x4b;
 pub const OP_PUSHDATA1: usize               = 0x4c;
 pub const OP_PUSHDATA2: usize               = 0x4d;
@@ -24,9 +44,127 @@ pub const OP_16: usize                      = 0x60;
 
 // Flow control opcodes https://en.bitcoin.it/wiki/Script#Flow_control
 pub const OP_NOP: usize                     = 0x61;
+pub const OP_IF: usize                      = 0x63;
+pub const OP_NOTIF: usize                   = 0x64;
+pub const OP_ELSE: usize                    = 0x67;
+pub const OP_ENDIF: usize                   = 0x68;
+pub const OP_VERIFY: usize                  = 0x69;
+pub const OP_RETURN: usize                  = 0x6a;
+
+// Stack opcodes https://en.bitcoin.it/wiki/Script#Stack
+pub const OP_TOALTSTACK: usize              = 0x6b;
+pub const OP_FROMALTSTACK: usize            = 0x6c;
+pub const OP_2DROP: usize                   = 0x6d;
+pub const OP_2DUP: usize                    = 0x6e;
+pub const OP_3DUP: usize                    = 0x6f;
+pub const OP_2OVER: usize                   = 0x70;
+pub const OP_2ROT: usize                    = 0x71;
+pub const OP_2SWAP: usize                   = 0x72;
+pub const OP_DEPTH: usize                   = 0x74;
+pub const OP_DROP: usize                    = 0x75;
+pub const OP_DUP: usize                     = 0x76;
+pub const OP_NIP: usize                     = 0x77;
+pub const OP_OVER: usize                    = 0x78;
+pub const OP_PICK: usize                    = 0x79;
+pub const OP_ROLL: usize                    = 0x7a;
+pub const OP_ROT: usize                     = 0x7b;
+pub const OP_SWAP: usize                    = 0x7c;
+pub const OP_TUCK: usize                    = 0x7d;
+
+// Splice opcodes https://en.bitcoin.it/wiki/Script#Splice
+pub const OP_CAT: usize                     = 0x7e; // Disabled
+pub const OP_SUBSTR: usize                  = 0x7f; // Disabled
+pub const OP_LEFT: usize                    = 0x80; // Disabled
+pub const OP_RIGHT: usize                   = 0x81; // Disabled
+pub const OP_SIZE: usize                    = 0x82;
+
+// Bitwise logic opcodes https://en.bitcoin.it/wiki/Script#Bitwise_logic
+pub const OP_INVERT: usize                  = 0x83; // Disabled
+pub const OP_AND: usize                     = 0x84; // Disabled
+pub const OP_OR: usize                      = 0x85; // Disabled
+pub const OP_XOR: usize                     = 0x86; // Disabled
+pub const OP_EQUAL: usize                   = 0x87;
+pub const OP_EQUALVERIFY: usize             = 0x88;
+
+// Arithmetic opcodes https://en.bitcoin.it/wiki/Script#Arithmetic
+pub const OP_1ADD: usize                    = 0x8b;
+pub const OP_1SUB: usize                    = 0x8c;
+pub const OP_2MUL: usize                    = 0x8d; // Disabled
+pub const OP_2DIV: usize                    = 0x8e; // Disabled
+pub const OP_NEGATE: usize                  = 0x8f;
+pub const OP_ABS: usize                     = 0x90;
+pub const OP_NOT: usize                     = 0x91;
+pub const OP_0NOTEQUAL: usize               = 0x92;
+pub const OP_ADD: usize                     = 0x93;
+pub const OP_SUB: usize                     = 0x94;
+pub const OP_MUL: usize                     = 0x95; // Disabled
+pub const OP_DIV: usize                     = 0x96; // Disabled
+pub const OP_MOD: usize                     = 0x97; // Disabled
+pub const OP_LSHIFT: usize                  = 0x98; // Disabled
+pub const OP_RSHIFT: usize                  = 0x99; // Disabled
+pub const OP_BOOLAND: usize                 = 0x9a;
+pub const OP_BOOLOR: usize                  = 0x9b;
+pub const OP_NUMEQUAL: usize                = 0x9c;
+pub const OP_LESSTHAN: usize                = 0x9f;
+pub const OP_GREATERTHAN: usize             = 0xa0;
+pub const OP_LESSTHANOREQUAL: usize         = 0xa1;
+pub const OP_GREATERTHANOREQUAL: usize      = 0xa2;
+pub const OP_MIN: usize                     = 0xa3;
+pub const OP_MAX: usize                     = 0xa4;
+pub const OP_WITHIN: usize                  = 0xa5;
 
 // Cryptographic operations opcodes https://en.bitcoin.it/wiki/Script#Crypto
+pub const OP_RIPEMD160: usize               = 0xa6;
+pub const OP_SHA1: usize                    = 0xa7;
+pub const OP_SHA256: usize                  = 0xa8;
+pub const OP_HASH160: usize                 = 0xa9;
+pub const OP_HASH256: usize                 = 0xaa;
+pub const OP_CODESEPARATOR: usize           = 0xab;
 pub const OP_CHECKSIG: usize                = 0xac;
+pub const OP_CHECKSIGVERIFY: usize          = 0xad;
+pub const OP_CHECKMULTISIG: usize           = 0xae;
+
+// Locktime opcodes https://en.bitcoin.it/wiki/Script#Locktime
+pub const OP_CHECKLOCKTIMEVERIFY: usize     = 0xb1; // Former OP_NOP2, redefined by BIP65
+pub const OP_CHECKSEQUENCEVERIFY: usize     = 0xb2; // Former OP_NOP3, redefined by BIP112
+
+// Reserved no-op opcodes https://en.bitcoin.it/wiki/Script#Reserved_words
+pub const OP_NOP1: usize                    = 0xb0;
+pub const OP_NOP4: usize                    = 0xb3;
+pub const OP_NOP10: usize                   = 0xb9;
+
+// Opcodes disabled by Bitcoin, which fail the script even in a branch which is not taken
+pub const DISABLED_OPCODES: [usize; 15] = [
+    OP_CAT, OP_SUBSTR, OP_LEFT, OP_RIGHT, OP_INVERT, OP_AND, OP_OR, OP_XOR,
+    OP_2MUL, OP_2DIV, OP_MUL, OP_DIV, OP_MOD, OP_LSHIFT, OP_RSHIFT,
+];
+
+// Tapscript opcode https://github.com/bitcoin/bips/blob/master/bip-0342.mediawiki
+pub const OP_CHECKSIGADD: usize             = 0xba;
+
+// Signature hash types https://en.bitcoin.it/wiki/OP_CHECKSIG#Hashtype_SIGHASH_ALL_.28default.29
+pub const SIGHASH_ALL: u8                   = 0x01;
+pub const SIGHASH_NONE: u8                  = 0x02;
+pub const SIGHASH_SINGLE: u8                = 0x03;
+pub const SIGHASH_ANYONECANPAY: u8          = 0x80;
+
+// Lengths of a DER-encoded signature followed by its sighash type byte, and ASN.1 tags of the
+// sequence holding r and s and of the integers r and s
+// https://github.com/bitcoin/bips/blob/master/bip-0066.mediawiki
+pub const MIN_DER_SIGNATURE_SIZE: usize     = 9;
+pub const MAX_DER_SIGNATURE_SIZE: usize     = 73;
+pub const DER_SEQUENCE_TAG: u8              = 0x30;
+pub const DER_INTEGER_TAG: u8               = 0x02;
+
+// Locktimes below the threshold are block heights and the others are UNIX timestamps
+// https://github.com/bitcoin/bips/blob/master/bip-0065.mediawiki
+pub const LOCKTIME_THRESHOLD: u64 = 500_000_000;
+
+// Flags of relative locktimes in nSequence, whose low 16 bits are the locktime
+// https://github.com/bitcoin/bips/blob/master/bip-0112.mediawiki
+pub const SEQUENCE_LOCKTIME_DISABLE_FLAG: u64 = 1 << 31;
+pub const SEQUENCE_LOCKTIME_TYPE_FLAG: u64 = 1 << 22;
+pub const SEQUENCE_LOCKTIME_MASK: u64 = 0x0000ffff;
 
 // Prefix bytes of secp256k1 public key serializations
 pub const PREFIX_PK_COMPRESSED_EVEN_Y: u64 = 0x02;
@@ -1,24 +1,50 @@
 use std::marker::PhantomData;
 use crate::Field;
-use crate::bitcoinvm_circuit::execution::ExecutionChipAssignedCells;
+use crate::bitcoinvm_circuit::execution::{ExecutionChip, ExecutionChipAssignedCells};
+use crate::bitcoinvm_circuit::opcode_table::OPCODE_TABLE_ROWS;
 use crate::bitcoinvm_circuit::util::expr::Expr;
 use crate::bitcoinvm_circuit::util::is_zero::{IsZeroConfig, IsZeroChip, IsZeroInstruction};
-use ecc::{EccConfig, GeneralEccChip};
+use crate::bitcoinvm_circuit::util::script_parser::bytes_item;
+use ecc::{AssignedPoint, EccConfig, GeneralEccChip};
 use ecdsa::ecdsa::{AssignedEcdsaSig, AssignedPublicKey, EcdsaChip};
 use halo2_proofs::poly::Rotation;
-use halo2_proofs::halo2curves::secp256k1::{Secp256k1Affine, Fq};
+use halo2_proofs::arithmetic::Field as HaloField;
+use halo2_proofs::halo2curves::{Coordinates, CurveAffine};
+use halo2_proofs::halo2curves::group::Curve;
+use halo2_proofs::halo2curves::group::prime::PrimeCurveAffine;
+use halo2_proofs::halo2curves::secp256k1::{Secp256k1Affine, Fp, Fq};
 use halo2_proofs::plonk::{Selector, Column, Advice, Expression, ConstraintSystem, Error};
 use halo2_proofs::circuit::{Layouter, Value, Region};
-use integer::{IntegerInstructions, Range};
-use maingate::{MainGateConfig, RangeConfig, RangeChip, RangeInstructions, MainGate, RegionCtx};
+use integer::{AssignedInteger, IntegerInstructions, Range};
+use integer::rns::Integer;
+use lazy_static::lazy_static;
+use std::rc::Rc;
+use maingate::{MainGateConfig, MainGateInstructions, RangeConfig, RangeChip, RangeInstructions, MainGate, RegionCtx};
 
 use crate::bitcoinvm_circuit::constants::*;
+use crate::bitcoinvm_circuit::error::BitcoinVmError;
 use super::parity_table::{ParityTableConfig, ParityTableChip};
 use super::super::util::sign_util::SignData;
 use super::checksig_util::{range_check, pk_bytes_swap_endianness, rlc, ChipsRef, integer_to_bytes_le, copy_integer_bytes_le, AssignedPublicKeyBytes, ct_option_ok_or};
 use super::super::util::pk_parser::PublicKeyInScript;
 
+// The RLC of an uncompressed public key serialization of 65 bytes, including the prefix
+// byte, uses the powers randomness^1..randomness^64
 const PK_POW_RAND_SIZE: usize = 64;
+const UNCOMPRESSED_PK_SIZE: usize = 65;
+const _: () = assert!(UNCOMPRESSED_PK_SIZE <= PK_POW_RAND_SIZE + 1);
+
+// Upper bound on the number of rows used by one ECDSA verification with window size 2,
+// checked against the rows of the "ecdsa chip verification" region in the tests
+const ECDSA_ROWS_PER_VERIFICATION: usize = 1 << 18;
+// Upper bound on the number of rows of the "ecc chip aux" region, checked in the tests
+const ECC_AUX_ROWS: usize = 1 << 12;
+
+// x coordinate from which the generator of the public key commitments is searched ("BitcoinV")
+const PK_COMMITMENT_GENERATOR_SEED: u64 = 0x426974636f696e56;
+
+type AssignedSecp256k1Point<F> = AssignedPoint<Fp, F, NUMBER_OF_LIMBS, BIT_LEN_LIMB>;
+type AssignedSecp256k1Scalar<F> = AssignedInteger<Fq, F, NUMBER_OF_LIMBS, BIT_LEN_LIMB>;
 
 /// OpCheckSig configuration
 #[derive(Debug, Clone)]
@@ -66,26 +92,56 @@ impl<F: Field> OpCheckSigConfig<F> {
 
 
 /// Gadget to verify the OP_CHECKSIG opcode
+///
+/// `MAX_CHECKSIG_COUNT` is the number of ECDSA verifications assigned. Only OP_CHECKSIG
+/// opcodes with a valid signature are verified, so a scriptPubkey can have more OP_CHECKSIG
+/// opcodes as long as the remaining ones fail.
 #[derive(Clone, Debug)]
 pub(crate) struct OpCheckSigChip<F: Field, const MAX_CHECKSIG_COUNT: usize> {
     /// Aux generator for EccChip
     pub aux_generator: Secp256k1Affine,
     /// Window size for EccChip
     pub window_size: usize,
+    /// Reject signatures whose s value is above half the curve order, as Bitcoin's standardness
+    /// rules do to prevent signature malleability
+    pub enforce_low_s: bool,
     /// Marker
     pub _marker: PhantomData<F>,
 }
 
 impl<F: Field, const MAX_CHECKSIG_COUNT: usize> OpCheckSigChip<F, MAX_CHECKSIG_COUNT> {
+    /// Returns an error if the aux generator is not a valid point for the ECC chip
     pub fn construct(
         aux_generator: Secp256k1Affine,
         window_size: usize,
-    ) -> Self {
-        Self {
+    ) -> Result<Self, BitcoinVmError> {
+        if !is_valid_aux_generator(&aux_generator) {
+            return Err(BitcoinVmError::InvalidAuxGenerator);
+        }
+        Ok(Self {
             aux_generator,
             window_size,
+            enforce_low_s: false,
             _marker: PhantomData,
-        }
+        })
+    }
+
+    /// Constructs the chip with an aux generator derived from `seed`, so that proofs are
+    /// reproducible without picking a random point.
+    ///
+    /// The ECC chip offsets its incomplete additions by the aux generator, which must not be a
+    /// low-order point nor have a known relation to the points it is added to. secp256k1 has
+    /// prime order, so its only low-order point is the identity, which is rejected like the
+    /// base point. The generator is found by try-and-increment from the seed as an x coordinate,
+    /// so its discrete logarithm is unknown, and different seeds give unrelated points.
+    pub fn with_deterministic_aux(seed: u64, window_size: usize) -> Result<Self, BitcoinVmError> {
+        Self::construct(point_from_seed(seed), window_size)
+    }
+
+    /// Sets whether high-S signatures are rejected, which they are not by default
+    pub(crate) fn with_low_s(mut self, enforce_low_s: bool) -> Self {
+        self.enforce_low_s = enforce_low_s;
+        self
     }
 
     pub(crate) fn configure(
@@ -269,15 +325,17 @@ impl<F: Field, const MAX_CHECKSIG_COUNT: usize> OpCheckSigChip<F, MAX_CHECKSIG_C
         ctx: &mut RegionCtx<F>,
         chips: &ChipsRef<F, NUMBER_OF_LIMBS, BIT_LEN_LIMB>,
         sign_data: &SignData,
-    ) -> Result<AssignedPublicKeyBytes<F>, Error> {
+    ) -> Result<(AssignedPublicKeyBytes<F>, AssignedSecp256k1Point<F>, AssignedSecp256k1Scalar<F>), Error> {
         let SignData {
             signature,
             pk,
+            message_hash,
+            sighash_type: _,
         } = sign_data;
         let (sig_r, sig_s) = signature;
 
         let ChipsRef {
-            main_gate: _,
+            main_gate,
             range_chip,
             ecc_chip,
             scalar_chip,
@@ -286,11 +344,21 @@ impl<F: Field, const MAX_CHECKSIG_COUNT: usize> OpCheckSigChip<F, MAX_CHECKSIG_C
 
         let integer_r = ecc_chip.new_unassigned_scalar(Value::known(*sig_r));
         let integer_s = ecc_chip.new_unassigned_scalar(Value::known(*sig_s));
-        // Message hash is always a fixed field element since we only need to prove ownership, not spend
-        let msg_hash = ecc_chip.new_unassigned_scalar(Value::known(Fq::from(ECDSA_MESSAGE_HASH as u64)));
+        // The message hash is exposed by `assign_signatures`, as a prover picking it could
+        // forge a signature for any public key
+        let msg_hash = ecc_chip.new_unassigned_scalar(Value::known(*message_hash));
 
         let r_assigned = scalar_chip.assign_integer(ctx, integer_r, Range::Remainder)?;
         let s_assigned = scalar_chip.assign_integer(ctx, integer_s, Range::Remainder)?;
+        if self.enforce_low_s {
+            // s <= (n-1)/2 exactly when 2s is below the odd curve order n, so that 2s mod n is
+            // even. A high s wraps around and 2s - n is odd.
+            let doubled_s = scalar_chip.add(ctx, &s_assigned, &s_assigned)?;
+            let doubled_s = scalar_chip.reduce(ctx, &doubled_s)?;
+            scalar_chip.assert_in_field(ctx, &doubled_s)?;
+            let doubled_s_parity = scalar_chip.sign(ctx, &doubled_s)?;
+            main_gate.assert_zero(ctx, &doubled_s_parity)?;
+        }
         let sig = AssignedEcdsaSig {
             r: r_assigned,
             s: s_assigned,
@@ -315,10 +383,40 @@ impl<F: Field, const MAX_CHECKSIG_COUNT: usize> OpCheckSigChip<F, MAX_CHECKSIG_C
         // - `IntegerChip::assign_integer_from_bytes_le`
         // - `GeneralEccChip::assing_point_from_bytes_le`
 
-        Ok(AssignedPublicKeyBytes {
-            pk_x_le,
-            pk_y_le,
-        })
+        Ok((
+            AssignedPublicKeyBytes {
+                pk_x_le,
+                pk_y_le,
+            },
+            pk_assigned.point,
+            msg_hash,
+        ))
+    }
+
+    /// Assigns the Pedersen commitment `pk + blinding*H` to a public key, where `H` is the
+    /// generator returned by `pk_commitment_generator`
+    fn assign_pk_commitment(
+        &self,
+        ctx: &mut RegionCtx<F>,
+        chips: &ChipsRef<F, NUMBER_OF_LIMBS, BIT_LEN_LIMB>,
+        pk_point: &AssignedSecp256k1Point<F>,
+        blinding: Fq,
+    ) -> Result<AssignedSecp256k1Point<F>, Error> {
+        let ChipsRef {
+            main_gate: _,
+            range_chip: _,
+            ecc_chip,
+            scalar_chip,
+            ecdsa_chip: _,
+        } = chips;
+
+        let generator = ecc_chip.assign_constant(ctx, pk_commitment_generator())?;
+        let blinding = ecc_chip.new_unassigned_scalar(Value::known(blinding));
+        let blinding = scalar_chip.assign_integer(ctx, blinding, Range::Remainder)?;
+
+        let blinded_generator = ecc_chip.mul(ctx, &generator, &blinding, self.window_size)?;
+        let commitment = ecc_chip.add(ctx, pk_point, &blinded_generator)?;
+        ecc_chip.normalize(ctx, &commitment)
     }
 
 
@@ -331,16 +429,105 @@ impl<F: Field, const MAX_CHECKSIG_COUNT: usize> OpCheckSigChip<F, MAX_CHECKSIG_C
         signatures: &[SignData],
         collected_pks: &[PublicKeyInScript],
     ) -> Result<(), Error> {
-        if signatures.len() > MAX_CHECKSIG_COUNT || signatures.len() != collected_pks.len() {
-            return Err(Error::Synthesis);
+        self.assign_signatures(config, layouter, execution_cells, randomness, signatures, collected_pks, &[])?;
+        Ok(())
+    }
+
+    /// Verifies the signatures like `assign` and binds each verified public key to a Pedersen
+    /// commitment `pk + blinding*H` exposed in the instance column of the main gate. The
+    /// commitment to the i-th public key occupies the `2*NUMBER_OF_LIMBS` instance rows
+    /// starting at `2*NUMBER_OF_LIMBS*i`, laid out as in `pk_commitment_public_inputs`.
+    /// The message hashes follow the commitments, at the rows given by `message_hash_row`.
+    ///
+    /// Only the commitments need to be public; the scriptPubkey RLC reveals the public keys
+    /// and must not be exposed when the keys are to remain hidden.
+    pub(crate) fn assign_with_pk_commitments(
+        &self,
+        config: &OpCheckSigConfig<F>,
+        layouter: &mut impl Layouter<F>,
+        execution_cells: &ExecutionChipAssignedCells<F>,
+        randomness: F,
+        signatures: &[SignData],
+        collected_pks: &[PublicKeyInScript],
+        blindings: &[Fq],
+    ) -> Result<(), Error> {
+        if blindings.len() != signatures.len() {
+            return Err(BitcoinVmError::MismatchedBlindingCount {
+                blindings: blindings.len(),
+                signatures: signatures.len(),
+            }.into());
+        }
+
+        let commitments = self.assign_signatures(
+            config,
+            layouter,
+            execution_cells,
+            randomness,
+            signatures,
+            collected_pks,
+            blindings,
+        )?;
+
+        let ecc_chip = GeneralEccChip::<Secp256k1Affine, F, NUMBER_OF_LIMBS, BIT_LEN_LIMB>::new(
+            config.ecc_chip_config(),
+        );
+        for (i, commitment) in commitments.into_iter().enumerate() {
+            ecc_chip.expose_public(
+                layouter.namespace(|| "public key commitment"),
+                commitment,
+                2 * NUMBER_OF_LIMBS * i,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Checks that there is one signature for each collected public key, up to MAX_CHECKSIG_COUNT,
+    /// and that the signatures have valid sighash types
+    pub(crate) fn validate_signatures(
+        signatures: &[SignData],
+        collected_pks: &[PublicKeyInScript],
+    ) -> Result<(), BitcoinVmError> {
+        if signatures.len() != collected_pks.len() {
+            return Err(BitcoinVmError::MismatchedSignatureCount {
+                signatures: signatures.len(),
+                public_keys: collected_pks.len(),
+            });
+        }
+        if signatures.len() > MAX_CHECKSIG_COUNT {
+            return Err(BitcoinVmError::TooManySignatures {
+                signatures: signatures.len(),
+                max_signatures: MAX_CHECKSIG_COUNT,
+            });
         }
 
-        for i in 0..signatures.len() {
+        for (index, (signature, collected_pk)) in signatures.iter().zip(collected_pks.iter()).enumerate() {
             // The two vectors should have the same public keys
-            if signatures[i].pk != collected_pks[i].pk {
-                return Err(Error::Synthesis);
+            if signature.pk != collected_pk.pk {
+                return Err(BitcoinVmError::MismatchedPublicKey { index });
+            }
+            // The sighash type byte is not part of the ECDSA verification but it must be a valid flag
+            if !signature.has_valid_sighash_type() {
+                return Err(BitcoinVmError::InvalidSighashType { index, sighash_type: signature.sighash_type });
             }
         }
+        Ok(())
+    }
+
+    // Assigns the signature verifications and the public key collection. The message hash of
+    // each signature is exposed at `message_hash_row(blindings.len(), i)`. A commitment is
+    // assigned for each of the first `blindings.len()` public keys and returned.
+    #[allow(clippy::too_many_arguments)]
+    fn assign_signatures(
+        &self,
+        config: &OpCheckSigConfig<F>,
+        layouter: &mut impl Layouter<F>,
+        execution_cells: &ExecutionChipAssignedCells<F>,
+        randomness: F,
+        signatures: &[SignData],
+        collected_pks: &[PublicKeyInScript],
+        blindings: &[Fq],
+    ) -> Result<Vec<AssignedSecp256k1Point<F>>, Error> {
+        Self::validate_signatures(signatures, collected_pks)?;
 
         // Load the range table
         config.load_range(layouter)?;
@@ -361,6 +548,8 @@ impl<F: Field, const MAX_CHECKSIG_COUNT: usize> OpCheckSigChip<F, MAX_CHECKSIG_C
         let ecdsa_chip = EcdsaChip::new(ecc_chip.clone());
 
         let mut assigned_pks = Vec::new();
+        let mut commitments = Vec::new();
+        let mut message_hashes = Vec::new();
 
         let chips = ChipsRef {
             main_gate: &main_gate,
@@ -374,6 +563,8 @@ impl<F: Field, const MAX_CHECKSIG_COUNT: usize> OpCheckSigChip<F, MAX_CHECKSIG_C
             || "ecdsa chip verification",
             |region| {
                 assigned_pks.clear();
+                commitments.clear();
+                message_hashes.clear();
                 let offset = &mut 0;
                 let mut ctx = RegionCtx::new(region, *offset);
                 for i in 0..MAX_CHECKSIG_COUNT {
@@ -383,13 +574,29 @@ impl<F: Field, const MAX_CHECKSIG_COUNT: usize> OpCheckSigChip<F, MAX_CHECKSIG_C
                         // padding (enabled when number of OP_CHECKSIG opcodes is less than max number)
                         SignData::default()
                     };
-                    let assigned_pk = self.assign_ecdsa(&mut ctx, &chips, &signature)?;
+                    let (assigned_pk, pk_point, msg_hash) = self.assign_ecdsa(&mut ctx, &chips, &signature)?;
                     assigned_pks.push(assigned_pk);
+                    if i < signatures.len() {
+                        message_hashes.push(msg_hash);
+                    }
+                    if i < blindings.len() {
+                        let commitment = self.assign_pk_commitment(&mut ctx, &chips, &pk_point, blindings[i])?;
+                        commitments.push(commitment);
+                    }
                 }
                 Ok(())
             },
         )?;
 
+        for (i, msg_hash) in message_hashes.iter().enumerate() {
+            for (j, limb) in msg_hash.limbs().iter().enumerate() {
+                main_gate.expose_public(
+                    layouter.namespace(|| "message hash"),
+                    limb.as_ref().clone(),
+                    message_hash_row(blindings.len(), i) + j,
+                )?;
+            }
+        }
 
         ParityTableChip::load(config.parity_table.clone(), layouter)?;
         
@@ -399,12 +606,16 @@ impl<F: Field, const MAX_CHECKSIG_COUNT: usize> OpCheckSigChip<F, MAX_CHECKSIG_C
                 let num_checksig_opcodes_is_zero_chip
                     = IsZeroChip::construct(config.num_checksig_opcodes_is_zero.clone());
 
-                let mut pk_rlc_acc: F = F::zero();
-                for i in 0..collected_pks.len() {
-                    for b in collected_pks[i].clone().bytes {
-                        pk_rlc_acc = F::from(b as u64) + randomness * pk_rlc_acc;
-                    }
-                }
+                // The keys keep their compressed or uncompressed serialization from the script, so
+                // their RLCs are the stack items accumulated by the ExecutionChip
+                let pk_rlcs: Vec<F> = collected_pks
+                    .iter()
+                    .map(|collected_pk| bytes_item(&collected_pk.bytes, randomness))
+                    .collect();
+                // The ExecutionChip accumulates the public key RLCs in the order of the collected keys
+                let mut pk_rlc_acc: F = pk_rlcs
+                    .iter()
+                    .fold(F::zero(), |acc, pk_rlc| acc * randomness + *pk_rlc);
 
                 // an extra row is assigned as queries are made to next rows
                 for offset in 0..MAX_CHECKSIG_COUNT+1 {
@@ -422,7 +633,9 @@ impl<F: Field, const MAX_CHECKSIG_COUNT: usize> OpCheckSigChip<F, MAX_CHECKSIG_C
                                 || Value::known(power),
                             )?;
                             // The value in the first row and first power_of_randomness array is constrained
-                            // to be equal to the randomness value used in the ExecutionChip
+                            // to be equal to the randomness value used in the ExecutionChip. Together with the
+                            // execution gate keeping randomness constant across rows, this ties the script RLC,
+                            // the pk_rlc_acc in the ExecutionChip and all the powers below to one field element
                             if offset == 0 && i == 0 {
                                 region.constrain_equal(rcell.cell(), execution_cells.randomness.cell())?;
                             }
@@ -437,17 +650,12 @@ impl<F: Field, const MAX_CHECKSIG_COUNT: usize> OpCheckSigChip<F, MAX_CHECKSIG_C
                             offset,
                             || Value::known(randomness),
                         )?;
-
-                        // The pk_rlc_acc value is queried in the extra row
-                        region.assign_advice(
-                            || "Assign pk_rlc_acc in extra row",
-                            config.pk_rlc_acc,
-                            offset,
-                            || Value::known(F::zero()),
-                        )?;
+                        // The pk_rlc_acc value queried in the extra row is assigned below
                     }
                     
                     if offset < collected_pks.len() {
+                        // The last accumulated public key is removed from pk_rlc_acc first
+                        let pk_index = collected_pks.len() - 1 - offset;
                         let num_checksig_opcodes_remaining = F::from((collected_pks.len() - offset) as u64);
                         let num_cs_cell = region.assign_advice(
                             || "Number of OP_CHECKSIG operations",
@@ -457,9 +665,9 @@ impl<F: Field, const MAX_CHECKSIG_COUNT: usize> OpCheckSigChip<F, MAX_CHECKSIG_C
                         )?;
 
                         // The value in the first row of the num_checksig_opcodes column is constrained
-                        // to be equal to the num_checksig_opcodes value calculated in the ExecutionChip
+                        // to be equal to the number of verified signatures calculated in the ExecutionChip
                         if offset == 0 {
-                            region.constrain_equal(num_cs_cell.cell(), execution_cells.num_checksig_opcodes.cell())?;
+                            region.constrain_equal(num_cs_cell.cell(), execution_cells.num_verified_checksigs.cell())?;
                         }
 
                         num_checksig_opcodes_is_zero_chip.assign(
@@ -472,14 +680,14 @@ impl<F: Field, const MAX_CHECKSIG_COUNT: usize> OpCheckSigChip<F, MAX_CHECKSIG_C
                         copy_integer_bytes_le(
                             &mut region,
                             "pk_x",
-                            &assigned_pks[offset].pk_x_le,
+                            &assigned_pks[pk_index].pk_x_le,
                             &config.pk[0],
                             offset,
                         )?;
                         copy_integer_bytes_le(
                             &mut region,
                             "pk_y",
-                            &assigned_pks[offset].pk_y_le,
+                            &assigned_pks[pk_index].pk_y_le,
                             &config.pk[1],
                             offset,
                         )?;
@@ -488,13 +696,10 @@ impl<F: Field, const MAX_CHECKSIG_COUNT: usize> OpCheckSigChip<F, MAX_CHECKSIG_C
                             || "Public key prefix byte",
                             config.pk_prefix,
                             offset,
-                            || Value::known(F::from(collected_pks[offset].bytes[0] as u64)),
+                            || Value::known(F::from(collected_pks[pk_index].bytes[0] as u64)),
                         )?;
 
-                        let mut pk_rlc = F::zero();
-                        for b in collected_pks[offset].clone().bytes {
-                            pk_rlc = F::from(b as u64) + randomness * pk_rlc;
-                        }
+                        let pk_rlc = pk_rlcs[pk_index];
 
                         region.assign_advice(
                             || "Public key RLC accumulator",
@@ -521,7 +726,7 @@ impl<F: Field, const MAX_CHECKSIG_COUNT: usize> OpCheckSigChip<F, MAX_CHECKSIG_C
                         pk_rlc_acc = randomness_inv * (pk_rlc_acc - pk_rlc);
                     }
                     else {
-                        region.assign_advice(
+                        let num_cs_cell = region.assign_advice(
                             || "Number of OP_CHECKSIG operations",
                             config.num_checksig_opcodes,
                             offset,
@@ -534,30 +739,175 @@ impl<F: Field, const MAX_CHECKSIG_COUNT: usize> OpCheckSigChip<F, MAX_CHECKSIG_C
                             Value::known(F::zero()),
                         )?;
 
-                        region.assign_advice(
+                        let acc_cell = region.assign_advice(
                             || "Public key RLC accumulator",
                             config.pk_rlc_acc,
                             offset,
                             || Value::known(pk_rlc_acc),
                         )?;
-                        
+
+                        // Without verified signatures the ExecutionChip must not have collected any key
+                        if offset == 0 {
+                            region.constrain_equal(num_cs_cell.cell(), execution_cells.num_verified_checksigs.cell())?;
+                            region.constrain_equal(acc_cell.cell(), execution_cells.pk_rlc_acc.cell())?;
+                        }
                     }
                 }
                 Ok(())
             },
         )?;
-        Ok(())
+        Ok(commitments)
+    }
+
+    /// Constrains several scriptPubkeys to use the public keys of the first one.
+    ///
+    /// The OP_CHECKSIG public keys of the first scriptPubkey are verified by `assign`. Equal
+    /// pk_rlc_acc values computed with the same randomness imply that the other scriptPubkeys
+    /// have the same public keys, so their signatures do not need to be verified again.
+    pub(crate) fn constrain_common_public_keys(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        execution_cells: &[ExecutionChipAssignedCells<F>],
+    ) -> Result<(), Error> {
+        if execution_cells.is_empty() {
+            return Err(BitcoinVmError::NoScriptPubkeys.into());
+        }
+
+        layouter.assign_region(
+            || "Common public key verification",
+            |mut region: Region<F>| {
+                let first = &execution_cells[0];
+                for cells in execution_cells[1..].iter() {
+                    region.constrain_equal(first.randomness.cell(), cells.randomness.cell())?;
+                    region.constrain_equal(first.num_verified_checksigs.cell(), cells.num_verified_checksigs.cell())?;
+                    region.constrain_equal(first.pk_rlc_acc.cell(), cells.pk_rlc_acc.cell())?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+}
+
+/// Checks that the aux generator of the ECC chip is a point on the curve which is neither the
+/// identity nor the base point or its negation. The aux generator is added to intermediate
+/// points of the scalar multiplication to avoid the exceptional cases of the incomplete
+/// addition formulas, which a degenerate point does not achieve.
+pub(crate) fn is_valid_aux_generator(aux_generator: &Secp256k1Affine) -> bool {
+    let generator = Secp256k1Affine::generator();
+    !bool::from(aux_generator.is_identity())
+        && bool::from(aux_generator.is_on_curve())
+        && *aux_generator != generator
+        && *aux_generator != -generator
+}
+
+// Try-and-increment from the seed as an x coordinate, so that the discrete logarithm of the
+// point with respect to the secp256k1 base point is unknown
+fn point_from_seed(seed: u64) -> Secp256k1Affine {
+    let mut x = Fp::from(seed);
+    loop {
+        let y_square = x.square() * x + Secp256k1Affine::b();
+        if let Some(y) = Option::<Fp>::from(y_square.sqrt()) {
+            break Option::<Secp256k1Affine>::from(Secp256k1Affine::from_xy(x, y))
+                .expect("point is on the curve");
+        }
+        x += Fp::one();
     }
+}
+
+lazy_static! {
+    static ref PK_COMMITMENT_GENERATOR: Secp256k1Affine = point_from_seed(PK_COMMITMENT_GENERATOR_SEED);
+}
+
+/// Returns the generator `H` used to blind public keys in Pedersen commitments `pk + blinding*H`
+pub(crate) fn pk_commitment_generator() -> Secp256k1Affine {
+    *PK_COMMITMENT_GENERATOR
+}
 
+/// Computes the Pedersen commitment to a public key outside the circuit
+pub(crate) fn commit_pk(pk: &Secp256k1Affine, blinding: Fq) -> Secp256k1Affine {
+    (pk.to_curve() + pk_commitment_generator() * blinding).to_affine()
+}
+
+/// Returns the instance values of a public key commitment exposed by
+/// `OpCheckSigChip::assign_with_pk_commitments`: the limbs of the x coordinate followed
+/// by the limbs of the y coordinate
+pub(crate) fn pk_commitment_public_inputs<F: Field>(commitment: &Secp256k1Affine) -> Vec<F> {
+    let (rns_base, _) = GeneralEccChip::<Secp256k1Affine, F, NUMBER_OF_LIMBS, BIT_LEN_LIMB>::rns();
+    let rns_base = Rc::new(rns_base);
+    let coordinates = Option::<Coordinates<_>>::from(commitment.coordinates())
+        .expect("commitment is the identity");
+
+    let mut public_inputs = Integer::from_fe(*coordinates.x(), rns_base.clone()).limbs();
+    public_inputs.extend(Integer::from_fe(*coordinates.y(), rns_base).limbs());
+    public_inputs
+}
+
+/// Returns the first instance row of the main gate holding the message hash of the i-th
+/// signature, after the `num_pk_commitments` public key commitments
+pub(crate) fn message_hash_row(num_pk_commitments: usize, index: usize) -> usize {
+    2 * NUMBER_OF_LIMBS * num_pk_commitments + NUMBER_OF_LIMBS * index
+}
+
+/// Returns the instance values of a message hash exposed by the OpCheckSigChip: the limbs
+/// of the hash as an integer of the secp256k1 scalar field
+pub(crate) fn message_hash_public_inputs<F: Field>(message_hash: &Fq) -> Vec<F> {
+    let (_, rns_scalar) = GeneralEccChip::<Secp256k1Affine, F, NUMBER_OF_LIMBS, BIT_LEN_LIMB>::rns();
+    Integer::from_fe(*message_hash, Rc::new(rns_scalar)).limbs()
+}
+
+/// Returns the minimum `k` such that a circuit combining the ExecutionChip and the
+/// OpCheckSigChip fits in `2^k` rows.
+///
+/// The regions of the two chips use disjoint columns and the lookup tables live in
+/// fixed columns, so the number of rows needed is the maximum of
+/// - the rows of the range chip lookup table (one row per value of each configured bit length),
+/// - the rows of the opcode table,
+/// - the rows of the scriptPubkey unrolling region (two more than the script size),
+/// - the rows of the ECDSA verifications (one per OP_CHECKSIG) and the ECC chip aux generator,
+///
+/// together with the rows which halo2 reserves for the blinding factors of the combined circuit.
+pub(crate) fn min_k<F: Field>(max_script_size: usize, max_checksig_count: usize) -> u32 {
+    let (rns_base, rns_scalar) =
+        GeneralEccChip::<Secp256k1Affine, F, NUMBER_OF_LIMBS, BIT_LEN_LIMB>::rns();
+    let mut bit_lengths: Vec<usize> = vec![BIT_LEN_LIMB / NUMBER_OF_LIMBS, 8];
+    bit_lengths.extend(rns_base.overflow_lengths());
+    bit_lengths.extend(rns_scalar.overflow_lengths());
+    bit_lengths.sort();
+    bit_lengths.dedup();
+    let range_table_rows: usize = bit_lengths.iter().map(|b| 1usize << b).sum();
+
+    let execution_rows = max_script_size + 2;
+    let ecdsa_rows = ECC_AUX_ROWS + max_checksig_count * ECDSA_ROWS_PER_VERIFICATION;
+
+    let mut cs = ConstraintSystem::<F>::default();
+    ExecutionChip::<F>::configure(&mut cs);
+    OpCheckSigChip::<F, MAX_CHECKSIG_COUNT>::configure(&mut cs);
+    let reserved_rows = cs.blinding_factors() + 1;
+
+    let rows = [range_table_rows, OPCODE_TABLE_ROWS, execution_rows, ecdsa_rows]
+        .into_iter()
+        .max()
+        .unwrap()
+        + reserved_rows;
+
+    let mut k = 0;
+    while (1usize << k) < rows {
+        k += 1;
+    }
+    k
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
     use halo2_proofs::arithmetic::Field as HaloField;
     use halo2_proofs::dev::MockProver;
     use halo2_proofs::halo2curves::CurveAffine;
+    use halo2_proofs::halo2curves::group::prime::PrimeCurveAffine;
+    use halo2_proofs::halo2curves::group::Curve;
     use halo2_proofs::halo2curves::bn256::Fr as BnScalar;
-    use halo2_proofs::circuit::{SimpleFloorPlanner, Layouter};
+    use halo2_proofs::circuit::{SimpleFloorPlanner, Layouter, Region, Value};
     use halo2_proofs::halo2curves::{secp256k1::{Secp256k1Affine, Fq, Fp}};
     use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
     use rand::{Rng, SeedableRng, RngCore};
@@ -566,11 +916,18 @@ mod tests {
     use secp256k1::constants::{PUBLIC_KEY_SIZE, UNCOMPRESSED_PUBLIC_KEY_SIZE};
 
     use crate::bitcoinvm_circuit::constants::*;
+    use crate::bitcoinvm_circuit::error::BitcoinVmError;
     use crate::bitcoinvm_circuit::crypto_opcodes::checksig::checksig_util::{ct_option_ok_or, pk_bytes_swap_endianness};
     use crate::bitcoinvm_circuit::crypto_opcodes::util::pk_parser::{PublicKeyInScript, collect_public_keys, StackElement};
-    use crate::bitcoinvm_circuit::crypto_opcodes::util::sign_util::{SignData, sign};
+    use crate::bitcoinvm_circuit::crypto_opcodes::util::sign_util::{SignData, sign, message_hash_from_digest};
     use crate::bitcoinvm_circuit::execution::{ExecutionChip, ExecutionConfig};
-    use super::{OpCheckSigChip, OpCheckSigConfig};
+    use crate::bitcoinvm_circuit::util::assignment_audit::find_double_assignments;
+    use crate::bitcoinvm_circuit::util::is_zero::{IsZeroChip, IsZeroInstruction};
+    use crate::bitcoinvm_circuit::util::region_profile::region_row_counts;
+    use crate::bitcoinvm_circuit::util::script_parser::{ScriptPubkeyParseState, bytes_item};
+    use crate::testing::{assert_proves, assert_rejects};
+    use super::super::parity_table::ParityTableChip;
+    use super::{OpCheckSigChip, OpCheckSigConfig, min_k, ECC_AUX_ROWS, ECDSA_ROWS_PER_VERIFICATION, commit_pk, is_valid_aux_generator, pk_commitment_generator, pk_commitment_public_inputs, message_hash_public_inputs, PK_POW_RAND_SIZE};
     use crate::Field;
 
     #[derive(Clone, Debug)]
@@ -583,6 +940,8 @@ mod tests {
         pub op_checksig_chip: OpCheckSigChip<F, MAX_CHECKSIG_COUNT>,
         pub script_pubkey: Vec<u8>,
         pub randomness: F,
+        // Randomness passed to the OP_CHECKSIG chip; equal to `randomness` in an honest assignment
+        pub checksig_randomness: F,
         pub initial_stack: [F; MAX_STACK_DEPTH],
         pub signatures: Vec<SignData>,
         pub collected_pks: Vec<PublicKeyInScript>,
@@ -597,10 +956,12 @@ mod tests {
                 op_checksig_chip: OpCheckSigChip::<F, MAX_CHECKSIG_COUNT> {
                     aux_generator: Secp256k1Affine::default(),
                     window_size: 0,
+                    enforce_low_s: false,
                     _marker: std::marker::PhantomData::default()
                 },
                 script_pubkey: vec![],
                 randomness: F::one(),
+                checksig_randomness: F::one(),
                 initial_stack: [F::zero(); MAX_STACK_DEPTH],
                 signatures: vec![],
                 collected_pks: vec![],
@@ -650,18 +1011,187 @@ mod tests {
 
             let checksig_chip: OpCheckSigChip<F, MAX_CHECKSIG_COUNT> = self.op_checksig_chip.clone();
             checksig_chip.assign(
+                &config.op_checksig_config,
+                &mut layouter,
+                &execution_chip_cells,
+                self.checksig_randomness,
+                &self.signatures,
+                &self.collected_pks,
+            )?;
+            Ok(())
+        }
+    }
+
+    struct TestCommonPkCircuit<F: Field, const MAX_CHECKSIG_COUNT: usize> {
+        pub op_checksig_chip: OpCheckSigChip<F, MAX_CHECKSIG_COUNT>,
+        pub script_pubkeys: Vec<Vec<u8>>,
+        pub randomness: F,
+        pub initial_stack: [F; MAX_STACK_DEPTH],
+        pub signatures: Vec<SignData>,
+        pub collected_pks: Vec<PublicKeyInScript>,
+    }
+
+    impl<F: Field, const MAX_CHECKSIG_COUNT: usize> Circuit<F> for TestCommonPkCircuit<F, MAX_CHECKSIG_COUNT> {
+        type Config = TestOpChecksigCircuitConfig<F, MAX_CHECKSIG_COUNT>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                op_checksig_chip: OpCheckSigChip::<F, MAX_CHECKSIG_COUNT> {
+                    aux_generator: Secp256k1Affine::default(),
+                    window_size: 0,
+                    enforce_low_s: false,
+                    _marker: std::marker::PhantomData::default()
+                },
+                script_pubkeys: vec![],
+                randomness: F::one(),
+                initial_stack: [F::zero(); MAX_STACK_DEPTH],
+                signatures: vec![],
+                collected_pks: vec![],
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            TestOpChecksigCircuitConfig {
+                execution_config: ExecutionChip::<F>::configure(meta),
+                op_checksig_config: OpCheckSigChip::<F, MAX_CHECKSIG_COUNT>::configure(meta),
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>
+        ) -> Result<(), Error> {
+            let exec_chip = ExecutionChip::construct();
+
+            let execution_chip_cells = exec_chip.assign_script_pubkeys_unroll(
+                config.execution_config.clone(),
+                &mut layouter,
+                &self.script_pubkeys,
+                self.randomness,
+                self.initial_stack,
+            )?;
+
+            for (i, cells) in execution_chip_cells.iter().enumerate() {
+                exec_chip.expose_public(
+                    config.execution_config.clone(),
+                    layouter.namespace(|| "script_length"),
+                    cells.clone().script_length,
+                    3*i,
+                )?;
+                exec_chip.expose_public(
+                    config.execution_config.clone(),
+                    layouter.namespace(|| "script_rlc_acc"),
+                    cells.clone().script_rlc_acc_init,
+                    3*i+1,
+                )?;
+                exec_chip.expose_public(
+                    config.execution_config.clone(),
+                    layouter.namespace(|| "randomness"),
+                    cells.clone().randomness,
+                    3*i+2,
+                )?;
+            }
+
+            let checksig_chip: OpCheckSigChip<F, MAX_CHECKSIG_COUNT> = self.op_checksig_chip.clone();
+            checksig_chip.assign(
+                &config.op_checksig_config,
+                &mut layouter,
+                &execution_chip_cells[0],
+                self.randomness,
+                &self.signatures,
+                &self.collected_pks,
+            )?;
+            checksig_chip.constrain_common_public_keys(&mut layouter, &execution_chip_cells)?;
+            Ok(())
+        }
+    }
+
+    struct TestPkCommitmentCircuit<F: Field, const MAX_CHECKSIG_COUNT: usize> {
+        pub op_checksig_chip: OpCheckSigChip<F, MAX_CHECKSIG_COUNT>,
+        pub script_pubkey: Vec<u8>,
+        pub randomness: F,
+        pub initial_stack: [F; MAX_STACK_DEPTH],
+        pub signatures: Vec<SignData>,
+        pub collected_pks: Vec<PublicKeyInScript>,
+        pub blindings: Vec<Fq>,
+    }
+
+    impl<F: Field, const MAX_CHECKSIG_COUNT: usize> Circuit<F> for TestPkCommitmentCircuit<F, MAX_CHECKSIG_COUNT> {
+        type Config = TestOpChecksigCircuitConfig<F, MAX_CHECKSIG_COUNT>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                op_checksig_chip: OpCheckSigChip::<F, MAX_CHECKSIG_COUNT> {
+                    aux_generator: Secp256k1Affine::default(),
+                    window_size: 0,
+                    enforce_low_s: false,
+                    _marker: std::marker::PhantomData::default()
+                },
+                script_pubkey: vec![],
+                randomness: F::one(),
+                initial_stack: [F::zero(); MAX_STACK_DEPTH],
+                signatures: vec![],
+                collected_pks: vec![],
+                blindings: vec![],
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            TestOpChecksigCircuitConfig {
+                execution_config: ExecutionChip::<F>::configure(meta),
+                op_checksig_config: OpCheckSigChip::<F, MAX_CHECKSIG_COUNT>::configure(meta),
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>
+        ) -> Result<(), Error> {
+            let exec_chip = ExecutionChip::construct();
+
+            let execution_chip_cells  = exec_chip.assign_script_pubkey_unroll(
+                config.execution_config.clone(),
+                &mut layouter,
+                self.script_pubkey.clone(),
+                self.randomness,
+                self.initial_stack,
+            )?;
+
+            // The scriptPubkey length and RLC are not exposed as they reveal the public key
+            exec_chip.expose_public(
+                config.execution_config.clone(),
+                layouter.namespace(|| "randomness"),
+                execution_chip_cells.clone().randomness,
+                0
+            )?;
+
+            let checksig_chip: OpCheckSigChip<F, MAX_CHECKSIG_COUNT> = self.op_checksig_chip.clone();
+            checksig_chip.assign_with_pk_commitments(
                 &config.op_checksig_config,
                 &mut layouter,
                 &execution_chip_cells,
                 self.randomness,
                 &self.signatures,
                 &self.collected_pks,
+                &self.blindings,
             )?;
             Ok(())
         }
     }
 
-    fn generate_sign_data(sk_vec: Vec<SecretKey>, mut rng: impl RngCore) -> Vec<SignData> {
+    fn generate_sign_data(sk_vec: Vec<SecretKey>, rng: impl RngCore) -> Vec<SignData> {
+        generate_sign_data_for_message(sk_vec, Fq::from(ECDSA_MESSAGE_HASH), rng)
+    }
+
+    fn generate_sign_data_for_message(
+        sk_vec: Vec<SecretKey>,
+        message_hash: Fq,
+        mut rng: impl RngCore,
+    ) -> Vec<SignData> {
         let secp = Secp256k1::new();
         let mut sign_data_vec = vec![];
 
@@ -674,7 +1204,7 @@ mod tests {
             let sk = ct_option_ok_or(
                 Fq::from_bytes(&sk_bytes), libsecp256k1::Error::InvalidSecretKey
             ).unwrap();
-            let sig = sign(sig_randomness, sk, Fq::from(ECDSA_MESSAGE_HASH as u64));
+            let sig = sign(sig_randomness, sk, message_hash);
     
             let pk_be = public_key.serialize_uncompressed();
             let pk_le = pk_bytes_swap_endianness(&pk_be[1..]);
@@ -693,12 +1223,25 @@ mod tests {
                 libsecp256k1::Error::InvalidPublicKey,
             ).expect("Public key corrupted");
 
-            let sign_data: SignData = SignData { signature: sig, pk };
+            let sign_data: SignData = SignData {
+                signature: sig,
+                pk,
+                message_hash,
+                sighash_type: SIGHASH_ALL,
+            };
             sign_data_vec.push(sign_data);
         }
         sign_data_vec
     }
 
+    // Instance column of the main gate, holding the message hash of each signature
+    fn checksig_instance(signatures: &[SignData]) -> Vec<BnScalar> {
+        signatures
+            .iter()
+            .flat_map(|signature| message_hash_public_inputs(&signature.message_hash))
+            .collect()
+    }
+
     fn generate_public_inputs<F: Field>(mut script_pubkey: Vec<u8>, randomness: F) -> Vec<F> {
         script_pubkey.reverse();
         let script_rlc_init = script_pubkey.clone().into_iter().fold(F::zero(), |acc, v| {
@@ -712,20 +1255,23 @@ mod tests {
         ]
     }
 
-    // High memory usage test.  Run in serial with:
-    // `cargo test opchecksig -- --ignored --test-threads 1`
-    #[ignore]
-    #[test]
-    fn test_opchecksig_compressed_p2pk() {
-        let k = 19;
-
+    // Secret key whose public key has a y coordinate of the parity given by the compressed prefix
+    fn secret_key_with_prefix(prefix: u64) -> SecretKey {
         let secp = Secp256k1::new();
-        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
-        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
-        let public_key_bytes: [u8; PUBLIC_KEY_SIZE] = public_key.serialize();
-        
+        (1..=u8::MAX)
+            .map(|byte| SecretKey::from_slice(&[byte; 32]).expect("32 bytes, within curve order"))
+            .find(|secret_key| PublicKey::from_secret_key(&secp, secret_key).serialize()[0] as u64 == prefix)
+            .expect("Half of the public keys have each parity")
+    }
+
+    // Circuit and public inputs of a P2PK scriptPubkey with the given public key serialization,
+    // spent with a signature of the secret key
+    fn p2pk_circuit(
+        secret_key: SecretKey,
+        public_key_bytes: &[u8],
+    ) -> (TestOpChecksigCircuit<BnScalar, MAX_CHECKSIG_COUNT>, Vec<BnScalar>) {
         let mut script_pubkey: Vec<u8> = vec![];
-        script_pubkey.push(PUBLIC_KEY_SIZE as u8); // "Push 33 bytes" opcode
+        script_pubkey.push(public_key_bytes.len() as u8); // "Push 33 bytes" or "Push 65 bytes" opcode
         script_pubkey.extend(public_key_bytes.iter());
         script_pubkey.push(OP_CHECKSIG as u8);
 
@@ -736,6 +1282,7 @@ mod tests {
         // TODO: Derive initial stack and pk_parser_initial_stack from the same value
         let pk_parser_initial_stack = vec![StackElement::ValidSignature];
         let collected_pks = collect_public_keys(script_pubkey.clone(), pk_parser_initial_stack).expect("PK collection failed");
+        assert_eq!(collected_pks[0].bytes, public_key_bytes);
 
         let mut rng = XorShiftRng::seed_from_u64(1);
         let aux_generator = Secp256k1Affine::random(&mut rng);
@@ -748,21 +1295,56 @@ mod tests {
             op_checksig_chip: OpCheckSigChip::<BnScalar, MAX_CHECKSIG_COUNT> {
                 aux_generator,
                 window_size: 2,
+                enforce_low_s: false,
                 _marker: std::marker::PhantomData,
             },
             script_pubkey: script_pubkey.clone(),
             randomness,
+            checksig_randomness: randomness,
             initial_stack,
             signatures,
             collected_pks,
         };
 
         let public_input = generate_public_inputs(script_pubkey, randomness);
+        (circuit, public_input)
+    }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test opchecksig -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn test_opchecksig_compressed_p2pk() {
+        let k = 19;
+
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+        let public_key_bytes: [u8; PUBLIC_KEY_SIZE] = public_key.serialize();
+        let (circuit, public_input) = p2pk_circuit(secret_key, &public_key_bytes);
 
-        let prover = MockProver::run(k, &circuit, vec![public_input.clone(), vec![]]).unwrap();
+        let prover = MockProver::run(k, &circuit, vec![public_input, checksig_instance(&circuit.signatures)]).unwrap();
         prover.assert_satisfied();
     }
 
+    // High memory usage test.  Run in serial with:
+    // `cargo test opchecksig -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn test_opchecksig_compressed_p2pk_even_and_odd_y() {
+        let k = 19;
+
+        // The RLC of a compressed key covers the prefix byte and the x coordinate only, and the
+        // prefix is checked against the parity of the y coordinate of the verified key
+        let secp = Secp256k1::new();
+        for prefix in [PREFIX_PK_COMPRESSED_EVEN_Y, PREFIX_PK_COMPRESSED_ODD_Y] {
+            let secret_key = secret_key_with_prefix(prefix);
+            let public_key_bytes = PublicKey::from_secret_key(&secp, &secret_key).serialize();
+            let (circuit, public_input) = p2pk_circuit(secret_key, &public_key_bytes);
+            assert_proves(k, &circuit, vec![public_input, checksig_instance(&circuit.signatures)]);
+        }
+    }
+
     // High memory usage test.  Run in serial with:
     // `cargo test opchecksig -- --ignored --test-threads 1`
     #[ignore]
@@ -774,9 +1356,157 @@ mod tests {
         let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
         let public_key = PublicKey::from_secret_key(&secp, &secret_key);
         let public_key_bytes: [u8; UNCOMPRESSED_PUBLIC_KEY_SIZE] = public_key.serialize_uncompressed();
+        let (circuit, public_input) = p2pk_circuit(secret_key, &public_key_bytes);
+
+        let prover = MockProver::run(k, &circuit, vec![public_input, checksig_instance(&circuit.signatures)]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test opchecksig -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn test_opchecksig_low_s() {
+        let k = 19;
+
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key_bytes = PublicKey::from_secret_key(&secp, &secret_key).serialize();
+        let (mut circuit, public_input) = p2pk_circuit(secret_key, &public_key_bytes);
+
+        // (r, s) and (r, -s) are both valid signatures, exactly one of which is high-S
+        let (r, s) = circuit.signatures[0].signature;
+        let is_high_s = s.double().to_bytes()[0] & 1 == 1;
+        let (low_s, high_s) = if is_high_s { (-s, s) } else { (s, -s) };
+
+        circuit.signatures[0].signature = (r, high_s);
+        assert_proves(k, &circuit, vec![public_input.clone(), checksig_instance(&circuit.signatures)]);
+
+        circuit.op_checksig_chip = circuit.op_checksig_chip.with_low_s(true);
+        assert_rejects(k, &circuit, vec![public_input.clone(), checksig_instance(&circuit.signatures)]);
+
+        circuit.signatures[0].signature = (r, low_s);
+        assert_proves(k, &circuit, vec![public_input, checksig_instance(&circuit.signatures)]);
+    }
+
+    #[test]
+    fn test_opchecksig_pk_rlc_of_serializations() {
+        let mut rng = XorShiftRng::seed_from_u64(1);
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let mut initial_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        initial_stack[0] = BnScalar::one();
+
+        let secp = Secp256k1::new();
+        for prefix in [PREFIX_PK_COMPRESSED_EVEN_Y, PREFIX_PK_COMPRESSED_ODD_Y] {
+            let secret_key = secret_key_with_prefix(prefix);
+            let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+            let compressed = public_key.serialize().to_vec();
+            let uncompressed = public_key.serialize_uncompressed().to_vec();
+            let mut pks = vec![];
+            for public_key_bytes in [compressed, uncompressed] {
+                let mut script_pubkey = vec![public_key_bytes.len() as u8];
+                script_pubkey.extend(public_key_bytes.iter());
+                script_pubkey.push(OP_CHECKSIG as u8);
+
+                // The collected key keeps the serialization of the script, whose RLC is the one
+                // accumulated by the ExecutionChip
+                let collected_pks = collect_public_keys(script_pubkey.clone(), vec![StackElement::ValidSignature])
+                    .expect("PK collection failed");
+                assert_eq!(collected_pks[0].bytes, public_key_bytes);
+                let mut parse_state = ScriptPubkeyParseState::new(randomness, initial_stack);
+                for byte in script_pubkey {
+                    parse_state.update(byte);
+                }
+                assert_eq!(parse_state.pk_rlc_acc, bytes_item(&collected_pks[0].bytes, randomness));
+                pks.push(collected_pks[0].pk);
+            }
+            // Both serializations decode to the same point, whose y parity matches the prefix
+            assert_eq!(pks[0], pks[1]);
+            let y_le = pks[0].coordinates().unwrap().y().to_bytes();
+            assert_eq!(PREFIX_PK_COMPRESSED_EVEN_Y + (y_le[0] & 1) as u64, prefix);
+        }
+    }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test opchecksig -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn test_opchecksig_2_of_3_multisig() {
+        // Two signatures are verified
+        const NUM_VERIFICATIONS: usize = 2;
+        let k = min_k::<BnScalar>(MAX_SCRIPT_PUBKEY_SIZE, NUM_VERIFICATIONS);
+
+        let secp = Secp256k1::new();
+        let secret_keys: Vec<SecretKey> = [0xcd, 0xce, 0xcf]
+            .iter()
+            .map(|b| SecretKey::from_slice(&[*b; 32]).expect("32 bytes, within curve order"))
+            .collect();
+
+        // OP_2 <pk1> <pk2> <pk3> OP_3 OP_CHECKMULTISIG
+        let mut script_pubkey: Vec<u8> = vec![OP_1 as u8 + 1];
+        for secret_key in secret_keys.iter() {
+            let public_key_bytes: [u8; PUBLIC_KEY_SIZE] = PublicKey::from_secret_key(&secp, secret_key).serialize();
+            script_pubkey.push(PUBLIC_KEY_SIZE as u8); // "Push 33 bytes" opcode
+            script_pubkey.extend(public_key_bytes.iter());
+        }
+        script_pubkey.push(OP_1 as u8 + 2);
+        script_pubkey.push(OP_CHECKMULTISIG as u8);
+
+        // Two valid signatures on top of the extra item popped by OP_CHECKMULTISIG
+        let mut initial_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        initial_stack[0] = BnScalar::one();
+        initial_stack[1] = BnScalar::one();
+        initial_stack[2] = BnScalar::from(EMPTY_ARRAY_REPRESENTATION);
+
+        let pk_parser_initial_stack = vec![
+            StackElement::ValidSignature,
+            StackElement::ValidSignature,
+            StackElement::Data(vec![]),
+        ];
+        let collected_pks = collect_public_keys(script_pubkey.clone(), pk_parser_initial_stack).expect("PK collection failed");
+
+        let mut rng = XorShiftRng::seed_from_u64(1);
+        let aux_generator = Secp256k1Affine::random(&mut rng);
+        // The signatures are matched with the last two public keys
+        let signatures = generate_sign_data(secret_keys[1..].to_vec(), rng.clone());
+
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        let circuit = TestOpChecksigCircuit::<BnScalar, NUM_VERIFICATIONS> {
+            op_checksig_chip: OpCheckSigChip::<BnScalar, NUM_VERIFICATIONS> {
+                aux_generator,
+                window_size: 2,
+                enforce_low_s: false,
+                _marker: std::marker::PhantomData,
+            },
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            checksig_randomness: randomness,
+            initial_stack,
+            signatures,
+            collected_pks,
+        };
+
+        let public_input = generate_public_inputs(script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input, checksig_instance(&circuit.signatures)]);
+    }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test opchecksig -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn test_opchecksig_inconsistent_randomness() {
+        let k = 19;
+
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+        let public_key_bytes: [u8; PUBLIC_KEY_SIZE] = public_key.serialize();
         
         let mut script_pubkey: Vec<u8> = vec![];
-        script_pubkey.push(UNCOMPRESSED_PUBLIC_KEY_SIZE as u8); // "Push 65 bytes" opcode
+        script_pubkey.push(PUBLIC_KEY_SIZE as u8); // "Push 33 bytes" opcode
         script_pubkey.extend(public_key_bytes.iter());
         script_pubkey.push(OP_CHECKSIG as u8);
 
@@ -784,7 +1514,6 @@ mod tests {
         initial_stack_vec.extend_from_slice(&[BnScalar::zero(); MAX_STACK_DEPTH-1]);
         let initial_stack: [BnScalar; MAX_STACK_DEPTH] = initial_stack_vec.as_slice().try_into().unwrap();
         
-        // TODO: Derive initial stack and pk_parser_initial_stack from the same value
         let pk_parser_initial_stack = vec![StackElement::ValidSignature];
         let collected_pks = collect_public_keys(script_pubkey.clone(), pk_parser_initial_stack).expect("PK collection failed");
 
@@ -795,14 +1524,81 @@ mod tests {
         let r: u64 = rng.gen();
         let randomness: BnScalar = BnScalar::from(r);
 
+        // The script RLC and the public key RLCs are computed with different randomness values
         let circuit = TestOpChecksigCircuit::<BnScalar, MAX_CHECKSIG_COUNT> {
             op_checksig_chip: OpCheckSigChip::<BnScalar, MAX_CHECKSIG_COUNT> {
                 aux_generator,
                 window_size: 2,
+                enforce_low_s: false,
+                _marker: std::marker::PhantomData,
+            },
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            checksig_randomness: randomness + BnScalar::one(),
+            initial_stack,
+            signatures,
+            collected_pks,
+        };
+
+        let public_input = generate_public_inputs(script_pubkey, randomness);
+
+        let prover = MockProver::run(k, &circuit, vec![public_input.clone(), checksig_instance(&circuit.signatures)]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test opchecksig -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn test_opchecksig_min_k() {
+        let k = min_k::<BnScalar>(MAX_SCRIPT_PUBKEY_SIZE, MAX_CHECKSIG_COUNT);
+
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+        let public_key_bytes: [u8; PUBLIC_KEY_SIZE] = public_key.serialize();
+
+        let mut rng = XorShiftRng::seed_from_u64(1);
+
+        // A scriptPubkey of maximum size with MAX_CHECKSIG_COUNT signature verifications
+        let mut script_pubkey: Vec<u8> = vec![];
+        for _ in 0..MAX_CHECKSIG_COUNT {
+            script_pubkey.push(PUBLIC_KEY_SIZE as u8); // "Push 33 bytes" opcode
+            script_pubkey.extend(public_key_bytes.iter());
+            script_pubkey.push(OP_CHECKSIG as u8);
+        }
+        let data_push_len = MAX_SCRIPT_PUBKEY_SIZE - script_pubkey.len() - 3;
+        script_pubkey.push(OP_PUSHDATA2 as u8);
+        script_pubkey.push((data_push_len % 256) as u8);
+        script_pubkey.push((data_push_len / 256) as u8);
+        for _ in 0..data_push_len {
+            script_pubkey.push(rng.gen());
+        }
+        assert_eq!(script_pubkey.len(), MAX_SCRIPT_PUBKEY_SIZE);
+
+        let mut initial_stack_vec = vec![BnScalar::one(); MAX_CHECKSIG_COUNT];
+        initial_stack_vec.extend_from_slice(&vec![BnScalar::zero(); MAX_STACK_DEPTH-MAX_CHECKSIG_COUNT]);
+        let initial_stack: [BnScalar; MAX_STACK_DEPTH] = initial_stack_vec.as_slice().try_into().unwrap();
+
+        let pk_parser_initial_stack = vec![StackElement::ValidSignature; MAX_CHECKSIG_COUNT];
+        let collected_pks = collect_public_keys(script_pubkey.clone(), pk_parser_initial_stack).expect("PK collection failed");
+
+        let aux_generator = Secp256k1Affine::random(&mut rng);
+        let signatures = generate_sign_data(vec![secret_key; MAX_CHECKSIG_COUNT], rng.clone());
+
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        let mut circuit = TestOpChecksigCircuit::<BnScalar, MAX_CHECKSIG_COUNT> {
+            op_checksig_chip: OpCheckSigChip::<BnScalar, MAX_CHECKSIG_COUNT> {
+                aux_generator,
+                window_size: 2,
+                enforce_low_s: false,
                 _marker: std::marker::PhantomData,
             },
             script_pubkey: script_pubkey.clone(),
             randomness,
+            checksig_randomness: randomness,
             initial_stack,
             signatures,
             collected_pks,
@@ -810,8 +1606,593 @@ mod tests {
 
         let public_input = generate_public_inputs(script_pubkey, randomness);
 
-        let prover = MockProver::run(k, &circuit, vec![public_input.clone(), vec![]]).unwrap();
+        // The circuit fits at the computed k
+        let prover = MockProver::run(k, &circuit, vec![public_input.clone(), checksig_instance(&circuit.signatures)]).unwrap();
         prover.assert_satisfied();
+
+        // The circuit does not fit in half as many rows
+        assert!(MockProver::run(k-1, &circuit, vec![public_input.clone(), checksig_instance(&circuit.signatures)]).is_err());
+
+        // One signature over the limit is rejected
+        circuit.signatures.push(circuit.signatures[0].clone());
+        circuit.collected_pks.push(circuit.collected_pks[0].clone());
+        assert!(MockProver::run(k, &circuit, vec![public_input.clone(), checksig_instance(&circuit.signatures)]).is_err());
+    }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test opchecksig -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn test_opchecksig_min_k_is_smallest_k() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key_bytes = PublicKey::from_secret_key(&secp, &secret_key).serialize();
+        let (circuit, public_input) = p2pk_circuit(secret_key, &public_key_bytes);
+        let instances = vec![public_input, checksig_instance(&circuit.signatures)];
+
+        // The row bounds used by min_k hold for the regions of the circuit
+        let row_counts: HashMap<String, usize> = region_row_counts(&circuit).unwrap().into_iter().collect();
+        assert!(row_counts["ecc chip aux"] <= ECC_AUX_ROWS);
+        assert!(row_counts["ecdsa chip verification"] <= MAX_CHECKSIG_COUNT * ECDSA_ROWS_PER_VERIFICATION);
+
+        let smallest_k = (1..)
+            .find(|k| MockProver::run(*k, &circuit, instances.clone()).map_or(false, |prover| prover.verify().is_ok()))
+            .unwrap();
+        assert_eq!(min_k::<BnScalar>(circuit.script_pubkey.len(), MAX_CHECKSIG_COUNT), smallest_k);
+    }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test opchecksig -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn test_opchecksig_invalid_sighash_type() {
+        let k = 19;
+
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+        let public_key_bytes: [u8; PUBLIC_KEY_SIZE] = public_key.serialize();
+        
+        let mut script_pubkey: Vec<u8> = vec![];
+        script_pubkey.push(PUBLIC_KEY_SIZE as u8); // "Push 33 bytes" opcode
+        script_pubkey.extend(public_key_bytes.iter());
+        script_pubkey.push(OP_CHECKSIG as u8);
+
+        let mut initial_stack_vec = vec![BnScalar::one()]; // This value will force a signature verification later
+        initial_stack_vec.extend_from_slice(&[BnScalar::zero(); MAX_STACK_DEPTH-1]);
+        let initial_stack: [BnScalar; MAX_STACK_DEPTH] = initial_stack_vec.as_slice().try_into().unwrap();
+        
+        let pk_parser_initial_stack = vec![StackElement::ValidSignature];
+        let collected_pks = collect_public_keys(script_pubkey.clone(), pk_parser_initial_stack).expect("PK collection failed");
+
+        let mut rng = XorShiftRng::seed_from_u64(1);
+        let aux_generator = Secp256k1Affine::random(&mut rng);
+        let mut signatures = generate_sign_data(vec![secret_key], rng.clone());
+        // 0x04 is not a valid sighash flag
+        signatures[0].sighash_type = 0x04;
+
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        let circuit = TestOpChecksigCircuit::<BnScalar, MAX_CHECKSIG_COUNT> {
+            op_checksig_chip: OpCheckSigChip::<BnScalar, MAX_CHECKSIG_COUNT> {
+                aux_generator,
+                window_size: 2,
+                enforce_low_s: false,
+                _marker: std::marker::PhantomData,
+            },
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            checksig_randomness: randomness,
+            initial_stack,
+            signatures,
+            collected_pks,
+        };
+
+        let public_input = generate_public_inputs(script_pubkey, randomness);
+
+        assert!(MockProver::run(k, &circuit, vec![public_input.clone(), checksig_instance(&circuit.signatures)]).is_err());
+    }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test opchecksig -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn test_opchecksig_computed_sighash() {
+        let k = 19;
+
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+        let public_key_bytes: [u8; PUBLIC_KEY_SIZE] = public_key.serialize();
+
+        let mut script_pubkey: Vec<u8> = vec![];
+        script_pubkey.push(PUBLIC_KEY_SIZE as u8); // "Push 33 bytes" opcode
+        script_pubkey.extend(public_key_bytes.iter());
+        script_pubkey.push(OP_CHECKSIG as u8);
+
+        let mut initial_stack_vec = vec![BnScalar::one()]; // This value will force a signature verification later
+        initial_stack_vec.extend_from_slice(&[BnScalar::zero(); MAX_STACK_DEPTH-1]);
+        let initial_stack: [BnScalar; MAX_STACK_DEPTH] = initial_stack_vec.as_slice().try_into().unwrap();
+
+        let pk_parser_initial_stack = vec![StackElement::ValidSignature];
+        let collected_pks = collect_public_keys(script_pubkey.clone(), pk_parser_initial_stack).expect("PK collection failed");
+
+        // Sighash of the native P2WPKH input in the BIP143 example transaction
+        let sighash: [u8; 32] = hex::decode(
+            "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"
+        ).unwrap().try_into().unwrap();
+        let message_hash = message_hash_from_digest(&sighash);
+
+        let mut rng = XorShiftRng::seed_from_u64(1);
+        let aux_generator = Secp256k1Affine::random(&mut rng);
+        let signatures = generate_sign_data_for_message(vec![secret_key], message_hash, rng.clone());
+
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        let mut circuit = TestOpChecksigCircuit::<BnScalar, MAX_CHECKSIG_COUNT> {
+            op_checksig_chip: OpCheckSigChip::<BnScalar, MAX_CHECKSIG_COUNT> {
+                aux_generator,
+                window_size: 2,
+                enforce_low_s: false,
+                _marker: std::marker::PhantomData,
+            },
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            checksig_randomness: randomness,
+            initial_stack,
+            signatures,
+            collected_pks,
+        };
+
+        let public_input = generate_public_inputs(script_pubkey, randomness);
+
+        let prover = MockProver::run(k, &circuit, vec![public_input.clone(), checksig_instance(&circuit.signatures)]).unwrap();
+        prover.assert_satisfied();
+
+        // The message hash is a public input, so the signature does not prove another message
+        let default_message_input: Vec<BnScalar> = message_hash_public_inputs(&Fq::from(ECDSA_MESSAGE_HASH));
+        let prover = MockProver::run(k, &circuit, vec![public_input.clone(), default_message_input.clone()]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // The signature does not verify against the default message hash
+        circuit.signatures[0].message_hash = Fq::from(ECDSA_MESSAGE_HASH);
+        let prover = MockProver::run(k, &circuit, vec![public_input, default_message_input]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test opchecksig -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn test_opchecksig_common_public_key() {
+        let k = 19;
+
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let other_secret_key = SecretKey::from_slice(&[0xab; 32]).expect("32 bytes, within curve order");
+
+        let p2pk_script = |secret_key: &SecretKey| {
+            let public_key_bytes: [u8; PUBLIC_KEY_SIZE] = PublicKey::from_secret_key(&secp, secret_key).serialize();
+            let mut script_pubkey: Vec<u8> = vec![];
+            script_pubkey.push(PUBLIC_KEY_SIZE as u8); // "Push 33 bytes" opcode
+            script_pubkey.extend(public_key_bytes.iter());
+            script_pubkey.push(OP_CHECKSIG as u8);
+            script_pubkey
+        };
+
+        let mut initial_stack_vec = vec![BnScalar::one()]; // This value will force a signature verification later
+        initial_stack_vec.extend_from_slice(&[BnScalar::zero(); MAX_STACK_DEPTH-1]);
+        let initial_stack: [BnScalar; MAX_STACK_DEPTH] = initial_stack_vec.as_slice().try_into().unwrap();
+
+        let mut rng = XorShiftRng::seed_from_u64(1);
+        let aux_generator = Secp256k1Affine::random(&mut rng);
+        let signatures = generate_sign_data(vec![secret_key], rng.clone());
+
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        let same_key_scripts = vec![p2pk_script(&secret_key); 3];
+        let mut different_key_scripts = same_key_scripts.clone();
+        different_key_scripts[2] = p2pk_script(&other_secret_key);
+
+        for (script_pubkeys, should_verify) in [(same_key_scripts, true), (different_key_scripts, false)] {
+            let pk_parser_initial_stack = vec![StackElement::ValidSignature];
+            let collected_pks = collect_public_keys(script_pubkeys[0].clone(), pk_parser_initial_stack).expect("PK collection failed");
+
+            let circuit = TestCommonPkCircuit::<BnScalar, MAX_CHECKSIG_COUNT> {
+                op_checksig_chip: OpCheckSigChip::<BnScalar, MAX_CHECKSIG_COUNT> {
+                    aux_generator,
+                    window_size: 2,
+                    enforce_low_s: false,
+                    _marker: std::marker::PhantomData,
+                },
+                script_pubkeys: script_pubkeys.clone(),
+                randomness,
+                initial_stack,
+                signatures: signatures.clone(),
+                collected_pks,
+            };
+
+            let public_input: Vec<BnScalar> = script_pubkeys
+                .into_iter()
+                .flat_map(|script_pubkey| generate_public_inputs(script_pubkey, randomness))
+                .collect();
+
+            let prover = MockProver::run(k, &circuit, vec![public_input, checksig_instance(&circuit.signatures)]).unwrap();
+            assert_eq!(prover.verify().is_ok(), should_verify);
+        }
+    }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test opchecksig -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn test_opchecksig_pk_commitment() {
+        let k = 19;
+
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+        let public_key_bytes: [u8; PUBLIC_KEY_SIZE] = public_key.serialize();
+
+        let mut script_pubkey: Vec<u8> = vec![];
+        script_pubkey.push(PUBLIC_KEY_SIZE as u8); // "Push 33 bytes" opcode
+        script_pubkey.extend(public_key_bytes.iter());
+        script_pubkey.push(OP_CHECKSIG as u8);
+
+        let mut initial_stack_vec = vec![BnScalar::one()]; // This value will force a signature verification later
+        initial_stack_vec.extend_from_slice(&[BnScalar::zero(); MAX_STACK_DEPTH-1]);
+        let initial_stack: [BnScalar; MAX_STACK_DEPTH] = initial_stack_vec.as_slice().try_into().unwrap();
+
+        let pk_parser_initial_stack = vec![StackElement::ValidSignature];
+        let collected_pks = collect_public_keys(script_pubkey.clone(), pk_parser_initial_stack).expect("PK collection failed");
+
+        let mut rng = XorShiftRng::seed_from_u64(1);
+        let aux_generator = Secp256k1Affine::random(&mut rng);
+        let signatures = generate_sign_data(vec![secret_key], rng.clone());
+
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let blinding = Fq::random(&mut rng);
+
+        let circuit = TestPkCommitmentCircuit::<BnScalar, MAX_CHECKSIG_COUNT> {
+            op_checksig_chip: OpCheckSigChip::<BnScalar, MAX_CHECKSIG_COUNT> {
+                aux_generator,
+                window_size: 2,
+                enforce_low_s: false,
+                _marker: std::marker::PhantomData,
+            },
+            script_pubkey,
+            randomness,
+            initial_stack,
+            signatures: signatures.clone(),
+            collected_pks,
+            blindings: vec![blinding],
+        };
+
+        // The verifier only sees the randomness and the commitment, not the public key bytes
+        let commitment = commit_pk(&signatures[0].pk, blinding);
+        let message_hash_input: Vec<BnScalar> = checksig_instance(&signatures);
+        let commitment_input: Vec<BnScalar> = [pk_commitment_public_inputs(&commitment), message_hash_input.clone()].concat();
+        let prover = MockProver::run(k, &circuit, vec![vec![randomness], commitment_input]).unwrap();
+        prover.assert_satisfied();
+
+        // A commitment with a different blinding factor does not open to the verified key
+        let other_commitment = commit_pk(&signatures[0].pk, blinding + Fq::one());
+        let other_commitment_input: Vec<BnScalar> = [pk_commitment_public_inputs(&other_commitment), message_hash_input].concat();
+        let prover = MockProver::run(k, &circuit, vec![vec![randomness], other_commitment_input]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test opchecksig -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn test_opchecksig_failed_checksigs_not_verified() {
+        const VERIFIED_CHECKSIG_COUNT: usize = 2;
+
+        let secp = Secp256k1::new();
+        let secret_keys: Vec<SecretKey> = [0xab, 0xcd, 0xef, 0x12, 0x34]
+            .iter()
+            .map(|b| SecretKey::from_slice(&[*b; 32]).expect("32 bytes, within curve order"))
+            .collect();
+
+        // The zero in the initial stack makes the first three OP_CHECKSIGs fail, each one leaving
+        // the false result as the signature of the next. The last two use the signature pushed by OP_1.
+        // The zero is above an empty array, as zero items at the bottom of the stack are not counted.
+        let mut script_pubkey: Vec<u8> = vec![];
+        for (i, secret_key) in secret_keys.iter().enumerate() {
+            if i >= 3 {
+                script_pubkey.push(OP_1 as u8);
+            }
+            script_pubkey.push(PUBLIC_KEY_SIZE as u8); // "Push 33 bytes" opcode
+            script_pubkey.extend(PublicKey::from_secret_key(&secp, secret_key).serialize().iter());
+            script_pubkey.push(OP_CHECKSIG as u8);
+        }
+
+        let mut rng = XorShiftRng::seed_from_u64(1);
+        let aux_generator = Secp256k1Affine::random(&mut rng);
+        let signatures = generate_sign_data(secret_keys[3..].to_vec(), rng.clone());
+        let collected_pks: Vec<PublicKeyInScript> = secret_keys[3..]
+            .iter()
+            .zip(signatures.iter())
+            .map(|(secret_key, sign_data)| PublicKeyInScript {
+                bytes: PublicKey::from_secret_key(&secp, secret_key).serialize().to_vec(),
+                pk: sign_data.pk,
+            })
+            .collect();
+
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        // Five OP_CHECKSIGs need only two ECDSA verifications
+        let k = min_k::<BnScalar>(script_pubkey.len(), VERIFIED_CHECKSIG_COUNT);
+        let mut initial_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        initial_stack[1] = BnScalar::from(EMPTY_ARRAY_REPRESENTATION);
+        let circuit = TestOpChecksigCircuit::<BnScalar, VERIFIED_CHECKSIG_COUNT> {
+            op_checksig_chip: OpCheckSigChip::<BnScalar, VERIFIED_CHECKSIG_COUNT> {
+                aux_generator,
+                window_size: 2,
+                enforce_low_s: false,
+                _marker: std::marker::PhantomData,
+            },
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            checksig_randomness: randomness,
+            initial_stack,
+            signatures,
+            collected_pks,
+        };
+
+        // Only the two verified signatures are assigned ECDSA verifications, each one taking
+        // the rows of the verification of a P2PK spend
+        let ecdsa_rows = |row_counts: Vec<(String, usize)>| {
+            row_counts
+                .into_iter()
+                .find(|(name, _)| name == "ecdsa chip verification")
+                .expect("The ECDSA verifications are assigned")
+                .1
+        };
+        let (p2pk, _) = p2pk_circuit(secret_keys[0], &PublicKey::from_secret_key(&secp, &secret_keys[0]).serialize());
+        assert_eq!(
+            ecdsa_rows(region_row_counts(&circuit).unwrap()),
+            VERIFIED_CHECKSIG_COUNT * ecdsa_rows(region_row_counts(&p2pk).unwrap()),
+        );
+
+        let public_input = generate_public_inputs(script_pubkey, randomness);
+
+        let prover = MockProver::run(k, &circuit, vec![public_input, checksig_instance(&circuit.signatures)]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_opchecksig_pk_commitment_generator() {
+        let generator = pk_commitment_generator();
+        assert!(bool::from(generator.is_on_curve()));
+        assert!(generator != Secp256k1Affine::generator());
+
+        let pk = (Secp256k1Affine::generator() * Fq::from(5)).to_affine();
+        assert_eq!(commit_pk(&pk, Fq::zero()), pk);
+        assert!(commit_pk(&pk, Fq::one()) != pk);
+    }
+
+    // Assigns a row of the public key collection region without public keys, in which
+    // the power of randomness at `forged_power.0` is replaced by `forged_power.1`
+    struct TestPowersOfRandomnessCircuit<F: Field> {
+        pub randomness: F,
+        pub forged_power: Option<(usize, F)>,
+    }
+
+    impl<F: Field> Circuit<F> for TestPowersOfRandomnessCircuit<F> {
+        type Config = OpCheckSigConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                randomness: F::zero(),
+                forged_power: None,
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            OpCheckSigChip::<F, 1>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>
+        ) -> Result<(), Error> {
+            ParityTableChip::load(config.parity_table.clone(), &mut layouter)?;
+
+            layouter.assign_region(
+                || "Powers of randomness",
+                |mut region: Region<F>| {
+                    config.q_enable.enable(&mut region, 0)?;
+                    let num_checksig_opcodes_is_zero_chip
+                        = IsZeroChip::construct(config.num_checksig_opcodes_is_zero.clone());
+
+                    // The gates also query the next row
+                    for offset in 0..2 {
+                        let mut power = self.randomness;
+                        for i in 0..PK_POW_RAND_SIZE {
+                            let value = match self.forged_power {
+                                Some((forged_index, forged_value)) if offset == 0 && forged_index == i => forged_value,
+                                _ => power,
+                            };
+                            region.assign_advice(
+                                || "Power of randomness",
+                                config.powers_of_randomness[i],
+                                offset,
+                                || Value::known(value),
+                            )?;
+                            power = power * self.randomness;
+                        }
+
+                        region.assign_advice(|| "num_checksig_opcodes", config.num_checksig_opcodes, offset, || Value::known(F::zero()))?;
+                        num_checksig_opcodes_is_zero_chip.assign(&mut region, offset, Value::known(F::zero()))?;
+                        region.assign_advice(|| "pk_rlc_acc", config.pk_rlc_acc, offset, || Value::known(F::zero()))?;
+                        region.assign_advice(|| "pk_rlc", config.pk_rlc, offset, || Value::known(F::zero()))?;
+                        region.assign_advice(|| "pk_prefix", config.pk_prefix, offset, || Value::known(F::zero()))?;
+                        for coordinate in config.pk.iter() {
+                            for column in coordinate.iter() {
+                                region.assign_advice(|| "pk byte", *column, offset, || Value::known(F::zero()))?;
+                            }
+                        }
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_opchecksig_powers_of_randomness() {
+        let k = 10;
+        let mut rng = XorShiftRng::seed_from_u64(1);
+        let randomness = BnScalar::from(rng.next_u64());
+
+        let circuit = TestPowersOfRandomnessCircuit {
+            randomness,
+            forged_power: None,
+        };
+        assert_proves(k, &circuit, vec![vec![]]);
+
+        // powers[5] must be randomness^6
+        let circuit = TestPowersOfRandomnessCircuit {
+            randomness,
+            forged_power: Some((5, randomness.pow_vartime([5]))),
+        };
+        let failures = assert_rejects(k, &circuit, vec![vec![]]);
+        for failure in failures {
+            assert!(format!("{:?}", failure).contains("Check that the powers of randomness are consistent"));
+        }
+    }
+
+    #[test]
+    fn test_opchecksig_aux_generator_validation() {
+        let mut rng = XorShiftRng::seed_from_u64(1);
+        let generator = Secp256k1Affine::generator();
+
+        for degenerate_point in [Secp256k1Affine::identity(), generator, -generator] {
+            assert!(matches!(
+                OpCheckSigChip::<BnScalar, MAX_CHECKSIG_COUNT>::construct(degenerate_point, 2),
+                Err(BitcoinVmError::InvalidAuxGenerator),
+            ));
+        }
+        assert!(OpCheckSigChip::<BnScalar, MAX_CHECKSIG_COUNT>::construct(Secp256k1Affine::random(&mut rng), 2).is_ok());
+    }
+
+    #[test]
+    fn test_opchecksig_deterministic_aux_generator() {
+        type Chip = OpCheckSigChip<BnScalar, MAX_CHECKSIG_COUNT>;
+        let chip = Chip::with_deterministic_aux(1, 2).unwrap();
+        assert_eq!(chip.aux_generator, Chip::with_deterministic_aux(1, 2).unwrap().aux_generator);
+        assert_ne!(chip.aux_generator, Chip::with_deterministic_aux(2, 2).unwrap().aux_generator);
+        assert_ne!(chip.aux_generator, pk_commitment_generator());
+        assert!(is_valid_aux_generator(&chip.aux_generator));
+    }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test opchecksig -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn test_opchecksig_deterministic_aux_generator_proves() {
+        let k = 19;
+
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key_bytes = PublicKey::from_secret_key(&secp, &secret_key).serialize();
+        let (mut circuit, public_input) = p2pk_circuit(secret_key, &public_key_bytes);
+        circuit.op_checksig_chip = OpCheckSigChip::with_deterministic_aux(1, 2).unwrap();
+        assert_proves(k, &circuit, vec![public_input, checksig_instance(&circuit.signatures)]);
+    }
+
+    #[test]
+    fn test_opchecksig_signature_validation() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key_bytes: [u8; PUBLIC_KEY_SIZE] = PublicKey::from_secret_key(&secp, &secret_key).serialize();
+
+        let mut script_pubkey: Vec<u8> = vec![PUBLIC_KEY_SIZE as u8]; // "Push 33 bytes" opcode
+        script_pubkey.extend(public_key_bytes.iter());
+        script_pubkey.push(OP_CHECKSIG as u8);
+        let collected_pks = collect_public_keys(script_pubkey, vec![StackElement::ValidSignature]).expect("PK collection failed");
+
+        let rng = XorShiftRng::seed_from_u64(1);
+        let signatures = generate_sign_data(vec![secret_key], rng.clone());
+        type Chip = OpCheckSigChip<BnScalar, MAX_CHECKSIG_COUNT>;
+        assert_eq!(Chip::validate_signatures(&signatures, &collected_pks), Ok(()));
+
+        assert_eq!(
+            Chip::validate_signatures(&[], &collected_pks),
+            Err(BitcoinVmError::MismatchedSignatureCount { signatures: 0, public_keys: 1 }),
+        );
+        assert_eq!(
+            OpCheckSigChip::<BnScalar, 0>::validate_signatures(&signatures, &collected_pks),
+            Err(BitcoinVmError::TooManySignatures { signatures: 1, max_signatures: 0 }),
+        );
+
+        let other_secret_key = SecretKey::from_slice(&[0xce; 32]).expect("32 bytes, within curve order");
+        let other_signatures = generate_sign_data(vec![other_secret_key], rng);
+        assert_eq!(
+            Chip::validate_signatures(&other_signatures, &collected_pks),
+            Err(BitcoinVmError::MismatchedPublicKey { index: 0 }),
+        );
+
+        let mut invalid_sighash_signatures = signatures;
+        invalid_sighash_signatures[0].sighash_type = 0x04;
+        assert_eq!(
+            Chip::validate_signatures(&invalid_sighash_signatures, &collected_pks),
+            Err(BitcoinVmError::InvalidSighashType { index: 0, sighash_type: 0x04 }),
+        );
+    }
+
+    #[test]
+    fn test_opchecksig_no_double_assignment() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+        let public_key_bytes: [u8; PUBLIC_KEY_SIZE] = public_key.serialize();
+        
+        let mut script_pubkey: Vec<u8> = vec![];
+        script_pubkey.push(PUBLIC_KEY_SIZE as u8); // "Push 33 bytes" opcode
+        script_pubkey.extend(public_key_bytes.iter());
+        script_pubkey.push(OP_CHECKSIG as u8);
+
+        let mut initial_stack_vec = vec![BnScalar::one()]; // This value will force a signature verification later
+        initial_stack_vec.extend_from_slice(&[BnScalar::zero(); MAX_STACK_DEPTH-1]);
+        let initial_stack: [BnScalar; MAX_STACK_DEPTH] = initial_stack_vec.as_slice().try_into().unwrap();
+        
+        let pk_parser_initial_stack = vec![StackElement::ValidSignature];
+        let collected_pks = collect_public_keys(script_pubkey.clone(), pk_parser_initial_stack).expect("PK collection failed");
+
+        let mut rng = XorShiftRng::seed_from_u64(1);
+        let aux_generator = Secp256k1Affine::random(&mut rng);
+        let signatures = generate_sign_data(vec![secret_key], rng.clone());
+
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        let circuit = TestOpChecksigCircuit::<BnScalar, MAX_CHECKSIG_COUNT> {
+            op_checksig_chip: OpCheckSigChip::<BnScalar, MAX_CHECKSIG_COUNT> {
+                aux_generator,
+                window_size: 2,
+                enforce_low_s: false,
+                _marker: std::marker::PhantomData,
+            },
+            script_pubkey,
+            randomness,
+            checksig_randomness: randomness,
+            initial_stack,
+            signatures,
+            collected_pks,
+        };
+
+        let double_assignments = find_double_assignments(&circuit).unwrap();
+        assert!(double_assignments.is_empty(), "{:?}", double_assignments);
     }
 
     #[cfg(feature = "dev-graph")]
@@ -834,10 +2215,12 @@ mod tests {
             op_checksig_chip: OpCheckSigChip::<BnScalar, CHECKSIG_COUNT> {
                 aux_generator,
                 window_size: 2,
+                enforce_low_s: false,
                 _marker: std::marker::PhantomData,
             },
             script_pubkey: vec![1u8; 35], // placeholder value for plotting circuit layout
             randomness: BnScalar::one(),
+            checksig_randomness: BnScalar::one(),
             initial_stack: [BnScalar::one(); MAX_STACK_DEPTH],
             signatures: vec![SignData::default(); num_collected_pks],
             collected_pks: vec![coll_pk; num_collected_pks],
@@ -0,0 +1,1187 @@
+use std::marker::PhantomData;
+use crate::Field;
+use crate::bitcoinvm_circuit::execution::ExecutionChipAssignedCells;
+use crate::bitcoinvm_circuit::util::expr::Expr;
+use crate::ripemd160::{self, RIPEMD160Instructions};
+use crate::ripemd160::table16::{AssignedBits, PaddedMessage};
+use crate::sha1::{self, Sha1Instructions};
+use crate::sha256::{self, Sha256Instructions};
+use halo2_proofs::circuit::{AssignedCell, Layouter, Region, Value};
+use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Selector};
+use halo2_proofs::poly::Rotation;
+
+use crate::bitcoinvm_circuit::constants::*;
+
+/// OpHash configuration
+#[derive(Debug, Clone)]
+pub(crate) struct OpHashConfig<F: Field> {
+    // Rows of the bytes of a padded item, which are added to the RLC of the item if they are
+    // not padding
+    q_item_byte: Selector,
+    // Rows of the bytes of a digest
+    q_digest_byte: Selector,
+    // Rows before the bytes of an item or a digest, where the RLC starts from zero
+    q_rlc_start: Selector,
+    // Row before the hashes, where the accumulator starts from zero
+    q_acc_start: Selector,
+    // Rows accumulating the opcode, the length and the RLC of an item and the RLC of its digest
+    q_acc: Selector,
+
+    // Byte of a padded item or of a digest, copied from a hash gadget
+    byte: Column<Advice>,
+    // 1 if the byte belongs to the item and 0 if it is padding. In the accumulator rows, the
+    // flag of the first byte, which is 0 only for the empty array.
+    in_message: Column<Advice>,
+    // RLC of the bytes so far
+    rlc: Column<Advice>,
+    // Length of the item, RLC of the item and RLC of the digest in the accumulator rows
+    len: Column<Advice>,
+    item_rlc: Column<Advice>,
+    digest_rlc: Column<Advice>,
+    // Accumulator of the hashes, computed like hash_rlc_acc in the ExecutionChip
+    hash_rlc_acc: Column<Advice>,
+    randomness: Column<Advice>,
+    // Hash opcode of the item in the accumulator rows. The gadgets hashing the items are
+    // chosen by their opcodes, so the circuit depends on the opcodes like on the lengths.
+    opcode: Column<Fixed>,
+
+    ripemd160: ripemd160::table16::Table16Config<F>,
+    sha256: sha256::table16::Table16Config<F>,
+    sha1: sha1::table16::Table16Config<F>,
+}
+
+/// Gadget to verify the digests of the hash opcodes
+///
+/// The ExecutionChip takes the digest of a hash opcode as a witness and accumulates the opcode,
+/// the length and the RLC of the hashed item and the RLC of the digest in hash_rlc_acc. The
+/// chip hashes each item with the gadget of its opcode, computes the RLCs from the bytes of the
+/// padded gadget input and of the gadget output, and constrains the accumulator it computes
+/// to be the hash_rlc_acc of the ExecutionChip. The number of blocks of each item is fixed by
+/// its length, so the circuit depends on the opcodes and the lengths of the hashed items.
+#[derive(Clone, Debug)]
+pub(crate) struct OpHashChip<F: Field> {
+    /// Marker
+    pub _marker: PhantomData<F>,
+}
+
+impl<F: Field> OpHashChip<F> {
+    pub fn construct() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<F>,
+    ) -> OpHashConfig<F> {
+        let q_item_byte = meta.selector();
+        let q_digest_byte = meta.selector();
+        let q_rlc_start = meta.selector();
+        let q_acc_start = meta.selector();
+        let q_acc = meta.selector();
+
+        let byte = meta.advice_column();
+        let in_message = meta.advice_column();
+        let rlc = meta.advice_column();
+        let len = meta.advice_column();
+        let item_rlc = meta.advice_column();
+        let digest_rlc = meta.advice_column();
+        let hash_rlc_acc = meta.advice_column();
+        let randomness = meta.advice_column();
+        let opcode = meta.fixed_column();
+        for column in [byte, in_message, rlc, len, item_rlc, digest_rlc, hash_rlc_acc, randomness] {
+            meta.enable_equality(column);
+        }
+
+        // The SHA-256 and SHA-1 chips share the spread table of the RIPEMD-160 chip
+        let ripemd160 = ripemd160::table16::Table16Chip::configure(meta);
+        let sha256 = sha256::table16::Table16Chip::configure_with_spread_table(meta, ripemd160.spread_table());
+        let sha1 = sha1::table16::Table16Chip::configure_with_spread_table(meta, ripemd160.spread_table());
+
+        meta.create_gate("RLC starts from zero", |meta| {
+            let q_rlc_start = meta.query_selector(q_rlc_start);
+            let rlc = meta.query_advice(rlc, Rotation::cur());
+            vec![q_rlc_start * rlc]
+        });
+
+        meta.create_gate("Item RLC", |meta| {
+            let q_item_byte = meta.query_selector(q_item_byte);
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let in_message = meta.query_advice(in_message, Rotation::cur());
+            let prev_rlc = meta.query_advice(rlc, Rotation::prev());
+            let cur_rlc = meta.query_advice(rlc, Rotation::cur());
+            let randomness = meta.query_advice(randomness, Rotation::cur());
+
+            // Padding bytes leave the RLC unchanged
+            let message_rlc = prev_rlc.clone() * randomness + byte;
+            vec![
+                q_item_byte
+                    * (cur_rlc - in_message.clone() * message_rlc - (1u8.expr() - in_message) * prev_rlc)
+            ]
+        });
+
+        meta.create_gate("Digest RLC", |meta| {
+            let q_digest_byte = meta.query_selector(q_digest_byte);
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let prev_rlc = meta.query_advice(rlc, Rotation::prev());
+            let cur_rlc = meta.query_advice(rlc, Rotation::cur());
+            let randomness = meta.query_advice(randomness, Rotation::cur());
+            vec![q_digest_byte * (cur_rlc - prev_rlc * randomness - byte)]
+        });
+
+        meta.create_gate("hash_rlc_acc starts from zero", |meta| {
+            let q_acc_start = meta.query_selector(q_acc_start);
+            let acc = meta.query_advice(hash_rlc_acc, Rotation::cur());
+            vec![q_acc_start * acc]
+        });
+
+        // The values are accumulated as in the "Hash opcodes" gate of the ExecutionChip. The
+        // empty array is not the RLC of its bytes but EMPTY_ARRAY_REPRESENTATION.
+        meta.create_gate("hash_rlc_acc", |meta| {
+            let q_acc = meta.query_selector(q_acc);
+            let prev_acc = meta.query_advice(hash_rlc_acc, Rotation::prev());
+            let cur_acc = meta.query_advice(hash_rlc_acc, Rotation::cur());
+            let randomness = meta.query_advice(randomness, Rotation::cur());
+            let is_item_non_empty = meta.query_advice(in_message, Rotation::cur());
+            let item = meta.query_advice(item_rlc, Rotation::cur())
+                + (1u8.expr() - is_item_non_empty) * Expression::Constant(F::from(EMPTY_ARRAY_REPRESENTATION));
+
+            let mut acc = prev_acc;
+            for value in [
+                meta.query_fixed(opcode, Rotation::cur()),
+                meta.query_advice(len, Rotation::cur()),
+                item,
+                meta.query_advice(digest_rlc, Rotation::cur()),
+            ] {
+                acc = acc * randomness.clone() + value;
+            }
+            vec![q_acc * (cur_acc - acc)]
+        });
+
+        OpHashConfig {
+            q_item_byte,
+            q_digest_byte,
+            q_rlc_start,
+            q_acc_start,
+            q_acc,
+            byte,
+            in_message,
+            rlc,
+            len,
+            item_rlc,
+            digest_rlc,
+            hash_rlc_acc,
+            randomness,
+            opcode,
+            ripemd160,
+            sha256,
+            sha1,
+        }
+    }
+
+    /// Hashes the items hashed by the hash opcodes of the script, as returned by
+    /// `hash_inputs`, and constrains hash_rlc_acc of the ExecutionChip to accumulate
+    /// their digests
+    pub(crate) fn assign(
+        &self,
+        config: &OpHashConfig<F>,
+        layouter: &mut impl Layouter<F>,
+        execution_cells: &ExecutionChipAssignedCells<F>,
+        randomness: F,
+        hashed_items: &[(usize, Vec<u8>)],
+    ) -> Result<(), Error> {
+        self.assign_regions(config, layouter, &[(execution_cells, hashed_items.to_vec())], randomness)
+    }
+
+    /// Like `assign` for several unrolling regions of the ExecutionChip, such as the
+    /// scriptPubkey and the redeem script of a P2SH spend, which share the spread table.
+    /// Each region is given with the items hashed by its script.
+    pub(crate) fn assign_regions(
+        &self,
+        config: &OpHashConfig<F>,
+        layouter: &mut impl Layouter<F>,
+        regions: &[(&ExecutionChipAssignedCells<F>, Vec<(usize, Vec<u8>)>)],
+        randomness: F,
+    ) -> Result<(), Error> {
+        // The spread table is shared by the RIPEMD-160, SHA-256 and SHA-1 chips
+        ripemd160::table16::Table16Chip::load(config.ripemd160.clone(), layouter)?;
+
+        for (execution_cells, hashed_items) in regions {
+            self.assign_hashes(
+                config,
+                layouter,
+                &execution_cells.randomness,
+                &execution_cells.hash_rlc_acc,
+                randomness,
+                hashed_items,
+            )?;
+        }
+        Ok(())
+    }
+
+    // Hashes the items and constrains the accumulator of the hashes to be equal to the
+    // hash_rlc_acc cell, with RLCs computed with the randomness of the randomness cell. The
+    // spread table must be loaded.
+    fn assign_hashes(
+        &self,
+        config: &OpHashConfig<F>,
+        layouter: &mut impl Layouter<F>,
+        randomness_cell: &AssignedCell<F, F>,
+        hash_rlc_acc_cell: &AssignedCell<F, F>,
+        randomness: F,
+        hashed_items: &[(usize, Vec<u8>)],
+    ) -> Result<(), Error> {
+        let mut hashes = Vec::with_capacity(hashed_items.len());
+        for (opcode, item) in hashed_items {
+            let (padded, digest) = Self::hash_item(config, layouter, *opcode, item)?;
+            hashes.push((*opcode, padded, digest));
+        }
+
+        let rlcs = layouter.assign_region(
+            || "Hash opcode item and digest RLCs",
+            |mut region: Region<F>| {
+                let offset = &mut 0;
+                let mut rlcs = Vec::with_capacity(hashes.len());
+                for (_, padded, digest) in hashes.iter() {
+                    let item_rlc = Self::assign_rlc(
+                        config, &mut region, offset, randomness_cell, randomness, &padded.bytes, Some(&padded.in_message),
+                    )?;
+                    let digest_rlc = Self::assign_rlc(
+                        config, &mut region, offset, randomness_cell, randomness, digest, None,
+                    )?;
+                    rlcs.push((item_rlc, digest_rlc));
+                }
+                Ok(rlcs)
+            },
+        )?;
+
+        layouter.assign_region(
+            || "Hash opcode hash_rlc_acc verification",
+            |mut region: Region<F>| {
+                config.q_acc_start.enable(&mut region, 0)?;
+                let mut acc = Value::known(F::zero());
+                let mut acc_cell = region.assign_advice(|| "hash_rlc_acc", config.hash_rlc_acc, 0, || acc)?;
+
+                for (i, ((opcode, padded, _), (item_rlc, digest_rlc))) in hashes.iter().zip(rlcs.iter()).enumerate() {
+                    let offset = i + 1;
+                    config.q_acc.enable(&mut region, offset)?;
+                    region.assign_fixed(|| "opcode", config.opcode, offset, || Value::known(F::from(*opcode as u64)))?;
+                    let randomness_copy = randomness_cell.copy_advice(
+                        || "randomness", &mut region, config.randomness, offset,
+                    )?;
+                    let len = padded.len.copy_advice(|| "item length", &mut region, config.len, offset)?;
+                    let is_item_non_empty = padded.in_message[0].copy_advice(
+                        || "first byte in item", &mut region, config.in_message, offset,
+                    )?;
+                    let item_rlc = item_rlc.copy_advice(|| "item RLC", &mut region, config.item_rlc, offset)?;
+                    let digest_rlc = digest_rlc.copy_advice(|| "digest RLC", &mut region, config.digest_rlc, offset)?;
+
+                    let item = item_rlc.value().copied()
+                        .zip(is_item_non_empty.value().copied())
+                        .map(|(item_rlc, is_item_non_empty)| {
+                            item_rlc + (F::one() - is_item_non_empty) * F::from(EMPTY_ARRAY_REPRESENTATION)
+                        });
+                    for value in [
+                        Value::known(F::from(*opcode as u64)),
+                        len.value().copied(),
+                        item,
+                        digest_rlc.value().copied(),
+                    ] {
+                        acc = acc.zip(randomness_copy.value().copied()).zip(value)
+                            .map(|((acc, randomness), value)| acc * randomness + value);
+                    }
+                    acc_cell = region.assign_advice(|| "hash_rlc_acc", config.hash_rlc_acc, offset, || acc)?;
+                }
+
+                // The accumulator of all the hashes is the one of the ExecutionChip
+                region.constrain_equal(acc_cell.cell(), hash_rlc_acc_cell.cell())
+            },
+        )
+    }
+
+    /// Fused `OP_HASH160 <hash> OP_EQUALVERIFY` for HTLC-style scripts: hashes the preimage with
+    /// HASH160 and constrains the RLC of the preimage to be the stack item and the RLC of its
+    /// digest to be the expected hash, in one region and without an accumulator row. The
+    /// preimage is not empty, as the empty array is not the RLC of its bytes.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn assign_hash160_equalverify(
+        &self,
+        config: &OpHashConfig<F>,
+        layouter: &mut impl Layouter<F>,
+        randomness_cell: &AssignedCell<F, F>,
+        item_cell: &AssignedCell<F, F>,
+        expected_hash_cell: &AssignedCell<F, F>,
+        randomness: F,
+        preimage: &[u8],
+    ) -> Result<(), Error> {
+        ripemd160::table16::Table16Chip::load(config.ripemd160.clone(), layouter)?;
+        let (padded, digest) = Self::hash_item(config, layouter, OP_HASH160, preimage)?;
+
+        layouter.assign_region(
+            || "OP_HASH160 OP_EQUALVERIFY",
+            |mut region: Region<F>| {
+                let offset = &mut 0;
+                let item_rlc = Self::assign_rlc(
+                    config, &mut region, offset, randomness_cell, randomness, &padded.bytes, Some(&padded.in_message),
+                )?;
+                let digest_rlc = Self::assign_rlc(
+                    config, &mut region, offset, randomness_cell, randomness, &digest, None,
+                )?;
+                region.constrain_equal(item_rlc.cell(), item_cell.cell())?;
+                region.constrain_equal(digest_rlc.cell(), expected_hash_cell.cell())
+            },
+        )
+    }
+
+    /// Like `assign`, and also hashes the script unrolled in the region with HASH160, for a
+    /// script which is private and only known to the verifier by its hash. The length of the
+    /// hashed script is constrained to be the script_length of the region. Its RLC with the first
+    /// byte at the lowest power of the randomness, computed over the bytes in reverse order, is
+    /// constrained to be the script_rlc_acc_init. Returns the cell of the RLC of the digest, to
+    /// be exposed instead of the script.
+    pub(crate) fn assign_with_script_hash160(
+        &self,
+        config: &OpHashConfig<F>,
+        layouter: &mut impl Layouter<F>,
+        execution_cells: &ExecutionChipAssignedCells<F>,
+        randomness: F,
+        hashed_items: &[(usize, Vec<u8>)],
+        script: &[u8],
+    ) -> Result<AssignedCell<F, F>, Error> {
+        self.assign(config, layouter, execution_cells, randomness, hashed_items)?;
+        let (padded, digest) = Self::hash_item(config, layouter, OP_HASH160, script)?;
+
+        layouter.assign_region(
+            || "Script HASH160",
+            |mut region: Region<F>| {
+                let offset = &mut 0;
+                // The padding bytes come first in reverse order and leave the RLC at zero
+                let reversed_bytes: Vec<AssignedBits<F, 8>> = padded.bytes.iter().rev().cloned().collect();
+                let reversed_in_message: Vec<AssignedCell<F, F>> = padded.in_message.iter().rev().cloned().collect();
+                let script_rlc = Self::assign_rlc(
+                    config, &mut region, offset, &execution_cells.randomness, randomness, &reversed_bytes, Some(&reversed_in_message),
+                )?;
+                let digest_rlc = Self::assign_rlc(
+                    config, &mut region, offset, &execution_cells.randomness, randomness, &digest, None,
+                )?;
+                region.constrain_equal(script_rlc.cell(), execution_cells.script_rlc_acc_init.cell())?;
+                region.constrain_equal(padded.len.cell(), execution_cells.script_length.cell())?;
+                Ok(digest_rlc)
+            },
+        )
+    }
+
+    // Hashes the item with the gadget of the hash opcode and returns the item padded in the
+    // circuit and the bytes of its digest
+    fn hash_item(
+        config: &OpHashConfig<F>,
+        layouter: &mut impl Layouter<F>,
+        opcode: usize,
+        item: &[u8],
+    ) -> Result<(PaddedMessage<F>, Vec<AssignedBits<F, 8>>), Error> {
+        let message: Vec<Value<u8>> = item.iter().map(|byte| Value::known(*byte)).collect();
+        let len = Value::known(item.len() as u64);
+        match opcode {
+            OP_RIPEMD160 => {
+                let chip = ripemd160::table16::Table16Chip::construct(config.ripemd160.clone());
+                let padded = chip.pad_message_bytes(&mut layouter.namespace(|| "pad"), &message, len)?;
+                let mut state = chip.initialization_vector(&mut layouter.namespace(|| "init"))?;
+                for block in padded.blocks.iter() {
+                    state = chip.compress_assigned(&mut layouter.namespace(|| "update"), &state, block)?;
+                }
+                let digest = chip.digest_bytes(&mut layouter.namespace(|| "digest"), &state)?;
+                Ok((padded, digest))
+            }
+            OP_SHA256 | OP_HASH256 | OP_HASH160 => {
+                let chip = sha256::table16::Table16Chip::construct(config.sha256.clone());
+                let padded = chip.pad_message_bytes(&mut layouter.namespace(|| "pad"), &message, len)?;
+                let mut state = chip.initialization_vector(&mut layouter.namespace(|| "init"))?;
+                for block in padded.blocks.iter() {
+                    state = chip.compress_assigned(&mut layouter.namespace(|| "update"), &state, block)?;
+                }
+                let digest = match opcode {
+                    // HASH256 hashes the SHA-256 digest again, as the single block of a 32-byte
+                    // message
+                    OP_HASH256 => {
+                        let digest = chip.digest_assigned(&mut layouter.namespace(|| "inner digest"), &state)?;
+                        let init = chip.initialization_vector(&mut layouter.namespace(|| "outer init"))?;
+                        let state = chip.compress_digest(&mut layouter.namespace(|| "outer update"), &init, &digest)?;
+                        chip.digest_bytes(&mut layouter.namespace(|| "digest"), &state)?
+                    }
+                    // HASH160 hashes the SHA-256 digest with RIPEMD-160. The digest words are
+                    // copied into the padded RIPEMD-160 message, so the intermediate digest
+                    // cannot be replaced.
+                    OP_HASH160 => {
+                        let digest = chip.digest_assigned(&mut layouter.namespace(|| "inner digest"), &state)?;
+                        let chip = ripemd160::table16::Table16Chip::construct(config.ripemd160.clone());
+                        let blocks = chip.pad_be_words(&mut layouter.namespace(|| "pad digest"), &digest)?;
+                        let mut state = chip.initialization_vector(&mut layouter.namespace(|| "outer init"))?;
+                        for block in blocks.iter() {
+                            state = chip.compress_assigned(&mut layouter.namespace(|| "outer update"), &state, block)?;
+                        }
+                        chip.digest_bytes(&mut layouter.namespace(|| "digest"), &state)?
+                    }
+                    _ => chip.digest_bytes(&mut layouter.namespace(|| "digest"), &state)?,
+                };
+                Ok((padded, digest))
+            }
+            OP_SHA1 => {
+                let chip = sha1::table16::Table16Chip::construct(config.sha1.clone());
+                let padded = chip.pad_message_bytes(&mut layouter.namespace(|| "pad"), &message, len)?;
+                let mut state = chip.initialization_vector(&mut layouter.namespace(|| "init"))?;
+                for block in padded.blocks.iter() {
+                    state = chip.compress_assigned(&mut layouter.namespace(|| "update"), &state, block)?;
+                }
+                let digest = chip.digest_bytes(&mut layouter.namespace(|| "digest"), &state)?;
+                Ok((padded, digest))
+            }
+            _ => panic!("Opcode {:#x} is not a hash opcode", opcode),
+        }
+    }
+
+    // Assigns the RLC of the bytes starting from zero in the row at `offset`, and returns the
+    // cell of the RLC of all the bytes. The bytes of a padded item are only added to the RLC if
+    // their flag in `in_message` says they belong to the item.
+    fn assign_rlc(
+        config: &OpHashConfig<F>,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        randomness_cell: &AssignedCell<F, F>,
+        randomness: F,
+        bytes: &[AssignedBits<F, 8>],
+        in_message: Option<&[AssignedCell<F, F>]>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        config.q_rlc_start.enable(region, *offset)?;
+        let mut rlc = Value::known(F::zero());
+        let mut rlc_cell = region.assign_advice(|| "RLC", config.rlc, *offset, || rlc)?;
+        *offset += 1;
+
+        for (i, byte) in bytes.iter().enumerate() {
+            let in_message = match in_message {
+                Some(in_message) => {
+                    config.q_item_byte.enable(region, *offset)?;
+                    in_message[i]
+                        .copy_advice(|| "in message", region, config.in_message, *offset)?
+                        .value()
+                        .map(|in_message| *in_message == F::one())
+                }
+                None => {
+                    config.q_digest_byte.enable(region, *offset)?;
+                    Value::known(true)
+                }
+            };
+            byte.copy_advice(|| "byte", region, config.byte, *offset)?;
+            randomness_cell.copy_advice(|| "randomness", region, config.randomness, *offset)?;
+
+            rlc = rlc.zip(in_message).zip(byte.value_u8()).map(|((rlc, in_message), byte)| {
+                if in_message {
+                    rlc * randomness + F::from(byte as u64)
+                } else {
+                    rlc
+                }
+            });
+            rlc_cell = region.assign_advice(|| "RLC", config.rlc, *offset, || rlc)?;
+            *offset += 1;
+        }
+        Ok(rlc_cell)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::arithmetic::Field as HaloField;
+    use halo2_proofs::circuit::{Layouter, Region, SimpleFloorPlanner, Value};
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bn256::Fr as BnScalar;
+    use halo2_proofs::plonk::{Advice, Circuit, Column, ConstraintSystem, Error};
+    use rand::Rng;
+
+    use crate::bitcoinvm_circuit::constants::*;
+    use crate::bitcoinvm_circuit::execution::{ExecutionChip, ExecutionConfig};
+    use crate::bitcoinvm_circuit::public_inputs::{
+        nlocktime_row, nsequence_row, PublicInputs, EXECUTION_INSTANCE_ROWS, RANDOMNESS_ROW, SCRIPT_LENGTH_ROW, SCRIPT_RLC_ACC_ROW,
+    };
+    use crate::bitcoinvm_circuit::util::script_parser::{bytes_item, hash_digest, hash_inputs};
+    use crate::bitcoinvm_circuit::util::script_type::split_p2sh_script_sig;
+    use crate::testing::{assert_proves, assert_rejects};
+    use crate::Field;
+    use crate::ripemd160;
+    use super::{OpHashChip, OpHashConfig};
+
+    // The spread table of the hash gadgets has 2^16 rows
+    const K: u32 = 17;
+
+    #[derive(Clone, Debug)]
+    struct TestOpHashCircuitConfig<F: Field> {
+        execution_config: ExecutionConfig<F>,
+        op_hash_config: OpHashConfig<F>,
+    }
+
+    struct TestOpHashCircuit<F: Field> {
+        pub script_pubkey: Vec<u8>,
+        pub randomness: F,
+        // Opcodes and items hashed by the OpHash chip; those of the script in an honest assignment
+        pub hashed_items: Vec<(usize, Vec<u8>)>,
+    }
+
+    impl<F: Field> Circuit<F> for TestOpHashCircuit<F> {
+        type Config = TestOpHashCircuitConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                script_pubkey: vec![],
+                randomness: F::one(),
+                hashed_items: self.hashed_items.clone(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            TestOpHashCircuitConfig {
+                execution_config: ExecutionChip::<F>::configure(meta),
+                op_hash_config: OpHashChip::<F>::configure(meta),
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>
+        ) -> Result<(), Error> {
+            let exec_chip = ExecutionChip::construct();
+            let execution_cells = exec_chip.assign_script_pubkey_unroll(
+                config.execution_config.clone(),
+                &mut layouter,
+                self.script_pubkey.clone(),
+                self.randomness,
+                [F::zero(); MAX_STACK_DEPTH],
+            )?;
+
+            let execution_config = config.execution_config.clone();
+            exec_chip.expose_public(execution_config.clone(), layouter.namespace(|| "script_length"), execution_cells.script_length.clone(), 0)?;
+            exec_chip.expose_public(execution_config.clone(), layouter.namespace(|| "script_rlc_acc"), execution_cells.script_rlc_acc_init.clone(), 1)?;
+            exec_chip.expose_public(execution_config.clone(), layouter.namespace(|| "randomness"), execution_cells.randomness.clone(), 2)?;
+            exec_chip.expose_public(execution_config.clone(), layouter.namespace(|| "nlocktime"), execution_cells.nlocktime.clone(), nlocktime_row(1))?;
+            exec_chip.expose_public(execution_config, layouter.namespace(|| "nsequence"), execution_cells.nsequence.clone(), nsequence_row(1))?;
+
+            OpHashChip::construct().assign(
+                &config.op_hash_config,
+                &mut layouter,
+                &execution_cells,
+                self.randomness,
+                &self.hashed_items,
+            )
+        }
+    }
+
+    // Circuit of the script hashing the items of the script and its public inputs
+    fn circuit_with_public_input(
+        script_pubkey: &[u8],
+        randomness: BnScalar,
+    ) -> (TestOpHashCircuit<BnScalar>, Vec<BnScalar>) {
+        let circuit = TestOpHashCircuit {
+            script_pubkey: script_pubkey.to_vec(),
+            randomness,
+            hashed_items: hash_inputs(script_pubkey, randomness, [BnScalar::zero(); MAX_STACK_DEPTH]),
+        };
+        let public_input = PublicInputs::new(script_pubkey.to_vec(), randomness).execution_instance();
+        (circuit, public_input)
+    }
+
+    // Script which pushes the bytes with a single push opcode
+    fn push_bytes(bytes: &[u8]) -> Vec<u8> {
+        if bytes.is_empty() {
+            return vec![OP_0 as u8];
+        }
+        let mut script = vec![bytes.len() as u8];
+        script.extend_from_slice(bytes);
+        script
+    }
+
+    fn hash_equals(opcode: usize, preimage: &[u8], digest: &[u8]) -> Vec<u8> {
+        let mut script = push_bytes(preimage);
+        script.push(opcode as u8);
+        script.extend(push_bytes(digest));
+        script.push(OP_EQUAL as u8);
+        script
+    }
+
+    // Asserts that the items of the script are hashed with the gadget of their opcode
+    fn assert_hash_digests(opcode: usize, randomness: BnScalar) {
+        // The empty array, an item of one block and a public key of two blocks
+        let uncompressed_pk = [0x04; 65];
+        for preimage in [&b""[..], &b"abc"[..], &uncompressed_pk[..]] {
+            let script_pubkey = hash_equals(opcode, preimage, &hash_digest(opcode, preimage));
+            let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+            assert_eq!(circuit.hashed_items, vec![(opcode, preimage.to_vec())]);
+            assert_proves(K, &circuit, vec![public_input]);
+        }
+
+        // A digest is hashed again
+        let digest = hash_digest(opcode, &hash_digest(opcode, b"abc"));
+        let mut script_pubkey = push_bytes(b"abc");
+        script_pubkey.extend([opcode as u8, opcode as u8]);
+        script_pubkey.extend(push_bytes(&digest));
+        script_pubkey.push(OP_EQUAL as u8);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_eq!(circuit.hashed_items.len(), 2);
+        assert_proves(K, &circuit, vec![public_input]);
+    }
+
+    #[test]
+    fn test_op_ripemd160_digests() {
+        let mut rng = rand::thread_rng();
+        let randomness = BnScalar::from(rng.gen::<u64>());
+        assert_hash_digests(OP_RIPEMD160, randomness);
+
+        // Without hash opcodes, the accumulator is zero
+        let (circuit, public_input) = circuit_with_public_input(&[OP_1 as u8], randomness);
+        assert!(circuit.hashed_items.is_empty());
+        assert_proves(K, &circuit, vec![public_input]);
+    }
+
+    #[test]
+    fn test_op_sha256_digests() {
+        use bitcoin_hashes::{hash160, Hash};
+
+        let mut rng = rand::thread_rng();
+        let randomness = BnScalar::from(rng.gen::<u64>());
+        assert_hash_digests(OP_SHA256, randomness);
+
+        // The RIPEMD-160 digest of a SHA-256 digest is the HASH160 of a public key. The items
+        // are hashed by different gadgets in the order of the opcodes.
+        let pubkey = hex::decode("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+        let mut script_pubkey = push_bytes(&pubkey);
+        script_pubkey.extend([OP_SHA256 as u8, OP_RIPEMD160 as u8]);
+        script_pubkey.extend(push_bytes(&hash160::Hash::hash(&pubkey).into_inner()));
+        script_pubkey.push(OP_EQUAL as u8);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_eq!(
+            circuit.hashed_items.iter().map(|(opcode, _)| *opcode).collect::<Vec<_>>(),
+            vec![OP_SHA256, OP_RIPEMD160],
+        );
+        assert_proves(K, &circuit, vec![public_input]);
+    }
+
+    #[test]
+    fn test_op_hash_other_items() {
+        let mut rng = rand::thread_rng();
+        let randomness = BnScalar::from(rng.gen::<u64>());
+        let mut script_pubkey = push_bytes(b"abc");
+        script_pubkey.extend([OP_RIPEMD160 as u8, OP_DROP as u8, OP_1 as u8]);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_eq!(circuit.hashed_items, vec![(OP_RIPEMD160, b"abc".to_vec())]);
+        assert_proves(K, &circuit, vec![public_input.clone()]);
+
+        // The chip has to hash the items hashed by the script, all of them and in the same order,
+        // with the hash function of their opcode
+        for hashed_items in [
+            vec![(OP_RIPEMD160, b"abd".to_vec())],
+            vec![],
+            vec![(OP_RIPEMD160, b"abc".to_vec()), (OP_RIPEMD160, b"abc".to_vec())],
+            vec![(OP_SHA256, b"abc".to_vec())],
+        ] {
+            let circuit = TestOpHashCircuit { script_pubkey: script_pubkey.clone(), randomness, hashed_items };
+            assert_rejects(K, &circuit, vec![public_input.clone()]);
+        }
+    }
+
+    // Checks the items against an accumulator assigned from the given digests, standing for the
+    // witnesses of a prover who pushes these digests in the ExecutionChip
+    struct TestForgedDigestCircuit<F: Field> {
+        pub randomness: F,
+        pub hashed_items: Vec<(usize, Vec<u8>)>,
+        pub digests: Vec<Vec<u8>>,
+    }
+
+    impl<F: Field> Circuit<F> for TestForgedDigestCircuit<F> {
+        type Config = (OpHashConfig<F>, Column<Advice>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                randomness: F::one(),
+                hashed_items: self.hashed_items.clone(),
+                digests: self.digests.clone(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let accumulator = meta.advice_column();
+            meta.enable_equality(accumulator);
+            (OpHashChip::<F>::configure(meta), accumulator)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>
+        ) -> Result<(), Error> {
+            let (config, accumulator) = config;
+            let hash_rlc_acc = self.hashed_items.iter().zip(self.digests.iter()).fold(F::zero(), |acc, ((opcode, item), digest)| {
+                [
+                    F::from(*opcode as u64),
+                    F::from(item.len() as u64),
+                    bytes_item(item, self.randomness),
+                    bytes_item(digest, self.randomness),
+                ]
+                .into_iter()
+                .fold(acc, |acc, value| acc * self.randomness + value)
+            });
+            let (randomness_cell, hash_rlc_acc_cell) = layouter.assign_region(
+                || "ExecutionChip accumulator",
+                |mut region: Region<F>| {
+                    Ok((
+                        region.assign_advice(|| "randomness", accumulator, 0, || Value::known(self.randomness))?,
+                        region.assign_advice(|| "hash_rlc_acc", accumulator, 1, || Value::known(hash_rlc_acc))?,
+                    ))
+                },
+            )?;
+            ripemd160::table16::Table16Chip::load(config.ripemd160.clone(), &mut layouter)?;
+            OpHashChip::construct().assign_hashes(
+                &config,
+                &mut layouter,
+                &randomness_cell,
+                &hash_rlc_acc_cell,
+                self.randomness,
+                &self.hashed_items,
+            )
+        }
+    }
+
+    // Asserts that the digests of the items are checked by the gadget of the opcode
+    fn assert_forged_digest_rejected(opcode: usize, randomness: BnScalar) {
+        let hashed_items = vec![(opcode, b"abc".to_vec()), (opcode, vec![0x11; 75])];
+        let digests: Vec<Vec<u8>> = hashed_items.iter().map(|(opcode, item)| hash_digest(*opcode, item)).collect();
+
+        let circuit = TestForgedDigestCircuit { randomness, hashed_items: hashed_items.clone(), digests: digests.clone() };
+        assert_proves(K, &circuit, vec![]);
+
+        // A digest which is not the digest of its item fails the circuit, even if it only differs
+        // in its last byte
+        let mut forged_digests = digests;
+        let last = forged_digests[1].len() - 1;
+        forged_digests[1][last] ^= 1;
+        let circuit = TestForgedDigestCircuit { randomness, hashed_items, digests: forged_digests };
+        assert_rejects(K, &circuit, vec![]);
+    }
+
+    #[test]
+    fn test_op_ripemd160_forged_digest() {
+        let mut rng = rand::thread_rng();
+        let randomness = BnScalar::from(rng.gen::<u64>());
+        assert_forged_digest_rejected(OP_RIPEMD160, randomness);
+    }
+
+    #[test]
+    fn test_op_sha256_forged_digest() {
+        let mut rng = rand::thread_rng();
+        let randomness = BnScalar::from(rng.gen::<u64>());
+        assert_forged_digest_rejected(OP_SHA256, randomness);
+
+        // The digest of another hash function of the item fails the circuit
+        let hashed_items = vec![(OP_SHA256, b"abc".to_vec())];
+        let digests = vec![hash_digest(OP_HASH256, b"abc")];
+        let circuit = TestForgedDigestCircuit { randomness, hashed_items, digests };
+        assert_rejects(K, &circuit, vec![]);
+    }
+
+    #[test]
+    fn test_op_sha1_digests() {
+        let mut rng = rand::thread_rng();
+        let randomness = BnScalar::from(rng.gen::<u64>());
+        assert_hash_digests(OP_SHA1, randomness);
+    }
+
+    #[test]
+    fn test_op_sha1_forged_digest() {
+        let mut rng = rand::thread_rng();
+        let randomness = BnScalar::from(rng.gen::<u64>());
+        assert_forged_digest_rejected(OP_SHA1, randomness);
+    }
+
+    #[test]
+    fn test_op_hash160_digests() {
+        let mut rng = rand::thread_rng();
+        let randomness = BnScalar::from(rng.gen::<u64>());
+        assert_hash_digests(OP_HASH160, randomness);
+    }
+
+    #[test]
+    fn test_op_hash160_forged_digest() {
+        let mut rng = rand::thread_rng();
+        let randomness = BnScalar::from(rng.gen::<u64>());
+        assert_forged_digest_rejected(OP_HASH160, randomness);
+
+        // The RIPEMD-160 digest of the item itself fails the circuit
+        let hashed_items = vec![(OP_HASH160, b"abc".to_vec())];
+        let digests = vec![hash_digest(OP_RIPEMD160, b"abc")];
+        let circuit = TestForgedDigestCircuit { randomness, hashed_items, digests };
+        assert_rejects(K, &circuit, vec![]);
+    }
+
+    // Checks a preimage against a stack item and an expected hash assigned from the given bytes
+    struct TestHash160EqualVerifyCircuit<F: Field> {
+        pub randomness: F,
+        pub item: Vec<u8>,
+        pub expected_hash: Vec<u8>,
+        pub preimage: Vec<u8>,
+    }
+
+    impl<F: Field> Circuit<F> for TestHash160EqualVerifyCircuit<F> {
+        type Config = (OpHashConfig<F>, Column<Advice>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                randomness: F::one(),
+                item: vec![],
+                expected_hash: vec![],
+                preimage: self.preimage.clone(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let stack = meta.advice_column();
+            meta.enable_equality(stack);
+            (OpHashChip::<F>::configure(meta), stack)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>
+        ) -> Result<(), Error> {
+            let (config, stack) = config;
+            let (randomness_cell, item_cell, expected_hash_cell) = layouter.assign_region(
+                || "ExecutionChip stack",
+                |mut region: Region<F>| {
+                    Ok((
+                        region.assign_advice(|| "randomness", stack, 0, || Value::known(self.randomness))?,
+                        region.assign_advice(|| "item", stack, 1, || Value::known(bytes_item(&self.item, self.randomness)))?,
+                        region.assign_advice(|| "expected hash", stack, 2, || Value::known(bytes_item(&self.expected_hash, self.randomness)))?,
+                    ))
+                },
+            )?;
+            OpHashChip::construct().assign_hash160_equalverify(
+                &config,
+                &mut layouter,
+                &randomness_cell,
+                &item_cell,
+                &expected_hash_cell,
+                self.randomness,
+                &self.preimage,
+            )
+        }
+    }
+
+    #[test]
+    fn test_hash160_equalverify() {
+        let mut rng = rand::thread_rng();
+        let randomness = BnScalar::from(rng.gen::<u64>());
+        let preimage: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
+        let expected_hash = hash_digest(OP_HASH160, &preimage);
+
+        // Knowledge of the preimage of the hash on the stack
+        let circuit = TestHash160EqualVerifyCircuit {
+            randomness,
+            item: preimage.clone(),
+            expected_hash: expected_hash.clone(),
+            preimage: preimage.clone(),
+        };
+        assert_proves(K, &circuit, vec![]);
+
+        // A wrong preimage on the stack fails, whether the gadget hashes it or the stack item is
+        // replaced by the right preimage
+        let mut wrong_preimage = preimage.clone();
+        wrong_preimage[31] ^= 1;
+        for (item, preimage) in [(wrong_preimage.clone(), wrong_preimage.clone()), (wrong_preimage, preimage)] {
+            let circuit = TestHash160EqualVerifyCircuit { randomness, item, expected_hash: expected_hash.clone(), preimage };
+            assert_rejects(K, &circuit, vec![]);
+        }
+    }
+
+    // P2SH spend whose scriptPubkey hashes the redeem script with the OpHash chip
+    struct TestP2shCircuit<F: Field> {
+        pub script_sig: Vec<u8>,
+        pub script_pubkey: Vec<u8>,
+        pub randomness: F,
+    }
+
+    impl<F: Field> Circuit<F> for TestP2shCircuit<F> {
+        type Config = TestOpHashCircuitConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                script_sig: self.script_sig.clone(),
+                script_pubkey: self.script_pubkey.clone(),
+                randomness: F::one(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            TestOpHashCircuitConfig {
+                execution_config: ExecutionChip::<F>::configure(meta),
+                op_hash_config: OpHashChip::<F>::configure(meta),
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>
+        ) -> Result<(), Error> {
+            let exec_chip = ExecutionChip::construct();
+            let execution_cells = exec_chip.assign_p2sh_spend(
+                config.execution_config.clone(),
+                &mut layouter,
+                self.script_sig.clone(),
+                self.script_pubkey.clone(),
+                self.randomness,
+            )?;
+
+            let num_scripts = execution_cells.len();
+            for (i, chip_cells) in execution_cells.iter().enumerate() {
+                let first_row = i * EXECUTION_INSTANCE_ROWS;
+                let execution_config = config.execution_config.clone();
+                exec_chip.expose_public(execution_config.clone(), layouter.namespace(|| "script_length"), chip_cells.script_length.clone(), first_row + SCRIPT_LENGTH_ROW)?;
+                exec_chip.expose_public(execution_config.clone(), layouter.namespace(|| "script_rlc_acc"), chip_cells.script_rlc_acc_init.clone(), first_row + SCRIPT_RLC_ACC_ROW)?;
+                exec_chip.expose_public(execution_config.clone(), layouter.namespace(|| "randomness"), chip_cells.randomness.clone(), first_row + RANDOMNESS_ROW)?;
+                exec_chip.expose_public(execution_config.clone(), layouter.namespace(|| "nlocktime"), chip_cells.nlocktime.clone(), nlocktime_row(num_scripts))?;
+                exec_chip.expose_public(execution_config, layouter.namespace(|| "nsequence"), chip_cells.nsequence.clone(), nsequence_row(num_scripts))?;
+            }
+
+            // The scriptSigs are push-only, so the items hashed by the scriptPubkey and the redeem
+            // script are those hashed after their scriptSigs
+            let (redeem_script_sig, redeem_script) = split_p2sh_script_sig(&self.script_sig)?;
+            let hashed_items = |script_sig: &[u8], script_pubkey: &[u8]| {
+                hash_inputs(&[script_sig, script_pubkey].concat(), self.randomness, [F::zero(); MAX_STACK_DEPTH])
+            };
+            OpHashChip::construct().assign_regions(
+                &config.op_hash_config,
+                &mut layouter,
+                &[
+                    (&execution_cells[1], hashed_items(&self.script_sig, &self.script_pubkey)),
+                    (&execution_cells[3], hashed_items(&redeem_script_sig, &redeem_script)),
+                ],
+                self.randomness,
+            )
+        }
+    }
+
+    #[test]
+    fn test_p2sh_spend() {
+        use secp256k1::{PublicKey, Secp256k1, SecretKey};
+
+        let mut rng = rand::thread_rng();
+        let randomness = BnScalar::from(rng.gen::<u64>());
+
+        let secp = Secp256k1::new();
+        let redeem_scripts: Vec<Vec<u8>> = [0xcd, 0xce]
+            .iter()
+            .map(|b| {
+                let secret_key = SecretKey::from_slice(&[*b; 32]).expect("32 bytes, within curve order");
+                let mut redeem_script = push_bytes(&PublicKey::from_secret_key(&secp, &secret_key).serialize());
+                redeem_script.push(OP_CHECKSIG as u8);
+                redeem_script
+            })
+            .collect();
+
+        // P2SH-wrapped P2PK. The signature is verified by the OpCheckSigChip, so the scriptSig
+        // pushes its validity below the redeem script.
+        // scriptSig: OP_1 <redeem script>
+        // scriptPubkey: OP_HASH160 <hash160(redeem script)> OP_EQUAL
+        // redeem script: <pk> OP_CHECKSIG
+        let p2sh_script_sig = |signature: usize, redeem_script: &[u8]| {
+            let mut script_sig = vec![signature as u8];
+            script_sig.extend(push_bytes(redeem_script));
+            script_sig
+        };
+        let mut script_pubkey = vec![OP_HASH160 as u8];
+        script_pubkey.extend(push_bytes(&hash_digest(OP_HASH160, &redeem_scripts[0])));
+        script_pubkey.push(OP_EQUAL as u8);
+
+        let p2sh_circuit = |script_sig: Vec<u8>| {
+            let public_input = PublicInputs::p2sh(script_sig.clone(), script_pubkey.clone(), randomness)
+                .expect("push-only scriptSig")
+                .execution_instance();
+            (TestP2shCircuit { script_sig, script_pubkey: script_pubkey.clone(), randomness }, public_input)
+        };
+
+        let (circuit, public_input) = p2sh_circuit(p2sh_script_sig(OP_1, &redeem_scripts[0]));
+        assert_proves(K, &circuit, vec![public_input]);
+
+        // Another redeem script fails the OP_EQUAL of the scriptPubkey
+        let (circuit, public_input) = p2sh_circuit(p2sh_script_sig(OP_1, &redeem_scripts[1]));
+        assert_rejects(K, &circuit, vec![public_input]);
+
+        // An invalid signature fails the redeem script
+        let (circuit, public_input) = p2sh_circuit(p2sh_script_sig(OP_0, &redeem_scripts[0]));
+        assert_rejects(K, &circuit, vec![public_input]);
+
+        // A hashlock redeem script, whose digest is checked in the redeem script region
+        // scriptSig: <preimage> <redeem script>
+        // redeem script: OP_SHA256 <sha256(preimage)> OP_EQUAL
+        let preimage = b"abc";
+        let mut redeem_script = vec![OP_SHA256 as u8];
+        redeem_script.extend(push_bytes(&hash_digest(OP_SHA256, preimage)));
+        redeem_script.push(OP_EQUAL as u8);
+        let mut script_sig = push_bytes(preimage);
+        script_sig.extend(push_bytes(&redeem_script));
+        let mut hashlock_script_pubkey = vec![OP_HASH160 as u8];
+        hashlock_script_pubkey.extend(push_bytes(&hash_digest(OP_HASH160, &redeem_script)));
+        hashlock_script_pubkey.push(OP_EQUAL as u8);
+        let public_input = PublicInputs::p2sh(script_sig.clone(), hashlock_script_pubkey.clone(), randomness)
+            .expect("push-only scriptSig")
+            .execution_instance();
+        let circuit = TestP2shCircuit { script_sig, script_pubkey: hashlock_script_pubkey, randomness };
+        assert_proves(K, &circuit, vec![public_input]);
+
+        // A scriptSig which is not push-only is not a P2SH spend
+        let mut script_sig = p2sh_script_sig(OP_1, &redeem_scripts[0]);
+        script_sig.insert(0, OP_NOP as u8);
+        let circuit = TestP2shCircuit { script_sig, script_pubkey: script_pubkey.clone(), randomness };
+        assert!(matches!(MockProver::run(K, &circuit, vec![vec![]]), Err(Error::Synthesis)));
+
+        // The scriptPubkey must be a P2SH script
+        let circuit = TestP2shCircuit {
+            script_sig: p2sh_script_sig(OP_1, &redeem_scripts[0]),
+            script_pubkey: redeem_scripts[0].clone(),
+            randomness,
+        };
+        assert!(matches!(MockProver::run(K, &circuit, vec![vec![]]), Err(Error::Synthesis)));
+    }
+
+    // Private script unrolled by the ExecutionChip, whose HASH160 is exposed instead of the
+    // script. The public inputs are the RLC of the digest, the randomness, the nLockTime and the
+    // nSequence.
+    struct TestPrivateScriptCircuit<F: Field> {
+        pub script: Vec<u8>,
+        // Script hashed by the OpHash chip, which is the unrolled script in an honest assignment
+        pub hashed_script: Vec<u8>,
+        pub randomness: F,
+    }
+
+    impl<F: Field> Circuit<F> for TestPrivateScriptCircuit<F> {
+        type Config = TestOpHashCircuitConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                script: vec![],
+                hashed_script: vec![],
+                randomness: F::one(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            TestOpHashCircuitConfig {
+                execution_config: ExecutionChip::<F>::configure(meta),
+                op_hash_config: OpHashChip::<F>::configure(meta),
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>
+        ) -> Result<(), Error> {
+            // A valid signature on the initial stack
+            let mut initial_stack = [F::zero(); MAX_STACK_DEPTH];
+            initial_stack[0] = F::one();
+
+            let exec_chip = ExecutionChip::construct();
+            let execution_cells = exec_chip.assign_script_pubkey_unroll(
+                config.execution_config.clone(),
+                &mut layouter,
+                self.script.clone(),
+                self.randomness,
+                initial_stack,
+            )?;
+            let script_hash = OpHashChip::construct().assign_with_script_hash160(
+                &config.op_hash_config,
+                &mut layouter,
+                &execution_cells,
+                self.randomness,
+                &hash_inputs(&self.script, self.randomness, initial_stack),
+                &self.hashed_script,
+            )?;
+
+            let execution_config = config.execution_config.clone();
+            exec_chip.expose_public(execution_config.clone(), layouter.namespace(|| "script_hash"), script_hash, 0)?;
+            exec_chip.expose_public(execution_config.clone(), layouter.namespace(|| "randomness"), execution_cells.randomness.clone(), 1)?;
+            exec_chip.expose_public(execution_config.clone(), layouter.namespace(|| "nlocktime"), execution_cells.nlocktime.clone(), 2)?;
+            exec_chip.expose_public(execution_config, layouter.namespace(|| "nsequence"), execution_cells.nsequence, 3)
+        }
+    }
+
+    #[test]
+    fn test_private_script_hash160() {
+        use secp256k1::{PublicKey, Secp256k1, SecretKey};
+
+        let mut rng = rand::thread_rng();
+        let randomness = BnScalar::from(rng.gen::<u64>());
+
+        // Hidden P2PK scripts: <pk> OP_CHECKSIG
+        let secp = Secp256k1::new();
+        let scripts: Vec<Vec<u8>> = [0xcd, 0xce]
+            .iter()
+            .map(|b| {
+                let secret_key = SecretKey::from_slice(&[*b; 32]).expect("32 bytes, within curve order");
+                let mut script = push_bytes(&PublicKey::from_secret_key(&secp, &secret_key).serialize());
+                script.push(OP_CHECKSIG as u8);
+                script
+            })
+            .collect();
+        let public_input = vec![
+            bytes_item(&hash_digest(OP_HASH160, &scripts[0]), randomness),
+            randomness,
+            BnScalar::zero(),
+            BnScalar::zero(),
+        ];
+
+        let circuit = TestPrivateScriptCircuit { script: scripts[0].clone(), hashed_script: scripts[0].clone(), randomness };
+        assert_proves(K, &circuit, vec![public_input.clone()]);
+
+        // Another script does not have the public hash
+        let circuit = TestPrivateScriptCircuit { script: scripts[1].clone(), hashed_script: scripts[1].clone(), randomness };
+        assert_rejects(K, &circuit, vec![public_input.clone()]);
+
+        // The hashed script must be the unrolled one
+        let circuit = TestPrivateScriptCircuit { script: scripts[1].clone(), hashed_script: scripts[0].clone(), randomness };
+        assert_rejects(K, &circuit, vec![public_input.clone()]);
+
+        // A script with the public hash must still succeed
+        let mut failing_script = scripts[0].clone();
+        failing_script.push(OP_NOT as u8);
+        let public_input = vec![
+            bytes_item(&hash_digest(OP_HASH160, &failing_script), randomness),
+            randomness,
+            BnScalar::zero(),
+            BnScalar::zero(),
+        ];
+        let circuit = TestPrivateScriptCircuit { script: failing_script.clone(), hashed_script: failing_script, randomness };
+        assert_rejects(K, &circuit, vec![public_input]);
+    }
+
+    #[test]
+    fn test_op_hash256_digests() {
+        let mut rng = rand::thread_rng();
+        let randomness = BnScalar::from(rng.gen::<u64>());
+        assert_hash_digests(OP_HASH256, randomness);
+    }
+
+    #[test]
+    fn test_op_hash256_forged_digest() {
+        let mut rng = rand::thread_rng();
+        let randomness = BnScalar::from(rng.gen::<u64>());
+        assert_forged_digest_rejected(OP_HASH256, randomness);
+
+        // The single SHA-256 digest of the item fails the circuit
+        let hashed_items = vec![(OP_HASH256, b"abc".to_vec())];
+        let digests = vec![hash_digest(OP_SHA256, b"abc")];
+        let circuit = TestForgedDigestCircuit { randomness, hashed_items, digests };
+        assert_rejects(K, &circuit, vec![]);
+    }
+}
@@ -0,0 +1 @@
+pub mod hash;
\ No newline at end of file
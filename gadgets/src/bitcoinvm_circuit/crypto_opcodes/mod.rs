@@ -1,2 +1,3 @@
 pub mod util;
-pub mod checksig;
\ No newline at end of file
+pub mod checksig;
+pub mod hash;
\ No newline at end of file
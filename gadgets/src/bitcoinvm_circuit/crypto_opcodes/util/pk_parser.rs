@@ -2,6 +2,9 @@ use std::vec;
 
 use halo2_proofs::halo2curves::{secp256k1::{self, Secp256k1Affine}, CurveAffine};
 use crate::bitcoinvm_circuit::{constants::*, crypto_opcodes::checksig::checksig_util::{pk_bytes_swap_endianness, ct_option_ok_or}};
+use crate::bitcoinvm_circuit::error::BitcoinVmError;
+use crate::bitcoinvm_circuit::util::script_num::{decode_script_num, encode_script_num, MAX_SCRIPT_NUM_SIZE};
+use crate::bitcoinvm_circuit::util::script_parser::{disabled_indicator, hash_digest, nop_indicator, stack_item_moves};
 use libsecp256k1::PublicKey;
 
 #[derive(Clone, Debug)]
@@ -17,20 +20,204 @@ pub enum StackElement {
     Data(Vec<u8>),
 }
 
+// Numeric value of a stack element. Elements which are not valid numbers are treated as zero,
+// as the circuit rejects scripts which use them as numeric operands.
+fn script_num_operand(element: &StackElement) -> i64 {
+    match element {
+        StackElement::Data(bytes) => decode_script_num(bytes, MAX_SCRIPT_NUM_SIZE).unwrap_or(0),
+        _ => 0,
+    }
+}
+
+// Stack elements are false iff all their bytes are zero, except for a sign bit in the last byte
+fn is_element_true(element: &StackElement) -> bool {
+    match element {
+        StackElement::InvalidSignature => false,
+        StackElement::ValidSignature => true,
+        StackElement::Data(bytes) => bytes
+            .iter()
+            .enumerate()
+            .any(|(i, byte)| *byte != 0 && !(i == bytes.len() - 1 && *byte == 0x80)),
+    }
+}
+
+// Parses a public key serialization found in the scriptPubkey
+fn parse_public_key(pk_bytes: Vec<u8>) -> Result<PublicKeyInScript, BitcoinVmError> {
+    let prefix = *pk_bytes.first().ok_or(BitcoinVmError::InvalidPublicKey)?;
+    let parsed_pk = if prefix as u64 == PREFIX_PK_UNCOMPRESSED {
+        // The below step implicitly checks that the pk is on the curve
+        PublicKey::parse(pk_bytes.as_slice().try_into().map_err(|_| BitcoinVmError::InvalidPublicKey)?)?
+    }
+    else if prefix as u64 == PREFIX_PK_COMPRESSED_EVEN_Y || prefix as u64 == PREFIX_PK_COMPRESSED_ODD_Y {
+        // The below step implicitly checks that the pk is on the curve
+        PublicKey::parse_compressed(pk_bytes.as_slice().try_into().map_err(|_| BitcoinVmError::InvalidPublicKey)?)?
+    }
+    else {
+        return Err(BitcoinVmError::UnexpectedPrefixByte(prefix));
+    };
+    let pk_be = parsed_pk.serialize();
+    let pk_le = pk_bytes_swap_endianness(&pk_be[1..]);
+    let x = ct_option_ok_or(
+        secp256k1::Fp::from_bytes(pk_le[..32].try_into().unwrap()),
+        BitcoinVmError::InvalidPublicKey,
+    )?;
+    let y = ct_option_ok_or(
+        secp256k1::Fp::from_bytes(pk_le[32..].try_into().unwrap()),
+        BitcoinVmError::InvalidPublicKey,
+    )?;
+    let pk = ct_option_ok_or(
+        Secp256k1Affine::from_xy(x, y),
+        BitcoinVmError::InvalidPublicKey,
+    )?;
+    Ok(PublicKeyInScript {
+        bytes: pk_bytes,
+        pk
+    })
+}
+
+/// Data pushed by the opcode at script[index] along with the number of script bytes the opcode
+/// occupies, or None if it is not a push of the next bytes or an OP_PUSHDATA. OP_PUSHDATA
+/// opcodes with zero length are rejected like in the ExecutionChip.
+pub(crate) fn pushed_data(script: &[u8], index: usize) -> Result<Option<(&[u8], usize)>, BitcoinVmError> {
+    let opcode = script[index] as usize;
+    let length_size = match opcode {
+        OP_PUSH_NEXT1..=OP_PUSH_NEXT75 => 0,
+        OP_PUSHDATA1 | OP_PUSHDATA2 | OP_PUSHDATA4 => 1 << (opcode - OP_PUSHDATA1),
+        _ => return Ok(None),
+    };
+    let data_length = if length_size == 0 {
+        opcode
+    }
+    else {
+        // The length of an OP_PUSHDATA is little endian
+        script
+            .get(index+1..index+1+length_size)
+            .ok_or(BitcoinVmError::TruncatedPush { opcode: opcode as u8 })?
+            .iter()
+            .rev()
+            .fold(0usize, |acc, byte| (acc << 8) + *byte as usize)
+    };
+    if data_length == 0 {
+        return Err(BitcoinVmError::EmptyPushData { opcode: opcode as u8 });
+    }
+    let data_start = index + 1 + length_size;
+    let data = script
+        .get(data_start..data_start+data_length)
+        .ok_or(BitcoinVmError::TruncatedPush { opcode: opcode as u8 })?;
+    Ok(Some((data, 1 + length_size + data_length)))
+}
+
+// Number of script bytes occupied by the opcode at script[index], including any pushed data
+fn opcode_length(script: &[u8], index: usize) -> Result<usize, BitcoinVmError> {
+    Ok(pushed_data(script, index)?.map_or(1, |(_, length)| length))
+}
+
+// Number of stack items needed by an opcode. OP_CHECKMULTISIG needs more items depending on
+// the counts on the stack, and OP_PICK and OP_ROLL depending on their depth operand.
+pub(crate) fn num_stack_operands(opcode: usize) -> usize {
+    if opcode == OP_2ROT {
+        6
+    }
+    else if opcode == OP_2OVER || opcode == OP_2SWAP {
+        4
+    }
+    else if opcode == OP_ROT
+    || opcode == OP_3DUP
+    || opcode == OP_WITHIN
+    || opcode == OP_CHECKSIGADD {
+        3
+    }
+    else if opcode == OP_SWAP
+    || opcode == OP_OVER
+    || opcode == OP_NIP
+    || opcode == OP_TUCK
+    || opcode == OP_2DUP
+    || opcode == OP_PICK
+    || opcode == OP_ROLL
+    || opcode == OP_EQUAL
+    || opcode == OP_EQUALVERIFY
+    || opcode == OP_2DROP
+    || opcode == OP_ADD
+    || opcode == OP_SUB
+    || opcode == OP_BOOLAND
+    || opcode == OP_BOOLOR
+    || opcode == OP_NUMEQUAL
+    || (OP_LESSTHAN..=OP_MAX).contains(&opcode)
+    || opcode == OP_CHECKSIG
+    || opcode == OP_CHECKSIGVERIFY {
+        2
+    }
+    else if opcode == OP_IF
+    || opcode == OP_NOTIF
+    || opcode == OP_TOALTSTACK
+    || opcode == OP_VERIFY
+    || opcode == OP_DROP
+    || opcode == OP_NOT
+    || opcode == OP_0NOTEQUAL
+    || opcode == OP_1ADD
+    || opcode == OP_1SUB
+    || opcode == OP_NEGATE
+    || opcode == OP_ABS
+    || opcode == OP_SIZE
+    || opcode == OP_RIPEMD160
+    || opcode == OP_SHA1
+    || opcode == OP_SHA256
+    || opcode == OP_HASH160
+    || opcode == OP_HASH256
+    || opcode == OP_CHECKLOCKTIMEVERIFY
+    || opcode == OP_CHECKSEQUENCEVERIFY
+    || opcode == OP_CHECKMULTISIG {
+        1
+    }
+    else {
+        0
+    }
+}
+
 pub(crate) fn collect_public_keys(
     script: Vec<u8>,
     initial_stack: Vec<StackElement>,
-) -> Result<Vec<PublicKeyInScript>, libsecp256k1::Error>  {
+) -> Result<Vec<PublicKeyInScript>, BitcoinVmError>  {
     use StackElement::Data as Data;
     let mut collected_keys: Vec<PublicKeyInScript> = vec![];
     let mut stack: Vec<StackElement> = initial_stack;
+    // Keys moved to the alt stack are collected after they are moved back to the main stack
+    let mut alt_stack: Vec<StackElement> = vec![];
+    // One entry per unterminated OP_IF/OP_NOTIF which is true iff its branch is taken
+    let mut branch_stack: Vec<bool> = vec![];
     let mut script_byte_index: usize = 0;
     let mut opcode: usize;
     
     while script_byte_index < script.len() {
         opcode = script[script_byte_index] as usize;
+        let is_executing = branch_stack.iter().all(|taken| *taken);
+        if disabled_indicator(opcode as u8) == 1 {
+            return Err(BitcoinVmError::DisabledOpcode { opcode: opcode as u8 });
+        }
+        if is_executing && stack.len() < num_stack_operands(opcode) {
+            return Err(BitcoinVmError::StackUnderflow { opcode: opcode as u8 });
+        }
 
-        if opcode == OP_0 {
+        if opcode == OP_IF || opcode == OP_NOTIF {
+            // The condition is only popped if the opcode is executed
+            let taken = is_executing && (is_element_true(&stack.remove(0)) == (opcode == OP_IF));
+            branch_stack.push(taken);
+            script_byte_index += 1;
+        }
+        else if opcode == OP_ELSE {
+            let taken = branch_stack.last_mut().ok_or(BitcoinVmError::UnbalancedConditional)?;
+            *taken = !*taken;
+            script_byte_index += 1;
+        }
+        else if opcode == OP_ENDIF {
+            branch_stack.pop().ok_or(BitcoinVmError::UnbalancedConditional)?;
+            script_byte_index += 1;
+        }
+        else if !is_executing {
+            // Public keys in branches which are not taken are not checked
+            script_byte_index += opcode_length(&script, script_byte_index)?;
+        }
+        else if opcode == OP_0 {
             stack.insert(0, Data(vec![]));
             script_byte_index += 1;
         }
@@ -38,34 +225,224 @@ pub(crate) fn collect_public_keys(
             stack.insert(0, Data(vec![(opcode - OP_RESERVED) as u8]));
             script_byte_index += 1;
         }
-        else if opcode >= OP_PUSH_NEXT1 && opcode <= OP_PUSH_NEXT75 {
-            let data = script[script_byte_index+1..(script_byte_index+opcode+1)].to_vec();
-            stack.insert(0, Data(data));
-            script_byte_index += opcode + 1;
+        else if let Some((data, length)) = pushed_data(&script, script_byte_index)? {
+            stack.insert(0, Data(data.to_vec()));
+            script_byte_index += length;
         }
-        else if opcode == OP_PUSHDATA1 {
-            let data_length: usize = script[script_byte_index+1] as usize;
-            let data = script[script_byte_index+2..(script_byte_index+data_length+2)].to_vec();
-            stack.insert(0, Data(data));
-            script_byte_index += data_length + 2;
+        else if nop_indicator(opcode as u8) == 1 || opcode == OP_CODESEPARATOR {
+            script_byte_index += 1;
+        }
+        else if opcode == OP_CHECKLOCKTIMEVERIFY || opcode == OP_CHECKSEQUENCEVERIFY {
+            // The locktimes are checked by the circuit and not popped
+            script_byte_index += 1;
+        }
+        else if opcode == OP_TOALTSTACK {
+            alt_stack.insert(0, stack.remove(0));
+            script_byte_index += 1;
         }
-        else if opcode == OP_PUSHDATA2 {
-            let data_length: usize = (script[script_byte_index+1] as usize) + 256usize * (script[script_byte_index+2] as usize);
-            let data = script[script_byte_index+3..(script_byte_index+data_length+3)].to_vec();
-            stack.insert(0, Data(data));
-            script_byte_index += data_length + 3;
+        else if opcode == OP_FROMALTSTACK {
+            if alt_stack.is_empty() {
+                return Err(BitcoinVmError::StackUnderflow { opcode: opcode as u8 });
+            }
+            stack.insert(0, alt_stack.remove(0));
+            script_byte_index += 1;
+        }
+        else if opcode == OP_EQUAL {
+            let first = stack.remove(0);
+            let second = stack.remove(0);
+            match (first, second) {
+                (Data(a), Data(b)) if a == b => stack.insert(0, Data(vec![1])),
+                _ => stack.insert(0, Data(vec![])),
+            }
+            script_byte_index += 1;
+        }
+        else if opcode == OP_EQUALVERIFY {
+            stack.remove(0);
+            stack.remove(0);
+            script_byte_index += 1;
+        }
+        else if opcode == OP_VERIFY || opcode == OP_DROP {
+            stack.remove(0);
+            script_byte_index += 1;
+        }
+        else if opcode == OP_SWAP {
+            stack.swap(0, 1);
+            script_byte_index += 1;
+        }
+        else if opcode == OP_OVER {
+            stack.insert(0, stack[1].clone());
+            script_byte_index += 1;
+        }
+        else if opcode == OP_ROT {
+            stack[0..3].rotate_right(1);
+            script_byte_index += 1;
+        }
+        else if opcode == OP_NIP {
+            stack.remove(1);
+            script_byte_index += 1;
+        }
+        else if opcode == OP_TUCK {
+            stack.insert(2, stack[0].clone());
+            script_byte_index += 1;
+        }
+        else if opcode == OP_PICK || opcode == OP_ROLL {
+            // A negative depth fails the script like a depth beyond the stack
+            let depth = script_num_operand(&stack.remove(0));
+            let index = usize::try_from(depth)
+                .ok()
+                .filter(|index| *index < stack.len())
+                .ok_or(BitcoinVmError::StackUnderflow { opcode: opcode as u8 })?;
+            let item = if opcode == OP_PICK { stack[index].clone() } else { stack.remove(index) };
+            stack.insert(0, item);
+            script_byte_index += 1;
+        }
+        else if (OP_2DUP..=OP_2SWAP).contains(&opcode) {
+            let (num_popped, pushed) = stack_item_moves(opcode);
+            let popped = stack[..num_popped].to_vec();
+            let moved: Vec<_> = pushed.into_iter().flatten().map(|index| popped[index].clone()).collect();
+            stack.splice(..num_popped, moved);
+            script_byte_index += 1;
+        }
+        else if opcode == OP_DEPTH {
+            let depth = stack.len();
+            stack.insert(0, Data(encode_script_num(depth as i64)));
+            script_byte_index += 1;
+        }
+        else if opcode == OP_SIZE {
+            // Signature flags are the one byte array or the empty array
+            let len = match &stack[0] {
+                Data(bytes) => bytes.len(),
+                StackElement::ValidSignature => 1,
+                StackElement::InvalidSignature => 0,
+            };
+            stack.insert(0, Data(encode_script_num(len as i64)));
+            script_byte_index += 1;
+        }
+        else if opcode == OP_RIPEMD160
+        || opcode == OP_SHA1
+        || opcode == OP_SHA256
+        || opcode == OP_HASH160
+        || opcode == OP_HASH256 {
+            // Signature flags are hashed as the one byte array or the empty array
+            let bytes = match stack.remove(0) {
+                Data(bytes) => bytes,
+                StackElement::ValidSignature => vec![1],
+                StackElement::InvalidSignature => vec![],
+            };
+            stack.insert(0, Data(hash_digest(opcode, &bytes)));
+            script_byte_index += 1;
+        }
+        else if opcode == OP_ADD {
+            let b = script_num_operand(&stack.remove(0));
+            let a = script_num_operand(&stack.remove(0));
+            stack.insert(0, Data(encode_script_num(a + b)));
+            script_byte_index += 1;
+        }
+        else if opcode == OP_SUB {
+            let b = script_num_operand(&stack.remove(0));
+            let a = script_num_operand(&stack.remove(0));
+            stack.insert(0, Data(encode_script_num(a - b)));
+            script_byte_index += 1;
+        }
+        else if opcode == OP_NOT || opcode == OP_0NOTEQUAL {
+            let is_true = is_element_true(&stack.remove(0));
+            let result = is_true == (opcode == OP_0NOTEQUAL);
+            stack.insert(0, Data(encode_script_num(result as i64)));
+            script_byte_index += 1;
+        }
+        else if opcode == OP_BOOLAND || opcode == OP_BOOLOR {
+            let b = is_element_true(&stack.remove(0));
+            let a = is_element_true(&stack.remove(0));
+            let result = if opcode == OP_BOOLAND { a && b } else { a || b };
+            stack.insert(0, Data(encode_script_num(result as i64)));
+            script_byte_index += 1;
         }
-        else if opcode == OP_PUSHDATA4 {
-            let data_length: usize = (script[script_byte_index+1] as usize) 
-                + (1 << 8) * (script[script_byte_index+2] as usize)
-                + (1 << 16) * (script[script_byte_index+3] as usize)
-                + (1 << 24) * (script[script_byte_index+4] as usize);
+        else if opcode == OP_NUMEQUAL {
+            let b = script_num_operand(&stack.remove(0));
+            let a = script_num_operand(&stack.remove(0));
+            stack.insert(0, Data(encode_script_num((a == b) as i64)));
+            script_byte_index += 1;
+        }
+        else if (OP_LESSTHAN..=OP_MAX).contains(&opcode) {
+            let b = script_num_operand(&stack.remove(0));
+            let a = script_num_operand(&stack.remove(0));
+            let result = match opcode {
+                OP_LESSTHAN => (a < b) as i64,
+                OP_GREATERTHAN => (a > b) as i64,
+                OP_LESSTHANOREQUAL => (a <= b) as i64,
+                OP_GREATERTHANOREQUAL => (a >= b) as i64,
+                OP_MIN => a.min(b),
+                _ => a.max(b),
+            };
+            stack.insert(0, Data(encode_script_num(result)));
+            script_byte_index += 1;
+        }
+        else if opcode == OP_WITHIN {
+            let max = script_num_operand(&stack.remove(0));
+            let min = script_num_operand(&stack.remove(0));
+            let x = script_num_operand(&stack.remove(0));
+            stack.insert(0, Data(encode_script_num((min..max).contains(&x) as i64)));
+            script_byte_index += 1;
+        }
+        else if opcode == OP_1ADD || opcode == OP_1SUB || opcode == OP_NEGATE || opcode == OP_ABS {
+            let a = script_num_operand(&stack.remove(0));
+            let result = match opcode {
+                OP_1ADD => a + 1,
+                OP_1SUB => a - 1,
+                OP_NEGATE => -a,
+                _ => a.abs(),
+            };
+            stack.insert(0, Data(encode_script_num(result)));
+            script_byte_index += 1;
+        }
+        else if opcode == OP_2DROP {
+            stack.remove(0);
+            stack.remove(0);
+            script_byte_index += 1;
+        }
+        else if opcode == OP_CHECKMULTISIG {
+            let num_keys = script_num_operand(&stack.remove(0)) as usize;
+            if stack.len() < num_keys + 1 {
+                return Err(BitcoinVmError::StackUnderflow { opcode: opcode as u8 });
+            }
+            let keys: Vec<StackElement> = stack.drain(0..num_keys).collect();
+            let num_sigs = script_num_operand(&stack.remove(0)) as usize;
+            if stack.len() < num_sigs + 1 {
+                return Err(BitcoinVmError::StackUnderflow { opcode: opcode as u8 });
+            }
+            let sigs: Vec<StackElement> = stack.drain(0..num_sigs).collect();
+            // Bitcoin pops an extra item due to an off-by-one error
+            stack.remove(0);
 
-            let data = script[script_byte_index+5..(script_byte_index+data_length+5)].to_vec();
-            stack.insert(0, Data(data));
-            script_byte_index += data_length + 5;
+            let are_sigs_valid = sigs.iter().all(|sig| matches!(sig, StackElement::ValidSignature));
+            if are_sigs_valid {
+                // The circuit accepts any m of the n public keys. The keys closest to the stack top
+                // are signed, and they are collected in the order they were pushed
+                for key in keys[..num_sigs].iter().rev() {
+                    match key {
+                        Data(pk_bytes) => collected_keys.push(parse_public_key(pk_bytes.clone())?),
+                        _ => return Err(BitcoinVmError::UnexpectedStackElement { opcode: opcode as u8 }),
+                    }
+                }
+            }
+            stack.insert(0, Data(encode_script_num(are_sigs_valid as i64)));
+            script_byte_index += 1;
         }
-        else if opcode == OP_CHECKSIG {
+        else if opcode == OP_CHECKSIGADD {
+            let pk = stack.remove(0);
+            let n = script_num_operand(&stack.remove(0));
+            let is_sig_valid = matches!(stack.remove(0), StackElement::ValidSignature);
+            if is_sig_valid {
+                match pk {
+                    Data(pk_bytes) => collected_keys.push(parse_public_key(pk_bytes)?),
+                    _ => return Err(BitcoinVmError::UnexpectedStackElement { opcode: opcode as u8 }),
+                }
+            }
+            stack.insert(0, Data(encode_script_num(n + is_sig_valid as i64)));
+            script_byte_index += 1;
+        }
+        // The result of OP_CHECKSIG is not pushed, so OP_CHECKSIGVERIFY is handled in the same way
+        else if opcode == OP_CHECKSIG || opcode == OP_CHECKSIGVERIFY {
             match stack[1] {
                 StackElement::InvalidSignature => {
                     stack.remove(0); // Remove the public key
@@ -76,46 +453,16 @@ pub(crate) fn collect_public_keys(
                     let stack_top = stack.remove(0); // Remove the public key
                     match stack_top {
                         Data(pk_bytes) => {
-                            let prefix = pk_bytes[0] as u64;
-                            let parsed_pk = if prefix == PREFIX_PK_UNCOMPRESSED {
-                                // The below step implicitly checks that the pk is on the curve
-                                PublicKey::parse(pk_bytes.as_slice().try_into().expect("Incorrect length"))?
-                            }
-                            else if prefix == PREFIX_PK_COMPRESSED_EVEN_Y || prefix ==  PREFIX_PK_COMPRESSED_ODD_Y {
-                                // The below step implicitly checks that the pk is on the curve
-                                PublicKey::parse_compressed(pk_bytes.as_slice().try_into().expect("Incorrect length"))?
-                            }
-                            else {
-                                panic!("Unexpected prefix byte")
-                            };
-                            let pk_be = parsed_pk.serialize();
-                            let pk_le = pk_bytes_swap_endianness(&pk_be[1..]);
-                            let x = ct_option_ok_or(
-                                secp256k1::Fp::from_bytes(pk_le[..32].try_into().unwrap()),
-                                libsecp256k1::Error::InvalidPublicKey,
-                            )?;
-                            let y = ct_option_ok_or(
-                                secp256k1::Fp::from_bytes(pk_le[32..].try_into().unwrap()),
-                                libsecp256k1::Error::InvalidPublicKey,
-                            )?;
-                            let pk = ct_option_ok_or(
-                                Secp256k1Affine::from_xy(x, y),
-                                libsecp256k1::Error::InvalidPublicKey,
-                            )?;
-                            let pk_in_script = PublicKeyInScript {
-                                bytes: pk_bytes,
-                                pk
-                            };
-                            collected_keys.push(pk_in_script); // Add the public key to the list of collected keys
-                            
+                            // Add the public key to the list of collected keys
+                            collected_keys.push(parse_public_key(pk_bytes)?);
                         },
-                        _ => panic!("Expected public key bytes")
+                        _ => return Err(BitcoinVmError::UnexpectedStackElement { opcode: opcode as u8 }),
                     }
                     stack.remove(0); // Remove stack item corresponding to the valid signature
                     script_byte_index += 1;
                 },
                 Data(_) => {
-                    panic!("Expected signature type");
+                    return Err(BitcoinVmError::UnexpectedStackElement { opcode: opcode as u8 });
                 }
             }
         }
@@ -129,6 +476,7 @@ mod tests {
     use secp256k1::{self, Secp256k1, SecretKey, PublicKey};
     use secp256k1::constants::{UNCOMPRESSED_PUBLIC_KEY_SIZE, PUBLIC_KEY_SIZE};
 
+    use crate::bitcoinvm_circuit::error::BitcoinVmError;
     use super::{StackElement, collect_public_keys};
 
     #[test]
@@ -251,4 +599,193 @@ mod tests {
         }
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_pk_parser_alt_stack() {
+        let secp = Secp256k1::new();
+        let secret_key1 = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key_bytes1: [u8; PUBLIC_KEY_SIZE] = PublicKey::from_secret_key(&secp, &secret_key1).serialize();
+        let secret_key2 = SecretKey::from_slice(&[0xef; 32]).expect("32 bytes, within curve order");
+        let public_key_bytes2: [u8; PUBLIC_KEY_SIZE] = PublicKey::from_secret_key(&secp, &secret_key2).serialize();
+
+        // <pk1> OP_TOALTSTACK <pk2> OP_CHECKSIG OP_FROMALTSTACK OP_CHECKSIG
+        let mut script_pubkey: Vec<u8> = vec![];
+        script_pubkey.push(PUBLIC_KEY_SIZE as u8); // "Push 33 bytes" opcode
+        script_pubkey.extend(public_key_bytes1.iter());
+        script_pubkey.push(OP_TOALTSTACK as u8);
+        script_pubkey.push(PUBLIC_KEY_SIZE as u8); // "Push 33 bytes" opcode
+        script_pubkey.extend(public_key_bytes2.iter());
+        script_pubkey.push(OP_CHECKSIG as u8);
+        script_pubkey.push(OP_FROMALTSTACK as u8);
+        script_pubkey.push(OP_CHECKSIG as u8);
+
+        let initial_stack = vec![
+            StackElement::ValidSignature,
+            StackElement::ValidSignature,
+        ];
+
+        // The key moved through the alt stack is checked last
+        let collect_pks = collect_public_keys(script_pubkey, initial_stack).unwrap();
+        assert_eq!(collect_pks.len(), 2);
+        assert_eq!(collect_pks[0].bytes, public_key_bytes2.to_vec());
+        assert_eq!(collect_pks[1].bytes, public_key_bytes1.to_vec());
+    }
+
+    #[test]
+    fn test_pk_parser_checkmultisig() {
+        let secp = Secp256k1::new();
+        let public_keys: Vec<[u8; PUBLIC_KEY_SIZE]> = [0xcd, 0xce, 0xcf]
+            .iter()
+            .map(|b| {
+                let secret_key = SecretKey::from_slice(&[*b; 32]).expect("32 bytes, within curve order");
+                PublicKey::from_secret_key(&secp, &secret_key).serialize()
+            })
+            .collect();
+
+        // OP_2 <pk1> <pk2> <pk3> OP_3 OP_CHECKMULTISIG
+        let mut script_pubkey: Vec<u8> = vec![OP_1 as u8 + 1];
+        for public_key in public_keys.iter() {
+            script_pubkey.push(PUBLIC_KEY_SIZE as u8); // "Push 33 bytes" opcode
+            script_pubkey.extend(public_key.iter());
+        }
+        script_pubkey.push(OP_1 as u8 + 2);
+        script_pubkey.push(OP_CHECKMULTISIG as u8);
+
+        // The extra item popped by OP_CHECKMULTISIG is below the signatures
+        let initial_stack = vec![
+            StackElement::ValidSignature,
+            StackElement::ValidSignature,
+            StackElement::Data(vec![]),
+        ];
+        // The signatures are matched with the last two public keys
+        let collect_pks = collect_public_keys(script_pubkey.clone(), initial_stack).unwrap();
+        assert_eq!(collect_pks.len(), 2);
+        assert_eq!(collect_pks[0].bytes, public_keys[1].to_vec());
+        assert_eq!(collect_pks[1].bytes, public_keys[2].to_vec());
+
+        let initial_stack = vec![
+            StackElement::ValidSignature,
+            StackElement::InvalidSignature,
+            StackElement::Data(vec![]),
+        ];
+        let collect_pks = collect_public_keys(script_pubkey, initial_stack).unwrap();
+        assert!(collect_pks.is_empty());
+    }
+
+    #[test]
+    fn test_pk_parser_checksigadd() {
+        let secp = Secp256k1::new();
+        let public_keys: Vec<[u8; PUBLIC_KEY_SIZE]> = [0xcd, 0xce, 0xcf]
+            .iter()
+            .map(|b| {
+                let secret_key = SecretKey::from_slice(&[*b; 32]).expect("32 bytes, within curve order");
+                PublicKey::from_secret_key(&secp, &secret_key).serialize()
+            })
+            .collect();
+
+        // OP_0 <pk1> OP_CHECKSIGADD <pk2> OP_CHECKSIGADD <pk3> OP_CHECKSIGADD OP_2 OP_NUMEQUAL
+        let mut script_pubkey: Vec<u8> = vec![OP_0 as u8];
+        for public_key in public_keys.iter() {
+            script_pubkey.push(PUBLIC_KEY_SIZE as u8); // "Push 33 bytes" opcode
+            script_pubkey.extend(public_key.iter());
+            script_pubkey.push(OP_CHECKSIGADD as u8);
+        }
+        script_pubkey.push(OP_1 as u8 + 1);
+        script_pubkey.push(OP_NUMEQUAL as u8);
+
+        // The signature of the first public key is on top of the stack
+        let initial_stack = vec![
+            StackElement::ValidSignature,
+            StackElement::InvalidSignature,
+            StackElement::ValidSignature,
+        ];
+        let collect_pks = collect_public_keys(script_pubkey, initial_stack).unwrap();
+        assert_eq!(collect_pks.len(), 2);
+        assert_eq!(collect_pks[0].bytes, public_keys[0].to_vec());
+        assert_eq!(collect_pks[1].bytes, public_keys[2].to_vec());
+    }
+
+    #[test]
+    fn test_pk_parser_errors() {
+        // A signature without a public key
+        let result = collect_public_keys(vec![OP_CHECKSIG as u8], vec![StackElement::ValidSignature]);
+        assert_eq!(result.unwrap_err(), BitcoinVmError::StackUnderflow { opcode: OP_CHECKSIG as u8 });
+
+        // OP_PICK of the second item below the depth on a stack of one item
+        let result = collect_public_keys(vec![OP_1 as u8, OP_1 as u8, OP_PICK as u8], vec![]);
+        assert_eq!(result.unwrap_err(), BitcoinVmError::StackUnderflow { opcode: OP_PICK as u8 });
+
+        // A 33-byte push whose prefix byte is neither 0x02 nor 0x03
+        let mut script_pubkey: Vec<u8> = vec![PUBLIC_KEY_SIZE as u8, 0x05];
+        script_pubkey.extend_from_slice(&[0x01; PUBLIC_KEY_SIZE - 1]);
+        script_pubkey.push(OP_CHECKSIG as u8);
+        let result = collect_public_keys(script_pubkey, vec![StackElement::ValidSignature]);
+        assert_eq!(result.unwrap_err(), BitcoinVmError::UnexpectedPrefixByte(0x05));
+
+        // A signature where the public key is expected
+        let result = collect_public_keys(
+            vec![OP_CHECKSIG as u8],
+            vec![StackElement::ValidSignature, StackElement::ValidSignature],
+        );
+        assert_eq!(result.unwrap_err(), BitcoinVmError::UnexpectedStackElement { opcode: OP_CHECKSIG as u8 });
+
+        let result = collect_public_keys(vec![OP_ENDIF as u8], vec![]);
+        assert_eq!(result.unwrap_err(), BitcoinVmError::UnbalancedConditional);
+
+        // A disabled opcode fails the script even in a branch which is not taken
+        let result = collect_public_keys(
+            vec![OP_0 as u8, OP_IF as u8, OP_CAT as u8, OP_ENDIF as u8, OP_1 as u8],
+            vec![],
+        );
+        assert_eq!(result.unwrap_err(), BitcoinVmError::DisabledOpcode { opcode: OP_CAT as u8 });
+    }
+
+    #[test]
+    fn test_pk_parser_truncated_push() {
+        let truncated_pushes: Vec<Vec<u8>> = vec![
+            // 33 bytes announced and 32 given
+            [&[PUBLIC_KEY_SIZE as u8][..], &[0x02; PUBLIC_KEY_SIZE - 1]].concat(),
+            // Lengths of the OP_PUSHDATA opcodes missing or cut short
+            vec![OP_PUSHDATA1 as u8],
+            vec![OP_PUSHDATA2 as u8, 0x01],
+            vec![OP_PUSHDATA4 as u8, 0x01, 0x00, 0x00],
+            // Data shorter than the length of the OP_PUSHDATA opcodes
+            vec![OP_PUSHDATA1 as u8, 0x02, 0x01],
+            vec![OP_PUSHDATA2 as u8, 0x00, 0x01, 0x01],
+            vec![OP_PUSHDATA4 as u8, 0xff, 0xff, 0xff, 0xff, 0x01],
+        ];
+        for script in truncated_pushes {
+            let opcode = script[0];
+            let result = collect_public_keys(script.clone(), vec![]);
+            assert_eq!(result.unwrap_err(), BitcoinVmError::TruncatedPush { opcode });
+
+            // A truncated push fails the script in a branch which is not taken too
+            let mut untaken_branch = vec![OP_0 as u8, OP_IF as u8];
+            untaken_branch.extend(script);
+            let result = collect_public_keys(untaken_branch, vec![]);
+            assert_eq!(result.unwrap_err(), BitcoinVmError::TruncatedPush { opcode });
+        }
+
+        // A push ending with the script is complete
+        let result = collect_public_keys(vec![OP_PUSHDATA2 as u8, 0x01, 0x00, 0x01], vec![]);
+        assert!(result.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_pk_parser_empty_pushdata() {
+        // The empty array is pushed by OP_0 only, even in a branch which is not taken
+        for opcode in [OP_PUSHDATA1, OP_PUSHDATA2, OP_PUSHDATA4] {
+            let mut script = vec![opcode as u8];
+            script.extend(vec![0x00; 1 << (opcode - OP_PUSHDATA1)]);
+            script.push(OP_1 as u8);
+            let result = collect_public_keys(script.clone(), vec![]);
+            assert_eq!(result.unwrap_err(), BitcoinVmError::EmptyPushData { opcode: opcode as u8 });
+
+            let mut untaken_branch = vec![OP_0 as u8, OP_IF as u8];
+            untaken_branch.extend(script);
+            untaken_branch.push(OP_ENDIF as u8);
+            let result = collect_public_keys(untaken_branch, vec![]);
+            assert_eq!(result.unwrap_err(), BitcoinVmError::EmptyPushData { opcode: opcode as u8 });
+        }
+        assert!(collect_public_keys(vec![OP_0 as u8, OP_1 as u8], vec![]).unwrap().is_empty());
+    }
+}
@@ -5,6 +5,9 @@ use halo2_proofs::halo2curves::secp256k1::{self, Secp256k1Affine};
 
 use lazy_static::lazy_static;
 
+use crate::bitcoinvm_circuit::constants::*;
+use crate::bitcoinvm_circuit::error::BitcoinVmError;
+
 
 
 /// Do a secp256k1 signature with a given randomness value.
@@ -34,13 +37,100 @@ pub fn sign(
 
 
 /// Signature data required by the OpCheckSig and OpCheckMultiSig chips as input to verify a
-/// signature. The message hash that is signed is always secp2356k1::Fq::one()
+/// signature
 #[derive(Clone, Debug)]
 pub struct SignData {
     /// Secp256k1 signature point
     pub signature: (secp256k1::Fq, secp256k1::Fq),
     /// Secp256k1 public key
     pub pk: Secp256k1Affine,
+    /// Message hash that is signed, e.g. the sighash of the spending transaction. It is a
+    /// public input of the OpCheckSigChip, which the verifier computes from the transaction
+    pub message_hash: secp256k1::Fq,
+    /// Sighash type byte trailing the serialized signature. It is stripped before the ECDSA
+    /// verification, as the message hash is given separately
+    pub sighash_type: u8,
+}
+
+impl SignData {
+    /// Returns true if the sighash type byte is one of SIGHASH_ALL, SIGHASH_NONE or
+    /// SIGHASH_SINGLE, optionally combined with SIGHASH_ANYONECANPAY
+    pub fn has_valid_sighash_type(&self) -> bool {
+        is_valid_sighash_type(self.sighash_type)
+    }
+
+    /// Parses a DER-encoded signature followed by its sighash type byte, as pushed by a
+    /// scriptSig. The encoding must be strict as required by BIP66, and r and s must be non-zero
+    /// scalars below the group order. The public key and message hash are not part of the
+    /// encoding and are left to their default values for the caller to set.
+    pub fn from_der(bytes: &[u8]) -> Result<SignData, BitcoinVmError> {
+        if !(MIN_DER_SIGNATURE_SIZE..=MAX_DER_SIGNATURE_SIZE).contains(&bytes.len())
+            || bytes[0] != DER_SEQUENCE_TAG
+            || bytes[1] as usize != bytes.len() - 3
+        {
+            return Err(BitcoinVmError::InvalidDerSignature);
+        }
+        let (sig_r, bytes_after_r) = der_scalar(&bytes[2..])?;
+        let (sig_s, bytes_after_s) = der_scalar(bytes_after_r)?;
+        // Only the sighash type byte follows the sequence
+        let sighash_type = match bytes_after_s {
+            [sighash_type] => *sighash_type,
+            _ => return Err(BitcoinVmError::InvalidDerSignature),
+        };
+
+        Ok(SignData {
+            signature: (sig_r, sig_s),
+            sighash_type,
+            ..SignData::default()
+        })
+    }
+}
+
+// Parses a DER integer at the start of `bytes` and returns it with the bytes which follow it.
+// The integer must be positive, minimally encoded and a non-zero scalar below the group order.
+fn der_scalar(bytes: &[u8]) -> Result<(secp256k1::Fq, &[u8]), BitcoinVmError> {
+    let length = match bytes {
+        [DER_INTEGER_TAG, length, ..] => *length as usize,
+        _ => return Err(BitcoinVmError::InvalidDerSignature),
+    };
+    let value = bytes.get(2..2+length).ok_or(BitcoinVmError::InvalidDerSignature)?;
+    let value = match value {
+        // Empty and negative integers
+        [] => return Err(BitcoinVmError::InvalidDerSignature),
+        [first, ..] if first & 0x80 != 0 => return Err(BitcoinVmError::InvalidDerSignature),
+        // A zero byte only pads a value whose next byte has its high bit set
+        [0x00, second, ..] if second & 0x80 == 0 => return Err(BitcoinVmError::InvalidDerSignature),
+        [0x00, rest @ ..] => rest,
+        _ => value,
+    };
+    if value.len() > 32 {
+        return Err(BitcoinVmError::InvalidDerSignature);
+    }
+
+    let mut value_le = [0u8; 32];
+    for (i, byte) in value.iter().rev().enumerate() {
+        value_le[i] = *byte;
+    }
+    let scalar = Option::<secp256k1::Fq>::from(secp256k1::Fq::from_bytes(&value_le))
+        .filter(|scalar| *scalar != secp256k1::Fq::zero())
+        .ok_or(BitcoinVmError::InvalidDerSignature)?;
+    Ok((scalar, &bytes[2+length..]))
+}
+
+/// Checks that a sighash type byte is a valid sighash flag
+pub fn is_valid_sighash_type(sighash_type: u8) -> bool {
+    let base_type = sighash_type & !SIGHASH_ANYONECANPAY;
+    (SIGHASH_ALL..=SIGHASH_SINGLE).contains(&base_type)
+}
+
+/// Converts a 32-byte digest, like a transaction sighash, to an ECDSA message hash.
+/// The digest is read as a big-endian integer and reduced modulo the secp256k1 group order
+pub fn message_hash_from_digest(digest: &[u8; 32]) -> secp256k1::Fq {
+    let mut digest_le = [0u8; 64];
+    for (i, byte) in digest.iter().rev().enumerate() {
+        digest_le[i] = *byte;
+    }
+    secp256k1::Fq::from_bytes_wide(&digest_le)
 }
 
 lazy_static! {
@@ -56,6 +146,8 @@ lazy_static! {
         SignData {
             signature: (sig_r, sig_s),
             pk,
+            message_hash: msg_hash,
+            sighash_type: SIGHASH_ALL,
         }
     };
 }
@@ -69,4 +161,142 @@ impl Default for SignData {
         // message hash and public key).
         SIGN_DATA_DEFAULT.clone()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::arithmetic::{FieldExt, Field};
+    use halo2_proofs::halo2curves::Coordinates;
+    use halo2_proofs::halo2curves::{group::Curve, CurveAffine};
+    use halo2_proofs::halo2curves::secp256k1::{Fq, Secp256k1Affine};
+    use secp256k1::{Message, Secp256k1, SecretKey};
+
+    use crate::bitcoinvm_circuit::constants::*;
+    use crate::bitcoinvm_circuit::error::BitcoinVmError;
+    use super::{is_valid_sighash_type, message_hash_from_digest, SignData};
+
+    fn fq_from_be_bytes(bytes: &[u8]) -> Fq {
+        let mut bytes_le: [u8; 32] = bytes.try_into().unwrap();
+        bytes_le.reverse();
+        Option::<Fq>::from(Fq::from_bytes(&bytes_le)).expect("not a canonical scalar")
+    }
+
+    #[test]
+    fn test_sighash_types() {
+        for sighash_type in [SIGHASH_ALL, SIGHASH_NONE, SIGHASH_SINGLE] {
+            assert!(is_valid_sighash_type(sighash_type));
+            assert!(is_valid_sighash_type(sighash_type | SIGHASH_ANYONECANPAY));
+        }
+        for sighash_type in [0x00, 0x04, 0x40, SIGHASH_ANYONECANPAY, 0xff] {
+            assert!(!is_valid_sighash_type(sighash_type));
+        }
+    }
+
+    #[test]
+    fn test_message_hash_from_digest() {
+        // Sighash of the native P2WPKH input in the BIP143 example transaction
+        let digest: [u8; 32] = hex::decode(
+            "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"
+        ).unwrap().try_into().unwrap();
+        let secret_key = SecretKey::from_slice(&[0x42; 32]).unwrap();
+        let secp = Secp256k1::new();
+        let signature = secp
+            .sign_ecdsa(&Message::from_slice(&digest).unwrap(), &secret_key)
+            .serialize_compact();
+        let sig_r = fq_from_be_bytes(&signature[..32]);
+        let sig_s = fq_from_be_bytes(&signature[32..]);
+
+        // Check the ECDSA verification equation with the converted message hash
+        let pk = Secp256k1Affine::generator() * fq_from_be_bytes(&secret_key.secret_bytes());
+        let s_inv = sig_s.invert().unwrap();
+        let message_hash = message_hash_from_digest(&digest);
+        let point = Secp256k1Affine::generator() * (message_hash * s_inv) + pk * (sig_r * s_inv);
+        let x = *Option::<Coordinates<_>>::from(point.to_affine().coordinates()).unwrap().x();
+        let mut x_bytes = [0u8; 64];
+        x_bytes[..32].copy_from_slice(x.to_bytes().as_slice());
+        assert_eq!(Fq::from_bytes_wide(&x_bytes), sig_r);
+    }
+
+    // DER-encoded signature followed by SIGHASH_ALL, with the r and s values it encodes
+    fn der_signature() -> (Vec<u8>, Fq, Fq) {
+        let secret_key = SecretKey::from_slice(&[0x42; 32]).unwrap();
+        let secp = Secp256k1::new();
+        let signature = secp.sign_ecdsa(&Message::from_slice(&[0x01; 32]).unwrap(), &secret_key);
+        let mut der = signature.serialize_der().to_vec();
+        der.push(SIGHASH_ALL);
+        let compact = signature.serialize_compact();
+        (der, fq_from_be_bytes(&compact[..32]), fq_from_be_bytes(&compact[32..]))
+    }
+
+    // Encodes r and s as DER integers of the given big-endian bytes, followed by SIGHASH_ALL
+    fn der_encode(r: &[u8], s: &[u8]) -> Vec<u8> {
+        let mut der = vec![DER_SEQUENCE_TAG, (r.len() + s.len() + 4) as u8];
+        for value in [r, s] {
+            der.extend([DER_INTEGER_TAG, value.len() as u8]);
+            der.extend(value);
+        }
+        der.push(SIGHASH_ALL);
+        der
+    }
+
+    #[test]
+    fn test_sign_data_from_der() {
+        let (der, sig_r, sig_s) = der_signature();
+        let sign_data = SignData::from_der(&der).unwrap();
+        assert_eq!(sign_data.signature, (sig_r, sig_s));
+        assert_eq!(sign_data.sighash_type, SIGHASH_ALL);
+
+        let mut anyonecanpay_der = der[..der.len()-1].to_vec();
+        anyonecanpay_der.push(SIGHASH_SINGLE | SIGHASH_ANYONECANPAY);
+        assert_eq!(
+            SignData::from_der(&anyonecanpay_der).unwrap().sighash_type,
+            SIGHASH_SINGLE | SIGHASH_ANYONECANPAY,
+        );
+
+        // A leading zero byte keeps a value with its high bit set positive
+        let sign_data = SignData::from_der(&der_encode(&[0x00, 0x80], &[0x01])).unwrap();
+        assert_eq!(sign_data.signature, (Fq::from(0x80), Fq::one()));
+    }
+
+    #[test]
+    fn test_sign_data_from_malformed_der() {
+        let (der, _, _) = der_signature();
+        let group_order: [u8; 32] = hex::decode(
+            "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141"
+        ).unwrap().try_into().unwrap();
+
+        let mut wrong_sequence_tag = der.clone();
+        wrong_sequence_tag[0] = 0x31;
+        let mut wrong_sequence_length = der.clone();
+        wrong_sequence_length[1] += 1;
+        let mut wrong_integer_tag = der.clone();
+        wrong_integer_tag[2] = 0x03;
+        let mut extra_byte = der.clone();
+        extra_byte.insert(der.len() - 1, 0x00);
+        extra_byte[1] += 1;
+        let mut r_past_s = der_encode(&[0x01], &[0x01]);
+        r_past_s[3] = 4;
+        let mut oversized = der_encode(&[0x01; 33], &[0x01; 33]);
+        oversized.resize(MAX_DER_SIGNATURE_SIZE + 1, SIGHASH_ALL);
+
+        let malformed: [(&str, Vec<u8>); 14] = [
+            ("empty", vec![]),
+            ("truncated", der[..MIN_DER_SIGNATURE_SIZE-1].to_vec()),
+            ("oversized", oversized),
+            ("missing sighash type", der[..der.len()-1].to_vec()),
+            ("wrong sequence tag", wrong_sequence_tag),
+            ("wrong sequence length", wrong_sequence_length),
+            ("wrong integer tag", wrong_integer_tag),
+            ("byte after s", extra_byte),
+            ("r length past s", r_past_s),
+            ("empty r", der_encode(&[], &[0x01, 0x01])),
+            ("negative s", der_encode(&[0x01], &[0x80])),
+            ("padded r", der_encode(&[0x00, 0x01], &[0x01])),
+            ("zero s", der_encode(&[0x01], &[0x00])),
+            ("r equal to the group order", der_encode(&[&[0x00][..], &group_order].concat(), &[0x01])),
+        ];
+        for (description, bytes) in malformed {
+            assert_eq!(SignData::from_der(&bytes).err(), Some(BitcoinVmError::InvalidDerSignature), "{}", description);
+        }
+    }
+}
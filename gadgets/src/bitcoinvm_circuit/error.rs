@@ -0,0 +1,112 @@
+use std::fmt;
+
+use halo2_proofs::plonk::Error;
+
+/// Errors detected in the inputs of the BitcoinVM chips. They are converted to
+/// `Error::Synthesis` when they occur while a circuit is synthesized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BitcoinVmError {
+    /// The scriptPubkey is longer than MAX_SCRIPT_PUBKEY_SIZE
+    OversizedScript { length: usize },
+    /// No scriptPubkeys were given
+    NoScriptPubkeys,
+    /// The number of signatures differs from the number of public keys collected from the
+    /// scriptPubkey
+    MismatchedSignatureCount { signatures: usize, public_keys: usize },
+    /// There are more signatures than verifications assigned by the OpCheckSig chip
+    TooManySignatures { signatures: usize, max_signatures: usize },
+    /// The public key of a signature differs from the public key collected from the scriptPubkey
+    MismatchedPublicKey { index: usize },
+    /// The sighash type byte of a signature is not a valid sighash flag
+    InvalidSighashType { index: usize, sighash_type: u8 },
+    /// The number of blinding factors differs from the number of signatures
+    MismatchedBlindingCount { blindings: usize, signatures: usize },
+    /// The aux generator cannot be used by the ECC chip
+    InvalidAuxGenerator,
+    /// A public key in the scriptPubkey has a prefix byte other than 0x02, 0x03 and 0x04
+    UnexpectedPrefixByte(u8),
+    /// A public key in the scriptPubkey is not a valid secp256k1 point
+    InvalidPublicKey,
+    /// An opcode needs more stack items than there are on the stack
+    StackUnderflow { opcode: u8 },
+    /// A stack item is a signature where data is expected or vice versa
+    UnexpectedStackElement { opcode: u8 },
+    /// An OP_ELSE or OP_ENDIF has no matching OP_IF or OP_NOTIF
+    UnbalancedConditional,
+    /// The initial stack has more items than MAX_STACK_DEPTH
+    OversizedStack { items: usize },
+    /// The scriptSig has an opcode other than a data push
+    InvalidScriptSig,
+    /// The script contains an opcode disabled by Bitcoin, even in a branch which is not taken
+    DisabledOpcode { opcode: u8 },
+    /// A signature is not a strict DER encoding of non-zero r and s values followed by a
+    /// sighash type byte
+    InvalidDerSignature,
+    /// A data push of the script runs past its end
+    TruncatedPush { opcode: u8 },
+    /// An OP_PUSHDATA pushes zero bytes, which only OP_0 is allowed to do
+    EmptyPushData { opcode: u8 },
+    /// The scriptPubkey of a P2SH spend is not `OP_HASH160 <20-byte hash> OP_EQUAL`
+    NotP2shScriptPubkey,
+}
+
+impl fmt::Display for BitcoinVmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitcoinVmError::OversizedScript { length } =>
+                write!(f, "scriptPubkey of {} bytes is too long", length),
+            BitcoinVmError::NoScriptPubkeys =>
+                write!(f, "no scriptPubkeys given"),
+            BitcoinVmError::MismatchedSignatureCount { signatures, public_keys } =>
+                write!(f, "{} signatures given for {} public keys", signatures, public_keys),
+            BitcoinVmError::TooManySignatures { signatures, max_signatures } =>
+                write!(f, "{} signatures given but at most {} can be verified", signatures, max_signatures),
+            BitcoinVmError::MismatchedPublicKey { index } =>
+                write!(f, "signature {} is not for the public key in the scriptPubkey", index),
+            BitcoinVmError::InvalidSighashType { index, sighash_type } =>
+                write!(f, "signature {} has invalid sighash type {:#04x}", index, sighash_type),
+            BitcoinVmError::MismatchedBlindingCount { blindings, signatures } =>
+                write!(f, "{} blinding factors given for {} signatures", blindings, signatures),
+            BitcoinVmError::InvalidAuxGenerator =>
+                write!(f, "invalid aux generator"),
+            BitcoinVmError::UnexpectedPrefixByte(prefix) =>
+                write!(f, "unexpected public key prefix byte {:#04x}", prefix),
+            BitcoinVmError::InvalidPublicKey =>
+                write!(f, "invalid public key"),
+            BitcoinVmError::StackUnderflow { opcode } =>
+                write!(f, "stack underflow at opcode {:#04x}", opcode),
+            BitcoinVmError::UnexpectedStackElement { opcode } =>
+                write!(f, "unexpected stack element at opcode {:#04x}", opcode),
+            BitcoinVmError::UnbalancedConditional =>
+                write!(f, "unbalanced conditional"),
+            BitcoinVmError::OversizedStack { items } =>
+                write!(f, "initial stack of {} items is too deep", items),
+            BitcoinVmError::InvalidScriptSig =>
+                write!(f, "scriptSig is not push-only"),
+            BitcoinVmError::DisabledOpcode { opcode } =>
+                write!(f, "disabled opcode {:#04x}", opcode),
+            BitcoinVmError::InvalidDerSignature =>
+                write!(f, "malformed DER signature"),
+            BitcoinVmError::TruncatedPush { opcode } =>
+                write!(f, "data push at opcode {:#04x} runs past the end of the script", opcode),
+            BitcoinVmError::EmptyPushData { opcode } =>
+                write!(f, "empty data push at opcode {:#04x}", opcode),
+            BitcoinVmError::NotP2shScriptPubkey =>
+                write!(f, "scriptPubkey is not a P2SH script"),
+        }
+    }
+}
+
+impl std::error::Error for BitcoinVmError {}
+
+impl From<libsecp256k1::Error> for BitcoinVmError {
+    fn from(_: libsecp256k1::Error) -> Self {
+        BitcoinVmError::InvalidPublicKey
+    }
+}
+
+impl From<BitcoinVmError> for Error {
+    fn from(_: BitcoinVmError) -> Self {
+        Error::Synthesis
+    }
+}
@@ -4,17 +4,24 @@ use halo2_proofs::circuit::{Layouter, Region, Value, AssignedCell};
 use halo2_proofs::plonk::{Column, Advice, Selector, ConstraintSystem, Expression, Error, Instance};
 use halo2_proofs::poly::Rotation;
 use super::constants::*;
+use super::error::BitcoinVmError;
 use super::util::expr::Expr;
 use super::util::is_zero::{IsZeroConfig, IsZeroChip};
-use super::opcode_table::{OpcodeTableConfig, OpcodeTableChip};
+use super::util::less_than::{LessThanConfig, LessThanChip};
+use super::util::script_num::{ScriptNumConfig, ScriptNumChip, MAX_SCRIPT_NUM_SIZE, MAX_SCRIPT_NUM_RESULT_SIZE, script_num_field};
+use super::opcode_table::{OpcodeInputs, OpcodeTableConfig, OpcodeTableChip};
+use super::public_inputs::{pk_rlc_acc_row, num_checksig_opcodes_row};
+use super::util::script_type::{classify_script, split_p2sh_script_sig, ScriptType};
 
 use crate::Field;
 use crate::bitcoinvm_circuit::util::is_zero::IsZeroInstruction;
 use crate::bitcoinvm_circuit::util::script_parser::*;
+use crate::bitcoinvm_circuit::crypto_opcodes::util::pk_parser::num_stack_operands;
 
 
+/// Configuration of an ExecutionChip with `STACK_DEPTH` stack columns
 #[derive(Clone, Debug)]
-pub(crate) struct ExecutionConfig<F: Field> {
+pub(crate) struct ExecutionConfig<F: Field, const STACK_DEPTH: usize = MAX_STACK_DEPTH> {
     // Instance column with scriptPubkey length and rlc values in first and second rows
     instance: Column<Instance>,
     // Randomness used for RLC
@@ -23,6 +30,14 @@ pub(crate) struct ExecutionConfig<F: Field> {
     q_first: Selector,
     // Selector that is active after first row
     q_execution: Selector,
+    // Selector for the row holding the final state of a scriptPubkey, which must succeed. It is
+    // not enabled for a scriptSig, whose final stack seeds the scriptPubkey.
+    q_script_end: Selector,
+    // Selector for the first row of a region whose initial stack is assigned from values rather
+    // than copied from a scriptSig region
+    q_initial_stack: Selector,
+    // Selector for the first row of a scriptSig region, which starts from the empty stack
+    q_empty_initial_stack: Selector,
     // Current opcode being processed
     opcode: Column<Advice>,
     opcode_table: OpcodeTableConfig,
@@ -34,6 +49,64 @@ pub(crate) struct ExecutionConfig<F: Field> {
     is_opcode_pushdata2: Column<Advice>,
     is_opcode_pushdata4: Column<Advice>,
     is_opcode_checksig: Column<Advice>,
+    is_opcode_equal: Column<Advice>,
+    is_opcode_equalverify: Column<Advice>,
+    is_opcode_verify: Column<Advice>,
+    is_opcode_drop: Column<Advice>,
+    is_opcode_2drop: Column<Advice>,
+    is_opcode_swap: Column<Advice>,
+    is_opcode_over: Column<Advice>,
+    is_opcode_rot: Column<Advice>,
+    is_opcode_nip: Column<Advice>,
+    is_opcode_tuck: Column<Advice>,
+    is_opcode_2dup: Column<Advice>,
+    is_opcode_3dup: Column<Advice>,
+    is_opcode_2over: Column<Advice>,
+    is_opcode_2rot: Column<Advice>,
+    is_opcode_2swap: Column<Advice>,
+    is_opcode_pick: Column<Advice>,
+    is_opcode_roll: Column<Advice>,
+    is_opcode_depth: Column<Advice>,
+    is_opcode_add: Column<Advice>,
+    is_opcode_sub: Column<Advice>,
+    is_opcode_1add: Column<Advice>,
+    is_opcode_1sub: Column<Advice>,
+    is_opcode_negate: Column<Advice>,
+    is_opcode_abs: Column<Advice>,
+    is_opcode_lessthan: Column<Advice>,
+    is_opcode_greaterthan: Column<Advice>,
+    is_opcode_lessthanorequal: Column<Advice>,
+    is_opcode_greaterthanorequal: Column<Advice>,
+    is_opcode_min: Column<Advice>,
+    is_opcode_max: Column<Advice>,
+    is_opcode_within: Column<Advice>,
+    is_opcode_not: Column<Advice>,
+    is_opcode_0notequal: Column<Advice>,
+    is_opcode_booland: Column<Advice>,
+    is_opcode_boolor: Column<Advice>,
+    is_opcode_if: Column<Advice>,
+    is_opcode_notif: Column<Advice>,
+    is_opcode_else: Column<Advice>,
+    is_opcode_endif: Column<Advice>,
+    is_opcode_checkmultisig: Column<Advice>,
+    is_opcode_checksigverify: Column<Advice>,
+    is_opcode_numequal: Column<Advice>,
+    is_opcode_checksigadd: Column<Advice>,
+    is_opcode_push1: Column<Advice>,
+    is_opcode_push1_to_push16: Column<Advice>,
+    is_opcode_toaltstack: Column<Advice>,
+    is_opcode_fromaltstack: Column<Advice>,
+    is_opcode_size: Column<Advice>,
+    is_opcode_hash256: Column<Advice>,
+    is_opcode_sha256: Column<Advice>,
+    is_opcode_ripemd160: Column<Advice>,
+    is_opcode_sha1: Column<Advice>,
+    is_opcode_hash160: Column<Advice>,
+    is_opcode_cltv: Column<Advice>,
+    is_opcode_csv: Column<Advice>,
+    is_opcode_codeseparator: Column<Advice>,
+    is_opcode_nop: Column<Advice>,
+    is_opcode_disabled: Column<Advice>,
 
     // Columns to track the parsing of script
     script_rlc_acc: Column<Advice>,
@@ -42,11 +115,62 @@ pub(crate) struct ExecutionConfig<F: Field> {
     num_script_bytes_remaining_is_zero: IsZeroConfig<F>,
 
     // Stack state
-    stack: [Column<Advice>; MAX_STACK_DEPTH],
+    stack: [Column<Advice>; STACK_DEPTH],
+    // Number of stack items, which is pushed by OP_DEPTH
+    stack_size: Column<Advice>,
+    stack_size_inv: Column<Advice>,
+    // Little-endian bytes of the number of stack items below the operands of the current opcode.
+    // Their range check rejects opcodes which pop more items than are on the stack.
+    stack_surplus_bytes: [Column<Advice>; 2],
+    // Byte lengths of the stack items, which OP_SIZE pushes. They move with the stack items.
+    stack_len: [Column<Advice>; STACK_DEPTH],
+
+    // Condition stack of the enclosing OP_IF and OP_NOTIF opcodes, innermost first. Entries are
+    // BRANCH_TAKEN, BRANCH_NOT_TAKEN or zero if unused.
+    condition_stack: [Column<Advice>; MAX_CONDITION_DEPTH],
+    // Number of entries which are BRANCH_NOT_TAKEN. Opcodes are only executed if it is zero.
+    num_false_conditions: Column<Advice>,
+    num_false_conditions_inv: Column<Advice>,
+    is_prev_branch_active: IsZeroConfig<F>,
+
+    // Alt stack of OP_TOALTSTACK and OP_FROMALTSTACK, top first. Unused entries are zero.
+    alt_stack: [Column<Advice>; MAX_ALTSTACK_DEPTH],
+    alt_stack_size: Column<Advice>,
+    alt_stack_size_inv: Column<Advice>,
+    is_prev_alt_stack_empty: IsZeroConfig<F>,
+    alt_stack_full_inv: Column<Advice>,
+    is_prev_alt_stack_full: IsZeroConfig<F>,
+    alt_stack_len: [Column<Advice>; MAX_ALTSTACK_DEPTH],
     
     // Columns to help verify that the top stack element is false
     is_stack_top_false_inv: Column<Advice>,
     is_stack_top_false: IsZeroConfig<F>,
+    // Columns to help verify that the top stack element before the current opcode is false
+    is_prev_stack_top_false_inv: Column<Advice>,
+    is_prev_stack_top_false: IsZeroConfig<F>,
+    // Columns to help verify that the second stack element before the current opcode is false
+    is_prev_stack_second_false_inv: Column<Advice>,
+    is_prev_stack_second_false: IsZeroConfig<F>,
+
+    // Columns to help compare the top two stack elements for OP_EQUAL and OP_EQUALVERIFY
+    are_top_two_equal_inv: Column<Advice>,
+    are_top_two_equal: IsZeroConfig<F>,
+    // The next script byte is 0x81, the data pushed by OP_1NEGATE
+    is_next_byte_negative_one_inv: Column<Advice>,
+    is_next_byte_negative_one: IsZeroConfig<F>,
+
+    // Numbers decoded from the top two items of the previous stack, which are the operands of
+    // the arithmetic opcodes, and from the top item of the current stack, which is the result
+    top_num: ScriptNumConfig<F, MAX_SCRIPT_NUM_SIZE>,
+    second_num: ScriptNumConfig<F, MAX_SCRIPT_NUM_SIZE>,
+    third_num: ScriptNumConfig<F, MAX_SCRIPT_NUM_SIZE>,
+    result_num: ScriptNumConfig<F, MAX_SCRIPT_NUM_RESULT_SIZE>,
+    // Comparisons of the decoded operands for the comparison opcodes and OP_WITHIN
+    are_top_two_nums_equal_inv: Column<Advice>,
+    are_top_two_nums_equal: IsZeroConfig<F>,
+    second_num_lt_top_num: LessThanConfig<F>,
+    third_num_lt_second_num: LessThanConfig<F>,
+    third_num_lt_top_num: LessThanConfig<F>,
 
     // Columns to help with data push operations
     num_data_bytes_remaining: Column<Advice>,
@@ -64,11 +188,68 @@ pub(crate) struct ExecutionConfig<F: Field> {
     // Public key accumulator OP_CHECKSIG opcodes
     pk_rlc_acc: Column<Advice>,
     num_checksig_opcodes: Column<Advice>,
+    // Number of OP_CHECKSIG opcodes with a valid signature; only these need an ECDSA verification
+    num_verified_checksigs: Column<Advice>,
+
+    // Accumulator of the hashed items and their digests of the hash opcodes
+    hash_rlc_acc: Column<Advice>,
+
+    // Number of script bytes read up to and including the current byte
+    num_script_bytes_read: Column<Advice>,
+    // Byte offset following the last executed OP_CODESEPARATOR, from which the script is covered
+    // by signatures. It is zero if no OP_CODESEPARATOR has been executed.
+    last_codeseparator_position: Column<Advice>,
+
+    // nLockTime of the spending transaction, which is the same in all rows and exposed publicly
+    nlocktime: Column<Advice>,
+    // In the rows of OP_CHECKLOCKTIMEVERIFY, whether the nLockTime is a block height, and the
+    // little-endian bytes of the distances of the locktime operand and of the nLockTime to
+    // LOCKTIME_THRESHOLD and of the nLockTime minus the locktime. Their range checks fail unless
+    // both are of the same type and the locktime is not above the nLockTime. They are zero in
+    // other rows.
+    is_nlocktime_height: Column<Advice>,
+    locktime_threshold_distance_bytes: [Column<Advice>; 4],
+    nlocktime_threshold_distance_bytes: [Column<Advice>; 4],
+    nlocktime_surplus_bytes: [Column<Advice>; 4],
+
+    // nSequence of the spending input, which is the same in all rows and exposed publicly
+    nsequence: Column<Advice>,
+    // In the rows of OP_CHECKSEQUENCEVERIFY, the little-endian bytes of the nSequence, bits 22,
+    // 23 and 31 of the relative locktime operand and of the nSequence, and the bytes of the
+    // masked nSequence minus the masked locktime. They are zero in other rows.
+    nsequence_bytes: [Column<Advice>; 4],
+    sequence_locktime_bits: [Column<Advice>; 3],
+    nsequence_bits: [Column<Advice>; 3],
+    nsequence_surplus_bytes: [Column<Advice>; 2],
+
+    // One-hot encodings of the number of public keys n and signatures m of an OP_CHECKMULTISIG,
+    // and of the number of items n+m+2 by which the items below them move up the stack
+    multisig_num_keys: [Column<Advice>; MAX_MULTISIG_N],
+    multisig_num_sigs: [Column<Advice>; MAX_MULTISIG_N],
+    multisig_shift: [Column<Advice>; 2*MAX_MULTISIG_N-1],
+    // Signatures of an OP_CHECKMULTISIG, or zero if there are fewer than MAX_MULTISIG_N
+    multisig_sigs: [Column<Advice>; MAX_MULTISIG_N],
+    // Public keys of an OP_CHECKMULTISIG which are accumulated in pk_rlc_acc, top first
+    is_multisig_key_signed: [Column<Advice>; MAX_MULTISIG_N],
+    // Values of pk_rlc_acc after the public key at the same index is processed
+    multisig_pk_rlc_acc: [Column<Advice>; MAX_MULTISIG_N],
+    multisig_num_invalid_sigs_inv: Column<Advice>,
+    are_multisig_sigs_valid: IsZeroConfig<F>,
+
+    // One-hot encoding of the index in the previous stack of the item picked by OP_PICK or
+    // OP_ROLL, which is the depth operand plus one
+    pick_index: [Column<Advice>; STACK_DEPTH],
 }
 
 
+/// Chip which unrolls the execution of a scriptPubkey. Only the top `STACK_DEPTH` stack items
+/// are stored in the circuit.
 #[derive(Debug, Clone)]
-pub(crate) struct ExecutionChip<F: Field>{
+pub(crate) struct ExecutionChip<F: Field, const STACK_DEPTH: usize = MAX_STACK_DEPTH>{
+    // nLockTime of the spending transaction, checked by OP_CHECKLOCKTIMEVERIFY
+    nlocktime: u32,
+    // nSequence of the spending input, checked by OP_CHECKSEQUENCEVERIFY
+    nsequence: u32,
     marker: PhantomData<F>,
 }
 
@@ -79,23 +260,75 @@ pub(crate) struct ExecutionChipAssignedCells<F: Field> {
     pub(crate) randomness: AssignedCell<F, F>,
     pub(crate) pk_rlc_acc: AssignedCell<F, F>,
     pub(crate) num_checksig_opcodes: AssignedCell<F, F>,
+    pub(crate) num_verified_checksigs: AssignedCell<F, F>,
+    pub(crate) hash_rlc_acc: AssignedCell<F, F>,
+    pub(crate) last_codeseparator_position: AssignedCell<F, F>,
+    pub(crate) nlocktime: AssignedCell<F, F>,
+    pub(crate) nsequence: AssignedCell<F, F>,
+    // Stack, stack_size and stack lengths in the row holding the final state of the script
+    pub(crate) final_stack: Vec<AssignedCell<F, F>>,
+    pub(crate) final_stack_size: AssignedCell<F, F>,
+    pub(crate) final_stack_len: Vec<AssignedCell<F, F>>,
 }
 
-impl<F: Field> ExecutionChip<F> {
+// Stack in the first row of an unrolling region
+#[derive(Clone, Copy)]
+enum InitialStack<'a, F: Field, const STACK_DEPTH: usize> {
+    // Items assigned from the given values
+    Values([F; STACK_DEPTH]),
+    // Stack left by a scriptSig, copied from the cells of its region
+    Copied {
+        stack: [F; STACK_DEPTH],
+        stack_size: F,
+        stack_len: [u64; STACK_DEPTH],
+        // Items whose bytes are known, to be hashed by the scriptPubkey
+        known_items: &'a [(F, Vec<u8>)],
+        cells: &'a ExecutionChipAssignedCells<F>,
+    },
+}
+
+impl<F: Field, const STACK_DEPTH: usize> ExecutionChip<F, STACK_DEPTH> {
 
     pub(crate) fn construct() -> Self {
-        Self { marker: PhantomData }
+        Self { nlocktime: 0, nsequence: 0, marker: PhantomData }
+    }
+
+    /// Sets the nLockTime of the spending transaction, which is zero by default
+    pub(crate) fn with_nlocktime(mut self, nlocktime: u32) -> Self {
+        self.nlocktime = nlocktime;
+        self
+    }
+
+    /// Sets the nSequence of the spending input, which is zero by default
+    pub(crate) fn with_nsequence(mut self, nsequence: u32) -> Self {
+        self.nsequence = nsequence;
+        self
     }
 
     pub(crate) fn configure(
         meta: &mut ConstraintSystem<F>,
-    ) -> ExecutionConfig<F> {
+    ) -> ExecutionConfig<F, STACK_DEPTH> {
+        Self::configure_with_minimal_push(meta, false)
+    }
+
+    /// Configures the chip. If `require_minimal_push` is true, data pushes which are not
+    /// encoded with the shortest opcode are rejected, as required by BIP62 rule 3.
+    pub(crate) fn configure_with_minimal_push(
+        meta: &mut ConstraintSystem<F>,
+        require_minimal_push: bool,
+    ) -> ExecutionConfig<F, STACK_DEPTH> {
+        // Opcodes like OP_CHECKMULTISIG access items up to this depth
+        assert!(STACK_DEPTH >= MIN_STACK_DEPTH);
+
         let instance = meta.instance_column();
         meta.enable_equality(instance);
         let randomness = meta.advice_column();
         meta.enable_equality(randomness);
         let q_first = meta.complex_selector();
         let q_execution = meta.complex_selector();
+        let q_script_end = meta.complex_selector();
+        let q_initial_stack = meta.selector();
+        let q_empty_initial_stack = meta.selector();
         let opcode = meta.advice_column();
         meta.enable_equality(opcode);
         let is_opcode_enabled = meta.advice_column();
@@ -114,11 +347,135 @@ impl<F: Field> ExecutionChip<F> {
         meta.enable_equality(is_opcode_pushdata4);
         let is_opcode_checksig = meta.advice_column();
         meta.enable_equality(is_opcode_checksig);
+        let is_opcode_equal = meta.advice_column();
+        meta.enable_equality(is_opcode_equal);
+        let is_opcode_equalverify = meta.advice_column();
+        meta.enable_equality(is_opcode_equalverify);
+        let is_opcode_verify = meta.advice_column();
+        meta.enable_equality(is_opcode_verify);
+        let is_opcode_drop = meta.advice_column();
+        meta.enable_equality(is_opcode_drop);
+        let is_opcode_2drop = meta.advice_column();
+        meta.enable_equality(is_opcode_2drop);
+        let is_opcode_swap = meta.advice_column();
+        meta.enable_equality(is_opcode_swap);
+        let is_opcode_over = meta.advice_column();
+        meta.enable_equality(is_opcode_over);
+        let is_opcode_rot = meta.advice_column();
+        meta.enable_equality(is_opcode_rot);
+        let is_opcode_nip = meta.advice_column();
+        meta.enable_equality(is_opcode_nip);
+        let is_opcode_tuck = meta.advice_column();
+        meta.enable_equality(is_opcode_tuck);
+        let is_opcode_2dup = meta.advice_column();
+        meta.enable_equality(is_opcode_2dup);
+        let is_opcode_3dup = meta.advice_column();
+        meta.enable_equality(is_opcode_3dup);
+        let is_opcode_2over = meta.advice_column();
+        meta.enable_equality(is_opcode_2over);
+        let is_opcode_2rot = meta.advice_column();
+        meta.enable_equality(is_opcode_2rot);
+        let is_opcode_2swap = meta.advice_column();
+        meta.enable_equality(is_opcode_2swap);
+        let is_opcode_pick = meta.advice_column();
+        meta.enable_equality(is_opcode_pick);
+        let is_opcode_roll = meta.advice_column();
+        meta.enable_equality(is_opcode_roll);
+        let is_opcode_depth = meta.advice_column();
+        meta.enable_equality(is_opcode_depth);
+        let is_opcode_add = meta.advice_column();
+        meta.enable_equality(is_opcode_add);
+        let is_opcode_sub = meta.advice_column();
+        meta.enable_equality(is_opcode_sub);
+        let is_opcode_1add = meta.advice_column();
+        meta.enable_equality(is_opcode_1add);
+        let is_opcode_1sub = meta.advice_column();
+        meta.enable_equality(is_opcode_1sub);
+        let is_opcode_negate = meta.advice_column();
+        meta.enable_equality(is_opcode_negate);
+        let is_opcode_abs = meta.advice_column();
+        meta.enable_equality(is_opcode_abs);
+        let is_opcode_lessthan = meta.advice_column();
+        meta.enable_equality(is_opcode_lessthan);
+        let is_opcode_greaterthan = meta.advice_column();
+        meta.enable_equality(is_opcode_greaterthan);
+        let is_opcode_lessthanorequal = meta.advice_column();
+        meta.enable_equality(is_opcode_lessthanorequal);
+        let is_opcode_greaterthanorequal = meta.advice_column();
+        meta.enable_equality(is_opcode_greaterthanorequal);
+        let is_opcode_min = meta.advice_column();
+        meta.enable_equality(is_opcode_min);
+        let is_opcode_max = meta.advice_column();
+        meta.enable_equality(is_opcode_max);
+        let is_opcode_within = meta.advice_column();
+        meta.enable_equality(is_opcode_within);
+        let is_opcode_not = meta.advice_column();
+        meta.enable_equality(is_opcode_not);
+        let is_opcode_0notequal = meta.advice_column();
+        meta.enable_equality(is_opcode_0notequal);
+        let is_opcode_booland = meta.advice_column();
+        meta.enable_equality(is_opcode_booland);
+        let is_opcode_boolor = meta.advice_column();
+        meta.enable_equality(is_opcode_boolor);
+        let is_opcode_if = meta.advice_column();
+        meta.enable_equality(is_opcode_if);
+        let is_opcode_notif = meta.advice_column();
+        meta.enable_equality(is_opcode_notif);
+        let is_opcode_else = meta.advice_column();
+        meta.enable_equality(is_opcode_else);
+        let is_opcode_endif = meta.advice_column();
+        meta.enable_equality(is_opcode_endif);
+        let is_opcode_checkmultisig = meta.advice_column();
+        meta.enable_equality(is_opcode_checkmultisig);
+        let is_opcode_checksigverify = meta.advice_column();
+        meta.enable_equality(is_opcode_checksigverify);
+        let is_opcode_numequal = meta.advice_column();
+        meta.enable_equality(is_opcode_numequal);
+        let is_opcode_checksigadd = meta.advice_column();
+        meta.enable_equality(is_opcode_checksigadd);
+        let is_opcode_push1 = meta.advice_column();
+        meta.enable_equality(is_opcode_push1);
+        let is_opcode_push1_to_push16 = meta.advice_column();
+        meta.enable_equality(is_opcode_push1_to_push16);
+        let is_opcode_toaltstack = meta.advice_column();
+        meta.enable_equality(is_opcode_toaltstack);
+        let is_opcode_fromaltstack = meta.advice_column();
+        meta.enable_equality(is_opcode_fromaltstack);
+        let is_opcode_size = meta.advice_column();
+        meta.enable_equality(is_opcode_size);
+        let is_opcode_hash256 = meta.advice_column();
+        meta.enable_equality(is_opcode_hash256);
+        let is_opcode_sha256 = meta.advice_column();
+        meta.enable_equality(is_opcode_sha256);
+        let is_opcode_ripemd160 = meta.advice_column();
+        meta.enable_equality(is_opcode_ripemd160);
+        let is_opcode_sha1 = meta.advice_column();
+        meta.enable_equality(is_opcode_sha1);
+        let is_opcode_hash160 = meta.advice_column();
+        meta.enable_equality(is_opcode_hash160);
+        let is_opcode_cltv = meta.advice_column();
+        meta.enable_equality(is_opcode_cltv);
+        let is_opcode_csv = meta.advice_column();
+        meta.enable_equality(is_opcode_csv);
+        let is_opcode_codeseparator = meta.advice_column();
+        meta.enable_equality(is_opcode_codeseparator);
+        let is_opcode_nop = meta.advice_column();
+        meta.enable_equality(is_opcode_nop);
+        let is_opcode_disabled = meta.advice_column();
+        meta.enable_equality(is_opcode_disabled);
 
         let script_rlc_acc = meta.advice_column();
         meta.enable_equality(script_rlc_acc);
-        let stack = [(); MAX_STACK_DEPTH].map(|_| meta.advice_column());
+        let stack = [(); STACK_DEPTH].map(|_| meta.advice_column());
         stack.iter().for_each(|c| meta.enable_equality(*c));
+        let stack_size = meta.advice_column();
+        meta.enable_equality(stack_size);
+        let stack_size_inv = meta.advice_column();
+        meta.enable_equality(stack_size_inv);
+        let stack_surplus_bytes = [(); 2].map(|_| meta.advice_column());
+        stack_surplus_bytes.iter().for_each(|c| meta.enable_equality(*c));
+        let stack_len = [(); STACK_DEPTH].map(|_| meta.advice_column());
+        stack_len.iter().for_each(|c| meta.enable_equality(*c));
 
         let is_stack_top_false_inv = meta.advice_column();
         meta.enable_equality(is_stack_top_false_inv);
@@ -132,6 +489,53 @@ impl<F: Field> ExecutionChip<F> {
             is_stack_top_false_inv,
         );
 
+        let is_prev_stack_top_false_inv = meta.advice_column();
+        meta.enable_equality(is_prev_stack_top_false_inv);
+        let is_prev_stack_top_false = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_selector(q_execution),
+            |meta| {
+                let prev_stack_top = meta.query_advice(stack[0], Rotation::prev());
+                prev_stack_top.clone() * (prev_stack_top - NEGATIVE_ZERO.expr())
+            },
+            is_prev_stack_top_false_inv,
+        );
+
+        let is_prev_stack_second_false_inv = meta.advice_column();
+        meta.enable_equality(is_prev_stack_second_false_inv);
+        let is_prev_stack_second_false = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_selector(q_execution),
+            |meta| {
+                let prev_stack_second = meta.query_advice(stack[1], Rotation::prev());
+                prev_stack_second.clone() * (prev_stack_second - NEGATIVE_ZERO.expr())
+            },
+            is_prev_stack_second_false_inv,
+        );
+
+        let are_top_two_equal_inv = meta.advice_column();
+        meta.enable_equality(are_top_two_equal_inv);
+        let are_top_two_equal = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_selector(q_execution),
+            |meta| {
+                // The operands of OP_EQUAL and OP_EQUALVERIFY are the top two items of the previous stack
+                let prev_stack_top = meta.query_advice(stack[0], Rotation::prev());
+                let prev_stack_second = meta.query_advice(stack[1], Rotation::prev());
+                prev_stack_top - prev_stack_second
+            },
+            are_top_two_equal_inv,
+        );
+
+        let is_next_byte_negative_one_inv = meta.advice_column();
+        meta.enable_equality(is_next_byte_negative_one_inv);
+        let is_next_byte_negative_one = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_selector(q_execution),
+            |meta| meta.query_advice(opcode, Rotation::next()) - 0x81u8.expr(),
+            is_next_byte_negative_one_inv,
+        );
+
 
         let num_script_bytes_remaining = meta.advice_column();
         meta.enable_equality(num_script_bytes_remaining);
@@ -181,19 +585,199 @@ impl<F: Field> ExecutionChip<F> {
         let num_data_length_acc_constant = meta.advice_column();
         meta.enable_equality(num_data_length_acc_constant);
 
+        // The current script byte is an opcode, and not a data byte or a data length byte of a
+        // push in progress. Every opcode gate must be gated by this expression.
+        let is_opcode_boundary = (1u8.expr() - num_script_bytes_remaining_is_zero.expr())
+            * num_data_bytes_remaining_is_zero.expr()
+            * num_data_length_bytes_remaining_is_zero.expr();
+
+        let condition_stack = [(); MAX_CONDITION_DEPTH].map(|_| meta.advice_column());
+        condition_stack.iter().for_each(|c| meta.enable_equality(*c));
+        let num_false_conditions = meta.advice_column();
+        meta.enable_equality(num_false_conditions);
+        let num_false_conditions_inv = meta.advice_column();
+        meta.enable_equality(num_false_conditions_inv);
+        let is_prev_branch_active = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_selector(q_execution),
+            |meta| meta.query_advice(num_false_conditions, Rotation::prev()),
+            num_false_conditions_inv,
+        );
+
+        let alt_stack = [(); MAX_ALTSTACK_DEPTH].map(|_| meta.advice_column());
+        alt_stack.iter().for_each(|c| meta.enable_equality(*c));
+        let alt_stack_size = meta.advice_column();
+        meta.enable_equality(alt_stack_size);
+        let alt_stack_size_inv = meta.advice_column();
+        meta.enable_equality(alt_stack_size_inv);
+        let is_prev_alt_stack_empty = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_selector(q_execution),
+            |meta| meta.query_advice(alt_stack_size, Rotation::prev()),
+            alt_stack_size_inv,
+        );
+        let alt_stack_full_inv = meta.advice_column();
+        meta.enable_equality(alt_stack_full_inv);
+        let is_prev_alt_stack_full = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_selector(q_execution),
+            |meta| meta.query_advice(alt_stack_size, Rotation::prev()) - (MAX_ALTSTACK_DEPTH as u64).expr(),
+            alt_stack_full_inv,
+        );
+        let alt_stack_len = [(); MAX_ALTSTACK_DEPTH].map(|_| meta.advice_column());
+        alt_stack_len.iter().for_each(|c| meta.enable_equality(*c));
+
+        // The current opcode is executed, i.e. it is an opcode which is not in a branch that is not
+        // taken. Every opcode gate which changes the stack must be gated by this expression.
+        let is_executed_opcode = is_opcode_boundary.clone() * is_prev_branch_active.expr();
+
         let opcode_table = OpcodeTableChip::configure(
+            meta,
+            OpcodeInputs {
+                q_execution,
+                opcode,
+                is_opcode_enabled,
+                is_opcode_op0,
+                is_opcode_op1_to_op16,
+                is_opcode_push1_to_push75,
+                is_opcode_pushdata1,
+                is_opcode_pushdata2,
+                is_opcode_pushdata4,
+                is_opcode_checksig,
+                is_opcode_equal,
+                is_opcode_equalverify,
+                is_opcode_verify,
+                is_opcode_drop,
+                is_opcode_2drop,
+                is_opcode_swap,
+                is_opcode_over,
+                is_opcode_rot,
+                is_opcode_nip,
+                is_opcode_tuck,
+                is_opcode_2dup,
+                is_opcode_3dup,
+                is_opcode_2over,
+                is_opcode_2rot,
+                is_opcode_2swap,
+                is_opcode_pick,
+                is_opcode_roll,
+                is_opcode_depth,
+                is_opcode_add,
+                is_opcode_sub,
+                is_opcode_1add,
+                is_opcode_1sub,
+                is_opcode_negate,
+                is_opcode_abs,
+                is_opcode_lessthan,
+                is_opcode_greaterthan,
+                is_opcode_lessthanorequal,
+                is_opcode_greaterthanorequal,
+                is_opcode_min,
+                is_opcode_max,
+                is_opcode_within,
+                is_opcode_not,
+                is_opcode_0notequal,
+                is_opcode_booland,
+                is_opcode_boolor,
+                is_opcode_if,
+                is_opcode_notif,
+                is_opcode_else,
+                is_opcode_endif,
+                is_opcode_checkmultisig,
+                is_opcode_checksigverify,
+                is_opcode_numequal,
+                is_opcode_checksigadd,
+                is_opcode_push1,
+                is_opcode_push1_to_push16,
+                is_opcode_toaltstack,
+                is_opcode_fromaltstack,
+                is_opcode_size,
+                is_opcode_hash256,
+                is_opcode_sha256,
+                is_opcode_ripemd160,
+                is_opcode_sha1,
+                is_opcode_hash160,
+                is_opcode_cltv,
+                is_opcode_csv,
+                is_opcode_codeseparator,
+                is_opcode_nop,
+                is_opcode_disabled,
+            },
+        );
+
+        // Operands may be zero stack items, which are treated as the empty array like in OP_VERIFY.
+        // Results are pushed with the canonical representation of the empty array.
+        let top_num = ScriptNumChip::configure(
             meta,
             q_execution,
-            opcode,
-            is_opcode_enabled,
-            is_opcode_op0,
-            is_opcode_op1_to_op16,
-            is_opcode_push1_to_push75,
-            is_opcode_pushdata1,
-            is_opcode_pushdata2,
-            is_opcode_pushdata4,
-            is_opcode_checksig,
+            |meta| meta.query_advice(stack[0], Rotation::prev()),
+            randomness,
+            opcode_table.table.opcode,
+            false,
+        );
+        let second_num = ScriptNumChip::configure(
+            meta,
+            q_execution,
+            |meta| meta.query_advice(stack[1], Rotation::prev()),
+            randomness,
+            opcode_table.table.opcode,
+            false,
+        );
+        let third_num = ScriptNumChip::configure(
+            meta,
+            q_execution,
+            |meta| meta.query_advice(stack[2], Rotation::prev()),
+            randomness,
+            opcode_table.table.opcode,
+            false,
+        );
+        let result_num = ScriptNumChip::configure(
+            meta,
+            q_execution,
+            |meta| meta.query_advice(stack[0], Rotation::cur()),
+            randomness,
+            opcode_table.table.opcode,
+            true,
+        );
+
+        // The decoded values of disabled decoders are zero, so the comparisons hold in all rows
+        let are_top_two_nums_equal_inv = meta.advice_column();
+        meta.enable_equality(are_top_two_nums_equal_inv);
+        let are_top_two_nums_equal = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_selector(q_execution),
+            |_| second_num.expr() - top_num.expr(),
+            are_top_two_nums_equal_inv,
+        );
+        let second_num_lt_top_num = LessThanChip::configure(
+            meta,
+            q_execution,
+            |_| second_num.expr(),
+            |_| top_num.expr(),
+            opcode_table.table.opcode,
+        );
+        let third_num_lt_second_num = LessThanChip::configure(
+            meta,
+            q_execution,
+            |_| third_num.expr(),
+            |_| second_num.expr(),
+            opcode_table.table.opcode,
         );
+        let third_num_lt_top_num = LessThanChip::configure(
+            meta,
+            q_execution,
+            |_| third_num.expr(),
+            |_| top_num.expr(),
+            opcode_table.table.opcode,
+        );
+
+        for byte in stack_surplus_bytes {
+            meta.lookup("stack surplus byte range", |meta| {
+                let q_execution = meta.query_selector(q_execution);
+                let byte = meta.query_advice(byte, Rotation::cur());
+                vec![(q_execution * byte, opcode_table.table.opcode)]
+            });
+        }
 
         let pk_rlc_acc = meta.advice_column();
         meta.enable_equality(pk_rlc_acc);
@@ -201,6 +785,96 @@ impl<F: Field> ExecutionChip<F> {
         let num_checksig_opcodes = meta.advice_column();
         meta.enable_equality(num_checksig_opcodes);
 
+        let num_verified_checksigs = meta.advice_column();
+        meta.enable_equality(num_verified_checksigs);
+
+        let hash_rlc_acc = meta.advice_column();
+        meta.enable_equality(hash_rlc_acc);
+
+        let num_script_bytes_read = meta.advice_column();
+        let last_codeseparator_position = meta.advice_column();
+        meta.enable_equality(last_codeseparator_position);
+
+        let nlocktime = meta.advice_column();
+        meta.enable_equality(nlocktime);
+        let is_nlocktime_height = meta.advice_column();
+        let locktime_threshold_distance_bytes = [(); 4].map(|_| meta.advice_column());
+        let nlocktime_threshold_distance_bytes = [(); 4].map(|_| meta.advice_column());
+        let nlocktime_surplus_bytes = [(); 4].map(|_| meta.advice_column());
+        let nsequence = meta.advice_column();
+        meta.enable_equality(nsequence);
+        let nsequence_bytes = [(); 4].map(|_| meta.advice_column());
+        let sequence_locktime_bits = [(); 3].map(|_| meta.advice_column());
+        let nsequence_bits = [(); 3].map(|_| meta.advice_column());
+        let nsequence_surplus_bytes = [(); 2].map(|_| meta.advice_column());
+        for byte in locktime_threshold_distance_bytes
+            .into_iter()
+            .chain(nlocktime_threshold_distance_bytes)
+            .chain(nlocktime_surplus_bytes)
+            .chain(nsequence_bytes)
+            .chain(nsequence_surplus_bytes)
+        {
+            meta.lookup("locktime byte range", |meta| {
+                let q_execution = meta.query_selector(q_execution);
+                let byte = meta.query_advice(byte, Rotation::cur());
+                vec![(q_execution * byte, opcode_table.table.opcode)]
+            });
+        }
+        // Bits 22 and 23 are the top bits of the third byte and bit 31 is the top bit of the
+        // fourth byte. As the bits are boolean, the remaining bits of the bytes are integers in
+        // [-192, 255] and [-128, 255], which are in [0, 63] and [0, 127] iff four and two times
+        // them are bytes. The locktime operand is decoded by result_num.
+        for (bytes, bits) in [
+            ([result_num.bytes[2], result_num.bytes[3]], sequence_locktime_bits),
+            ([nsequence_bytes[2], nsequence_bytes[3]], nsequence_bits),
+        ] {
+            meta.lookup("sequence bits 22 and 23", |meta| {
+                let q_execution = meta.query_selector(q_execution);
+                let is_opcode_csv = meta.query_advice(is_opcode_csv, Rotation::cur());
+                let third_byte = meta.query_advice(bytes[0], Rotation::cur());
+                let bit_22 = meta.query_advice(bits[0], Rotation::cur());
+                let bit_23 = meta.query_advice(bits[1], Rotation::cur());
+                let low_bits = third_byte - 64u8.expr() * bit_22 - 128u8.expr() * bit_23;
+                vec![(q_execution * is_opcode_csv * 4u8.expr() * low_bits, opcode_table.table.opcode)]
+            });
+            meta.lookup("sequence bit 31", |meta| {
+                let q_execution = meta.query_selector(q_execution);
+                let is_opcode_csv = meta.query_advice(is_opcode_csv, Rotation::cur());
+                let fourth_byte = meta.query_advice(bytes[1], Rotation::cur());
+                let bit_31 = meta.query_advice(bits[2], Rotation::cur());
+                let low_bits = fourth_byte - 128u8.expr() * bit_31;
+                vec![(q_execution * is_opcode_csv * 2u8.expr() * low_bits, opcode_table.table.opcode)]
+            });
+        }
+
+        let multisig_num_keys = [(); MAX_MULTISIG_N].map(|_| meta.advice_column());
+        let multisig_num_sigs = [(); MAX_MULTISIG_N].map(|_| meta.advice_column());
+        let multisig_shift = [(); 2*MAX_MULTISIG_N-1].map(|_| meta.advice_column());
+        let multisig_sigs = [(); MAX_MULTISIG_N].map(|_| meta.advice_column());
+        let is_multisig_key_signed = [(); MAX_MULTISIG_N].map(|_| meta.advice_column());
+        let multisig_pk_rlc_acc = [(); MAX_MULTISIG_N].map(|_| meta.advice_column());
+        let multisig_num_invalid_sigs_inv = meta.advice_column();
+        // The signatures are valid if all m of them are one
+        let are_multisig_sigs_valid = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_selector(q_execution),
+            |meta| {
+                let mut num_invalid_sigs = 0u8.expr();
+                for i in 0..MAX_MULTISIG_N {
+                    num_invalid_sigs = num_invalid_sigs
+                        + meta.query_advice(multisig_num_sigs[i], Rotation::cur()) * (i as u64 + 1).expr()
+                        - meta.query_advice(multisig_sigs[i], Rotation::cur());
+                }
+                num_invalid_sigs
+            },
+            multisig_num_invalid_sigs_inv,
+        );
+
+        let pick_index = [(); STACK_DEPTH].map(|_| meta.advice_column());
+
+        // q_first is a fixed selector enabled only in the first row of the unrolling region. It is
+        // part of the verifying key, so a prover cannot disable it to start the accumulators
+        // below from forged values.
         meta.create_gate("First row constraints", |meta| {
             let q_first = meta.query_selector(q_first);
 
@@ -225,9 +899,82 @@ impl<F: Field> ExecutionChip<F> {
             let first_row_num_checksig_opcodes = meta.query_advice(num_checksig_opcodes, Rotation::cur());
             // The number of OP_CHECKSIG opcodes in the first row is zero
             constraints.push(q_first.clone() * first_row_num_checksig_opcodes);
+            let first_row_num_verified_checksigs = meta.query_advice(num_verified_checksigs, Rotation::cur());
+            // The number of verified signatures in the first row is zero
+            constraints.push(q_first.clone() * first_row_num_verified_checksigs);
+            let first_row_hash_rlc_acc = meta.query_advice(hash_rlc_acc, Rotation::cur());
+            // The hash accumulator in the first row is zero
+            constraints.push(q_first.clone() * first_row_hash_rlc_acc);
+            // No script bytes are read and no OP_CODESEPARATOR is executed in the first row
+            for column in [num_script_bytes_read, last_codeseparator_position] {
+                let first_row_value = meta.query_advice(column, Rotation::cur());
+                constraints.push(q_first.clone() * first_row_value);
+            }
+
+            // The condition stack is initially empty
+            for column in condition_stack {
+                let first_row_condition = meta.query_advice(column, Rotation::cur());
+                constraints.push(q_first.clone() * first_row_condition);
+            }
+            // The alt stack is initially empty. Like in Bitcoin, it is not carried over from the
+            // scriptSig to the scriptPubkey.
+            for column in alt_stack.iter().chain(alt_stack_len.iter()).chain([&alt_stack_size]) {
+                let first_row_item = meta.query_advice(*column, Rotation::cur());
+                constraints.push(q_first.clone() * first_row_item);
+            }
+            let first_row_num_false_conditions = meta.query_advice(num_false_conditions, Rotation::cur());
+            constraints.push(q_first.clone() * first_row_num_false_conditions);
+            constraints
+        });
+
+        // The items of an initial stack assigned from values are witnesses, and empty slots are
+        // zero. Zero items such as the flags of invalid signatures may be above the deepest
+        // non-zero item, so the stack size is the number of slots down to that item. The byte
+        // lengths of the items are not known, so they are UNKNOWN_ITEM_LENGTH. In the first row,
+        // the pick_index columns flag the slots in the stack and stack_size_inv holds the inverse
+        // of the deepest item, as they are not used otherwise.
+        meta.create_gate("Initial stack", |meta| {
+            let q_initial_stack = meta.query_selector(q_initial_stack);
+            let items = stack.map(|column| meta.query_advice(column, Rotation::cur()));
+            let in_stack = pick_index.map(|column| meta.query_advice(column, Rotation::cur()));
+            let deepest_item_inv = meta.query_advice(stack_size_inv, Rotation::cur());
+
+            let mut constraints = vec![];
+            let mut num_items = 0u8.expr();
+            let mut deepest_item = 0u8.expr();
+            for i in 0..STACK_DEPTH {
+                constraints.push(q_initial_stack.clone() * in_stack[i].clone() * (1u8.expr() - in_stack[i].clone()));
+                // Slots below the stack are empty
+                constraints.push(q_initial_stack.clone() * (1u8.expr() - in_stack[i].clone()) * items[i].clone());
+                let first_row_len = meta.query_advice(stack_len[i], Rotation::cur());
+                constraints.push(q_initial_stack.clone() * (first_row_len - in_stack[i].clone() * UNKNOWN_ITEM_LENGTH.expr()));
+                let is_deepest = if i + 1 < STACK_DEPTH {
+                    // A slot below the stack is not above a slot in the stack
+                    constraints.push(q_initial_stack.clone() * (1u8.expr() - in_stack[i].clone()) * in_stack[i+1].clone());
+                    in_stack[i].clone() - in_stack[i+1].clone()
+                } else {
+                    in_stack[i].clone()
+                };
+                deepest_item = deepest_item + is_deepest * items[i].clone();
+                num_items = num_items + in_stack[i].clone();
+            }
+            // The deepest item of a non-empty stack is not zero
+            constraints.push(q_initial_stack.clone() * (deepest_item * deepest_item_inv - in_stack[0].clone()));
+            let first_row_stack_size = meta.query_advice(stack_size, Rotation::cur());
+            constraints.push(q_initial_stack * (first_row_stack_size - num_items));
             constraints
         });
 
+        // A scriptSig is executed on the empty stack. With the "Initial stack" gate, its stack size
+        // and item lengths are then zero.
+        meta.create_gate("Empty initial stack", |meta| {
+            let q_empty_initial_stack = meta.query_selector(q_empty_initial_stack);
+            stack
+                .iter()
+                .map(|column| q_empty_initial_stack.clone() * meta.query_advice(*column, Rotation::cur()))
+                .collect::<Vec<_>>()
+        });
+
         meta.create_gate("Randomness values are the same in all rows", |meta| {
             let q_execution = meta.query_selector(q_execution);
             let cur_randomness = meta.query_advice(randomness, Rotation::cur());
@@ -235,6 +982,20 @@ impl<F: Field> ExecutionChip<F> {
             vec![q_execution * (cur_randomness - prev_randomness)]
         });
 
+        meta.create_gate("nLockTime values are the same in all rows", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let cur_nlocktime = meta.query_advice(nlocktime, Rotation::cur());
+            let prev_nlocktime = meta.query_advice(nlocktime, Rotation::prev());
+            vec![q_execution * (cur_nlocktime - prev_nlocktime)]
+        });
+
+        meta.create_gate("nSequence values are the same in all rows", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let cur_nsequence = meta.query_advice(nsequence, Rotation::cur());
+            let prev_nsequence = meta.query_advice(nsequence, Rotation::prev());
+            vec![q_execution * (cur_nsequence - prev_nsequence)]
+        });
+
         meta.create_gate("Pop byte out of script_rlc_acc", |meta| {
             let q_execution = meta.query_selector(q_execution);
             let randomness = meta.query_advice(randomness, Rotation::cur());
@@ -281,7 +1042,7 @@ impl<F: Field> ExecutionChip<F> {
             ];
 
             // Check that the stack items remain the same
-            for i in 0..MAX_STACK_DEPTH {
+            for i in 0..STACK_DEPTH {
                 let current_stack_item = meta.query_advice(stack[i], Rotation::cur());
                 let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
                 constraints.push(is_script_read_complete.clone() * (current_stack_item - prev_stack_item));
@@ -293,43 +1054,166 @@ impl<F: Field> ExecutionChip<F> {
             constraints
         });
 
-        meta.create_gate("Top stack element is true after script is read", |meta| {
+        meta.create_gate("Stack state unchanged in branches which are not taken", |meta| {
             let q_execution = meta.query_selector(q_execution);
-            vec![
-                q_execution
-                * is_stack_top_false.expr()
-                * num_script_bytes_remaining_is_zero.expr()
-            ]
+            // Opcodes, data length bytes and data bytes in a branch which is not taken are read
+            // without changing the stack. This includes OP_IF and OP_NOTIF, which do not pop
+            // their condition in such a branch.
+            let is_skipped_byte = q_execution
+                * (1u8.expr() - num_script_bytes_remaining_is_zero.expr())
+                * (1u8.expr() - is_prev_branch_active.expr());
+
+            let mut constraints = vec![];
+            for i in 0..STACK_DEPTH {
+                let current_stack_item = meta.query_advice(stack[i], Rotation::cur());
+                let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
+                constraints.push(is_skipped_byte.clone() * (current_stack_item - prev_stack_item));
+            }
+            constraints
+        });
+
+        meta.create_gate("Condition stack", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let mut query = |column: Column<Advice>| meta.query_advice(column, Rotation::cur());
+            let is_opcode_if = query(is_opcode_if);
+            let is_opcode_notif = query(is_opcode_notif);
+            let is_opcode_else = query(is_opcode_else);
+            let is_opcode_endif = query(is_opcode_endif);
+
+            // The conditional opcodes change the condition stack even in branches which are not taken
+            let q_boundary = q_execution.clone() * is_opcode_boundary.clone();
+            let is_push_condition = is_opcode_if.clone() + is_opcode_notif.clone();
+            let is_condition_opcode = is_push_condition.clone() + is_opcode_else.clone() + is_opcode_endif.clone();
+
+            let cur_conditions: Vec<Expression<F>> = condition_stack
+                .iter()
+                .map(|c| meta.query_advice(*c, Rotation::cur()))
+                .collect();
+            let prev_conditions: Vec<Expression<F>> = condition_stack
+                .iter()
+                .map(|c| meta.query_advice(*c, Rotation::prev()))
+                .collect();
+            let cur_num_false_conditions = meta.query_advice(num_false_conditions, Rotation::cur());
+            let prev_num_false_conditions = meta.query_advice(num_false_conditions, Rotation::prev());
+
+            let mut constraints = vec![];
+
+            // OP_IF and OP_NOTIF push a new entry. The branch is taken if the enclosing branch is
+            // active and the truthiness of the popped stack top matches the opcode.
+            let is_prev_stack_top_true = 1u8.expr() - is_prev_stack_top_false.expr();
+            let is_taken = is_prev_branch_active.expr()
+                * (is_opcode_if * is_prev_stack_top_true.clone()
+                    + is_opcode_notif * (1u8.expr() - is_prev_stack_top_true));
+            let is_relevant_opcode = q_boundary.clone() * is_push_condition;
+            constraints.push(
+                is_relevant_opcode.clone()
+                * (cur_conditions[0].clone() - BRANCH_NOT_TAKEN.expr() + is_taken.clone())
+            );
+            for i in 1..MAX_CONDITION_DEPTH {
+                constraints.push(is_relevant_opcode.clone() * (cur_conditions[i].clone() - prev_conditions[i-1].clone()));
+            }
+            // Conditions cannot be nested deeper than MAX_CONDITION_DEPTH
+            constraints.push(is_relevant_opcode.clone() * prev_conditions[MAX_CONDITION_DEPTH-1].clone());
+            constraints.push(
+                is_relevant_opcode
+                * (cur_num_false_conditions.clone() - prev_num_false_conditions.clone() - 1u8.expr() + is_taken)
+            );
+
+            // OP_ELSE and OP_ENDIF need an enclosing OP_IF or OP_NOTIF, i.e. the top entry is either
+            // BRANCH_TAKEN or BRANCH_NOT_TAKEN
+            let prev_top = prev_conditions[0].clone();
+            constraints.push(
+                q_boundary.clone()
+                * (is_opcode_else.clone() + is_opcode_endif.clone())
+                * (prev_top.clone() - BRANCH_TAKEN.expr())
+                * (prev_top.clone() - BRANCH_NOT_TAKEN.expr())
+            );
+
+            // OP_ELSE toggles the top entry
+            let is_relevant_opcode = q_boundary.clone() * is_opcode_else;
+            constraints.push(
+                is_relevant_opcode.clone()
+                * (cur_conditions[0].clone() + prev_top.clone() - (BRANCH_TAKEN + BRANCH_NOT_TAKEN).expr())
+            );
+            for i in 1..MAX_CONDITION_DEPTH {
+                constraints.push(is_relevant_opcode.clone() * (cur_conditions[i].clone() - prev_conditions[i].clone()));
+            }
+            // The number of false conditions is incremented if the top entry was BRANCH_TAKEN and
+            // decremented if it was BRANCH_NOT_TAKEN
+            constraints.push(
+                is_relevant_opcode
+                * (cur_num_false_conditions.clone() - prev_num_false_conditions.clone()
+                    - (BRANCH_TAKEN + BRANCH_NOT_TAKEN).expr() + 2u8.expr() * prev_top.clone())
+            );
+
+            // OP_ENDIF pops the top entry
+            let is_relevant_opcode = q_boundary.clone() * is_opcode_endif;
+            for i in 1..MAX_CONDITION_DEPTH {
+                constraints.push(is_relevant_opcode.clone() * (cur_conditions[i-1].clone() - prev_conditions[i].clone()));
+            }
+            constraints.push(is_relevant_opcode.clone() * cur_conditions[MAX_CONDITION_DEPTH-1].clone());
+            constraints.push(
+                is_relevant_opcode
+                * (cur_num_false_conditions.clone() - prev_num_false_conditions.clone()
+                    + prev_top - BRANCH_TAKEN.expr())
+            );
+
+            // Other bytes leave the condition stack unchanged
+            let is_not_condition_opcode = q_execution.clone() * (1u8.expr() - is_opcode_boundary.clone() * is_condition_opcode);
+            for i in 0..MAX_CONDITION_DEPTH {
+                constraints.push(is_not_condition_opcode.clone() * (cur_conditions[i].clone() - prev_conditions[i].clone()));
+            }
+            constraints.push(is_not_condition_opcode * (cur_num_false_conditions - prev_num_false_conditions));
+
+            // Every OP_IF and OP_NOTIF has a matching OP_ENDIF. As the used entries are a prefix of
+            // the condition stack, it is empty iff the top entry is unused.
+            constraints.push(q_execution * num_script_bytes_remaining_is_zero.expr() * cur_conditions[0].clone());
+            constraints
+        });
+
+        // The stack is unchanged once the script is read, so it suffices to check the last row
+        // with q_execution enabled. It holds the final state even for a script of
+        // MAX_SCRIPT_PUBKEY_SIZE bytes.
+        meta.create_gate("Top stack element is true after script is read", |meta| {
+            let q_script_end = meta.query_selector(q_script_end);
+            vec![q_script_end * is_stack_top_false.expr()]
         });
 
         meta.create_gate("Only supported opcodes allowed", |meta| {
             let q_execution = meta.query_selector(q_execution);
             let is_opcode_enabled = meta.query_advice(is_opcode_enabled, Rotation::cur());
-            let is_current_byte_an_opcode = q_execution
-                * (1u8.expr() - num_script_bytes_remaining_is_zero.expr())
-                * num_data_bytes_remaining_is_zero.expr()
-                * num_data_length_bytes_remaining_is_zero.expr();
+            let is_current_byte_an_opcode = q_execution * is_opcode_boundary.clone();
 
             vec![is_current_byte_an_opcode * (1u8.expr() - is_opcode_enabled)]
         });
 
+        // Disabled opcodes fail the script wherever they appear, also in branches which are not
+        // taken. They are not enabled either, but are rejected on their own to match Bitcoin.
+        meta.create_gate("Disabled opcodes", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let is_opcode_disabled = meta.query_advice(is_opcode_disabled, Rotation::cur());
+            let is_current_byte_an_opcode = q_execution * is_opcode_boundary.clone();
+
+            vec![is_current_byte_an_opcode * is_opcode_disabled]
+        });
+
         meta.create_gate("OP_1 to OP_16", |meta| {
             let q_execution = meta.query_selector(q_execution);
             let is_opcode_op1_to_op16 = meta.query_advice(is_opcode_op1_to_op16, Rotation::cur());
             let is_relevant_opcode = q_execution 
-                * (1u8.expr() - num_script_bytes_remaining_is_zero.expr())
-                * is_opcode_op1_to_op16
-                * num_data_bytes_remaining_is_zero.expr()
-                * num_data_length_bytes_remaining_is_zero.expr();
+                * is_executed_opcode.clone()
+                * is_opcode_op1_to_op16;
 
             let opcode = meta.query_advice(opcode, Rotation::cur());
-            // OP_1 has code 81, OP_2 has code 82, and so on
+            // OP_1 has code 81, OP_2 has code 82, and so on. The pushed value N equals the RLC
+            // of the one byte CScriptNum encoding of N, so OP_N and a data push of that byte
+            // leave the same stack item
             let value_to_push = opcode - 80_u8.expr(); 
             let stack_top = meta.query_advice(stack[0], Rotation::cur());
             let mut constraints = vec![is_relevant_opcode.clone() * (stack_top - value_to_push)];
             
             // Check that the stack items to are shifted to the right
-            for i in 1..MAX_STACK_DEPTH {
+            for i in 1..STACK_DEPTH {
                 let current_stack_item = meta.query_advice(stack[i], Rotation::cur());
                 let prev_stack_item  = meta.query_advice(stack[i-1], Rotation::prev());
                 constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
@@ -341,10 +1225,8 @@ impl<F: Field> ExecutionChip<F> {
             let q_execution = meta.query_selector(q_execution);
             let is_opcode_op0 = meta.query_advice(is_opcode_op0, Rotation::cur());
             let is_relevant_opcode = q_execution 
-                * (1u8.expr() - num_script_bytes_remaining_is_zero.expr())
-                * is_opcode_op0
-                * num_data_bytes_remaining_is_zero.expr()
-                * num_data_length_bytes_remaining_is_zero.expr();
+                * is_executed_opcode.clone()
+                * is_opcode_op0;
 
             // OP_0 pushes an empty array of bytes onto the stack in Bitcoin. The empty array evaluates to false.
             // So we represent the empty array by the negative zero.
@@ -353,7 +1235,7 @@ impl<F: Field> ExecutionChip<F> {
             let mut constraints = vec![is_relevant_opcode.clone() * (stack_top - value_to_push)];
             
             // Check that the stack items to are shifted to the right
-            for i in 1..MAX_STACK_DEPTH {
+            for i in 1..STACK_DEPTH {
                 let current_stack_item = meta.query_advice(stack[i], Rotation::cur());
                 let prev_stack_item  = meta.query_advice(stack[i-1], Rotation::prev());
                 constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
@@ -364,23 +1246,25 @@ impl<F: Field> ExecutionChip<F> {
         meta.create_gate("PUSH1 to PUSH75", |meta| {
             let q_execution = meta.query_selector(q_execution);
             let is_opcode_push1_to_push75 = meta.query_advice(is_opcode_push1_to_push75, Rotation::cur());
-            let is_relevant_opcode = q_execution 
-                * (1u8.expr() - num_script_bytes_remaining_is_zero.expr())
-                * is_opcode_push1_to_push75
-                * num_data_bytes_remaining_is_zero.expr()
-                * num_data_length_bytes_remaining_is_zero.expr();
+            let is_relevant_opcode = q_execution.clone()
+                * is_opcode_boundary.clone()
+                * is_opcode_push1_to_push75.clone();
 
             let opcode = meta.query_advice(opcode, Rotation::cur());
             let next_num_data_bytes_remaining = meta.query_advice(num_data_bytes_remaining, Rotation::next());
-            // Number of bytes to push onto the stack equals the opcode value for opcodes 1 to 75
-            let mut constraints = vec![is_relevant_opcode.clone() * (next_num_data_bytes_remaining - opcode)];
+            // Number of bytes to push onto the stack equals the opcode value for opcodes 1 to 75.
+            // The data bytes are also read in branches which are not taken.
+            let mut constraints = vec![is_relevant_opcode * (next_num_data_bytes_remaining - opcode)];
 
+            let is_relevant_opcode = q_execution
+                * is_executed_opcode.clone()
+                * is_opcode_push1_to_push75;
             let stack_top = meta.query_advice(stack[0], Rotation::cur());
             // Check that stack_top is zero
             constraints.push(is_relevant_opcode.clone() * stack_top);
 
             // Check that the stack items to are shifted to the right
-            for i in 1..MAX_STACK_DEPTH {
+            for i in 1..STACK_DEPTH {
                 let current_stack_item = meta.query_advice(stack[i], Rotation::cur());
                 let prev_stack_item  = meta.query_advice(stack[i-1], Rotation::prev());
                 constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
@@ -394,11 +1278,9 @@ impl<F: Field> ExecutionChip<F> {
                     let q_execution = meta.query_selector(q_execution);
                     let data_len = $data_len;
                     let is_opcode_pushdata = meta.query_advice($is_opcode_pushdata_col, Rotation::cur());
-                    let is_relevant_opcode = q_execution 
-                        * (1u8.expr() - num_script_bytes_remaining_is_zero.expr())
-                        * is_opcode_pushdata
-                        * num_data_bytes_remaining_is_zero.expr()
-                        * num_data_length_bytes_remaining_is_zero.expr();
+                    let is_relevant_opcode = q_execution.clone()
+                        * is_opcode_boundary.clone()
+                        * is_opcode_pushdata.clone();
                     
                     let next_num_data_length_bytes_remaining: Expression<F> = meta.query_advice(num_data_length_bytes_remaining, Rotation::next());
                     // Place length of data in the next row of num_data_length_bytes_remaining
@@ -412,10 +1294,18 @@ impl<F: Field> ExecutionChip<F> {
                     // Check that num_data_bytes_remaining is zero
                     constraints.push(is_relevant_opcode.clone() * current_num_data_bytes_remaining);
 
-                    // Check that the stack items remain the same
-                    for i in 0..MAX_STACK_DEPTH {
+                    // The data length and data bytes are also read in branches which are not taken
+                    let is_relevant_opcode = q_execution
+                        * is_executed_opcode.clone()
+                        * is_opcode_pushdata;
+                    let stack_top = meta.query_advice(stack[0], Rotation::cur());
+                    // Check that stack_top is zero. The data bytes are accumulated into it.
+                    constraints.push(is_relevant_opcode.clone() * stack_top);
+
+                    // Check that the stack items to are shifted to the right
+                    for i in 1..STACK_DEPTH {
                         let current_stack_item = meta.query_advice(stack[i], Rotation::cur());
-                        let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
+                        let prev_stack_item  = meta.query_advice(stack[i-1], Rotation::prev());
                         constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
                     }
                     constraints
@@ -428,6 +1318,36 @@ impl<F: Field> ExecutionChip<F> {
         create_pushdata_gate!("PUSHDATA2", is_opcode_pushdata2, 2u8);
         create_pushdata_gate!("PUSHDATA4", is_opcode_pushdata4, 4u8);
 
+        // Data pushes must use the shortest encoding. The data bytes and data length bytes
+        // following an opcode are looked up in the opcode table like opcodes, so their
+        // indicator columns classify their values.
+        if require_minimal_push {
+            meta.create_gate("Minimal data push", |meta| {
+                let q_execution = meta.query_selector(q_execution);
+                let is_relevant_opcode = q_execution * is_executed_opcode.clone();
+                let is_opcode_push1 = meta.query_advice(is_opcode_push1, Rotation::cur());
+                let is_opcode_pushdata1 = meta.query_advice(is_opcode_pushdata1, Rotation::cur());
+                let is_opcode_pushdata2 = meta.query_advice(is_opcode_pushdata2, Rotation::cur());
+                let is_opcode_pushdata4 = meta.query_advice(is_opcode_pushdata4, Rotation::cur());
+                let is_next_byte_1_to_16 = meta.query_advice(is_opcode_push1_to_push16, Rotation::next());
+                let is_next_byte_zero = meta.query_advice(is_opcode_op0, Rotation::next());
+                let is_next_byte_1_to_75 = meta.query_advice(is_opcode_push1_to_push75, Rotation::next());
+                let is_byte_after_next_zero = meta.query_advice(is_opcode_op0, Rotation(2));
+
+                vec![
+                    // The byte arrays [1] to [16] and [0x81] are pushed by OP_1 to OP_16 and OP_1NEGATE
+                    is_relevant_opcode.clone() * is_opcode_push1
+                        * (is_next_byte_1_to_16 + is_next_byte_negative_one.expr()),
+                    // Up to 75 bytes are pushed by PUSH1 to PUSH75
+                    is_relevant_opcode.clone() * is_opcode_pushdata1 * (is_next_byte_zero + is_next_byte_1_to_75),
+                    // Up to 255 bytes are pushed by PUSHDATA1, so the high length byte is non-zero
+                    is_relevant_opcode.clone() * is_opcode_pushdata2 * is_byte_after_next_zero,
+                    // PUSHDATA4 is only minimal for more than 65535 bytes, which exceeds MAX_SCRIPT_PUBKEY_SIZE
+                    is_relevant_opcode * is_opcode_pushdata4,
+                ]
+            });
+        }
+
         meta.create_gate("Accumulate data byte in stack top", |meta| {
             let q_execution = meta.query_selector(q_execution);
             let randomness = meta.query_advice(randomness, Rotation::cur());
@@ -435,17 +1355,19 @@ impl<F: Field> ExecutionChip<F> {
                 * (1u8.expr() - num_script_bytes_remaining_is_zero.expr())
                 * (1u8.expr() - num_data_bytes_remaining_is_zero.expr())
                 * num_data_length_bytes_remaining_is_zero.expr();
+            // Data bytes in a branch which is not taken are read without pushing them
+            let data_push_executed = data_push_in_progress.clone() * is_prev_branch_active.expr();
             let data_byte = meta.query_advice(opcode, Rotation::cur());
             let stack_top = meta.query_advice(stack[0], Rotation::cur());
             let prev_stack_top = meta.query_advice(stack[0], Rotation::prev());
             // Check that the data byte has been accumulated into stack_top
-            let mut constraints = vec![data_push_in_progress.clone() * (data_byte + randomness.clone() * prev_stack_top - stack_top)];
+            let mut constraints = vec![data_push_executed.clone() * (data_byte + randomness.clone() * prev_stack_top - stack_top)];
             
             // Check that the non-top stack items remain the same
-            for i in 1..MAX_STACK_DEPTH {
+            for i in 1..STACK_DEPTH {
                 let current_stack_item = meta.query_advice(stack[i], Rotation::cur());
                 let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
-                constraints.push(data_push_in_progress.clone() * (current_stack_item - prev_stack_item));
+                constraints.push(data_push_executed.clone() * (current_stack_item - prev_stack_item));
             }
 
             let current_num_bytes_remaining = meta.query_advice(num_data_bytes_remaining, Rotation::cur());
@@ -474,7 +1396,7 @@ impl<F: Field> ExecutionChip<F> {
             ];
             
             // Check that the stack items remain the same
-            for i in 0..MAX_STACK_DEPTH {
+            for i in 0..STACK_DEPTH {
                 let current_stack_item = meta.query_advice(stack[i], Rotation::cur());
                 let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
                 constraints.push(data_length_push_in_progress.clone() * (current_stack_item - prev_stack_item));
@@ -487,34 +1409,46 @@ impl<F: Field> ExecutionChip<F> {
 
             let current_num_data_length_acc_constant = meta.query_advice(num_data_length_acc_constant, Rotation::cur());
             let prev_num_data_length_acc_constant = meta.query_advice(num_data_length_acc_constant, Rotation::prev());
-            // Check that num_data_length_acc_constant is multiplied by 256
+            let prev_num_data_length_bytes_remaining = meta.query_advice(num_data_length_bytes_remaining, Rotation::prev());
+            // Check that num_data_length_acc_constant is multiplied by 256. The constraint applies to all data length
+            // bytes except the first one, where num_data_length_acc_constant is set to 1 by the PUSHDATA gates.
+            // For the first byte the previous num_data_length_bytes_remaining is zero and for the others it is
+            // at least two, so the acc_constant cannot be reset to 1 in the middle of a data length
             constraints.push(
                 data_length_push_in_progress.clone()
-                * (1u8.expr() - current_num_data_length_acc_constant.clone())  // Constraint applies when the acc_constant is not 1
+                * prev_num_data_length_bytes_remaining
                 * (prev_num_data_length_acc_constant * 256u64.expr() - current_num_data_length_acc_constant)
             );
 
             // If the current byte is the last data length byte, ensure that the current value of num_data_bytes_remaining is
             // non-zero and equal to next value.
-            // The reason for checking the non-zero condition is to prevent OP_PUSHDATA opcodes with zero length
-            constraints.push(
-                data_length_push_in_progress
-                * num_data_length_bytes_remaining_is_one.expr()
-                * (1u8.expr() - num_data_bytes_remaining_is_zero.expr())
-                * (current_data_length - next_data_length)
-            );
+            // OP_PUSHDATA opcodes with zero length are rejected, as the empty array is pushed by OP_0. A zero length would
+            // also leave the num_data_bytes_remaining of the next row unconstrained.
+            let is_last_data_length_byte = data_length_push_in_progress * num_data_length_bytes_remaining_is_one.expr();
+            constraints.push(is_last_data_length_byte.clone() * num_data_bytes_remaining_is_zero.expr());
+            constraints.push(is_last_data_length_byte * (current_data_length - next_data_length));
             constraints
         });
 
         meta.create_gate("OP_CHECKSIG", |meta| {
             let q_execution = meta.query_selector(q_execution);
             let is_opcode_checksig = meta.query_advice(is_opcode_checksig, Rotation::cur());
-            let is_cur_byte_checksig = (1u8.expr() - num_script_bytes_remaining_is_zero.expr())
-                * is_opcode_checksig.clone()
-                * num_data_bytes_remaining_is_zero.expr()
-                * num_data_length_bytes_remaining_is_zero.expr();
+            let is_opcode_checksigverify = meta.query_advice(is_opcode_checksigverify, Rotation::cur());
+            let is_opcode_checkmultisig = meta.query_advice(is_opcode_checkmultisig, Rotation::cur());
+            let is_opcode_checksigadd = meta.query_advice(is_opcode_checksigadd, Rotation::cur());
+            // OP_CHECKSIGVERIFY shares the constraints of OP_CHECKSIG except for the result, which is
+            // constrained in its own gate
+            let is_cur_byte_checksig = is_executed_opcode.clone()
+                * (is_opcode_checksig.clone() + is_opcode_checksigverify.clone());
             let is_relevant_opcode = q_execution.clone() * is_cur_byte_checksig.clone();
-            let is_cur_byte_not_checksig = q_execution * (1u8.expr() - is_cur_byte_checksig);
+            // The public key accumulator and counters of OP_CHECKMULTISIG and OP_CHECKSIGADD are
+            // constrained in their gates
+            let is_cur_byte_not_checksig = q_execution.clone()
+                * (1u8.expr() - is_executed_opcode.clone()
+                    * (is_opcode_checksig.clone()
+                        + is_opcode_checksigverify
+                        + is_opcode_checkmultisig
+                        + is_opcode_checksigadd));
 
             // The second stack item must have the signature when OP_CHECKSIG is evaluated
             let sig_item = meta.query_advice(stack[1], Rotation::prev());
@@ -538,813 +1472,6695 @@ impl<F: Field> ExecutionChip<F> {
             constraints.push(
                 is_relevant_opcode.clone()
                 * sig_item.clone()
-                * (prev_pk_rlc_acc * randomness + pk_item - cur_pk_rlc_acc) 
+                * (prev_pk_rlc_acc.clone() * randomness + pk_item - cur_pk_rlc_acc.clone()) 
+            );
+            // If sig_item is zero, then the pk_item is not accumulated as no signature is verified
+            constraints.push(
+                is_relevant_opcode.clone()
+                * (1u8.expr() - sig_item.clone())
+                * (prev_pk_rlc_acc - cur_pk_rlc_acc) 
             );
             
             let prev_num_checksig_opcodes = meta.query_advice(num_checksig_opcodes, Rotation::prev());
             let cur_num_checksig_opcodes = meta.query_advice(num_checksig_opcodes, Rotation::cur());
             // If the current opcode is not a OP_CHECKSIG, then the number of checksig opcodes is unchanged
             constraints.push(
-                is_cur_byte_not_checksig
+                is_cur_byte_not_checksig.clone()
                 * (prev_num_checksig_opcodes.clone() - cur_num_checksig_opcodes.clone()) 
             );
-            // If sig_item is non-zero, then the number of checksig opcodes is incremented
+            // Every OP_CHECKSIG increments the number of checksig opcodes
             constraints.push(
                 is_relevant_opcode.clone()
-                * sig_item.clone()
                 * (prev_num_checksig_opcodes + 1u8.expr() - cur_num_checksig_opcodes) 
             );
+
+            let prev_num_verified_checksigs = meta.query_advice(num_verified_checksigs, Rotation::prev());
+            let cur_num_verified_checksigs = meta.query_advice(num_verified_checksigs, Rotation::cur());
+            // If the current opcode is not a OP_CHECKSIG, then the number of verified signatures is unchanged
+            constraints.push(
+                is_cur_byte_not_checksig
+                * (prev_num_verified_checksigs.clone() - cur_num_verified_checksigs.clone()) 
+            );
+            // The number of verified signatures is incremented by the sig_item value, which is 0 or 1
+            constraints.push(
+                is_relevant_opcode.clone()
+                * (prev_num_verified_checksigs + sig_item.clone() - cur_num_verified_checksigs) 
+            );
             
             // The first item in the current stack is forced to be equal to the sig_item value
             // Our convention is the valid signature is indicated by sig_item = 1
+            let is_relevant_opcode = q_execution
+                * is_executed_opcode.clone()
+                * is_opcode_checksig;
             let cur_stack_top = meta.query_advice(stack[0], Rotation::cur());
             constraints.push(
                 is_relevant_opcode.clone()
                 * (cur_stack_top - sig_item)
             );
 
-            // Check that the stack items at indices 2 to MAX_STACK_DEPTH-1 to are shifted to the left
-            for i in 2..MAX_STACK_DEPTH {
+            // Check that the stack items at indices 2 to STACK_DEPTH-1 to are shifted to the left
+            for i in 2..STACK_DEPTH {
                 let current_stack_item = meta.query_advice(stack[i-1], Rotation::cur());
                 let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
                 constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
             }
-            let cur_stack_bottom = meta.query_advice(stack[MAX_STACK_DEPTH-1], Rotation::cur());
+            let cur_stack_bottom = meta.query_advice(stack[STACK_DEPTH-1], Rotation::cur());
             // The last item in the current stack is forced to be zero
             constraints.push(is_relevant_opcode.clone() * cur_stack_bottom);
             constraints
         });
 
-        ExecutionConfig {
-            instance,
-            randomness,
-            q_first,
-            q_execution,
-            opcode,
-            opcode_table,
-            is_opcode_enabled,
-            is_opcode_op0,
-            is_opcode_op1_to_op16,
-            is_opcode_push1_to_push75,
-            is_opcode_pushdata1,
-            is_opcode_pushdata2,
-            is_opcode_pushdata4,
-            is_opcode_checksig,
-            script_rlc_acc,
-            num_script_bytes_remaining,
-            num_script_bytes_remaining_inv,
-            num_script_bytes_remaining_is_zero,
-            stack,
-            is_stack_top_false_inv,
-            is_stack_top_false,
-            num_data_bytes_remaining,
-            num_data_bytes_remaining_inv,
-            num_data_bytes_remaining_is_zero,
-            num_data_length_bytes_remaining,
-            num_data_length_bytes_remaining_inv,
-            num_data_length_bytes_remaining_is_zero,
-            num_data_length_bytes_remaining_minus_one_inv,
-            num_data_length_bytes_remaining_is_one,
-            num_data_length_acc_constant,
-            pk_rlc_acc,
-            num_checksig_opcodes,
-        }
-    }
+        // OP_CHECKSIGVERIFY is OP_CHECKSIG followed by OP_VERIFY. The public key accumulation and
+        // the counters are constrained in the OP_CHECKSIG gate.
+        meta.create_gate("OP_CHECKSIGVERIFY", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let is_opcode_checksigverify = meta.query_advice(is_opcode_checksigverify, Rotation::cur());
+            let is_relevant_opcode = q_execution
+                * is_executed_opcode.clone()
+                * is_opcode_checksigverify;
 
-    pub(crate) fn assign_script_pubkey_unroll(
-        &self,
-        config: ExecutionConfig<F>,
-        layouter: &mut impl Layouter<F>,
-        script_pubkey: Vec<u8>,
-        randomness: F,
-        initial_stack: [F; MAX_STACK_DEPTH],
-    ) -> Result<ExecutionChipAssignedCells<F>, Error> {
-        assert!(script_pubkey.len() <= MAX_SCRIPT_PUBKEY_SIZE);
+            // The signature must be valid
+            let sig_item = meta.query_advice(stack[1], Rotation::prev());
+            let mut constraints = vec![is_relevant_opcode.clone() * (1u8.expr() - sig_item)];
 
-        OpcodeTableChip::load(config.opcode_table.clone(), layouter)?;
+            // The public key and the signature are popped
+            for i in 2..STACK_DEPTH {
+                let current_stack_item = meta.query_advice(stack[i-2], Rotation::cur());
+                let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
+                constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+            }
+            for i in STACK_DEPTH-2..STACK_DEPTH {
+                let cur_stack_item = meta.query_advice(stack[i], Rotation::cur());
+                constraints.push(is_relevant_opcode.clone() * cur_stack_item);
+            }
+            constraints
+        });
 
-        layouter.assign_region(
-            || "ScriptPubkey unrolling",
-            |mut region: Region<F>| {
+        // OP_CHECKSIGADD pops the public key, a number n and the signature and pushes n+1 if the
+        // signature is valid and n otherwise. The public key accumulation and the counters are
+        // the same as in OP_CHECKSIG.
+        meta.create_gate("OP_CHECKSIGADD", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let is_opcode_checksigadd = meta.query_advice(is_opcode_checksigadd, Rotation::cur());
+            let is_relevant_opcode = q_execution
+                * is_executed_opcode.clone()
+                * is_opcode_checksigadd;
 
-                config.q_first.enable(&mut region, 0)?;
+            // The third stack item has the signature, which is either 0 or 1
+            let sig_item = meta.query_advice(stack[2], Rotation::prev());
+            let mut constraints = vec![
+                is_relevant_opcode.clone() * sig_item.clone() * (1u8.expr() - sig_item.clone())
+            ];
 
-                macro_rules! assign_first_row {
-                    ($annotation:expr, $column:ident, $val:expr) => {
-                        region.assign_advice(
-                            || $annotation,
-                            config.$column,
-                            0,
-                            || Value::known($val),
-                        )?
-                    };
-                    ($annotation:expr, $column:ident) => {
-                        region.assign_advice(
-                            || $annotation,
-                            config.$column,
-                            0,
-                            || Value::known(F::zero()),
-                        )?
-                    };
+            // The public key in the first stack item is accumulated if the signature is valid
+            let pk_item = meta.query_advice(stack[0], Rotation::prev());
+            let prev_pk_rlc_acc = meta.query_advice(pk_rlc_acc, Rotation::prev());
+            let cur_pk_rlc_acc = meta.query_advice(pk_rlc_acc, Rotation::cur());
+            let randomness = meta.query_advice(randomness, Rotation::cur());
+            constraints.push(
+                is_relevant_opcode.clone()
+                * sig_item.clone()
+                * (prev_pk_rlc_acc.clone() * randomness + pk_item - cur_pk_rlc_acc.clone())
+            );
+            constraints.push(
+                is_relevant_opcode.clone()
+                * (1u8.expr() - sig_item.clone())
+                * (prev_pk_rlc_acc - cur_pk_rlc_acc)
+            );
+
+            let prev_num_checksig_opcodes = meta.query_advice(num_checksig_opcodes, Rotation::prev());
+            let cur_num_checksig_opcodes = meta.query_advice(num_checksig_opcodes, Rotation::cur());
+            constraints.push(
+                is_relevant_opcode.clone()
+                * (prev_num_checksig_opcodes + 1u8.expr() - cur_num_checksig_opcodes)
+            );
+            let prev_num_verified_checksigs = meta.query_advice(num_verified_checksigs, Rotation::prev());
+            let cur_num_verified_checksigs = meta.query_advice(num_verified_checksigs, Rotation::cur());
+            constraints.push(
+                is_relevant_opcode.clone()
+                * (prev_num_verified_checksigs + sig_item.clone() - cur_num_verified_checksigs)
+            );
+
+            // n is decoded from the second stack item and the sum is decoded from the result
+            for column in [second_num.enabled, result_num.enabled] {
+                let enabled = meta.query_advice(column, Rotation::cur());
+                constraints.push(is_relevant_opcode.clone() * (1u8.expr() - enabled));
+            }
+            constraints.push(is_relevant_opcode.clone() * (result_num.expr() - second_num.expr() - sig_item));
+
+            // The three operands are replaced by the result
+            for i in 3..STACK_DEPTH {
+                let current_stack_item = meta.query_advice(stack[i-2], Rotation::cur());
+                let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
+                constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+            }
+            for i in STACK_DEPTH-2..STACK_DEPTH {
+                let cur_stack_item = meta.query_advice(stack[i], Rotation::cur());
+                constraints.push(is_relevant_opcode.clone() * cur_stack_item);
+            }
+            constraints
+        });
+
+        // OP_CHECKMULTISIG pops n, the n public keys, m, the m signatures and an extra item which
+        // Bitcoin pops due to an off-by-one error. If all signatures are valid, m of the public keys
+        // are accumulated in pk_rlc_acc and each of them needs an ECDSA verification.
+        meta.create_gate("OP_CHECKMULTISIG", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let is_opcode_checkmultisig = meta.query_advice(is_opcode_checkmultisig, Rotation::cur());
+            let is_relevant_opcode = q_execution
+                * is_executed_opcode.clone()
+                * is_opcode_checkmultisig;
+
+            let num_keys = multisig_num_keys.map(|c| meta.query_advice(c, Rotation::cur()));
+            let num_sigs = multisig_num_sigs.map(|c| meta.query_advice(c, Rotation::cur()));
+            let shift = multisig_shift.map(|c| meta.query_advice(c, Rotation::cur()));
+            let sigs = multisig_sigs.map(|c| meta.query_advice(c, Rotation::cur()));
+            let is_key_signed = is_multisig_key_signed.map(|c| meta.query_advice(c, Rotation::cur()));
+            let pk_rlc_accs = multisig_pk_rlc_acc.map(|c| meta.query_advice(c, Rotation::cur()));
+            let prev_stack = stack.map(|c| meta.query_advice(c, Rotation::prev()));
+            let cur_stack = stack.map(|c| meta.query_advice(c, Rotation::cur()));
+
+            let mut constraints = vec![];
+
+            // Exactly one entry of each one-hot encoding is one
+            for one_hot in [&num_keys[..], &num_sigs[..], &shift[..]] {
+                let mut sum = 0u8.expr();
+                for bit in one_hot.iter() {
+                    constraints.push(is_relevant_opcode.clone() * bit.clone() * (1u8.expr() - bit.clone()));
+                    sum = sum + bit.clone();
+                }
+                constraints.push(is_relevant_opcode.clone() * (1u8.expr() - sum));
+            }
+            let decode = |one_hot: &[Expression<F>], first_value: u64| {
+                one_hot
+                    .iter()
+                    .enumerate()
+                    .fold(0u8.expr(), |acc, (i, bit)| acc + bit.clone() * (i as u64 + first_value).expr())
+            };
+            // Sum of the items at prev_stack[i + offset] weighted by the one-hot encoding of n, i.e.
+            // the item at prev_stack[n - 1 + offset]
+            let item_after_keys = |offset: usize| {
+                num_keys
+                    .iter()
+                    .enumerate()
+                    .fold(0u8.expr(), |acc, (i, bit)| acc + bit.clone() * prev_stack[i + offset].clone())
+            };
+            let n = decode(&num_keys[..], 1);
+            let m = decode(&num_sigs[..], 1);
+
+            // The stack top is n and m follows the public keys
+            constraints.push(is_relevant_opcode.clone() * (prev_stack[0].clone() - n.clone()));
+            constraints.push(is_relevant_opcode.clone() * (item_after_keys(2) - m.clone()));
+            // m is at most n
+            for i in 0..MAX_MULTISIG_N {
+                for j in i+1..MAX_MULTISIG_N {
+                    constraints.push(is_relevant_opcode.clone() * num_keys[i].clone() * num_sigs[j].clone());
                 }
+            }
+            constraints.push(is_relevant_opcode.clone() * (decode(&shift[..], 4) - n.clone() - m.clone() - 2u8.expr()));
+
+            // The signatures follow m. Signature values are either 0 or 1 like in OP_CHECKSIG.
+            for k in 0..MAX_MULTISIG_N {
+                let is_sig_used = num_sigs[k..].iter().fold(0u8.expr(), |acc, bit| acc + bit.clone());
+                constraints.push(
+                    is_relevant_opcode.clone()
+                    * is_sig_used.clone()
+                    * (sigs[k].clone() - item_after_keys(3 + k))
+                );
+                constraints.push(is_relevant_opcode.clone() * (1u8.expr() - is_sig_used) * sigs[k].clone());
+                constraints.push(is_relevant_opcode.clone() * sigs[k].clone() * (1u8.expr() - sigs[k].clone()));
+            }
 
-                let script_length_cell = assign_first_row!(
-                    "Byte length of scriptPubkey",
-                    num_script_bytes_remaining,
-                    F::from(script_pubkey.len() as u64)
+            // Only the n public keys can be signed. If all signatures are valid, m public keys are
+            // signed and otherwise none.
+            let mut num_signed_keys = 0u8.expr();
+            for i in 0..MAX_MULTISIG_N {
+                let is_key_present = num_keys[i..].iter().fold(0u8.expr(), |acc, bit| acc + bit.clone());
+                constraints.push(
+                    is_relevant_opcode.clone()
+                    * is_key_signed[i].clone()
+                    * (1u8.expr() - is_key_signed[i].clone())
+                );
+                constraints.push(is_relevant_opcode.clone() * (1u8.expr() - is_key_present) * is_key_signed[i].clone());
+                num_signed_keys = num_signed_keys + is_key_signed[i].clone();
+            }
+            constraints.push(
+                is_relevant_opcode.clone()
+                * (num_signed_keys.clone() - are_multisig_sigs_valid.expr() * m)
+            );
+
+            // The signed public keys are accumulated in the order they were pushed
+            let randomness = meta.query_advice(randomness, Rotation::cur());
+            let mut acc = meta.query_advice(pk_rlc_acc, Rotation::prev());
+            for i in (0..MAX_MULTISIG_N).rev() {
+                let pk_item = prev_stack[i + 1].clone();
+                constraints.push(
+                    is_relevant_opcode.clone()
+                    * (pk_rlc_accs[i].clone() - acc.clone()
+                        - is_key_signed[i].clone() * (acc * (randomness.clone() - 1u8.expr()) + pk_item))
                 );
+                acc = pk_rlc_accs[i].clone();
+            }
+            let cur_pk_rlc_acc = meta.query_advice(pk_rlc_acc, Rotation::cur());
+            constraints.push(is_relevant_opcode.clone() * (cur_pk_rlc_acc - acc));
+
+            // Each public key counts as a checksig opcode and each signed key needs a verification
+            let prev_num_checksig_opcodes = meta.query_advice(num_checksig_opcodes, Rotation::prev());
+            let cur_num_checksig_opcodes = meta.query_advice(num_checksig_opcodes, Rotation::cur());
+            constraints.push(is_relevant_opcode.clone() * (prev_num_checksig_opcodes + n - cur_num_checksig_opcodes));
+            let prev_num_verified_checksigs = meta.query_advice(num_verified_checksigs, Rotation::prev());
+            let cur_num_verified_checksigs = meta.query_advice(num_verified_checksigs, Rotation::cur());
+            constraints.push(
+                is_relevant_opcode.clone()
+                * (prev_num_verified_checksigs + num_signed_keys - cur_num_verified_checksigs)
+            );
+
+            // The result is one if all signatures are valid and zero otherwise, like in OP_CHECKSIG
+            constraints.push(is_relevant_opcode.clone() * (cur_stack[0].clone() - are_multisig_sigs_valid.expr()));
+            // The remaining stack items move up by n+m+2
+            for (i, bit) in shift.iter().enumerate() {
+                let num_moved = i + 4;
+                for k in 1..STACK_DEPTH {
+                    let moved_item = if k + num_moved < STACK_DEPTH {
+                        prev_stack[k + num_moved].clone()
+                    } else {
+                        0u8.expr()
+                    };
+                    constraints.push(is_relevant_opcode.clone() * bit.clone() * (cur_stack[k].clone() - moved_item));
+                }
+            }
+            constraints
+        });
+
+        // Stack items are compared by their RLC values. As the RLC of a byte vector does not
+        // depend on its leading zero bytes, vectors which differ only in leading zero bytes
+        // are considered equal.
+        meta.create_gate("OP_EQUAL", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let is_opcode_equal = meta.query_advice(is_opcode_equal, Rotation::cur());
+            let is_relevant_opcode = q_execution
+                * is_executed_opcode.clone()
+                * is_opcode_equal;
+
+            // The result is 1 if the top two items are equal and the empty array otherwise
+            let value_to_push = are_top_two_equal.expr()
+                + (1u8.expr() - are_top_two_equal.expr()) * EMPTY_ARRAY_REPRESENTATION.expr();
+            let cur_stack_top = meta.query_advice(stack[0], Rotation::cur());
+            let mut constraints = vec![is_relevant_opcode.clone() * (cur_stack_top - value_to_push)];
+
+            // Check that the stack items at indices 2 to STACK_DEPTH-1 to are shifted to the left
+            for i in 2..STACK_DEPTH {
+                let current_stack_item = meta.query_advice(stack[i-1], Rotation::cur());
+                let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
+                constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+            }
+            let cur_stack_bottom = meta.query_advice(stack[STACK_DEPTH-1], Rotation::cur());
+            // The last item in the current stack is forced to be zero
+            constraints.push(is_relevant_opcode * cur_stack_bottom);
+            constraints
+        });
+
+        meta.create_gate("OP_EQUALVERIFY", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let is_opcode_equalverify = meta.query_advice(is_opcode_equalverify, Rotation::cur());
+            let is_relevant_opcode = q_execution
+                * is_executed_opcode.clone()
+                * is_opcode_equalverify;
+
+            // Script execution fails unless the top two items are equal
+            let mut constraints = vec![is_relevant_opcode.clone() * (1u8.expr() - are_top_two_equal.expr())];
+
+            // Check that the stack items at indices 2 to STACK_DEPTH-1 are shifted two places to the left
+            for i in 2..STACK_DEPTH {
+                let current_stack_item = meta.query_advice(stack[i-2], Rotation::cur());
+                let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
+                constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+            }
+            // The last two items in the current stack are forced to be zero
+            for i in STACK_DEPTH-2..STACK_DEPTH {
+                let cur_stack_item = meta.query_advice(stack[i], Rotation::cur());
+                constraints.push(is_relevant_opcode.clone() * cur_stack_item);
+            }
+            constraints
+        });
+
+        meta.create_gate("OP_VERIFY", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let is_opcode_verify = meta.query_advice(is_opcode_verify, Rotation::cur());
+            let is_relevant_opcode = q_execution
+                * is_executed_opcode.clone()
+                * is_opcode_verify;
+
+            // Script execution fails unless the top item is true
+            let mut constraints = vec![is_relevant_opcode.clone() * is_prev_stack_top_false.expr()];
+
+            // Check that the stack items at indices 1 to STACK_DEPTH-1 are shifted to the left
+            for i in 1..STACK_DEPTH {
+                let current_stack_item = meta.query_advice(stack[i-1], Rotation::cur());
+                let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
+                constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+            }
+            let cur_stack_bottom = meta.query_advice(stack[STACK_DEPTH-1], Rotation::cur());
+            // The last item in the current stack is forced to be zero
+            constraints.push(is_relevant_opcode * cur_stack_bottom);
+            constraints
+        });
+
+        macro_rules! create_drop_gate {
+            ($annotation:expr, $is_opcode_drop_col:ident, $num_dropped:expr) => {
+                meta.create_gate($annotation, |meta| {
+                    let q_execution = meta.query_selector(q_execution);
+                    let is_opcode_drop = meta.query_advice($is_opcode_drop_col, Rotation::cur());
+                    let is_relevant_opcode = q_execution
+                        * is_executed_opcode.clone()
+                        * is_opcode_drop;
+
+                    let mut constraints = vec![];
+                    // Check that the remaining stack items are shifted to the left
+                    for i in $num_dropped..STACK_DEPTH {
+                        let current_stack_item = meta.query_advice(stack[i-$num_dropped], Rotation::cur());
+                        let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
+                        constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+                    }
+                    // The vacated items at the bottom of the current stack are forced to be zero
+                    for i in STACK_DEPTH-$num_dropped..STACK_DEPTH {
+                        let cur_stack_item = meta.query_advice(stack[i], Rotation::cur());
+                        constraints.push(is_relevant_opcode.clone() * cur_stack_item);
+                    }
+                    constraints
+                });
+            };
+        }
+
+        create_drop_gate!("OP_DROP", is_opcode_drop, 1);
+        create_drop_gate!("OP_2DROP", is_opcode_2drop, 2);
+        // OP_IF and OP_NOTIF pop their condition when they are executed
+        create_drop_gate!("OP_IF", is_opcode_if, 1);
+        create_drop_gate!("OP_NOTIF", is_opcode_notif, 1);
+        // OP_TOALTSTACK pops the item it moves to the alt stack
+        create_drop_gate!("OP_TOALTSTACK", is_opcode_toaltstack, 1);
+
+        meta.create_gate("OP_SWAP", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let is_opcode_swap = meta.query_advice(is_opcode_swap, Rotation::cur());
+            let is_relevant_opcode = q_execution
+                * is_executed_opcode.clone()
+                * is_opcode_swap;
+
+            let prev_stack_top = meta.query_advice(stack[0], Rotation::prev());
+            let prev_stack_second = meta.query_advice(stack[1], Rotation::prev());
+            let cur_stack_top = meta.query_advice(stack[0], Rotation::cur());
+            let cur_stack_second = meta.query_advice(stack[1], Rotation::cur());
+            // Check that the top two stack items are swapped
+            let mut constraints = vec![
+                is_relevant_opcode.clone() * (cur_stack_top - prev_stack_second),
+                is_relevant_opcode.clone() * (cur_stack_second - prev_stack_top),
+            ];
+
+            // Check that the stack items at indices 2 to STACK_DEPTH-1 remain the same
+            for i in 2..STACK_DEPTH {
+                let current_stack_item = meta.query_advice(stack[i], Rotation::cur());
+                let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
+                constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+            }
+            constraints
+        });
+
+        meta.create_gate("OP_OVER", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let is_opcode_over = meta.query_advice(is_opcode_over, Rotation::cur());
+            let is_relevant_opcode = q_execution
+                * is_executed_opcode.clone()
+                * is_opcode_over;
+
+            // The second stack item is copied to the top
+            let prev_stack_second = meta.query_advice(stack[1], Rotation::prev());
+            let cur_stack_top = meta.query_advice(stack[0], Rotation::cur());
+            let mut constraints = vec![is_relevant_opcode.clone() * (cur_stack_top - prev_stack_second)];
+
+            // Check that the stack items to are shifted to the right
+            for i in 1..STACK_DEPTH {
+                let current_stack_item = meta.query_advice(stack[i], Rotation::cur());
+                let prev_stack_item  = meta.query_advice(stack[i-1], Rotation::prev());
+                constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+            }
+            constraints
+        });
+
+        meta.create_gate("OP_ROT", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let is_opcode_rot = meta.query_advice(is_opcode_rot, Rotation::cur());
+            let is_relevant_opcode = q_execution
+                * is_executed_opcode.clone()
+                * is_opcode_rot;
+
+            // The third stack item is moved to the top, i.e. (x1 x2 x3) -> (x2 x3 x1) with x3 on top
+            let mut constraints = vec![];
+            for (cur_index, prev_index) in [(0, 2), (1, 0), (2, 1)] {
+                let current_stack_item = meta.query_advice(stack[cur_index], Rotation::cur());
+                let prev_stack_item  = meta.query_advice(stack[prev_index], Rotation::prev());
+                constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+            }
+
+            // Check that the stack items at indices 3 to STACK_DEPTH-1 remain the same
+            for i in 3..STACK_DEPTH {
+                let current_stack_item = meta.query_advice(stack[i], Rotation::cur());
+                let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
+                constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+            }
+            constraints
+        });
+
+        // Opcodes which only copy and reorder the top stack items, as listed by stack_item_moves.
+        // The items below them are shifted by the number of copies and the items shifted past
+        // the bottom of the stack are dropped.
+        macro_rules! create_stack_move_gate {
+            ($annotation:expr, $is_opcode_col:ident, $opcode:expr) => {
+                meta.create_gate($annotation, |meta| {
+                    let q_execution = meta.query_selector(q_execution);
+                    let is_opcode = meta.query_advice($is_opcode_col, Rotation::cur());
+                    let is_relevant_opcode = q_execution
+                        * is_executed_opcode.clone()
+                        * is_opcode;
+
+                    let (num_popped, pushed) = stack_item_moves($opcode);
+                    let mut constraints = vec![];
+                    for i in 0..STACK_DEPTH {
+                        let current_stack_item = meta.query_advice(stack[i], Rotation::cur());
+                        let moved_stack_item = match pushed.get(i) {
+                            Some(Some(index)) => meta.query_advice(stack[*index], Rotation::prev()),
+                            Some(None) => panic!("{} pushes a new stack item", $annotation),
+                            None if i + num_popped - pushed.len() < STACK_DEPTH =>
+                                meta.query_advice(stack[i + num_popped - pushed.len()], Rotation::prev()),
+                            None => 0u8.expr(),
+                        };
+                        constraints.push(is_relevant_opcode.clone() * (current_stack_item - moved_stack_item));
+                    }
+                    constraints
+                });
+            };
+        }
+
+        create_stack_move_gate!("OP_2DUP", is_opcode_2dup, OP_2DUP);
+        create_stack_move_gate!("OP_3DUP", is_opcode_3dup, OP_3DUP);
+        create_stack_move_gate!("OP_2OVER", is_opcode_2over, OP_2OVER);
+        create_stack_move_gate!("OP_2SWAP", is_opcode_2swap, OP_2SWAP);
+        create_stack_move_gate!("OP_2ROT", is_opcode_2rot, OP_2ROT);
+
+        // OP_PICK and OP_ROLL pop the depth n and copy or move the item at index n+1 of the
+        // previous stack to the top. The item is selected by the one-hot encoding of its index.
+        meta.create_gate("OP_PICK and OP_ROLL", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let is_opcode_pick = meta.query_advice(is_opcode_pick, Rotation::cur());
+            let is_opcode_roll = meta.query_advice(is_opcode_roll, Rotation::cur());
+            let is_pick = q_execution.clone() * is_executed_opcode.clone() * is_opcode_pick;
+            let is_roll = q_execution * is_executed_opcode.clone() * is_opcode_roll;
+            let is_relevant_opcode = is_pick.clone() + is_roll.clone();
+
+            let pick_index = pick_index.map(|c| meta.query_advice(c, Rotation::cur()));
+            let prev_stack = stack.map(|c| meta.query_advice(c, Rotation::prev()));
+            let cur_stack = stack.map(|c| meta.query_advice(c, Rotation::cur()));
+            let top_num_enabled = meta.query_advice(top_num.enabled, Rotation::cur());
+
+            let mut constraints = vec![];
+            let mut sum = 0u8.expr();
+            let mut index = 0u8.expr();
+            for (i, bit) in pick_index.iter().enumerate() {
+                constraints.push(is_relevant_opcode.clone() * bit.clone() * (1u8.expr() - bit.clone()));
+                sum = sum + bit.clone();
+                index = index + bit.clone() * (i as u64).expr();
+            }
+            constraints.push(is_relevant_opcode.clone() * (1u8.expr() - sum));
+            // The depth itself cannot be picked, so negative depths fail
+            constraints.push(is_relevant_opcode.clone() * pick_index[0].clone());
+            constraints.push(is_relevant_opcode.clone() * (1u8.expr() - top_num_enabled));
+            constraints.push(is_relevant_opcode * (index - top_num.expr() - 1u8.expr()));
+
+            for (is_opcode, is_roll) in [(is_pick, false), (is_roll, true)] {
+                for (cur_stack_item, moved_stack_item) in cur_stack.iter().zip(picked_stack(&prev_stack, &pick_index, is_roll)) {
+                    constraints.push(is_opcode.clone() * (cur_stack_item.clone() - moved_stack_item));
+                }
+            }
+            constraints
+        });
+
+        meta.create_gate("OP_NIP", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let is_opcode_nip = meta.query_advice(is_opcode_nip, Rotation::cur());
+            let is_relevant_opcode = q_execution
+                * is_executed_opcode.clone()
+                * is_opcode_nip;
+
+            // The top stack item is unchanged
+            let prev_stack_top = meta.query_advice(stack[0], Rotation::prev());
+            let cur_stack_top = meta.query_advice(stack[0], Rotation::cur());
+            let mut constraints = vec![is_relevant_opcode.clone() * (cur_stack_top - prev_stack_top)];
+
+            // Check that the stack items at indices 2 to STACK_DEPTH-1 are shifted to the left
+            for i in 2..STACK_DEPTH {
+                let current_stack_item = meta.query_advice(stack[i-1], Rotation::cur());
+                let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
+                constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+            }
+            let cur_stack_bottom = meta.query_advice(stack[STACK_DEPTH-1], Rotation::cur());
+            // The last item in the current stack is forced to be zero
+            constraints.push(is_relevant_opcode * cur_stack_bottom);
+            constraints
+        });
+
+        meta.create_gate("OP_TUCK", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let is_opcode_tuck = meta.query_advice(is_opcode_tuck, Rotation::cur());
+            let is_relevant_opcode = q_execution
+                * is_executed_opcode.clone()
+                * is_opcode_tuck;
+
+            // The top stack item is unchanged and copied below the second item
+            let prev_stack_top = meta.query_advice(stack[0], Rotation::prev());
+            let cur_stack_top = meta.query_advice(stack[0], Rotation::cur());
+            let cur_stack_second = meta.query_advice(stack[1], Rotation::cur());
+            let prev_stack_second = meta.query_advice(stack[1], Rotation::prev());
+            let cur_stack_third = meta.query_advice(stack[2], Rotation::cur());
+            let mut constraints = vec![
+                is_relevant_opcode.clone() * (cur_stack_top - prev_stack_top.clone()),
+                is_relevant_opcode.clone() * (cur_stack_second - prev_stack_second),
+                is_relevant_opcode.clone() * (cur_stack_third - prev_stack_top),
+            ];
+
+            // Check that the stack items at indices 2 to STACK_DEPTH-2 are shifted to the right.
+            // The last item of the previous stack is dropped like in the other push operations.
+            for i in 3..STACK_DEPTH {
+                let current_stack_item = meta.query_advice(stack[i], Rotation::cur());
+                let prev_stack_item  = meta.query_advice(stack[i-1], Rotation::prev());
+                constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+            }
+            constraints
+        });
+
+        // The stack size in the first row is that of the initial stack, which is a witness like the
+        // initial stack items. It may exceed STACK_DEPTH, in which case the bottom items are
+        // not present in the stack columns.
+        macro_rules! create_arithmetic_gate {
+            ($annotation:expr, $is_opcode_col:ident, $num_operands:expr, $result:expr) => {
+                meta.create_gate($annotation, |meta| {
+                    let q_execution = meta.query_selector(q_execution);
+                    let is_opcode = meta.query_advice($is_opcode_col, Rotation::cur());
+                    let is_relevant_opcode = q_execution
+                        * is_executed_opcode.clone()
+                        * is_opcode;
+
+                    let mut constraints = vec![];
+                    // The operands and the result are decoded as numbers
+                    let mut enabled_columns = vec![result_num.enabled];
+                    enabled_columns.extend_from_slice(&[top_num.enabled, second_num.enabled, third_num.enabled][..$num_operands]);
+                    for column in enabled_columns {
+                        let enabled = meta.query_advice(column, Rotation::cur());
+                        constraints.push(is_relevant_opcode.clone() * (1u8.expr() - enabled));
+                    }
+                    constraints.push(is_relevant_opcode.clone() * (result_num.expr() - $result));
+
+                    // The operands are replaced by the result and the remaining stack items are
+                    // shifted to the left
+                    for i in $num_operands..STACK_DEPTH {
+                        let current_stack_item = meta.query_advice(stack[i+1-$num_operands], Rotation::cur());
+                        let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
+                        constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+                    }
+                    // The vacated items at the bottom of the current stack are forced to be zero
+                    for i in STACK_DEPTH+1-$num_operands..STACK_DEPTH {
+                        let cur_stack_item = meta.query_advice(stack[i], Rotation::cur());
+                        constraints.push(is_relevant_opcode.clone() * cur_stack_item);
+                    }
+                    constraints
+                });
+            };
+        }
+
+        create_arithmetic_gate!("OP_ADD", is_opcode_add, 2, second_num.expr() + top_num.expr());
+        create_arithmetic_gate!("OP_SUB", is_opcode_sub, 2, second_num.expr() - top_num.expr());
+        create_arithmetic_gate!("OP_1ADD", is_opcode_1add, 1, top_num.expr() + 1u8.expr());
+        create_arithmetic_gate!("OP_1SUB", is_opcode_1sub, 1, top_num.expr() - 1u8.expr());
+        create_arithmetic_gate!("OP_NEGATE", is_opcode_negate, 1, -top_num.expr());
+        create_arithmetic_gate!("OP_ABS", is_opcode_abs, 1, top_num.abs_expr());
+
+        // Comparisons push the canonical booleans 1 and the empty array, as the result is
+        // decoded with the canonical representation of zero
+        let lt = second_num_lt_top_num.expr();
+        let eq = are_top_two_nums_equal.expr();
+        create_arithmetic_gate!("OP_LESSTHAN", is_opcode_lessthan, 2, lt.clone());
+        create_arithmetic_gate!("OP_GREATERTHAN", is_opcode_greaterthan, 2, 1u8.expr() - lt.clone() - eq.clone());
+        create_arithmetic_gate!("OP_LESSTHANOREQUAL", is_opcode_lessthanorequal, 2, lt.clone() + eq.clone());
+        create_arithmetic_gate!("OP_GREATERTHANOREQUAL", is_opcode_greaterthanorequal, 2, 1u8.expr() - lt.clone());
+        create_arithmetic_gate!("OP_NUMEQUAL", is_opcode_numequal, 2, eq);
+        create_arithmetic_gate!(
+            "OP_MIN",
+            is_opcode_min,
+            2,
+            lt.clone() * second_num.expr() + (1u8.expr() - lt.clone()) * top_num.expr()
+        );
+        create_arithmetic_gate!(
+            "OP_MAX",
+            is_opcode_max,
+            2,
+            lt.clone() * top_num.expr() + (1u8.expr() - lt) * second_num.expr()
+        );
+        // The third item is in the range [second item, top item)
+        create_arithmetic_gate!(
+            "OP_WITHIN",
+            is_opcode_within,
+            3,
+            (1u8.expr() - third_num_lt_second_num.expr()) * third_num_lt_top_num.expr()
+        );
+
+        // The result of a boolean opcode is pushed as 1 if true and as the empty array otherwise
+        macro_rules! create_boolean_gate {
+            ($annotation:expr, $is_opcode_col:ident, $num_operands:expr, $is_result_true:expr) => {
+                meta.create_gate($annotation, |meta| {
+                    let q_execution = meta.query_selector(q_execution);
+                    let is_opcode = meta.query_advice($is_opcode_col, Rotation::cur());
+                    let is_relevant_opcode = q_execution
+                        * is_executed_opcode.clone()
+                        * is_opcode;
+
+                    let is_result_true = $is_result_true;
+                    let cur_stack_top = meta.query_advice(stack[0], Rotation::cur());
+                    let mut constraints = vec![
+                        is_relevant_opcode.clone()
+                        * (cur_stack_top
+                            - is_result_true.clone()
+                            - (1u8.expr() - is_result_true) * EMPTY_ARRAY_REPRESENTATION.expr())
+                    ];
+
+                    // The operands are replaced by the result and the remaining stack items are
+                    // shifted to the left
+                    for i in $num_operands..STACK_DEPTH {
+                        let current_stack_item = meta.query_advice(stack[i+1-$num_operands], Rotation::cur());
+                        let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
+                        constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+                    }
+                    // The vacated items at the bottom of the current stack are forced to be zero
+                    for i in STACK_DEPTH+1-$num_operands..STACK_DEPTH {
+                        let cur_stack_item = meta.query_advice(stack[i], Rotation::cur());
+                        constraints.push(is_relevant_opcode.clone() * cur_stack_item);
+                    }
+                    constraints
+                });
+            };
+        }
+
+        let is_top_false = is_prev_stack_top_false.expr();
+        let is_second_false = is_prev_stack_second_false.expr();
+        create_boolean_gate!("OP_NOT", is_opcode_not, 1, is_top_false.clone());
+        create_boolean_gate!("OP_0NOTEQUAL", is_opcode_0notequal, 1, 1u8.expr() - is_top_false.clone());
+        create_boolean_gate!(
+            "OP_BOOLAND",
+            is_opcode_booland,
+            2,
+            (1u8.expr() - is_top_false.clone()) * (1u8.expr() - is_second_false.clone())
+        );
+        create_boolean_gate!(
+            "OP_BOOLOR",
+            is_opcode_boolor,
+            2,
+            1u8.expr() - is_top_false * is_second_false
+        );
+
+        meta.create_gate("Alt stack", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let mut query = |column: Column<Advice>| meta.query_advice(column, Rotation::cur());
+            let is_opcode_toaltstack = query(is_opcode_toaltstack);
+            let is_opcode_fromaltstack = query(is_opcode_fromaltstack);
+            let cur_alt_stack_size = query(alt_stack_size);
+
+            let cur_alt_stack: Vec<Expression<F>> = alt_stack
+                .iter()
+                .map(|c| meta.query_advice(*c, Rotation::cur()))
+                .collect();
+            let prev_alt_stack: Vec<Expression<F>> = alt_stack
+                .iter()
+                .map(|c| meta.query_advice(*c, Rotation::prev()))
+                .collect();
+            let prev_alt_stack_size = meta.query_advice(alt_stack_size, Rotation::prev());
+
+            let mut constraints = vec![];
+
+            // OP_TOALTSTACK moves the top stack item onto the alt stack, which must not be full
+            let is_relevant_opcode = q_execution.clone() * is_executed_opcode.clone() * is_opcode_toaltstack.clone();
+            let prev_stack_top = meta.query_advice(stack[0], Rotation::prev());
+            constraints.push(is_relevant_opcode.clone() * (cur_alt_stack[0].clone() - prev_stack_top));
+            for i in 1..MAX_ALTSTACK_DEPTH {
+                constraints.push(is_relevant_opcode.clone() * (cur_alt_stack[i].clone() - prev_alt_stack[i-1].clone()));
+            }
+            constraints.push(is_relevant_opcode * is_prev_alt_stack_full.expr());
+
+            // OP_FROMALTSTACK moves the top alt stack item onto the stack. The alt stack must not be empty.
+            let is_relevant_opcode = q_execution.clone() * is_executed_opcode.clone() * is_opcode_fromaltstack.clone();
+            let cur_stack_top = meta.query_advice(stack[0], Rotation::cur());
+            constraints.push(is_relevant_opcode.clone() * (cur_stack_top - prev_alt_stack[0].clone()));
+            for i in 1..STACK_DEPTH {
+                let current_stack_item = meta.query_advice(stack[i], Rotation::cur());
+                let prev_stack_item  = meta.query_advice(stack[i-1], Rotation::prev());
+                constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+            }
+            for i in 1..MAX_ALTSTACK_DEPTH {
+                constraints.push(is_relevant_opcode.clone() * (cur_alt_stack[i-1].clone() - prev_alt_stack[i].clone()));
+            }
+            constraints.push(is_relevant_opcode.clone() * cur_alt_stack[MAX_ALTSTACK_DEPTH-1].clone());
+            constraints.push(is_relevant_opcode * (1u8.expr() - is_prev_alt_stack_empty.expr()));
+
+            // Other bytes leave the alt stack unchanged
+            let is_alt_stack_opcode = is_executed_opcode.clone()
+                * (is_opcode_toaltstack.clone() + is_opcode_fromaltstack.clone());
+            let is_not_alt_stack_opcode = q_execution.clone() * (1u8.expr() - is_alt_stack_opcode);
+            for i in 0..MAX_ALTSTACK_DEPTH {
+                constraints.push(is_not_alt_stack_opcode.clone() * (cur_alt_stack[i].clone() - prev_alt_stack[i].clone()));
+            }
+            constraints.push(
+                q_execution
+                * (cur_alt_stack_size - prev_alt_stack_size
+                    - is_executed_opcode.clone() * (is_opcode_toaltstack - is_opcode_fromaltstack))
+            );
+            constraints
+        });
+
+        meta.create_gate("Stack size", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            // OP_CHECKMULTISIG pops n+m+3 items and pushes its result
+            let mut multisig_num_popped = 1u8.expr();
+            for (i, column) in multisig_shift.iter().enumerate() {
+                multisig_num_popped = multisig_num_popped + meta.query_advice(*column, Rotation::cur()) * (i as u64 + 4).expr();
+            }
+            let mut query = |column: Column<Advice>| meta.query_advice(column, Rotation::cur());
+
+            let mut stack_size_delta = 0u8.expr();
+            // Opcodes which push one item
+            for column in [
+                is_opcode_op0,
+                is_opcode_op1_to_op16,
+                is_opcode_push1_to_push75,
+                is_opcode_pushdata1,
+                is_opcode_pushdata2,
+                is_opcode_pushdata4,
+                is_opcode_over,
+                is_opcode_tuck,
+                is_opcode_depth,
+                is_opcode_fromaltstack,
+                is_opcode_size,
+            ] {
+                stack_size_delta = stack_size_delta + query(column);
+            }
+            // Opcodes which copy two or three items
+            for (column, num_pushed) in [
+                (is_opcode_2dup, 2u8),
+                (is_opcode_3dup, 3u8),
+                (is_opcode_2over, 2u8),
+            ] {
+                stack_size_delta = stack_size_delta + query(column) * num_pushed.expr();
+            }
+            // Opcodes which pop one or two items
+            for (column, num_popped) in [
+                (is_opcode_checksig, 1u8),
+                (is_opcode_checksigverify, 2u8),
+                (is_opcode_checksigadd, 2u8),
+                (is_opcode_if, 1u8),
+                (is_opcode_notif, 1u8),
+                (is_opcode_equal, 1u8),
+                (is_opcode_equalverify, 2u8),
+                (is_opcode_verify, 1u8),
+                (is_opcode_drop, 1u8),
+                (is_opcode_2drop, 2u8),
+                (is_opcode_nip, 1u8),
+                (is_opcode_roll, 1u8),
+                (is_opcode_toaltstack, 1u8),
+                (is_opcode_add, 1u8),
+                (is_opcode_sub, 1u8),
+                (is_opcode_lessthan, 1u8),
+                (is_opcode_greaterthan, 1u8),
+                (is_opcode_lessthanorequal, 1u8),
+                (is_opcode_greaterthanorequal, 1u8),
+                (is_opcode_min, 1u8),
+                (is_opcode_max, 1u8),
+                (is_opcode_within, 2u8),
+                (is_opcode_numequal, 1u8),
+                (is_opcode_booland, 1u8),
+                (is_opcode_boolor, 1u8),
+            ] {
+                stack_size_delta = stack_size_delta - query(column) * num_popped.expr();
+            }
+            stack_size_delta = stack_size_delta - query(is_opcode_checkmultisig) * (multisig_num_popped - 1u8.expr());
+
+            let cur_stack_size = meta.query_advice(stack_size, Rotation::cur());
+            let prev_stack_size = meta.query_advice(stack_size, Rotation::prev());
+            // The stack size only changes at opcodes. It is unchanged in data bytes and padding rows.
+            vec![
+                q_execution
+                * (cur_stack_size - prev_stack_size - is_executed_opcode.clone() * stack_size_delta)
+            ]
+        });
+
+        meta.create_gate("Stack underflow", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let mut multisig_num_popped = 1u8.expr();
+            for (i, column) in multisig_shift.iter().enumerate() {
+                multisig_num_popped = multisig_num_popped + meta.query_advice(*column, Rotation::cur()) * (i as u64 + 4).expr();
+            }
+            let mut query = |column: Column<Advice>| meta.query_advice(column, Rotation::cur());
+
+            // Number of stack items which must be present before the opcode is executed
+            let mut num_operands = query(is_opcode_checkmultisig) * multisig_num_popped;
+            for (column, count) in [
+                (is_opcode_checksig, 2u8),
+                (is_opcode_checksigverify, 2u8),
+                (is_opcode_checksigadd, 3u8),
+                (is_opcode_if, 1u8),
+                (is_opcode_notif, 1u8),
+                (is_opcode_equal, 2u8),
+                (is_opcode_equalverify, 2u8),
+                (is_opcode_verify, 1u8),
+                (is_opcode_drop, 1u8),
+                (is_opcode_2drop, 2u8),
+                (is_opcode_swap, 2u8),
+                (is_opcode_over, 2u8),
+                (is_opcode_rot, 3u8),
+                (is_opcode_nip, 2u8),
+                (is_opcode_tuck, 2u8),
+                (is_opcode_2dup, 2u8),
+                (is_opcode_3dup, 3u8),
+                (is_opcode_2over, 4u8),
+                (is_opcode_2rot, 6u8),
+                (is_opcode_2swap, 4u8),
+                (is_opcode_toaltstack, 1u8),
+                (is_opcode_size, 1u8),
+                (is_opcode_hash256, 1u8),
+                (is_opcode_sha256, 1u8),
+                (is_opcode_ripemd160, 1u8),
+                (is_opcode_sha1, 1u8),
+                (is_opcode_hash160, 1u8),
+                (is_opcode_cltv, 1u8),
+                (is_opcode_csv, 1u8),
+                (is_opcode_add, 2u8),
+                (is_opcode_sub, 2u8),
+                (is_opcode_1add, 1u8),
+                (is_opcode_1sub, 1u8),
+                (is_opcode_negate, 1u8),
+                (is_opcode_abs, 1u8),
+                (is_opcode_not, 1u8),
+                (is_opcode_0notequal, 1u8),
+                (is_opcode_lessthan, 2u8),
+                (is_opcode_greaterthan, 2u8),
+                (is_opcode_lessthanorequal, 2u8),
+                (is_opcode_greaterthanorequal, 2u8),
+                (is_opcode_min, 2u8),
+                (is_opcode_max, 2u8),
+                (is_opcode_within, 3u8),
+                (is_opcode_numequal, 2u8),
+                (is_opcode_booland, 2u8),
+                (is_opcode_boolor, 2u8),
+            ] {
+                num_operands = num_operands + query(column) * count.expr();
+            }
+            // OP_PICK and OP_ROLL need the depth and the items down to the picked one
+            let mut pick_index_value = 0u8.expr();
+            for (i, column) in pick_index.iter().enumerate() {
+                pick_index_value = pick_index_value + query(*column) * (i as u64).expr();
+            }
+            num_operands = num_operands
+                + (query(is_opcode_pick) + query(is_opcode_roll)) * (pick_index_value + 1u8.expr());
+
+            let surplus = query(stack_surplus_bytes[0]) + query(stack_surplus_bytes[1]) * 256u64.expr();
+            let prev_stack_size = meta.query_advice(stack_size, Rotation::prev());
+            // The surplus is a two byte number, so the previous stack holds at least the operands.
+            // It is zero in rows which do not execute an opcode.
+            vec![
+                q_execution
+                * (surplus - is_executed_opcode.clone() * (prev_stack_size - num_operands))
+            ]
+        });
+
+        meta.create_gate("OP_DEPTH", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let is_opcode_depth = meta.query_advice(is_opcode_depth, Rotation::cur());
+            let is_relevant_opcode = q_execution
+                * is_executed_opcode.clone()
+                * is_opcode_depth;
+
+            // The stack size before OP_DEPTH is pushed as a number, decoded from the stack top by
+            // result_num. The zero size is the empty array. Sizes whose least significant byte is
+            // zero, e.g. 256, cannot be decoded and are not supported.
+            let enabled = meta.query_advice(result_num.enabled, Rotation::cur());
+            let prev_stack_size = meta.query_advice(stack_size, Rotation::prev());
+            let mut constraints = vec![
+                is_relevant_opcode.clone() * (1u8.expr() - enabled),
+                is_relevant_opcode.clone() * (result_num.expr() - prev_stack_size),
+            ];
+
+            // Check that the stack items to are shifted to the right
+            for i in 1..STACK_DEPTH {
+                let current_stack_item = meta.query_advice(stack[i], Rotation::cur());
+                let prev_stack_item  = meta.query_advice(stack[i-1], Rotation::prev());
+                constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+            }
+            constraints
+        });
+
+        meta.create_gate("OP_SIZE", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let is_opcode_size = meta.query_advice(is_opcode_size, Rotation::cur());
+            let is_relevant_opcode = q_execution
+                * is_executed_opcode.clone()
+                * is_opcode_size;
+
+            // The byte length of the top item is pushed as a number. Lengths whose least
+            // significant byte is zero, e.g. 256, cannot be decoded and are not supported. The
+            // unknown lengths of initial items do not fit in result_num, so OP_SIZE fails on them.
+            const _: () = assert!(UNKNOWN_ITEM_LENGTH >> (8 * MAX_SCRIPT_NUM_RESULT_SIZE - 1) > 0);
+            let enabled = meta.query_advice(result_num.enabled, Rotation::cur());
+            let prev_stack_top_len = meta.query_advice(stack_len[0], Rotation::prev());
+            let mut constraints = vec![
+                is_relevant_opcode.clone() * (1u8.expr() - enabled),
+                is_relevant_opcode.clone() * (result_num.expr() - prev_stack_top_len),
+            ];
+
+            // The top item is not popped, so the stack items are shifted to the right
+            for i in 1..STACK_DEPTH {
+                let current_stack_item = meta.query_advice(stack[i], Rotation::cur());
+                let prev_stack_item  = meta.query_advice(stack[i-1], Rotation::prev());
+                constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+            }
+            constraints
+        });
+
+        // Hash opcodes replace the top item by its digest, which is a witness. The opcode, the length
+        // and the RLC of the hashed item and the RLC of the digest are accumulated in hash_rlc_acc,
+        // which OpHashChip checks against the hash gadgets like pk_rlc_acc is checked against ECDSA
+        // verifications.
+        // The empty array is told apart from other items by its zero length.
+        meta.create_gate("Hash opcodes", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let is_opcode_hash = meta.query_advice(is_opcode_hash256, Rotation::cur())
+                + meta.query_advice(is_opcode_sha256, Rotation::cur())
+                + meta.query_advice(is_opcode_ripemd160, Rotation::cur())
+                + meta.query_advice(is_opcode_sha1, Rotation::cur())
+                + meta.query_advice(is_opcode_hash160, Rotation::cur());
+            let is_relevant_opcode = q_execution.clone()
+                * is_executed_opcode.clone()
+                * is_opcode_hash.clone();
+            let is_cur_byte_not_hash = q_execution
+                * (1u8.expr() - is_executed_opcode.clone() * is_opcode_hash);
+
+            let prev_hash_rlc_acc = meta.query_advice(hash_rlc_acc, Rotation::prev());
+            let cur_hash_rlc_acc = meta.query_advice(hash_rlc_acc, Rotation::cur());
+            let randomness = meta.query_advice(randomness, Rotation::cur());
+            let mut acc = prev_hash_rlc_acc.clone();
+            for value in [
+                meta.query_advice(opcode, Rotation::cur()),
+                meta.query_advice(stack_len[0], Rotation::prev()),
+                meta.query_advice(stack[0], Rotation::prev()),
+                meta.query_advice(stack[0], Rotation::cur()),
+            ] {
+                acc = acc * randomness.clone() + value;
+            }
+            let mut constraints = vec![
+                // Other bytes leave the accumulator unchanged
+                is_cur_byte_not_hash * (cur_hash_rlc_acc.clone() - prev_hash_rlc_acc),
+                is_relevant_opcode.clone() * (cur_hash_rlc_acc - acc),
+            ];
+
+            // The items below the top are unchanged
+            for i in 1..STACK_DEPTH {
+                let current_stack_item = meta.query_advice(stack[i], Rotation::cur());
+                let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
+                constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+            }
+            constraints
+        });
+
+        // OP_NOP and the reserved no-ops leave the stack unchanged. Padding bytes are OP_NOP too,
+        // but they follow the script and are not executed.
+        meta.create_gate("No-op opcodes", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let is_opcode_nop = meta.query_advice(is_opcode_nop, Rotation::cur());
+            let is_relevant_opcode = q_execution
+                * is_executed_opcode.clone()
+                * is_opcode_nop;
+
+            let mut constraints = vec![];
+            for i in 0..STACK_DEPTH {
+                let current_stack_item = meta.query_advice(stack[i], Rotation::cur());
+                let prev_stack_item  = meta.query_advice(stack[i], Rotation::prev());
+                constraints.push(is_relevant_opcode.clone() * (current_stack_item - prev_stack_item));
+            }
+            constraints
+        });
+
+        // OP_CODESEPARATOR records the byte offset following it, from which a signature covers the
+        // script, and leaves the stack unchanged. The offset is the number of bytes read, which
+        // grows by one in each row until the script is read.
+        meta.create_gate("OP_CODESEPARATOR", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let cur_stack = stack.map(|c| meta.query_advice(c, Rotation::cur()));
+            let prev_stack = stack.map(|c| meta.query_advice(c, Rotation::prev()));
+            let is_opcode_codeseparator = meta.query_advice(is_opcode_codeseparator, Rotation::cur());
+            let is_relevant_opcode = q_execution.clone()
+                * is_executed_opcode.clone()
+                * is_opcode_codeseparator.clone();
+            let is_cur_byte_not_codeseparator = q_execution.clone()
+                * (1u8.expr() - is_executed_opcode.clone() * is_opcode_codeseparator);
+
+            let prev_num_bytes_read = meta.query_advice(num_script_bytes_read, Rotation::prev());
+            let cur_num_bytes_read = meta.query_advice(num_script_bytes_read, Rotation::cur());
+            let prev_position = meta.query_advice(last_codeseparator_position, Rotation::prev());
+            let cur_position = meta.query_advice(last_codeseparator_position, Rotation::cur());
+            let mut constraints = vec![
+                q_execution
+                    * (cur_num_bytes_read.clone() - prev_num_bytes_read
+                        - (1u8.expr() - num_script_bytes_remaining_is_zero.expr())),
+                is_relevant_opcode.clone() * (cur_position.clone() - cur_num_bytes_read),
+                // Other bytes leave the position unchanged
+                is_cur_byte_not_codeseparator * (cur_position - prev_position),
+            ];
+
+            for i in 0..STACK_DEPTH {
+                constraints.push(is_relevant_opcode.clone() * (cur_stack[i].clone() - prev_stack[i].clone()));
+            }
+            constraints
+        });
+
+        // OP_CHECKLOCKTIMEVERIFY checks the locktime on top of the stack against the nLockTime and
+        // leaves the stack unchanged, as in BIP65. Script execution fails unless the locktime is
+        // non-negative, of the same type as the nLockTime, i.e. both are block heights or both are
+        // timestamps, and not above the nLockTime. Five byte locktimes cannot be decoded and are
+        // not supported.
+        meta.create_gate("OP_CHECKLOCKTIMEVERIFY", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let cur_stack = stack.map(|c| meta.query_advice(c, Rotation::cur()));
+            let prev_stack = stack.map(|c| meta.query_advice(c, Rotation::prev()));
+            let mut query = |column: Column<Advice>| meta.query_advice(column, Rotation::cur());
+            let is_relevant_opcode = q_execution
+                * is_executed_opcode.clone()
+                * query(is_opcode_cltv);
+
+            let enabled = query(top_num.enabled);
+            let nlocktime = query(nlocktime);
+            let is_height = query(is_nlocktime_height);
+            let mut bytes_value = |bytes: [Column<Advice>; 4]| {
+                bytes.iter().rev().fold(0u8.expr(), |acc, byte| acc * 256u64.expr() + query(*byte))
+            };
+            let locktime_threshold_distance = bytes_value(locktime_threshold_distance_bytes);
+            let nlocktime_threshold_distance = bytes_value(nlocktime_threshold_distance_bytes);
+            let nlocktime_surplus = bytes_value(nlocktime_surplus_bytes);
+
+            // Distance of a value to the threshold, which is a four byte number iff the value is a
+            // block height when is_height is set and a timestamp otherwise
+            let threshold = LOCKTIME_THRESHOLD.expr();
+            let threshold_distance = |value: Expression<F>| {
+                is_height.clone() * (threshold.clone() - 1u8.expr() - value.clone())
+                    + (1u8.expr() - is_height.clone()) * (value - threshold.clone())
+            };
+
+            let mut constraints = vec![
+                is_relevant_opcode.clone() * (1u8.expr() - enabled),
+                is_relevant_opcode.clone() * top_num.is_negative_expression.clone(),
+                is_relevant_opcode.clone() * is_height.clone() * (1u8.expr() - is_height.clone()),
+                is_relevant_opcode.clone() * (threshold_distance(top_num.expr()) - locktime_threshold_distance),
+                is_relevant_opcode.clone() * (threshold_distance(nlocktime.clone()) - nlocktime_threshold_distance),
+                is_relevant_opcode.clone() * (nlocktime - top_num.expr() - nlocktime_surplus),
+            ];
+
+            // The locktime is not popped
+            for i in 0..STACK_DEPTH {
+                constraints.push(is_relevant_opcode.clone() * (cur_stack[i].clone() - prev_stack[i].clone()));
+            }
+            constraints
+        });
+
+        // OP_CHECKSEQUENCEVERIFY checks the relative locktime on top of the stack against the
+        // nSequence and leaves the stack unchanged, as in BIP112. Script execution fails unless
+        // the locktime is non-negative. If its disable flag is set, the opcode does nothing else.
+        // Otherwise the disable flag of the nSequence must be unset, both must have the same type
+        // flag, i.e. both are block counts or both are time intervals, and the masked locktime
+        // must not be above the masked nSequence. The version of the transaction is not checked.
+        meta.create_gate("OP_CHECKSEQUENCEVERIFY", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let cur_stack = stack.map(|c| meta.query_advice(c, Rotation::cur()));
+            let prev_stack = stack.map(|c| meta.query_advice(c, Rotation::prev()));
+            let mut query = |column: Column<Advice>| meta.query_advice(column, Rotation::cur());
+            let is_relevant_opcode = q_execution
+                * is_executed_opcode.clone()
+                * query(is_opcode_csv);
+
+            // As the stack is unchanged, the locktime is decoded from the current stack top by
+            // result_num, which allows the 5 byte locktimes needed for the disable flag
+            let enabled = query(result_num.enabled);
+            let nsequence = query(nsequence);
+            let [locktime_type, locktime_bit_23, locktime_disable] = sequence_locktime_bits.map(&mut query);
+            let [nsequence_type, nsequence_bit_23, nsequence_disable] = nsequence_bits.map(&mut query);
+            let mut bytes_value = |bytes: &[Column<Advice>]| {
+                bytes.iter().rev().fold(0u8.expr(), |acc, byte| acc * 256u64.expr() + query(*byte))
+            };
+            let nsequence_value = bytes_value(&nsequence_bytes);
+            let masked_locktime = bytes_value(&result_num.bytes[..2]);
+            let masked_nsequence = bytes_value(&nsequence_bytes[..2]);
+            let nsequence_surplus = bytes_value(&nsequence_surplus_bytes);
+            let is_enabled_locktime = is_relevant_opcode.clone() * (1u8.expr() - locktime_disable.clone());
+
+            let mut constraints = vec![
+                is_relevant_opcode.clone() * (1u8.expr() - enabled),
+                is_relevant_opcode.clone() * result_num.is_negative_expression.clone(),
+                is_relevant_opcode.clone() * (nsequence - nsequence_value),
+                is_enabled_locktime.clone() * nsequence_disable.clone(),
+                is_enabled_locktime.clone() * (locktime_type.clone() - nsequence_type.clone()),
+                is_enabled_locktime * (masked_nsequence - masked_locktime - nsequence_surplus),
+            ];
+            for bit in [
+                locktime_type,
+                locktime_bit_23,
+                locktime_disable,
+                nsequence_type,
+                nsequence_bit_23,
+                nsequence_disable,
+            ] {
+                constraints.push(is_relevant_opcode.clone() * bit.clone() * (1u8.expr() - bit));
+            }
+
+            // The locktime is not popped
+            for i in 0..STACK_DEPTH {
+                constraints.push(is_relevant_opcode.clone() * (cur_stack[i].clone() - prev_stack[i].clone()));
+            }
+            constraints
+        });
+
+        // The byte lengths move like the stack items. Data bytes which are accumulated into the
+        // stack top increment its length. The lengths of the initial stack are witnesses like the
+        // initial stack items.
+        meta.create_gate("Stack lengths", |meta| {
+            let q_execution = meta.query_selector(q_execution);
+            let cur_len = stack_len.map(|c| meta.query_advice(c, Rotation::cur()));
+            let prev_len = stack_len.map(|c| meta.query_advice(c, Rotation::prev()));
+            let cur_alt_len = alt_stack_len.map(|c| meta.query_advice(c, Rotation::cur()));
+            let prev_alt_len = alt_stack_len.map(|c| meta.query_advice(c, Rotation::prev()));
+            let mut query = |column: Column<Advice>| meta.query_advice(column, Rotation::cur());
+
+            // Booleans are 1 or the empty array and signature results are 1 or 0, so their length
+            // is one iff they are true
+            let is_result_true = 1u8.expr() - is_stack_top_false.expr();
+            // Indicator of opcodes, an opcode for which it is set and the length of the pushed item
+            let opcodes = [
+                (query(is_opcode_op0), OP_0, 0u8.expr()),
+                (query(is_opcode_op1_to_op16), OP_1, 1u8.expr()),
+                (query(is_opcode_push1_to_push75), OP_PUSH_NEXT1, 0u8.expr()),
+                (query(is_opcode_pushdata1), OP_PUSHDATA1, 0u8.expr()),
+                (query(is_opcode_pushdata2), OP_PUSHDATA2, 0u8.expr()),
+                (query(is_opcode_pushdata4), OP_PUSHDATA4, 0u8.expr()),
+                (query(is_opcode_checksig), OP_CHECKSIG, is_result_true.clone()),
+                (query(is_opcode_checksigverify), OP_CHECKSIGVERIFY, 0u8.expr()),
+                (query(is_opcode_checksigadd), OP_CHECKSIGADD, result_num.len_expr()),
+                (query(is_opcode_equal), OP_EQUAL, is_result_true.clone()),
+                (query(is_opcode_equalverify), OP_EQUALVERIFY, 0u8.expr()),
+                (query(is_opcode_verify), OP_VERIFY, 0u8.expr()),
+                (query(is_opcode_if), OP_IF, 0u8.expr()),
+                (query(is_opcode_notif), OP_NOTIF, 0u8.expr()),
+                (query(is_opcode_drop), OP_DROP, 0u8.expr()),
+                (query(is_opcode_2drop), OP_2DROP, 0u8.expr()),
+                (query(is_opcode_swap), OP_SWAP, 0u8.expr()),
+                (query(is_opcode_over), OP_OVER, 0u8.expr()),
+                (query(is_opcode_rot), OP_ROT, 0u8.expr()),
+                (query(is_opcode_nip), OP_NIP, 0u8.expr()),
+                (query(is_opcode_tuck), OP_TUCK, 0u8.expr()),
+                (query(is_opcode_2dup), OP_2DUP, 0u8.expr()),
+                (query(is_opcode_3dup), OP_3DUP, 0u8.expr()),
+                (query(is_opcode_2over), OP_2OVER, 0u8.expr()),
+                (query(is_opcode_2rot), OP_2ROT, 0u8.expr()),
+                (query(is_opcode_2swap), OP_2SWAP, 0u8.expr()),
+                (query(is_opcode_depth), OP_DEPTH, result_num.len_expr()),
+                (query(is_opcode_toaltstack), OP_TOALTSTACK, 0u8.expr()),
+                (query(is_opcode_fromaltstack), OP_FROMALTSTACK, prev_alt_len[0].clone()),
+                (query(is_opcode_size), OP_SIZE, result_num.len_expr()),
+                (query(is_opcode_hash256), OP_HASH256, hash_digest_size(OP_HASH256).unwrap().expr()),
+                (query(is_opcode_sha256), OP_SHA256, hash_digest_size(OP_SHA256).unwrap().expr()),
+                (query(is_opcode_ripemd160), OP_RIPEMD160, hash_digest_size(OP_RIPEMD160).unwrap().expr()),
+                (query(is_opcode_sha1), OP_SHA1, hash_digest_size(OP_SHA1).unwrap().expr()),
+                (query(is_opcode_hash160), OP_HASH160, hash_digest_size(OP_HASH160).unwrap().expr()),
+                (query(is_opcode_add), OP_ADD, result_num.len_expr()),
+                (query(is_opcode_sub), OP_SUB, result_num.len_expr()),
+                (query(is_opcode_1add), OP_1ADD, result_num.len_expr()),
+                (query(is_opcode_1sub), OP_1SUB, result_num.len_expr()),
+                (query(is_opcode_negate), OP_NEGATE, result_num.len_expr()),
+                (query(is_opcode_abs), OP_ABS, result_num.len_expr()),
+                (query(is_opcode_lessthan), OP_LESSTHAN, result_num.len_expr()),
+                (query(is_opcode_greaterthan), OP_GREATERTHAN, result_num.len_expr()),
+                (query(is_opcode_lessthanorequal), OP_LESSTHANOREQUAL, result_num.len_expr()),
+                (query(is_opcode_greaterthanorequal), OP_GREATERTHANOREQUAL, result_num.len_expr()),
+                (query(is_opcode_min), OP_MIN, result_num.len_expr()),
+                (query(is_opcode_max), OP_MAX, result_num.len_expr()),
+                (query(is_opcode_within), OP_WITHIN, result_num.len_expr()),
+                (query(is_opcode_numequal), OP_NUMEQUAL, result_num.len_expr()),
+                (query(is_opcode_not), OP_NOT, is_result_true.clone()),
+                (query(is_opcode_0notequal), OP_0NOTEQUAL, is_result_true.clone()),
+                (query(is_opcode_booland), OP_BOOLAND, is_result_true.clone()),
+                (query(is_opcode_boolor), OP_BOOLOR, is_result_true.clone()),
+            ];
+
+            // Change of the length at each stack index caused by the executed opcode
+            let mut len_delta = vec![0u8.expr(); STACK_DEPTH];
+            for (indicator, opcode, new_item_len) in opcodes {
+                let (num_popped, pushed) = stack_item_moves(opcode);
+                for i in 0..STACK_DEPTH {
+                    let moved_len = match pushed.get(i) {
+                        Some(Some(index)) => prev_len[*index].clone(),
+                        Some(None) => new_item_len.clone(),
+                        None if i + num_popped - pushed.len() < STACK_DEPTH => prev_len[i + num_popped - pushed.len()].clone(),
+                        None => 0u8.expr(),
+                    };
+                    len_delta[i] = len_delta[i].clone() + indicator.clone() * (moved_len - prev_len[i].clone());
+                }
+            }
+            // OP_CHECKMULTISIG replaces n+m+3 items by its result, where n+m+2 is given by the shift
+            let is_opcode_checkmultisig = query(is_opcode_checkmultisig);
+            len_delta[0] = len_delta[0].clone() + is_opcode_checkmultisig.clone() * (is_result_true - prev_len[0].clone());
+            for (k, column) in multisig_shift.iter().enumerate() {
+                let bit = query(*column);
+                let num_moved = k + 4;
+                for i in 1..STACK_DEPTH {
+                    let moved_len = if i + num_moved < STACK_DEPTH {
+                        prev_len[i + num_moved].clone()
+                    } else {
+                        0u8.expr()
+                    };
+                    len_delta[i] = len_delta[i].clone()
+                        + is_opcode_checkmultisig.clone() * bit.clone() * (moved_len - prev_len[i].clone());
+                }
+            }
+
+            // OP_PICK and OP_ROLL move the length of the picked item like the item itself
+            let pick_index_bits: Vec<_> = pick_index.iter().map(|column| query(*column)).collect();
+            for (indicator, is_roll) in [(query(is_opcode_pick), false), (query(is_opcode_roll), true)] {
+                for (i, moved_len) in picked_stack(&prev_len, &pick_index_bits, is_roll).into_iter().enumerate() {
+                    len_delta[i] = len_delta[i].clone() + indicator.clone() * (moved_len - prev_len[i].clone());
+                }
+            }
+
+            let is_opcode_toaltstack = query(is_opcode_toaltstack);
+            let is_opcode_fromaltstack = query(is_opcode_fromaltstack);
+            let is_data_byte_executed = (1u8.expr() - num_script_bytes_remaining_is_zero.expr())
+                * (1u8.expr() - num_data_bytes_remaining_is_zero.expr())
+                * num_data_length_bytes_remaining_is_zero.expr()
+                * is_prev_branch_active.expr();
+
+            let mut constraints = vec![];
+            for i in 0..STACK_DEPTH {
+                let data_byte_delta = if i == 0 { is_data_byte_executed.clone() } else { 0u8.expr() };
+                constraints.push(
+                    q_execution.clone()
+                    * (cur_len[i].clone() - prev_len[i].clone()
+                        - is_executed_opcode.clone() * len_delta[i].clone()
+                        - data_byte_delta)
+                );
+            }
+
+            // The alt stack lengths move with the alt stack items
+            for i in 0..MAX_ALTSTACK_DEPTH {
+                let to_alt_stack_len = if i == 0 { prev_len[0].clone() } else { prev_alt_len[i-1].clone() };
+                let from_alt_stack_len = if i + 1 < MAX_ALTSTACK_DEPTH { prev_alt_len[i+1].clone() } else { 0u8.expr() };
+                let alt_len_delta = is_opcode_toaltstack.clone() * (to_alt_stack_len - prev_alt_len[i].clone())
+                    + is_opcode_fromaltstack.clone() * (from_alt_stack_len - prev_alt_len[i].clone());
+                constraints.push(
+                    q_execution.clone()
+                    * (cur_alt_len[i].clone() - prev_alt_len[i].clone() - is_executed_opcode.clone() * alt_len_delta)
+                );
+            }
+            constraints
+        });
+
+        ExecutionConfig {
+            instance,
+            randomness,
+            q_first,
+            q_execution,
+            q_script_end,
+            q_initial_stack,
+            q_empty_initial_stack,
+            opcode,
+            opcode_table,
+            is_opcode_enabled,
+            is_opcode_op0,
+            is_opcode_op1_to_op16,
+            is_opcode_push1_to_push75,
+            is_opcode_pushdata1,
+            is_opcode_pushdata2,
+            is_opcode_pushdata4,
+            is_opcode_checksig,
+            is_opcode_equal,
+            is_opcode_equalverify,
+            is_opcode_verify,
+            is_opcode_drop,
+            is_opcode_2drop,
+            is_opcode_swap,
+            is_opcode_over,
+            is_opcode_rot,
+            is_opcode_nip,
+            is_opcode_tuck,
+            is_opcode_2dup,
+            is_opcode_3dup,
+            is_opcode_2over,
+            is_opcode_2rot,
+            is_opcode_2swap,
+            is_opcode_pick,
+            is_opcode_roll,
+            is_opcode_depth,
+            is_opcode_add,
+            is_opcode_sub,
+            is_opcode_1add,
+            is_opcode_1sub,
+            is_opcode_negate,
+            is_opcode_abs,
+            is_opcode_lessthan,
+            is_opcode_greaterthan,
+            is_opcode_lessthanorequal,
+            is_opcode_greaterthanorequal,
+            is_opcode_min,
+            is_opcode_max,
+            is_opcode_within,
+            is_opcode_not,
+            is_opcode_0notequal,
+            is_opcode_booland,
+            is_opcode_boolor,
+            is_opcode_if,
+            is_opcode_notif,
+            is_opcode_else,
+            is_opcode_endif,
+            is_opcode_checkmultisig,
+            is_opcode_checksigverify,
+            is_opcode_numequal,
+            is_opcode_checksigadd,
+            is_opcode_push1,
+            is_opcode_push1_to_push16,
+            is_opcode_toaltstack,
+            is_opcode_fromaltstack,
+            is_opcode_size,
+            is_opcode_hash256,
+            is_opcode_sha256,
+            is_opcode_ripemd160,
+            is_opcode_sha1,
+            is_opcode_hash160,
+            is_opcode_cltv,
+            is_opcode_csv,
+            is_opcode_codeseparator,
+            is_opcode_nop,
+            is_opcode_disabled,
+            script_rlc_acc,
+            num_script_bytes_remaining,
+            num_script_bytes_remaining_inv,
+            num_script_bytes_remaining_is_zero,
+            stack,
+            stack_size,
+            stack_size_inv,
+            stack_surplus_bytes,
+            stack_len,
+            condition_stack,
+            num_false_conditions,
+            num_false_conditions_inv,
+            is_prev_branch_active,
+            alt_stack,
+            alt_stack_size,
+            alt_stack_size_inv,
+            is_prev_alt_stack_empty,
+            alt_stack_full_inv,
+            is_prev_alt_stack_full,
+            alt_stack_len,
+            is_stack_top_false_inv,
+            is_stack_top_false,
+            is_prev_stack_top_false_inv,
+            is_prev_stack_top_false,
+            is_prev_stack_second_false_inv,
+            is_prev_stack_second_false,
+            are_top_two_equal_inv,
+            are_top_two_equal,
+            is_next_byte_negative_one_inv,
+            is_next_byte_negative_one,
+            top_num,
+            second_num,
+            third_num,
+            result_num,
+            are_top_two_nums_equal_inv,
+            are_top_two_nums_equal,
+            second_num_lt_top_num,
+            third_num_lt_second_num,
+            third_num_lt_top_num,
+            num_data_bytes_remaining,
+            num_data_bytes_remaining_inv,
+            num_data_bytes_remaining_is_zero,
+            num_data_length_bytes_remaining,
+            num_data_length_bytes_remaining_inv,
+            num_data_length_bytes_remaining_is_zero,
+            num_data_length_bytes_remaining_minus_one_inv,
+            num_data_length_bytes_remaining_is_one,
+            num_data_length_acc_constant,
+            pk_rlc_acc,
+            num_checksig_opcodes,
+            num_verified_checksigs,
+            hash_rlc_acc,
+            num_script_bytes_read,
+            last_codeseparator_position,
+            nlocktime,
+            is_nlocktime_height,
+            locktime_threshold_distance_bytes,
+            nlocktime_threshold_distance_bytes,
+            nlocktime_surplus_bytes,
+            nsequence,
+            nsequence_bytes,
+            sequence_locktime_bits,
+            nsequence_bits,
+            nsequence_surplus_bytes,
+            multisig_num_keys,
+            multisig_num_sigs,
+            multisig_shift,
+            multisig_sigs,
+            is_multisig_key_signed,
+            multisig_pk_rlc_acc,
+            multisig_num_invalid_sigs_inv,
+            are_multisig_sigs_valid,
+            pick_index,
+        }
+    }
+
+    /// Returns an error if the scriptPubkey is longer than MAX_SCRIPT_PUBKEY_SIZE
+    pub(crate) fn assign_script_pubkey_unroll(
+        &self,
+        config: ExecutionConfig<F, STACK_DEPTH>,
+        layouter: &mut impl Layouter<F>,
+        script_pubkey: Vec<u8>,
+        randomness: F,
+        initial_stack: [F; STACK_DEPTH],
+    ) -> Result<ExecutionChipAssignedCells<F>, Error> {
+        check_script_pubkey_size(&script_pubkey)?;
+
+        OpcodeTableChip::load(config.opcode_table.clone(), layouter)?;
+
+        self.assign_script_pubkey_region(config, layouter, script_pubkey, randomness, InitialStack::Values(initial_stack), true)
+    }
+
+    /// Unrolls a scriptSig on an empty stack and then the scriptPubkey on the stack left by the
+    /// scriptSig, in separate regions. The stack is copied from the last row of the scriptSig
+    /// region to the first row of the scriptPubkey region. Only the scriptPubkey must leave a true
+    /// stack top. Returns the cells of the scriptSig and the scriptPubkey regions.
+    pub(crate) fn assign_script_sig_then_pubkey(
+        &self,
+        config: ExecutionConfig<F, STACK_DEPTH>,
+        layouter: &mut impl Layouter<F>,
+        script_sig: Vec<u8>,
+        script_pubkey: Vec<u8>,
+        randomness: F,
+    ) -> Result<(ExecutionChipAssignedCells<F>, ExecutionChipAssignedCells<F>), Error> {
+        check_script_pubkey_size(&script_sig)?;
+        check_script_pubkey_size(&script_pubkey)?;
+
+        OpcodeTableChip::load(config.opcode_table.clone(), layouter)?;
+
+        self.assign_script_sig_then_pubkey_regions(config, layouter, script_sig, script_pubkey, randomness)
+    }
+
+    /// Unrolls a P2SH spend in four regions which share the opcode table: the scriptSig, the
+    /// scriptPubkey `OP_HASH160 <20-byte hash> OP_EQUAL` on its stack, the scriptSig without its
+    /// last push and the redeem script pushed last on the stack of the latter. The stack left by
+    /// a push-only scriptSig without its last push is the stack on which Bitcoin runs the redeem
+    /// script. Returns an error if the scriptPubkey is not a P2SH script or if the scriptSig is
+    /// not push-only or does not end with a data push. Returns the cells of the regions in this
+    /// order.
+    pub(crate) fn assign_p2sh_spend(
+        &self,
+        config: ExecutionConfig<F, STACK_DEPTH>,
+        layouter: &mut impl Layouter<F>,
+        script_sig: Vec<u8>,
+        script_pubkey: Vec<u8>,
+        randomness: F,
+    ) -> Result<Vec<ExecutionChipAssignedCells<F>>, Error> {
+        if classify_script(&script_pubkey) != ScriptType::P2SH {
+            return Err(BitcoinVmError::NotP2shScriptPubkey.into());
+        }
+        let (redeem_script_sig, redeem_script) = split_p2sh_script_sig(&script_sig)?;
+        check_script_pubkey_size(&script_sig)?;
+        check_script_pubkey_size(&redeem_script)?;
+
+        OpcodeTableChip::load(config.opcode_table.clone(), layouter)?;
+
+        let (script_sig_cells, script_pubkey_cells) = self.assign_script_sig_then_pubkey_regions(
+            config.clone(),
+            layouter,
+            script_sig,
+            script_pubkey,
+            randomness,
+        )?;
+        let (redeem_script_sig_cells, redeem_script_cells) = self.assign_script_sig_then_pubkey_regions(
+            config,
+            layouter,
+            redeem_script_sig,
+            redeem_script,
+            randomness,
+        )?;
+        Ok(vec![script_sig_cells, script_pubkey_cells, redeem_script_sig_cells, redeem_script_cells])
+    }
+
+    // Unrolls a scriptSig region and a scriptPubkey region on the stack copied from it
+    fn assign_script_sig_then_pubkey_regions(
+        &self,
+        config: ExecutionConfig<F, STACK_DEPTH>,
+        layouter: &mut impl Layouter<F>,
+        script_sig: Vec<u8>,
+        script_pubkey: Vec<u8>,
+        randomness: F,
+    ) -> Result<(ExecutionChipAssignedCells<F>, ExecutionChipAssignedCells<F>), Error> {
+        let empty_stack = [F::zero(); STACK_DEPTH];
+        let script_sig_cells = self.assign_script_pubkey_region(
+            config.clone(),
+            layouter,
+            script_sig.clone(),
+            randomness,
+            InitialStack::Values(empty_stack),
+            false,
+        )?;
+
+        let mut script_sig_state = ScriptPubkeyParseState::new(randomness, empty_stack);
+        for byte in script_sig.iter() {
+            script_sig_state.update(*byte);
+        }
+        let script_pubkey_cells = self.assign_script_pubkey_region(
+            config,
+            layouter,
+            script_pubkey,
+            randomness,
+            InitialStack::Copied {
+                stack: script_sig_state.stack,
+                stack_size: script_sig_state.stack_size,
+                stack_len: script_sig_state.stack_len,
+                known_items: &script_sig_state.known_items,
+                cells: &script_sig_cells,
+            },
+            true,
+        )?;
+        Ok((script_sig_cells, script_pubkey_cells))
+    }
+
+    /// Unrolls several scriptPubkeys in separate regions which share the opcode table. Returns an
+    /// error if any of them is longer than MAX_SCRIPT_PUBKEY_SIZE
+    pub(crate) fn assign_script_pubkeys_unroll(
+        &self,
+        config: ExecutionConfig<F, STACK_DEPTH>,
+        layouter: &mut impl Layouter<F>,
+        script_pubkeys: &[Vec<u8>],
+        randomness: F,
+        initial_stack: [F; STACK_DEPTH],
+    ) -> Result<Vec<ExecutionChipAssignedCells<F>>, Error> {
+        for script_pubkey in script_pubkeys {
+            check_script_pubkey_size(script_pubkey)?;
+        }
+
+        OpcodeTableChip::load(config.opcode_table.clone(), layouter)?;
+
+        let mut execution_cells = vec![];
+        for script_pubkey in script_pubkeys {
+            execution_cells.push(self.assign_script_pubkey_region(
+                config.clone(),
+                layouter,
+                script_pubkey.clone(),
+                randomness,
+                InitialStack::Values(initial_stack),
+                true,
+            )?);
+        }
+        Ok(execution_cells)
+    }
+
+    // Unrolls a scriptPubkey, or a scriptSig if `is_script_pubkey` is false, in its own region
+    fn assign_script_pubkey_region(
+        &self,
+        config: ExecutionConfig<F, STACK_DEPTH>,
+        layouter: &mut impl Layouter<F>,
+        script_pubkey: Vec<u8>,
+        randomness: F,
+        initial: InitialStack<'_, F, STACK_DEPTH>,
+        is_script_pubkey: bool,
+    ) -> Result<ExecutionChipAssignedCells<F>, Error> {
+        let (initial_stack, initial_stack_size, initial_stack_len) = match initial {
+            InitialStack::Values(stack) => (stack, initial_stack_size(&stack), initial_stack_len(&stack)),
+            InitialStack::Copied { stack, stack_size, stack_len, .. } => (stack, stack_size, stack_len),
+        };
+        layouter.assign_region(
+            || if is_script_pubkey { "ScriptPubkey unrolling" } else { "ScriptSig unrolling" },
+            |mut region: Region<F>| {
+
+                config.q_first.enable(&mut region, 0)?;
+
+                macro_rules! assign_first_row {
+                    ($annotation:expr, $column:ident, $val:expr) => {
+                        region.assign_advice(
+                            || $annotation,
+                            config.$column,
+                            0,
+                            || Value::known($val),
+                        )?
+                    };
+                    ($annotation:expr, $column:ident) => {
+                        region.assign_advice(
+                            || $annotation,
+                            config.$column,
+                            0,
+                            || Value::known(F::zero()),
+                        )?
+                    };
+                }
+
+                let script_length_cell = assign_first_row!(
+                    "Byte length of scriptPubkey",
+                    num_script_bytes_remaining,
+                    F::from(script_pubkey.len() as u64)
+                );
+
+                let randomness_cell =
+                    assign_first_row!("Randomness of RLC operations", randomness, randomness);
+                let nlocktime_cell =
+                    assign_first_row!("nLockTime of the transaction", nlocktime, F::from(self.nlocktime as u64));
+                let nsequence_cell =
+                    assign_first_row!("nSequence of the input", nsequence, F::from(self.nsequence as u64));
+
+                let mut final_stack_size_cell = match initial {
+                    InitialStack::Values(_) =>
+                        assign_first_row!("Initialize stack_size", stack_size, initial_stack_size),
+                    InitialStack::Copied { cells, .. } =>
+                        cells.final_stack_size.copy_advice(|| "Copy stack_size", &mut region, config.stack_size, 0)?,
+                };
+                assign_first_row!("Initialize num_false_conditions to zero", num_false_conditions);
+                for i in 0..MAX_CONDITION_DEPTH {
+                    region.assign_advice(
+                        || "Initialize condition stack to zero elements",
+                        config.condition_stack[i],
+                        0,
+                        || Value::known(F::zero()),
+                    )?;
+                }
+                assign_first_row!("Initialize alt_stack_size to zero", alt_stack_size);
+                for i in 0..MAX_ALTSTACK_DEPTH {
+                    region.assign_advice(
+                        || "Initialize alt stack to zero elements",
+                        config.alt_stack[i],
+                        0,
+                        || Value::known(F::zero()),
+                    )?;
+                    region.assign_advice(
+                        || "Initialize alt stack lengths to zero",
+                        config.alt_stack_len[i],
+                        0,
+                        || Value::known(F::zero()),
+                    )?;
+                }
+
+                let mut final_stack_cells = match initial {
+                    InitialStack::Values(_) => (0..STACK_DEPTH)
+                        .map(|i| region.assign_advice(
+                            || "Initialize stack elements",
+                            config.stack[i],
+                            0,
+                            || Value::known(initial_stack[i]),
+                        ))
+                        .collect::<Result<Vec<_>, Error>>()?,
+                    InitialStack::Copied { cells, .. } => cells.final_stack
+                        .iter()
+                        .zip(config.stack.iter())
+                        .map(|(cell, column)| cell.copy_advice(|| "Copy stack elements", &mut region, *column, 0))
+                        .collect::<Result<Vec<_>, Error>>()?,
+                };
+                let mut final_stack_len_cells = match initial {
+                    InitialStack::Values(_) => (0..STACK_DEPTH)
+                        .map(|i| region.assign_advice(
+                            || "Initialize stack lengths",
+                            config.stack_len[i],
+                            0,
+                            || Value::known(F::from(initial_stack_len[i])),
+                        ))
+                        .collect::<Result<Vec<_>, Error>>()?,
+                    InitialStack::Copied { cells, .. } => cells.final_stack_len
+                        .iter()
+                        .zip(config.stack_len.iter())
+                        .map(|(cell, column)| cell.copy_advice(|| "Copy stack lengths", &mut region, *column, 0))
+                        .collect::<Result<Vec<_>, Error>>()?,
+                };
+                if let InitialStack::Values(_) = initial {
+                    config.q_initial_stack.enable(&mut region, 0)?;
+                    assign_initial_stack_flags(&mut region, &config, &initial_stack)?;
+                }
+                if !is_script_pubkey {
+                    config.q_empty_initial_stack.enable(&mut region, 0)?;
+                }
+
+                assign_first_row!("Initialize num_data_bytes_remaining to zero", num_data_bytes_remaining);
+                assign_first_row!("Initialize num_data_length_bytes_remaining to zero", num_data_length_bytes_remaining);
+                assign_first_row!("Initialize num_data_length_acc_constant to zero", num_data_length_acc_constant);
+                let mut pk_rlc_acc_cell =
+                    assign_first_row!("Initialize pk_rlc_acc to zero", pk_rlc_acc);
+                let mut num_checksig_opcodes_cell =
+                    assign_first_row!("Initialize num_checksig_opcodes to zero", num_checksig_opcodes);
+                let mut num_verified_checksigs_cell =
+                    assign_first_row!("Initialize num_verified_checksigs to zero", num_verified_checksigs);
+                let mut hash_rlc_acc_cell =
+                    assign_first_row!("Initialize hash_rlc_acc to zero", hash_rlc_acc);
+                assign_first_row!("Initialize num_script_bytes_read to zero", num_script_bytes_read);
+                let mut last_codeseparator_position_cell = assign_first_row!(
+                    "Initialize last_codeseparator_position to zero",
+                    last_codeseparator_position
+                );
+
+                let mut script_rlc_acc_vec = vec![];
+                let mut acc_value = F::zero();
+                script_rlc_acc_vec.push(acc_value);
+
+                for i in (0..script_pubkey.len()).rev() {
+                    acc_value = acc_value * randomness + F::from(script_pubkey[i] as u64);
+                    script_rlc_acc_vec.push(acc_value);
+                }
+
+                // Reverse the script_rlc_acc running sum vector
+                script_rlc_acc_vec.reverse();
+
+                let script_rlc_acc_init_cell =
+                    assign_first_row!("Initialize script_rlc_acc", script_rlc_acc, script_rlc_acc_vec[0]);
+
+                let num_script_bytes_remaining_is_zero_chip
+                    = IsZeroChip::construct(config.num_script_bytes_remaining_is_zero.clone());
+                let is_stack_top_false_chip
+                    = IsZeroChip::construct(config.is_stack_top_false.clone());
+                let is_prev_stack_top_false_chip
+                    = IsZeroChip::construct(config.is_prev_stack_top_false.clone());
+                let is_prev_stack_second_false_chip
+                    = IsZeroChip::construct(config.is_prev_stack_second_false.clone());
+                let is_prev_branch_active_chip
+                    = IsZeroChip::construct(config.is_prev_branch_active.clone());
+                let is_prev_alt_stack_empty_chip
+                    = IsZeroChip::construct(config.is_prev_alt_stack_empty.clone());
+                let is_prev_alt_stack_full_chip
+                    = IsZeroChip::construct(config.is_prev_alt_stack_full.clone());
+                let are_multisig_sigs_valid_chip
+                    = IsZeroChip::construct(config.are_multisig_sigs_valid.clone());
+                let are_top_two_equal_chip
+                    = IsZeroChip::construct(config.are_top_two_equal.clone());
+                let is_next_byte_negative_one_chip
+                    = IsZeroChip::construct(config.is_next_byte_negative_one.clone());
+                let num_data_bytes_remaining_is_zero_chip
+                    = IsZeroChip::construct(config.num_data_bytes_remaining_is_zero.clone());
+                let num_data_length_bytes_remaining_is_zero_chip
+                    = IsZeroChip::construct(config.num_data_length_bytes_remaining_is_zero.clone());
+                let num_data_length_bytes_remaining_is_one_chip
+                    = IsZeroChip::construct(config.num_data_length_bytes_remaining_is_one.clone());
+                let top_num_chip = ScriptNumChip::construct(config.top_num.clone());
+                let second_num_chip = ScriptNumChip::construct(config.second_num.clone());
+                let third_num_chip = ScriptNumChip::construct(config.third_num.clone());
+                let result_num_chip = ScriptNumChip::construct(config.result_num.clone());
+                let are_top_two_nums_equal_chip
+                    = IsZeroChip::construct(config.are_top_two_nums_equal.clone());
+                let second_num_lt_top_num_chip = LessThanChip::construct(config.second_num_lt_top_num.clone());
+                let third_num_lt_second_num_chip = LessThanChip::construct(config.third_num_lt_second_num.clone());
+                let third_num_lt_top_num_chip = LessThanChip::construct(config.third_num_lt_top_num.clone());
+
+                let mut script_state = ScriptPubkeyParseState::new(randomness, initial_stack);
+                // A stack left by a scriptSig may have items whose value is zero
+                script_state.stack_size = initial_stack_size;
+                script_state.stack_len = initial_stack_len;
+                if let InitialStack::Copied { known_items, .. } = initial {
+                    script_state.known_items = known_items.to_vec();
+                }
+
+                for byte_index in 0..MAX_SCRIPT_PUBKEY_SIZE+1 { // an extra row is assigned as queries are made to next rows
+                    
+                    let offset = byte_index + 1;
+                    // Stack before the current byte is processed
+                    let prev_stack = script_state.stack;
+                    let prev_num_false_conditions = script_state.num_false_conditions;
+                    let prev_alt_stack_size = script_state.alt_stack_size;
+                    let prev_pk_rlc_acc = script_state.pk_rlc_acc;
+                    
+                    if byte_index != MAX_SCRIPT_PUBKEY_SIZE {
+                        config.q_execution.enable(&mut region, offset)?;
+                    }
+                    // The last row with q_execution enabled holds the final state
+                    if is_script_pubkey && offset == MAX_SCRIPT_PUBKEY_SIZE {
+                        config.q_script_end.enable(&mut region, offset)?;
+                    }
+
+                    region.assign_advice(
+                        || "Randomness for RLC operations",
+                        config.randomness,
+                        offset,
+                        || Value::known(randomness),
+                    )?;
+
+                    region.assign_advice(
+                        || "nLockTime of the transaction",
+                        config.nlocktime,
+                        offset,
+                        || Value::known(F::from(self.nlocktime as u64)),
+                    )?;
+
+                    region.assign_advice(
+                        || "nSequence of the input",
+                        config.nsequence,
+                        offset,
+                        || Value::known(F::from(self.nsequence as u64)),
+                    )?;
+
+                    if byte_index < script_pubkey.len() {
+                        region.assign_advice(
+                            || "Load scriptPubkey bytes",
+                            config.opcode,
+                            offset,
+                            || Value::known(F::from(script_pubkey[byte_index] as u64)),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load script_rlc_acc intermediate values",
+                            config.script_rlc_acc,
+                            offset,
+                            || Value::known(script_rlc_acc_vec[offset]),
+                        )?;
+
+                        let num_script_bytes_remaining = F::from((script_pubkey.len() - byte_index) as u64);
+
+                        region.assign_advice(
+                            || "Load num_script_bytes_remaining values",
+                            config.num_script_bytes_remaining,
+                            offset,
+                            || Value::known(num_script_bytes_remaining),
+                        )?;
+
+                        num_script_bytes_remaining_is_zero_chip.assign(
+                            &mut region,
+                            offset,
+                            Value::known(num_script_bytes_remaining),
+                        )?;
+
+                        // The state of the script parser is updated
+                        script_state.update(script_pubkey[byte_index]);
+
+                        region.assign_advice(
+                            || "Load num_data_bytes_remaining values",
+                            config.num_data_bytes_remaining,
+                            offset,
+                            || Value::known(F::from(script_state.num_data_bytes_remaining)),
+                        )?;
+
+                        num_data_bytes_remaining_is_zero_chip.assign(
+                            &mut region,
+                            offset,
+                            Value::known(F::from(script_state.num_data_bytes_remaining)),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load num_data_length_bytes_remaining values",
+                            config.num_data_length_bytes_remaining,
+                            offset,
+                            || Value::known(F::from(script_state.num_data_length_bytes_remaining)),
+                        )?;
+
+                        num_data_length_bytes_remaining_is_zero_chip.assign(
+                            &mut region,
+                            offset,
+                            Value::known(F::from(script_state.num_data_length_bytes_remaining)),
+                        )?;
+
+                        let data_length_bytes_minus_one_val = if script_state.num_data_length_bytes_remaining > 0 {
+                            F::from(script_state.num_data_length_bytes_remaining.wrapping_sub(1))
+                        } else {
+                            -F::one()
+                        };
+                        num_data_length_bytes_remaining_is_one_chip.assign(
+                            &mut region,
+                            offset,
+                            Value::known(data_length_bytes_minus_one_val),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load num_data_length_acc_constant values",
+                            config.num_data_length_acc_constant,
+                            offset,
+                            || Value::known(F::from(script_state.num_data_length_acc_constant)),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_enabled column",
+                            config.is_opcode_enabled,
+                            offset,
+                            || Value::known(F::from(opcode_enabled(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_op0 column",
+                            config.is_opcode_op0,
+                            offset,
+                            || Value::known(F::from(op0_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_op1_to_op16 column",
+                            config.is_opcode_op1_to_op16,
+                            offset,
+                            || Value::known(F::from(op1_to_op16_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_push1_to_push75 column",
+                            config.is_opcode_push1_to_push75,
+                            offset,
+                            || Value::known(F::from(push1_to_push75_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_pushdata1 column",
+                            config.is_opcode_pushdata1,
+                            offset,
+                            || Value::known(F::from(pushdata1_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_pushdata2 column",
+                            config.is_opcode_pushdata2,
+                            offset,
+                            || Value::known(F::from(pushdata2_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_pushdata4 column",
+                            config.is_opcode_pushdata4,
+                            offset,
+                            || Value::known(F::from(pushdata4_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_checksig column",
+                            config.is_opcode_checksig,
+                            offset,
+                            || Value::known(F::from(checksig_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_equal column",
+                            config.is_opcode_equal,
+                            offset,
+                            || Value::known(F::from(equal_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_equalverify column",
+                            config.is_opcode_equalverify,
+                            offset,
+                            || Value::known(F::from(equalverify_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_verify column",
+                            config.is_opcode_verify,
+                            offset,
+                            || Value::known(F::from(verify_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_drop column",
+                            config.is_opcode_drop,
+                            offset,
+                            || Value::known(F::from(drop_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_2drop column",
+                            config.is_opcode_2drop,
+                            offset,
+                            || Value::known(F::from(op_2drop_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_swap column",
+                            config.is_opcode_swap,
+                            offset,
+                            || Value::known(F::from(swap_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_over column",
+                            config.is_opcode_over,
+                            offset,
+                            || Value::known(F::from(over_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_rot column",
+                            config.is_opcode_rot,
+                            offset,
+                            || Value::known(F::from(rot_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_nip column",
+                            config.is_opcode_nip,
+                            offset,
+                            || Value::known(F::from(nip_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_tuck column",
+                            config.is_opcode_tuck,
+                            offset,
+                            || Value::known(F::from(tuck_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_2dup column",
+                            config.is_opcode_2dup,
+                            offset,
+                            || Value::known(F::from(op_2dup_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_3dup column",
+                            config.is_opcode_3dup,
+                            offset,
+                            || Value::known(F::from(op_3dup_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_2over column",
+                            config.is_opcode_2over,
+                            offset,
+                            || Value::known(F::from(op_2over_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_2rot column",
+                            config.is_opcode_2rot,
+                            offset,
+                            || Value::known(F::from(op_2rot_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_2swap column",
+                            config.is_opcode_2swap,
+                            offset,
+                            || Value::known(F::from(op_2swap_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_pick column",
+                            config.is_opcode_pick,
+                            offset,
+                            || Value::known(F::from(pick_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_roll column",
+                            config.is_opcode_roll,
+                            offset,
+                            || Value::known(F::from(roll_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_depth column",
+                            config.is_opcode_depth,
+                            offset,
+                            || Value::known(F::from(depth_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_add column",
+                            config.is_opcode_add,
+                            offset,
+                            || Value::known(F::from(add_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_sub column",
+                            config.is_opcode_sub,
+                            offset,
+                            || Value::known(F::from(sub_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_1add column",
+                            config.is_opcode_1add,
+                            offset,
+                            || Value::known(F::from(op_1add_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_1sub column",
+                            config.is_opcode_1sub,
+                            offset,
+                            || Value::known(F::from(op_1sub_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_negate column",
+                            config.is_opcode_negate,
+                            offset,
+                            || Value::known(F::from(negate_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_abs column",
+                            config.is_opcode_abs,
+                            offset,
+                            || Value::known(F::from(abs_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_lessthan column",
+                            config.is_opcode_lessthan,
+                            offset,
+                            || Value::known(F::from(lessthan_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_greaterthan column",
+                            config.is_opcode_greaterthan,
+                            offset,
+                            || Value::known(F::from(greaterthan_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_lessthanorequal column",
+                            config.is_opcode_lessthanorequal,
+                            offset,
+                            || Value::known(F::from(lessthanorequal_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_greaterthanorequal column",
+                            config.is_opcode_greaterthanorequal,
+                            offset,
+                            || Value::known(F::from(greaterthanorequal_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_min column",
+                            config.is_opcode_min,
+                            offset,
+                            || Value::known(F::from(min_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_max column",
+                            config.is_opcode_max,
+                            offset,
+                            || Value::known(F::from(max_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_within column",
+                            config.is_opcode_within,
+                            offset,
+                            || Value::known(F::from(within_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_not column",
+                            config.is_opcode_not,
+                            offset,
+                            || Value::known(F::from(not_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_0notequal column",
+                            config.is_opcode_0notequal,
+                            offset,
+                            || Value::known(F::from(op_0notequal_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_booland column",
+                            config.is_opcode_booland,
+                            offset,
+                            || Value::known(F::from(booland_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_boolor column",
+                            config.is_opcode_boolor,
+                            offset,
+                            || Value::known(F::from(boolor_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_if column",
+                            config.is_opcode_if,
+                            offset,
+                            || Value::known(F::from(if_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_notif column",
+                            config.is_opcode_notif,
+                            offset,
+                            || Value::known(F::from(notif_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_else column",
+                            config.is_opcode_else,
+                            offset,
+                            || Value::known(F::from(else_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_endif column",
+                            config.is_opcode_endif,
+                            offset,
+                            || Value::known(F::from(endif_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_checkmultisig column",
+                            config.is_opcode_checkmultisig,
+                            offset,
+                            || Value::known(F::from(checkmultisig_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_checksigverify column",
+                            config.is_opcode_checksigverify,
+                            offset,
+                            || Value::known(F::from(checksigverify_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_numequal column",
+                            config.is_opcode_numequal,
+                            offset,
+                            || Value::known(F::from(numequal_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_checksigadd column",
+                            config.is_opcode_checksigadd,
+                            offset,
+                            || Value::known(F::from(checksigadd_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_push1 column",
+                            config.is_opcode_push1,
+                            offset,
+                            || Value::known(F::from(push1_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_push1_to_push16 column",
+                            config.is_opcode_push1_to_push16,
+                            offset,
+                            || Value::known(F::from(push1_to_push16_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_toaltstack column",
+                            config.is_opcode_toaltstack,
+                            offset,
+                            || Value::known(F::from(toaltstack_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_fromaltstack column",
+                            config.is_opcode_fromaltstack,
+                            offset,
+                            || Value::known(F::from(fromaltstack_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_size column",
+                            config.is_opcode_size,
+                            offset,
+                            || Value::known(F::from(size_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_hash256 column",
+                            config.is_opcode_hash256,
+                            offset,
+                            || Value::known(F::from(hash256_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_sha256 column",
+                            config.is_opcode_sha256,
+                            offset,
+                            || Value::known(F::from(sha256_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_ripemd160 column",
+                            config.is_opcode_ripemd160,
+                            offset,
+                            || Value::known(F::from(ripemd160_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_sha1 column",
+                            config.is_opcode_sha1,
+                            offset,
+                            || Value::known(F::from(sha1_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_hash160 column",
+                            config.is_opcode_hash160,
+                            offset,
+                            || Value::known(F::from(hash160_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_cltv column",
+                            config.is_opcode_cltv,
+                            offset,
+                            || Value::known(F::from(cltv_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_csv column",
+                            config.is_opcode_csv,
+                            offset,
+                            || Value::known(F::from(csv_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_codeseparator column",
+                            config.is_opcode_codeseparator,
+                            offset,
+                            || Value::known(F::from(codeseparator_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_nop column",
+                            config.is_opcode_nop,
+                            offset,
+                            || Value::known(F::from(nop_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_disabled column",
+                            config.is_opcode_disabled,
+                            offset,
+                            || Value::known(F::from(disabled_indicator(script_pubkey[byte_index]))),
+                        )?;
+
+                    }
+                    else {
+
+                        if byte_index != MAX_SCRIPT_PUBKEY_SIZE {
+                            region.assign_advice(
+                                || "Load scriptPubkey padding bytes",
+                                config.opcode,
+                                offset,
+                                || Value::known(F::from(OP_NOP as u64)),
+                            )?;
+
+                            region.assign_advice(
+                                || "Load is_opcode_enabled column",
+                                config.is_opcode_enabled,
+                                offset,
+                                || Value::known(F::one()),
+                            )?;
+                        }
+                        else {
+                            region.assign_advice(
+                                || "Load scriptPubkey padding bytes",
+                                config.opcode,
+                                offset,
+                                || Value::known(F::zero()),
+                            )?;
+
+                            region.assign_advice(
+                                || "Load is_opcode_enabled column",
+                                config.is_opcode_enabled,
+                                offset,
+                                || Value::known(F::zero()),
+                            )?;
+                        }
+
+                        region.assign_advice(
+                            || "Load script_rlc_acc padding",
+                            config.script_rlc_acc,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load num_script_bytes_remaining values",
+                            config.num_script_bytes_remaining,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        num_script_bytes_remaining_is_zero_chip.assign(
+                            &mut region,
+                            offset,
+                            Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load num_data_bytes_remaining values",
+                            config.num_data_bytes_remaining,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        num_data_bytes_remaining_is_zero_chip.assign(
+                            &mut region,
+                            offset,
+                            Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load num_data_length_bytes_remaining values",
+                            config.num_data_length_bytes_remaining,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        num_data_length_bytes_remaining_is_zero_chip.assign(
+                            &mut region,
+                            offset,
+                            Value::known(F::zero()),
+                        )?;
+
+                        num_data_length_bytes_remaining_is_one_chip.assign(
+                            &mut region,
+                            offset,
+                            Value::known(-F::one()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load num_data_length_acc_constant values",
+                            config.num_data_length_acc_constant,
+                            offset,
+                            || Value::known(F::from(script_state.num_data_length_acc_constant)),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_op0 column",
+                            config.is_opcode_op0,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_op1_to_op16 column",
+                            config.is_opcode_op1_to_op16,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_push1_to_push75 column",
+                            config.is_opcode_push1_to_push75,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_pushdata1 column",
+                            config.is_opcode_pushdata1,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_pushdata2 column",
+                            config.is_opcode_pushdata2,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_pushdata4 column",
+                            config.is_opcode_pushdata4,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_checksig column",
+                            config.is_opcode_checksig,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_equal column",
+                            config.is_opcode_equal,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_equalverify column",
+                            config.is_opcode_equalverify,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_verify column",
+                            config.is_opcode_verify,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_drop column",
+                            config.is_opcode_drop,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_2drop column",
+                            config.is_opcode_2drop,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_swap column",
+                            config.is_opcode_swap,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_over column",
+                            config.is_opcode_over,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_rot column",
+                            config.is_opcode_rot,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_nip column",
+                            config.is_opcode_nip,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_tuck column",
+                            config.is_opcode_tuck,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_2dup column",
+                            config.is_opcode_2dup,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_3dup column",
+                            config.is_opcode_3dup,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_2over column",
+                            config.is_opcode_2over,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_2rot column",
+                            config.is_opcode_2rot,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_2swap column",
+                            config.is_opcode_2swap,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_pick column",
+                            config.is_opcode_pick,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_roll column",
+                            config.is_opcode_roll,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_depth column",
+                            config.is_opcode_depth,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_add column",
+                            config.is_opcode_add,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_sub column",
+                            config.is_opcode_sub,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_1add column",
+                            config.is_opcode_1add,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_1sub column",
+                            config.is_opcode_1sub,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_negate column",
+                            config.is_opcode_negate,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_abs column",
+                            config.is_opcode_abs,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_lessthan column",
+                            config.is_opcode_lessthan,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_greaterthan column",
+                            config.is_opcode_greaterthan,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_lessthanorequal column",
+                            config.is_opcode_lessthanorequal,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_greaterthanorequal column",
+                            config.is_opcode_greaterthanorequal,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_min column",
+                            config.is_opcode_min,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_max column",
+                            config.is_opcode_max,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_within column",
+                            config.is_opcode_within,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_not column",
+                            config.is_opcode_not,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_0notequal column",
+                            config.is_opcode_0notequal,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_booland column",
+                            config.is_opcode_booland,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_boolor column",
+                            config.is_opcode_boolor,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_if column",
+                            config.is_opcode_if,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_notif column",
+                            config.is_opcode_notif,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_else column",
+                            config.is_opcode_else,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_endif column",
+                            config.is_opcode_endif,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_checkmultisig column",
+                            config.is_opcode_checkmultisig,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_checksigverify column",
+                            config.is_opcode_checksigverify,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_numequal column",
+                            config.is_opcode_numequal,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_checksigadd column",
+                            config.is_opcode_checksigadd,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_push1 column",
+                            config.is_opcode_push1,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_push1_to_push16 column",
+                            config.is_opcode_push1_to_push16,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_toaltstack column",
+                            config.is_opcode_toaltstack,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_fromaltstack column",
+                            config.is_opcode_fromaltstack,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_size column",
+                            config.is_opcode_size,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_hash256 column",
+                            config.is_opcode_hash256,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_sha256 column",
+                            config.is_opcode_sha256,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_ripemd160 column",
+                            config.is_opcode_ripemd160,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_sha1 column",
+                            config.is_opcode_sha1,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_hash160 column",
+                            config.is_opcode_hash160,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_cltv column",
+                            config.is_opcode_cltv,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_csv column",
+                            config.is_opcode_csv,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_codeseparator column",
+                            config.is_opcode_codeseparator,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                        // Padding bytes are OP_NOP, except in the last row
+                        region.assign_advice(
+                            || "Load is_opcode_nop column",
+                            config.is_opcode_nop,
+                            offset,
+                            || Value::known(F::from((byte_index != MAX_SCRIPT_PUBKEY_SIZE) as u64)),
+                        )?;
+
+                        region.assign_advice(
+                            || "Load is_opcode_disabled column",
+                            config.is_opcode_disabled,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+
+                    }
+
+                    let stack_cells = (0..STACK_DEPTH)
+                        .map(|i| region.assign_advice(
+                            || "Load stack values",
+                            config.stack[i],
+                            offset,
+                            || Value::known(script_state.stack[i]),
+                        ))
+                        .collect::<Result<Vec<_>, Error>>()?;
+
+                    let stack_size_cell = region.assign_advice(
+                        || "Load stack_size column",
+                        config.stack_size,
+                        offset,
+                        || Value::known(script_state.stack_size),
+                    )?;
+
+                    let stack_len_cells = (0..STACK_DEPTH)
+                        .map(|i| region.assign_advice(
+                            || "Load stack_len values",
+                            config.stack_len[i],
+                            offset,
+                            || Value::known(F::from(script_state.stack_len[i])),
+                        ))
+                        .collect::<Result<Vec<_>, Error>>()?;
+
+                    if offset == MAX_SCRIPT_PUBKEY_SIZE {
+                        final_stack_cells = stack_cells;
+                        final_stack_size_cell = stack_size_cell;
+                        final_stack_len_cells = stack_len_cells;
+                    }
+
+                    for i in 0..MAX_CONDITION_DEPTH {
+                        region.assign_advice(
+                            || "Load condition stack values",
+                            config.condition_stack[i],
+                            offset,
+                            || Value::known(F::from(script_state.condition_stack[i])),
+                        )?;
+                    }
+
+                    region.assign_advice(
+                        || "Load num_false_conditions column",
+                        config.num_false_conditions,
+                        offset,
+                        || Value::known(F::from(script_state.num_false_conditions)),
+                    )?;
+
+                    is_prev_branch_active_chip.assign(
+                        &mut region,
+                        offset,
+                        Value::known(F::from(prev_num_false_conditions)),
+                    )?;
+
+                    for i in 0..MAX_ALTSTACK_DEPTH {
+                        region.assign_advice(
+                            || "Load alt stack values",
+                            config.alt_stack[i],
+                            offset,
+                            || Value::known(script_state.alt_stack[i]),
+                        )?;
+                        region.assign_advice(
+                            || "Load alt stack lengths",
+                            config.alt_stack_len[i],
+                            offset,
+                            || Value::known(F::from(script_state.alt_stack_len[i])),
+                        )?;
+                    }
+
+                    region.assign_advice(
+                        || "Load alt_stack_size column",
+                        config.alt_stack_size,
+                        offset,
+                        || Value::known(F::from(script_state.alt_stack_size)),
+                    )?;
+
+                    is_prev_alt_stack_empty_chip.assign(
+                        &mut region,
+                        offset,
+                        Value::known(F::from(prev_alt_stack_size)),
+                    )?;
+
+                    is_prev_alt_stack_full_chip.assign(
+                        &mut region,
+                        offset,
+                        Value::known(F::from(prev_alt_stack_size) - F::from(MAX_ALTSTACK_DEPTH as u64)),
+                    )?;
+
+                    // The OP_CHECKMULTISIG columns are zero in other rows
+                    let (num_keys, num_sigs, are_sigs_valid) = match script_state.multisig {
+                        Some(multisig) if byte_index < script_pubkey.len() => multisig,
+                        _ => (0, 0, false),
+                    };
+                    let mut multisig_pk_rlc_acc = prev_pk_rlc_acc;
+                    let mut num_valid_sigs = 0u64;
+                    for i in (0..MAX_MULTISIG_N).rev() {
+                        let sig = if i < num_sigs { prev_stack[num_keys + 2 + i] } else { F::zero() };
+                        if sig == F::one() {
+                            num_valid_sigs += 1;
+                        }
+                        let is_key_signed = are_sigs_valid && i < num_sigs;
+                        if is_key_signed {
+                            multisig_pk_rlc_acc = multisig_pk_rlc_acc * randomness + prev_stack[i + 1];
+                        }
+                        for (column, value) in [
+                            (config.multisig_num_keys[i], F::from((num_keys == i + 1) as u64)),
+                            (config.multisig_num_sigs[i], F::from((num_sigs == i + 1) as u64)),
+                            (config.multisig_sigs[i], sig),
+                            (config.is_multisig_key_signed[i], F::from(is_key_signed as u64)),
+                            (config.multisig_pk_rlc_acc[i], multisig_pk_rlc_acc),
+                        ] {
+                            region.assign_advice(
+                                || "Load OP_CHECKMULTISIG columns",
+                                column,
+                                offset,
+                                || Value::known(value),
+                            )?;
+                        }
+                    }
+                    for i in 0..2*MAX_MULTISIG_N-1 {
+                        let is_shift = num_keys > 0 && num_keys + num_sigs + 2 == i + 4;
+                        region.assign_advice(
+                            || "Load OP_CHECKMULTISIG shift",
+                            config.multisig_shift[i],
+                            offset,
+                            || Value::known(F::from(is_shift as u64)),
+                        )?;
+                    }
+
+                    // The index of the item picked by OP_PICK or OP_ROLL is zero in other rows
+                    let pick_depth = match script_state.pick_depth {
+                        Some(depth) if byte_index < script_pubkey.len() => Some(depth),
+                        _ => None,
+                    };
+                    for (i, column) in config.pick_index.iter().enumerate() {
+                        region.assign_advice(
+                            || "Load pick_index column",
+                            *column,
+                            offset,
+                            || Value::known(F::from((pick_depth.map(|depth| depth + 1) == Some(i)) as u64)),
+                        )?;
+                    }
+
+                    // Stack items below the operands of an executed opcode. Its bytes fail the
+                    // range check if the opcode pops more items than are on the stack.
+                    let is_executed_opcode = byte_index < script_pubkey.len()
+                        && script_state.num_data_bytes_remaining == 0
+                        && script_state.num_data_length_bytes_remaining == 0
+                        && prev_num_false_conditions == 0;
+                    let stack_surplus = if is_executed_opcode {
+                        let opcode = script_pubkey[byte_index] as usize;
+                        let num_operands = if opcode == OP_CHECKMULTISIG && num_keys > 0 {
+                            num_keys + num_sigs + 3
+                        } else if let Some(depth) = script_state.pick_depth {
+                            depth + 2
+                        } else {
+                            num_stack_operands(opcode)
+                        };
+                        prev_stack_size - F::from(num_operands as u64)
+                    } else {
+                        F::zero()
+                    };
+                    let stack_surplus_repr = stack_surplus.to_repr();
+                    for (i, column) in config.stack_surplus_bytes.iter().enumerate() {
+                        region.assign_advice(
+                            || "Load stack_surplus_bytes column",
+                            *column,
+                            offset,
+                            || Value::known(F::from(stack_surplus_repr[i] as u64)),
+                        )?;
+                    }
+                    are_multisig_sigs_valid_chip.assign(
+                        &mut region,
+                        offset,
+                        Value::known(F::from(num_sigs as u64) - F::from(num_valid_sigs)),
+                    )?;
+
+                    // The OP_CHECKLOCKTIMEVERIFY columns are zero in other rows. Distances which
+                    // are negative or too large are left at zero, which fails the gate.
+                    let locktime = match script_state.num_operands.first() {
+                        Some(Some(locktime)) if is_executed_opcode
+                            && script_pubkey[byte_index] as usize == OP_CHECKLOCKTIMEVERIFY => Some(*locktime),
+                        _ => None,
+                    };
+                    let nlocktime = self.nlocktime as i64;
+                    let threshold = LOCKTIME_THRESHOLD as i64;
+                    let is_nlocktime_height = locktime.is_some() && nlocktime < threshold;
+                    let threshold_distance = |value: i64| {
+                        if is_nlocktime_height { threshold - 1 - value } else { value - threshold }
+                    };
+                    let distances = match locktime {
+                        Some(locktime) => [threshold_distance(locktime), threshold_distance(nlocktime), nlocktime - locktime],
+                        None => [0; 3],
+                    };
+                    region.assign_advice(
+                        || "Load is_nlocktime_height column",
+                        config.is_nlocktime_height,
+                        offset,
+                        || Value::known(F::from(is_nlocktime_height as u64)),
+                    )?;
+                    for (columns, distance) in [
+                        config.locktime_threshold_distance_bytes,
+                        config.nlocktime_threshold_distance_bytes,
+                        config.nlocktime_surplus_bytes,
+                    ].into_iter().zip(distances) {
+                        let bytes = u32::try_from(distance).unwrap_or(0).to_le_bytes();
+                        for (column, byte) in columns.into_iter().zip(bytes) {
+                            region.assign_advice(
+                                || "Load locktime distance bytes",
+                                column,
+                                offset,
+                                || Value::known(F::from(byte as u64)),
+                            )?;
+                        }
+                    }
+
+                    // The OP_CHECKSEQUENCEVERIFY columns are zero in other rows. If the masked
+                    // locktime is above the masked nSequence, the surplus is left at zero, which
+                    // fails the gate.
+                    let sequence_locktime = match script_state.num_result {
+                        Some(locktime) if is_executed_opcode && locktime >= 0
+                            && script_pubkey[byte_index] as usize == OP_CHECKSEQUENCEVERIFY => Some(locktime as u64),
+                        _ => None,
+                    };
+                    let nsequence = sequence_locktime.map_or(0, |_| self.nsequence as u64);
+                    let sequence_bits = |value: u64| [22, 23, 31].map(|bit| (value >> bit) & 1);
+                    let nsequence_surplus = match sequence_locktime {
+                        Some(locktime) if locktime & SEQUENCE_LOCKTIME_DISABLE_FLAG == 0 =>
+                            (nsequence & SEQUENCE_LOCKTIME_MASK).checked_sub(locktime & SEQUENCE_LOCKTIME_MASK).unwrap_or(0),
+                        _ => 0,
+                    };
+                    for (columns, bytes) in [
+                        (config.nsequence_bytes.as_slice(), &nsequence.to_le_bytes()[..4]),
+                        (config.nsequence_surplus_bytes.as_slice(), &nsequence_surplus.to_le_bytes()[..2]),
+                    ] {
+                        for (column, byte) in columns.iter().zip(bytes) {
+                            region.assign_advice(
+                                || "Load nSequence bytes",
+                                *column,
+                                offset,
+                                || Value::known(F::from(*byte as u64)),
+                            )?;
+                        }
+                    }
+                    for (columns, value) in [
+                        (config.sequence_locktime_bits, sequence_locktime.unwrap_or(0)),
+                        (config.nsequence_bits, nsequence),
+                    ] {
+                        for (column, bit) in columns.into_iter().zip(sequence_bits(value)) {
+                            region.assign_advice(
+                                || "Load sequence flag bits",
+                                column,
+                                offset,
+                                || Value::known(F::from(bit)),
+                            )?;
+                        }
+                    }
+
+                    pk_rlc_acc_cell = region.assign_advice(
+                        || "Load pk_rlc_acc column",
+                        config.pk_rlc_acc,
+                        offset,
+                        || Value::known(script_state.pk_rlc_acc),
+                    )?;
+
+                    num_checksig_opcodes_cell = region.assign_advice(
+                        || "Load num_checksig_opcodes column",
+                        config.num_checksig_opcodes,
+                        offset,
+                        || Value::known(F::from(script_state.num_checksig_opcodes)),
+                    )?;
+
+                    num_verified_checksigs_cell = region.assign_advice(
+                        || "Load num_verified_checksigs column",
+                        config.num_verified_checksigs,
+                        offset,
+                        || Value::known(F::from(script_state.num_verified_checksigs)),
+                    )?;
+
+                    hash_rlc_acc_cell = region.assign_advice(
+                        || "Load hash_rlc_acc column",
+                        config.hash_rlc_acc,
+                        offset,
+                        || Value::known(script_state.hash_rlc_acc),
+                    )?;
+
+                    region.assign_advice(
+                        || "Load num_script_bytes_read column",
+                        config.num_script_bytes_read,
+                        offset,
+                        || Value::known(F::from(script_state.num_script_bytes_read)),
+                    )?;
+
+                    last_codeseparator_position_cell = region.assign_advice(
+                        || "Load last_codeseparator_position column",
+                        config.last_codeseparator_position,
+                        offset,
+                        || Value::known(F::from(script_state.last_codeseparator_position)),
+                    )?;
+
+                    is_stack_top_false_chip.assign(
+                        &mut region,
+                        offset,
+                        Value::known(script_state.stack[0] *(script_state.stack[0] - F::from(NEGATIVE_ZERO))),
+                    )?;
+
+                    is_prev_stack_top_false_chip.assign(
+                        &mut region,
+                        offset,
+                        Value::known(prev_stack[0] * (prev_stack[0] - F::from(NEGATIVE_ZERO))),
+                    )?;
+
+                    is_prev_stack_second_false_chip.assign(
+                        &mut region,
+                        offset,
+                        Value::known(prev_stack[1] * (prev_stack[1] - F::from(NEGATIVE_ZERO))),
+                    )?;
+
+                    are_top_two_equal_chip.assign(
+                        &mut region,
+                        offset,
+                        Value::known(prev_stack[0] - prev_stack[1]),
+                    )?;
+
+                    // The scriptPubkey is padded with OP_NOP up to MAX_SCRIPT_PUBKEY_SIZE bytes
+                    let next_byte = match byte_index + 1 {
+                        i if i < script_pubkey.len() => script_pubkey[i] as u64,
+                        i if i < MAX_SCRIPT_PUBKEY_SIZE => OP_NOP as u64,
+                        _ => 0,
+                    };
+                    is_next_byte_negative_one_chip.assign(
+                        &mut region,
+                        offset,
+                        Value::known(F::from(next_byte) - F::from(0x81)),
+                    )?;
+
+                    // Numbers are only decoded in the rows of numeric opcodes
+                    let (num_operands, num_result) = if byte_index < script_pubkey.len() {
+                        (script_state.num_operands.clone(), script_state.num_result)
+                    }
+                    else {
+                        (vec![], None)
+                    };
+                    let num_operand = |i: usize| num_operands.get(i).copied().flatten();
+                    top_num_chip.assign(&mut region, offset, randomness, num_operand(0))?;
+                    second_num_chip.assign(&mut region, offset, randomness, num_operand(1))?;
+                    third_num_chip.assign(&mut region, offset, randomness, num_operand(2))?;
+                    result_num_chip.assign(&mut region, offset, randomness, num_result)?;
+
+                    let (top_num, second_num, third_num) = (
+                        num_operand(0).unwrap_or(0),
+                        num_operand(1).unwrap_or(0),
+                        num_operand(2).unwrap_or(0),
+                    );
+                    are_top_two_nums_equal_chip.assign(
+                        &mut region,
+                        offset,
+                        Value::known(script_num_field(second_num - top_num)),
+                    )?;
+                    second_num_lt_top_num_chip.assign(&mut region, offset, second_num, top_num)?;
+                    third_num_lt_second_num_chip.assign(&mut region, offset, third_num, second_num)?;
+                    third_num_lt_top_num_chip.assign(&mut region, offset, third_num, top_num)?;
+
+                }
+                Ok(ExecutionChipAssignedCells {
+                        script_length: script_length_cell,
+                        script_rlc_acc_init: script_rlc_acc_init_cell,
+                        randomness: randomness_cell,
+                        pk_rlc_acc: pk_rlc_acc_cell.clone(),
+                        num_checksig_opcodes: num_checksig_opcodes_cell.clone(),
+                        num_verified_checksigs: num_verified_checksigs_cell.clone(),
+                        hash_rlc_acc: hash_rlc_acc_cell.clone(),
+                        last_codeseparator_position: last_codeseparator_position_cell.clone(),
+                        nlocktime: nlocktime_cell.clone(),
+                        nsequence: nsequence_cell.clone(),
+                        final_stack: final_stack_cells.clone(),
+                        final_stack_size: final_stack_size_cell.clone(),
+                        final_stack_len: final_stack_len_cells.clone(),
+                })
+            }
+        )
+    }
+    
+    pub fn expose_public(
+        &self,
+        config: ExecutionConfig<F, STACK_DEPTH>,
+        mut layouter: impl Layouter<F>,
+        cell: AssignedCell<F, F>,
+        row: usize,
+    ) -> Result<(), Error> {
+        layouter.constrain_instance(cell.cell(), config.instance, row)
+    }
+
+    /// Exposes the pk_rlc_acc and num_checksig_opcodes of the script in the rows following the
+    /// nSequence, so that a verifier can check the public keys used by the script against the
+    /// ones it expects instead of leaving them to the OpCheckSigChip only
+    pub(crate) fn expose_checksig_accumulators(
+        &self,
+        config: ExecutionConfig<F, STACK_DEPTH>,
+        mut layouter: impl Layouter<F>,
+        cells: &ExecutionChipAssignedCells<F>,
+        num_script_pubkeys: usize,
+    ) -> Result<(), Error> {
+        self.expose_public(
+            config.clone(),
+            layouter.namespace(|| "pk_rlc_acc"),
+            cells.pk_rlc_acc.clone(),
+            pk_rlc_acc_row(num_script_pubkeys),
+        )?;
+        self.expose_public(
+            config,
+            layouter.namespace(|| "num_checksig_opcodes"),
+            cells.num_checksig_opcodes.clone(),
+            num_checksig_opcodes_row(num_script_pubkeys),
+        )
+    }
+}
+
+// Items of the stack after an OP_PICK or OP_ROLL, given the items before it and the one-hot
+// encoding of the index of the picked item. OP_ROLL closes the gap left by the picked item.
+fn picked_stack<F: Field>(
+    prev: &[Expression<F>],
+    pick_index: &[Expression<F>],
+    is_roll: bool,
+) -> Vec<Expression<F>> {
+    let picked_item = pick_index
+        .iter()
+        .zip(prev)
+        .fold(0u8.expr(), |acc, (bit, item)| acc + bit.clone() * item.clone());
+    let mut items = vec![picked_item];
+    // Whether the index is at or below the picked item
+    let mut is_below_picked = pick_index[0].clone();
+    for (i, (item, bit)) in prev.iter().zip(pick_index).enumerate().skip(1) {
+        is_below_picked = is_below_picked + bit.clone();
+        if is_roll {
+            let next_item = prev.get(i + 1).cloned().unwrap_or_else(|| 0u8.expr());
+            items.push(item.clone() + is_below_picked.clone() * (next_item - item.clone()));
+        }
+        else {
+            items.push(item.clone());
+        }
+    }
+    items
+}
+
+// Assigns the witnesses of the "Initial stack" gate in the first row of a region, which are the
+// flags of the slots in the stack and the inverse of the deepest item
+fn assign_initial_stack_flags<F: Field, const STACK_DEPTH: usize>(
+    region: &mut Region<'_, F>,
+    config: &ExecutionConfig<F, STACK_DEPTH>,
+    initial_stack: &[F; STACK_DEPTH],
+) -> Result<(), Error> {
+    let stack_size = initial_stack.iter().rposition(|item| *item != F::zero()).map_or(0, |i| i + 1);
+    for (i, column) in config.pick_index.iter().enumerate() {
+        region.assign_advice(
+            || "Initial stack slot in the stack",
+            *column,
+            0,
+            || Value::known(F::from((i < stack_size) as u64)),
+        )?;
+    }
+    let deepest_item = stack_size.checked_sub(1).map_or(F::zero(), |i| initial_stack[i]);
+    region.assign_advice(
+        || "Inverse of the deepest initial item",
+        config.stack_size_inv,
+        0,
+        || Value::known(deepest_item.invert().unwrap_or(F::zero())),
+    )?;
+    Ok(())
+}
+
+/// Checks that a scriptPubkey fits in the unrolling region
+pub(crate) fn check_script_pubkey_size(script_pubkey: &[u8]) -> Result<(), BitcoinVmError> {
+    if script_pubkey.len() > MAX_SCRIPT_PUBKEY_SIZE {
+        return Err(BitcoinVmError::OversizedScript { length: script_pubkey.len() });
+    }
+    Ok(())
+}
+
+    
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bn256::Fr as BnScalar;
+    use halo2_proofs::circuit::{SimpleFloorPlanner, Layouter};
+    use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+    use secp256k1::constants::PUBLIC_KEY_SIZE;
+
+    use crate::bitcoinvm_circuit::constants::*;
+    use crate::bitcoinvm_circuit::error::BitcoinVmError;
+    use crate::bitcoinvm_circuit::execution::{ExecutionChip, ExecutionChipAssignedCells, ExecutionConfig, assign_initial_stack_flags, check_script_pubkey_size};
+    use crate::bitcoinvm_circuit::opcode_table::OpcodeTableChip;
+    use crate::bitcoinvm_circuit::public_inputs::{PublicInputs, EXECUTION_INSTANCE_ROWS, RANDOMNESS_ROW, SCRIPT_LENGTH_ROW, SCRIPT_RLC_ACC_ROW, nlocktime_row, nsequence_row, pk_rlc_acc_row};
+    use crate::bitcoinvm_circuit::util::advice_trace::{record_advice, format_field};
+    use crate::bitcoinvm_circuit::util::forged_advice::run_with_forged_advice;
+    use crate::bitcoinvm_circuit::util::random_script::random_valid_script;
+    use crate::bitcoinvm_circuit::util::script_num::{encode_script_num, script_num_item};
+    use crate::bitcoinvm_circuit::util::script_parser::{ScriptPubkeyParseState, StackItem, bytes_item, final_stack, hash_digest, initial_stack_len};
+    use crate::testing::{assert_proves, assert_rejects, run_and_verify};
+    use crate::Field;
+    use halo2_proofs::circuit::Value;
+
+
+    struct TestExecutionCircuit<F: Field, const STACK_DEPTH: usize = MAX_STACK_DEPTH> {
+        pub script_pubkey: Vec<u8>,
+        pub randomness: F,
+        pub initial_stack: [F; STACK_DEPTH],
+    }
+
+    impl<F: Field, const STACK_DEPTH: usize> Circuit<F> for TestExecutionCircuit<F, STACK_DEPTH> {
+        type Config = ExecutionConfig<F, STACK_DEPTH>;
+
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                script_pubkey: vec![],
+                randomness: F::zero(),
+                initial_stack: [F::zero(); STACK_DEPTH],
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            ExecutionChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            layouter: impl Layouter<F>
+        ) -> Result<(), Error> {
+            self.synthesize_with_locktimes(config, layouter, 0, 0)?;
+            Ok(())
+        }
+    }
+
+    impl<F: Field, const STACK_DEPTH: usize> TestExecutionCircuit<F, STACK_DEPTH> {
+        fn synthesize_with_locktimes(
+            &self,
+            config: ExecutionConfig<F, STACK_DEPTH>,
+            mut layouter: impl Layouter<F>,
+            nlocktime: u32,
+            nsequence: u32,
+        ) -> Result<ExecutionChipAssignedCells<F>, Error> {
+            let chip = ExecutionChip::construct().with_nlocktime(nlocktime).with_nsequence(nsequence);
+
+            let chip_cells  = chip.assign_script_pubkey_unroll(
+                config.clone(),
+                &mut layouter,
+                self.script_pubkey.clone(),
+                self.randomness,
+                self.initial_stack,
+            )?;
+            
+            chip.expose_public(config.clone(), layouter.namespace(|| "script_length"), chip_cells.script_length.clone(), 0)?;
+            chip.expose_public(config.clone(), layouter.namespace(|| "script_rlc_acc"), chip_cells.script_rlc_acc_init.clone(), 1)?;
+            chip.expose_public(config.clone(), layouter.namespace(|| "randomness"), chip_cells.randomness.clone(), 2)?;
+            chip.expose_public(config.clone(), layouter.namespace(|| "nlocktime"), chip_cells.nlocktime.clone(), nlocktime_row(1))?;
+            chip.expose_public(config, layouter.namespace(|| "nsequence"), chip_cells.nsequence.clone(), nsequence_row(1))?;
+            Ok(chip_cells)
+        }
+    }
+
+    // Same as TestExecutionCircuit, but for a transaction with the given nLockTime and an input
+    // with the given nSequence
+    struct TestLockTimeCircuit<F: Field> {
+        pub circuit: TestExecutionCircuit<F>,
+        pub nlocktime: u32,
+        pub nsequence: u32,
+    }
+
+    impl<F: Field> Circuit<F> for TestLockTimeCircuit<F> {
+        type Config = ExecutionConfig<F>;
+
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                circuit: self.circuit.without_witnesses(),
+                nlocktime: 0,
+                nsequence: 0,
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            ExecutionChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            layouter: impl Layouter<F>
+        ) -> Result<(), Error> {
+            self.circuit.synthesize_with_locktimes(config, layouter, self.nlocktime, self.nsequence)?;
+            Ok(())
+        }
+    }
+
+    // Same as TestExecutionCircuit, but also exposing the pk_rlc_acc and num_checksig_opcodes
+    struct TestCheckSigAccumulatorsCircuit<F: Field>(TestExecutionCircuit<F>);
+
+    impl<F: Field> Circuit<F> for TestCheckSigAccumulatorsCircuit<F> {
+        type Config = ExecutionConfig<F>;
+
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self(self.0.without_witnesses())
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            ExecutionChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>
+        ) -> Result<(), Error> {
+            let chip_cells = self.0.synthesize_with_locktimes(config.clone(), layouter.namespace(|| "execution"), 0, 0)?;
+            ExecutionChip::construct().expose_checksig_accumulators(
+                config,
+                layouter.namespace(|| "checksig accumulators"),
+                &chip_cells,
+                1,
+            )
+        }
+    }
+
+    // Same as TestExecutionCircuit, but configured to require minimal data pushes
+    struct TestMinimalPushCircuit<F: Field>(TestExecutionCircuit<F>);
+
+    impl<F: Field> Circuit<F> for TestMinimalPushCircuit<F> {
+        type Config = ExecutionConfig<F>;
+
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self(self.0.without_witnesses())
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            ExecutionChip::configure_with_minimal_push(meta, true)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            layouter: impl Layouter<F>
+        ) -> Result<(), Error> {
+            self.0.synthesize(config, layouter)
+        }
+    }
+
+    struct TestScriptSigCircuit<F: Field> {
+        pub script_sig: Vec<u8>,
+        pub script_pubkey: Vec<u8>,
+        pub randomness: F,
+    }
+
+    impl<F: Field> Circuit<F> for TestScriptSigCircuit<F> {
+        type Config = ExecutionConfig<F>;
+
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                script_sig: vec![],
+                script_pubkey: vec![],
+                randomness: F::zero(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            ExecutionChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>
+        ) -> Result<(), Error> {
+            let chip = ExecutionChip::construct();
+
+            let (script_sig_cells, script_pubkey_cells) = chip.assign_script_sig_then_pubkey(
+                config.clone(),
+                &mut layouter,
+                self.script_sig.clone(),
+                self.script_pubkey.clone(),
+                self.randomness,
+            )?;
+
+            // The scriptSig is exposed before the scriptPubkey, in the order of their regions.
+            // Both regions share the nLockTime and the nSequence.
+            for (i, chip_cells) in [script_sig_cells, script_pubkey_cells].into_iter().enumerate() {
+                let first_row = i * EXECUTION_INSTANCE_ROWS;
+                chip.expose_public(config.clone(), layouter.namespace(|| "script_length"), chip_cells.script_length, first_row + SCRIPT_LENGTH_ROW)?;
+                chip.expose_public(config.clone(), layouter.namespace(|| "script_rlc_acc"), chip_cells.script_rlc_acc_init, first_row + SCRIPT_RLC_ACC_ROW)?;
+                chip.expose_public(config.clone(), layouter.namespace(|| "randomness"), chip_cells.randomness, first_row + RANDOMNESS_ROW)?;
+                chip.expose_public(config.clone(), layouter.namespace(|| "nlocktime"), chip_cells.nlocktime, nlocktime_row(2))?;
+                chip.expose_public(config.clone(), layouter.namespace(|| "nsequence"), chip_cells.nsequence, nsequence_row(2))?;
+            }
+            Ok(())
+        }
+    }
+
+    // Assigns only the first row of an unrolling region, with the given values of the
+    // accumulators which the "First row constraints" gate forces to zero
+    struct TestFirstRowCircuit<F: Field> {
+        pub pk_rlc_acc: F,
+        pub num_checksig_opcodes: F,
+        pub num_verified_checksigs: F,
+        pub hash_rlc_acc: F,
+        pub last_codeseparator_position: F,
+    }
+
+    impl<F: Field> Circuit<F> for TestFirstRowCircuit<F> {
+        type Config = ExecutionConfig<F>;
+
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                pk_rlc_acc: F::zero(),
+                num_checksig_opcodes: F::zero(),
+                num_verified_checksigs: F::zero(),
+                hash_rlc_acc: F::zero(),
+                last_codeseparator_position: F::zero(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            ExecutionChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>
+        ) -> Result<(), Error> {
+            OpcodeTableChip::load(config.opcode_table.clone(), &mut layouter)?;
+
+            layouter.assign_region(
+                || "First row",
+                |mut region| {
+                    config.q_first.enable(&mut region, 0)?;
+                    for (column, value) in [
+                        (config.pk_rlc_acc, self.pk_rlc_acc),
+                        (config.num_checksig_opcodes, self.num_checksig_opcodes),
+                        (config.num_verified_checksigs, self.num_verified_checksigs),
+                        (config.hash_rlc_acc, self.hash_rlc_acc),
+                        (config.last_codeseparator_position, self.last_codeseparator_position),
+                    ] {
+                        region.assign_advice(|| "First row accumulator", column, 0, || Value::known(value))?;
+                    }
+                    // The gate also queries the data counters in the next row
+                    for column in [config.num_data_bytes_remaining, config.num_data_length_bytes_remaining] {
+                        for offset in 0..2 {
+                            region.assign_advice(|| "Data counter", column, offset, || Value::known(F::zero()))?;
+                        }
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_script_pubkey_first_row_initialization() {
+        let k = 10;
+        let circuit = TestFirstRowCircuit {
+            pk_rlc_acc: BnScalar::zero(),
+            num_checksig_opcodes: BnScalar::zero(),
+            num_verified_checksigs: BnScalar::zero(),
+            hash_rlc_acc: BnScalar::zero(),
+            last_codeseparator_position: BnScalar::zero(),
+        };
+        assert_proves(k, &circuit, vec![vec![]]);
+
+        // Starting any accumulator from a forged value violates the first row constraints
+        let forged = BnScalar::from(7);
+        for circuit in [
+            TestFirstRowCircuit { pk_rlc_acc: forged, ..circuit },
+            TestFirstRowCircuit { num_checksig_opcodes: forged, ..circuit },
+            TestFirstRowCircuit { num_verified_checksigs: forged, ..circuit },
+            TestFirstRowCircuit { hash_rlc_acc: forged, ..circuit },
+            TestFirstRowCircuit { last_codeseparator_position: forged, ..circuit },
+        ] {
+            let failures = assert_rejects(k, &circuit, vec![vec![]]);
+            for failure in failures {
+                assert!(format!("{:?}", failure).contains("First row constraints"));
+            }
+        }
+    }
+
+    // Assigns only the first row of an unrolling region whose initial stack is assigned from
+    // values, with the given stack size and length of the stack top. The region is the one of a
+    // scriptSig if `is_script_sig` is true.
+    struct TestInitialStackCircuit<F: Field> {
+        pub initial_stack: [F; MAX_STACK_DEPTH],
+        pub stack_size: F,
+        pub stack_top_len: F,
+        pub is_script_sig: bool,
+    }
+
+    impl<F: Field> Circuit<F> for TestInitialStackCircuit<F> {
+        type Config = ExecutionConfig<F>;
+
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                initial_stack: [F::zero(); MAX_STACK_DEPTH],
+                stack_size: F::zero(),
+                stack_top_len: F::zero(),
+                is_script_sig: self.is_script_sig,
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            ExecutionChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>
+        ) -> Result<(), Error> {
+            OpcodeTableChip::load(config.opcode_table.clone(), &mut layouter)?;
+
+            layouter.assign_region(
+                || "First row",
+                |mut region| {
+                    config.q_initial_stack.enable(&mut region, 0)?;
+                    if self.is_script_sig {
+                        config.q_empty_initial_stack.enable(&mut region, 0)?;
+                    }
+                    region.assign_advice(|| "stack_size", config.stack_size, 0, || Value::known(self.stack_size))?;
+                    let stack_len = initial_stack_len(&self.initial_stack);
+                    for (i, item) in self.initial_stack.iter().enumerate() {
+                        region.assign_advice(|| "Stack item", config.stack[i], 0, || Value::known(*item))?;
+                        let len = if i == 0 { self.stack_top_len } else { F::from(stack_len[i]) };
+                        region.assign_advice(|| "Stack item length", config.stack_len[i], 0, || Value::known(len))?;
+                    }
+                    assign_initial_stack_flags(&mut region, &config, &self.initial_stack)
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_script_pubkey_initial_stack() {
+        let k = 10;
+        let mut initial_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        let circuit = TestInitialStackCircuit {
+            initial_stack,
+            stack_size: BnScalar::zero(),
+            stack_top_len: BnScalar::zero(),
+            is_script_sig: false,
+        };
+        assert_proves(k, &circuit, vec![vec![]]);
+        assert_proves(k, &TestInitialStackCircuit { is_script_sig: true, ..circuit }, vec![vec![]]);
+
+        // The zero item above the deepest non-zero item is counted
+        initial_stack[0] = BnScalar::from(5);
+        initial_stack[2] = BnScalar::from(EMPTY_ARRAY_REPRESENTATION);
+        let circuit = TestInitialStackCircuit {
+            initial_stack,
+            stack_size: BnScalar::from(3),
+            stack_top_len: BnScalar::from(UNKNOWN_ITEM_LENGTH),
+            is_script_sig: false,
+        };
+        assert_proves(k, &circuit, vec![vec![]]);
+
+        // A scriptSig cannot start from items chosen by the prover
+        let failures = assert_rejects(k, &TestInitialStackCircuit { is_script_sig: true, ..circuit }, vec![vec![]]);
+        for failure in failures {
+            assert!(format!("{:?}", failure).contains("Empty initial stack"));
+        }
+
+        // The length of an item cannot be chosen by the prover, e.g. for OP_SIZE to push it
+        for len in [0, 1, 3] {
+            let circuit = TestInitialStackCircuit { stack_top_len: BnScalar::from(len), ..circuit };
+            let failures = assert_rejects(k, &circuit, vec![vec![]]);
+            for failure in failures {
+                assert!(format!("{:?}", failure).contains("Initial stack"));
+            }
+        }
+
+        // A stack size other than the number of items is rejected, e.g. a size of zero for which
+        // OP_DEPTH would push the empty array
+        for stack_size in [0, 1, 2, 4, MAX_STACK_DEPTH as u64] {
+            let circuit = TestInitialStackCircuit { stack_size: BnScalar::from(stack_size), ..circuit };
+            let failures = assert_rejects(k, &circuit, vec![vec![]]);
+            for failure in failures {
+                assert!(format!("{:?}", failure).contains("Initial stack"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_script_pubkey_min_stack_depth() {
+        let k = 10;
+        let script_pubkey = vec![OP_1 as u8, OP_2 as u8, OP_ADD as u8, OP_3 as u8, OP_EQUAL as u8];
+
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        let initial_stack = [BnScalar::zero(); MIN_STACK_DEPTH];
+        let stack = final_stack(&script_pubkey, randomness, initial_stack, false);
+        assert_eq!(stack.len(), MIN_STACK_DEPTH);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::one()));
+
+        // A chip with fewer stack columns proves scripts which fit on its stack
+        let circuit: TestExecutionCircuit<BnScalar, MIN_STACK_DEPTH> = TestExecutionCircuit {
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            initial_stack,
+        };
+        let public_inputs = PublicInputs::new(script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_inputs.execution_instance()]);
+    }
+
+    #[test]
+    fn test_script_pubkey_push_constants() {
+        let k = 10;
+        let mut script_pubkey = vec![];
+        for i in 0..17 {
+            script_pubkey.push((OP_1 + i) as u8);
+        }
+        
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        
+        let circuit = TestExecutionCircuit {
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+        };
+        script_pubkey.reverse();
+        let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
+            acc * randomness + BnScalar::from(v as u64)
+        });
+
+        let public_input = vec![
+            BnScalar::from(script_pubkey.len() as u64),
+            script_rlc_init,
+            randomness,
+        ];
+
+        let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_script_pubkey_push1_to_push75() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let mut script_pubkey: Vec<u8> = vec![];
+        let mut data_push_len: u8 = rng.gen();
+        data_push_len = (data_push_len % (OP_PUSH_NEXT75 as u8)) + 1;
+
+        script_pubkey.push(data_push_len);
+        for _i in 0..data_push_len {
+            script_pubkey.push(rng.gen());
+        }
+        
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        
+        let circuit = TestExecutionCircuit {
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+        };
+        script_pubkey.reverse();
+        let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
+            acc * randomness + BnScalar::from(v as u64)
+        });
+
+        let public_input = vec![
+            BnScalar::from(script_pubkey.len() as u64),
+            script_rlc_init,
+            randomness,
+        ];
+
+        let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_script_pubkey_pushdata1() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let mut script_pubkey: Vec<u8> = vec![];
+        let mut data_push_len: u8 = rng.gen();
+        data_push_len = (data_push_len % 254) + 1;
+
+        script_pubkey.push(OP_PUSHDATA1 as u8);
+        script_pubkey.push(data_push_len);
+        for _i in 0..data_push_len {
+            script_pubkey.push(rng.gen());
+        }
+        
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        
+        let circuit = TestExecutionCircuit {
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+        };
+        script_pubkey.reverse();
+        let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
+            acc * randomness + BnScalar::from(v as u64)
+        });
+
+        let public_input = vec![
+            BnScalar::from(script_pubkey.len() as u64),
+            script_rlc_init,
+            randomness,
+        ];
+
+        let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_script_pubkey_empty_pushdata() {
+        let k = 10;
+        let mut rng = XorShiftRng::seed_from_u64(1);
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        // The empty array is pushed by OP_0 only
+        for opcode in [OP_PUSHDATA1, OP_PUSHDATA2, OP_PUSHDATA4] {
+            let mut script_pubkey = vec![opcode as u8];
+            script_pubkey.extend(vec![0x00; 1 << (opcode - OP_PUSHDATA1)]);
+            script_pubkey.push(OP_1 as u8);
+
+            let public_input = PublicInputs::new(script_pubkey.clone(), randomness).execution_instance();
+            let circuit = TestExecutionCircuit {
+                script_pubkey,
+                randomness,
+                initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+            };
+            let failures = assert_rejects(k, &circuit, vec![public_input]);
+            assert!(failures.iter().any(|failure| format!("{:?}", failure).contains("Accumulate data length")));
+        }
+    }
+
+    #[test]
+    fn test_script_pubkey_pushdata2() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let mut script_pubkey: Vec<u8> = vec![];
+        let data_push_len_byte0: u8 = rng.gen();
+        let data_push_len_byte1: u8 = 1;
+
+        script_pubkey.push(OP_PUSHDATA2 as u8);
+        script_pubkey.push(data_push_len_byte0);
+        script_pubkey.push(data_push_len_byte1);
+        let data_push_len: usize =
+            data_push_len_byte0 as usize +
+            256 * (data_push_len_byte1 as usize);
+
+        for _i in 0..data_push_len {
+            script_pubkey.push(rng.gen());
+        }
+        
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        
+        let circuit = TestExecutionCircuit {
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+        };
+        script_pubkey.reverse();
+        let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
+            acc * randomness + BnScalar::from(v as u64)
+        });
+
+        let public_input = vec![
+            BnScalar::from(script_pubkey.len() as u64),
+            script_rlc_init,
+            randomness,
+        ];
+
+        let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_script_pubkey_back_to_back_pushdata2() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+
+        // Data lengths with non-zero high bytes are misdecoded if the power of 256 is not reset
+        let first_data_len: usize = 0x0100;
+        let second_data_len: usize = 0x0101;
+        let first_data: Vec<u8> = (0..first_data_len).map(|_| rng.gen()).collect();
+        let second_data: Vec<u8> = (0..second_data_len).map(|_| rng.gen()).collect();
+
+        let mut script_pubkey = vec![OP_PUSHDATA2 as u8];
+        script_pubkey.extend_from_slice(&(first_data_len as u16).to_le_bytes());
+        script_pubkey.extend_from_slice(&first_data);
+        script_pubkey.push(OP_PUSHDATA2 as u8);
+        script_pubkey.extend_from_slice(&(second_data_len as u16).to_le_bytes());
+        script_pubkey.extend_from_slice(&second_data);
+        script_pubkey.push(OP_1 as u8);
+        assert!(script_pubkey.len() <= MAX_SCRIPT_PUBKEY_SIZE);
+
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        // The second length is decoded correctly and the full data is pushed
+        let stack = final_stack(&script_pubkey, randomness, [BnScalar::zero(); MAX_STACK_DEPTH], false);
+        let data_rlc = |data: &Vec<u8>| data.iter().fold(BnScalar::zero(), |acc, v| acc * randomness + BnScalar::from(*v as u64));
+        assert_eq!(stack[0], StackItem::Value(BnScalar::one()));
+        assert_eq!(stack[1], StackItem::Value(data_rlc(&second_data)));
+        assert_eq!(stack[2], StackItem::Value(data_rlc(&first_data)));
+
+        let circuit = TestExecutionCircuit {
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+        };
+        script_pubkey.reverse();
+        let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
+            acc * randomness + BnScalar::from(v as u64)
+        });
+
+        let public_input = vec![
+            BnScalar::from(script_pubkey.len() as u64),
+            script_rlc_init,
+            randomness,
+        ];
+
+        let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_script_pubkey_pushdata4() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let mut script_pubkey: Vec<u8> = vec![];
+        let data_push_len_byte0: u8 = rng.gen();
+        let data_push_len_byte1: u8 = 1;
+        let data_push_len_byte2: u8 = 0;
+        let data_push_len_byte3: u8 = 0;
+
+        script_pubkey.push(OP_PUSHDATA4 as u8);
+        script_pubkey.push(data_push_len_byte0);
+        script_pubkey.push(data_push_len_byte1);
+        script_pubkey.push(data_push_len_byte2);
+        script_pubkey.push(data_push_len_byte3);
+        let data_push_len: usize =
+            data_push_len_byte0 as usize +
+            256 * (data_push_len_byte1 as usize) +
+            256 * 256 * (data_push_len_byte2 as usize) +
+            256 * 256 * 256 * (data_push_len_byte3 as usize);
+
+        for _i in 0..data_push_len {
+            script_pubkey.push(rng.gen());
+        }
+        
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        
+        let circuit = TestExecutionCircuit {
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+        };
+        script_pubkey.reverse();
+        let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
+            acc * randomness + BnScalar::from(v as u64)
+        });
+
+        let public_input = vec![
+            BnScalar::from(script_pubkey.len() as u64),
+            script_rlc_init,
+            randomness,
+        ];
+
+        let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    use secp256k1::{self, Secp256k1, SecretKey, PublicKey};
+
+    #[test]
+    fn test_script_pubkey_checksig() {
+        let k = 10;
+
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+        let public_key_bytes: [u8; PUBLIC_KEY_SIZE] = public_key.serialize();
+        
+        let mut script_pubkey: Vec<u8> = vec![];
+        script_pubkey.push(PUBLIC_KEY_SIZE as u8); // "Push 33 bytes" opcode
+        for i in 0..PUBLIC_KEY_SIZE {
+            script_pubkey.push(public_key_bytes[i]);
+        }
+        script_pubkey.push(OP_CHECKSIG as u8);
+
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let mut initial_stack_vec = vec![BnScalar::one()]; // This value will force a signature verification later
+        initial_stack_vec.extend_from_slice(&[BnScalar::zero(); MAX_STACK_DEPTH-1]);
+        let initial_stack: [BnScalar; MAX_STACK_DEPTH] = initial_stack_vec.as_slice().try_into().unwrap();
+
+        let circuit = TestExecutionCircuit {
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            initial_stack,
+        };
+
+        script_pubkey.reverse();
+        let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
+            acc * randomness + BnScalar::from(v as u64)
+        });
+
+        let public_input = vec![
+            BnScalar::from(script_pubkey.len() as u64),
+            script_rlc_init,
+            randomness,
+        ];
+
+        let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_script_pubkey_exposed_checksig_accumulators() {
+        let k = 10;
+        let mut rng = XorShiftRng::seed_from_u64(1);
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        let secp = Secp256k1::new();
+        let public_key_bytes = |byte: u8| {
+            let secret_key = SecretKey::from_slice(&[byte; 32]).expect("32 bytes, within curve order");
+            PublicKey::from_secret_key(&secp, &secret_key).serialize()
+        };
+
+        // <pk> OP_CHECKSIG
+        let mut script_pubkey = vec![PUBLIC_KEY_SIZE as u8];
+        script_pubkey.extend_from_slice(&public_key_bytes(0xcd));
+        script_pubkey.push(OP_CHECKSIG as u8);
+        let mut initial_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        initial_stack[0] = BnScalar::one();
+
+        let public_inputs = PublicInputs::new(script_pubkey.clone(), randomness);
+        let circuit = TestCheckSigAccumulatorsCircuit(TestExecutionCircuit {
+            script_pubkey,
+            randomness,
+            initial_stack,
+        });
+
+        // The verifier computes the accumulators from the public key it expects
+        let pk_rlc_acc = bytes_item(&public_key_bytes(0xcd), randomness);
+        let instance = public_inputs.clone().with_checksig_accumulators(pk_rlc_acc, 1).execution_instance();
+        assert_eq!(instance[pk_rlc_acc_row(1)], pk_rlc_acc);
+        assert_proves(k, &circuit, vec![instance]);
+
+        // Another public key or another number of OP_CHECKSIG opcodes is rejected
+        let other_pk_rlc_acc = bytes_item(&public_key_bytes(0xce), randomness);
+        for (pk_rlc_acc, num_checksig_opcodes) in [(other_pk_rlc_acc, 1), (pk_rlc_acc, 2)] {
+            let instance = public_inputs.clone()
+                .with_checksig_accumulators(pk_rlc_acc, num_checksig_opcodes)
+                .execution_instance();
+            assert_rejects(k, &circuit, vec![instance]);
+        }
+    }
+
+    #[test]
+    fn test_script_pubkey_checksigverify() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key_bytes: [u8; PUBLIC_KEY_SIZE] = PublicKey::from_secret_key(&secp, &secret_key).serialize();
+
+        // <pk> OP_CHECKSIGVERIFY OP_1
+        let mut script_pubkey = vec![PUBLIC_KEY_SIZE as u8];
+        script_pubkey.extend_from_slice(&public_key_bytes);
+        script_pubkey.extend([OP_CHECKSIGVERIFY as u8, OP_1 as u8]);
+
+        let initial_stack = |sig: u64| {
+            let mut stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+            stack[0] = BnScalar::from(sig);
+            stack
+        };
+
+        // The public key is accumulated and the signature is popped along with it
+        let mut parse_state = ScriptPubkeyParseState::new(randomness, initial_stack(1));
+        for byte in script_pubkey.iter() {
+            parse_state.update(*byte);
+        }
+        let pk_rlc = public_key_bytes
+            .iter()
+            .fold(BnScalar::zero(), |acc, b| acc * randomness + BnScalar::from(*b as u64));
+        assert_eq!(parse_state.pk_rlc_acc, pk_rlc);
+        assert_eq!(parse_state.num_checksig_opcodes, 1);
+        assert_eq!(parse_state.num_verified_checksigs, 1);
+        assert_eq!(parse_state.stack[0], BnScalar::one());
+        assert_eq!(parse_state.stack[1], BnScalar::zero());
+        assert_eq!(parse_state.stack_size, BnScalar::one());
+
+        let public_input = PublicInputs::new(script_pubkey.clone(), randomness).execution_instance();
+        let circuit = TestExecutionCircuit {
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            initial_stack: initial_stack(1),
+        };
+        assert_proves(k, &circuit, vec![public_input.clone()]);
+
+        // An invalid signature fails the script
+        let circuit = TestExecutionCircuit {
+            script_pubkey,
+            randomness,
+            initial_stack: initial_stack(0),
+        };
+        let failures = assert_rejects(k, &circuit, vec![public_input]);
+        assert!(failures.iter().any(|failure| format!("{:?}", failure).contains("OP_CHECKSIGVERIFY")));
+    }
+
+    #[test]
+    fn test_script_pubkey_checkmultisig() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        let secp = Secp256k1::new();
+        let public_keys: Vec<[u8; PUBLIC_KEY_SIZE]> = [0xcd, 0xce, 0xcf]
+            .iter()
+            .map(|b| {
+                let secret_key = SecretKey::from_slice(&[*b; 32]).expect("32 bytes, within curve order");
+                PublicKey::from_secret_key(&secp, &secret_key).serialize()
+            })
+            .collect();
+        let pk_rlc = |bytes: &[u8]| {
+            bytes.iter().fold(BnScalar::zero(), |acc, b| acc * randomness + BnScalar::from(*b as u64))
+        };
+
+        // 2-of-3 multisig
+        let mut script_pubkey = vec![OP_1 as u8 + 1];
+        for public_key in public_keys.iter() {
+            script_pubkey.push(PUBLIC_KEY_SIZE as u8);
+            script_pubkey.extend_from_slice(public_key);
+        }
+        script_pubkey.extend([OP_1 as u8 + 2, OP_CHECKMULTISIG as u8]);
+
+        // The two signatures are on top of the extra item popped by OP_CHECKMULTISIG
+        let initial_stack = |sigs: [u64; 2]| {
+            let mut stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+            stack[0] = BnScalar::from(sigs[0]);
+            stack[1] = BnScalar::from(sigs[1]);
+            stack[2] = BnScalar::from(EMPTY_ARRAY_REPRESENTATION);
+            stack
+        };
+
+        for (sigs, are_sigs_valid) in [([1, 1], true), ([1, 0], false), ([0, 0], false)] {
+            let mut script_pubkey = script_pubkey.clone();
+            let mut parse_state = ScriptPubkeyParseState::new(randomness, initial_stack(sigs));
+            for byte in script_pubkey.iter() {
+                parse_state.update(*byte);
+            }
+            assert_eq!(parse_state.stack[0], BnScalar::from(are_sigs_valid as u64));
+            assert_eq!(parse_state.stack[1], BnScalar::zero());
+            assert_eq!(parse_state.stack_size, BnScalar::one());
+            assert_eq!(parse_state.num_checksig_opcodes, 3);
+            if are_sigs_valid {
+                // The two public keys closest to the stack top are accumulated in the order they were pushed
+                assert_eq!(
+                    parse_state.pk_rlc_acc,
+                    pk_rlc(&public_keys[1]) * randomness + pk_rlc(&public_keys[2]),
+                );
+                assert_eq!(parse_state.num_verified_checksigs, 2);
+            }
+            else {
+                assert_eq!(parse_state.pk_rlc_acc, BnScalar::zero());
+                assert_eq!(parse_state.num_verified_checksigs, 0);
+                // A failed OP_CHECKMULTISIG would fail the script
+                script_pubkey.push(OP_NOT as u8);
+            }
+
+            let circuit = TestExecutionCircuit {
+                script_pubkey: script_pubkey.clone(),
+                randomness,
+                initial_stack: initial_stack(sigs),
+            };
+            let public_input = PublicInputs::new(script_pubkey, randomness).execution_instance();
+            assert_proves(k, &circuit, vec![public_input]);
+        }
+
+        // More signatures than public keys and signature values other than 0 and 1 are rejected
+        let mut more_sigs_than_keys = script_pubkey.clone();
+        more_sigs_than_keys[0] = OP_1 as u8 + 3;
+        let mut invalid_sig_value = initial_stack([1, 1]);
+        invalid_sig_value[0] = BnScalar::from(2);
+        for (script_pubkey, initial_stack) in [
+            (more_sigs_than_keys, initial_stack([1, 1])),
+            (script_pubkey, invalid_sig_value),
+        ] {
+            let circuit = TestExecutionCircuit {
+                script_pubkey: script_pubkey.clone(),
+                randomness,
+                initial_stack,
+            };
+            let public_input = PublicInputs::new(script_pubkey, randomness).execution_instance();
+            let failures = assert_rejects(k, &circuit, vec![public_input]);
+            assert!(failures.iter().any(|failure| format!("{:?}", failure).contains("OP_CHECKMULTISIG")));
+        }
+    }
+
+    #[test]
+    fn test_script_pubkey_checkmultisig_signed_keys() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        let secp = Secp256k1::new();
+        let public_keys: Vec<[u8; PUBLIC_KEY_SIZE]> = [0xcd, 0xce, 0xcf]
+            .iter()
+            .map(|b| {
+                let secret_key = SecretKey::from_slice(&[*b; 32]).expect("32 bytes, within curve order");
+                PublicKey::from_secret_key(&secp, &secret_key).serialize()
+            })
+            .collect();
+        let pk_items: Vec<BnScalar> = public_keys.iter().map(|pk| bytes_item(pk, randomness)).collect();
+
+        // 2-of-3 multisig with both signatures valid
+        let mut script_pubkey = vec![OP_1 as u8 + 1];
+        for public_key in public_keys.iter() {
+            script_pubkey.push(PUBLIC_KEY_SIZE as u8);
+            script_pubkey.extend_from_slice(public_key);
+        }
+        script_pubkey.extend([OP_1 as u8 + 2, OP_CHECKMULTISIG as u8]);
+        let mut initial_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        initial_stack[0] = BnScalar::one();
+        initial_stack[1] = BnScalar::one();
+        initial_stack[2] = BnScalar::from(EMPTY_ARRAY_REPRESENTATION);
+        let circuit = TestCheckSigAccumulatorsCircuit(TestExecutionCircuit {
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            initial_stack,
+        });
+        let public_inputs = PublicInputs::new(script_pubkey.clone(), randomness);
+
+        let (config, regions) = record_advice(&circuit).unwrap();
+        let region = regions
+            .iter()
+            .find(|region| region.name == "ScriptPubkey unrolling")
+            .expect("Unrolling region is assigned");
+        let offset = (0..script_pubkey.len() + 2)
+            .find(|offset| region.value(config.is_opcode_checkmultisig, *offset) == Some(BnScalar::one()))
+            .expect("OP_CHECKMULTISIG is executed");
+
+        // The witness signs the keys closest to the stack top, i.e. the second and the third
+        // pushed keys. The prover may instead sign the first and the third, whose RLCs are
+        // accumulated in the order they were pushed. Index i of the OP_CHECKMULTISIG columns
+        // is the key at prev_stack[i + 1], so the third pushed key is at index 0.
+        let signed_keys = |is_key_signed: [bool; MAX_MULTISIG_N]| {
+            let mut acc = BnScalar::zero();
+            let mut forged_cells = vec![];
+            for i in (0..MAX_MULTISIG_N).rev() {
+                if is_key_signed[i] {
+                    acc = acc * randomness + pk_items[public_keys.len() - 1 - i];
+                }
+                let row = region.start + offset;
+                forged_cells.push((config.is_multisig_key_signed[i], row, BnScalar::from(is_key_signed[i] as u64)));
+                forged_cells.push((config.multisig_pk_rlc_acc[i], row, acc));
+            }
+            // pk_rlc_acc keeps its value in the following rows
+            let mut row_offset = offset;
+            while region.value(config.pk_rlc_acc, row_offset).is_some() {
+                forged_cells.push((config.pk_rlc_acc, region.start + row_offset, acc));
+                row_offset += 1;
+            }
+            (acc, forged_cells)
+        };
+
+        let (acc, forged_cells) = signed_keys([true, false, true]);
+        assert_eq!(acc, pk_items[0] * randomness + pk_items[2]);
+        let instance = public_inputs.clone().with_checksig_accumulators(acc, 3).execution_instance();
+        run_with_forged_advice(k, &circuit, vec![instance], &forged_cells)
+            .expect("any 2 of the 3 keys can be signed");
+
+        // Signing one key or all three keys is rejected
+        for is_key_signed in [[true, false, false], [true, true, true]] {
+            let (acc, forged_cells) = signed_keys(is_key_signed);
+            let instance = public_inputs.clone().with_checksig_accumulators(acc, 3).execution_instance();
+            let failures = run_with_forged_advice(k, &circuit, vec![instance], &forged_cells)
+                .expect_err("m keys must be signed");
+            assert!(failures.iter().any(|failure| format!("{:?}", failure).contains("OP_CHECKMULTISIG")));
+        }
+    }
+
+    #[test]
+    fn test_script_pubkey_minimal_push() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        let push_with_length = |prefix: &[u8], length: usize| {
+            let mut script_pubkey = prefix.to_vec();
+            script_pubkey.extend(vec![0xab; length]);
+            script_pubkey
+        };
+        let minimal_scripts = vec![
+            vec![0x01, 0x11],
+            push_with_length(&[OP_PUSHDATA1 as u8, 76], 76),
+            push_with_length(&[OP_PUSHDATA2 as u8, 0x00, 0x01], 256),
+            // Pushes in branches which are not taken are not checked
+            vec![OP_0 as u8, OP_IF as u8, 0x01, 0x05, OP_ENDIF as u8, OP_1 as u8],
+        ];
+        let non_minimal_scripts = vec![
+            // OP_5 pushes [0x05]
+            vec![0x01, 0x05],
+            // OP_1NEGATE pushes [0x81]
+            vec![0x01, 0x81],
+            push_with_length(&[OP_PUSHDATA1 as u8, 75], 75),
+            push_with_length(&[OP_PUSHDATA2 as u8, 0xff, 0x00], 255),
+            vec![OP_PUSHDATA4 as u8, 0x01, 0x00, 0x00, 0x00, 0x07],
+        ];
+
+        let circuit = |script_pubkey: &Vec<u8>| TestExecutionCircuit {
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+        };
+        let public_input = |script_pubkey: &Vec<u8>| {
+            vec![PublicInputs::new(script_pubkey.clone(), randomness).execution_instance()]
+        };
+        for script_pubkey in minimal_scripts.iter() {
+            assert_proves(k, &circuit(script_pubkey), public_input(script_pubkey));
+            assert_proves(k, &TestMinimalPushCircuit(circuit(script_pubkey)), public_input(script_pubkey));
+        }
+        // Non-minimal pushes are only rejected if minimal pushes are required
+        for script_pubkey in non_minimal_scripts.iter() {
+            assert_proves(k, &circuit(script_pubkey), public_input(script_pubkey));
+            let failures = assert_rejects(k, &TestMinimalPushCircuit(circuit(script_pubkey)), public_input(script_pubkey));
+            for failure in failures {
+                assert!(format!("{:?}", failure).contains("Minimal data push"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_script_sig_then_pubkey() {
+        use bitcoin_hashes::{hash160, Hash};
+
+        // Two regions of MAX_SCRIPT_PUBKEY_SIZE+2 rows
+        let k = 11;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        let secp = Secp256k1::new();
+        let public_keys: Vec<[u8; PUBLIC_KEY_SIZE]> = [0xcd, 0xce]
+            .iter()
+            .map(|b| {
+                let secret_key = SecretKey::from_slice(&[*b; 32]).expect("32 bytes, within curve order");
+                PublicKey::from_secret_key(&secp, &secret_key).serialize()
+            })
+            .collect();
+
+        // P2PKH-style spend. OP_DUP is not supported, so OP_TUCK keeps a copy of the public key
+        // below the signature. The signature is verified by the OpCheckSigChip, so the scriptSig
+        // pushes its validity.
+        // scriptSig: OP_1 <pk>
+        // scriptPubkey: OP_TUCK OP_HASH160 <hash160(pk)> OP_EQUALVERIFY OP_SWAP OP_CHECKSIG
+        let script_sig = |public_key: &[u8; PUBLIC_KEY_SIZE]| {
+            let mut script_sig = vec![OP_1 as u8, PUBLIC_KEY_SIZE as u8];
+            script_sig.extend_from_slice(public_key);
+            script_sig
+        };
+        let mut script_pubkey = vec![OP_TUCK as u8, OP_HASH160 as u8];
+        script_pubkey.extend(push_bytes(&hash160::Hash::hash(&public_keys[0]).into_inner()));
+        script_pubkey.extend([OP_EQUALVERIFY as u8, OP_SWAP as u8, OP_CHECKSIG as u8]);
+
+        let public_input = |script_sig: &Vec<u8>| {
+            let mut public_inputs = PublicInputs::new(script_sig.clone(), randomness);
+            public_inputs.script_pubkeys.push(script_pubkey.clone());
+            public_inputs.execution_instance()
+        };
+
+        let circuit = TestScriptSigCircuit {
+            script_sig: script_sig(&public_keys[0]),
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+        };
+        assert_proves(k, &circuit, vec![public_input(&circuit.script_sig)]);
+
+        // A scriptSig with another public key fails the OP_EQUALVERIFY of the scriptPubkey
+        let circuit = TestScriptSigCircuit {
+            script_sig: script_sig(&public_keys[1]),
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+        };
+        assert_rejects(k, &circuit, vec![public_input(&circuit.script_sig)]);
+
+        // Only the scriptPubkey has to leave a true stack top
+        let script_sig = vec![OP_1 as u8, OP_0 as u8];
+        let script_pubkey = vec![OP_DROP as u8];
+        let mut public_inputs = PublicInputs::new(script_sig.clone(), randomness);
+        public_inputs.script_pubkeys.push(script_pubkey.clone());
+        let circuit = TestScriptSigCircuit { script_sig, script_pubkey, randomness };
+        assert_proves(k, &circuit, vec![public_inputs.execution_instance()]);
+    }
+
+    #[test]
+    fn test_script_pubkey_checksigadd() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        let secp = Secp256k1::new();
+        let public_keys: Vec<[u8; PUBLIC_KEY_SIZE]> = [0xcd, 0xce, 0xcf]
+            .iter()
+            .map(|b| {
+                let secret_key = SecretKey::from_slice(&[*b; 32]).expect("32 bytes, within curve order");
+                PublicKey::from_secret_key(&secp, &secret_key).serialize()
+            })
+            .collect();
+        let pk_rlc = |bytes: &[u8]| {
+            bytes.iter().fold(BnScalar::zero(), |acc, b| acc * randomness + BnScalar::from(*b as u64))
+        };
+
+        // 2-of-3 tapscript multisig
+        // OP_0 <pk1> OP_CHECKSIGADD <pk2> OP_CHECKSIGADD <pk3> OP_CHECKSIGADD OP_2 OP_NUMEQUAL
+        let mut script_pubkey = vec![OP_0 as u8];
+        for public_key in public_keys.iter() {
+            script_pubkey.push(PUBLIC_KEY_SIZE as u8);
+            script_pubkey.extend_from_slice(public_key);
+            script_pubkey.push(OP_CHECKSIGADD as u8);
+        }
+        script_pubkey.extend([OP_1 as u8 + 1, OP_NUMEQUAL as u8]);
+
+        // The signature of the first public key is on top of the stack
+        let initial_stack = |sigs: [u64; 3]| {
+            let mut stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+            for (i, sig) in sigs.iter().enumerate() {
+                stack[i] = BnScalar::from(*sig);
+            }
+            stack
+        };
+
+        let mut parse_state = ScriptPubkeyParseState::new(randomness, initial_stack([1, 0, 1]));
+        for byte in script_pubkey.iter() {
+            parse_state.update(*byte);
+        }
+        assert_eq!(parse_state.pk_rlc_acc, pk_rlc(&public_keys[0]) * randomness + pk_rlc(&public_keys[2]));
+        assert_eq!(parse_state.num_checksig_opcodes, 3);
+        assert_eq!(parse_state.num_verified_checksigs, 2);
+        assert_eq!(parse_state.stack[0], BnScalar::one());
+        assert_eq!(parse_state.stack_size, BnScalar::one());
+
+        let public_input = PublicInputs::new(script_pubkey.clone(), randomness).execution_instance();
+        let circuit = TestExecutionCircuit {
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            initial_stack: initial_stack([1, 0, 1]),
+        };
+        assert_proves(k, &circuit, vec![public_input.clone()]);
+
+        // Three valid signatures do not satisfy OP_2 OP_NUMEQUAL
+        let circuit = TestExecutionCircuit {
+            script_pubkey,
+            randomness,
+            initial_stack: initial_stack([1, 1, 1]),
+        };
+        assert_rejects(k, &circuit, vec![public_input]);
+    }
+
+    #[test]
+    fn test_script_pubkey_op0() {
+        let k = 10;
+        // OP_0 pushes the empty array which is represented by negative zero
+        let mut script_pubkey = vec![OP_0 as u8, OP_1 as u8];
+
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        let circuit = TestExecutionCircuit {
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+        };
+        script_pubkey.reverse();
+        let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
+            acc * randomness + BnScalar::from(v as u64)
+        });
+
+        let public_input = vec![
+            BnScalar::from(script_pubkey.len() as u64),
+            script_rlc_init,
+            randomness,
+        ];
+
+        assert_proves(k, &circuit, vec![public_input]);
+    }
+
+    #[test]
+    fn test_script_pubkey_negative_zero_is_false() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        // The stack top is the empty array pushed by OP_0
+        {
+            let mut script_pubkey = vec![OP_1 as u8, OP_0 as u8];
+            let circuit = TestExecutionCircuit {
+                script_pubkey: script_pubkey.clone(),
+                randomness,
+                initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+            };
+            script_pubkey.reverse();
+            let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
+                acc * randomness + BnScalar::from(v as u64)
+            });
+
+            let public_input = vec![
+                BnScalar::from(script_pubkey.len() as u64),
+                script_rlc_init,
+                randomness,
+            ];
+
+            assert_rejects(k, &circuit, vec![public_input]);
+        }
+
+        // The stack top is negative zero in the initial stack
+        {
+            let mut script_pubkey = vec![OP_NOP as u8];
+            let mut initial_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+            initial_stack[0] = BnScalar::from(NEGATIVE_ZERO);
+            let circuit = TestExecutionCircuit {
+                script_pubkey: script_pubkey.clone(),
+                randomness,
+                initial_stack,
+            };
+            script_pubkey.reverse();
+            let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
+                acc * randomness + BnScalar::from(v as u64)
+            });
+
+            let public_input = vec![
+                BnScalar::from(script_pubkey.len() as u64),
+                script_rlc_init,
+                randomness,
+            ];
+
+            assert_rejects(k, &circuit, vec![public_input]);
+        }
+    }
+
+    #[test]
+    fn test_script_pubkey_empty() {
+        let k = 10;
+        let script_pubkey: Vec<u8> = vec![];
+
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        // The RLC of the empty script is zero
+        let public_input = vec![
+            BnScalar::zero(),
+            BnScalar::zero(),
+            randomness,
+        ];
+
+        for (initial_stack_top, should_accept) in [
+            (BnScalar::zero(), false),
+            (BnScalar::from(EMPTY_ARRAY_REPRESENTATION), false),
+            (BnScalar::one(), true),
+        ] {
+            let mut initial_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+            initial_stack[0] = initial_stack_top;
+            let circuit = TestExecutionCircuit {
+                script_pubkey: script_pubkey.clone(),
+                randomness,
+                initial_stack,
+            };
+
+            // An empty script leaves the initial stack unchanged, so an empty or false
+            // stack top is rejected
+            let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
+            assert_eq!(prover.verify().is_ok(), should_accept);
+        }
+    }
+
+    #[test]
+    fn test_script_pubkey_max_depth_push() {
+        let k = 10;
+        // Fill the stack to capacity with the constants 1, 2, ..., 16, 1, 2, ...
+        let mut script_pubkey = vec![];
+        for i in 0..MAX_STACK_DEPTH {
+            script_pubkey.push((OP_1 + i % 16) as u8);
+        }
+
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        // The first pushed constant ends up in the bottom slot
+        let mut parse_state = ScriptPubkeyParseState::new(randomness, [BnScalar::zero(); MAX_STACK_DEPTH]);
+        for opcode in script_pubkey.iter() {
+            parse_state.update(*opcode);
+        }
+        for i in 0..MAX_STACK_DEPTH {
+            let expected = ((MAX_STACK_DEPTH - 1 - i) % 16 + 1) as u64;
+            assert_eq!(parse_state.stack[i], BnScalar::from(expected));
+        }
+        assert_eq!(parse_state.stack[MAX_STACK_DEPTH-1], BnScalar::one());
+
+        let circuit = TestExecutionCircuit {
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+        };
+        script_pubkey.reverse();
+        let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
+            acc * randomness + BnScalar::from(v as u64)
+        });
+
+        let public_input = vec![
+            BnScalar::from(script_pubkey.len() as u64),
+            script_rlc_init,
+            randomness,
+        ];
+
+        let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_script_pubkey_max_depth_checksig() {
+        let k = 10;
+
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+        let public_key_bytes: [u8; PUBLIC_KEY_SIZE] = public_key.serialize();
+
+        // MAX_STACK_DEPTH-2 constants, the signature flag and the public key fill the stack
+        let mut script_pubkey: Vec<u8> = vec![];
+        for i in 0..MAX_STACK_DEPTH-2 {
+            script_pubkey.push((OP_1 + i % 16) as u8);
+        }
+        script_pubkey.push(OP_1 as u8); // This value will force a signature verification later
+        script_pubkey.push(PUBLIC_KEY_SIZE as u8); // "Push 33 bytes" opcode
+        script_pubkey.extend_from_slice(&public_key_bytes);
+
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        let mut parse_state = ScriptPubkeyParseState::new(randomness, [BnScalar::zero(); MAX_STACK_DEPTH]);
+        for opcode in script_pubkey.iter() {
+            parse_state.update(*opcode);
+        }
+        // The stack is full before OP_CHECKSIG
+        assert_eq!(parse_state.stack[MAX_STACK_DEPTH-1], BnScalar::one());
+        assert_eq!(parse_state.stack[1], BnScalar::one());
+
+        script_pubkey.push(OP_CHECKSIG as u8);
+        parse_state.update(OP_CHECKSIG as u8);
+        // The public key and signature are replaced by the result, the remaining items
+        // are shifted up and the bottom slot is zero-filled
+        assert_eq!(parse_state.stack[0], BnScalar::one());
+        for i in 1..MAX_STACK_DEPTH-1 {
+            let expected = ((MAX_STACK_DEPTH - 2 - i) % 16 + 1) as u64;
+            assert_eq!(parse_state.stack[i], BnScalar::from(expected));
+        }
+        assert_eq!(parse_state.stack[MAX_STACK_DEPTH-1], BnScalar::zero());
+        assert_eq!(parse_state.num_checksig_opcodes, 1);
+
+        let circuit = TestExecutionCircuit {
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+        };
+
+        script_pubkey.reverse();
+        let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
+            acc * randomness + BnScalar::from(v as u64)
+        });
+
+        let public_input = vec![
+            BnScalar::from(script_pubkey.len() as u64),
+            script_rlc_init,
+            randomness,
+        ];
+
+        let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_script_pubkey_opcode_bytes_in_pushes() {
+        let k = 10;
+        let opcode_bytes = [OP_0 as u8, OP_1 as u8, OP_16 as u8, OP_PUSHDATA1 as u8, OP_CHECKSIG as u8];
+
+        // Data bytes and data length bytes equal to opcodes must not trigger the opcode gates
+        let mut script_pubkey: Vec<u8> = vec![opcode_bytes.len() as u8];
+        script_pubkey.extend_from_slice(&opcode_bytes);
+        script_pubkey.push(OP_PUSHDATA1 as u8);
+        script_pubkey.push(OP_CHECKSIG as u8); // Data length byte
+        for i in 0..OP_CHECKSIG {
+            script_pubkey.push(opcode_bytes[i % opcode_bytes.len()]);
+        }
+        script_pubkey.push(OP_1 as u8);
+
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        let data_rlc = |data: &[u8]| {
+            data.iter().fold(BnScalar::zero(), |acc, b| acc * randomness + BnScalar::from(*b as u64))
+        };
+        let mut parse_state = ScriptPubkeyParseState::new(randomness, [BnScalar::zero(); MAX_STACK_DEPTH]);
+        for opcode in script_pubkey.iter() {
+            parse_state.update(*opcode);
+        }
+        assert_eq!(parse_state.stack[0], BnScalar::one());
+        assert_eq!(parse_state.stack[1], data_rlc(&script_pubkey[8..8+OP_CHECKSIG]));
+        assert_eq!(parse_state.stack[2], data_rlc(&opcode_bytes));
+        assert_eq!(parse_state.stack[3], BnScalar::zero());
+        assert_eq!(parse_state.num_checksig_opcodes, 0);
+
+        let circuit = TestExecutionCircuit {
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+        };
+        script_pubkey.reverse();
+        let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
+            acc * randomness + BnScalar::from(v as u64)
+        });
+
+        let public_input = vec![
+            BnScalar::from(script_pubkey.len() as u64),
+            script_rlc_init,
+            randomness,
+        ];
+
+        assert_proves(k, &circuit, vec![public_input]);
+    }
+
+    #[test]
+    fn test_script_pubkey_failed_checksigs_not_verified() {
+        let k = 10;
+
+        let secp = Secp256k1::new();
+        let public_key_bytes: Vec<[u8; PUBLIC_KEY_SIZE]> = [0xab, 0xcd, 0xef, 0x12, 0x34]
+            .iter()
+            .map(|b| {
+                let secret_key = SecretKey::from_slice(&[*b; 32]).expect("32 bytes, within curve order");
+                PublicKey::from_secret_key(&secp, &secret_key).serialize()
+            })
+            .collect();
+
+        // The zero in the initial stack makes the first three OP_CHECKSIGs fail, each one leaving
+        // the false result as the signature of the next. The last two use the signature pushed by OP_1.
+        // The zero is above an empty array, as zero items at the bottom of the stack are not counted.
+        let mut script_pubkey: Vec<u8> = vec![];
+        for (i, pk_bytes) in public_key_bytes.iter().enumerate() {
+            if i >= 3 {
+                script_pubkey.push(OP_1 as u8);
+            }
+            script_pubkey.push(PUBLIC_KEY_SIZE as u8); // "Push 33 bytes" opcode
+            script_pubkey.extend_from_slice(pk_bytes);
+            script_pubkey.push(OP_CHECKSIG as u8);
+        }
+
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        let mut initial_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        initial_stack[1] = BnScalar::from(EMPTY_ARRAY_REPRESENTATION);
+        let mut parse_state = ScriptPubkeyParseState::new(randomness, initial_stack);
+        for opcode in script_pubkey.iter() {
+            parse_state.update(*opcode);
+        }
+        assert_eq!(parse_state.num_checksig_opcodes, 5);
+        assert_eq!(parse_state.num_verified_checksigs, 2);
+
+        // Only the public keys of the verified signatures are accumulated
+        let pk_rlc = |pk_bytes: &[u8]| {
+            pk_bytes.iter().fold(BnScalar::zero(), |acc, b| acc * randomness + BnScalar::from(*b as u64))
+        };
+        let expected_pk_rlc_acc = pk_rlc(&public_key_bytes[3]) * randomness + pk_rlc(&public_key_bytes[4]);
+        assert_eq!(parse_state.pk_rlc_acc, expected_pk_rlc_acc);
+
+        let circuit = TestExecutionCircuit {
+            script_pubkey: script_pubkey.clone(),
+            randomness,
+            initial_stack,
+        };
+        script_pubkey.reverse();
+        let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
+            acc * randomness + BnScalar::from(v as u64)
+        });
+
+        let public_input = vec![
+            BnScalar::from(script_pubkey.len() as u64),
+            script_rlc_init,
+            randomness,
+        ];
+
+        assert_proves(k, &circuit, vec![public_input]);
+    }
+
+    #[test]
+    fn test_script_pubkey_small_int_push_equivalence() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        // OP_N pushes N and a one byte data push of N pushes the RLC of the byte, which is N.
+        // Both forms leave the same item on the stack for the arithmetic opcodes, e.g.
+        // OP_5 OP_3 and <0x05> <0x03> are equivalent inputs to OP_ADD.
+        let mut scripts = vec![(vec![(OP_1 + 4) as u8, (OP_1 + 2) as u8], vec![0x01, 0x05, 0x01, 0x03])];
+        for n in 1..=16u8 {
+            scripts.push((vec![OP_RESERVED as u8 + n], vec![0x01, n]));
+        }
+
+        for (op_n_script, push_script) in scripts {
+            assert_eq!(
+                final_stack(&op_n_script, randomness, [BnScalar::zero(); MAX_STACK_DEPTH], true),
+                final_stack(&push_script, randomness, [BnScalar::zero(); MAX_STACK_DEPTH], true),
+            );
+
+            for mut script_pubkey in [op_n_script, push_script] {
+                let circuit = TestExecutionCircuit {
+                    script_pubkey: script_pubkey.clone(),
+                    randomness,
+                    initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+                };
+                script_pubkey.reverse();
+                let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
+                    acc * randomness + BnScalar::from(v as u64)
+                });
+
+                let public_input = vec![
+                    BnScalar::from(script_pubkey.len() as u64),
+                    script_rlc_init,
+                    randomness,
+                ];
+
+                assert_proves(k, &circuit, vec![public_input]);
+            }
+        }
+    }
+
+    // Long running test. Run with:
+    // `cargo test random_scripts -- --ignored`
+    #[ignore]
+    #[test]
+    fn test_script_pubkey_random_scripts() {
+        let k = 10;
+        let mut rng = XorShiftRng::seed_from_u64(1);
+
+        for _ in 0..1000 {
+            let mut script_pubkey = random_valid_script(&mut rng, MAX_SCRIPT_PUBKEY_SIZE);
+
+            let r: u64 = rng.gen();
+            let randomness: BnScalar = BnScalar::from(r);
+
+            let circuit = TestExecutionCircuit {
+                script_pubkey: script_pubkey.clone(),
+                randomness,
+                initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+            };
+            script_pubkey.reverse();
+            let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
+                acc * randomness + BnScalar::from(v as u64)
+            });
+
+            let public_input = vec![
+                BnScalar::from(script_pubkey.len() as u64),
+                script_rlc_init,
+                randomness,
+            ];
+
+            let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
+            prover.assert_satisfied();
+        }
+    }
+
+    // Returns the circuit unrolling the script_pubkey on an all-zero initial stack and its public inputs
+    fn circuit_with_public_input(
+        script_pubkey: &[u8],
+        randomness: BnScalar,
+    ) -> (TestExecutionCircuit<BnScalar>, Vec<BnScalar>) {
+        let circuit = TestExecutionCircuit {
+            script_pubkey: script_pubkey.to_vec(),
+            randomness,
+            initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+        };
+        let public_input = PublicInputs::new(script_pubkey.to_vec(), randomness).execution_instance();
+        (circuit, public_input)
+    }
+
+    #[test]
+    fn test_script_pubkey_equal() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        let (op_2, op_3) = (OP_1 as u8 + 1, OP_1 as u8 + 2);
+
+        // Equal values leave 1 on the stack
+        let script_pubkey = vec![op_2, op_2, OP_EQUAL as u8];
+        let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::one()));
+        assert_eq!(stack[1], StackItem::Value(BnScalar::zero()));
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // Equal data pushes are compared by their RLC values
+        let script_pubkey = vec![2, 0xab, 0xcd, OP_PUSHDATA1 as u8, 2, 0xab, 0xcd, OP_EQUAL as u8];
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // Unequal values leave the empty array on the stack, which fails the script
+        let script_pubkey = vec![op_2, op_3, OP_EQUAL as u8];
+        let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Empty);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_rejects(k, &circuit, vec![public_input]);
+
+        let script_pubkey = vec![op_2, op_3, OP_EQUAL as u8, OP_1 as u8];
+        let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::one()));
+        assert_eq!(stack[1], StackItem::Empty);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+    }
+
+    #[test]
+    fn test_script_pubkey_equalverify() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        let (op_2, op_3) = (OP_1 as u8 + 1, OP_1 as u8 + 2);
+
+        // Both items are popped when they are equal
+        let script_pubkey = vec![op_2, op_2, OP_EQUALVERIFY as u8, OP_1 as u8];
+        let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::one()));
+        assert_eq!(stack[1], StackItem::Value(BnScalar::zero()));
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // Unequal items fail the script even if a true value is pushed afterwards
+        let script_pubkey = vec![op_2, op_3, OP_EQUALVERIFY as u8, OP_1 as u8];
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        let failures = assert_rejects(k, &circuit, vec![public_input]);
+        assert!(failures.iter().any(|failure| format!("{:?}", failure).contains("OP_EQUALVERIFY")));
+    }
+
+    #[test]
+    fn test_script_pubkey_verify() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        // A true top is popped and execution continues
+        let script_pubkey = vec![OP_1 as u8 + 1, OP_1 as u8, OP_VERIFY as u8];
+        let stack = final_stack(&script_pubkey, randomness, [BnScalar::zero(); MAX_STACK_DEPTH], true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::from(2)));
+        assert_eq!(stack[1], StackItem::Value(BnScalar::zero()));
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // A false top fails the script even if a true value is pushed afterwards
+        for false_push in [vec![OP_0 as u8], vec![1, NEGATIVE_ZERO as u8]] {
+            let mut script_pubkey = false_push;
+            script_pubkey.extend_from_slice(&[OP_VERIFY as u8, OP_1 as u8]);
+            let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+            let failures = assert_rejects(k, &circuit, vec![public_input]);
+            assert!(failures.iter().any(|failure| format!("{:?}", failure).contains("OP_VERIFY")));
+        }
+    }
+
+    #[test]
+    fn test_script_pubkey_drop() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        let (op_2, op_3, op_4) = (OP_1 as u8 + 1, OP_1 as u8 + 2, OP_1 as u8 + 3);
+
+        let script_pubkey = vec![OP_1 as u8, op_2, op_3, op_4, OP_DROP as u8];
+        let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::from(3)));
+        assert_eq!(stack[1], StackItem::Value(BnScalar::from(2)));
+        assert_eq!(stack[2], StackItem::Value(BnScalar::one()));
+        assert_eq!(stack[3], StackItem::Value(BnScalar::zero()));
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        let script_pubkey = vec![OP_1 as u8, op_2, op_3, op_4, OP_2DROP as u8];
+        let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::from(2)));
+        assert_eq!(stack[1], StackItem::Value(BnScalar::one()));
+        assert_eq!(stack[2], StackItem::Value(BnScalar::zero()));
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // Dropping the true values leaves a false top
+        let script_pubkey = vec![OP_0 as u8, op_2, op_3, OP_2DROP as u8];
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_rejects(k, &circuit, vec![public_input]);
+    }
+
+    #[test]
+    fn test_script_pubkey_drop_max_depth() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+
+        // The bottom items of a full stack are vacated by the drops
+        let mut script_pubkey = vec![OP_1 as u8; MAX_STACK_DEPTH];
+        script_pubkey.extend_from_slice(&[OP_DROP as u8, OP_2DROP as u8]);
+        let stack = final_stack(&script_pubkey, randomness, [BnScalar::zero(); MAX_STACK_DEPTH], true);
+        for (i, item) in stack.iter().enumerate() {
+            let expected = if i < MAX_STACK_DEPTH - 3 { BnScalar::one() } else { BnScalar::zero() };
+            assert_eq!(*item, StackItem::Value(expected));
+        }
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+    }
+
+    #[test]
+    fn test_script_pubkey_swap() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        let op_2 = OP_1 as u8 + 1;
+
+        let script_pubkey = vec![op_2, OP_1 as u8, OP_0 as u8, OP_SWAP as u8];
+        let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::one()));
+        assert_eq!(stack[1], StackItem::Empty);
+        assert_eq!(stack[2], StackItem::Value(BnScalar::from(2)));
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // Swapping the false value to the top fails the script
+        let script_pubkey = vec![OP_0 as u8, OP_1 as u8, OP_SWAP as u8];
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_rejects(k, &circuit, vec![public_input]);
+    }
+
+    #[test]
+    fn test_script_pubkey_over() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        let op_2 = OP_1 as u8 + 1;
+
+        let script_pubkey = vec![op_2, OP_0 as u8, OP_OVER as u8];
+        let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::from(2)));
+        assert_eq!(stack[1], StackItem::Empty);
+        assert_eq!(stack[2], StackItem::Value(BnScalar::from(2)));
+        assert_eq!(stack[3], StackItem::Value(BnScalar::zero()));
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // Copying a false value to the top fails the script
+        let script_pubkey = vec![OP_0 as u8, op_2, OP_OVER as u8];
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_rejects(k, &circuit, vec![public_input]);
+    }
+
+    #[test]
+    fn test_script_pubkey_rot() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        let (op_2, op_3, op_4) = (OP_1 as u8 + 1, OP_1 as u8 + 2, OP_1 as u8 + 3);
+
+        let script_pubkey = vec![op_4, op_2, op_3, OP_1 as u8, OP_ROT as u8];
+        let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::from(2)));
+        assert_eq!(stack[1], StackItem::Value(BnScalar::one()));
+        assert_eq!(stack[2], StackItem::Value(BnScalar::from(3)));
+        assert_eq!(stack[3], StackItem::Value(BnScalar::from(4)));
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // Rotating a false value to the top fails the script
+        let script_pubkey = vec![OP_0 as u8, op_2, op_3, OP_ROT as u8];
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_rejects(k, &circuit, vec![public_input]);
+    }
+
+    // Pushes the small numbers `values` from bottom to top, runs `opcode` on them and checks the
+    // final stack, top first, in the witness and in the circuit. A zero is pushed below the
+    // values, so the final stack top is the only true item checked by the circuit.
+    fn assert_stack_moves(opcode: usize, values: &[u8], expected: &[u8]) {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+
+        let mut script_pubkey = vec![OP_0 as u8];
+        script_pubkey.extend(values.iter().map(|value| OP_RESERVED as u8 + value));
+        script_pubkey.push(opcode as u8);
+        let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+        for (item, value) in stack.iter().zip(expected) {
+            assert_eq!(*item, StackItem::Value(BnScalar::from(*value as u64)));
+        }
+        // The item below the moved ones is shifted by the number of copies
+        assert_eq!(stack[expected.len()], StackItem::Empty);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // Too few items for the opcode fail the script
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey[2..], randomness);
+        assert_rejects(k, &circuit, vec![public_input]);
+    }
+
+    #[test]
+    fn test_script_pubkey_2dup() {
+        assert_stack_moves(OP_2DUP, &[5, 6], &[6, 5, 6, 5]);
+    }
+
+    #[test]
+    fn test_script_pubkey_3dup() {
+        assert_stack_moves(OP_3DUP, &[4, 5, 6], &[6, 5, 4, 6, 5, 4]);
+    }
 
-                let randomness_cell =
-                    assign_first_row!("Randomness of RLC operations", randomness, randomness);
+    #[test]
+    fn test_script_pubkey_2over() {
+        // (x1 x2 x3 x4) -> (x1 x2 x3 x4 x1 x2)
+        assert_stack_moves(OP_2OVER, &[3, 4, 5, 6], &[4, 3, 6, 5, 4, 3]);
+    }
 
-                for i in 0..MAX_STACK_DEPTH {
-                    region.assign_advice(
-                        || "Initialize stack to zero elements",
-                        config.stack[i],
-                        0,
-                        || Value::known(initial_stack[i]),
-                    )?;
-                }
+    #[test]
+    fn test_script_pubkey_2swap() {
+        // (x1 x2 x3 x4) -> (x3 x4 x1 x2)
+        assert_stack_moves(OP_2SWAP, &[3, 4, 5, 6], &[4, 3, 6, 5]);
+    }
 
-                assign_first_row!("Initialize num_data_bytes_remaining to zero", num_data_bytes_remaining);
-                assign_first_row!("Initialize num_data_length_bytes_remaining to zero", num_data_length_bytes_remaining);
-                assign_first_row!("Initialize num_data_length_acc_constant to zero", num_data_length_acc_constant);
-                let mut pk_rlc_acc_cell =
-                    assign_first_row!("Initialize pk_rlc_acc to zero", pk_rlc_acc);
-                let mut num_checksig_opcodes_cell =
-                    assign_first_row!("Initialize num_checksig_opcodes to zero", num_checksig_opcodes);
+    #[test]
+    fn test_script_pubkey_2rot() {
+        // (x1 x2 x3 x4 x5 x6) -> (x3 x4 x5 x6 x1 x2)
+        assert_stack_moves(OP_2ROT, &[1, 2, 3, 4, 5, 6], &[2, 1, 6, 5, 4, 3]);
+    }
 
-                let mut script_rlc_acc_vec = vec![];
-                let mut acc_value = F::zero();
-                script_rlc_acc_vec.push(acc_value);
+    #[test]
+    fn test_script_pubkey_pick_and_roll() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        let values = [1u8, 2, 3, 4, 5];
+        let pick_script = |depth: i64, opcode: usize| {
+            let mut script_pubkey: Vec<u8> = values.iter().map(|value| OP_RESERVED as u8 + value).collect();
+            script_pubkey.extend(push_script_num(depth));
+            script_pubkey.push(opcode as u8);
+            script_pubkey
+        };
 
-                for i in (0..script_pubkey.len()).rev() {
-                    acc_value = acc_value * randomness + F::from(script_pubkey[i] as u64);
-                    script_rlc_acc_vec.push(acc_value);
+        for depth in [0, 1, 2, 4] {
+            for opcode in [OP_PICK, OP_ROLL] {
+                let script_pubkey = pick_script(depth as i64, opcode);
+                // OP_PICK copies the item below the depth and OP_ROLL moves it
+                let mut expected: Vec<u8> = values.iter().rev().copied().collect();
+                let picked = if opcode == OP_PICK { expected[depth] } else { expected.remove(depth) };
+                expected.insert(0, picked);
+                let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+                for (item, value) in stack.iter().zip(&expected) {
+                    assert_eq!(*item, StackItem::Value(BnScalar::from(*value as u64)));
                 }
+                assert_eq!(stack[expected.len()], StackItem::Value(BnScalar::zero()));
+                let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+                assert_proves(k, &circuit, vec![public_input]);
+            }
+        }
 
-                // Reverse the script_rlc_acc running sum vector
-                script_rlc_acc_vec.reverse();
+        // Depths beyond the stack and negative depths fail the script
+        for depth in [5, -1] {
+            for opcode in [OP_PICK, OP_ROLL] {
+                let (circuit, public_input) = circuit_with_public_input(&pick_script(depth, opcode), randomness);
+                assert_rejects(k, &circuit, vec![public_input]);
+            }
+        }
+    }
 
-                let script_rlc_acc_init_cell =
-                    assign_first_row!("Initialize script_rlc_acc", script_rlc_acc, script_rlc_acc_vec[0]);
+    #[test]
+    fn test_script_pubkey_alt_stack() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        let (op_2, op_3, op_4) = (OP_1 as u8 + 1, OP_1 as u8 + 2, OP_1 as u8 + 3);
+        let (op_toaltstack, op_fromaltstack) = (OP_TOALTSTACK as u8, OP_FROMALTSTACK as u8);
+
+        // The items moved to the alt stack come back in reverse order, on top of the item pushed
+        // in between
+        let script_pubkey = vec![
+            OP_1 as u8, op_2, op_3,
+            op_toaltstack, op_toaltstack, op_toaltstack,
+            op_4,
+            op_fromaltstack, op_fromaltstack, op_fromaltstack,
+        ];
+        let mut parse_state = ScriptPubkeyParseState::new(randomness, zero_stack);
+        for (i, byte) in script_pubkey.iter().enumerate() {
+            parse_state.update(*byte);
+            if i == 5 {
+                assert_eq!(parse_state.alt_stack[..3], [BnScalar::one(), BnScalar::from(2), BnScalar::from(3)]);
+                assert_eq!(parse_state.alt_stack_size, 3);
+                assert_eq!(parse_state.stack_size, BnScalar::zero());
+            }
+        }
+        assert_eq!(parse_state.alt_stack_size, 0);
+        let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::from(3)));
+        assert_eq!(stack[1], StackItem::Value(BnScalar::from(2)));
+        assert_eq!(stack[2], StackItem::Value(BnScalar::one()));
+        assert_eq!(stack[3], StackItem::Value(BnScalar::from(4)));
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // OP_FROMALTSTACK on an empty alt stack and OP_TOALTSTACK on a full alt stack fail
+        let mut overflow = vec![OP_1 as u8; MAX_ALTSTACK_DEPTH + 2];
+        overflow.extend([op_toaltstack; MAX_ALTSTACK_DEPTH + 1]);
+        for script_pubkey in [
+            vec![OP_1 as u8, op_fromaltstack, OP_DROP as u8],
+            vec![OP_1 as u8, op_toaltstack, op_fromaltstack, op_fromaltstack, OP_DROP as u8],
+            overflow,
+        ] {
+            let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+            let failures = assert_rejects(k, &circuit, vec![public_input]);
+            assert!(failures.iter().any(|failure| format!("{:?}", failure).contains("Alt stack")));
+        }
+    }
 
-                let num_script_bytes_remaining_is_zero_chip
-                    = IsZeroChip::construct(config.num_script_bytes_remaining_is_zero.clone());
-                let is_stack_top_false_chip
-                    = IsZeroChip::construct(config.is_stack_top_false.clone());
-                let num_data_bytes_remaining_is_zero_chip
-                    = IsZeroChip::construct(config.num_data_bytes_remaining_is_zero.clone());
-                let num_data_length_bytes_remaining_is_zero_chip
-                    = IsZeroChip::construct(config.num_data_length_bytes_remaining_is_zero.clone());
-                let num_data_length_bytes_remaining_is_one_chip
-                    = IsZeroChip::construct(config.num_data_length_bytes_remaining_is_one.clone());
+    #[test]
+    fn test_script_pubkey_nip_and_tuck() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        let (op_2, op_3) = (OP_1 as u8 + 1, OP_1 as u8 + 2);
+
+        let script_pubkey = vec![op_3, OP_0 as u8, OP_1 as u8, OP_NIP as u8];
+        let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::one()));
+        assert_eq!(stack[1], StackItem::Value(BnScalar::from(3)));
+        assert_eq!(stack[2], StackItem::Value(BnScalar::zero()));
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        let script_pubkey = vec![op_3, op_2, OP_1 as u8, OP_TUCK as u8];
+        let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::one()));
+        assert_eq!(stack[1], StackItem::Value(BnScalar::from(2)));
+        assert_eq!(stack[2], StackItem::Value(BnScalar::one()));
+        assert_eq!(stack[3], StackItem::Value(BnScalar::from(3)));
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // OP_NIP keeps a false top
+        let script_pubkey = vec![OP_1 as u8, OP_0 as u8, OP_NIP as u8];
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_rejects(k, &circuit, vec![public_input]);
+    }
 
-                let mut script_state = ScriptPubkeyParseState::new(randomness, initial_stack);
-                
-                for byte_index in 0..MAX_SCRIPT_PUBKEY_SIZE+1 { // an extra row is assigned as queries are made to next rows
-                    
-                    let offset = byte_index + 1;
-                    
-                    if byte_index != MAX_SCRIPT_PUBKEY_SIZE {
-                        config.q_execution.enable(&mut region, offset)?;
-                    }
+    #[test]
+    fn test_script_pubkey_nip_and_tuck_max_depth() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
 
-                    region.assign_advice(
-                        || "Randomness for RLC operations",
-                        config.randomness,
-                        offset,
-                        || Value::known(randomness),
-                    )?;
+        // OP_TUCK on a full stack drops the bottom item and OP_NIP vacates it again
+        let mut script_pubkey = vec![OP_1 as u8 + 1; MAX_STACK_DEPTH - 1];
+        script_pubkey.push(OP_1 as u8);
+        for opcode in [OP_TUCK, OP_NIP] {
+            script_pubkey.push(opcode as u8);
+            let stack = final_stack(&script_pubkey, randomness, [BnScalar::zero(); MAX_STACK_DEPTH], true);
+            assert_eq!(stack[0], StackItem::Value(BnScalar::one()));
+            let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+            assert_proves(k, &circuit, vec![public_input]);
+        }
+        let stack = final_stack(&script_pubkey, randomness, [BnScalar::zero(); MAX_STACK_DEPTH], true);
+        assert_eq!(stack[1], StackItem::Value(BnScalar::one()));
+        assert_eq!(stack[MAX_STACK_DEPTH - 1], StackItem::Value(BnScalar::zero()));
+    }
 
-                    if byte_index < script_pubkey.len() {
-                        region.assign_advice(
-                            || "Load scriptPubkey bytes",
-                            config.opcode,
-                            offset,
-                            || Value::known(F::from(script_pubkey[byte_index] as u64)),
-                        )?;
+    #[test]
+    fn test_script_pubkey_depth() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+
+        // The empty stack has depth zero, which is pushed as the empty array
+        let script_pubkey = vec![OP_DEPTH as u8, OP_DEPTH as u8];
+        let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::one()));
+        assert_eq!(stack[1], StackItem::Empty);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // Pops and data pushes are counted
+        let script_pubkey = vec![
+            OP_1 as u8, OP_1 as u8, OP_1 as u8, OP_DROP as u8, 2, 0xab, 0xcd, OP_0 as u8, OP_NIP as u8, OP_DEPTH as u8,
+        ];
+        let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::from(3)));
+        assert_eq!(stack[1], StackItem::Empty);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // Items of the initial stack are counted
+        let mut initial_stack = zero_stack;
+        initial_stack[0] = BnScalar::one();
+        initial_stack[1] = BnScalar::zero();
+        initial_stack[2] = BnScalar::one();
+        let script_pubkey = vec![OP_DEPTH as u8];
+        let stack = final_stack(&script_pubkey, randomness, initial_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::from(3)));
+        let circuit = TestExecutionCircuit { script_pubkey: script_pubkey.clone(), randomness, initial_stack };
+        let public_input = PublicInputs::new(script_pubkey, randomness).execution_instance();
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // Depths from 128 are encoded in two CScriptNum bytes, as the sign bit of the first byte
+        // is set
+        for depth in [127i64, 128, 200] {
+            let mut script_pubkey = vec![OP_1 as u8; depth as usize];
+            script_pubkey.push(OP_DEPTH as u8);
+            let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+            assert_eq!(stack[0], StackItem::Value(script_num_item(depth, randomness)));
+            script_pubkey.extend(push_script_num(depth));
+            script_pubkey.push(OP_NUMEQUAL as u8);
+            let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+            assert_proves(k, &circuit, vec![public_input]);
+        }
 
-                        region.assign_advice(
-                            || "Load script_rlc_acc intermediate values",
-                            config.script_rlc_acc,
-                            offset,
-                            || Value::known(script_rlc_acc_vec[offset]),
-                        )?;
+        // The depth 128 is not the single byte 0x80, which is negative zero
+        let mut script_pubkey = vec![OP_1 as u8; 128];
+        script_pubkey.extend_from_slice(&[OP_DEPTH as u8, 1, NEGATIVE_ZERO as u8, OP_EQUAL as u8]);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_rejects(k, &circuit, vec![public_input]);
+    }
 
-                        let num_script_bytes_remaining = F::from((script_pubkey.len() - byte_index) as u64);
+    #[test]
+    fn test_script_pubkey_size() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        let size_equals = |len: i64| {
+            let mut script = vec![OP_SIZE as u8];
+            script.extend(push_script_num(len));
+            script.push(OP_EQUALVERIFY as u8);
+            script
+        };
 
-                        region.assign_advice(
-                            || "Load num_script_bytes_remaining values",
-                            config.num_script_bytes_remaining,
-                            offset,
-                            || Value::known(num_script_bytes_remaining),
-                        )?;
+        // The lengths of the empty array, PUSH75 and PUSHDATA1 data and of moved items are pushed
+        // without popping the items
+        let mut script_pubkey = vec![OP_0 as u8];
+        script_pubkey.extend(size_equals(0));
+        script_pubkey.push(75);
+        script_pubkey.extend([0x11; 75]);
+        script_pubkey.extend(size_equals(75));
+        script_pubkey.extend([OP_PUSHDATA1 as u8, 200]);
+        script_pubkey.extend([0x22; 200]);
+        script_pubkey.extend(size_equals(200));
+        script_pubkey.extend([3, 0xaa, 0xbb, 0xcc, OP_SWAP as u8]);
+        script_pubkey.extend(size_equals(200));
+        script_pubkey.extend([OP_TOALTSTACK as u8, OP_1 as u8, OP_FROMALTSTACK as u8]);
+        script_pubkey.extend(size_equals(200));
+        script_pubkey.extend([OP_DROP as u8, OP_DROP as u8]);
+        script_pubkey.extend(size_equals(3));
+        script_pubkey.extend([OP_16 as u8, OP_16 as u8, OP_ADD as u8]);
+        script_pubkey.extend(size_equals(1));
+        let mut parse_state = ScriptPubkeyParseState::new(randomness, zero_stack);
+        for byte in script_pubkey.iter() {
+            parse_state.update(*byte);
+        }
+        assert_eq!(parse_state.stack_len[..4], [1, 3, 75, 0]);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // A wrong length fails the OP_EQUALVERIFY
+        let mut script_pubkey = vec![3, 0xaa, 0xbb, 0xcc];
+        script_pubkey.extend(size_equals(2));
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_rejects(k, &circuit, vec![public_input]);
+
+        // The lengths of the stack left by a scriptSig are carried over to the scriptPubkey
+        let mut script_sig = vec![33];
+        script_sig.extend([0x02; 33]);
+        let script_pubkey = size_equals(33);
+        let mut public_inputs = PublicInputs::new(script_sig.clone(), randomness);
+        public_inputs.script_pubkeys.push(script_pubkey.clone());
+        let circuit = TestScriptSigCircuit { script_sig, script_pubkey, randomness };
+        assert_proves(k + 1, &circuit, vec![public_inputs.execution_instance()]);
+
+        // The lengths of an initial stack assigned from values are not known, so OP_SIZE fails
+        // even on a single byte item
+        let mut initial_stack = zero_stack;
+        initial_stack[0] = BnScalar::from(0x11);
+        let script_pubkey = size_equals(1);
+        let mut parse_state = ScriptPubkeyParseState::new(randomness, initial_stack);
+        for byte in script_pubkey.iter() {
+            parse_state.update(*byte);
+        }
+        assert_eq!(parse_state.stack_len[0], UNKNOWN_ITEM_LENGTH);
+        let circuit = TestExecutionCircuit { script_pubkey: script_pubkey.clone(), randomness, initial_stack };
+        let public_input = PublicInputs::new(script_pubkey, randomness).execution_instance();
+        let failures = assert_rejects(k, &circuit, vec![public_input]);
+        assert!(failures.iter().any(|failure| format!("{:?}", failure).contains("OP_SIZE")));
+    }
 
-                        num_script_bytes_remaining_is_zero_chip.assign(
-                            &mut region,
-                            offset,
-                            Value::known(num_script_bytes_remaining),
-                        )?;
+    // Script which pushes the bytes with a single push opcode
+    fn push_bytes(bytes: &[u8]) -> Vec<u8> {
+        if bytes.is_empty() {
+            return vec![OP_0 as u8];
+        }
+        let mut script = vec![bytes.len() as u8];
+        script.extend_from_slice(bytes);
+        script
+    }
 
-                        // The state of the script parser is updated
-                        script_state.update(script_pubkey[byte_index]);
+    #[test]
+    fn test_script_pubkey_hash256() {
+        use bitcoin_hashes::{sha256d, Hash};
 
-                        region.assign_advice(
-                            || "Load num_data_bytes_remaining values",
-                            config.num_data_bytes_remaining,
-                            offset,
-                            || Value::known(F::from(script_state.num_data_bytes_remaining)),
-                        )?;
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        let hash256 = |bytes: &[u8]| sha256d::Hash::hash(bytes).into_inner().to_vec();
+        let hash256_equals = |preimage: &[u8], digest: &[u8]| {
+            let mut script = push_bytes(preimage);
+            script.push(OP_HASH256 as u8);
+            script.extend(push_bytes(digest));
+            script.push(OP_EQUAL as u8);
+            script
+        };
 
-                        num_data_bytes_remaining_is_zero_chip.assign(
-                            &mut region,
-                            offset,
-                            Value::known(F::from(script_state.num_data_bytes_remaining)),
-                        )?;
+        // The digests of the empty array, short and long pushes are pushed as 32-byte items
+        for preimage in [vec![], b"abc".to_vec(), vec![0x11; 75]] {
+            let script_pubkey = hash256_equals(&preimage, &hash256(&preimage));
+            let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+            assert_proves(k, &circuit, vec![public_input]);
+        }
 
-                        region.assign_advice(
-                            || "Load num_data_length_bytes_remaining values",
-                            config.num_data_length_bytes_remaining,
-                            offset,
-                            || Value::known(F::from(script_state.num_data_length_bytes_remaining)),
-                        )?;
+        // The digest can be hashed again and its length is 32
+        let mut script_pubkey = push_bytes(b"abc");
+        script_pubkey.extend([OP_HASH256 as u8, OP_SIZE as u8]);
+        script_pubkey.extend(push_script_num(32));
+        script_pubkey.extend([OP_EQUALVERIFY as u8, OP_HASH256 as u8]);
+        script_pubkey.extend(push_bytes(&hash256(&hash256(b"abc"))));
+        script_pubkey.push(OP_EQUAL as u8);
+        let mut parse_state = ScriptPubkeyParseState::new(randomness, zero_stack);
+        for byte in script_pubkey.iter() {
+            parse_state.update(*byte);
+        }
+        // The accumulator holds the opcode, the length and the RLC of the hashed item and the RLC
+        // of the digest of both hashes
+        let mut expected_hash_rlc_acc = BnScalar::zero();
+        for (preimage, digest) in [
+            (b"abc".to_vec(), hash256(b"abc")),
+            (hash256(b"abc"), hash256(&hash256(b"abc"))),
+        ] {
+            for value in [
+                BnScalar::from(OP_HASH256 as u64),
+                BnScalar::from(preimage.len() as u64),
+                bytes_item(&preimage, randomness),
+                bytes_item(&digest, randomness),
+            ] {
+                expected_hash_rlc_acc = expected_hash_rlc_acc * randomness + value;
+            }
+        }
+        assert_eq!(parse_state.hash_rlc_acc, expected_hash_rlc_acc);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // A wrong digest fails the script
+        let script_pubkey = hash256_equals(b"abc", &hash256(b"abd"));
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_rejects(k, &circuit, vec![public_input]);
+
+        // A preimage pushed by the scriptSig is hashed by the scriptPubkey
+        let script_sig = push_bytes(b"preimage");
+        let mut script_pubkey = vec![OP_HASH256 as u8];
+        script_pubkey.extend(push_bytes(&hash256(b"preimage")));
+        script_pubkey.push(OP_EQUAL as u8);
+        let mut public_inputs = PublicInputs::new(script_sig.clone(), randomness);
+        public_inputs.script_pubkeys.push(script_pubkey.clone());
+        let circuit = TestScriptSigCircuit { script_sig, script_pubkey, randomness };
+        assert_proves(k + 1, &circuit, vec![public_inputs.execution_instance()]);
+    }
 
-                        num_data_length_bytes_remaining_is_zero_chip.assign(
-                            &mut region,
-                            offset,
-                            Value::known(F::from(script_state.num_data_length_bytes_remaining)),
-                        )?;
+    #[test]
+    fn test_script_pubkey_sha256() {
+        use bitcoin_hashes::{sha256d, Hash};
 
-                        let data_length_bytes_minus_one_val = if script_state.num_data_length_bytes_remaining > 0 {
-                            F::from(script_state.num_data_length_bytes_remaining.wrapping_sub(1))
-                        } else {
-                            -F::one()
-                        };
-                        num_data_length_bytes_remaining_is_one_chip.assign(
-                            &mut region,
-                            offset,
-                            Value::known(data_length_bytes_minus_one_val),
-                        )?;
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        let sha256_equals = |preimage: &[u8], digest: &[u8]| {
+            let mut script = push_bytes(preimage);
+            script.push(OP_SHA256 as u8);
+            script.extend(push_bytes(digest));
+            script.push(OP_EQUAL as u8);
+            script
+        };
 
-                        region.assign_advice(
-                            || "Load num_data_length_acc_constant values",
-                            config.num_data_length_acc_constant,
-                            offset,
-                            || Value::known(F::from(script_state.num_data_length_acc_constant)),
-                        )?;
+        // SHA-256 test vector of FIPS 180-2
+        let abc_digest = hex::decode("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad").unwrap();
+        let script_pubkey = sha256_equals(b"abc", &abc_digest);
+        let mut parse_state = ScriptPubkeyParseState::new(randomness, zero_stack);
+        for byte in script_pubkey.iter() {
+            parse_state.update(*byte);
+        }
+        let mut expected_hash_rlc_acc = BnScalar::zero();
+        for value in [
+            BnScalar::from(OP_SHA256 as u64),
+            BnScalar::from(3),
+            bytes_item(b"abc", randomness),
+            bytes_item(&abc_digest, randomness),
+        ] {
+            expected_hash_rlc_acc = expected_hash_rlc_acc * randomness + value;
+        }
+        assert_eq!(parse_state.hash_rlc_acc, expected_hash_rlc_acc);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // Two SHA-256 invocations give the HASH256 digest
+        let mut script_pubkey = push_bytes(b"abc");
+        script_pubkey.extend([OP_SHA256 as u8, OP_SHA256 as u8]);
+        script_pubkey.extend(push_bytes(&sha256d::Hash::hash(b"abc").into_inner()));
+        script_pubkey.push(OP_EQUAL as u8);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // A wrong digest fails the script
+        let script_pubkey = sha256_equals(b"abd", &abc_digest);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_rejects(k, &circuit, vec![public_input]);
+    }
 
-                        region.assign_advice(
-                            || "Load is_opcode_enabled column",
-                            config.is_opcode_enabled,
-                            offset,
-                            || Value::known(F::from(opcode_enabled(script_pubkey[byte_index]))),
-                        )?;
+    #[test]
+    fn test_script_pubkey_ripemd160() {
+        use bitcoin_hashes::{hash160, Hash};
 
-                        region.assign_advice(
-                            || "Load is_opcode_op0 column",
-                            config.is_opcode_op0,
-                            offset,
-                            || Value::known(F::from(op0_indicator(script_pubkey[byte_index]))),
-                        )?;
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let ripemd160_equals = |preimage: &[u8], digest: &[u8]| {
+            let mut script = push_bytes(preimage);
+            script.push(OP_RIPEMD160 as u8);
+            script.extend(push_bytes(digest));
+            script.push(OP_EQUAL as u8);
+            script
+        };
 
-                        region.assign_advice(
-                            || "Load is_opcode_op1_to_op16 column",
-                            config.is_opcode_op1_to_op16,
-                            offset,
-                            || Value::known(F::from(op1_to_op16_indicator(script_pubkey[byte_index]))),
-                        )?;
+        // RIPEMD-160 test vector of the specification, pushed as a 20-byte item
+        let abc_digest = hex::decode("8eb208f7e05d987a9b044a8e98c6b087f15a0bfc").unwrap();
+        let mut script_pubkey = push_bytes(b"abc");
+        script_pubkey.extend([OP_RIPEMD160 as u8, OP_SIZE as u8]);
+        script_pubkey.extend(push_script_num(20));
+        script_pubkey.extend([OP_EQUALVERIFY as u8]);
+        script_pubkey.extend(push_bytes(&abc_digest));
+        script_pubkey.push(OP_EQUAL as u8);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // The RIPEMD-160 digest of a SHA-256 digest is the HASH160 of a public key
+        let pubkey = hex::decode("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+        let mut script_pubkey = push_bytes(&pubkey);
+        script_pubkey.extend([OP_SHA256 as u8, OP_RIPEMD160 as u8]);
+        script_pubkey.extend(push_bytes(&hash160::Hash::hash(&pubkey).into_inner()));
+        script_pubkey.push(OP_EQUAL as u8);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // A wrong digest fails the script
+        let script_pubkey = ripemd160_equals(b"abd", &abc_digest);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_rejects(k, &circuit, vec![public_input]);
+    }
 
-                        region.assign_advice(
-                            || "Load is_opcode_push1_to_push75 column",
-                            config.is_opcode_push1_to_push75,
-                            offset,
-                            || Value::known(F::from(push1_to_push75_indicator(script_pubkey[byte_index]))),
-                        )?;
+    #[test]
+    fn test_script_pubkey_sha1() {
+        use bitcoin_hashes::{sha1, Hash};
 
-                        region.assign_advice(
-                            || "Load is_opcode_pushdata1 column",
-                            config.is_opcode_pushdata1,
-                            offset,
-                            || Value::known(F::from(pushdata1_indicator(script_pubkey[byte_index]))),
-                        )?;
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        let sha1_equals = |preimage: &[u8], digest: &[u8]| {
+            let mut script = push_bytes(preimage);
+            script.push(OP_SHA1 as u8);
+            script.extend(push_bytes(digest));
+            script.push(OP_EQUAL as u8);
+            script
+        };
 
-                        region.assign_advice(
-                            || "Load is_opcode_pushdata2 column",
-                            config.is_opcode_pushdata2,
-                            offset,
-                            || Value::known(F::from(pushdata2_indicator(script_pubkey[byte_index]))),
-                        )?;
+        // SHA-1 test vector of FIPS 180-2
+        let abc_digest = hex::decode("a9993e364706816aba3e25717850c26c9cd0d89d").unwrap();
+        let script_pubkey = sha1_equals(b"abc", &abc_digest);
+        let mut parse_state = ScriptPubkeyParseState::new(randomness, zero_stack);
+        for byte in script_pubkey.iter() {
+            parse_state.update(*byte);
+        }
+        let mut expected_hash_rlc_acc = BnScalar::zero();
+        for value in [
+            BnScalar::from(OP_SHA1 as u64),
+            BnScalar::from(3),
+            bytes_item(b"abc", randomness),
+            bytes_item(&abc_digest, randomness),
+        ] {
+            expected_hash_rlc_acc = expected_hash_rlc_acc * randomness + value;
+        }
+        assert_eq!(parse_state.hash_rlc_acc, expected_hash_rlc_acc);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // The digest is a 20-byte item, and the empty array is hashed like other items
+        let mut script_pubkey = vec![OP_0 as u8, OP_SHA1 as u8, OP_SIZE as u8];
+        script_pubkey.extend(push_script_num(20));
+        script_pubkey.extend([OP_EQUALVERIFY as u8]);
+        script_pubkey.extend(push_bytes(&sha1::Hash::hash(b"").into_inner()));
+        script_pubkey.push(OP_EQUAL as u8);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // A wrong digest fails the script
+        let script_pubkey = sha1_equals(b"abd", &abc_digest);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_rejects(k, &circuit, vec![public_input]);
+    }
 
-                        region.assign_advice(
-                            || "Load is_opcode_pushdata4 column",
-                            config.is_opcode_pushdata4,
-                            offset,
-                            || Value::known(F::from(pushdata4_indicator(script_pubkey[byte_index]))),
-                        )?;
+    #[test]
+    fn test_script_pubkey_hash160() {
+        use bitcoin_hashes::{hash160, Hash};
 
-                        region.assign_advice(
-                            || "Load is_opcode_checksig column",
-                            config.is_opcode_checksig,
-                            offset,
-                            || Value::known(F::from(checksig_indicator(script_pubkey[byte_index]))),
-                        )?;
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        let hash160 = |bytes: &[u8]| hash160::Hash::hash(bytes).into_inner().to_vec();
+        let hash160_equals = |preimage: &[u8], digest: &[u8]| {
+            let mut script = push_bytes(preimage);
+            script.push(OP_HASH160 as u8);
+            script.extend(push_bytes(digest));
+            script.push(OP_EQUAL as u8);
+            script
+        };
 
-                    }
-                    else {
+        // The digests of the empty array, short and long pushes are pushed as 20-byte items
+        let pubkey = hex::decode("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+        for preimage in [vec![], b"abc".to_vec(), pubkey.clone(), vec![0x11; 75]] {
+            let script_pubkey = hash160_equals(&preimage, &hash160(&preimage));
+            let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+            assert_proves(k, &circuit, vec![public_input]);
+        }
 
-                        if byte_index != MAX_SCRIPT_PUBKEY_SIZE {
-                            region.assign_advice(
-                                || "Load scriptPubkey padding bytes",
-                                config.opcode,
-                                offset,
-                                || Value::known(F::from(OP_NOP as u64)),
-                            )?;
+        // The accumulator holds the opcode, the length and the RLC of the hashed item and the RLC
+        // of its digest, which is the digest of OP_SHA256 followed by OP_RIPEMD160
+        let script_pubkey = hash160_equals(&pubkey, &hash160(&pubkey));
+        let mut parse_state = ScriptPubkeyParseState::new(randomness, zero_stack);
+        for byte in script_pubkey.iter() {
+            parse_state.update(*byte);
+        }
+        let mut expected_hash_rlc_acc = BnScalar::zero();
+        for value in [
+            BnScalar::from(OP_HASH160 as u64),
+            BnScalar::from(pubkey.len() as u64),
+            bytes_item(&pubkey, randomness),
+            bytes_item(&hash_digest(OP_RIPEMD160, &hash_digest(OP_SHA256, &pubkey)), randomness),
+        ] {
+            expected_hash_rlc_acc = expected_hash_rlc_acc * randomness + value;
+        }
+        assert_eq!(parse_state.hash_rlc_acc, expected_hash_rlc_acc);
+
+        // The digest is a 20-byte item
+        let mut script_pubkey = push_bytes(&pubkey);
+        script_pubkey.extend([OP_HASH160 as u8, OP_SIZE as u8]);
+        script_pubkey.extend(push_script_num(20));
+        script_pubkey.extend([OP_EQUALVERIFY as u8, OP_DROP as u8, OP_1 as u8]);
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // A wrong digest fails the script
+        let script_pubkey = hash160_equals(&pubkey, &hash160(b"abc"));
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_rejects(k, &circuit, vec![public_input]);
+    }
 
-                            region.assign_advice(
-                                || "Load is_opcode_enabled column",
-                                config.is_opcode_enabled,
-                                offset,
-                                || Value::known(F::one()),
-                            )?;
-                        }
-                        else {
-                            region.assign_advice(
-                                || "Load scriptPubkey padding bytes",
-                                config.opcode,
-                                offset,
-                                || Value::known(F::zero()),
-                            )?;
+    #[test]
+    fn test_script_pubkey_disabled_opcodes() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let assert_disabled = |script_pubkey: &[u8]| {
+            let (circuit, public_input) = circuit_with_public_input(script_pubkey, randomness);
+            let failures = assert_rejects(k, &circuit, vec![public_input]);
+            assert!(
+                failures.iter().any(|failure| format!("{:?}", failure).contains("Disabled opcodes")),
+                "Script {:02x?} is not rejected as disabled", script_pubkey,
+            );
+        };
 
-                            region.assign_advice(
-                                || "Load is_opcode_enabled column",
-                                config.is_opcode_enabled,
-                                offset,
-                                || Value::known(F::zero()),
-                            )?;
-                        }
+        // OP_CAT would leave a true stack top
+        assert_disabled(&[OP_1 as u8, OP_1 as u8, OP_CAT as u8]);
+        // Disabled opcodes fail the script even in a branch which is not taken
+        assert_disabled(&[OP_0 as u8, OP_IF as u8, OP_CAT as u8, OP_ENDIF as u8, OP_1 as u8]);
+        for opcode in DISABLED_OPCODES {
+            assert_disabled(&[OP_1 as u8, OP_1 as u8, opcode as u8, OP_DROP as u8, OP_1 as u8]);
+        }
 
-                        region.assign_advice(
-                            || "Load script_rlc_acc padding",
-                            config.script_rlc_acc,
-                            offset,
-                            || Value::known(F::zero()),
-                        )?;
+        // The byte of a disabled opcode may be pushed as data
+        let script_pubkey = [1, OP_CAT as u8, OP_DROP as u8, OP_1 as u8];
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+    }
 
-                        region.assign_advice(
-                            || "Load num_script_bytes_remaining values",
-                            config.num_script_bytes_remaining,
-                            offset,
-                            || Value::known(F::zero()),
-                        )?;
+    #[test]
+    fn test_script_pubkey_nop() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
 
-                        num_script_bytes_remaining_is_zero_chip.assign(
-                            &mut region,
-                            offset,
-                            Value::known(F::zero()),
-                        )?;
+        // The no-ops are executed in the middle of the script, before its padding. OP_NOP2 and
+        // OP_NOP3 are OP_CHECKLOCKTIMEVERIFY and OP_CHECKSEQUENCEVERIFY, which are tested separately.
+        let script_pubkey = vec![
+            OP_1 as u8,
+            OP_NOP1 as u8,
+            OP_2 as u8,
+            OP_NOP4 as u8,
+            OP_ADD as u8,
+            OP_NOP as u8,
+            OP_3 as u8,
+            OP_NOP10 as u8,
+            OP_EQUAL as u8,
+        ];
+        let mut parse_state = ScriptPubkeyParseState::new(randomness, [BnScalar::zero(); MAX_STACK_DEPTH]);
+        for byte in script_pubkey.iter() {
+            parse_state.update(*byte);
+        }
+        assert_eq!(parse_state.stack[0], BnScalar::one());
+        assert_eq!(parse_state.stack_size, BnScalar::one());
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // All the reserved no-ops which are not redefined are accepted
+        for opcode in [OP_NOP1].into_iter().chain(OP_NOP4..=OP_NOP10) {
+            let script_pubkey = [OP_1 as u8, opcode as u8, OP_1 as u8, OP_EQUAL as u8];
+            let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+            assert_proves(k, &circuit, vec![public_input]);
+        }
 
-                        region.assign_advice(
-                            || "Load num_data_bytes_remaining values",
-                            config.num_data_bytes_remaining,
-                            offset,
-                            || Value::known(F::zero()),
-                        )?;
+        // A no-op in a branch which is not taken is not executed
+        let script_pubkey = [OP_0 as u8, OP_IF as u8, OP_NOP4 as u8, OP_ENDIF as u8, OP_1 as u8];
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
 
-                        num_data_bytes_remaining_is_zero_chip.assign(
-                            &mut region,
-                            offset,
-                            Value::known(F::zero()),
-                        )?;
+        // The no-ops do not make a false stack top true
+        let script_pubkey = [OP_1 as u8, OP_0 as u8, OP_NOP1 as u8];
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_rejects(k, &circuit, vec![public_input]);
+    }
 
-                        region.assign_advice(
-                            || "Load num_data_length_bytes_remaining values",
-                            config.num_data_length_bytes_remaining,
-                            offset,
-                            || Value::known(F::zero()),
-                        )?;
+    #[test]
+    fn test_script_pubkey_codeseparator() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
 
-                        num_data_length_bytes_remaining_is_zero_chip.assign(
-                            &mut region,
-                            offset,
-                            Value::known(F::zero()),
-                        )?;
+        // The OP_CODESEPARATOR in the branch which is not taken is not executed
+        let script_pubkey = vec![
+            OP_1 as u8,
+            OP_CODESEPARATOR as u8,
+            OP_0 as u8,
+            OP_IF as u8,
+            OP_CODESEPARATOR as u8,
+            OP_ENDIF as u8,
+            OP_CODESEPARATOR as u8,
+            OP_1 as u8,
+        ];
+        let expected_positions = [0, 0, 2, 2, 2, 2, 2, 7, 7, 7];
 
-                        num_data_length_bytes_remaining_is_one_chip.assign(
-                            &mut region,
-                            offset,
-                            Value::known(-F::one()),
-                        )?;
+        let mut parse_state = ScriptPubkeyParseState::new(randomness, [BnScalar::zero(); MAX_STACK_DEPTH]);
+        for byte in script_pubkey.iter() {
+            parse_state.update(*byte);
+        }
+        assert_eq!(parse_state.last_codeseparator_position, 7);
+        assert_eq!(parse_state.stack_size, BnScalar::from(2));
+
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // The position is recorded in the row of each executed OP_CODESEPARATOR and kept in the
+        // padding rows
+        let (config, regions) = record_advice(&circuit).unwrap();
+        let region = regions
+            .iter()
+            .find(|region| region.name == "ScriptPubkey unrolling")
+            .expect("Unrolling region is assigned");
+        for (offset, position) in expected_positions.into_iter().enumerate() {
+            assert_eq!(
+                region.value(config.last_codeseparator_position, offset),
+                Some(BnScalar::from(position)),
+                "Unexpected position in row {}", offset,
+            );
+        }
 
-                        region.assign_advice(
-                            || "Load num_data_length_acc_constant values",
-                            config.num_data_length_acc_constant,
-                            offset,
-                            || Value::known(F::from(script_state.num_data_length_acc_constant)),
-                        )?;
+        // Without an OP_CODESEPARATOR, the whole script is covered
+        let mut parse_state = ScriptPubkeyParseState::new(randomness, [BnScalar::zero(); MAX_STACK_DEPTH]);
+        for byte in [OP_1 as u8, OP_NOP as u8] {
+            parse_state.update(byte);
+        }
+        assert_eq!(parse_state.last_codeseparator_position, 0);
+    }
 
-                        region.assign_advice(
-                            || "Load is_opcode_op0 column",
-                            config.is_opcode_op0,
-                            offset,
-                            || Value::known(F::zero()),
-                        )?;
+    #[test]
+    fn test_script_pubkey_checklocktimeverify() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        // Locktimes whose least significant byte is zero cannot be decoded, so the
+        // timestamps are one above a round number
+        let run = |locktime: i64, nlocktime: u32, public_nlocktime: u32| {
+            let mut script_pubkey = push_script_num(locktime);
+            script_pubkey.extend([OP_CHECKLOCKTIMEVERIFY as u8, OP_DROP as u8, OP_1 as u8]);
+            let circuit = TestLockTimeCircuit {
+                circuit: TestExecutionCircuit {
+                    script_pubkey: script_pubkey.clone(),
+                    randomness,
+                    initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+                },
+                nlocktime,
+                nsequence: 0,
+            };
+            let public_input = PublicInputs::new(script_pubkey, randomness)
+                .with_nlocktime(public_nlocktime)
+                .execution_instance();
+            run_and_verify(k, &circuit, vec![public_input])
+        };
 
-                        region.assign_advice(
-                            || "Load is_opcode_op1_to_op16 column",
-                            config.is_opcode_op1_to_op16,
-                            offset,
-                            || Value::known(F::zero()),
-                        )?;
+        // The locktime is not above the nLockTime, and both are block heights or both are timestamps
+        for (locktime, nlocktime) in [
+            (0, 0),
+            (100, 100),
+            (100, 101),
+            (499_999_999, 499_999_999),
+            (500_000_001, 500_000_001),
+            (500_000_001, 600_000_001),
+            (600_000_001, u32::MAX),
+        ] {
+            assert_eq!(run(locktime, nlocktime, nlocktime), Ok(()), "Locktime {} rejected for nLockTime {}", locktime, nlocktime);
+        }
 
-                        region.assign_advice(
-                            || "Load is_opcode_push1_to_push75 column",
-                            config.is_opcode_push1_to_push75,
-                            offset,
-                            || Value::known(F::zero()),
-                        )?;
+        for (locktime, nlocktime) in [
+            // The locktime is above the nLockTime
+            (101, 100),
+            (600_000_001, 500_000_001),
+            // A block height and a timestamp are not comparable
+            (100, 500_000_001),
+            (499_999_999, 500_000_001),
+            (500_000_001, 499_999_999),
+            // Negative locktimes fail the script
+            (-1, 100),
+        ] {
+            assert!(run(locktime, nlocktime, nlocktime).is_err(), "Locktime {} accepted for nLockTime {}", locktime, nlocktime);
+        }
 
-                        region.assign_advice(
-                            || "Load is_opcode_pushdata1 column",
-                            config.is_opcode_pushdata1,
-                            offset,
-                            || Value::known(F::zero()),
-                        )?;
+        // The nLockTime of the circuit is the public one
+        assert!(run(100, 100, 99).is_err());
 
-                        region.assign_advice(
-                            || "Load is_opcode_pushdata2 column",
-                            config.is_opcode_pushdata2,
-                            offset,
-                            || Value::known(F::zero()),
-                        )?;
+        // The locktime is not popped, so it is the result of the script
+        let script_pubkey = [push_script_num(100), vec![OP_CHECKLOCKTIMEVERIFY as u8]].concat();
+        let mut parse_state = ScriptPubkeyParseState::new(randomness, [BnScalar::zero(); MAX_STACK_DEPTH]);
+        for byte in script_pubkey.iter() {
+            parse_state.update(*byte);
+        }
+        assert_eq!(parse_state.stack[0], script_num_item(100, randomness));
+        assert_eq!(parse_state.stack_size, BnScalar::one());
+        let circuit = TestLockTimeCircuit {
+            circuit: TestExecutionCircuit {
+                script_pubkey: script_pubkey.clone(),
+                randomness,
+                initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+            },
+            nlocktime: 100,
+            nsequence: 0,
+        };
+        let public_input = PublicInputs::new(script_pubkey, randomness).with_nlocktime(100).execution_instance();
+        assert_proves(k, &circuit, vec![public_input]);
 
-                        region.assign_advice(
-                            || "Load is_opcode_pushdata4 column",
-                            config.is_opcode_pushdata4,
-                            offset,
-                            || Value::known(F::zero()),
-                        )?;
+        // The locktime operand must be present
+        let (circuit, public_input) = circuit_with_public_input(&[OP_CHECKLOCKTIMEVERIFY as u8, OP_1 as u8], randomness);
+        assert_rejects(k, &circuit, vec![public_input]);
+    }
 
-                        region.assign_advice(
-                            || "Load is_opcode_checksig column",
-                            config.is_opcode_checksig,
-                            offset,
-                            || Value::known(F::zero()),
-                        )?;
+    #[test]
+    fn test_script_pubkey_checksequenceverify() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let run = |locktime: i64, nsequence: u32, public_nsequence: u32| {
+            let mut script_pubkey = push_script_num(locktime);
+            script_pubkey.extend([OP_CHECKSEQUENCEVERIFY as u8, OP_DROP as u8, OP_1 as u8]);
+            let circuit = TestLockTimeCircuit {
+                circuit: TestExecutionCircuit {
+                    script_pubkey: script_pubkey.clone(),
+                    randomness,
+                    initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+                },
+                nlocktime: 0,
+                nsequence,
+            };
+            let public_input = PublicInputs::new(script_pubkey, randomness)
+                .with_nsequence(public_nsequence)
+                .execution_instance();
+            run_and_verify(k, &circuit, vec![public_input])
+        };
+        // Relative locktimes in units of 512 seconds have the type flag set
+        let time = |value: u32| SEQUENCE_LOCKTIME_TYPE_FLAG as u32 | value;
+        let disable_flag = SEQUENCE_LOCKTIME_DISABLE_FLAG as u32;
+
+        // The masked locktime is not above the masked nSequence, and both are block counts or
+        // both are time intervals
+        for (locktime, nsequence) in [
+            (0, 0),
+            (1, 1),
+            (10, 11),
+            (10, 0x1000b),
+            (0x1000b, 11),
+            (time(10), time(10)),
+            (time(10), time(11)),
+            // The disable flag of the locktime turns the opcode into a NOP, whose 5 byte locktime
+            // is not compared with the nSequence
+            (disable_flag | 1, 0),
+            (disable_flag | 11, 10),
+            (disable_flag | 1, time(1)),
+            (disable_flag | 1, disable_flag | 1),
+        ] {
+            assert_eq!(
+                run(locktime as i64, nsequence, nsequence), Ok(()),
+                "Locktime {:#x} rejected for nSequence {:#x}", locktime, nsequence,
+            );
+        }
 
-                    }
+        for (locktime, nsequence) in [
+            // The masked locktime is above the masked nSequence
+            (11, 10),
+            (0x1000b, 0x2000a),
+            (time(11), time(10)),
+            // The disable flag of the nSequence fails an enabled locktime
+            (1, disable_flag | 1),
+            (0, disable_flag),
+            // A block count and a time interval are not comparable
+            (10, time(10)),
+            (time(10), 10),
+        ] {
+            assert!(
+                run(locktime as i64, nsequence, nsequence).is_err(),
+                "Locktime {:#x} accepted for nSequence {:#x}", locktime, nsequence,
+            );
+        }
 
-                    for i in 0..MAX_STACK_DEPTH {
-                        region.assign_advice(
-                            || "Load stack values",
-                            config.stack[i],
-                            offset,
-                            || Value::known(script_state.stack[i]),
-                        )?;
-                    }
+        // Negative locktimes fail the script, even with the disable flag
+        assert!(run(-1, 10, 10).is_err());
+        assert!(run(-(disable_flag as i64 | 1), 10, 10).is_err());
 
-                    pk_rlc_acc_cell = region.assign_advice(
-                        || "Load pk_rlc_acc column",
-                        config.pk_rlc_acc,
-                        offset,
-                        || Value::known(script_state.pk_rlc_acc),
-                    )?;
+        // The nSequence of the circuit is the public one
+        assert!(run(10, 10, 9).is_err());
 
-                    num_checksig_opcodes_cell = region.assign_advice(
-                        || "Load num_checksig_opcodes column",
-                        config.num_checksig_opcodes,
-                        offset,
-                        || Value::known(F::from(script_state.num_checksig_opcodes)),
-                    )?;
+        // The locktime is not popped, so it is the result of the script
+        let script_pubkey = [push_script_num(disable_flag as i64 | 1), vec![OP_CHECKSEQUENCEVERIFY as u8]].concat();
+        let mut parse_state = ScriptPubkeyParseState::new(randomness, [BnScalar::zero(); MAX_STACK_DEPTH]);
+        for byte in script_pubkey.iter() {
+            parse_state.update(*byte);
+        }
+        assert_eq!(parse_state.stack[0], script_num_item(disable_flag as i64 | 1, randomness));
+        assert_eq!(parse_state.stack_size, BnScalar::one());
+        let circuit = TestLockTimeCircuit {
+            circuit: TestExecutionCircuit {
+                script_pubkey: script_pubkey.clone(),
+                randomness,
+                initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+            },
+            nlocktime: 0,
+            nsequence: 10,
+        };
+        let public_input = PublicInputs::new(script_pubkey, randomness).with_nsequence(10).execution_instance();
+        assert_proves(k, &circuit, vec![public_input]);
 
-                    is_stack_top_false_chip.assign(
-                        &mut region,
-                        offset,
-                        Value::known(script_state.stack[0] *(script_state.stack[0] - F::from(NEGATIVE_ZERO))),
-                    )?;
+        // The locktime operand must be present
+        let (circuit, public_input) = circuit_with_public_input(&[OP_CHECKSEQUENCEVERIFY as u8, OP_1 as u8], randomness);
+        assert_rejects(k, &circuit, vec![public_input]);
+    }
 
-                }
-                Ok(ExecutionChipAssignedCells {
-                        script_length: script_length_cell,
-                        script_rlc_acc_init: script_rlc_acc_init_cell,
-                        randomness: randomness_cell,
-                        pk_rlc_acc: pk_rlc_acc_cell.clone(),
-                        num_checksig_opcodes: num_checksig_opcodes_cell.clone(),
-                })
-            }
-        )
+    #[test]
+    fn test_script_pubkey_add() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        let (op_2, op_3) = (OP_1 as u8 + 1, OP_1 as u8 + 2);
+
+        // Positive result
+        let script_pubkey = vec![op_2, op_3, OP_ADD as u8];
+        let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::from(5)));
+        assert_eq!(stack[1], StackItem::Value(BnScalar::zero()));
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // Negative result: -5 + 3 = -2
+        let script_pubkey = vec![1, 0x85, op_3, OP_ADD as u8];
+        let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::from(0x82)));
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // Zero result is pushed as the empty array: -1 + 1 = 0
+        let script_pubkey = vec![1, 0x81, OP_1 as u8, OP_ADD as u8, OP_0 as u8, OP_EQUAL as u8];
+        let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::one()));
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // The sum of two 4 byte operands needs 5 bytes
+        let script_pubkey = vec![4, 0xff, 0xff, 0xff, 0x7f, 4, 0xff, 0xff, 0xff, 0x7f, OP_ADD as u8];
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // Operands longer than 4 bytes and non-minimal operands are rejected
+        for script_pubkey in [
+            vec![5, 0x01, 0x01, 0x01, 0x01, 0x01, OP_1 as u8, OP_ADD as u8],
+            vec![2, 0x01, 0x00, OP_1 as u8, OP_ADD as u8],
+        ] {
+            let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+            let failures = assert_rejects(k, &circuit, vec![public_input]);
+            assert!(failures.iter().any(|failure| format!("{:?}", failure).contains("OP_ADD")));
+        }
     }
-    
-    pub fn expose_public(
-        &self,
-        config: ExecutionConfig<F>,
-        mut layouter: impl Layouter<F>,
-        cell: AssignedCell<F, F>,
-        row: usize,
-    ) -> Result<(), Error> {
-        layouter.constrain_instance(cell.cell(), config.instance, row)
+
+    // Script which pushes the minimal encoding of a number
+    fn push_script_num(value: i64) -> Vec<u8> {
+        let bytes = encode_script_num(value);
+        if bytes.is_empty() {
+            return vec![OP_0 as u8];
+        }
+        let mut script = vec![bytes.len() as u8];
+        script.extend(bytes);
+        script
     }
-}
 
-    
+    // Checks that `opcode` maps the numbers to the expected result and that the script is proven.
+    // A zero result is compared with the empty array, as it would fail the script.
+    fn assert_numeric_opcode(opcode: usize, operands: &[i64], expected: i64) {
+        let k = 10;
+        let mut rng = rand::thread_rng();
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
 
-#[cfg(test)]
-mod tests {
-    use halo2_proofs::dev::MockProver;
-    use halo2_proofs::halo2curves::bn256::Fr as BnScalar;
-    use halo2_proofs::circuit::{SimpleFloorPlanner, Layouter};
-    use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
-    use rand::Rng;
-    use secp256k1::constants::PUBLIC_KEY_SIZE;
+        let mut script_pubkey: Vec<u8> = operands.iter().flat_map(|v| push_script_num(*v)).collect();
+        script_pubkey.push(opcode as u8);
+        let stack = final_stack(&script_pubkey, randomness, [BnScalar::zero(); MAX_STACK_DEPTH], false);
+        assert_eq!(stack[0], StackItem::Value(script_num_item(expected, randomness)));
 
-    use crate::bitcoinvm_circuit::constants::*;
-    use crate::bitcoinvm_circuit::execution::{ExecutionChip, ExecutionConfig};
-    use crate::Field;
+        if expected == 0 {
+            script_pubkey.extend([OP_0 as u8, OP_EQUAL as u8]);
+        }
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+    }
 
+    #[test]
+    fn test_script_pubkey_sub() {
+        assert_numeric_opcode(OP_SUB, &[5, 3], 2);
+        assert_numeric_opcode(OP_SUB, &[3, 5], -2);
+        assert_numeric_opcode(OP_SUB, &[-3, -3], 0);
+        assert_numeric_opcode(OP_SUB, &[-1, 127], -128);
+        assert_numeric_opcode(OP_SUB, &[0x7fffffff, -0x7fffffff], 0xfffffffe);
+    }
 
-    struct TestExecutionCircuit<F: Field> {
-        pub script_pubkey: Vec<u8>,
-        pub randomness: F,
-        pub initial_stack: [F; MAX_STACK_DEPTH],
+    #[test]
+    fn test_script_pubkey_1add() {
+        assert_numeric_opcode(OP_1ADD, &[1], 2);
+        assert_numeric_opcode(OP_1ADD, &[-1], 0);
+        assert_numeric_opcode(OP_1ADD, &[0], 1);
+        assert_numeric_opcode(OP_1ADD, &[127], 128);
+        assert_numeric_opcode(OP_1ADD, &[-129], -128);
     }
 
-    impl<F: Field> Circuit<F> for TestExecutionCircuit<F> {
-        type Config = ExecutionConfig<F>;
+    #[test]
+    fn test_script_pubkey_1sub() {
+        assert_numeric_opcode(OP_1SUB, &[2], 1);
+        assert_numeric_opcode(OP_1SUB, &[1], 0);
+        assert_numeric_opcode(OP_1SUB, &[0], -1);
+        assert_numeric_opcode(OP_1SUB, &[-127], -128);
+        assert_numeric_opcode(OP_1SUB, &[129], 128);
+    }
 
-        type FloorPlanner = SimpleFloorPlanner;
+    #[test]
+    fn test_script_pubkey_negate() {
+        assert_numeric_opcode(OP_NEGATE, &[5], -5);
+        assert_numeric_opcode(OP_NEGATE, &[-5], 5);
+        assert_numeric_opcode(OP_NEGATE, &[0], 0);
+        assert_numeric_opcode(OP_NEGATE, &[128], -128);
+        assert_numeric_opcode(OP_NEGATE, &[-0x7fffffff], 0x7fffffff);
+    }
 
-        fn without_witnesses(&self) -> Self {
-            Self {
-                script_pubkey: vec![],
-                randomness: F::zero(),
-                initial_stack: [F::zero(); MAX_STACK_DEPTH],
-            }
-        }
+    #[test]
+    fn test_script_pubkey_abs() {
+        assert_numeric_opcode(OP_ABS, &[5], 5);
+        assert_numeric_opcode(OP_ABS, &[-5], 5);
+        assert_numeric_opcode(OP_ABS, &[0], 0);
+        assert_numeric_opcode(OP_ABS, &[-128], 128);
+        assert_numeric_opcode(OP_ABS, &[127], 127);
+    }
 
-        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
-            ExecutionChip::configure(meta)
+    #[test]
+    fn test_script_pubkey_comparisons() {
+        for (operands, lt, gt) in [
+            ([2, 3], 1, 0),
+            ([3, 2], 0, 1),
+            ([3, 3], 0, 0),
+            ([-3, 2], 1, 0),
+            ([-0x7fffffff, 0x7fffffff], 1, 0),
+            ([0, -1], 0, 1),
+        ] {
+            let eq = (operands[0] == operands[1]) as i64;
+            assert_numeric_opcode(OP_LESSTHAN, &operands, lt);
+            assert_numeric_opcode(OP_GREATERTHAN, &operands, gt);
+            assert_numeric_opcode(OP_LESSTHANOREQUAL, &operands, lt + eq);
+            assert_numeric_opcode(OP_GREATERTHANOREQUAL, &operands, gt + eq);
+            assert_numeric_opcode(OP_MIN, &operands, operands[0].min(operands[1]));
+            assert_numeric_opcode(OP_MAX, &operands, operands[0].max(operands[1]));
         }
+    }
 
-        fn synthesize(
-            &self,
-            config: Self::Config,
-            mut layouter: impl Layouter<F>
-        ) -> Result<(), Error> {
-            let chip = ExecutionChip::construct();
-
-            let chip_cells  = chip.assign_script_pubkey_unroll(
-                config.clone(),
-                &mut layouter,
-                self.script_pubkey.clone(),
-                self.randomness,
-                self.initial_stack,
-            )?;
-            
-            chip.expose_public(config.clone(), layouter.namespace(|| "script_length"), chip_cells.script_length, 0)?;
-            chip.expose_public(config.clone(), layouter.namespace(|| "script_rlc_acc"), chip_cells.script_rlc_acc_init, 1)?;
-            chip.expose_public(config, layouter.namespace(|| "randomness"), chip_cells.randomness, 2)?;
-            Ok(())
-        }
+    #[test]
+    fn test_script_pubkey_within() {
+        // The lower bound is inclusive and the upper bound is exclusive
+        assert_numeric_opcode(OP_WITHIN, &[2, 2, 5], 1);
+        assert_numeric_opcode(OP_WITHIN, &[4, 2, 5], 1);
+        assert_numeric_opcode(OP_WITHIN, &[5, 2, 5], 0);
+        assert_numeric_opcode(OP_WITHIN, &[1, 2, 5], 0);
+        assert_numeric_opcode(OP_WITHIN, &[-1, -2, 0], 1);
+        assert_numeric_opcode(OP_WITHIN, &[3, 3, 3], 0);
     }
 
     #[test]
-    fn test_script_pubkey_push_constants() {
+    fn test_script_pubkey_comparison_conditions() {
+        use halo2_proofs::arithmetic::Field as HaloField;
+
         let k = 10;
-        let mut script_pubkey = vec![];
-        for i in 0..17 {
-            script_pubkey.push((OP_1 + i) as u8);
-        }
-        
         let mut rng = rand::thread_rng();
         let r: u64 = rng.gen();
         let randomness: BnScalar = BnScalar::from(r);
-        
-        let circuit = TestExecutionCircuit {
-            script_pubkey: script_pubkey.clone(),
-            randomness,
-            initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
-        };
-        script_pubkey.reverse();
-        let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
-            acc * randomness + BnScalar::from(v as u64)
-        });
 
-        let public_input = vec![
-            BnScalar::from(script_pubkey.len() as u64),
-            script_rlc_init,
-            randomness,
+        let (op_0, op_1, op_2, op_3) = (OP_0 as u8, OP_1 as u8, OP_1 as u8 + 1, OP_1 as u8 + 2);
+        let (op_if, op_notif, op_else, op_endif) = (OP_IF as u8, OP_NOTIF as u8, OP_ELSE as u8, OP_ENDIF as u8);
+
+        // Comparisons of 2 and 3 and whether they are true
+        let comparisons = [
+            (vec![op_2, op_3, OP_LESSTHAN as u8], true),
+            (vec![op_3, op_2, OP_LESSTHAN as u8], false),
+            (vec![op_2, op_3, OP_GREATERTHAN as u8], false),
+            (vec![op_3, op_3, OP_LESSTHANOREQUAL as u8], true),
+            (vec![op_2, op_3, OP_GREATERTHANOREQUAL as u8], false),
+            (vec![op_3, op_3, OP_NUMEQUAL as u8], true),
+            (vec![op_2, op_2, op_3, OP_WITHIN as u8], true),
+            (vec![op_3, op_2, op_3, OP_WITHIN as u8], false),
         ];
+        for (comparison, is_true) in comparisons {
+            // The result selects the branch of OP_IF and OP_NOTIF, which push 2 if the result is
+            // true and 3 otherwise
+            for (opcode, expected) in [(op_if, if is_true { 2 } else { 3 }), (op_notif, if is_true { 3 } else { 2 })] {
+                let mut script_pubkey = comparison.clone();
+                script_pubkey.extend_from_slice(&[opcode, op_2, op_else, op_3, op_endif]);
+                let stack = final_stack(&script_pubkey, randomness, [BnScalar::zero(); MAX_STACK_DEPTH], true);
+                assert_eq!(stack[0], StackItem::Value(BnScalar::from(expected)));
+                let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+                assert_proves(k, &circuit, vec![public_input]);
+            }
 
-        let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
-        prover.assert_satisfied();
+            // OP_VERIFY continues after a true result and fails the script after a false one
+            let mut script_pubkey = comparison.clone();
+            script_pubkey.extend_from_slice(&[OP_VERIFY as u8, op_1]);
+            let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+            if is_true {
+                assert_proves(k, &circuit, vec![public_input]);
+            }
+            else {
+                assert_rejects(k, &circuit, vec![public_input]);
+            }
+        }
+
+        // The result is the canonical boolean 1, not any true value
+        let script_pubkey = vec![op_2, op_3, OP_LESSTHAN as u8, OP_1 as u8 + 1, OP_EQUAL as u8, OP_NOT as u8];
+        let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // A prover pushing the true value 2 as the result of OP_LESSTHAN, with the result decoded
+        // as 2, is rejected whichever branch the honest result takes
+        for script_pubkey in [
+            vec![op_2, op_3, OP_LESSTHAN as u8, op_if, op_1, op_else, op_0, op_endif],
+            vec![op_3, op_2, OP_LESSTHAN as u8, op_notif, op_1, op_else, op_0, op_endif],
+        ] {
+            let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+            let (config, regions) = record_advice(&circuit).unwrap();
+            let region = regions
+                .iter()
+                .find(|region| region.name == "ScriptPubkey unrolling")
+                .expect("Unrolling region is assigned");
+            let row = region.start + (0..script_pubkey.len() + 2)
+                .find(|offset| region.value(config.is_opcode_lessthan, *offset) == Some(BnScalar::one()))
+                .expect("OP_LESSTHAN is executed");
+
+            let forged = BnScalar::from(2);
+            let forged_inv = HaloField::invert(&forged).unwrap();
+            let forged_cells = [
+                (config.stack[0], row, forged),
+                (config.result_num.bytes[0], row, forged),
+                (config.result_num.is_byte_used[0], row, BnScalar::one()),
+                (config.result_num.rlc_acc[0], row, forged),
+                (config.result_num.first_byte_inv, row, forged_inv),
+                (config.result_num.top_magnitude_inv, row, forged_inv),
+                (config.result_num.value, row, forged),
+            ];
+            let failures = run_with_forged_advice(k, &circuit, vec![public_input], &forged_cells)
+                .expect_err("forged comparison result is rejected");
+            assert!(failures.iter().any(|failure| format!("{:?}", failure).contains("OP_LESSTHAN")));
+        }
     }
 
     #[test]
-    fn test_script_pubkey_push1_to_push75() {
+    fn test_script_pubkey_boolean_opcodes() {
         let k = 10;
         let mut rng = rand::thread_rng();
-        let mut script_pubkey: Vec<u8> = vec![];
-        let mut data_push_len: u8 = rng.gen();
-        data_push_len = (data_push_len % (OP_PUSH_NEXT75 as u8)) + 1;
-
-        script_pubkey.push(data_push_len);
-        for _i in 0..data_push_len {
-            script_pubkey.push(rng.gen());
-        }
-        
         let r: u64 = rng.gen();
         let randomness: BnScalar = BnScalar::from(r);
-        
-        let circuit = TestExecutionCircuit {
-            script_pubkey: script_pubkey.clone(),
-            randomness,
-            initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+
+        let op_2 = OP_1 as u8 + 1;
+        let zero = vec![OP_0 as u8];
+        let negative_zero = vec![1, NEGATIVE_ZERO as u8];
+        let one = vec![OP_1 as u8];
+        let two = vec![op_2];
+        let minus_one = vec![1, 0x81];
+        let inputs = [(&zero, false), (&negative_zero, false), (&one, true), (&two, true), (&minus_one, true)];
+
+        let assert_result = |mut script_pubkey: Vec<u8>, expected: bool| {
+            let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+            let expected_item = if expected { StackItem::Value(BnScalar::one()) } else { StackItem::Empty };
+            assert_eq!(stack[0], expected_item);
+            // A false result would fail the script
+            if !expected {
+                script_pubkey.push(OP_NOT as u8);
+            }
+            let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+            assert_proves(k, &circuit, vec![public_input]);
         };
-        script_pubkey.reverse();
-        let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
-            acc * randomness + BnScalar::from(v as u64)
-        });
-
-        let public_input = vec![
-            BnScalar::from(script_pubkey.len() as u64),
-            script_rlc_init,
-            randomness,
-        ];
 
-        let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
-        prover.assert_satisfied();
+        for (input, is_true) in inputs.iter() {
+            for (opcode, expected) in [(OP_NOT, !is_true), (OP_0NOTEQUAL, *is_true)] {
+                let mut script_pubkey = input.to_vec();
+                script_pubkey.push(opcode as u8);
+                assert_result(script_pubkey, expected);
+            }
+            for (second_input, is_second_true) in inputs.iter() {
+                for (opcode, expected) in [
+                    (OP_BOOLAND, *is_true && *is_second_true),
+                    (OP_BOOLOR, *is_true || *is_second_true),
+                ] {
+                    let mut script_pubkey = second_input.to_vec();
+                    script_pubkey.extend_from_slice(input);
+                    script_pubkey.push(opcode as u8);
+                    assert_result(script_pubkey, expected);
+                }
+            }
+        }
     }
 
     #[test]
-    fn test_script_pubkey_pushdata1() {
+    fn test_script_pubkey_conditionals() {
         let k = 10;
         let mut rng = rand::thread_rng();
-        let mut script_pubkey: Vec<u8> = vec![];
-        let mut data_push_len: u8 = rng.gen();
-        data_push_len = (data_push_len % 254) + 1;
-
-        script_pubkey.push(OP_PUSHDATA1 as u8);
-        script_pubkey.push(data_push_len);
-        for _i in 0..data_push_len {
-            script_pubkey.push(rng.gen());
-        }
-        
         let r: u64 = rng.gen();
         let randomness: BnScalar = BnScalar::from(r);
-        
-        let circuit = TestExecutionCircuit {
-            script_pubkey: script_pubkey.clone(),
-            randomness,
-            initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
-        };
-        script_pubkey.reverse();
-        let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
-            acc * randomness + BnScalar::from(v as u64)
-        });
-
-        let public_input = vec![
-            BnScalar::from(script_pubkey.len() as u64),
-            script_rlc_init,
-            randomness,
-        ];
-
-        let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
-        prover.assert_satisfied();
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+
+        let (op_0, op_1, op_2, op_3) = (OP_0 as u8, OP_1 as u8, OP_1 as u8 + 1, OP_1 as u8 + 2);
+        let (op_if, op_notif, op_else, op_endif) = (OP_IF as u8, OP_NOTIF as u8, OP_ELSE as u8, OP_ENDIF as u8);
+
+        for (script_pubkey, expected) in [
+            // Taken and not taken branches
+            (vec![op_1, op_if, op_2, op_else, op_0, op_endif], 2u64),
+            (vec![op_0, op_if, op_0, op_else, op_2, op_endif], 2),
+            (vec![op_0, op_notif, op_3, op_endif], 3),
+            (vec![op_2, op_notif, op_0, op_endif, op_1], 1),
+            // Nested conditionals
+            (vec![op_1, op_1, op_if, op_if, op_2, op_else, op_0, op_endif, op_else, op_0, op_endif], 2),
+            // The nested OP_IF in a branch which is not taken does not pop a condition and the
+            // nested OP_ELSE does not activate its branch
+            (vec![op_0, op_if, op_1, op_if, op_0, op_endif, op_0, op_else, op_3, op_endif], 3),
+            (vec![op_0, op_if, op_0, op_if, op_else, op_0, op_endif, op_else, op_1, op_endif], 1),
+            // Pushed data in a branch which is not taken is skipped, even if it contains opcodes
+            (vec![op_0, op_if, 2, op_endif, op_else, op_endif, op_1], 1),
+            // Conditionals nested MAX_CONDITION_DEPTH deep
+            (vec![op_1, op_1, op_1, op_1, op_if, op_if, op_if, op_if, op_3, op_endif, op_endif, op_endif, op_endif], 3),
+        ] {
+            let stack = final_stack(&script_pubkey, randomness, zero_stack, true);
+            assert_eq!(stack[0], StackItem::Value(BnScalar::from(expected)));
+            assert_eq!(stack[1], StackItem::Value(BnScalar::zero()));
+
+            let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+            assert_proves(k, &circuit, vec![public_input]);
+        }
     }
 
     #[test]
-    fn test_script_pubkey_pushdata2() {
+    fn test_script_pubkey_unbalanced_conditionals() {
         let k = 10;
         let mut rng = rand::thread_rng();
-        let mut script_pubkey: Vec<u8> = vec![];
-        let data_push_len_byte0: u8 = rng.gen();
-        let data_push_len_byte1: u8 = 1;
-
-        script_pubkey.push(OP_PUSHDATA2 as u8);
-        script_pubkey.push(data_push_len_byte0);
-        script_pubkey.push(data_push_len_byte1);
-        let data_push_len: usize =
-            data_push_len_byte0 as usize +
-            256 * (data_push_len_byte1 as usize);
+        let r: u64 = rng.gen();
+        let randomness: BnScalar = BnScalar::from(r);
 
-        for _i in 0..data_push_len {
-            script_pubkey.push(rng.gen());
+        let op_1 = OP_1 as u8;
+        let (op_if, op_else, op_endif) = (OP_IF as u8, OP_ELSE as u8, OP_ENDIF as u8);
+
+        for script_pubkey in [
+            // OP_ENDIF and OP_ELSE without an OP_IF
+            vec![op_1, op_endif],
+            vec![op_1, op_1, op_if, op_endif, op_endif],
+            vec![op_1, op_else, op_1],
+            // OP_IF without an OP_ENDIF
+            vec![op_1, op_1, op_if],
+            // Conditionals nested deeper than MAX_CONDITION_DEPTH
+            vec![op_1, op_1, op_1, op_1, op_1, op_1, op_if, op_if, op_if, op_if, op_if, op_endif, op_endif, op_endif, op_endif, op_endif],
+        ] {
+            let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+            let failures = assert_rejects(k, &circuit, vec![public_input]);
+            assert!(failures.iter().any(|failure| format!("{:?}", failure).contains("Condition stack")));
         }
-        
+    }
+
+    #[test]
+    fn test_script_pubkey_stack_underflow() {
+        let k = 10;
+        let mut rng = rand::thread_rng();
         let r: u64 = rng.gen();
         let randomness: BnScalar = BnScalar::from(r);
-        
+
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key_bytes: [u8; PUBLIC_KEY_SIZE] = PublicKey::from_secret_key(&secp, &secret_key).serialize();
+
+        // <pk> OP_CHECKSIG OP_NOT
+        let mut checksig_not = vec![PUBLIC_KEY_SIZE as u8];
+        checksig_not.extend_from_slice(&public_key_bytes);
+        checksig_not.extend([OP_CHECKSIG as u8, OP_NOT as u8]);
+
+        // An invalid signature below the public key makes the script succeed
+        let mut initial_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        initial_stack[1] = BnScalar::from(EMPTY_ARRAY_REPRESENTATION);
         let circuit = TestExecutionCircuit {
-            script_pubkey: script_pubkey.clone(),
+            script_pubkey: checksig_not.clone(),
             randomness,
-            initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+            initial_stack,
         };
-        script_pubkey.reverse();
-        let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
-            acc * randomness + BnScalar::from(v as u64)
-        });
-
-        let public_input = vec![
-            BnScalar::from(script_pubkey.len() as u64),
-            script_rlc_init,
-            randomness,
-        ];
-
-        let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
-        prover.assert_satisfied();
+        let public_input = PublicInputs::new(checksig_not.clone(), randomness).execution_instance();
+        assert_proves(k, &circuit, vec![public_input]);
+
+        // Opcodes which pop more items than are on the stack fail, even where the zero-filled
+        // stack slots would make the script succeed
+        let (op_1, op_2) = (OP_1 as u8, OP_1 as u8 + 1);
+        for script_pubkey in [
+            // OP_CHECKSIG on a one-element stack
+            checksig_not,
+            vec![op_1, OP_ADD as u8],
+            vec![OP_DROP as u8, op_1],
+            vec![op_1, op_2, OP_ROT as u8, OP_DROP as u8],
+            vec![op_1, OP_IF as u8, OP_NOT as u8, OP_ENDIF as u8, op_1],
+        ] {
+            let (circuit, public_input) = circuit_with_public_input(&script_pubkey, randomness);
+            let failures = assert_rejects(k, &circuit, vec![public_input]);
+            assert!(failures.iter().any(|failure| format!("{:?}", failure).contains("Stack underflow")));
+        }
     }
 
     #[test]
-    fn test_script_pubkey_pushdata4() {
+    fn test_script_pubkey_oversized() {
         let k = 10;
-        let mut rng = rand::thread_rng();
-        let mut script_pubkey: Vec<u8> = vec![];
-        let data_push_len_byte0: u8 = rng.gen();
-        let data_push_len_byte1: u8 = 1;
-        let data_push_len_byte2: u8 = 0;
-        let data_push_len_byte3: u8 = 0;
-
-        script_pubkey.push(OP_PUSHDATA4 as u8);
-        script_pubkey.push(data_push_len_byte0);
-        script_pubkey.push(data_push_len_byte1);
-        script_pubkey.push(data_push_len_byte2);
-        script_pubkey.push(data_push_len_byte3);
-        let data_push_len: usize =
-            data_push_len_byte0 as usize +
-            256 * (data_push_len_byte1 as usize) +
-            256 * 256 * (data_push_len_byte2 as usize) +
-            256 * 256 * 256 * (data_push_len_byte3 as usize);
+        let script_pubkey = vec![OP_1 as u8; MAX_SCRIPT_PUBKEY_SIZE + 1];
 
-        for _i in 0..data_push_len {
-            script_pubkey.push(rng.gen());
-        }
-        
+        let mut rng = rand::thread_rng();
         let r: u64 = rng.gen();
         let randomness: BnScalar = BnScalar::from(r);
-        
+
         let circuit = TestExecutionCircuit {
             script_pubkey: script_pubkey.clone(),
             randomness,
             initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
         };
-        script_pubkey.reverse();
-        let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
-            acc * randomness + BnScalar::from(v as u64)
-        });
 
         let public_input = vec![
             BnScalar::from(script_pubkey.len() as u64),
-            script_rlc_init,
+            BnScalar::zero(),
             randomness,
         ];
 
-        let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
-        prover.assert_satisfied();
+        // Scripts longer than MAX_SCRIPT_PUBKEY_SIZE do not fit in the circuit
+        assert_eq!(
+            check_script_pubkey_size(&script_pubkey),
+            Err(BitcoinVmError::OversizedScript { length: MAX_SCRIPT_PUBKEY_SIZE + 1 }),
+        );
+        assert!(matches!(MockProver::run(k, &circuit, vec![public_input]), Err(Error::Synthesis)));
     }
 
-    use secp256k1::{self, Secp256k1, SecretKey, PublicKey};
-
-    #[test]
-    fn test_script_pubkey_checksig() {
-        let k = 10;
-
-        let secp = Secp256k1::new();
-        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
-        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
-        let public_key_bytes: [u8; PUBLIC_KEY_SIZE] = public_key.serialize();
-        
-        let mut script_pubkey: Vec<u8> = vec![];
-        script_pubkey.push(PUBLIC_KEY_SIZE as u8); // "Push 33 bytes" opcode
-        for i in 0..PUBLIC_KEY_SIZE {
-            script_pubkey.push(public_key_bytes[i]);
-        }
-        script_pubkey.push(OP_CHECKSIG as u8);
-
-        let mut rng = rand::thread_rng();
-        let r: u64 = rng.gen();
-        let randomness: BnScalar = BnScalar::from(r);
-        let mut initial_stack_vec = vec![BnScalar::one()]; // This value will force a signature verification later
-        initial_stack_vec.extend_from_slice(&[BnScalar::zero(); MAX_STACK_DEPTH-1]);
-        let initial_stack: [BnScalar; MAX_STACK_DEPTH] = initial_stack_vec.as_slice().try_into().unwrap();
+    // Number of stack elements included in an execution trace
+    const TRACE_STACK_DEPTH: usize = 2;
 
+    // Returns the key columns of the unrolling region with one line per row, from the first
+    // row up to the first padding row. Unassigned cells are shown as "-".
+    fn execution_trace(
+        script_pubkey: Vec<u8>,
+        randomness: BnScalar,
+        initial_stack: [BnScalar; MAX_STACK_DEPTH],
+    ) -> String {
+        let num_rows = script_pubkey.len() + 2;
         let circuit = TestExecutionCircuit {
-            script_pubkey: script_pubkey.clone(),
+            script_pubkey,
             randomness,
             initial_stack,
         };
+        let (config, regions) = record_advice(&circuit).unwrap();
+        let region = regions
+            .iter()
+            .find(|region| region.name == "ScriptPubkey unrolling")
+            .expect("Unrolling region is assigned");
+
+        let mut columns = vec![("opcode".to_string(), config.opcode)];
+        for i in 0..TRACE_STACK_DEPTH {
+            columns.push((format!("stack[{}]", i), config.stack[i]));
+        }
+        columns.extend([
+            ("num_script_bytes_remaining".to_string(), config.num_script_bytes_remaining),
+            ("num_data_bytes_remaining".to_string(), config.num_data_bytes_remaining),
+            ("num_data_length_bytes_remaining".to_string(), config.num_data_length_bytes_remaining),
+            ("pk_rlc_acc".to_string(), config.pk_rlc_acc),
+            ("num_checksig_opcodes".to_string(), config.num_checksig_opcodes),
+            ("num_verified_checksigs".to_string(), config.num_verified_checksigs),
+        ]);
+
+        let mut header = vec!["row".to_string()];
+        header.extend(columns.iter().map(|(name, _)| name.clone()));
+        let mut trace = header.join(" ") + "\n";
+        for offset in 0..num_rows {
+            let mut line = vec![offset.to_string()];
+            line.extend(columns.iter().map(|(_, column)| {
+                region.value(*column, offset).map_or("-".to_string(), format_field)
+            }));
+            trace += &(line.join(" ") + "\n");
+        }
+        trace
+    }
 
-        script_pubkey.reverse();
-        let script_rlc_init = script_pubkey.clone().into_iter().fold(BnScalar::zero(), |acc, v| {
-            acc * randomness + BnScalar::from(v as u64)
-        });
+    #[test]
+    fn test_script_pubkey_p2pk_golden_trace() {
+        // Compressed serialization of the secp256k1 generator
+        let public_key_bytes =
+            hex::decode("0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798").unwrap();
+        let mut script_pubkey = vec![PUBLIC_KEY_SIZE as u8];
+        script_pubkey.extend_from_slice(&public_key_bytes);
+        script_pubkey.push(OP_CHECKSIG as u8);
 
-        let public_input = vec![
-            BnScalar::from(script_pubkey.len() as u64),
-            script_rlc_init,
-            randomness,
-        ];
+        let randomness = BnScalar::from(0x0123456789abcdef);
+        let mut initial_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        initial_stack[0] = BnScalar::one(); // Valid signature
 
-        let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
-        prover.assert_satisfied();
+        // On an intended change of the assignment, regenerate the golden file
+        // from the printed trace
+        let trace = execution_trace(script_pubkey, randomness, initial_stack);
+        assert_eq!(trace, include_str!("golden/p2pk.trace"), "Execution trace:\n{}", trace);
     }
-}
\ No newline at end of file
+}
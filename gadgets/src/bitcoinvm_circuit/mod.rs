@@ -1,5 +1,10 @@
 pub mod constants;
+pub mod error;
 pub mod execution;
 pub mod opcode_table;
 pub mod util;
-pub mod crypto_opcodes;
\ No newline at end of file
+pub mod crypto_opcodes;
+pub mod public_inputs;
+pub mod witness;
+#[cfg(test)]
+pub(crate) mod scenario;
\ No newline at end of file
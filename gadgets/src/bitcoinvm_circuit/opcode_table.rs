@@ -7,6 +7,11 @@ use halo2_proofs::{
 use std::marker::PhantomData;
 
 use super::constants::*;
+use super::util::script_parser::{disabled_indicator, nop_indicator, opcode_enabled};
+
+const NUM_OPCODES: usize = 256;
+/// Number of rows of the opcode table: one per opcode and the all-zeros row
+pub(crate) const OPCODE_TABLE_ROWS: usize = NUM_OPCODES + 1;
 
 #[derive(Clone, Debug)]
 pub(super) struct OpcodeInputs {
@@ -20,6 +25,64 @@ pub(super) struct OpcodeInputs {
     pub(super) is_opcode_pushdata2: Column<Advice>,
     pub(super) is_opcode_pushdata4: Column<Advice>,
     pub(super) is_opcode_checksig: Column<Advice>,
+    pub(super) is_opcode_equal: Column<Advice>,
+    pub(super) is_opcode_equalverify: Column<Advice>,
+    pub(super) is_opcode_verify: Column<Advice>,
+    pub(super) is_opcode_drop: Column<Advice>,
+    pub(super) is_opcode_2drop: Column<Advice>,
+    pub(super) is_opcode_swap: Column<Advice>,
+    pub(super) is_opcode_over: Column<Advice>,
+    pub(super) is_opcode_rot: Column<Advice>,
+    pub(super) is_opcode_nip: Column<Advice>,
+    pub(super) is_opcode_tuck: Column<Advice>,
+    pub(super) is_opcode_2dup: Column<Advice>,
+    pub(super) is_opcode_3dup: Column<Advice>,
+    pub(super) is_opcode_2over: Column<Advice>,
+    pub(super) is_opcode_2rot: Column<Advice>,
+    pub(super) is_opcode_2swap: Column<Advice>,
+    pub(super) is_opcode_pick: Column<Advice>,
+    pub(super) is_opcode_roll: Column<Advice>,
+    pub(super) is_opcode_depth: Column<Advice>,
+    pub(super) is_opcode_add: Column<Advice>,
+    pub(super) is_opcode_sub: Column<Advice>,
+    pub(super) is_opcode_1add: Column<Advice>,
+    pub(super) is_opcode_1sub: Column<Advice>,
+    pub(super) is_opcode_negate: Column<Advice>,
+    pub(super) is_opcode_abs: Column<Advice>,
+    pub(super) is_opcode_lessthan: Column<Advice>,
+    pub(super) is_opcode_greaterthan: Column<Advice>,
+    pub(super) is_opcode_lessthanorequal: Column<Advice>,
+    pub(super) is_opcode_greaterthanorequal: Column<Advice>,
+    pub(super) is_opcode_min: Column<Advice>,
+    pub(super) is_opcode_max: Column<Advice>,
+    pub(super) is_opcode_within: Column<Advice>,
+    pub(super) is_opcode_not: Column<Advice>,
+    pub(super) is_opcode_0notequal: Column<Advice>,
+    pub(super) is_opcode_booland: Column<Advice>,
+    pub(super) is_opcode_boolor: Column<Advice>,
+    pub(super) is_opcode_if: Column<Advice>,
+    pub(super) is_opcode_notif: Column<Advice>,
+    pub(super) is_opcode_else: Column<Advice>,
+    pub(super) is_opcode_endif: Column<Advice>,
+    pub(super) is_opcode_checkmultisig: Column<Advice>,
+    pub(super) is_opcode_checksigverify: Column<Advice>,
+    pub(super) is_opcode_numequal: Column<Advice>,
+    pub(super) is_opcode_checksigadd: Column<Advice>,
+    pub(super) is_opcode_push1: Column<Advice>,
+    pub(super) is_opcode_push1_to_push16: Column<Advice>,
+    pub(super) is_opcode_toaltstack: Column<Advice>,
+    pub(super) is_opcode_fromaltstack: Column<Advice>,
+    pub(super) is_opcode_size: Column<Advice>,
+    pub(super) is_opcode_hash256: Column<Advice>,
+    pub(super) is_opcode_sha256: Column<Advice>,
+    pub(super) is_opcode_ripemd160: Column<Advice>,
+    pub(super) is_opcode_sha1: Column<Advice>,
+    pub(super) is_opcode_hash160: Column<Advice>,
+    pub(super) is_opcode_cltv: Column<Advice>,
+    pub(super) is_opcode_csv: Column<Advice>,
+    pub(super) is_opcode_codeseparator: Column<Advice>,
+    pub(super) is_opcode_nop: Column<Advice>,
+    pub(super) is_opcode_disabled: Column<Advice>,
 }
 
 #[derive(Clone, Debug)]
@@ -34,6 +97,64 @@ pub(super) struct OpcodeTable {
     pub(super) is_opcode_pushdata2: TableColumn,
     pub(super) is_opcode_pushdata4: TableColumn,
     pub(super) is_opcode_checksig: TableColumn,
+    pub(super) is_opcode_equal: TableColumn,
+    pub(super) is_opcode_equalverify: TableColumn,
+    pub(super) is_opcode_verify: TableColumn,
+    pub(super) is_opcode_drop: TableColumn,
+    pub(super) is_opcode_2drop: TableColumn,
+    pub(super) is_opcode_swap: TableColumn,
+    pub(super) is_opcode_over: TableColumn,
+    pub(super) is_opcode_rot: TableColumn,
+    pub(super) is_opcode_nip: TableColumn,
+    pub(super) is_opcode_tuck: TableColumn,
+    pub(super) is_opcode_2dup: TableColumn,
+    pub(super) is_opcode_3dup: TableColumn,
+    pub(super) is_opcode_2over: TableColumn,
+    pub(super) is_opcode_2rot: TableColumn,
+    pub(super) is_opcode_2swap: TableColumn,
+    pub(super) is_opcode_pick: TableColumn,
+    pub(super) is_opcode_roll: TableColumn,
+    pub(super) is_opcode_depth: TableColumn,
+    pub(super) is_opcode_add: TableColumn,
+    pub(super) is_opcode_sub: TableColumn,
+    pub(super) is_opcode_1add: TableColumn,
+    pub(super) is_opcode_1sub: TableColumn,
+    pub(super) is_opcode_negate: TableColumn,
+    pub(super) is_opcode_abs: TableColumn,
+    pub(super) is_opcode_lessthan: TableColumn,
+    pub(super) is_opcode_greaterthan: TableColumn,
+    pub(super) is_opcode_lessthanorequal: TableColumn,
+    pub(super) is_opcode_greaterthanorequal: TableColumn,
+    pub(super) is_opcode_min: TableColumn,
+    pub(super) is_opcode_max: TableColumn,
+    pub(super) is_opcode_within: TableColumn,
+    pub(super) is_opcode_not: TableColumn,
+    pub(super) is_opcode_0notequal: TableColumn,
+    pub(super) is_opcode_booland: TableColumn,
+    pub(super) is_opcode_boolor: TableColumn,
+    pub(super) is_opcode_if: TableColumn,
+    pub(super) is_opcode_notif: TableColumn,
+    pub(super) is_opcode_else: TableColumn,
+    pub(super) is_opcode_endif: TableColumn,
+    pub(super) is_opcode_checkmultisig: TableColumn,
+    pub(super) is_opcode_checksigverify: TableColumn,
+    pub(super) is_opcode_numequal: TableColumn,
+    pub(super) is_opcode_checksigadd: TableColumn,
+    pub(super) is_opcode_push1: TableColumn,
+    pub(super) is_opcode_push1_to_push16: TableColumn,
+    pub(super) is_opcode_toaltstack: TableColumn,
+    pub(super) is_opcode_fromaltstack: TableColumn,
+    pub(super) is_opcode_size: TableColumn,
+    pub(super) is_opcode_hash256: TableColumn,
+    pub(super) is_opcode_sha256: TableColumn,
+    pub(super) is_opcode_ripemd160: TableColumn,
+    pub(super) is_opcode_sha1: TableColumn,
+    pub(super) is_opcode_hash160: TableColumn,
+    pub(super) is_opcode_cltv: TableColumn,
+    pub(super) is_opcode_csv: TableColumn,
+    pub(super) is_opcode_codeseparator: TableColumn,
+    pub(super) is_opcode_nop: TableColumn,
+    pub(super) is_opcode_disabled: TableColumn,
 }
 
 #[derive(Clone, Debug)]
@@ -64,79 +185,155 @@ impl<F: FieldExt> Chip<F> for OpcodeTableChip<F> {
 impl<F: FieldExt> OpcodeTableChip<F> {
     pub(super) fn configure(
         meta: &mut ConstraintSystem<F>,
-        q_execution: Selector,
-        opcode: Column<Advice>,
-        is_opcode_enabled: Column<Advice>,
-        is_opcode_op0: Column<Advice>,
-        is_opcode_op1_to_op16: Column<Advice>,
-        is_opcode_push1_to_push75: Column<Advice>,
-        is_opcode_pushdata1: Column<Advice>,
-        is_opcode_pushdata2: Column<Advice>,
-        is_opcode_pushdata4: Column<Advice>,
-        is_opcode_checksig: Column<Advice>,
+        input: OpcodeInputs,
     ) -> <Self as Chip<F>>::Config {
-        let table_q_execution = meta.lookup_table_column();
-        let table_opcode = meta.lookup_table_column();
-        let table_is_opcode_enabled = meta.lookup_table_column();
-        let table_is_opcode_op0 = meta.lookup_table_column();
-        let table_is_opcode_op1_to_op16 = meta.lookup_table_column();
-        let table_is_opcode_push1_to_push75 = meta.lookup_table_column();
-        let table_is_opcode_pushdata1 = meta.lookup_table_column();
-        let table_is_opcode_pushdata2 = meta.lookup_table_column();
-        let table_is_opcode_pushdata4 = meta.lookup_table_column();
-        let table_is_opcode_checksig = meta.lookup_table_column();
+        let table = OpcodeTable {
+            q_execution: meta.lookup_table_column(),
+            opcode: meta.lookup_table_column(),
+            is_opcode_enabled: meta.lookup_table_column(),
+            is_opcode_op0: meta.lookup_table_column(),
+            is_opcode_op1_to_op16: meta.lookup_table_column(),
+            is_opcode_push1_to_push75: meta.lookup_table_column(),
+            is_opcode_pushdata1: meta.lookup_table_column(),
+            is_opcode_pushdata2: meta.lookup_table_column(),
+            is_opcode_pushdata4: meta.lookup_table_column(),
+            is_opcode_checksig: meta.lookup_table_column(),
+            is_opcode_equal: meta.lookup_table_column(),
+            is_opcode_equalverify: meta.lookup_table_column(),
+            is_opcode_verify: meta.lookup_table_column(),
+            is_opcode_drop: meta.lookup_table_column(),
+            is_opcode_2drop: meta.lookup_table_column(),
+            is_opcode_swap: meta.lookup_table_column(),
+            is_opcode_over: meta.lookup_table_column(),
+            is_opcode_rot: meta.lookup_table_column(),
+            is_opcode_nip: meta.lookup_table_column(),
+            is_opcode_tuck: meta.lookup_table_column(),
+            is_opcode_2dup: meta.lookup_table_column(),
+            is_opcode_3dup: meta.lookup_table_column(),
+            is_opcode_2over: meta.lookup_table_column(),
+            is_opcode_2rot: meta.lookup_table_column(),
+            is_opcode_2swap: meta.lookup_table_column(),
+            is_opcode_pick: meta.lookup_table_column(),
+            is_opcode_roll: meta.lookup_table_column(),
+            is_opcode_depth: meta.lookup_table_column(),
+            is_opcode_add: meta.lookup_table_column(),
+            is_opcode_sub: meta.lookup_table_column(),
+            is_opcode_1add: meta.lookup_table_column(),
+            is_opcode_1sub: meta.lookup_table_column(),
+            is_opcode_negate: meta.lookup_table_column(),
+            is_opcode_abs: meta.lookup_table_column(),
+            is_opcode_lessthan: meta.lookup_table_column(),
+            is_opcode_greaterthan: meta.lookup_table_column(),
+            is_opcode_lessthanorequal: meta.lookup_table_column(),
+            is_opcode_greaterthanorequal: meta.lookup_table_column(),
+            is_opcode_min: meta.lookup_table_column(),
+            is_opcode_max: meta.lookup_table_column(),
+            is_opcode_within: meta.lookup_table_column(),
+            is_opcode_not: meta.lookup_table_column(),
+            is_opcode_0notequal: meta.lookup_table_column(),
+            is_opcode_booland: meta.lookup_table_column(),
+            is_opcode_boolor: meta.lookup_table_column(),
+            is_opcode_if: meta.lookup_table_column(),
+            is_opcode_notif: meta.lookup_table_column(),
+            is_opcode_else: meta.lookup_table_column(),
+            is_opcode_endif: meta.lookup_table_column(),
+            is_opcode_checkmultisig: meta.lookup_table_column(),
+            is_opcode_checksigverify: meta.lookup_table_column(),
+            is_opcode_numequal: meta.lookup_table_column(),
+            is_opcode_checksigadd: meta.lookup_table_column(),
+            is_opcode_push1: meta.lookup_table_column(),
+            is_opcode_push1_to_push16: meta.lookup_table_column(),
+            is_opcode_toaltstack: meta.lookup_table_column(),
+            is_opcode_fromaltstack: meta.lookup_table_column(),
+            is_opcode_size: meta.lookup_table_column(),
+            is_opcode_hash256: meta.lookup_table_column(),
+            is_opcode_sha256: meta.lookup_table_column(),
+            is_opcode_ripemd160: meta.lookup_table_column(),
+            is_opcode_sha1: meta.lookup_table_column(),
+            is_opcode_hash160: meta.lookup_table_column(),
+            is_opcode_cltv: meta.lookup_table_column(),
+            is_opcode_csv: meta.lookup_table_column(),
+            is_opcode_codeseparator: meta.lookup_table_column(),
+            is_opcode_nop: meta.lookup_table_column(),
+            is_opcode_disabled: meta.lookup_table_column(),
+        };
 
         meta.lookup("Opcode properties table", |meta| {
-            let q_execution_cur = meta.query_selector(q_execution);
-            let input_opcode_cur = meta.query_advice(opcode, Rotation::cur());
-            let is_opcode_enabled_cur = meta.query_advice(is_opcode_enabled, Rotation::cur());
-            let is_opcode_op0_cur = meta.query_advice(is_opcode_op0, Rotation::cur());
-            let is_opcode_op1_to_op16_cur = meta.query_advice(is_opcode_op1_to_op16, Rotation::cur());
-            let is_opcode_push1_to_push75_cur = meta.query_advice(is_opcode_push1_to_push75, Rotation::cur());
-            let is_opcode_pushdata1_cur = meta.query_advice(is_opcode_pushdata1, Rotation::cur());
-            let is_opcode_pushdata2_cur = meta.query_advice(is_opcode_pushdata2, Rotation::cur());
-            let is_opcode_pushdata4_cur = meta.query_advice(is_opcode_pushdata4, Rotation::cur());
-            let is_opcode_checksig_cur = meta.query_advice(is_opcode_checksig, Rotation::cur());
+            let q_execution_cur = meta.query_selector(input.q_execution);
+            let mut query = |column: Column<Advice>| meta.query_advice(column, Rotation::cur());
             vec![
-                (q_execution_cur,                table_q_execution),
-                (input_opcode_cur,               table_opcode),
-                (is_opcode_enabled_cur,          table_is_opcode_enabled),
-                (is_opcode_op0_cur,              table_is_opcode_op0),
-                (is_opcode_op1_to_op16_cur,      table_is_opcode_op1_to_op16),
-                (is_opcode_push1_to_push75_cur,  table_is_opcode_push1_to_push75),
-                (is_opcode_pushdata1_cur,        table_is_opcode_pushdata1),
-                (is_opcode_pushdata2_cur,        table_is_opcode_pushdata2),
-                (is_opcode_pushdata4_cur,        table_is_opcode_pushdata4),
-                (is_opcode_checksig_cur,         table_is_opcode_checksig),
+                (q_execution_cur,                           table.q_execution),
+                (query(input.opcode),                       table.opcode),
+                (query(input.is_opcode_enabled),            table.is_opcode_enabled),
+                (query(input.is_opcode_op0),                table.is_opcode_op0),
+                (query(input.is_opcode_op1_to_op16),        table.is_opcode_op1_to_op16),
+                (query(input.is_opcode_push1_to_push75),    table.is_opcode_push1_to_push75),
+                (query(input.is_opcode_pushdata1),          table.is_opcode_pushdata1),
+                (query(input.is_opcode_pushdata2),          table.is_opcode_pushdata2),
+                (query(input.is_opcode_pushdata4),          table.is_opcode_pushdata4),
+                (query(input.is_opcode_checksig),           table.is_opcode_checksig),
+                (query(input.is_opcode_equal),              table.is_opcode_equal),
+                (query(input.is_opcode_equalverify),        table.is_opcode_equalverify),
+                (query(input.is_opcode_verify),             table.is_opcode_verify),
+                (query(input.is_opcode_drop),               table.is_opcode_drop),
+                (query(input.is_opcode_2drop),              table.is_opcode_2drop),
+                (query(input.is_opcode_swap),               table.is_opcode_swap),
+                (query(input.is_opcode_over),               table.is_opcode_over),
+                (query(input.is_opcode_rot),                table.is_opcode_rot),
+                (query(input.is_opcode_nip),                table.is_opcode_nip),
+                (query(input.is_opcode_tuck),               table.is_opcode_tuck),
+                (query(input.is_opcode_2dup),               table.is_opcode_2dup),
+                (query(input.is_opcode_3dup),               table.is_opcode_3dup),
+                (query(input.is_opcode_2over),              table.is_opcode_2over),
+                (query(input.is_opcode_2rot),               table.is_opcode_2rot),
+                (query(input.is_opcode_2swap),              table.is_opcode_2swap),
+                (query(input.is_opcode_pick),               table.is_opcode_pick),
+                (query(input.is_opcode_roll),               table.is_opcode_roll),
+                (query(input.is_opcode_depth),              table.is_opcode_depth),
+                (query(input.is_opcode_add),                table.is_opcode_add),
+                (query(input.is_opcode_sub),                table.is_opcode_sub),
+                (query(input.is_opcode_1add),               table.is_opcode_1add),
+                (query(input.is_opcode_1sub),               table.is_opcode_1sub),
+                (query(input.is_opcode_negate),             table.is_opcode_negate),
+                (query(input.is_opcode_abs),                table.is_opcode_abs),
+                (query(input.is_opcode_lessthan),           table.is_opcode_lessthan),
+                (query(input.is_opcode_greaterthan),        table.is_opcode_greaterthan),
+                (query(input.is_opcode_lessthanorequal),    table.is_opcode_lessthanorequal),
+                (query(input.is_opcode_greaterthanorequal), table.is_opcode_greaterthanorequal),
+                (query(input.is_opcode_min),                table.is_opcode_min),
+                (query(input.is_opcode_max),                table.is_opcode_max),
+                (query(input.is_opcode_within),             table.is_opcode_within),
+                (query(input.is_opcode_not),                table.is_opcode_not),
+                (query(input.is_opcode_0notequal),          table.is_opcode_0notequal),
+                (query(input.is_opcode_booland),            table.is_opcode_booland),
+                (query(input.is_opcode_boolor),             table.is_opcode_boolor),
+                (query(input.is_opcode_if),                 table.is_opcode_if),
+                (query(input.is_opcode_notif),              table.is_opcode_notif),
+                (query(input.is_opcode_else),               table.is_opcode_else),
+                (query(input.is_opcode_endif),              table.is_opcode_endif),
+                (query(input.is_opcode_checkmultisig),      table.is_opcode_checkmultisig),
+                (query(input.is_opcode_checksigverify),     table.is_opcode_checksigverify),
+                (query(input.is_opcode_numequal),           table.is_opcode_numequal),
+                (query(input.is_opcode_checksigadd),        table.is_opcode_checksigadd),
+                (query(input.is_opcode_push1),              table.is_opcode_push1),
+                (query(input.is_opcode_push1_to_push16),    table.is_opcode_push1_to_push16),
+                (query(input.is_opcode_toaltstack),         table.is_opcode_toaltstack),
+                (query(input.is_opcode_fromaltstack),       table.is_opcode_fromaltstack),
+                (query(input.is_opcode_size),               table.is_opcode_size),
+                (query(input.is_opcode_hash256),            table.is_opcode_hash256),
+                (query(input.is_opcode_sha256),             table.is_opcode_sha256),
+                (query(input.is_opcode_ripemd160),          table.is_opcode_ripemd160),
+                (query(input.is_opcode_sha1),               table.is_opcode_sha1),
+                (query(input.is_opcode_hash160),            table.is_opcode_hash160),
+                (query(input.is_opcode_cltv),               table.is_opcode_cltv),
+                (query(input.is_opcode_csv),                table.is_opcode_csv),
+                (query(input.is_opcode_codeseparator),      table.is_opcode_codeseparator),
+                (query(input.is_opcode_nop),                table.is_opcode_nop),
+                (query(input.is_opcode_disabled),           table.is_opcode_disabled),
             ]
         });
 
-        OpcodeTableConfig {
-            input: OpcodeInputs {
-                q_execution,
-                opcode,
-                is_opcode_enabled,
-                is_opcode_op0,
-                is_opcode_op1_to_op16,
-                is_opcode_push1_to_push75,
-                is_opcode_pushdata1,
-                is_opcode_pushdata2,
-                is_opcode_pushdata4,
-                is_opcode_checksig,
-            }, 
-            table: OpcodeTable {
-                q_execution: table_q_execution,
-                opcode: table_opcode,
-                is_opcode_enabled: table_is_opcode_enabled,
-                is_opcode_op0: table_is_opcode_op0,
-                is_opcode_op1_to_op16: table_is_opcode_op1_to_op16,
-                is_opcode_push1_to_push75: table_is_opcode_push1_to_push75,
-                is_opcode_pushdata1: table_is_opcode_pushdata1,
-                is_opcode_pushdata2: table_is_opcode_pushdata2,
-                is_opcode_pushdata4: table_is_opcode_pushdata4,
-                is_opcode_checksig: table_is_opcode_checksig,
-            }
-        }
+        OpcodeTableConfig { input, table }
     }
 
     pub(super) fn load(
@@ -148,7 +345,7 @@ impl<F: FieldExt> OpcodeTableChip<F> {
             |mut table| {
 
                 // Run through all possible values of an opcode
-                for opcode in 0..256 {
+                for opcode in 0..NUM_OPCODES {
 
                     table.assign_cell(
                         || "q_execution",
@@ -164,23 +361,12 @@ impl<F: FieldExt> OpcodeTableChip<F> {
                         || Value::known(F::from(opcode as u64)),
                     )?;
 
-                    if (opcode <= OP_NOP && opcode != OP_1NEGATE && opcode != OP_RESERVED)
-                    || (opcode == OP_CHECKSIG) {
-                        table.assign_cell(
-                            || "opcode enabled",
-                            config.table.is_opcode_enabled,
-                            opcode,
-                            || Value::known(F::one()),
-                        )?;
-                    }
-                    else {
-                        table.assign_cell(
-                            || "opcode disabled",
-                            config.table.is_opcode_enabled,
-                            opcode,
-                            || Value::known(F::zero()),
-                        )?;
-                    }
+                    table.assign_cell(
+                        || "opcode enabled",
+                        config.table.is_opcode_enabled,
+                        opcode,
+                        || Value::known(F::from(opcode_enabled(opcode as u8))),
+                    )?;
 
                     let mut assign_is_opcode = |opcode_val: usize, t: TableColumn| -> Result<(), Error> {
                         if opcode == opcode_val {
@@ -207,6 +393,61 @@ impl<F: FieldExt> OpcodeTableChip<F> {
                     assign_is_opcode(OP_PUSHDATA2, config.table.is_opcode_pushdata2)?;
                     assign_is_opcode(OP_PUSHDATA4, config.table.is_opcode_pushdata4)?;
                     assign_is_opcode(OP_CHECKSIG, config.table.is_opcode_checksig)?;
+                    assign_is_opcode(OP_EQUAL, config.table.is_opcode_equal)?;
+                    assign_is_opcode(OP_EQUALVERIFY, config.table.is_opcode_equalverify)?;
+                    assign_is_opcode(OP_VERIFY, config.table.is_opcode_verify)?;
+                    assign_is_opcode(OP_DROP, config.table.is_opcode_drop)?;
+                    assign_is_opcode(OP_2DROP, config.table.is_opcode_2drop)?;
+                    assign_is_opcode(OP_SWAP, config.table.is_opcode_swap)?;
+                    assign_is_opcode(OP_OVER, config.table.is_opcode_over)?;
+                    assign_is_opcode(OP_ROT, config.table.is_opcode_rot)?;
+                    assign_is_opcode(OP_NIP, config.table.is_opcode_nip)?;
+                    assign_is_opcode(OP_TUCK, config.table.is_opcode_tuck)?;
+                    assign_is_opcode(OP_2DUP, config.table.is_opcode_2dup)?;
+                    assign_is_opcode(OP_3DUP, config.table.is_opcode_3dup)?;
+                    assign_is_opcode(OP_2OVER, config.table.is_opcode_2over)?;
+                    assign_is_opcode(OP_2ROT, config.table.is_opcode_2rot)?;
+                    assign_is_opcode(OP_2SWAP, config.table.is_opcode_2swap)?;
+                    assign_is_opcode(OP_PICK, config.table.is_opcode_pick)?;
+                    assign_is_opcode(OP_ROLL, config.table.is_opcode_roll)?;
+                    assign_is_opcode(OP_DEPTH, config.table.is_opcode_depth)?;
+                    assign_is_opcode(OP_ADD, config.table.is_opcode_add)?;
+                    assign_is_opcode(OP_SUB, config.table.is_opcode_sub)?;
+                    assign_is_opcode(OP_1ADD, config.table.is_opcode_1add)?;
+                    assign_is_opcode(OP_1SUB, config.table.is_opcode_1sub)?;
+                    assign_is_opcode(OP_NEGATE, config.table.is_opcode_negate)?;
+                    assign_is_opcode(OP_ABS, config.table.is_opcode_abs)?;
+                    assign_is_opcode(OP_LESSTHAN, config.table.is_opcode_lessthan)?;
+                    assign_is_opcode(OP_GREATERTHAN, config.table.is_opcode_greaterthan)?;
+                    assign_is_opcode(OP_LESSTHANOREQUAL, config.table.is_opcode_lessthanorequal)?;
+                    assign_is_opcode(OP_GREATERTHANOREQUAL, config.table.is_opcode_greaterthanorequal)?;
+                    assign_is_opcode(OP_MIN, config.table.is_opcode_min)?;
+                    assign_is_opcode(OP_MAX, config.table.is_opcode_max)?;
+                    assign_is_opcode(OP_WITHIN, config.table.is_opcode_within)?;
+                    assign_is_opcode(OP_NOT, config.table.is_opcode_not)?;
+                    assign_is_opcode(OP_0NOTEQUAL, config.table.is_opcode_0notequal)?;
+                    assign_is_opcode(OP_BOOLAND, config.table.is_opcode_booland)?;
+                    assign_is_opcode(OP_BOOLOR, config.table.is_opcode_boolor)?;
+                    assign_is_opcode(OP_IF, config.table.is_opcode_if)?;
+                    assign_is_opcode(OP_NOTIF, config.table.is_opcode_notif)?;
+                    assign_is_opcode(OP_ELSE, config.table.is_opcode_else)?;
+                    assign_is_opcode(OP_ENDIF, config.table.is_opcode_endif)?;
+                    assign_is_opcode(OP_CHECKMULTISIG, config.table.is_opcode_checkmultisig)?;
+                    assign_is_opcode(OP_CHECKSIGVERIFY, config.table.is_opcode_checksigverify)?;
+                    assign_is_opcode(OP_NUMEQUAL, config.table.is_opcode_numequal)?;
+                    assign_is_opcode(OP_CHECKSIGADD, config.table.is_opcode_checksigadd)?;
+                    assign_is_opcode(OP_PUSH_NEXT1, config.table.is_opcode_push1)?;
+                    assign_is_opcode(OP_TOALTSTACK, config.table.is_opcode_toaltstack)?;
+                    assign_is_opcode(OP_FROMALTSTACK, config.table.is_opcode_fromaltstack)?;
+                    assign_is_opcode(OP_SIZE, config.table.is_opcode_size)?;
+                    assign_is_opcode(OP_HASH256, config.table.is_opcode_hash256)?;
+                    assign_is_opcode(OP_SHA256, config.table.is_opcode_sha256)?;
+                    assign_is_opcode(OP_RIPEMD160, config.table.is_opcode_ripemd160)?;
+                    assign_is_opcode(OP_SHA1, config.table.is_opcode_sha1)?;
+                    assign_is_opcode(OP_HASH160, config.table.is_opcode_hash160)?;
+                    assign_is_opcode(OP_CHECKLOCKTIMEVERIFY, config.table.is_opcode_cltv)?;
+                    assign_is_opcode(OP_CHECKSEQUENCEVERIFY, config.table.is_opcode_csv)?;
+                    assign_is_opcode(OP_CODESEPARATOR, config.table.is_opcode_codeseparator)?;
 
                     let mut assign_is_opcode_in_range
                         = |min_val: usize, max_val: usize, t: TableColumn| -> Result<(), Error> {
@@ -231,10 +472,25 @@ impl<F: FieldExt> OpcodeTableChip<F> {
 
                     assign_is_opcode_in_range(OP_1, OP_16, config.table.is_opcode_op1_to_op16)?;
                     assign_is_opcode_in_range(OP_PUSH_NEXT1, OP_PUSH_NEXT75, config.table.is_opcode_push1_to_push75)?;
+                    assign_is_opcode_in_range(OP_PUSH_NEXT1, OP_PUSH_NEXT16, config.table.is_opcode_push1_to_push16)?;
+
+                    table.assign_cell(
+                        || "nop",
+                        config.table.is_opcode_nop,
+                        opcode,
+                        || Value::known(F::from(nop_indicator(opcode as u8))),
+                    )?;
+
+                    table.assign_cell(
+                        || "disabled",
+                        config.table.is_opcode_disabled,
+                        opcode,
+                        || Value::known(F::from(disabled_indicator(opcode as u8))),
+                    )?;
 
                 }
 
-                let offset = 256usize;
+                let offset = NUM_OPCODES;
                 // Assign an all-zeros row for non-execution rows in the circuit
                 macro_rules! assign_zero {
                     ($annotation:expr, $table_col:ident) => {
@@ -257,6 +513,64 @@ impl<F: FieldExt> OpcodeTableChip<F> {
                 assign_zero!("pushdata2", is_opcode_pushdata2);
                 assign_zero!("pushdata4", is_opcode_pushdata4);
                 assign_zero!("checksig", is_opcode_checksig);
+                assign_zero!("equal", is_opcode_equal);
+                assign_zero!("equalverify", is_opcode_equalverify);
+                assign_zero!("verify", is_opcode_verify);
+                assign_zero!("drop", is_opcode_drop);
+                assign_zero!("2drop", is_opcode_2drop);
+                assign_zero!("swap", is_opcode_swap);
+                assign_zero!("over", is_opcode_over);
+                assign_zero!("rot", is_opcode_rot);
+                assign_zero!("nip", is_opcode_nip);
+                assign_zero!("tuck", is_opcode_tuck);
+                assign_zero!("2dup", is_opcode_2dup);
+                assign_zero!("3dup", is_opcode_3dup);
+                assign_zero!("2over", is_opcode_2over);
+                assign_zero!("2rot", is_opcode_2rot);
+                assign_zero!("2swap", is_opcode_2swap);
+                assign_zero!("pick", is_opcode_pick);
+                assign_zero!("roll", is_opcode_roll);
+                assign_zero!("depth", is_opcode_depth);
+                assign_zero!("add", is_opcode_add);
+                assign_zero!("sub", is_opcode_sub);
+                assign_zero!("1add", is_opcode_1add);
+                assign_zero!("1sub", is_opcode_1sub);
+                assign_zero!("negate", is_opcode_negate);
+                assign_zero!("abs", is_opcode_abs);
+                assign_zero!("lessthan", is_opcode_lessthan);
+                assign_zero!("greaterthan", is_opcode_greaterthan);
+                assign_zero!("lessthanorequal", is_opcode_lessthanorequal);
+                assign_zero!("greaterthanorequal", is_opcode_greaterthanorequal);
+                assign_zero!("min", is_opcode_min);
+                assign_zero!("max", is_opcode_max);
+                assign_zero!("within", is_opcode_within);
+                assign_zero!("not", is_opcode_not);
+                assign_zero!("0notequal", is_opcode_0notequal);
+                assign_zero!("booland", is_opcode_booland);
+                assign_zero!("boolor", is_opcode_boolor);
+                assign_zero!("if", is_opcode_if);
+                assign_zero!("notif", is_opcode_notif);
+                assign_zero!("else", is_opcode_else);
+                assign_zero!("endif", is_opcode_endif);
+                assign_zero!("checkmultisig", is_opcode_checkmultisig);
+                assign_zero!("checksigverify", is_opcode_checksigverify);
+                assign_zero!("numequal", is_opcode_numequal);
+                assign_zero!("checksigadd", is_opcode_checksigadd);
+                assign_zero!("push1", is_opcode_push1);
+                assign_zero!("push1_to_push16", is_opcode_push1_to_push16);
+                assign_zero!("toaltstack", is_opcode_toaltstack);
+                assign_zero!("fromaltstack", is_opcode_fromaltstack);
+                assign_zero!("size", is_opcode_size);
+                assign_zero!("hash256", is_opcode_hash256);
+                assign_zero!("sha256", is_opcode_sha256);
+                assign_zero!("ripemd160", is_opcode_ripemd160);
+                assign_zero!("sha1", is_opcode_sha1);
+                assign_zero!("hash160", is_opcode_hash160);
+                assign_zero!("checklocktimeverify", is_opcode_cltv);
+                assign_zero!("checksequenceverify", is_opcode_csv);
+                assign_zero!("codeseparator", is_opcode_codeseparator);
+                assign_zero!("nop", is_opcode_nop);
+                assign_zero!("disabled", is_opcode_disabled);
 
                 Ok(())
             },
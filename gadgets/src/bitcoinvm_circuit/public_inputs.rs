@@ -0,0 +1,223 @@
+//! Public inputs of a circuit combining the ExecutionChip and the OpCheckSigChip.
+//!
+//! The instance values are serialized in a fixed order, so callers need not mirror the
+//! rows passed to `constrain_instance` by hand. New public inputs are to be appended
+//! after the existing ones of their instance column.
+
+use halo2_proofs::halo2curves::secp256k1::{Fq, Secp256k1Affine};
+
+use super::crypto_opcodes::checksig::checksig::{message_hash_public_inputs, pk_commitment_public_inputs};
+use super::error::BitcoinVmError;
+use super::util::script_type::split_p2sh_script_sig;
+use crate::Field;
+
+// Rows of the execution instance column exposed for a scriptPubkey, relative to the
+// first row of the scriptPubkey
+pub const SCRIPT_LENGTH_ROW: usize = 0;
+pub const SCRIPT_RLC_ACC_ROW: usize = 1;
+pub const RANDOMNESS_ROW: usize = 2;
+// Number of execution instance rows exposed for each scriptPubkey
+pub const EXECUTION_INSTANCE_ROWS: usize = 3;
+
+/// Row of the execution instance column holding the nLockTime, which follows the rows of
+/// the `num_script_pubkeys` scriptPubkeys
+pub fn nlocktime_row(num_script_pubkeys: usize) -> usize {
+    num_script_pubkeys * EXECUTION_INSTANCE_ROWS
+}
+
+/// Row of the execution instance column holding the nSequence, which follows the nLockTime
+pub fn nsequence_row(num_script_pubkeys: usize) -> usize {
+    nlocktime_row(num_script_pubkeys) + 1
+}
+
+/// Row of the execution instance column holding the pk_rlc_acc exposed by
+/// `ExecutionChip::expose_checksig_accumulators`, which follows the nSequence
+pub fn pk_rlc_acc_row(num_script_pubkeys: usize) -> usize {
+    nsequence_row(num_script_pubkeys) + 1
+}
+
+/// Row of the execution instance column holding the num_checksig_opcodes exposed by
+/// `ExecutionChip::expose_checksig_accumulators`, which follows the pk_rlc_acc
+pub fn num_checksig_opcodes_row(num_script_pubkeys: usize) -> usize {
+    pk_rlc_acc_row(num_script_pubkeys) + 1
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct PublicInputs<F: Field> {
+    // scriptPubkeys unrolled by the ExecutionChip, in the order of their regions
+    pub script_pubkeys: Vec<Vec<u8>>,
+    // Randomness of the RLC operations
+    pub randomness: F,
+    // Public key commitments exposed by `OpCheckSigChip::assign_with_pk_commitments`
+    pub pk_commitments: Vec<Secp256k1Affine>,
+    // Message hashes of the signatures verified by the OpCheckSigChip
+    pub message_hashes: Vec<Fq>,
+    // nLockTime of the spending transaction, checked by OP_CHECKLOCKTIMEVERIFY
+    pub nlocktime: u32,
+    // nSequence of the spending input, checked by OP_CHECKSEQUENCEVERIFY
+    pub nsequence: u32,
+    // pk_rlc_acc and num_checksig_opcodes of the ExecutionChip, if they are exposed for the
+    // verifier to check against the public keys it expects
+    pub checksig_accumulators: Option<(F, u64)>,
+}
+
+impl<F: Field> PublicInputs<F> {
+    pub(crate) fn new(script_pubkey: Vec<u8>, randomness: F) -> Self {
+        Self {
+            script_pubkeys: vec![script_pubkey],
+            randomness,
+            pk_commitments: vec![],
+            message_hashes: vec![],
+            nlocktime: 0,
+            nsequence: 0,
+            checksig_accumulators: None,
+        }
+    }
+
+    /// Public inputs of a P2SH spend unrolled by `ExecutionChip::assign_p2sh_spend`. The
+    /// verifier derives the scripts of the last two regions from the public scriptSig, so
+    /// the redeem script is the one hashed by the scriptPubkey.
+    pub(crate) fn p2sh(script_sig: Vec<u8>, script_pubkey: Vec<u8>, randomness: F) -> Result<Self, BitcoinVmError> {
+        let (redeem_script_sig, redeem_script) = split_p2sh_script_sig(&script_sig)?;
+        let mut public_inputs = Self::new(script_sig, randomness);
+        public_inputs.script_pubkeys.extend([script_pubkey, redeem_script_sig, redeem_script]);
+        Ok(public_inputs)
+    }
+
+    pub(crate) fn with_pk_commitments(mut self, pk_commitments: Vec<Secp256k1Affine>) -> Self {
+        self.pk_commitments = pk_commitments;
+        self
+    }
+
+    pub(crate) fn with_message_hashes(mut self, message_hashes: Vec<Fq>) -> Self {
+        self.message_hashes = message_hashes;
+        self
+    }
+
+    pub(crate) fn with_nlocktime(mut self, nlocktime: u32) -> Self {
+        self.nlocktime = nlocktime;
+        self
+    }
+
+    pub(crate) fn with_nsequence(mut self, nsequence: u32) -> Self {
+        self.nsequence = nsequence;
+        self
+    }
+
+    pub(crate) fn with_checksig_accumulators(mut self, pk_rlc_acc: F, num_checksig_opcodes: u64) -> Self {
+        self.checksig_accumulators = Some((pk_rlc_acc, num_checksig_opcodes));
+        self
+    }
+
+    /// Instance column of the ExecutionChip. Each scriptPubkey occupies
+    /// `EXECUTION_INSTANCE_ROWS` rows, in the order of the scriptPubkeys, and the
+    /// nLockTime and the nSequence follow them. The pk_rlc_acc and num_checksig_opcodes
+    /// come last if they are exposed.
+    pub(crate) fn execution_instance(&self) -> Vec<F> {
+        let mut instance = vec![];
+        for script_pubkey in self.script_pubkeys.iter() {
+            let mut rows = [F::zero(); EXECUTION_INSTANCE_ROWS];
+            rows[SCRIPT_LENGTH_ROW] = F::from(script_pubkey.len() as u64);
+            rows[SCRIPT_RLC_ACC_ROW] = script_rlc(script_pubkey, self.randomness);
+            rows[RANDOMNESS_ROW] = self.randomness;
+            instance.extend(rows);
+        }
+        instance.push(F::from(self.nlocktime as u64));
+        instance.push(F::from(self.nsequence as u64));
+        if let Some((pk_rlc_acc, num_checksig_opcodes)) = self.checksig_accumulators {
+            instance.push(pk_rlc_acc);
+            instance.push(F::from(num_checksig_opcodes));
+        }
+        instance
+    }
+
+    /// Instance column of the main gate used by the OpCheckSigChip. The public key
+    /// commitments are followed by the message hashes of the signatures.
+    pub(crate) fn checksig_instance(&self) -> Vec<F> {
+        self.pk_commitments
+            .iter()
+            .flat_map(pk_commitment_public_inputs::<F>)
+            .chain(self.message_hashes.iter().flat_map(message_hash_public_inputs::<F>))
+            .collect()
+    }
+
+    /// Instance columns of a circuit which configures the ExecutionChip followed by the
+    /// OpCheckSigChip
+    pub(crate) fn to_instances(&self) -> Vec<Vec<F>> {
+        vec![self.execution_instance(), self.checksig_instance()]
+    }
+}
+
+/// Returns the RLC of the scriptPubkey bytes, with the first byte having the lowest power
+/// of the randomness
+pub(crate) fn script_rlc<F: Field>(script_pubkey: &[u8], randomness: F) -> F {
+    script_pubkey.iter().rev().fold(F::zero(), |acc, v| {
+        acc * randomness + F::from(*v as u64)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr as BnScalar;
+    use halo2_proofs::halo2curves::CurveAffine;
+    use halo2_proofs::halo2curves::secp256k1::Secp256k1Affine;
+
+    use crate::bitcoinvm_circuit::constants::NUMBER_OF_LIMBS;
+    use crate::bitcoinvm_circuit::crypto_opcodes::checksig::checksig::message_hash_row;
+    use super::*;
+
+    #[test]
+    fn test_public_inputs_order() {
+        let randomness = BnScalar::from(0x1234u64);
+        let script_pubkeys = vec![vec![0x51, 0x52], vec![0x53]];
+        let public_inputs = PublicInputs {
+            script_pubkeys,
+            randomness,
+            pk_commitments: vec![],
+            message_hashes: vec![],
+            nlocktime: 500_000_001,
+            nsequence: 0xfffffffe,
+            checksig_accumulators: None,
+        };
+
+        let instances = public_inputs.to_instances();
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[0], vec![
+            BnScalar::from(2),
+            BnScalar::from(0x51) + randomness * BnScalar::from(0x52),
+            randomness,
+            BnScalar::from(1),
+            BnScalar::from(0x53),
+            randomness,
+            BnScalar::from(500_000_001),
+            BnScalar::from(0xfffffffe),
+        ]);
+        assert_eq!(nlocktime_row(public_inputs.script_pubkeys.len()), instances[0].len() - 2);
+        assert_eq!(nsequence_row(public_inputs.script_pubkeys.len()), instances[0].len() - 1);
+        assert!(instances[1].is_empty());
+
+        // The checksig accumulators follow the nSequence
+        let pk_rlc_acc = BnScalar::from(0x5678u64);
+        let public_inputs = public_inputs.with_checksig_accumulators(pk_rlc_acc, 3);
+        let instances = public_inputs.to_instances();
+        assert_eq!(instances[0][pk_rlc_acc_row(2)], pk_rlc_acc);
+        assert_eq!(instances[0][num_checksig_opcodes_row(2)], BnScalar::from(3));
+        assert_eq!(instances[0].len(), num_checksig_opcodes_row(2) + 1);
+
+        // Each commitment occupies the limbs of its two coordinates
+        let public_inputs = public_inputs.with_pk_commitments(vec![Secp256k1Affine::generator(); 2]);
+        let instances = public_inputs.to_instances();
+        assert_eq!(instances[1].len(), 4 * NUMBER_OF_LIMBS);
+        assert_eq!(instances[1][..2 * NUMBER_OF_LIMBS], instances[1][2 * NUMBER_OF_LIMBS..]);
+
+        // The message hashes follow the commitments
+        let message_hash = Fq::from(0x9abcu64);
+        let public_inputs = public_inputs.with_message_hashes(vec![Fq::from(1u64), message_hash]);
+        let instances = public_inputs.to_instances();
+        assert_eq!(instances[1].len(), message_hash_row(2, 2));
+        assert_eq!(
+            instances[1][message_hash_row(2, 1)..],
+            message_hash_public_inputs::<BnScalar>(&message_hash)[..],
+        );
+    }
+}
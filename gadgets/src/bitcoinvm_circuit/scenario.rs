@@ -0,0 +1,413 @@
+//! Replayable proving inputs for regression tests.
+//!
+//! A [`Scenario`] bundles a scriptPubkey, the initial stack, the keys signing for the
+//! OP_CHECKSIG public keys and the expected outcome. Adding coverage for an opcode is a
+//! matter of adding a scenario to [`canonical_scenarios`].
+
+use halo2_proofs::arithmetic::Field as HaloField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+use halo2_proofs::halo2curves::bn256::Fr as BnScalar;
+use halo2_proofs::halo2curves::secp256k1::{Fq, Secp256k1Affine};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use secp256k1::constants::PUBLIC_KEY_SIZE;
+
+use super::constants::*;
+use super::crypto_opcodes::checksig::checksig::{OpCheckSigChip, OpCheckSigConfig};
+use super::crypto_opcodes::checksig::checksig_util::ct_option_ok_or;
+use super::crypto_opcodes::util::pk_parser::{collect_public_keys, PublicKeyInScript, StackElement};
+use super::crypto_opcodes::util::sign_util::{sign, SignData};
+use super::execution::{ExecutionChip, ExecutionConfig};
+use super::public_inputs::{nlocktime_row, nsequence_row, PublicInputs, RANDOMNESS_ROW, SCRIPT_LENGTH_ROW, SCRIPT_RLC_ACC_ROW};
+use super::witness::initial_stack_values;
+use crate::testing::run_and_verify;
+
+// The ECDSA verification needs 2^18 rows
+const CHECKSIG_K: u32 = 19;
+const EXECUTION_K: u32 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Expected {
+    Accept,
+    Reject,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Scenario {
+    pub name: &'static str,
+    pub script_pubkey: Vec<u8>,
+    // Stack before the scriptPubkey is run, top first
+    pub initial_stack: Vec<StackElement>,
+    // Seed for the RLC randomness, the aux generator and the signature nonces
+    pub seed: u64,
+    // Keys signing for the OP_CHECKSIG public keys, in the order they are collected
+    pub secret_keys: Vec<SecretKey>,
+    pub expected: Expected,
+}
+
+impl Scenario {
+    pub(crate) fn uses_checksig(&self) -> bool {
+        !collect_public_keys(self.script_pubkey.clone(), self.initial_stack.clone())
+            .expect("PK collection failed")
+            .is_empty()
+    }
+
+    /// Builds the circuit for the scenario, runs it in the MockProver and asserts
+    /// that the outcome is the expected one
+    pub(crate) fn run(&self) {
+        let collected_pks = collect_public_keys(self.script_pubkey.clone(), self.initial_stack.clone())
+            .expect("PK collection failed");
+        assert_eq!(collected_pks.len(), self.secret_keys.len(), "Scenario {}: one secret key needed per public key", self.name);
+
+        let public_inputs = self.public_inputs();
+
+        let result = if collected_pks.is_empty() {
+            run_and_verify(EXECUTION_K, &self.execution_circuit(), vec![public_inputs.execution_instance()])
+        }
+        else {
+            run_and_verify(CHECKSIG_K, &self.checksig_circuit(), public_inputs.to_instances())
+        };
+
+        match self.expected {
+            Expected::Accept => assert!(result.is_ok(), "Scenario {} was rejected: {:?}", self.name, result),
+            Expected::Reject => assert!(result.is_err(), "Scenario {} was accepted", self.name),
+        }
+    }
+
+    // The RLC randomness is the first value drawn from the seed
+    fn randomness(&self) -> BnScalar {
+        let mut rng = XorShiftRng::seed_from_u64(self.seed);
+        let r: u64 = rng.gen();
+        BnScalar::from(r)
+    }
+
+    // Each secret key signs the fixed ECDSA_MESSAGE_HASH
+    fn public_inputs(&self) -> PublicInputs<BnScalar> {
+        PublicInputs::new(self.script_pubkey.clone(), self.randomness())
+            .with_message_hashes(vec![Fq::from(ECDSA_MESSAGE_HASH); self.secret_keys.len()])
+    }
+
+    fn execution_circuit(&self) -> ScenarioExecutionCircuit {
+        let randomness = self.randomness();
+        ScenarioExecutionCircuit {
+            script_pubkey: self.script_pubkey.clone(),
+            randomness,
+            initial_stack: self.initial_stack_values(randomness),
+        }
+    }
+
+    /// Builds the circuit verifying the OP_CHECKSIG signatures of the scenario. The
+    /// RLC randomness is drawn from the seed before the ECDSA randomness.
+    fn checksig_circuit(&self) -> ScenarioChecksigCircuit {
+        let mut rng = XorShiftRng::seed_from_u64(self.seed);
+        let r: u64 = rng.gen();
+        let randomness = BnScalar::from(r);
+        let initial_stack = self.initial_stack_values(randomness);
+
+        let collected_pks = collect_public_keys(self.script_pubkey.clone(), self.initial_stack.clone())
+            .expect("PK collection failed");
+
+        let aux_generator = Secp256k1Affine::random(&mut rng);
+        // The signatures are checked against the public keys in the script, so a
+        // secret key not matching its public key gives an invalid signature
+        let signatures: Vec<SignData> = self.secret_keys
+            .iter()
+            .zip(collected_pks.iter())
+            .map(|(secret_key, pk_in_script)| {
+                let mut sk_bytes = secret_key.secret_bytes();
+                sk_bytes.reverse();
+                let sk = ct_option_ok_or(Fq::from_bytes(&sk_bytes), libsecp256k1::Error::InvalidSecretKey).unwrap();
+                let sig_randomness = Fq::random(&mut rng);
+                SignData {
+                    signature: sign(sig_randomness, sk, Fq::from(ECDSA_MESSAGE_HASH)),
+                    pk: pk_in_script.pk,
+                    message_hash: Fq::from(ECDSA_MESSAGE_HASH),
+                    sighash_type: SIGHASH_ALL,
+                }
+            })
+            .collect();
+
+        ScenarioChecksigCircuit {
+            op_checksig_chip: OpCheckSigChip::construct(aux_generator, 2).expect("Invalid aux generator"),
+            script_pubkey: self.script_pubkey.clone(),
+            randomness,
+            initial_stack,
+            signatures,
+            collected_pks,
+        }
+    }
+
+    fn initial_stack_values(&self, randomness: BnScalar) -> [BnScalar; MAX_STACK_DEPTH] {
+        initial_stack_values(&self.initial_stack, randomness).expect("Initial stack too deep")
+    }
+}
+
+fn p2pk_script(secret_key: &SecretKey) -> Vec<u8> {
+    let secp = Secp256k1::new();
+    let public_key_bytes: [u8; PUBLIC_KEY_SIZE] = PublicKey::from_secret_key(&secp, secret_key).serialize();
+    let mut script_pubkey: Vec<u8> = vec![PUBLIC_KEY_SIZE as u8]; // "Push 33 bytes" opcode
+    script_pubkey.extend(public_key_bytes.iter());
+    script_pubkey.push(OP_CHECKSIG as u8);
+    script_pubkey
+}
+
+/// Regression suite of scenarios
+pub(crate) fn canonical_scenarios() -> Vec<Scenario> {
+    let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+    let other_secret_key = SecretKey::from_slice(&[0xab; 32]).expect("32 bytes, within curve order");
+
+    vec![
+        Scenario {
+            name: "P2PK valid signature",
+            script_pubkey: p2pk_script(&secret_key),
+            initial_stack: vec![StackElement::ValidSignature],
+            seed: 1,
+            secret_keys: vec![secret_key],
+            expected: Expected::Accept,
+        },
+        Scenario {
+            name: "P2PK invalid signature",
+            script_pubkey: p2pk_script(&secret_key),
+            initial_stack: vec![StackElement::ValidSignature],
+            seed: 1,
+            secret_keys: vec![other_secret_key],
+            expected: Expected::Reject,
+        },
+        Scenario {
+            name: "Push only",
+            script_pubkey: vec![OP_1 as u8, 0x02, 0xab, 0xcd, OP_PUSHDATA1 as u8, 0x01, 0xef, OP_16 as u8],
+            initial_stack: vec![],
+            seed: 1,
+            secret_keys: vec![],
+            expected: Expected::Accept,
+        },
+        Scenario {
+            name: "OP_0 only",
+            script_pubkey: vec![OP_0 as u8],
+            initial_stack: vec![],
+            seed: 1,
+            secret_keys: vec![],
+            expected: Expected::Reject,
+        },
+    ]
+}
+
+struct ScenarioExecutionCircuit {
+    script_pubkey: Vec<u8>,
+    randomness: BnScalar,
+    initial_stack: [BnScalar; MAX_STACK_DEPTH],
+}
+
+impl Circuit<BnScalar> for ScenarioExecutionCircuit {
+    type Config = ExecutionConfig<BnScalar>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            script_pubkey: vec![],
+            randomness: BnScalar::zero(),
+            initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<BnScalar>) -> Self::Config {
+        ExecutionChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<BnScalar>
+    ) -> Result<(), Error> {
+        let chip = ExecutionChip::construct();
+        let chip_cells = chip.assign_script_pubkey_unroll(
+            config.clone(),
+            &mut layouter,
+            self.script_pubkey.clone(),
+            self.randomness,
+            self.initial_stack,
+        )?;
+        chip.expose_public(config.clone(), layouter.namespace(|| "script_length"), chip_cells.script_length, SCRIPT_LENGTH_ROW)?;
+        chip.expose_public(config.clone(), layouter.namespace(|| "script_rlc_acc"), chip_cells.script_rlc_acc_init, SCRIPT_RLC_ACC_ROW)?;
+        chip.expose_public(config.clone(), layouter.namespace(|| "randomness"), chip_cells.randomness, RANDOMNESS_ROW)?;
+        chip.expose_public(config.clone(), layouter.namespace(|| "nlocktime"), chip_cells.nlocktime, nlocktime_row(1))?;
+        chip.expose_public(config, layouter.namespace(|| "nsequence"), chip_cells.nsequence, nsequence_row(1))?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ScenarioChecksigConfig {
+    execution_config: ExecutionConfig<BnScalar>,
+    op_checksig_config: OpCheckSigConfig<BnScalar>,
+}
+
+struct ScenarioChecksigCircuit {
+    op_checksig_chip: OpCheckSigChip<BnScalar, MAX_CHECKSIG_COUNT>,
+    script_pubkey: Vec<u8>,
+    randomness: BnScalar,
+    initial_stack: [BnScalar; MAX_STACK_DEPTH],
+    signatures: Vec<SignData>,
+    collected_pks: Vec<PublicKeyInScript>,
+}
+
+impl Circuit<BnScalar> for ScenarioChecksigCircuit {
+    type Config = ScenarioChecksigConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            op_checksig_chip: self.op_checksig_chip.clone(),
+            script_pubkey: vec![],
+            randomness: BnScalar::one(),
+            initial_stack: [BnScalar::zero(); MAX_STACK_DEPTH],
+            signatures: vec![],
+            collected_pks: vec![],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<BnScalar>) -> Self::Config {
+        ScenarioChecksigConfig {
+            execution_config: ExecutionChip::configure(meta),
+            op_checksig_config: OpCheckSigChip::<BnScalar, MAX_CHECKSIG_COUNT>::configure(meta),
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<BnScalar>
+    ) -> Result<(), Error> {
+        let chip = ExecutionChip::construct();
+        let chip_cells = chip.assign_script_pubkey_unroll(
+            config.execution_config.clone(),
+            &mut layouter,
+            self.script_pubkey.clone(),
+            self.randomness,
+            self.initial_stack,
+        )?;
+        chip.expose_public(config.execution_config.clone(), layouter.namespace(|| "script_length"), chip_cells.script_length.clone(), SCRIPT_LENGTH_ROW)?;
+        chip.expose_public(config.execution_config.clone(), layouter.namespace(|| "script_rlc_acc"), chip_cells.script_rlc_acc_init.clone(), SCRIPT_RLC_ACC_ROW)?;
+        chip.expose_public(config.execution_config.clone(), layouter.namespace(|| "randomness"), chip_cells.randomness.clone(), RANDOMNESS_ROW)?;
+        chip.expose_public(config.execution_config.clone(), layouter.namespace(|| "nlocktime"), chip_cells.nlocktime.clone(), nlocktime_row(1))?;
+        chip.expose_public(config.execution_config, layouter.namespace(|| "nsequence"), chip_cells.nsequence.clone(), nsequence_row(1))?;
+
+        self.op_checksig_chip.assign(
+            &config.op_checksig_config,
+            &mut layouter,
+            &chip_cells,
+            self.randomness,
+            &self.signatures,
+            &self.collected_pks,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::canonical_scenarios;
+    use crate::bitcoinvm_circuit::constants::MAX_SCRIPT_PUBKEY_SIZE;
+    use crate::bitcoinvm_circuit::util::advice_trace::record_instances;
+    use crate::bitcoinvm_circuit::util::region_profile::print_region_summary;
+    #[cfg(feature = "dev-graph")]
+    use super::CHECKSIG_K;
+
+    #[test]
+    fn test_scenarios_without_checksig() {
+        for scenario in canonical_scenarios().into_iter().filter(|s| !s.uses_checksig()) {
+            scenario.run();
+        }
+    }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test scenarios -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn test_scenarios_with_checksig() {
+        for scenario in canonical_scenarios().into_iter().filter(|s| s.uses_checksig()) {
+            scenario.run();
+        }
+    }
+
+    // The instance columns serialized by PublicInputs are the ones which the
+    // constrain_instance calls of the chips expect
+    #[test]
+    fn test_scenarios_public_inputs_layout() {
+        for scenario in canonical_scenarios().into_iter().filter(|s| !s.uses_checksig()) {
+            let instances = record_instances(&scenario.execution_circuit()).unwrap();
+            assert_eq!(instances, vec![scenario.public_inputs().execution_instance()], "Scenario {}", scenario.name);
+        }
+    }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test scenarios -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn test_scenarios_public_inputs_layout_with_checksig() {
+        for scenario in canonical_scenarios().into_iter().filter(|s| s.uses_checksig()) {
+            let instances = record_instances(&scenario.checksig_circuit()).unwrap();
+            assert_eq!(instances, scenario.public_inputs().to_instances(), "Scenario {}", scenario.name);
+        }
+    }
+
+    #[test]
+    fn test_scenarios_region_profile() {
+        let scenario = canonical_scenarios()
+            .into_iter()
+            .find(|s| !s.uses_checksig())
+            .expect("A scenario without OP_CHECKSIG exists");
+        let row_counts = print_region_summary(&scenario.execution_circuit()).unwrap();
+
+        // The first row, a row per scriptPubkey byte and the extra row queried by the last byte
+        assert!(row_counts.contains(&("ScriptPubkey unrolling".to_string(), MAX_SCRIPT_PUBKEY_SIZE + 2)));
+    }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test scenarios -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn test_scenarios_region_profile_with_checksig() {
+        let scenario = canonical_scenarios()
+            .into_iter()
+            .find(|s| s.uses_checksig())
+            .expect("A scenario with OP_CHECKSIG exists");
+        let row_counts = print_region_summary(&scenario.checksig_circuit()).unwrap();
+
+        for name in [
+            "ScriptPubkey unrolling",
+            "ecc chip aux",
+            "ecdsa chip verification",
+            "OP_CHECKSIG public key collection verification",
+        ] {
+            assert!(
+                row_counts.iter().any(|(region, rows)| region == name && *rows > 0),
+                "Region {} is missing from {:?}", name, row_counts,
+            );
+        }
+    }
+
+    // Renders the layout of the ExecutionChip and the OpCheckSigChip sharing a circuit.
+    // Chips for further opcodes are to be added to the scenario circuits so that they
+    // appear in this plot.
+    #[cfg(feature = "dev-graph")]
+    #[test]
+    fn plot_full_bitcoinvm() {
+        use plotters::prelude::*;
+
+        let scenario = canonical_scenarios()
+            .into_iter()
+            .find(|s| s.uses_checksig())
+            .expect("A scenario with OP_CHECKSIG exists");
+        let circuit = scenario.checksig_circuit();
+
+        let root = BitMapBackend::new("bitcoinvm-layout.png", (1024, 3096)).into_drawing_area();
+        root.fill(&WHITE).unwrap();
+        let root = root.titled("BitcoinVM Layout", ("sans-serif", 60)).unwrap();
+
+        halo2_proofs::dev::CircuitLayout::default()
+            .render(CHECKSIG_K, &circuit, &root)
+            .unwrap();
+    }
+}
@@ -0,0 +1,204 @@
+//! Assignment backend which records the values of the assigned advice cells.
+//!
+//! Synthesizing a circuit with this backend instead of the `MockProver` gives
+//! the witness of every region, e.g. to compare an execution trace against a
+//! golden file, or the instance values expected by the `constrain_instance` calls.
+
+use std::collections::HashMap;
+
+use halo2_proofs::circuit::Value;
+use halo2_proofs::plonk::{
+    Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Error, Fixed,
+    FloorPlanner, Instance, Selector,
+};
+
+use crate::Field;
+
+/// The advice values assigned in a region
+#[derive(Clone, Debug)]
+pub(crate) struct RecordedRegion<F: Field> {
+    pub name: String,
+    // Absolute row of the first cell assigned in the region
+    pub start: usize,
+    // Maps (column index, absolute row) to the assigned value
+    pub cells: HashMap<(usize, usize), F>,
+}
+
+impl<F: Field> RecordedRegion<F> {
+    /// Value assigned to `column` at `offset` rows from the region start, if any
+    pub(crate) fn value(&self, column: Column<Advice>, offset: usize) -> Option<F> {
+        self.cells.get(&(column.index(), self.start + offset)).copied()
+    }
+}
+
+#[derive(Default)]
+struct AdviceRecorder<F: Field> {
+    regions: Vec<RecordedRegion<F>>,
+    current_region: Option<RecordedRegion<F>>,
+    // Maps (column index, absolute row) of every assigned advice cell to its value
+    values: HashMap<(usize, usize), F>,
+    // (column index, row, value) of the instance cells which advice cells are copied to
+    instance_cells: Vec<(usize, usize, F)>,
+}
+
+impl<F: Field> Assignment<F> for AdviceRecorder<F> {
+    fn enter_region<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.current_region = Some(RecordedRegion {
+            name: name_fn().into(),
+            start: usize::MAX,
+            cells: HashMap::new(),
+        });
+    }
+
+    fn exit_region(&mut self) {
+        if let Some(region) = self.current_region.take() {
+            self.regions.push(region);
+        }
+    }
+
+    fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, _: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        Ok(())
+    }
+
+    fn query_instance(&self, _: Column<Instance>, _: usize) -> Result<Value<F>, Error> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        column: Column<Advice>,
+        row: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let mut value = None;
+        let _ = to().map(|v| value = Some(v.into().evaluate()));
+        if let Some(value) = value {
+            self.values.insert((column.index(), row), value);
+            if let Some(region) = self.current_region.as_mut() {
+                region.start = region.start.min(row);
+                region.cells.insert((column.index(), row), value);
+            }
+        }
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Fixed>,
+        _: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let _ = to();
+        Ok(())
+    }
+
+    fn copy(
+        &mut self,
+        left_column: Column<Any>,
+        left_row: usize,
+        right_column: Column<Any>,
+        right_row: usize,
+    ) -> Result<(), Error> {
+        // `constrain_instance` copies an assigned cell to an instance cell
+        if let (Ok(advice), Ok(instance)) = (
+            Column::<Advice>::try_from(left_column),
+            Column::<Instance>::try_from(right_column),
+        ) {
+            if let Some(value) = self.values.get(&(advice.index(), left_row)) {
+                self.instance_cells.push((instance.index(), right_row, *value));
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        _: Column<Fixed>,
+        _: usize,
+        _: Value<Assigned<F>>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {}
+}
+
+fn synthesize<F: Field, C: Circuit<F>>(
+    circuit: &C,
+) -> Result<(ConstraintSystem<F>, C::Config, AdviceRecorder<F>), Error> {
+    let mut cs = ConstraintSystem::default();
+    let config = C::configure(&mut cs);
+    let mut recorder = AdviceRecorder::default();
+    C::FloorPlanner::synthesize(&mut recorder, circuit, config.clone(), cs.constants().clone())?;
+    Ok((cs, config, recorder))
+}
+
+/// Synthesizes `circuit` and returns its configuration along with the advice
+/// values of every region, in the order in which the regions were assigned
+pub(crate) fn record_advice<F: Field, C: Circuit<F>>(
+    circuit: &C,
+) -> Result<(C::Config, Vec<RecordedRegion<F>>), Error> {
+    let (_, config, recorder) = synthesize(circuit)?;
+    Ok((config, recorder.regions))
+}
+
+/// Synthesizes `circuit` and returns the instance columns which satisfy its
+/// `constrain_instance` calls. Rows without a copy constraint are set to zero.
+pub(crate) fn record_instances<F: Field, C: Circuit<F>>(
+    circuit: &C,
+) -> Result<Vec<Vec<F>>, Error> {
+    let (cs, _, recorder) = synthesize(circuit)?;
+
+    let mut instances = vec![vec![]; cs.num_instance_columns()];
+    for (column, row, value) in recorder.instance_cells {
+        let instance: &mut Vec<F> = &mut instances[column];
+        if instance.len() <= row {
+            instance.resize(row + 1, F::zero());
+        }
+        instance[row] = value;
+    }
+    Ok(instances)
+}
+
+/// Formats a field element as a decimal number if it fits in a u64 and as a
+/// big-endian hex string otherwise
+pub(crate) fn format_field<F: Field>(value: F) -> String {
+    let repr = value.to_repr();
+    if repr[8..].iter().all(|b| *b == 0) {
+        let mut low = [0u8; 8];
+        low.copy_from_slice(&repr[..8]);
+        u64::from_le_bytes(low).to_string()
+    } else {
+        let hex: String = repr.iter().rev().map(|b| format!("{:02x}", b)).collect();
+        format!("0x{}", hex)
+    }
+}
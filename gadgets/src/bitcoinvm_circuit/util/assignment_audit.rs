@@ -0,0 +1,134 @@
+//! Assignment backend which records the advice cells assigned during synthesis.
+//!
+//! Synthesizing a circuit with this backend instead of the `MockProver` reports
+//! every advice cell which is assigned more than once, e.g. due to two regions
+//! sharing a column at overlapping offsets.
+
+use std::collections::HashMap;
+
+use halo2_proofs::circuit::Value;
+use halo2_proofs::plonk::{
+    Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Error, Fixed,
+    FloorPlanner, Instance, Selector,
+};
+
+use crate::Field;
+
+/// An advice cell which was assigned more than once
+#[derive(Clone, Debug)]
+pub(crate) struct DoubleAssignment {
+    pub region: String,
+    pub annotation: String,
+    pub column: usize,
+    pub row: usize,
+}
+
+#[derive(Default)]
+struct AssignmentAudit {
+    // Maps (column index, row) of every assigned advice cell to the region it was assigned in
+    assigned_cells: HashMap<(usize, usize), String>,
+    current_region: String,
+    double_assignments: Vec<DoubleAssignment>,
+}
+
+impl<F: Field> Assignment<F> for AssignmentAudit {
+    fn enter_region<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.current_region = name_fn().into();
+    }
+
+    fn exit_region(&mut self) {
+        self.current_region.clear();
+    }
+
+    fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, _: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        Ok(())
+    }
+
+    fn query_instance(&self, _: Column<Instance>, _: usize) -> Result<Value<F>, Error> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        annotation: A,
+        column: Column<Advice>,
+        row: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // The value is computed as the chips may depend on it
+        let _ = to();
+        if self.assigned_cells.insert((column.index(), row), self.current_region.clone()).is_some() {
+            self.double_assignments.push(DoubleAssignment {
+                region: self.current_region.clone(),
+                annotation: annotation().into(),
+                column: column.index(),
+                row,
+            });
+        }
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Fixed>,
+        _: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let _ = to();
+        Ok(())
+    }
+
+    fn copy(&mut self, _: Column<Any>, _: usize, _: Column<Any>, _: usize) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        _: Column<Fixed>,
+        _: usize,
+        _: Value<Assigned<F>>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {}
+}
+
+/// Synthesizes `circuit` and returns the advice cells which were assigned more than once
+pub(crate) fn find_double_assignments<F: Field, C: Circuit<F>>(
+    circuit: &C,
+) -> Result<Vec<DoubleAssignment>, Error> {
+    let mut cs = ConstraintSystem::default();
+    let config = C::configure(&mut cs);
+    let mut audit = AssignmentAudit::default();
+    C::FloorPlanner::synthesize(&mut audit, circuit, config, cs.constants().clone())?;
+    Ok(audit.double_assignments)
+}
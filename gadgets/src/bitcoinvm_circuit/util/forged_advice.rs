@@ -0,0 +1,188 @@
+//! Assignment backend which replaces advice values before they reach the `MockProver`.
+//!
+//! Running a circuit with forged values checks that its constraints reject a witness
+//! which the chips never assign, e.g. a non-canonical boolean pushed by a comparison
+//! opcode. The forged cells are addressed by column and absolute row, as recorded by
+//! `advice_trace::record_advice`.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use halo2_proofs::arithmetic::{Field as HaloField, FieldExt};
+use halo2_proofs::circuit::{Layouter, Value};
+use halo2_proofs::dev::VerifyFailure;
+use halo2_proofs::plonk::{
+    Advice, Any as AnyColumn, Assigned, Assignment, Circuit, Column, ConstraintSystem, Error,
+    Fixed, FloorPlanner, Instance, Selector,
+};
+
+use crate::testing::run_and_verify;
+
+thread_local! {
+    // Forged values of the next circuit synthesized by a ForgingFloorPlanner, as a
+    // HashMap<(usize, usize), F> keyed by (column index, row). The floor planner is
+    // generic over the circuit, so it cannot read them from the circuit.
+    static FORGED_CELLS: RefCell<Option<Box<dyn Any>>> = RefCell::new(None);
+}
+
+struct ForgingAssignment<'a, F: HaloField, CS: Assignment<F>> {
+    inner: &'a mut CS,
+    forged_cells: HashMap<(usize, usize), F>,
+}
+
+impl<'a, F: HaloField, CS: Assignment<F>> Assignment<F> for ForgingAssignment<'a, F, CS> {
+    fn enter_region<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.inner.enter_region(name_fn)
+    }
+
+    fn exit_region(&mut self) {
+        self.inner.exit_region()
+    }
+
+    fn enable_selector<A, AR>(&mut self, annotation: A, selector: &Selector, row: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inner.enable_selector(annotation, selector, row)
+    }
+
+    fn query_instance(&self, column: Column<Instance>, row: usize) -> Result<Value<F>, Error> {
+        self.inner.query_instance(column, row)
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        annotation: A,
+        column: Column<Advice>,
+        row: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        match self.forged_cells.get(&(column.index(), row)) {
+            Some(forged) => {
+                let forged = *forged;
+                self.inner.assign_advice(annotation, column, row, || Value::known(forged))
+            }
+            None => self.inner.assign_advice(annotation, column, row, to),
+        }
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        annotation: A,
+        column: Column<Fixed>,
+        row: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inner.assign_fixed(annotation, column, row, to)
+    }
+
+    fn copy(
+        &mut self,
+        left_column: Column<AnyColumn>,
+        left_row: usize,
+        right_column: Column<AnyColumn>,
+        right_row: usize,
+    ) -> Result<(), Error> {
+        self.inner.copy(left_column, left_row, right_column, right_row)
+    }
+
+    fn fill_from_row(
+        &mut self,
+        column: Column<Fixed>,
+        row: usize,
+        to: Value<Assigned<F>>,
+    ) -> Result<(), Error> {
+        self.inner.fill_from_row(column, row, to)
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.inner.push_namespace(name_fn)
+    }
+
+    fn pop_namespace(&mut self, gadget_name: Option<String>) {
+        self.inner.pop_namespace(gadget_name)
+    }
+}
+
+// Floor planner which lays out the circuit with the floor planner P and the forged values
+struct ForgingFloorPlanner<P: FloorPlanner>(PhantomData<P>);
+
+impl<P: FloorPlanner> FloorPlanner for ForgingFloorPlanner<P> {
+    fn synthesize<F: HaloField, CS: Assignment<F>, C: Circuit<F>>(
+        cs: &mut CS,
+        circuit: &C,
+        config: C::Config,
+        constants: Vec<Column<Fixed>>,
+    ) -> Result<(), Error> {
+        let forged_cells = FORGED_CELLS
+            .with(|cells| cells.borrow_mut().take())
+            .and_then(|cells| cells.downcast::<HashMap<(usize, usize), F>>().ok())
+            .map(|cells| *cells)
+            .unwrap_or_default();
+        let mut forging = ForgingAssignment { inner: cs, forged_cells };
+        P::synthesize(&mut forging, circuit, config, constants)
+    }
+}
+
+// The circuit laid out by a ForgingFloorPlanner
+struct ForgedCircuit<'a, F: HaloField, C: Circuit<F>> {
+    circuit: &'a C,
+    marker: PhantomData<F>,
+}
+
+impl<'a, F: HaloField, C: Circuit<F>> Circuit<F> for ForgedCircuit<'a, F, C> {
+    type Config = C::Config;
+    type FloorPlanner = ForgingFloorPlanner<C::FloorPlanner>;
+
+    fn without_witnesses(&self) -> Self {
+        Self { circuit: self.circuit, marker: PhantomData }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        C::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, layouter: impl Layouter<F>) -> Result<(), Error> {
+        self.circuit.synthesize(config, layouter)
+    }
+}
+
+/// Runs the circuit in the MockProver with the advice cells at the given columns and
+/// absolute rows assigned the forged values instead, and returns the constraint failures,
+/// if any. Panics if the circuit cannot be synthesized.
+pub(crate) fn run_with_forged_advice<F: FieldExt, C: Circuit<F>>(
+    k: u32,
+    circuit: &C,
+    public_inputs: Vec<Vec<F>>,
+    forged_cells: &[(Column<Advice>, usize, F)],
+) -> Result<(), Vec<VerifyFailure>> {
+    let forged_cells: HashMap<(usize, usize), F> = forged_cells
+        .iter()
+        .map(|(column, row, value)| ((column.index(), *row), *value))
+        .collect();
+    FORGED_CELLS.with(|cells| *cells.borrow_mut() = Some(Box::new(forged_cells)));
+    run_and_verify(k, &ForgedCircuit { circuit, marker: PhantomData }, public_inputs)
+}
@@ -0,0 +1,129 @@
+//! LessThan gadget compares two script numbers decoded by the ScriptNum gadget.
+//!
+//! Given `lhs` and `rhs` in the range of 4 byte script numbers, i.e. (-2^31, 2^31):
+//!  - witnesses the boolean `lt` which is 1 iff `lhs` < `rhs`
+//!  - witnesses the bytes of `lhs - rhs + lt * 2^32`, which is in [0, 2^32) iff `lt` is correct
+//!
+//! As |lhs - rhs| < 2^32 - 1, the difference is negative iff `lt` is 1. The bytes are range
+//! checked with a lookup into a table of all byte values.
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector, TableColumn, VirtualCells},
+    poly::Rotation,
+};
+
+use super::expr::Expr;
+use super::script_num::MAX_SCRIPT_NUM_SIZE;
+
+#[derive(Clone, Debug)]
+pub struct LessThanConfig<F> {
+    pub lt: Column<Advice>,
+    /// Little-endian bytes of `lhs - rhs + lt * 2^32`
+    pub diff_bytes: [Column<Advice>; MAX_SCRIPT_NUM_SIZE],
+    /// One if `lhs` < `rhs` in the current row
+    pub lt_expression: Expression<F>,
+}
+
+impl<F: FieldExt> LessThanConfig<F> {
+    /// Returns the lt expression
+    pub fn expr(&self) -> Expression<F> {
+        self.lt_expression.clone()
+    }
+}
+
+pub struct LessThanChip<F> {
+    config: LessThanConfig<F>,
+}
+
+impl<F: FieldExt> LessThanChip<F> {
+    /// Configures the comparison of `lhs` and `rhs` in the rows where `q_enable` is set. The
+    /// difference bytes are range checked against `byte_table`, which must contain all byte values.
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        q_enable: Selector,
+        lhs: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
+        rhs: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
+        byte_table: TableColumn,
+    ) -> LessThanConfig<F> {
+        let lt = meta.advice_column();
+        let diff_bytes = [(); MAX_SCRIPT_NUM_SIZE].map(|_| meta.advice_column());
+
+        // dummy initialization
+        let mut lt_expression = 0u8.expr();
+
+        meta.create_gate("less than gate", |meta| {
+            let q_enable = meta.query_selector(q_enable);
+            lt_expression = meta.query_advice(lt, Rotation::cur());
+
+            let mut diff = 0u8.expr();
+            let mut weight = F::one();
+            for byte in diff_bytes.iter() {
+                diff = diff + meta.query_advice(*byte, Rotation::cur()) * Expression::Constant(weight);
+                weight *= F::from(256);
+            }
+            // weight is 2^32 after the loop
+            vec![
+                q_enable.clone() * lt_expression.clone() * (1u8.expr() - lt_expression.clone()),
+                q_enable * (lhs(meta) - rhs(meta) + lt_expression.clone() * Expression::Constant(weight) - diff),
+            ]
+        });
+
+        for byte in diff_bytes {
+            meta.lookup("less than difference byte range", |meta| {
+                let q_enable = meta.query_selector(q_enable);
+                let byte = meta.query_advice(byte, Rotation::cur());
+                vec![(q_enable * byte, byte_table)]
+            });
+        }
+
+        LessThanConfig {
+            lt,
+            diff_bytes,
+            lt_expression,
+        }
+    }
+
+    pub fn construct(config: LessThanConfig<F>) -> Self {
+        Self { config }
+    }
+
+    pub fn assign(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        lhs: i64,
+        rhs: i64,
+    ) -> Result<(), Error> {
+        let config = self.config();
+        let lt = lhs < rhs;
+        let diff = lhs - rhs + if lt { 1i64 << (8 * MAX_SCRIPT_NUM_SIZE) } else { 0 };
+        assert!((0..1i64 << (8 * MAX_SCRIPT_NUM_SIZE)).contains(&diff));
+
+        region.assign_advice(|| "less than", config.lt, offset, || Value::known(F::from(lt as u64)))?;
+        for (i, column) in config.diff_bytes.iter().enumerate() {
+            let byte = (diff >> (8 * i)) & 0xff;
+            region.assign_advice(
+                || "less than difference byte",
+                *column,
+                offset,
+                || Value::known(F::from(byte as u64)),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<F: FieldExt> Chip<F> for LessThanChip<F> {
+    type Config = LessThanConfig<F>;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
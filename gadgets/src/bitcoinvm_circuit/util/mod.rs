@@ -1,3 +1,15 @@
 pub mod expr;
 pub mod is_zero;
-pub mod script_parser;
\ No newline at end of file
+pub mod less_than;
+pub mod script_num;
+pub mod script_parser;
+pub mod random_script;
+pub mod script_type;
+#[cfg(test)]
+pub(crate) mod assignment_audit;
+#[cfg(test)]
+pub(crate) mod advice_trace;
+#[cfg(test)]
+pub(crate) mod forged_advice;
+#[cfg(test)]
+pub(crate) mod region_profile;
@@ -0,0 +1,79 @@
+use std::cmp::min;
+
+use rand::Rng;
+
+use super::super::constants::*;
+
+/// Generates a random well-formed scriptPubkey of at most `max_len` bytes for stress testing.
+///
+/// The script consists of OP_0, OP_1 to OP_16, OP_NOP, PUSH1 to PUSH75 and
+/// PUSHDATA1/2/4 opcodes with non-zero data lengths. The last byte is always one of
+/// OP_1 to OP_16 so that the script leaves a true value on the top of the stack.
+pub fn random_valid_script(rng: &mut impl Rng, max_len: usize) -> Vec<u8> {
+    assert!(max_len > 0 && max_len <= MAX_SCRIPT_PUBKEY_SIZE);
+
+    let script_len = rng.gen_range(1..=max_len);
+    let mut script: Vec<u8> = vec![];
+
+    // The last byte is reserved for an OP_1 to OP_16 opcode
+    while script.len() < script_len - 1 {
+        let remaining = script_len - 1 - script.len();
+        match rng.gen_range(0..7) {
+            0 => script.push(OP_0 as u8),
+            1 => script.push(rng.gen_range(OP_1..=OP_16) as u8),
+            2 if remaining >= 2 => {
+                let data_len = rng.gen_range(OP_PUSH_NEXT1..=min(OP_PUSH_NEXT75, remaining - 1));
+                script.push(data_len as u8);
+                push_random_bytes(rng, &mut script, data_len);
+            },
+            3 if remaining >= 3 => {
+                let data_len = rng.gen_range(1..=min(0xff, remaining - 2));
+                script.push(OP_PUSHDATA1 as u8);
+                script.push(data_len as u8);
+                push_random_bytes(rng, &mut script, data_len);
+            },
+            4 if remaining >= 4 => {
+                let data_len = rng.gen_range(1..=min(0xffff, remaining - 3));
+                script.push(OP_PUSHDATA2 as u8);
+                script.extend_from_slice(&(data_len as u16).to_le_bytes());
+                push_random_bytes(rng, &mut script, data_len);
+            },
+            5 if remaining >= 6 => {
+                let data_len = rng.gen_range(1..=remaining - 5);
+                script.push(OP_PUSHDATA4 as u8);
+                script.extend_from_slice(&(data_len as u32).to_le_bytes());
+                push_random_bytes(rng, &mut script, data_len);
+            },
+            _ => script.push(OP_NOP as u8),
+        }
+    }
+    script.push(rng.gen_range(OP_1..=OP_16) as u8);
+    script
+}
+
+fn push_random_bytes(rng: &mut impl Rng, script: &mut Vec<u8>, num_bytes: usize) {
+    for _ in 0..num_bytes {
+        script.push(rng.gen());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    use crate::bitcoinvm_circuit::constants::*;
+    use crate::bitcoinvm_circuit::crypto_opcodes::util::pk_parser::collect_public_keys;
+    use super::random_valid_script;
+
+    #[test]
+    fn test_random_valid_script_parses() {
+        let mut rng = XorShiftRng::seed_from_u64(1);
+        for _ in 0..1000 {
+            let script = random_valid_script(&mut rng, MAX_SCRIPT_PUBKEY_SIZE);
+            assert!(!script.is_empty() && script.len() <= MAX_SCRIPT_PUBKEY_SIZE);
+            let collected_pks = collect_public_keys(script, vec![]).expect("Script parsing failed");
+            assert!(collected_pks.is_empty());
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Assignment backend which records the number of rows used by each region.
+//!
+//! Synthesizing a circuit with this backend instead of the `MockProver` shows which
+//! regions dominate the number of rows, and hence `k`. Regions assigned several times
+//! under the same name, e.g. one per message block, are reported as a total.
+
+use std::collections::HashMap;
+
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::circuit::Value;
+use halo2_proofs::plonk::{
+    Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Error, Fixed,
+    FloorPlanner, Instance, Selector,
+};
+
+#[derive(Default)]
+struct RegionProfiler {
+    // (name, first row, last row) of the region being assigned
+    current_region: Option<(String, usize, usize)>,
+    // Total number of rows of the regions with a given name, in the order of first assignment
+    row_counts: Vec<(String, usize)>,
+    indices: HashMap<String, usize>,
+}
+
+impl RegionProfiler {
+    fn touch(&mut self, row: usize) {
+        if let Some((_, first, last)) = self.current_region.as_mut() {
+            *first = (*first).min(row);
+            *last = (*last).max(row);
+        }
+    }
+}
+
+impl<F: FieldExt> Assignment<F> for RegionProfiler {
+    fn enter_region<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.current_region = Some((name_fn().into(), usize::MAX, 0));
+    }
+
+    fn exit_region(&mut self) {
+        if let Some((name, first, last)) = self.current_region.take() {
+            let rows = if first <= last { last - first + 1 } else { 0 };
+            match self.indices.get(&name) {
+                Some(index) => self.row_counts[*index].1 += rows,
+                None => {
+                    self.indices.insert(name.clone(), self.row_counts.len());
+                    self.row_counts.push((name, rows));
+                }
+            }
+        }
+    }
+
+    fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, row: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        Ok(())
+    }
+
+    fn query_instance(&self, _: Column<Instance>, _: usize) -> Result<Value<F>, Error> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Advice>,
+        row: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // The value is computed as the chips may depend on it
+        let _ = to();
+        self.touch(row);
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Fixed>,
+        row: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let _ = to();
+        self.touch(row);
+        Ok(())
+    }
+
+    fn copy(&mut self, _: Column<Any>, _: usize, _: Column<Any>, _: usize) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        _: Column<Fixed>,
+        _: usize,
+        _: Value<Assigned<F>>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {}
+}
+
+/// Synthesizes `circuit` and returns the number of rows used by the regions of each
+/// name, in the order in which the names were first assigned
+pub(crate) fn region_row_counts<F: FieldExt, C: Circuit<F>>(
+    circuit: &C,
+) -> Result<Vec<(String, usize)>, Error> {
+    let mut cs = ConstraintSystem::default();
+    let config = C::configure(&mut cs);
+    let mut profiler = RegionProfiler::default();
+    C::FloorPlanner::synthesize(&mut profiler, circuit, config, cs.constants().clone())?;
+    Ok(profiler.row_counts)
+}
+
+/// Formats the row counts as a table with the largest regions first
+pub(crate) fn region_summary(row_counts: &[(String, usize)]) -> String {
+    let mut sorted = row_counts.to_vec();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1));
+    let mut summary = String::from("Rows per region:\n");
+    for (name, rows) in sorted {
+        summary += &format!("{:>10}  {}\n", rows, name);
+    }
+    summary
+}
+
+/// Synthesizes `circuit`, prints the summary of its row counts and returns the row counts
+pub(crate) fn print_region_summary<F: FieldExt, C: Circuit<F>>(
+    circuit: &C,
+) -> Result<Vec<(String, usize)>, Error> {
+    let row_counts = region_row_counts(circuit)?;
+    print!("{}", region_summary(&row_counts));
+    Ok(row_counts)
+}
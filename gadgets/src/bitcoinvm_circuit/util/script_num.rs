@@ -0,0 +1,436 @@
+//! ScriptNum gadget decodes a stack item into the signed integer of its CScriptNum encoding.
+//!
+//! Stack items are RLCs of their bytes. The gadget witnesses the little-endian bytes of the
+//! number, with the sign in the most significant bit of the last byte, and checks that their
+//! RLC equals the stack item. Bytes are range checked with a lookup into a table of all byte
+//! values. Only minimally encoded numbers are decoded, as required by the MINIMALDATA rule of
+//! Bitcoin Core. Zero is the empty array, whose stack item is EMPTY_ARRAY_REPRESENTATION.
+//!
+//! As the RLC of a byte vector does not depend on its leading zero bytes, a number whose least
+//! significant byte is zero, e.g. 256, cannot be told apart from the shorter number without
+//! that byte. The first byte of a number is therefore required to be non-zero and such numbers
+//! are not supported.
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector, TableColumn, VirtualCells},
+    poly::Rotation,
+};
+
+use super::super::constants::EMPTY_ARRAY_REPRESENTATION;
+use super::expr::Expr;
+
+/// Maximum byte length of a numeric operand. Bitcoin fails scripts with longer operands.
+pub const MAX_SCRIPT_NUM_SIZE: usize = 4;
+/// Maximum byte length of the result of an arithmetic opcode. The sum of two 4 byte operands
+/// may need 5 bytes. Such results can be pushed but not used as operands.
+pub const MAX_SCRIPT_NUM_RESULT_SIZE: usize = MAX_SCRIPT_NUM_SIZE + 1;
+
+/// Returns the minimal CScriptNum encoding of `value`
+pub fn encode_script_num(value: i64) -> Vec<u8> {
+    let mut bytes = vec![];
+    let mut abs_value = value.unsigned_abs();
+    while abs_value > 0 {
+        bytes.push((abs_value & 0xff) as u8);
+        abs_value >>= 8;
+    }
+    if let Some(last) = bytes.last_mut() {
+        if *last & 0x80 != 0 {
+            bytes.push(if value < 0 { 0x80 } else { 0x00 });
+        }
+        else if value < 0 {
+            *last |= 0x80;
+        }
+    }
+    bytes
+}
+
+/// Decodes a minimally encoded number of at most `max_size` bytes
+pub fn decode_script_num(bytes: &[u8], max_size: usize) -> Option<i64> {
+    if bytes.len() > max_size {
+        return None;
+    }
+    if let Some(last) = bytes.last() {
+        // The last byte may only be a sign byte if the sign bit of the previous byte is set
+        if *last & 0x7f == 0 && (bytes.len() == 1 || bytes[bytes.len() - 2] & 0x80 == 0) {
+            return None;
+        }
+    }
+    let mut abs_value: i64 = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        let byte = if i == bytes.len() - 1 { byte & 0x7f } else { *byte };
+        abs_value |= (byte as i64) << (8 * i);
+    }
+    match bytes.last() {
+        Some(last) if last & 0x80 != 0 => Some(-abs_value),
+        _ => Some(abs_value),
+    }
+}
+
+/// Returns the field element of a signed `value`
+pub fn script_num_field<F: FieldExt>(value: i64) -> F {
+    if value < 0 {
+        -F::from(value.unsigned_abs())
+    }
+    else {
+        F::from(value as u64)
+    }
+}
+
+/// Returns the stack item of `value`, i.e. the RLC of its encoding
+pub fn script_num_item<F: FieldExt>(value: i64, randomness: F) -> F {
+    if value == 0 {
+        return F::from(EMPTY_ARRAY_REPRESENTATION);
+    }
+    encode_script_num(value)
+        .iter()
+        .fold(F::zero(), |acc, byte| acc * randomness + F::from(*byte as u64))
+}
+
+#[derive(Clone, Debug)]
+pub struct ScriptNumConfig<F, const N: usize> {
+    /// One in the rows where the stack item is decoded, zero otherwise
+    pub enabled: Column<Advice>,
+    /// Little-endian bytes of the number
+    pub bytes: [Column<Advice>; N],
+    /// The used bytes are a prefix of `bytes` whose length is the byte length of the number
+    pub is_byte_used: [Column<Advice>; N],
+    /// RLC of the used bytes up to and including the current byte
+    pub rlc_acc: [Column<Advice>; N],
+    pub is_negative: Column<Advice>,
+    pub first_byte_inv: Column<Advice>,
+    /// Inverse of the last byte without its sign bit
+    pub top_magnitude_inv: Column<Advice>,
+    /// One if the last byte of the number only carries the sign bit
+    pub is_top_byte_sign_only: Column<Advice>,
+    /// Signed value of the number
+    pub value: Column<Advice>,
+    /// Value of the number in the current row
+    pub value_expression: Expression<F>,
+    /// Sign of the number in the current row
+    pub is_negative_expression: Expression<F>,
+    /// Byte length of the number in the current row
+    pub len_expression: Expression<F>,
+}
+
+impl<F: FieldExt, const N: usize> ScriptNumConfig<F, N> {
+    /// Returns the value of the decoded number
+    pub fn expr(&self) -> Expression<F> {
+        self.value_expression.clone()
+    }
+
+    /// Returns the absolute value of the decoded number
+    pub fn abs_expr(&self) -> Expression<F> {
+        self.value_expression.clone() * (1u8.expr() - 2u8.expr() * self.is_negative_expression.clone())
+    }
+
+    /// Returns the byte length of the decoded number
+    pub fn len_expr(&self) -> Expression<F> {
+        self.len_expression.clone()
+    }
+}
+
+pub struct ScriptNumChip<F, const N: usize> {
+    config: ScriptNumConfig<F, N>,
+}
+
+// Expressions of the number in the current row
+struct ScriptNumExpressions<F> {
+    bytes: Vec<Expression<F>>,
+    is_byte_used: Vec<Expression<F>>,
+    is_negative: Expression<F>,
+    // The last used byte and the byte before it
+    top_byte: Expression<F>,
+    second_top_byte: Expression<F>,
+    // 256^(len-1), the weight of the last used byte
+    top_byte_weight: Expression<F>,
+}
+
+impl<F: FieldExt, const N: usize> ScriptNumChip<F, N> {
+    fn query_expressions(
+        meta: &mut VirtualCells<'_, F>,
+        bytes: [Column<Advice>; N],
+        is_byte_used: [Column<Advice>; N],
+        is_negative: Column<Advice>,
+    ) -> ScriptNumExpressions<F> {
+        let bytes: Vec<Expression<F>> = bytes.iter().map(|c| meta.query_advice(*c, Rotation::cur())).collect();
+        let mut is_byte_used: Vec<Expression<F>> =
+            is_byte_used.iter().map(|c| meta.query_advice(*c, Rotation::cur())).collect();
+        let is_negative = meta.query_advice(is_negative, Rotation::cur());
+
+        // As the used bytes are a prefix, the byte at index i is the last used byte
+        // iff is_byte_used[i] - is_byte_used[i+1] is one
+        is_byte_used.push(0u8.expr());
+        is_byte_used.push(0u8.expr());
+        let mut top_byte = 0u8.expr();
+        let mut second_top_byte = 0u8.expr();
+        let mut top_byte_weight = 0u8.expr();
+        let mut weight = F::one();
+        for i in 0..N {
+            let is_top = is_byte_used[i].clone() - is_byte_used[i+1].clone();
+            let is_second_top = is_byte_used[i+1].clone() - is_byte_used[i+2].clone();
+            top_byte = top_byte + is_top.clone() * bytes[i].clone();
+            second_top_byte = second_top_byte + is_second_top * bytes[i].clone();
+            top_byte_weight = top_byte_weight + is_top * Expression::Constant(weight);
+            weight *= F::from(256);
+        }
+        is_byte_used.truncate(N);
+
+        ScriptNumExpressions {
+            bytes,
+            is_byte_used,
+            is_negative,
+            top_byte,
+            second_top_byte,
+            top_byte_weight,
+        }
+    }
+
+    /// Configures the decoding of the stack item given by `item` in the rows where `q_enable`
+    /// is set and `enabled` is one. Bytes are range checked against `byte_table`, which must
+    /// contain all byte values. If `canonical_empty` is set, zero must be represented by
+    /// EMPTY_ARRAY_REPRESENTATION, else the zero item is also decoded as zero.
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        q_enable: Selector,
+        item: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
+        randomness: Column<Advice>,
+        byte_table: TableColumn,
+        canonical_empty: bool,
+    ) -> ScriptNumConfig<F, N> {
+        let enabled = meta.advice_column();
+        let bytes = [(); N].map(|_| meta.advice_column());
+        let is_byte_used = [(); N].map(|_| meta.advice_column());
+        let rlc_acc = [(); N].map(|_| meta.advice_column());
+        let is_negative = meta.advice_column();
+        let first_byte_inv = meta.advice_column();
+        let top_magnitude_inv = meta.advice_column();
+        let is_top_byte_sign_only = meta.advice_column();
+        let value = meta.advice_column();
+
+        // dummy initialization
+        let mut value_expression = 0u8.expr();
+        let mut is_negative_expression = 0u8.expr();
+        let mut len_expression = 0u8.expr();
+
+        meta.create_gate("Script number decoding", |meta| {
+            let q_enable = meta.query_selector(q_enable);
+            let enabled = meta.query_advice(enabled, Rotation::cur());
+            let q_decode = q_enable.clone() * enabled.clone();
+            let randomness = meta.query_advice(randomness, Rotation::cur());
+            let item = item(meta);
+            let num = Self::query_expressions(meta, bytes, is_byte_used, is_negative);
+
+            let mut constraints = vec![q_enable * enabled.clone() * (1u8.expr() - enabled)];
+
+            let mut prev_rlc_acc = 0u8.expr();
+            for i in 0..N {
+                let is_used = num.is_byte_used[i].clone();
+                let byte = num.bytes[i].clone();
+                constraints.push(q_decode.clone() * is_used.clone() * (1u8.expr() - is_used.clone()));
+                if i > 0 {
+                    // The used bytes are a prefix
+                    constraints.push(q_decode.clone() * is_used.clone() * (1u8.expr() - num.is_byte_used[i-1].clone()));
+                }
+                // Unused bytes are zero
+                constraints.push(q_decode.clone() * (1u8.expr() - is_used.clone()) * byte.clone());
+
+                // Only the used bytes are accumulated
+                let cur_rlc_acc = meta.query_advice(rlc_acc[i], Rotation::cur());
+                constraints.push(
+                    q_decode.clone()
+                    * (cur_rlc_acc.clone() - is_used.clone() * (prev_rlc_acc.clone() * randomness.clone() + byte)
+                        - (1u8.expr() - is_used) * prev_rlc_acc)
+                );
+                prev_rlc_acc = cur_rlc_acc;
+            }
+
+            len_expression = num.is_byte_used.iter().fold(0u8.expr(), |acc, is_used| acc + is_used.clone());
+            let is_empty = 1u8.expr() - num.is_byte_used[0].clone();
+            // The RLC of a non-empty number is the stack item
+            constraints.push(q_decode.clone() * num.is_byte_used[0].clone() * (prev_rlc_acc - item.clone()));
+            if canonical_empty {
+                constraints.push(q_decode.clone() * is_empty.clone() * (item - EMPTY_ARRAY_REPRESENTATION.expr()));
+            }
+            else {
+                constraints.push(
+                    q_decode.clone() * is_empty * item.clone() * (item - EMPTY_ARRAY_REPRESENTATION.expr())
+                );
+            }
+
+            // The first byte of a number is non-zero
+            let first_byte_inv = meta.query_advice(first_byte_inv, Rotation::cur());
+            constraints.push(
+                q_decode.clone()
+                * num.is_byte_used[0].clone()
+                * (1u8.expr() - num.bytes[0].clone() * first_byte_inv)
+            );
+
+            let is_negative = num.is_negative.clone();
+            is_negative_expression = is_negative.clone();
+            constraints.push(q_decode.clone() * is_negative.clone() * (1u8.expr() - is_negative.clone()));
+
+            // The last byte either has a non-zero magnitude, or it is a sign byte which is needed
+            // as the sign bit of the previous byte is set. The latter is range checked below.
+            let top_magnitude = num.top_byte.clone() - is_negative.clone() * 128u8.expr();
+            let top_magnitude_inv = meta.query_advice(top_magnitude_inv, Rotation::cur());
+            let is_sign_only = meta.query_advice(is_top_byte_sign_only, Rotation::cur());
+            constraints.push(q_decode.clone() * is_sign_only.clone() * (1u8.expr() - is_sign_only.clone()));
+            constraints.push(q_decode.clone() * is_sign_only.clone() * top_magnitude.clone());
+            constraints.push(
+                q_decode.clone()
+                * num.is_byte_used[0].clone()
+                * (1u8.expr() - is_sign_only)
+                * (1u8.expr() - top_magnitude * top_magnitude_inv)
+            );
+
+            let mut abs_value = 0u8.expr();
+            let mut weight = F::one();
+            for byte in num.bytes.iter() {
+                abs_value = abs_value + byte.clone() * Expression::Constant(weight);
+                weight *= F::from(256);
+            }
+            // Remove the sign bit from the last byte
+            abs_value = abs_value - is_negative.clone() * 128u8.expr() * num.top_byte_weight;
+            value_expression = meta.query_advice(value, Rotation::cur());
+            constraints.push(q_decode * (value_expression.clone() - abs_value.clone() + 2u8.expr() * is_negative * abs_value));
+
+            constraints
+        });
+
+        for byte in bytes {
+            meta.lookup("Script number byte range", |meta| {
+                let q_enable = meta.query_selector(q_enable);
+                let byte = meta.query_advice(byte, Rotation::cur());
+                vec![(q_enable * byte, byte_table)]
+            });
+        }
+
+        // The magnitude of the last byte is in [0, 127]. The top byte and the sign are range
+        // checked, so the magnitude is an integer in [-128, 255] and twice the magnitude is a
+        // byte iff the magnitude is in [0, 127].
+        meta.lookup("Script number sign bit", |meta| {
+            let q_enable = meta.query_selector(q_enable);
+            let num = Self::query_expressions(meta, bytes, is_byte_used, is_negative);
+            let top_magnitude = num.top_byte - num.is_negative * 128u8.expr();
+            vec![(q_enable * 2u8.expr() * top_magnitude, byte_table)]
+        });
+
+        // A sign byte is only allowed if the byte before it is in [128, 255]
+        meta.lookup("Script number sign byte", |meta| {
+            let q_enable = meta.query_selector(q_enable);
+            let is_sign_only = meta.query_advice(is_top_byte_sign_only, Rotation::cur());
+            let num = Self::query_expressions(meta, bytes, is_byte_used, is_negative);
+            vec![(q_enable * is_sign_only * 2u8.expr() * (num.second_top_byte - 128u8.expr()), byte_table)]
+        });
+
+        ScriptNumConfig {
+            enabled,
+            bytes,
+            is_byte_used,
+            rlc_acc,
+            is_negative,
+            first_byte_inv,
+            top_magnitude_inv,
+            is_top_byte_sign_only,
+            value,
+            value_expression,
+            is_negative_expression,
+            len_expression,
+        }
+    }
+
+    pub fn construct(config: ScriptNumConfig<F, N>) -> Self {
+        Self { config }
+    }
+
+    /// Assigns the decoding of `value` at `offset`. If `value` is None, the decoding is disabled
+    /// and all cells are assigned zero.
+    pub fn assign(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        randomness: F,
+        value: Option<i64>,
+    ) -> Result<(), Error> {
+        let config = self.config();
+        let encoding = value.map(encode_script_num).unwrap_or_default();
+        assert!(encoding.len() <= N);
+
+        let mut assign = |annotation: &'static str, column: Column<Advice>, val: F| -> Result<(), Error> {
+            region.assign_advice(|| annotation, column, offset, || Value::known(val))?;
+            Ok(())
+        };
+
+        assign("script number enabled", config.enabled, F::from(value.is_some() as u64))?;
+
+        let mut rlc_acc = F::zero();
+        for i in 0..N {
+            let byte = encoding.get(i).map_or(F::zero(), |b| F::from(*b as u64));
+            if i < encoding.len() {
+                rlc_acc = rlc_acc * randomness + byte;
+            }
+            assign("script number byte", config.bytes[i], byte)?;
+            assign("script number byte used", config.is_byte_used[i], F::from((i < encoding.len()) as u64))?;
+            assign("script number rlc_acc", config.rlc_acc[i], rlc_acc)?;
+        }
+
+        let is_negative = value.map_or(false, |v| v < 0);
+        assign("script number sign", config.is_negative, F::from(is_negative as u64))?;
+        assign("script number value", config.value, script_num_field(value.unwrap_or(0)))?;
+
+        let first_byte = encoding.first().map_or(F::zero(), |b| F::from(*b as u64));
+        assign("script number first byte inverse", config.first_byte_inv, first_byte.invert().unwrap_or(F::zero()))?;
+
+        let top_magnitude = encoding.last().map_or(F::zero(), |b| F::from((b & 0x7f) as u64));
+        assign("script number top magnitude inverse", config.top_magnitude_inv, top_magnitude.invert().unwrap_or(F::zero()))?;
+        let is_sign_only = !encoding.is_empty() && top_magnitude == F::zero();
+        assign("script number sign byte", config.is_top_byte_sign_only, F::from(is_sign_only as u64))?;
+
+        Ok(())
+    }
+}
+
+impl<F: FieldExt, const N: usize> Chip<F> for ScriptNumChip<F, N> {
+    type Config = ScriptNumConfig<F, N>;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_script_num, encode_script_num, MAX_SCRIPT_NUM_SIZE, MAX_SCRIPT_NUM_RESULT_SIZE};
+
+    #[test]
+    fn test_script_num_encoding() {
+        assert_eq!(encode_script_num(0), Vec::<u8>::new());
+        assert_eq!(encode_script_num(1), vec![0x01]);
+        assert_eq!(encode_script_num(-1), vec![0x81]);
+        assert_eq!(encode_script_num(127), vec![0x7f]);
+        assert_eq!(encode_script_num(128), vec![0x80, 0x00]);
+        assert_eq!(encode_script_num(-128), vec![0x80, 0x80]);
+        assert_eq!(encode_script_num(-255), vec![0xff, 0x80]);
+        assert_eq!(encode_script_num(0x7fffffff), vec![0xff, 0xff, 0xff, 0x7f]);
+        assert_eq!(encode_script_num(-0xffffffff), vec![0xff, 0xff, 0xff, 0xff, 0x80]);
+
+        for value in [0, 1, -1, 127, -127, 128, -128, 0x1234, -0x7fffffff, 0x7fffffff] {
+            assert_eq!(decode_script_num(&encode_script_num(value), MAX_SCRIPT_NUM_SIZE), Some(value));
+        }
+        let large = 0xffffffffi64;
+        assert_eq!(decode_script_num(&encode_script_num(large), MAX_SCRIPT_NUM_SIZE), None);
+        assert_eq!(decode_script_num(&encode_script_num(large), MAX_SCRIPT_NUM_RESULT_SIZE), Some(large));
+
+        // Non-minimal encodings are rejected
+        assert_eq!(decode_script_num(&[0x80], MAX_SCRIPT_NUM_SIZE), None);
+        assert_eq!(decode_script_num(&[0x00], MAX_SCRIPT_NUM_SIZE), None);
+        assert_eq!(decode_script_num(&[0x01, 0x00], MAX_SCRIPT_NUM_SIZE), None);
+    }
+}
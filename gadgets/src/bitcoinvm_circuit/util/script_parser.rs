@@ -1,9 +1,24 @@
+use halo2_proofs::circuit::Value;
+
 use super::super::constants::*;
+use super::script_num::{decode_script_num, encode_script_num, script_num_item, MAX_SCRIPT_NUM_RESULT_SIZE, MAX_SCRIPT_NUM_SIZE};
+use crate::ripemd160;
+use crate::sha1;
+use crate::sha256;
 use crate::Field;
 
-pub(crate) struct ScriptPubkeyParseState<F: Field> {
+pub(crate) struct ScriptPubkeyParseState<F: Field, const STACK_DEPTH: usize = MAX_STACK_DEPTH> {
     pub randomness: F,
-    pub stack: [F; MAX_STACK_DEPTH],
+    pub stack: [F; STACK_DEPTH],
+    pub alt_stack: [F; MAX_ALTSTACK_DEPTH],
+    // Number of items on the alt stack. OP_TOALTSTACK on a full alt stack drops its bottom item,
+    // which the circuit rejects.
+    pub alt_stack_size: u64,
+    // Number of items on the stack, which may exceed STACK_DEPTH
+    pub stack_size: F,
+    // Byte lengths of the stack items and the alt stack items, as read by OP_SIZE
+    pub stack_len: [u64; STACK_DEPTH],
+    pub alt_stack_len: [u64; MAX_ALTSTACK_DEPTH],
     pub num_data_bytes_remaining: u64,
     pub next_num_data_bytes_remaining: u64,
     pub num_data_length_bytes_remaining: u64,
@@ -11,16 +26,50 @@ pub(crate) struct ScriptPubkeyParseState<F: Field> {
     pub num_data_length_acc_constant: u64,
     pub pk_rlc_acc: F,
     pub num_checksig_opcodes: u64,
+    pub num_verified_checksigs: u64,
+    // Accumulator of the opcode, the hashed item and the digest of the hash opcodes
+    pub hash_rlc_acc: F,
+    // Opcodes and bytes of the hashed items, in the order they are accumulated in hash_rlc_acc.
+    // Items whose bytes are not known are left out.
+    pub hashed_items: Vec<(usize, Vec<u8>)>,
+    // Number of script bytes parsed so far
+    pub num_script_bytes_read: u64,
+    // Byte offset following the last executed OP_CODESEPARATOR, from which the script is covered
+    // by signatures. It is zero if no OP_CODESEPARATOR was executed.
+    pub last_codeseparator_position: u64,
+    // Bytes of the data push in progress
+    pub data_bytes: Vec<u8>,
+    // Stack items along with their byte encodings, used to decode numbers and to hash items
+    pub known_items: Vec<(F, Vec<u8>)>,
+    // Numeric operands of the current opcode, top first, and its numeric result. Operands which
+    // are not decoded, like the public key of OP_CHECKSIGADD, are None.
+    pub num_operands: Vec<Option<i64>>,
+    pub num_result: Option<i64>,
+    // Entries for the enclosing OP_IF and OP_NOTIF opcodes, innermost first
+    pub condition_stack: [u64; MAX_CONDITION_DEPTH],
+    // Number of enclosing branches which are not taken. Opcodes are only executed if it is zero.
+    pub num_false_conditions: u64,
+    // Number of public keys, number of signatures and validity of the signatures of the current
+    // opcode if it is an OP_CHECKMULTISIG
+    pub multisig: Option<(usize, usize, bool)>,
+    // Depth operand of the current opcode if it is an OP_PICK or OP_ROLL whose picked item is
+    // one of the STACK_DEPTH stack items
+    pub pick_depth: Option<usize>,
 }
 
-impl<F: Field> ScriptPubkeyParseState<F> {
+impl<F: Field, const STACK_DEPTH: usize> ScriptPubkeyParseState<F, STACK_DEPTH> {
     pub(crate) fn new(
         randomness: F,
-        initial_stack: [F; MAX_STACK_DEPTH],
+        initial_stack: [F; STACK_DEPTH],
     ) -> Self {
         Self {
             randomness,
             stack: initial_stack,
+            alt_stack: [F::zero(); MAX_ALTSTACK_DEPTH],
+            alt_stack_size: 0,
+            stack_size: initial_stack_size(&initial_stack),
+            stack_len: initial_stack_len(&initial_stack),
+            alt_stack_len: [0; MAX_ALTSTACK_DEPTH],
             num_data_bytes_remaining: 0,
             next_num_data_bytes_remaining: 0,
             num_data_length_bytes_remaining: 0,
@@ -28,13 +77,164 @@ impl<F: Field> ScriptPubkeyParseState<F> {
             num_data_length_acc_constant: 0,
             pk_rlc_acc: F::zero(),
             num_checksig_opcodes: 0,
+            num_verified_checksigs: 0,
+            hash_rlc_acc: F::zero(),
+            hashed_items: vec![],
+            num_script_bytes_read: 0,
+            last_codeseparator_position: 0,
+            data_bytes: vec![],
+            known_items: vec![],
+            num_operands: vec![],
+            num_result: None,
+            condition_stack: [0; MAX_CONDITION_DEPTH],
+            num_false_conditions: 0,
+            multisig: None,
+            pick_depth: None,
+        }
+    }
+
+    /// Returns the number encoded by a stack item, if it is a minimally encoded number
+    /// which can be used as an operand of a numeric opcode
+    pub(crate) fn script_num_value(&self, item: F) -> Option<i64> {
+        self.script_num_value_of_size(item, MAX_SCRIPT_NUM_SIZE)
+    }
+
+    /// Returns the number encoded by a stack item, if it is a minimally encoded number of at
+    /// most `max_size` bytes
+    pub(crate) fn script_num_value_of_size(&self, item: F, max_size: usize) -> Option<i64> {
+        if item == F::zero() || item == F::from(EMPTY_ARRAY_REPRESENTATION) {
+            return Some(0);
+        }
+        let bytes = self.known_items
+            .iter()
+            .rev()
+            .find(|(value, _)| *value == item)
+            .map(|(_, bytes)| bytes.clone())
+            .or_else(|| (1..256u64).find(|b| F::from(*b) == item).map(|b| vec![b as u8]))?;
+        // Numbers whose first byte is zero have the same RLC as a shorter byte vector
+        if bytes[0] == 0 {
+            return None;
+        }
+        decode_script_num(&bytes, max_size)
+    }
+
+    /// Returns the bytes of a stack item of `len` bytes, if they are known
+    pub(crate) fn item_bytes(&self, item: F, len: u64) -> Option<Vec<u8>> {
+        if len == 0 {
+            return Some(vec![]);
+        }
+        self.known_items
+            .iter()
+            .rev()
+            .find(|(value, bytes)| *value == item && bytes.len() as u64 == len)
+            .map(|(_, bytes)| bytes.clone())
+            .or_else(|| (1..256u64).find(|b| len == 1 && F::from(*b) == item).map(|b| vec![b as u8]))
+    }
+
+    // Replaces the top item by its digest under the hash function of the opcode. The opcode, the
+    // length and the RLC of the item and the RLC of the digest are accumulated in hash_rlc_acc for
+    // a hash gadget to check. If the bytes of the item are not known, the digest is zero and the
+    // script cannot be proven.
+    fn apply_hash_op(&mut self, opcode: usize) {
+        let (item, len) = (self.stack[0], self.stack_len[0]);
+        let digest = match self.item_bytes(item, len) {
+            Some(bytes) => {
+                let digest = hash_digest(opcode, &bytes);
+                let digest_item = bytes_item(&digest, self.randomness);
+                self.known_items.push((digest_item, digest));
+                self.hashed_items.push((opcode, bytes));
+                digest_item
+            }
+            None => F::zero(),
+        };
+        for value in [F::from(opcode as u64), F::from(len), item, digest] {
+            self.hash_rlc_acc = self.hash_rlc_acc * self.randomness + value;
         }
+        self.stack[0] = digest;
+    }
+
+    // Pops `num_operands` numbers and pushes the result of `op` on them. If an operand is not a
+    // valid number, the result is zero and the script cannot be proven.
+    fn apply_numeric_op(&mut self, num_operands: usize, op: impl Fn(&[i64]) -> i64) {
+        let operands: Option<Vec<i64>> = (0..num_operands)
+            .map(|i| self.script_num_value(self.stack[i]))
+            .collect();
+        self.num_result = operands.as_ref().map(|operands| op(operands));
+        self.num_operands = operands.unwrap_or_default().into_iter().map(Some).collect();
+
+        for i in num_operands..STACK_DEPTH {
+            self.stack[i+1-num_operands] = self.stack[i];
+        }
+        for i in STACK_DEPTH+1-num_operands..STACK_DEPTH {
+            self.stack[i] = F::zero();
+        }
+        self.push_num_result();
+    }
+
+    // Replaces the stack top with the numeric result of the current opcode
+    fn push_num_result(&mut self) {
+        self.stack[0] = match self.num_result {
+            Some(value) => {
+                let item = script_num_item(value, self.randomness);
+                self.known_items.push((item, encode_script_num(value)));
+                item
+            }
+            None => F::zero(),
+        };
+    }
+
+    fn accumulate_data_byte(&mut self, byte: u8) {
+        self.stack[0] = F::from(byte as u64) + self.randomness * self.stack[0];
+        self.data_bytes.push(byte);
+        self.stack_len[0] += 1;
+        self.known_items.push((self.stack[0], self.data_bytes.clone()));
     }
 
     pub(crate) fn update(
         &mut self,
         opcode: u8,
     ) -> () {
+        // Opcodes and data bytes in a branch which is not taken are parsed, but they do not
+        // change the stack
+        let is_branch_active = self.num_false_conditions == 0;
+        let (stack, alt_stack, alt_stack_size, stack_size) =
+            (self.stack, self.alt_stack, self.alt_stack_size, self.stack_size);
+        let (stack_len, alt_stack_len) = (self.stack_len, self.alt_stack_len);
+        let (pk_rlc_acc, num_checksig_opcodes, num_verified_checksigs) =
+            (self.pk_rlc_acc, self.num_checksig_opcodes, self.num_verified_checksigs);
+        let hash_rlc_acc = self.hash_rlc_acc;
+        let last_codeseparator_position = self.last_codeseparator_position;
+
+        self.num_script_bytes_read += 1;
+        self.execute(opcode);
+
+        if !is_branch_active {
+            self.stack = stack;
+            self.alt_stack = alt_stack;
+            self.alt_stack_size = alt_stack_size;
+            self.stack_size = stack_size;
+            self.stack_len = stack_len;
+            self.alt_stack_len = alt_stack_len;
+            self.pk_rlc_acc = pk_rlc_acc;
+            self.num_checksig_opcodes = num_checksig_opcodes;
+            self.num_verified_checksigs = num_verified_checksigs;
+            self.hash_rlc_acc = hash_rlc_acc;
+            self.last_codeseparator_position = last_codeseparator_position;
+            self.num_operands.clear();
+            self.num_result = None;
+            self.multisig = None;
+            self.pick_depth = None;
+        }
+    }
+
+    fn execute(
+        &mut self,
+        opcode: u8,
+    ) {
+        self.num_operands.clear();
+        self.num_result = None;
+        self.multisig = None;
+        self.pick_depth = None;
         let opcode = opcode as usize;
         let (a,b,c,d) = (
             self.num_data_bytes_remaining,
@@ -46,21 +246,31 @@ impl<F: Field> ScriptPubkeyParseState<F> {
                 if self.num_data_bytes_remaining == 1 {
                     self.num_data_bytes_remaining = 0;
                 }
+                let (prev_stack_size, prev_stack_len, prev_alt_stack_len) =
+                    (self.stack_size, self.stack_len, self.alt_stack_len);
+                let delta = stack_size_delta(opcode);
+                if delta >= 0 {
+                    self.stack_size += F::from(delta as u64);
+                }
+                else {
+                    self.stack_size -= F::from(delta.unsigned_abs());
+                }
                 if opcode == OP_0 {
-                    for i in (1..MAX_STACK_DEPTH).rev() {
+                    for i in (1..STACK_DEPTH).rev() {
                         self.stack[i] = self.stack[i-1];
                     }
-                    self.stack[0] = F::from(256u64);
+                    self.stack[0] = F::from(EMPTY_ARRAY_REPRESENTATION);
                 }
                 else if opcode >= OP_1 && opcode <= OP_16 {
-                    for i in (1..MAX_STACK_DEPTH).rev() {
+                    for i in (1..STACK_DEPTH).rev() {
                         self.stack[i] = self.stack[i-1];
                     }
                     self.stack[0] = F::from((opcode - OP_RESERVED) as u64);
                 }
                 else if opcode >= OP_PUSH_NEXT1 && opcode <= OP_PUSH_NEXT75 {
                    self.next_num_data_bytes_remaining = opcode as u64; 
-                    for i in (1..MAX_STACK_DEPTH).rev() {
+                    self.data_bytes.clear();
+                    for i in (1..STACK_DEPTH).rev() {
                         self.stack[i] = self.stack[i-1];
                     }
                     self.stack[0] = F::zero();
@@ -68,27 +278,337 @@ impl<F: Field> ScriptPubkeyParseState<F> {
                 else if opcode >= OP_PUSHDATA1 && opcode <= OP_PUSHDATA4 {
                     self.next_num_data_length_bytes_remaining = 1u64 << (opcode - OP_PUSHDATA1);
                     self.num_data_bytes_remaining = 0;
-                    for i in (1..MAX_STACK_DEPTH).rev() {
+                    self.data_bytes.clear();
+                    for i in (1..STACK_DEPTH).rev() {
                         self.stack[i] = self.stack[i-1];
                     }
                     self.stack[0] = F::zero();
                 }
+                else if opcode == OP_TOALTSTACK {
+                    for i in (1..MAX_ALTSTACK_DEPTH).rev() {
+                        self.alt_stack[i] = self.alt_stack[i-1];
+                    }
+                    self.alt_stack[0] = self.stack[0];
+                    self.alt_stack_size += 1;
+                    for i in 1..STACK_DEPTH {
+                        self.stack[i-1] = self.stack[i];
+                    }
+                    self.stack[STACK_DEPTH-1] = F::zero();
+                }
+                else if opcode == OP_FROMALTSTACK {
+                    for i in (1..STACK_DEPTH).rev() {
+                        self.stack[i] = self.stack[i-1];
+                    }
+                    self.stack[0] = self.alt_stack[0];
+                    for i in 1..MAX_ALTSTACK_DEPTH {
+                        self.alt_stack[i-1] = self.alt_stack[i];
+                    }
+                    self.alt_stack[MAX_ALTSTACK_DEPTH-1] = F::zero();
+                    self.alt_stack_size = self.alt_stack_size.saturating_sub(1);
+                }
+                else if opcode == OP_EQUAL {
+                    // Stack items are compared by their RLC values
+                    let result = if self.stack[0] == self.stack[1] {
+                        F::one()
+                    }
+                    else {
+                        F::from(EMPTY_ARRAY_REPRESENTATION)
+                    };
+                    for i in 2..STACK_DEPTH {
+                        self.stack[i-1] = self.stack[i];
+                    }
+                    self.stack[STACK_DEPTH-1] = F::zero();
+                    self.stack[0] = result;
+                }
+                else if opcode == OP_EQUALVERIFY {
+                    // The circuit rejects scripts where the top two items differ, so both are popped
+                    for i in 2..STACK_DEPTH {
+                        self.stack[i-2] = self.stack[i];
+                    }
+                    self.stack[STACK_DEPTH-2] = F::zero();
+                    self.stack[STACK_DEPTH-1] = F::zero();
+                }
+                else if opcode == OP_VERIFY {
+                    // The circuit rejects scripts where the top item is false, so it is popped
+                    for i in 1..STACK_DEPTH {
+                        self.stack[i-1] = self.stack[i];
+                    }
+                    self.stack[STACK_DEPTH-1] = F::zero();
+                }
+                else if opcode == OP_DROP || opcode == OP_2DROP {
+                    let num_dropped = if opcode == OP_DROP { 1 } else { 2 };
+                    for i in num_dropped..STACK_DEPTH {
+                        self.stack[i-num_dropped] = self.stack[i];
+                    }
+                    for i in STACK_DEPTH-num_dropped..STACK_DEPTH {
+                        self.stack[i] = F::zero();
+                    }
+                }
+                else if opcode == OP_SWAP {
+                    self.stack.swap(0, 1);
+                }
+                else if opcode == OP_OVER {
+                    let second = self.stack[1];
+                    for i in (1..STACK_DEPTH).rev() {
+                        self.stack[i] = self.stack[i-1];
+                    }
+                    self.stack[0] = second;
+                }
+                else if opcode == OP_ROT {
+                    // The third item is moved to the top
+                    self.stack[0..3].rotate_right(1);
+                }
+                else if opcode == OP_NIP {
+                    for i in 2..STACK_DEPTH {
+                        self.stack[i-1] = self.stack[i];
+                    }
+                    self.stack[STACK_DEPTH-1] = F::zero();
+                }
+                else if opcode == OP_TUCK {
+                    // The top item is copied below the second item
+                    for i in (3..STACK_DEPTH).rev() {
+                        self.stack[i] = self.stack[i-1];
+                    }
+                    self.stack[2] = self.stack[0];
+                }
+                else if opcode == OP_PICK || opcode == OP_ROLL {
+                    // The depth n is popped and the item below the next n items is copied to the
+                    // top or moved there. Depths beyond the stack columns are rejected by the circuit.
+                    let depth = self.script_num_value(self.stack[0]);
+                    self.num_operands = vec![depth];
+                    self.pick_depth = depth
+                        .and_then(|n| usize::try_from(n).ok())
+                        .filter(|n| n + 2 <= STACK_DEPTH);
+                    let index = self.pick_depth.unwrap_or(0) + 1;
+                    let item = self.stack[index];
+                    if opcode == OP_ROLL {
+                        for i in index..STACK_DEPTH-1 {
+                            self.stack[i] = self.stack[i+1];
+                        }
+                        self.stack[STACK_DEPTH-1] = F::zero();
+                    }
+                    self.stack[0] = item;
+                }
+                else if (OP_2DUP..=OP_2SWAP).contains(&opcode) {
+                    // The top items are copied and reordered and the others are shifted
+                    let (num_popped, pushed) = stack_item_moves(opcode);
+                    let prev_stack = self.stack;
+                    for (i, item) in self.stack.iter_mut().enumerate() {
+                        *item = match pushed.get(i) {
+                            Some(Some(index)) => prev_stack[*index],
+                            Some(None) => unreachable!("Opcode {:#x} pushes a new item", opcode),
+                            None => prev_stack[i + num_popped - pushed.len()],
+                        };
+                    }
+                }
+                else if opcode == OP_DEPTH {
+                    // The stack size before the push is pushed as a number
+                    self.num_result = Some(prev_stack_size.get_lower_32() as i64);
+                    for i in (1..STACK_DEPTH).rev() {
+                        self.stack[i] = self.stack[i-1];
+                    }
+                    self.push_num_result();
+                }
+                else if opcode == OP_SIZE {
+                    // The length of the top item is pushed as a number without popping the item.
+                    // An unknown length cannot be pushed and the script cannot be proven.
+                    self.num_result = (self.stack_len[0] != UNKNOWN_ITEM_LENGTH).then(|| self.stack_len[0] as i64);
+                    for i in (1..STACK_DEPTH).rev() {
+                        self.stack[i] = self.stack[i-1];
+                    }
+                    self.push_num_result();
+                }
+                else if opcode == OP_ADD {
+                    self.apply_numeric_op(2, |operands| operands[1] + operands[0]);
+                }
+                else if opcode == OP_SUB {
+                    // The top item is subtracted from the second item
+                    self.apply_numeric_op(2, |operands| operands[1] - operands[0]);
+                }
+                else if opcode == OP_1ADD {
+                    self.apply_numeric_op(1, |operands| operands[0] + 1);
+                }
+                else if opcode == OP_1SUB {
+                    self.apply_numeric_op(1, |operands| operands[0] - 1);
+                }
+                else if opcode == OP_NEGATE {
+                    self.apply_numeric_op(1, |operands| -operands[0]);
+                }
+                else if opcode == OP_ABS {
+                    self.apply_numeric_op(1, |operands| operands[0].abs());
+                }
+                else if opcode == OP_IF || opcode == OP_NOTIF {
+                    // The condition is popped. In a branch which is not taken, the new branch is
+                    // not taken either and the stack is restored by update().
+                    let is_true = is_item_true(self.stack[0]);
+                    let is_taken = self.num_false_conditions == 0 && is_true == (opcode == OP_IF);
+                    for i in 1..STACK_DEPTH {
+                        self.stack[i-1] = self.stack[i];
+                    }
+                    self.stack[STACK_DEPTH-1] = F::zero();
+
+                    // Scripts nested deeper than MAX_CONDITION_DEPTH are rejected by the circuit
+                    self.condition_stack.rotate_right(1);
+                    self.condition_stack[0] = if is_taken { BRANCH_TAKEN } else { BRANCH_NOT_TAKEN };
+                    if !is_taken {
+                        self.num_false_conditions += 1;
+                    }
+                }
+                else if opcode == OP_ELSE {
+                    if self.condition_stack[0] == BRANCH_TAKEN {
+                        self.condition_stack[0] = BRANCH_NOT_TAKEN;
+                        self.num_false_conditions += 1;
+                    }
+                    else if self.condition_stack[0] == BRANCH_NOT_TAKEN {
+                        self.condition_stack[0] = BRANCH_TAKEN;
+                        self.num_false_conditions -= 1;
+                    }
+                }
+                else if opcode == OP_ENDIF {
+                    if self.condition_stack[0] == BRANCH_NOT_TAKEN {
+                        self.num_false_conditions -= 1;
+                    }
+                    self.condition_stack.rotate_left(1);
+                    self.condition_stack[MAX_CONDITION_DEPTH-1] = 0;
+                }
+                else if opcode == OP_NOT || opcode == OP_0NOTEQUAL {
+                    let is_true = is_item_true(self.stack[0]);
+                    self.stack[0] = bool_item(is_true == (opcode == OP_0NOTEQUAL));
+                }
+                else if opcode == OP_BOOLAND || opcode == OP_BOOLOR {
+                    let (a, b) = (is_item_true(self.stack[1]), is_item_true(self.stack[0]));
+                    let result = if opcode == OP_BOOLAND { a && b } else { a || b };
+                    for i in 2..STACK_DEPTH {
+                        self.stack[i-1] = self.stack[i];
+                    }
+                    self.stack[STACK_DEPTH-1] = F::zero();
+                    self.stack[0] = bool_item(result);
+                }
+                // Comparisons push 1 if true and the empty array otherwise
+                else if opcode == OP_LESSTHAN {
+                    self.apply_numeric_op(2, |operands| (operands[1] < operands[0]) as i64);
+                }
+                else if opcode == OP_GREATERTHAN {
+                    self.apply_numeric_op(2, |operands| (operands[1] > operands[0]) as i64);
+                }
+                else if opcode == OP_LESSTHANOREQUAL {
+                    self.apply_numeric_op(2, |operands| (operands[1] <= operands[0]) as i64);
+                }
+                else if opcode == OP_GREATERTHANOREQUAL {
+                    self.apply_numeric_op(2, |operands| (operands[1] >= operands[0]) as i64);
+                }
+                else if opcode == OP_MIN {
+                    self.apply_numeric_op(2, |operands| operands[1].min(operands[0]));
+                }
+                else if opcode == OP_MAX {
+                    self.apply_numeric_op(2, |operands| operands[1].max(operands[0]));
+                }
+                else if opcode == OP_WITHIN {
+                    // The third item is checked to be in the range [second item, top item)
+                    self.apply_numeric_op(3, |operands| (operands[1]..operands[0]).contains(&operands[2]) as i64);
+                }
+                else if opcode == OP_NUMEQUAL {
+                    self.apply_numeric_op(2, |operands| (operands[1] == operands[0]) as i64);
+                }
+                else if opcode == OP_RIPEMD160
+                || opcode == OP_SHA1
+                || opcode == OP_SHA256
+                || opcode == OP_HASH160
+                || opcode == OP_HASH256 {
+                    self.apply_hash_op(opcode);
+                }
+                else if opcode == OP_CHECKLOCKTIMEVERIFY {
+                    // The locktime is decoded for the comparison with the nLockTime and is not popped
+                    self.num_operands = vec![self.script_num_value(self.stack[0])];
+                }
+                else if opcode == OP_CODESEPARATOR {
+                    // Signatures cover the script from the byte after the OP_CODESEPARATOR
+                    self.last_codeseparator_position = self.num_script_bytes_read;
+                }
+                else if opcode == OP_CHECKSEQUENCEVERIFY {
+                    // The relative locktime is decoded for the comparison with the nSequence and is
+                    // not popped. As its disable flag is bit 31, it is decoded like a result of up
+                    // to 5 bytes.
+                    self.num_result = self.script_num_value_of_size(self.stack[0], MAX_SCRIPT_NUM_RESULT_SIZE);
+                }
                 else if opcode == OP_CHECKSIG {
-                    self.pk_rlc_acc = self.pk_rlc_acc * self.randomness + self.stack[0];
+                    // Only public keys with a valid signature are accumulated and verified
+                    if self.stack[1] == F::one() {
+                        self.pk_rlc_acc = self.pk_rlc_acc * self.randomness + self.stack[0];
+                        self.num_verified_checksigs += 1;
+                    }
                     self.stack[0] = self.stack[1]; // Signature is assumed to be F::zero or F::one
                     // Shift stack elements on step to the left (up)
-                    for i in 2..MAX_STACK_DEPTH {
+                    for i in 2..STACK_DEPTH {
                         self.stack[i-1] = self.stack[i];
                     }
                     // Last element is forced to be zero
-                    self.stack[MAX_STACK_DEPTH-1] = F::zero();
+                    self.stack[STACK_DEPTH-1] = F::zero();
                     // Increment num_checksig_opcodes
                     self.num_checksig_opcodes += 1;
                 }
+                else if opcode == OP_CHECKSIGVERIFY {
+                    // Scripts with an invalid signature are rejected by the circuit
+                    if self.stack[1] == F::one() {
+                        self.pk_rlc_acc = self.pk_rlc_acc * self.randomness + self.stack[0];
+                        self.num_verified_checksigs += 1;
+                    }
+                    // The public key and the signature are popped
+                    for i in 2..STACK_DEPTH {
+                        self.stack[i-2] = self.stack[i];
+                    }
+                    self.stack[STACK_DEPTH-2] = F::zero();
+                    self.stack[STACK_DEPTH-1] = F::zero();
+                    self.num_checksig_opcodes += 1;
+                }
+                else if opcode == OP_CHECKSIGADD {
+                    // The public key is on top of the number n and the signature
+                    let is_sig_valid = self.stack[2] == F::one();
+                    if is_sig_valid {
+                        self.pk_rlc_acc = self.pk_rlc_acc * self.randomness + self.stack[0];
+                        self.num_verified_checksigs += 1;
+                    }
+                    let n = self.script_num_value(self.stack[1]);
+                    self.num_operands = vec![None, n];
+                    self.num_result = n.map(|n| n + is_sig_valid as i64);
+                    for i in 3..STACK_DEPTH {
+                        self.stack[i-2] = self.stack[i];
+                    }
+                    self.stack[STACK_DEPTH-2] = F::zero();
+                    self.stack[STACK_DEPTH-1] = F::zero();
+                    self.push_num_result();
+                    self.num_checksig_opcodes += 1;
+                }
+                else if opcode == OP_CHECKMULTISIG {
+                    // Scripts with invalid counts are rejected by the circuit
+                    if let Some((n, m, are_sigs_valid)) = multisig_counts(&self.stack) {
+                        if are_sigs_valid {
+                            // The circuit accepts any m of the n public keys. The witness signs the keys
+                            // closest to the stack top, which are accumulated in the order they were pushed
+                            for i in (1..=m).rev() {
+                                self.pk_rlc_acc = self.pk_rlc_acc * self.randomness + self.stack[i];
+                            }
+                            self.num_verified_checksigs += m as u64;
+                        }
+                        // n, the public keys, m, the signatures and the extra item are replaced by the result
+                        let num_popped = n + m + 3;
+                        for i in num_popped..STACK_DEPTH {
+                            self.stack[i+1-num_popped] = self.stack[i];
+                        }
+                        for i in STACK_DEPTH+1-num_popped..STACK_DEPTH {
+                            self.stack[i] = F::zero();
+                        }
+                        self.stack[0] = F::from(are_sigs_valid as u64);
+                        self.stack_size -= F::from((num_popped - 1) as u64);
+                        self.num_checksig_opcodes += n as u64;
+                        self.multisig = Some((n, m, are_sigs_valid));
+                    }
+                }
+                self.move_stack_len(opcode, prev_stack_len, prev_alt_stack_len);
         }
         else if self.next_num_data_bytes_remaining > 0 && self.num_data_bytes_remaining == 0 {
             // Accumulate data byte into stack top
-            self.stack[0] = F::from(opcode as u64) + self.randomness * self.stack[0];
+            self.accumulate_data_byte(opcode as u8);
             // Replace num_data_bytes_remaining
             self.num_data_bytes_remaining = self.next_num_data_bytes_remaining;
             self.next_num_data_bytes_remaining = 0;
@@ -96,13 +616,13 @@ impl<F: Field> ScriptPubkeyParseState<F> {
         }
         else if self.num_data_bytes_remaining > 0 && self.num_data_length_bytes_remaining == 0 {
             // Accumulate data byte into stack top
-            self.stack[0] = F::from(opcode as u64) + self.randomness * self.stack[0];
+            self.accumulate_data_byte(opcode as u8);
             // Decrement number of remaining data bytes
             self.num_data_bytes_remaining -= 1;
         }
         else if self.num_data_bytes_remaining > 0 && self.num_data_length_bytes_remaining == 1 {
             // Accumulate data byte into stack top
-            self.stack[0] = F::from(opcode as u64) + self.randomness * self.stack[0];
+            self.accumulate_data_byte(opcode as u8);
             // Decrement number of remaining data length bytes
             self.num_data_length_bytes_remaining = 0;
         }
@@ -133,13 +653,424 @@ impl<F: Field> ScriptPubkeyParseState<F> {
             }
         }
     }
-    
+
+    // Moves the lengths of the stack items like the items moved by the executed opcode and sets
+    // the length of the pushed item
+    fn move_stack_len(
+        &mut self,
+        opcode: usize,
+        prev_stack_len: [u64; STACK_DEPTH],
+        prev_alt_stack_len: [u64; MAX_ALTSTACK_DEPTH],
+    ) {
+        let (num_popped, pushed) = match self.multisig {
+            Some((n, m, _)) if opcode == OP_CHECKMULTISIG => (n + m + 3, vec![None]),
+            _ if opcode == OP_PICK || opcode == OP_ROLL => pick_item_moves(opcode, self.pick_depth.unwrap_or(0)),
+            _ => stack_item_moves(opcode),
+        };
+        let new_item_len = if opcode == OP_0 || (OP_PUSH_NEXT1..=OP_PUSHDATA4).contains(&opcode) {
+            // Data bytes are counted as they are accumulated
+            0
+        }
+        else if (OP_1..=OP_16).contains(&opcode) {
+            1
+        }
+        else if opcode == OP_FROMALTSTACK {
+            prev_alt_stack_len[0]
+        }
+        else if let Some(size) = hash_digest_size(opcode) {
+            size
+        }
+        else if let Some(value) = self.num_result {
+            encode_script_num(value).len() as u64
+        }
+        else {
+            // Boolean results are 1 or the empty array and signature results are 1 or 0
+            (self.stack[0] == F::one()) as u64
+        };
+        for (i, len) in self.stack_len.iter_mut().enumerate() {
+            let j = i + num_popped;
+            *len = match pushed.get(i) {
+                Some(Some(index)) => prev_stack_len[*index],
+                Some(None) => new_item_len,
+                None if j - pushed.len() < STACK_DEPTH => prev_stack_len[j - pushed.len()],
+                None => 0,
+            };
+        }
+
+        if opcode == OP_TOALTSTACK {
+            for i in (1..MAX_ALTSTACK_DEPTH).rev() {
+                self.alt_stack_len[i] = self.alt_stack_len[i-1];
+            }
+            self.alt_stack_len[0] = prev_stack_len[0];
+        }
+        else if opcode == OP_FROMALTSTACK {
+            for i in 1..MAX_ALTSTACK_DEPTH {
+                self.alt_stack_len[i-1] = self.alt_stack_len[i];
+            }
+            self.alt_stack_len[MAX_ALTSTACK_DEPTH-1] = 0;
+        }
+    }
+}
+
+/// An item of the final stack reconstructed from a scriptPubkey
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StackItem<F: Field> {
+    /// The empty byte vector pushed by OP_0
+    Empty,
+    /// The field element representation of the item used in the circuit
+    Value(F),
+}
+
+impl<F: Field> StackItem<F> {
+    /// Returns true if the item evaluates to true, with the same rule as the circuit
+    pub fn is_true(&self) -> bool {
+        match self {
+            StackItem::Empty => false,
+            StackItem::Value(v) => is_item_true(*v),
+        }
+    }
+}
+
+/// Runs the scriptPubkey on the initial stack and returns the final stack, top first.
+/// If `decode_empty_array` is true, the internal EMPTY_ARRAY_REPRESENTATION is emitted as
+/// the empty byte vector of Bitcoin instead of the negative zero.
+pub fn final_stack<F: Field, const STACK_DEPTH: usize>(
+    script_pubkey: &[u8],
+    randomness: F,
+    initial_stack: [F; STACK_DEPTH],
+    decode_empty_array: bool,
+) -> Vec<StackItem<F>> {
+    let mut parse_state = ScriptPubkeyParseState::new(randomness, initial_stack);
+    for opcode in script_pubkey.iter() {
+        parse_state.update(*opcode);
+    }
+    parse_state.stack
+        .iter()
+        .map(|v| {
+            if decode_empty_array && *v == F::from(EMPTY_ARRAY_REPRESENTATION) {
+                StackItem::Empty
+            }
+            else {
+                StackItem::Value(*v)
+            }
+        })
+        .collect()
+}
+
+/// Runs the scriptPubkey on the initial stack and returns the hash opcodes and the bytes of
+/// the items they hash, in the order they are accumulated in hash_rlc_acc
+pub(crate) fn hash_inputs<F: Field, const STACK_DEPTH: usize>(
+    script_pubkey: &[u8],
+    randomness: F,
+    initial_stack: [F; STACK_DEPTH],
+) -> Vec<(usize, Vec<u8>)> {
+    let mut parse_state = ScriptPubkeyParseState::new(randomness, initial_stack);
+    for opcode in script_pubkey.iter() {
+        parse_state.update(*opcode);
+    }
+    parse_state.hashed_items
+}
+
+/// Size in bytes of the digest pushed by a hash opcode, or `None` for other opcodes
+pub(crate) fn hash_digest_size(opcode: usize) -> Option<u64> {
+    if opcode == OP_RIPEMD160 || opcode == OP_HASH160 {
+        Some(ripemd160::ref_impl::constants::DIGEST_SIZE_BYTES as u64)
+    }
+    else if opcode == OP_SHA1 {
+        Some(sha1::ref_impl::DIGEST_SIZE_BYTES as u64)
+    }
+    else if opcode == OP_SHA256 || opcode == OP_HASH256 {
+        Some(sha256::ref_impl::DIGEST_SIZE_BYTES as u64)
+    }
+    else {
+        None
+    }
+}
+
+/// Digest of the bytes under the hash function of a hash opcode
+pub(crate) fn hash_digest(opcode: usize, bytes: &[u8]) -> Vec<u8> {
+    match opcode {
+        OP_RIPEMD160 => ripemd160::ref_impl::ripemd160::hash(bytes.to_vec()).to_vec(),
+        OP_SHA1 => sha1::ref_impl::hash(bytes.to_vec()).to_vec(),
+        OP_SHA256 => sha256::ref_impl::hash(bytes.to_vec()).to_vec(),
+        OP_HASH160 => ripemd160::ref_impl::ripemd160::hash(sha256::ref_impl::hash(bytes.to_vec()).to_vec()).to_vec(),
+        OP_HASH256 => sha256::ref_impl::hash(sha256::ref_impl::hash(bytes.to_vec()).to_vec()).to_vec(),
+        _ => panic!("Opcode {:#x} is not a hash opcode", opcode),
+    }
+}
+
+/// Field element representation of a non-empty byte vector, i.e. the RLC of its bytes
+pub(crate) fn bytes_item<F: Field>(bytes: &[u8], randomness: F) -> F {
+    bytes.iter().fold(F::zero(), |acc, byte| acc * randomness + F::from(*byte as u64))
+}
+
+// The RIPEMD-160 gadget takes little-endian words of padded blocks while stack items are RLCs
+// of their bytes, which cannot be inverted. The bytes of a hashed item are recovered from the
+// pushes and digests of the script with `ScriptPubkeyParseState::item_bytes`, and the item is
+// looked up by its RLC and its length, as an RLC alone does not tell leading zero bytes apart.
+// The bytes are padded and packed into the gadget input by `ripemd160_blocks`. The digest words
+// in the `DigestByteOrder::Internal` byte order are little-endian words of the digest bytes as
+// they appear in scripts, so `ripemd160_digest_item` maps the gadget output back to the item
+// pushed by OP_RIPEMD160.
+
+/// Padded RIPEMD-160 blocks of the bytes of a stack item, the input of the RIPEMD-160 gadget
+pub(crate) fn ripemd160_blocks(
+    bytes: &[u8],
+) -> Vec<[ripemd160::table16::BlockWord; ripemd160::ref_impl::constants::BLOCK_SIZE]> {
+    use ripemd160::ref_impl::constants::{BLOCK_SIZE, BLOCK_SIZE_BYTES};
+    ripemd160::ref_impl::ripemd160::pad_message_bytes(bytes.to_vec())
+        .into_iter()
+        .map(ripemd160::table16::util::convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
+        .collect()
+}
+
+/// Stack item pushed by OP_RIPEMD160 for a digest of the RIPEMD-160 gadget in the
+/// `DigestByteOrder::Internal` byte order
+pub(crate) fn ripemd160_digest_item<F: Field>(
+    digest: &ripemd160::RIPEMD160Digest<ripemd160::table16::BlockWord>,
+    randomness: F,
+) -> Value<F> {
+    digest.to_le_bytes().map(|bytes| bytes_item(&bytes, randomness))
+}
+
+// Stack items are false iff they are zero or the negative zero
+fn is_item_true<F: Field>(item: F) -> bool {
+    item != F::zero() && item != F::from(NEGATIVE_ZERO)
+}
+
+// Booleans are pushed as 1 or the empty array
+fn bool_item<F: Field>(value: bool) -> F {
+    if value {
+        F::one()
+    }
+    else {
+        F::from(EMPTY_ARRAY_REPRESENTATION)
+    }
+}
+
+/// Number of items of the initial stack. As empty stack slots are zero, the items below the
+/// deepest non-zero item are not counted. Zero items above it, e.g. the flags of invalid
+/// signatures, are counted.
+pub(crate) fn initial_stack_size<F: Field>(initial_stack: &[F]) -> F {
+    let size = initial_stack
+        .iter()
+        .rposition(|v| *v != F::zero())
+        .map_or(0, |i| i + 1);
+    F::from(size as u64)
+}
+
+/// Byte lengths of the items of the initial stack. The length of an item cannot be told from its
+/// RLC, so items are given UNKNOWN_ITEM_LENGTH, on which OP_SIZE and the hash opcodes fail.
+/// Stacks left by a scriptSig have the exact lengths.
+pub(crate) fn initial_stack_len<F: Field, const STACK_DEPTH: usize>(initial_stack: &[F; STACK_DEPTH]) -> [u64; STACK_DEPTH] {
+    let stack_size = initial_stack.iter().rposition(|v| *v != F::zero()).map_or(0, |i| i + 1);
+    let mut stack_len = [0; STACK_DEPTH];
+    stack_len[..stack_size].fill(UNKNOWN_ITEM_LENGTH);
+    stack_len
+}
+
+/// Number of items popped by an opcode and the items it pushes, top first. A pushed item is either
+/// the popped item at the given index or a new item. The items popped by OP_CHECKMULTISIG depend
+/// on the stack and are not included.
+pub(crate) fn stack_item_moves(opcode: usize) -> (usize, Vec<Option<usize>>) {
+    if opcode == OP_0
+    || (OP_1..=OP_16).contains(&opcode)
+    || (OP_PUSH_NEXT1..=OP_PUSHDATA4).contains(&opcode)
+    || opcode == OP_DEPTH
+    || opcode == OP_FROMALTSTACK
+    || opcode == OP_SIZE {
+        (0, vec![None])
+    }
+    else if opcode == OP_NOT
+    || opcode == OP_0NOTEQUAL
+    || opcode == OP_RIPEMD160
+    || opcode == OP_SHA1
+    || opcode == OP_SHA256
+    || opcode == OP_HASH160
+    || opcode == OP_HASH256
+    || (OP_1ADD..=OP_1SUB).contains(&opcode)
+    || (OP_NEGATE..=OP_ABS).contains(&opcode) {
+        (1, vec![None])
+    }
+    else if opcode == OP_CHECKSIG
+    || opcode == OP_EQUAL
+    || opcode == OP_ADD
+    || opcode == OP_SUB
+    || opcode == OP_BOOLAND
+    || opcode == OP_BOOLOR
+    || opcode == OP_NUMEQUAL
+    || (OP_LESSTHAN..=OP_MAX).contains(&opcode) {
+        (2, vec![None])
+    }
+    else if opcode == OP_CHECKSIGADD || opcode == OP_WITHIN {
+        (3, vec![None])
+    }
+    else if opcode == OP_IF
+    || opcode == OP_NOTIF
+    || opcode == OP_VERIFY
+    || opcode == OP_DROP
+    || opcode == OP_TOALTSTACK {
+        (1, vec![])
+    }
+    else if opcode == OP_2DROP || opcode == OP_EQUALVERIFY || opcode == OP_CHECKSIGVERIFY {
+        (2, vec![])
+    }
+    else if opcode == OP_SWAP {
+        (2, vec![Some(1), Some(0)])
+    }
+    else if opcode == OP_OVER {
+        (2, vec![Some(1), Some(0), Some(1)])
+    }
+    else if opcode == OP_ROT {
+        (3, vec![Some(2), Some(0), Some(1)])
+    }
+    else if opcode == OP_NIP {
+        (2, vec![Some(0)])
+    }
+    else if opcode == OP_TUCK {
+        (2, vec![Some(0), Some(1), Some(0)])
+    }
+    else if opcode == OP_2DUP {
+        (2, vec![Some(0), Some(1), Some(0), Some(1)])
+    }
+    else if opcode == OP_3DUP {
+        (3, vec![Some(0), Some(1), Some(2), Some(0), Some(1), Some(2)])
+    }
+    else if opcode == OP_2OVER {
+        (4, vec![Some(2), Some(3), Some(0), Some(1), Some(2), Some(3)])
+    }
+    else if opcode == OP_2SWAP {
+        (4, vec![Some(2), Some(3), Some(0), Some(1)])
+    }
+    else if opcode == OP_2ROT {
+        (6, vec![Some(4), Some(5), Some(0), Some(1), Some(2), Some(3)])
+    }
+    else {
+        (0, vec![])
+    }
+}
+
+/// Items moved by an OP_PICK or OP_ROLL with the depth operand `depth`, like in stack_item_moves.
+/// The depth and the items above the picked item are pushed back below the picked item, which
+/// OP_PICK also leaves in its place.
+pub(crate) fn pick_item_moves(opcode: usize, depth: usize) -> (usize, Vec<Option<usize>>) {
+    let picked = depth + 1;
+    let num_kept = if opcode == OP_ROLL { picked } else { picked + 1 };
+    let pushed = std::iter::once(picked).chain(1..num_kept).map(Some).collect();
+    (depth + 2, pushed)
+}
+
+/// Number of public keys n, number of signatures m and whether all signatures are valid for an
+/// OP_CHECKMULTISIG executed on `stack`. The stack holds n, the public keys, m, the signatures and
+/// an extra item which Bitcoin pops due to an off-by-one error. Returns `None` unless
+/// 1 <= m <= n <= MAX_MULTISIG_N.
+pub(crate) fn multisig_counts<F: Field>(stack: &[F]) -> Option<(usize, usize, bool)> {
+    let count = |item: F| (1..=MAX_MULTISIG_N).find(|c| item == F::from(*c as u64));
+    let n = count(stack[0])?;
+    let m = count(stack[n+1]).filter(|m| *m <= n)?;
+    let are_sigs_valid = stack[n+2..n+2+m].iter().all(|sig| *sig == F::one());
+    Some((n, m, are_sigs_valid))
+}
+
+/// Change in the number of stack items caused by an opcode. The change caused by OP_CHECKMULTISIG
+/// depends on the stack and is not included.
+pub(crate) fn stack_size_delta(opcode: usize) -> i64 {
+    if opcode == OP_0
+    || (OP_1..=OP_16).contains(&opcode)
+    || (OP_PUSH_NEXT1..=OP_PUSH_NEXT75).contains(&opcode)
+    || (OP_PUSHDATA1..=OP_PUSHDATA4).contains(&opcode)
+    || opcode == OP_OVER
+    || opcode == OP_TUCK
+    || opcode == OP_DEPTH
+    || opcode == OP_FROMALTSTACK
+    || opcode == OP_SIZE {
+        1
+    }
+    else if opcode == OP_2DUP || opcode == OP_2OVER {
+        2
+    }
+    else if opcode == OP_3DUP {
+        3
+    }
+    else if opcode == OP_CHECKSIG
+    || opcode == OP_EQUAL
+    || opcode == OP_VERIFY
+    || opcode == OP_DROP
+    || opcode == OP_NIP
+    || opcode == OP_ROLL
+    || opcode == OP_TOALTSTACK
+    || opcode == OP_ADD
+    || opcode == OP_SUB
+    || opcode == OP_IF
+    || opcode == OP_NOTIF
+    || opcode == OP_BOOLAND
+    || opcode == OP_BOOLOR
+    || opcode == OP_NUMEQUAL
+    || (OP_LESSTHAN..=OP_MAX).contains(&opcode) {
+        -1
+    }
+    else if opcode == OP_EQUALVERIFY
+    || opcode == OP_CHECKSIGVERIFY
+    || opcode == OP_CHECKSIGADD
+    || opcode == OP_2DROP
+    || opcode == OP_WITHIN {
+        -2
+    }
+    else {
+        0
+    }
 }
 
 pub fn opcode_enabled(opcode: u8) -> u64 {
     let opcode = opcode as usize;
     if (opcode <= OP_NOP && opcode != OP_1NEGATE && opcode != OP_RESERVED)
-    || opcode == OP_CHECKSIG {
+    || opcode == OP_CHECKSIG
+    || opcode == OP_CHECKSIGVERIFY
+    || opcode == OP_CHECKMULTISIG
+    || opcode == OP_CHECKSIGADD
+    || opcode == OP_IF
+    || opcode == OP_NOTIF
+    || opcode == OP_ELSE
+    || opcode == OP_ENDIF
+    || opcode == OP_EQUAL
+    || opcode == OP_EQUALVERIFY
+    || opcode == OP_VERIFY
+    || opcode == OP_DROP
+    || opcode == OP_2DROP
+    || opcode == OP_SWAP
+    || opcode == OP_OVER
+    || opcode == OP_ROT
+    || opcode == OP_NIP
+    || opcode == OP_TUCK
+    || (OP_2DUP..=OP_2SWAP).contains(&opcode)
+    || opcode == OP_PICK
+    || opcode == OP_ROLL
+    || opcode == OP_TOALTSTACK
+    || opcode == OP_FROMALTSTACK
+    || opcode == OP_DEPTH
+    || opcode == OP_SIZE
+    || opcode == OP_1ADD
+    || opcode == OP_1SUB
+    || opcode == OP_NEGATE
+    || opcode == OP_ABS
+    || opcode == OP_NOT
+    || opcode == OP_0NOTEQUAL
+    || opcode == OP_ADD
+    || opcode == OP_SUB
+    || opcode == OP_BOOLAND
+    || opcode == OP_BOOLOR
+    || opcode == OP_NUMEQUAL
+    || opcode == OP_RIPEMD160
+    || opcode == OP_SHA1
+    || opcode == OP_SHA256
+    || opcode == OP_HASH160
+    || opcode == OP_HASH256
+    || opcode == OP_CHECKLOCKTIMEVERIFY
+    || opcode == OP_CHECKSEQUENCEVERIFY
+    || opcode == OP_CODESEPARATOR
+    || opcode == OP_NOP1
+    || (OP_NOP4..=OP_NOP10).contains(&opcode)
+    || (OP_LESSTHAN..=OP_WITHIN).contains(&opcode) {
         1
     }
     else {
@@ -167,6 +1098,73 @@ opcode_indicator!(pushdata1_indicator, OP_PUSHDATA1);
 opcode_indicator!(pushdata2_indicator, OP_PUSHDATA2);
 opcode_indicator!(pushdata4_indicator, OP_PUSHDATA4);
 opcode_indicator!(checksig_indicator, OP_CHECKSIG);
+opcode_indicator!(equal_indicator, OP_EQUAL);
+opcode_indicator!(equalverify_indicator, OP_EQUALVERIFY);
+opcode_indicator!(verify_indicator, OP_VERIFY);
+opcode_indicator!(drop_indicator, OP_DROP);
+opcode_indicator!(op_2drop_indicator, OP_2DROP);
+opcode_indicator!(swap_indicator, OP_SWAP);
+opcode_indicator!(over_indicator, OP_OVER);
+opcode_indicator!(rot_indicator, OP_ROT);
+opcode_indicator!(nip_indicator, OP_NIP);
+opcode_indicator!(tuck_indicator, OP_TUCK);
+opcode_indicator!(op_2dup_indicator, OP_2DUP);
+opcode_indicator!(op_3dup_indicator, OP_3DUP);
+opcode_indicator!(op_2over_indicator, OP_2OVER);
+opcode_indicator!(op_2rot_indicator, OP_2ROT);
+opcode_indicator!(op_2swap_indicator, OP_2SWAP);
+opcode_indicator!(pick_indicator, OP_PICK);
+opcode_indicator!(roll_indicator, OP_ROLL);
+opcode_indicator!(depth_indicator, OP_DEPTH);
+opcode_indicator!(add_indicator, OP_ADD);
+opcode_indicator!(sub_indicator, OP_SUB);
+opcode_indicator!(op_1add_indicator, OP_1ADD);
+opcode_indicator!(op_1sub_indicator, OP_1SUB);
+opcode_indicator!(negate_indicator, OP_NEGATE);
+opcode_indicator!(abs_indicator, OP_ABS);
+opcode_indicator!(lessthan_indicator, OP_LESSTHAN);
+opcode_indicator!(greaterthan_indicator, OP_GREATERTHAN);
+opcode_indicator!(lessthanorequal_indicator, OP_LESSTHANOREQUAL);
+opcode_indicator!(greaterthanorequal_indicator, OP_GREATERTHANOREQUAL);
+opcode_indicator!(min_indicator, OP_MIN);
+opcode_indicator!(max_indicator, OP_MAX);
+opcode_indicator!(within_indicator, OP_WITHIN);
+opcode_indicator!(not_indicator, OP_NOT);
+opcode_indicator!(op_0notequal_indicator, OP_0NOTEQUAL);
+opcode_indicator!(booland_indicator, OP_BOOLAND);
+opcode_indicator!(boolor_indicator, OP_BOOLOR);
+opcode_indicator!(if_indicator, OP_IF);
+opcode_indicator!(notif_indicator, OP_NOTIF);
+opcode_indicator!(else_indicator, OP_ELSE);
+opcode_indicator!(endif_indicator, OP_ENDIF);
+opcode_indicator!(checkmultisig_indicator, OP_CHECKMULTISIG);
+opcode_indicator!(checksigverify_indicator, OP_CHECKSIGVERIFY);
+opcode_indicator!(numequal_indicator, OP_NUMEQUAL);
+opcode_indicator!(checksigadd_indicator, OP_CHECKSIGADD);
+opcode_indicator!(push1_indicator, OP_PUSH_NEXT1);
+opcode_indicator!(toaltstack_indicator, OP_TOALTSTACK);
+opcode_indicator!(fromaltstack_indicator, OP_FROMALTSTACK);
+opcode_indicator!(size_indicator, OP_SIZE);
+opcode_indicator!(ripemd160_indicator, OP_RIPEMD160);
+opcode_indicator!(sha1_indicator, OP_SHA1);
+opcode_indicator!(hash160_indicator, OP_HASH160);
+opcode_indicator!(sha256_indicator, OP_SHA256);
+opcode_indicator!(hash256_indicator, OP_HASH256);
+opcode_indicator!(cltv_indicator, OP_CHECKLOCKTIMEVERIFY);
+opcode_indicator!(csv_indicator, OP_CHECKSEQUENCEVERIFY);
+opcode_indicator!(codeseparator_indicator, OP_CODESEPARATOR);
+
+/// Returns 1 for OP_NOP and the reserved no-ops which are not redefined, i.e. OP_NOP1 and
+/// OP_NOP4 to OP_NOP10. OP_NOP2 and OP_NOP3 are OP_CHECKLOCKTIMEVERIFY and OP_CHECKSEQUENCEVERIFY.
+pub fn nop_indicator(opcode: u8) -> u64 {
+    let opcode = opcode as usize;
+    (opcode == OP_NOP || opcode == OP_NOP1 || (OP_NOP4..=OP_NOP10).contains(&opcode)) as u64
+}
+
+/// Returns 1 for the opcodes in DISABLED_OPCODES
+pub fn disabled_indicator(opcode: u8) -> u64 {
+    DISABLED_OPCODES.contains(&(opcode as usize)) as u64
+}
 
 macro_rules! opcode_range_indicator {
     ($name:ident, $opval_min:expr, $opval_max:expr) => {
@@ -185,5 +1183,125 @@ macro_rules! opcode_range_indicator {
 
 opcode_range_indicator!(op1_to_op16_indicator, OP_1, OP_16);
 opcode_range_indicator!(push1_to_push75_indicator, OP_PUSH_NEXT1, OP_PUSH_NEXT75);
+opcode_range_indicator!(push1_to_push16_indicator, OP_PUSH_NEXT1, OP_PUSH_NEXT16);
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr as BnScalar;
+
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        plonk::{self, Circuit, ConstraintSystem},
+    };
 
+    use crate::bitcoinvm_circuit::constants::*;
+    use crate::ripemd160::{table16::{Table16Chip, Table16Config}, DigestByteOrder, RIPEMD160};
+    use super::{final_stack, ripemd160_blocks, ripemd160_digest_item, ScriptPubkeyParseState, StackItem};
 
+    #[test]
+    fn test_final_stack_empty_array() {
+        let randomness = BnScalar::from(0x1234u64);
+        let script_pubkey = vec![OP_1 as u8, OP_0 as u8];
+
+        let stack = final_stack(&script_pubkey, randomness, [BnScalar::zero(); MAX_STACK_DEPTH], true);
+        assert_eq!(stack[0], StackItem::Empty);
+        assert!(!stack[0].is_true());
+        assert_eq!(stack[1], StackItem::Value(BnScalar::one()));
+        assert!(stack[1].is_true());
+
+        // Without decoding, the internal representation is exposed
+        let stack = final_stack(&script_pubkey, randomness, [BnScalar::zero(); MAX_STACK_DEPTH], false);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::from(EMPTY_ARRAY_REPRESENTATION)));
+        assert!(!stack[0].is_true());
+    }
+
+    #[test]
+    fn test_final_stack_alt_stack() {
+        let randomness = BnScalar::from(0x1234u64);
+        // OP_1 OP_TOALTSTACK OP_2 OP_FROMALTSTACK leaves 1 above 2
+        let script_pubkey = vec![OP_1 as u8, OP_TOALTSTACK as u8, OP_1 as u8 + 1, OP_FROMALTSTACK as u8];
+
+        let stack = final_stack(&script_pubkey, randomness, [BnScalar::zero(); MAX_STACK_DEPTH], true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::one()));
+        assert_eq!(stack[1], StackItem::Value(BnScalar::from(2)));
+        assert_eq!(stack[2], StackItem::Value(BnScalar::zero()));
+    }
+
+    #[test]
+    fn test_final_stack_equal() {
+        let randomness = BnScalar::from(0x1234u64);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+
+        let stack = final_stack(&[OP_1 as u8, OP_1 as u8 + 1, OP_1 as u8 + 1, OP_EQUAL as u8], randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::one()));
+        assert_eq!(stack[1], StackItem::Value(BnScalar::one()));
+        assert_eq!(stack[2], StackItem::Value(BnScalar::zero()));
+
+        let stack = final_stack(&[OP_1 as u8 + 1, OP_1 as u8 + 2, OP_EQUAL as u8], randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Empty);
+
+        let stack = final_stack(&[OP_1 as u8, OP_1 as u8 + 1, OP_1 as u8 + 1, OP_EQUALVERIFY as u8], randomness, zero_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::one()));
+        assert_eq!(stack[1], StackItem::Value(BnScalar::zero()));
+    }
+
+    // Runs the RIPEMD-160 gadget on the bytes of a stack item and checks that its digest
+    // maps to the item pushed by OP_RIPEMD160
+    struct RIPEMD160ItemCircuit {
+        bytes: Vec<u8>,
+        digest_item: BnScalar,
+        randomness: BnScalar,
+    }
+
+    impl Circuit<BnScalar> for RIPEMD160ItemCircuit {
+        type Config = Table16Config<BnScalar>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            RIPEMD160ItemCircuit {
+                bytes: self.bytes.clone(),
+                digest_item: self.digest_item,
+                randomness: self.randomness,
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<BnScalar>) -> Self::Config {
+            Table16Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self, config: Self::Config,
+            mut layouter: impl Layouter<BnScalar>,
+        ) -> Result<(), plonk::Error> {
+            let table16_chip = Table16Chip::construct(config.clone());
+            Table16Chip::load(config, &mut layouter)?;
+
+            let blocks = ripemd160_blocks(&self.bytes);
+            let digest = RIPEMD160::digest(table16_chip, layouter, &blocks, DigestByteOrder::Internal)?;
+            ripemd160_digest_item(&digest, self.randomness).assert_if_known(|item| *item == self.digest_item);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_ripemd160_gadget_digest_item() {
+        let randomness = BnScalar::from(0x1234u64);
+        let zero_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+
+        // The bytes of the hashed item are recovered from its RLC and its length
+        let mut state = ScriptPubkeyParseState::new(randomness, zero_stack);
+        for byte in [3, b'a', b'b', b'c'] {
+            state.update(byte);
+        }
+        let bytes = state.item_bytes(state.stack[0], state.stack_len[0]).unwrap();
+        assert_eq!(bytes, b"abc".to_vec());
+        state.update(OP_RIPEMD160 as u8);
+        assert_eq!(state.stack_len[0], 20);
+
+        let circuit = RIPEMD160ItemCircuit { bytes, digest_item: state.stack[0], randomness };
+        let prover = MockProver::<BnScalar>::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
@@ -0,0 +1,265 @@
+use super::super::constants::*;
+use super::super::crypto_opcodes::util::pk_parser::pushed_data;
+use super::super::error::BitcoinVmError;
+
+/// Standard scriptPubkey types, following the classification of Bitcoin Core
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptType {
+    /// `<pubkey> OP_CHECKSIG`
+    P2PK,
+    /// `OP_DUP OP_HASH160 <20-byte hash> OP_EQUALVERIFY OP_CHECKSIG`
+    P2PKH,
+    /// `OP_HASH160 <20-byte hash> OP_EQUAL`
+    P2SH,
+    /// `OP_m <pubkey_1> ... <pubkey_n> OP_n OP_CHECKMULTISIG` with 1 <= m <= n <= 16
+    Multisig,
+    /// `OP_RETURN` followed by data pushes
+    OpReturn,
+    /// Any other script
+    NonStandard,
+}
+
+const HASH160_SIZE: usize = 20;
+const COMPRESSED_PK_SIZE: usize = 33;
+const UNCOMPRESSED_PK_SIZE: usize = 65;
+
+/// Classifies a scriptPubkey into one of the standard script types
+pub fn classify_script(script: &[u8]) -> ScriptType {
+    // P2PKH and P2SH are matched on the exact byte pattern
+    if script.len() == HASH160_SIZE + 5
+        && script[0] as usize == OP_DUP
+        && script[1] as usize == OP_HASH160
+        && script[2] as usize == HASH160_SIZE
+        && script[HASH160_SIZE + 3] as usize == OP_EQUALVERIFY
+        && script[HASH160_SIZE + 4] as usize == OP_CHECKSIG {
+        return ScriptType::P2PKH;
+    }
+    if script.len() == HASH160_SIZE + 3
+        && script[0] as usize == OP_HASH160
+        && script[1] as usize == HASH160_SIZE
+        && script[HASH160_SIZE + 2] as usize == OP_EQUAL {
+        return ScriptType::P2SH;
+    }
+
+    let ops = match split_script(script) {
+        Ok(ops) => ops,
+        Err(_) => return ScriptType::NonStandard,
+    };
+
+    if !ops.is_empty() && ops[0].0 == OP_RETURN && ops[1..].iter().all(|(opcode, _)| is_push_opcode(*opcode)) {
+        return ScriptType::OpReturn;
+    }
+
+    if ops.len() == 2 && ops[1].0 == OP_CHECKSIG {
+        if let Some(pk) = &ops[0].1 {
+            if is_valid_pk(pk) {
+                return ScriptType::P2PK;
+            }
+        }
+    }
+
+    if ops.len() >= 4 && ops[ops.len() - 1].0 == OP_CHECKMULTISIG {
+        let num_required = small_integer(ops[0].0);
+        let num_pks = small_integer(ops[ops.len() - 2].0);
+        let pks = &ops[1..ops.len() - 2];
+        if let (Some(m), Some(n)) = (num_required, num_pks) {
+            if m >= 1
+                && m <= n
+                && pks.len() == n
+                && pks.iter().all(|(_, data)| matches!(data, Some(pk) if is_valid_pk(pk))) {
+                return ScriptType::Multisig;
+            }
+        }
+    }
+
+    ScriptType::NonStandard
+}
+
+/// Splits the scriptSig of a P2SH spend into the pushes which leave the stack of the redeem
+/// script and the redeem script, which is the data pushed last. Fails if the scriptSig is not
+/// push-only or does not end with a data push.
+pub fn split_p2sh_script_sig(script_sig: &[u8]) -> Result<(Vec<u8>, Vec<u8>), BitcoinVmError> {
+    let mut last_push = None;
+    let mut i = 0;
+    while i < script_sig.len() {
+        let opcode = script_sig[i] as usize;
+        if !is_push_opcode(opcode) {
+            return Err(BitcoinVmError::InvalidScriptSig);
+        }
+        match pushed_data(script_sig, i)? {
+            Some((data, length)) => {
+                last_push = Some((i, data.to_vec()));
+                i += length;
+            },
+            None => {
+                last_push = None;
+                i += 1;
+            },
+        }
+    }
+    match last_push {
+        Some((start, redeem_script)) => Ok((script_sig[..start].to_vec(), redeem_script)),
+        None => Err(BitcoinVmError::InvalidScriptSig),
+    }
+}
+
+// Splits a script into opcodes and the data pushed by the data push opcodes. Fails on
+// truncated and zero-length data pushes, which the ExecutionChip rejects.
+fn split_script(script: &[u8]) -> Result<Vec<(usize, Option<Vec<u8>>)>, BitcoinVmError> {
+    let mut ops = vec![];
+    let mut i = 0;
+    while i < script.len() {
+        let opcode = script[i] as usize;
+        match pushed_data(script, i)? {
+            Some((data, length)) => {
+                ops.push((opcode, Some(data.to_vec())));
+                i += length;
+            },
+            None => {
+                ops.push((opcode, None));
+                i += 1;
+            },
+        }
+    }
+    Ok(ops)
+}
+
+fn is_push_opcode(opcode: usize) -> bool {
+    opcode <= OP_16 && opcode != OP_RESERVED
+}
+
+// Returns the value of OP_1 to OP_16
+fn small_integer(opcode: usize) -> Option<usize> {
+    if (OP_1..=OP_16).contains(&opcode) {
+        Some(opcode - OP_RESERVED)
+    }
+    else {
+        None
+    }
+}
+
+fn is_valid_pk(pk: &[u8]) -> bool {
+    let prefix = match pk.first() {
+        Some(prefix) => *prefix as u64,
+        None => return false,
+    };
+    (pk.len() == COMPRESSED_PK_SIZE && (prefix == PREFIX_PK_COMPRESSED_EVEN_Y || prefix == PREFIX_PK_COMPRESSED_ODD_Y))
+    || (pk.len() == UNCOMPRESSED_PK_SIZE && prefix == PREFIX_PK_UNCOMPRESSED)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bitcoinvm_circuit::constants::*;
+    use crate::bitcoinvm_circuit::error::BitcoinVmError;
+    use super::{classify_script, split_p2sh_script_sig, ScriptType};
+
+    fn compressed_pk(prefix: u8) -> Vec<u8> {
+        let mut pk = vec![prefix];
+        pk.extend_from_slice(&[0x11; 32]);
+        pk
+    }
+
+    #[test]
+    fn test_classify_script() {
+        let pk = compressed_pk(PREFIX_PK_COMPRESSED_EVEN_Y as u8);
+        let mut uncompressed_pk = vec![PREFIX_PK_UNCOMPRESSED as u8];
+        uncompressed_pk.extend_from_slice(&[0x22; 64]);
+        let hash = [0x33u8; 20];
+
+        let mut p2pk = vec![pk.len() as u8];
+        p2pk.extend_from_slice(&pk);
+        p2pk.push(OP_CHECKSIG as u8);
+        assert_eq!(classify_script(&p2pk), ScriptType::P2PK);
+
+        let mut p2pk_uncompressed = vec![uncompressed_pk.len() as u8];
+        p2pk_uncompressed.extend_from_slice(&uncompressed_pk);
+        p2pk_uncompressed.push(OP_CHECKSIG as u8);
+        assert_eq!(classify_script(&p2pk_uncompressed), ScriptType::P2PK);
+
+        let mut p2pkh = vec![OP_DUP as u8, OP_HASH160 as u8, hash.len() as u8];
+        p2pkh.extend_from_slice(&hash);
+        p2pkh.extend_from_slice(&[OP_EQUALVERIFY as u8, OP_CHECKSIG as u8]);
+        assert_eq!(classify_script(&p2pkh), ScriptType::P2PKH);
+
+        let mut p2sh = vec![OP_HASH160 as u8, hash.len() as u8];
+        p2sh.extend_from_slice(&hash);
+        p2sh.push(OP_EQUAL as u8);
+        assert_eq!(classify_script(&p2sh), ScriptType::P2SH);
+
+        // 2-of-3 multisig
+        let mut multisig = vec![(OP_1 + 1) as u8];
+        for prefix in [PREFIX_PK_COMPRESSED_EVEN_Y, PREFIX_PK_COMPRESSED_ODD_Y, PREFIX_PK_COMPRESSED_EVEN_Y] {
+            multisig.push(pk.len() as u8);
+            multisig.extend_from_slice(&compressed_pk(prefix as u8));
+        }
+        multisig.extend_from_slice(&[(OP_1 + 2) as u8, OP_CHECKMULTISIG as u8]);
+        assert_eq!(classify_script(&multisig), ScriptType::Multisig);
+
+        let op_return = vec![OP_RETURN as u8, 0x04, 0xde, 0xad, 0xbe, 0xef];
+        assert_eq!(classify_script(&op_return), ScriptType::OpReturn);
+        assert_eq!(classify_script(&[OP_RETURN as u8]), ScriptType::OpReturn);
+    }
+
+    #[test]
+    fn test_classify_non_standard_script() {
+        let pk = compressed_pk(PREFIX_PK_COMPRESSED_EVEN_Y as u8);
+
+        // Invalid public key prefix
+        let mut p2pk = vec![pk.len() as u8, PREFIX_PK_UNCOMPRESSED as u8];
+        p2pk.extend_from_slice(&pk[1..]);
+        p2pk.push(OP_CHECKSIG as u8);
+        assert_eq!(classify_script(&p2pk), ScriptType::NonStandard);
+
+        // 3-of-2 multisig
+        let mut multisig = vec![(OP_1 + 2) as u8];
+        for _ in 0..2 {
+            multisig.push(pk.len() as u8);
+            multisig.extend_from_slice(&pk);
+        }
+        multisig.extend_from_slice(&[(OP_1 + 1) as u8, OP_CHECKMULTISIG as u8]);
+        assert_eq!(classify_script(&multisig), ScriptType::NonStandard);
+
+        // OP_RETURN followed by a non-push opcode
+        assert_eq!(classify_script(&[OP_RETURN as u8, OP_NOP as u8]), ScriptType::NonStandard);
+
+        // Truncated data pushes
+        assert_eq!(classify_script(&[0x05, 0x01, 0x02]), ScriptType::NonStandard);
+        assert_eq!(classify_script(&[OP_RETURN as u8, OP_PUSHDATA2 as u8, 0x01]), ScriptType::NonStandard);
+
+        // OP_PUSHDATA with zero length
+        assert_eq!(classify_script(&[OP_RETURN as u8, OP_PUSHDATA1 as u8, 0x00]), ScriptType::NonStandard);
+
+        assert_eq!(classify_script(&[OP_1 as u8]), ScriptType::NonStandard);
+        assert_eq!(classify_script(&[]), ScriptType::NonStandard);
+    }
+
+    #[test]
+    fn test_split_p2sh_script_sig() {
+        let redeem_script = [OP_1 as u8, OP_EQUAL as u8];
+        let mut script_sig = vec![OP_0 as u8, 0x02, 0xab, 0xcd, OP_1 as u8, redeem_script.len() as u8];
+        script_sig.extend_from_slice(&redeem_script);
+        assert_eq!(
+            split_p2sh_script_sig(&script_sig),
+            Ok((vec![OP_0 as u8, 0x02, 0xab, 0xcd, OP_1 as u8], redeem_script.to_vec())),
+        );
+
+        // A redeem script alone leaves an empty stack
+        let mut script_sig = vec![OP_PUSHDATA1 as u8, redeem_script.len() as u8];
+        script_sig.extend_from_slice(&redeem_script);
+        assert_eq!(split_p2sh_script_sig(&script_sig), Ok((vec![], redeem_script.to_vec())));
+
+        // Not push-only
+        let script_sig = [0x01, 0xab, OP_DROP as u8, 0x01, 0xcd];
+        assert_eq!(split_p2sh_script_sig(&script_sig), Err(BitcoinVmError::InvalidScriptSig));
+
+        // The last push is not a data push
+        assert_eq!(split_p2sh_script_sig(&[0x01, 0xab, OP_1 as u8]), Err(BitcoinVmError::InvalidScriptSig));
+        assert_eq!(split_p2sh_script_sig(&[]), Err(BitcoinVmError::InvalidScriptSig));
+
+        // Truncated data push
+        assert_eq!(
+            split_p2sh_script_sig(&[0x05, 0x01]),
+            Err(BitcoinVmError::TruncatedPush { opcode: 0x05 }),
+        );
+    }
+}
@@ -0,0 +1,255 @@
+//! Witness of the BitcoinVM circuits computed from the bytes of a spend.
+//!
+//! [`BitcoinVmWitness::from_script`] runs the reference interpreter on the scriptSig and the
+//! scriptPubkey, so the initial stack of the ExecutionChip, the public keys collected for the
+//! OpCheckSigChip and the public inputs are all derived from the same values.
+
+use super::constants::*;
+use super::crypto_opcodes::checksig::checksig::OpCheckSigChip;
+use super::crypto_opcodes::util::pk_parser::{collect_public_keys, pushed_data, PublicKeyInScript, StackElement};
+use super::crypto_opcodes::util::sign_util::SignData;
+use super::error::BitcoinVmError;
+use super::public_inputs::PublicInputs;
+use crate::Field;
+
+#[derive(Clone, Debug)]
+pub(crate) struct BitcoinVmWitness<F: Field> {
+    pub script_pubkey: Vec<u8>,
+    // Randomness of the RLC operations
+    pub randomness: F,
+    // Stack left by the scriptSig, top first
+    pub stack_elements: Vec<StackElement>,
+    // Stack left by the scriptSig as assigned by the ExecutionChip
+    pub initial_stack: [F; MAX_STACK_DEPTH],
+    // Signatures, in the order of the public keys collected from the scriptPubkey
+    pub signatures: Vec<SignData>,
+    pub collected_pks: Vec<PublicKeyInScript>,
+    pub public_inputs: PublicInputs<F>,
+}
+
+impl<F: Field> BitcoinVmWitness<F> {
+    /// Computes the witness of a spend of `script_pubkey` by `script_sig`. The scriptSig must
+    /// only push data. Pushes with the shape of a DER-encoded signature are signatures. The
+    /// public keys they are checked against are matched in order with `signatures`.
+    pub(crate) fn from_script(
+        script_pubkey: Vec<u8>,
+        script_sig: Vec<u8>,
+        signatures: Vec<SignData>,
+        randomness: F,
+    ) -> Result<Self, BitcoinVmError> {
+        // The last push of the scriptSig is the stack top
+        let stack_elements: Vec<StackElement> = script_sig_pushes(&script_sig)?
+            .into_iter()
+            .rev()
+            .map(|bytes| {
+                if is_der_signature_shaped(&bytes) {
+                    StackElement::ValidSignature
+                }
+                else {
+                    StackElement::Data(bytes)
+                }
+            })
+            .collect();
+
+        let collected_pks = collect_public_keys(script_pubkey.clone(), stack_elements.clone())?;
+        OpCheckSigChip::<F, MAX_CHECKSIG_COUNT>::validate_signatures(&signatures, &collected_pks)?;
+        let initial_stack = initial_stack_values(&stack_elements, randomness)?;
+        let public_inputs = PublicInputs::new(script_pubkey.clone(), randomness)
+            .with_message_hashes(signatures.iter().map(|signature| signature.message_hash).collect());
+
+        Ok(Self {
+            script_pubkey,
+            randomness,
+            stack_elements,
+            initial_stack,
+            signatures,
+            collected_pks,
+            public_inputs,
+        })
+    }
+}
+
+/// Converts stack elements, top first, to the stack items assigned by the ExecutionChip.
+/// Signatures are represented by their validity and data by the RLC of its bytes.
+pub(crate) fn initial_stack_values<F: Field>(
+    stack_elements: &[StackElement],
+    randomness: F,
+) -> Result<[F; MAX_STACK_DEPTH], BitcoinVmError> {
+    if stack_elements.len() > MAX_STACK_DEPTH {
+        return Err(BitcoinVmError::OversizedStack { items: stack_elements.len() });
+    }
+    let mut initial_stack = [F::zero(); MAX_STACK_DEPTH];
+    for (i, element) in stack_elements.iter().enumerate() {
+        initial_stack[i] = match element {
+            StackElement::ValidSignature => F::one(),
+            StackElement::InvalidSignature => F::zero(),
+            StackElement::Data(bytes) if bytes.is_empty() => F::from(EMPTY_ARRAY_REPRESENTATION),
+            StackElement::Data(bytes) => bytes.iter().fold(F::zero(), |acc, v| {
+                acc * randomness + F::from(*v as u64)
+            }),
+        };
+    }
+    Ok(initial_stack)
+}
+
+// Byte arrays pushed by a push-only script, in the order of the pushes
+fn script_sig_pushes(script_sig: &[u8]) -> Result<Vec<Vec<u8>>, BitcoinVmError> {
+    let mut pushes = vec![];
+    let mut index = 0;
+    while index < script_sig.len() {
+        if let Some((data, length)) = pushed_data(script_sig, index)? {
+            pushes.push(data.to_vec());
+            index += length;
+            continue;
+        }
+        let opcode = script_sig[index] as usize;
+        match opcode {
+            OP_0 => pushes.push(vec![]),
+            OP_1NEGATE => pushes.push(vec![0x81]),
+            OP_1..=OP_16 => pushes.push(vec![(opcode - OP_1 + 1) as u8]),
+            _ => return Err(BitcoinVmError::InvalidScriptSig),
+        }
+        index += 1;
+    }
+    Ok(pushes)
+}
+
+// A DER-encoded signature is a sequence whose length byte covers the rest of the encoding,
+// except for the trailing sighash type byte
+fn is_der_signature_shaped(bytes: &[u8]) -> bool {
+    (MIN_DER_SIGNATURE_SIZE..=MAX_DER_SIGNATURE_SIZE).contains(&bytes.len())
+        && bytes[0] == DER_SEQUENCE_TAG
+        && bytes[1] as usize == bytes.len() - 3
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::arithmetic::Field as HaloField;
+    use halo2_proofs::halo2curves::bn256::Fr as BnScalar;
+    use halo2_proofs::halo2curves::group::Curve;
+    use halo2_proofs::halo2curves::secp256k1::{Fq, Secp256k1Affine};
+    use halo2_proofs::halo2curves::CurveAffine;
+    use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+    use secp256k1::constants::PUBLIC_KEY_SIZE;
+
+    use super::BitcoinVmWitness;
+    use crate::bitcoinvm_circuit::constants::*;
+    use crate::bitcoinvm_circuit::crypto_opcodes::util::sign_util::{message_hash_from_digest, SignData};
+    use crate::bitcoinvm_circuit::error::BitcoinVmError;
+    use crate::bitcoinvm_circuit::public_inputs::PublicInputs;
+    use crate::bitcoinvm_circuit::util::script_parser::{final_stack, StackItem};
+
+    // P2PK scriptPubkey, DER-encoded signature in a scriptSig and the matching SignData
+    fn p2pk_spend(secret_key: &SecretKey) -> (Vec<u8>, Vec<u8>, SignData) {
+        let secp = Secp256k1::new();
+        let public_key_bytes: [u8; PUBLIC_KEY_SIZE] = PublicKey::from_secret_key(&secp, secret_key).serialize();
+        let mut script_pubkey: Vec<u8> = vec![PUBLIC_KEY_SIZE as u8];
+        script_pubkey.extend(public_key_bytes.iter());
+        script_pubkey.push(OP_CHECKSIG as u8);
+
+        let mut digest = [0u8; 32];
+        digest[31] = ECDSA_MESSAGE_HASH as u8;
+        let signature = secp.sign_ecdsa(&Message::from_slice(&digest).unwrap(), secret_key);
+        let mut der_signature = signature.serialize_der().to_vec();
+        der_signature.push(SIGHASH_ALL);
+        let mut script_sig = vec![der_signature.len() as u8];
+        script_sig.extend(der_signature.iter());
+
+        let compact_signature = signature.serialize_compact();
+        let mut sk_bytes = secret_key.secret_bytes();
+        sk_bytes.reverse();
+        let sk = Fq::from_bytes(&sk_bytes).unwrap();
+        let sign_data = SignData {
+            signature: (
+                message_hash_from_digest(compact_signature[..32].try_into().unwrap()),
+                message_hash_from_digest(compact_signature[32..].try_into().unwrap()),
+            ),
+            pk: (Secp256k1Affine::generator() * sk).to_affine(),
+            message_hash: message_hash_from_digest(&digest),
+            sighash_type: SIGHASH_ALL,
+        };
+        (script_pubkey, script_sig, sign_data)
+    }
+
+    #[test]
+    fn test_witness_from_p2pk_spend() {
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let (script_pubkey, script_sig, sign_data) = p2pk_spend(&secret_key);
+        let randomness = BnScalar::from(0x1234u64);
+
+        let witness = BitcoinVmWitness::from_script(script_pubkey.clone(), script_sig, vec![sign_data.clone()], randomness)
+            .expect("Valid P2PK spend");
+        // The hand-assembled witness of a P2PK spend
+        let mut initial_stack = [BnScalar::zero(); MAX_STACK_DEPTH];
+        initial_stack[0] = BnScalar::one();
+        assert_eq!(witness.initial_stack, initial_stack);
+        assert_eq!(witness.collected_pks.len(), 1);
+        assert_eq!(witness.collected_pks[0].pk, sign_data.pk);
+        assert_eq!(witness.collected_pks[0].bytes, script_pubkey[1..1+PUBLIC_KEY_SIZE].to_vec());
+        assert_eq!(
+            witness.public_inputs.execution_instance(),
+            PublicInputs::new(script_pubkey.clone(), randomness).execution_instance(),
+        );
+
+        // The scriptPubkey succeeds on the computed initial stack
+        let stack = final_stack(&script_pubkey, randomness, witness.initial_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::one()));
+    }
+
+    #[test]
+    fn test_witness_from_data_pushes() {
+        let randomness = BnScalar::from(0x1234u64);
+        let script_sig = vec![OP_2 as u8, OP_PUSHDATA1 as u8, 0x01, 0x03, OP_0 as u8];
+        let script_pubkey = vec![OP_DROP as u8, OP_ADD as u8, (OP_1 + 4) as u8, OP_EQUAL as u8];
+
+        let witness = BitcoinVmWitness::from_script(script_pubkey.clone(), script_sig, vec![], randomness)
+            .expect("Valid data pushes");
+        assert_eq!(witness.initial_stack[0], BnScalar::from(EMPTY_ARRAY_REPRESENTATION));
+        assert_eq!(witness.initial_stack[1], BnScalar::from(3));
+        assert_eq!(witness.initial_stack[2], BnScalar::from(2));
+        assert!(witness.collected_pks.is_empty());
+
+        let stack = final_stack(&script_pubkey, randomness, witness.initial_stack, true);
+        assert_eq!(stack[0], StackItem::Value(BnScalar::one()));
+    }
+
+    #[test]
+    fn test_witness_errors() {
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let other_secret_key = SecretKey::from_slice(&[0xab; 32]).expect("32 bytes, within curve order");
+        let (script_pubkey, script_sig, _) = p2pk_spend(&secret_key);
+        let (_, _, other_sign_data) = p2pk_spend(&other_secret_key);
+        let randomness = BnScalar::from(0x1234u64);
+
+        // No signature for the signature push
+        assert_eq!(
+            BitcoinVmWitness::from_script(script_pubkey.clone(), script_sig.clone(), vec![], randomness).unwrap_err(),
+            BitcoinVmError::MismatchedSignatureCount { signatures: 0, public_keys: 1 },
+        );
+        // A signature for a different public key
+        assert_eq!(
+            BitcoinVmWitness::from_script(script_pubkey.clone(), script_sig.clone(), vec![other_sign_data], randomness).unwrap_err(),
+            BitcoinVmError::MismatchedPublicKey { index: 0 },
+        );
+        // The scriptSig may only push data
+        assert_eq!(
+            BitcoinVmWitness::from_script(script_pubkey.clone(), vec![OP_1 as u8, OP_DUP as u8], vec![], randomness).unwrap_err(),
+            BitcoinVmError::InvalidScriptSig,
+        );
+        // The empty array is pushed by OP_0 only
+        assert_eq!(
+            BitcoinVmWitness::from_script(script_pubkey.clone(), vec![OP_PUSHDATA1 as u8, 0x00], vec![], randomness).unwrap_err(),
+            BitcoinVmError::EmptyPushData { opcode: OP_PUSHDATA1 as u8 },
+        );
+        // A push runs past the end of the scriptSig
+        assert_eq!(
+            BitcoinVmWitness::from_script(script_pubkey.clone(), script_sig[..10].to_vec(), vec![], randomness).unwrap_err(),
+            BitcoinVmError::TruncatedPush { opcode: script_sig[0] },
+        );
+        // The length of an OP_PUSHDATA runs past the end of the scriptSig
+        assert_eq!(
+            BitcoinVmWitness::from_script(script_pubkey, vec![OP_PUSHDATA2 as u8, 0x01], vec![], randomness).unwrap_err(),
+            BitcoinVmError::TruncatedPush { opcode: OP_PUSHDATA2 as u8 },
+        );
+    }
+}
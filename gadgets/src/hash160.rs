@@ -0,0 +1,123 @@
+//! The HASH160 function of Bitcoin, the RIPEMD-160 hash of the SHA-256 hash of the data.
+//!
+//! HASH160 digests are the public key and script hashes of P2PKH and P2SH outputs.
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::Layouter,
+    plonk::Error,
+};
+
+use crate::ripemd160::{DigestByteOrder, RIPEMD160Digest, RIPEMD160Instructions, RIPEMD160};
+use crate::sha256::{ref_impl::BLOCK_SIZE, Sha256, Sha256Instructions};
+
+/// A gadget that constrains a HASH160 invocation. The SHA-256 digest is copied into the
+/// RIPEMD-160 message in the circuit and the padding of the 32-byte RIPEMD-160 message
+/// is constrained, so the intermediate digest cannot be replaced by the prover.
+#[derive(Debug)]
+pub struct Hash160<F, Sha256Chip, RIPEMD160Chip> {
+    _marker: PhantomData<(F, Sha256Chip, RIPEMD160Chip)>,
+}
+
+impl<F, Sha256Chip, RIPEMD160Chip> Hash160<F, Sha256Chip, RIPEMD160Chip>
+where
+    F: FieldExt,
+    Sha256Chip: Sha256Instructions<F>,
+    RIPEMD160Chip: RIPEMD160Instructions<F, AssignedWord = Sha256Chip::AssignedWord>,
+{
+    /// Convenience function to compute HASH160 of the data, which is assumed to be
+    /// already padded to SHA-256 blocks. The digest words are in the
+    /// [`DigestByteOrder::Internal`] byte order, i.e. their little-endian bytes are the
+    /// hash as it appears in scripts.
+    pub fn digest(
+        sha256_chip: Sha256Chip,
+        ripemd160_chip: RIPEMD160Chip,
+        mut layouter: impl Layouter<F>,
+        data: &Vec<[Sha256Chip::BlockWord; BLOCK_SIZE]>,
+    ) -> Result<RIPEMD160Digest<RIPEMD160Chip::BlockWord>, Error> {
+        let mut sha256 = Sha256::new(sha256_chip, layouter.namespace(|| "sha256 init"))?;
+        sha256.update(layouter.namespace(|| "sha256 update"), data)?;
+        let sha256_digest = sha256.finalize_assigned(layouter.namespace(|| "sha256 finalize"))?;
+
+        let blocks = ripemd160_chip.pad_be_words(&mut layouter.namespace(|| "pad"), &sha256_digest)?;
+        let mut ripemd160 = RIPEMD160::new(ripemd160_chip, layouter.namespace(|| "ripemd160 init"))?;
+        ripemd160.update_assigned(layouter.namespace(|| "ripemd160 update"), &blocks)?;
+        ripemd160.finalize(layouter.namespace(|| "ripemd160 finalize"), DigestByteOrder::Internal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin_hashes::{hash160, Hash};
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        halo2curves::pasta::pallas,
+        plonk::{self, Circuit, ConstraintSystem},
+    };
+
+    use super::Hash160;
+    use crate::ripemd160;
+    use crate::sha256;
+    use crate::sha256::ref_impl::{convert_byte_slice_to_u32_slice, pad_message_bytes, BLOCK_SIZE, BLOCK_SIZE_BYTES};
+    use crate::sha256::table16::BlockWord;
+
+    struct Hash160Circuit {
+        input: Vec<u8>,
+        expected: [u8; 20],
+    }
+
+    impl Circuit<pallas::Base> for Hash160Circuit {
+        type Config = (ripemd160::table16::Table16Config<pallas::Base>, sha256::table16::Table16Config<pallas::Base>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Hash160Circuit { input: self.input.clone(), expected: self.expected }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let ripemd160_config = ripemd160::table16::Table16Chip::configure(meta);
+            let sha256_config =
+                sha256::table16::Table16Chip::configure_with_spread_table(meta, ripemd160_config.spread_table());
+            (ripemd160_config, sha256_config)
+        }
+
+        fn synthesize(
+            &self, config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), plonk::Error> {
+            ripemd160::table16::Table16Chip::load(config.0.clone(), &mut layouter)?;
+
+            let data: Vec<[BlockWord; BLOCK_SIZE]> = pad_message_bytes(self.input.clone())
+                .into_iter()
+                .map(|block| convert_byte_slice_to_u32_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>(block).map(BlockWord::from))
+                .collect();
+
+            let digest = Hash160::digest(
+                sha256::table16::Table16Chip::construct(config.1),
+                ripemd160::table16::Table16Chip::construct(config.0),
+                layouter.namespace(|| "hash160"),
+                &data,
+            )?;
+            digest.to_le_bytes().assert_if_known(|bytes| *bytes == self.expected);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn hash160_matches_bitcoin_hashes() {
+        let compressed_pubkey =
+            hex::decode("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+        for input in [b"".to_vec(), b"abc".to_vec(), compressed_pubkey, vec![0xab; 100]] {
+            let circuit = Hash160Circuit {
+                expected: hash160::Hash::hash(&input).into_inner(),
+                input,
+            };
+
+            let prover = MockProver::<pallas::Base>::run(17, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! The HASH256 function of Bitcoin, the double SHA-256 hash of the data.
+//!
+//! HASH256 digests are the transaction and block hashes, and the result of `OP_HASH256`.
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::Layouter,
+    plonk::Error,
+};
+
+use crate::sha256::{ref_impl::BLOCK_SIZE, Sha256, Sha256Digest, Sha256Instructions};
+
+/// A gadget that constrains a HASH256 invocation. The digest of the first SHA-256
+/// invocation is copied into the message of the second one and the padding of the
+/// 32-byte message is fixed, so the intermediate digest cannot be replaced by the prover.
+#[derive(Debug)]
+pub struct Hash256<F, Sha256Chip> {
+    _marker: PhantomData<(F, Sha256Chip)>,
+}
+
+impl<F, Sha256Chip> Hash256<F, Sha256Chip>
+where
+    F: FieldExt,
+    Sha256Chip: Sha256Instructions<F> + Clone,
+{
+    /// Convenience function to compute HASH256 of the data, which is assumed to be
+    /// already padded to SHA-256 blocks. The digest bytes are the big-endian bytes of
+    /// the words.
+    pub fn digest(
+        chip: Sha256Chip,
+        mut layouter: impl Layouter<F>,
+        data: &Vec<[Sha256Chip::BlockWord; BLOCK_SIZE]>,
+    ) -> Result<Sha256Digest<Sha256Chip::BlockWord>, Error> {
+        let mut first = Sha256::new(chip.clone(), layouter.namespace(|| "first sha256 init"))?;
+        first.update(layouter.namespace(|| "first sha256 update"), data)?;
+        let first_digest = first.finalize_assigned(layouter.namespace(|| "first sha256 finalize"))?;
+
+        let mut second = Sha256::new(chip, layouter.namespace(|| "second sha256 init"))?;
+        second.update_digest(layouter.namespace(|| "second sha256 update"), &first_digest)?;
+        second.finalize(layouter.namespace(|| "second sha256 finalize"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin_hashes::{sha256d, Hash};
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        halo2curves::pasta::pallas,
+        plonk::{self, Circuit, ConstraintSystem},
+    };
+
+    use super::Hash256;
+    use crate::sha256::ref_impl::{convert_byte_slice_to_u32_slice, pad_message_bytes, BLOCK_SIZE, BLOCK_SIZE_BYTES};
+    use crate::sha256::table16::{BlockWord, Table16Chip, Table16Config};
+
+    struct Hash256Circuit {
+        input: Vec<u8>,
+        expected: [u8; 32],
+    }
+
+    impl Circuit<pallas::Base> for Hash256Circuit {
+        type Config = Table16Config<pallas::Base>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Hash256Circuit { input: self.input.clone(), expected: self.expected }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            Table16Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self, config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), plonk::Error> {
+            let table16_chip = Table16Chip::construct(config.clone());
+            Table16Chip::load(config, &mut layouter)?;
+
+            let data: Vec<[BlockWord; BLOCK_SIZE]> = pad_message_bytes(self.input.clone())
+                .into_iter()
+                .map(|block| convert_byte_slice_to_u32_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>(block).map(BlockWord::from))
+                .collect();
+
+            let digest = Hash256::digest(table16_chip, layouter.namespace(|| "hash256"), &data)?;
+            digest.to_be_bytes().assert_if_known(|bytes| *bytes == self.expected);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn hash256_matches_bitcoin_hashes() {
+        // The genesis block header, whose HASH256 is the genesis block hash
+        let genesis_header = hex::decode(
+            "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c"
+        ).unwrap();
+        for input in [b"".to_vec(), b"abc".to_vec(), genesis_header, vec![0xab; 100]] {
+            let circuit = Hash256Circuit {
+                expected: sha256d::Hash::hash(&input).into_inner(),
+                input,
+            };
+
+            let prover = MockProver::<pallas::Base>::run(17, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+}
@@ -1,6 +1,12 @@
 #![allow(dead_code)]
 pub mod bitcoinvm_circuit;
+pub mod hash160;
+pub mod hash256;
 pub mod ripemd160;
+pub mod sha1;
+pub mod sha256;
+#[cfg(test)]
+pub(crate) mod testing;
 
 use halo2_proofs::arithmetic::{Field as Halo2Field, FieldExt};
 use halo2_proofs::halo2curves::group::ff::PrimeField;
@@ -8,11 +8,11 @@ use std::fmt;
 
 use halo2_proofs::{
     arithmetic::FieldExt,
-    circuit::{Chip, Layouter},
-    plonk::Error,
+    circuit::{Chip, Layouter, Value},
+    plonk::{Column, Error, Instance},
 };
 
-use self::ref_impl::constants::{BLOCK_SIZE, DIGEST_SIZE};
+use self::ref_impl::constants::{BLOCK_SIZE, DIGEST_SIZE, DIGEST_SIZE_BYTES};
 
 /// The set of circuit instructions required to use the [`RIPEMD160`] gadget.
 pub trait RIPEMD160Instructions<F: FieldExt>: Chip<F> {
@@ -21,6 +21,9 @@ pub trait RIPEMD160Instructions<F: FieldExt>: Chip<F> {
     /// Variable representing a 32-bit word of the input block to the RIPEMD-160 compression
     /// function.
     type BlockWord: Copy + fmt::Debug + Default;
+    /// Variable representing a 32-bit word of a message block which is assigned in the
+    /// circuit.
+    type AssignedWord: Clone + fmt::Debug;
 
     /// Places the RIPEMD-160 IV in the circuit, returning the initial state variable.
     fn initialization_vector(&self, layouter: &mut impl Layouter<F>) -> Result<Self::State, Error>;
@@ -34,18 +37,120 @@ pub trait RIPEMD160Instructions<F: FieldExt>: Chip<F> {
         input: [Self::BlockWord; BLOCK_SIZE],
     ) -> Result<Self::State, Error>;
 
-    /// Converts the given state into a message digest.
+    /// Starting from the given initialized state, processes the blocks of input in order
+    /// and returns the final state. The blocks can share a region, so this may take fewer
+    /// rows than calling [`RIPEMD160Instructions::compress`] for each block.
+    fn compress_blocks(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &Self::State,
+        input: &[[Self::BlockWord; BLOCK_SIZE]],
+    ) -> Result<Self::State, Error>;
+
+    /// Pads the first `len` bytes of `message` in the circuit and returns the padded blocks.
+    /// The number of blocks is fixed by `message.len()`, so `len` has to be padded to the
+    /// same number of blocks.
+    fn pad_message(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        message: &[Value<u8>],
+        len: Value<u64>,
+    ) -> Result<Vec<[Self::AssignedWord; BLOCK_SIZE]>, Error>;
+
+    /// Pads the message made of the big-endian bytes of `words`, which are already
+    /// assigned in the circuit, e.g. by a SHA-256 gadget, and returns the padded blocks.
+    /// The message length is fixed by `words.len()`.
+    fn pad_be_words(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        words: &[Self::AssignedWord],
+    ) -> Result<Vec<[Self::AssignedWord; BLOCK_SIZE]>, Error>;
+
+    /// Starting from the given initialized state, processes a block which is already
+    /// assigned in the circuit and returns the final state.
+    fn compress_assigned(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &Self::State,
+        input: &[Self::AssignedWord; BLOCK_SIZE],
+    ) -> Result<Self::State, Error>;
+
+    /// Converts the given state into a message digest with words in the given byte order.
     fn digest(
         &self,
         layouter: &mut impl Layouter<F>,
         state: &Self::State,
+        byte_order: DigestByteOrder,
+    ) -> Result<[Self::BlockWord; DIGEST_SIZE], Error>;
+
+    /// Converts the state at position `num_blocks - 1` of `states` into a message digest
+    /// with words in the given byte order. The number of blocks is a witness, so the
+    /// circuit is the same for every number of blocks up to `states.len()`.
+    fn select_digest(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        states: &[Self::State],
+        num_blocks: Value<u64>,
+        byte_order: DigestByteOrder,
+    ) -> Result<[Self::BlockWord; DIGEST_SIZE], Error>;
+
+    /// Converts the given state into a message digest and constrains the digest words
+    /// to be equal to the `DIGEST_SIZE` cells of `instance` starting at `start_row`.
+    fn digest_to_instance(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &Self::State,
+        instance: Column<Instance>,
+        start_row: usize,
     ) -> Result<[Self::BlockWord; DIGEST_SIZE], Error>;
 }
 
+/// Byte order of the digest words returned by the [`RIPEMD160`] gadget.
+///
+/// Bitcoin uses RIPEMD-160 digests in two byte orders. The default is `Internal`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DigestByteOrder {
+    /// The state words of the RIPEMD-160 specification. Their little-endian bytes are the
+    /// digest bytes as they appear in scripts, e.g. the hash in a P2PKH scriptPubkey.
+    Internal,
+    /// The little-endian words of the reversed digest bytes, matching the byte order in
+    /// which Bitcoin software displays hashes.
+    Display,
+}
+
+impl Default for DigestByteOrder {
+    fn default() -> Self {
+        DigestByteOrder::Internal
+    }
+}
+
 /// The output of a RIPEMD-160 circuit invocation.
 #[derive(Debug)]
 pub struct RIPEMD160Digest<BlockWord>([BlockWord; DIGEST_SIZE]);
 
+impl<BlockWord> RIPEMD160Digest<BlockWord> {
+    /// Returns the words of the digest in the byte order it was finalized with.
+    pub fn words(&self) -> &[BlockWord; DIGEST_SIZE] {
+        &self.0
+    }
+}
+
+impl RIPEMD160Digest<table16::BlockWord> {
+    /// Returns the little-endian bytes of the digest words. For a digest in the
+    /// [`DigestByteOrder::Internal`] byte order these are the digest bytes as they appear
+    /// in scripts, and for [`DigestByteOrder::Display`] the reversed bytes.
+    pub fn to_le_bytes(&self) -> Value<[u8; DIGEST_SIZE_BYTES]> {
+        let words: Value<Vec<u32>> = self.0.iter().map(|word| word.0).collect();
+        words.map(|words| {
+            let mut bytes = [0u8; DIGEST_SIZE_BYTES];
+            for (chunk, word) in bytes.chunks_exact_mut(4).zip(words) {
+                chunk.copy_from_slice(&word.to_le_bytes());
+            }
+            bytes
+        })
+    }
+}
+
 /// A gadget that constrains a RIPEMD-160 invocation. It supports input at a granularity of
 /// 32 bits.
 #[derive(Debug)]
@@ -84,34 +189,133 @@ impl<F: FieldExt, RIPEMD160Chip: RIPEMD160Instructions<F>> RIPEMD160<F, RIPEMD16
         Ok(())
     }
 
-    /// Retrieve result and consume hasher instance.
+    /// Updating the internal state by consuming all message blocks like
+    /// [`RIPEMD160::update`], but assigning the blocks in a single region. The digest is
+    /// the same, and the blocks are packed into fewer rows.
+    pub fn update_blocks(
+        &mut self,
+        mut layouter: impl Layouter<F>,
+        data: &[[RIPEMD160Chip::BlockWord; BLOCK_SIZE]],
+    ) -> Result<(), Error> {
+        self.state = self.chip.compress_blocks(&mut layouter, &self.state, data)?;
+        Ok(())
+    }
+
+    /// Updating the internal state by consuming message blocks which are already
+    /// assigned in the circuit, e.g. by [`RIPEMD160Instructions::pad_message`]
+    pub fn update_assigned(
+        &mut self,
+        mut layouter: impl Layouter<F>,
+        data: &[[RIPEMD160Chip::AssignedWord; BLOCK_SIZE]],
+    ) -> Result<(), Error> {
+        for b in data {
+            self.state = self.chip.compress_assigned(&mut layouter, &self.state, b)?;
+        }
+
+        Ok(())
+    }
+
+    /// Retrieve result in the given byte order and consume hasher instance.
     pub fn finalize(
         self,
         mut layouter: impl Layouter<F>,
+        byte_order: DigestByteOrder,
     ) -> Result<RIPEMD160Digest<RIPEMD160Chip::BlockWord>, Error> {
         self.chip
-            .digest(&mut layouter, &self.state)
+            .digest(&mut layouter, &self.state, byte_order)
             .map(RIPEMD160Digest)
     }
 
-    /// Convenience function to compute hash of the data.
+    /// Retrieve result, constrain it to be equal to the expected digest in the
+    /// `instance` column starting at `start_row`, and consume hasher instance.
+    pub fn finalize_to_instance(
+        self,
+        mut layouter: impl Layouter<F>,
+        instance: Column<Instance>,
+        start_row: usize,
+    ) -> Result<RIPEMD160Digest<RIPEMD160Chip::BlockWord>, Error> {
+        self.chip
+            .digest_to_instance(&mut layouter, &self.state, instance, start_row)
+            .map(RIPEMD160Digest)
+    }
+
+    /// Convenience function to compute hash of the data in the given byte order.
     pub fn digest(
         chip: RIPEMD160Chip,
         mut layouter: impl Layouter<F>,
         data: &Vec<[RIPEMD160Chip::BlockWord; BLOCK_SIZE]>,
+        byte_order: DigestByteOrder,
+    ) -> Result<RIPEMD160Digest<RIPEMD160Chip::BlockWord>, Error> {
+        let mut hasher = Self::new(chip, layouter.namespace(|| "init"))?;
+        hasher.update(layouter.namespace(|| "update"), data)?;
+        hasher.finalize(layouter.namespace(|| "finalize"), byte_order)
+    }
+
+    /// Convenience function to compute hash of the first `num_blocks` blocks of the padded
+    /// data in the given byte order. All `MAX_BLOCKS` blocks are compressed and the digest
+    /// after the witnessed number of blocks is selected, so the circuit does not depend on
+    /// the length of the data. The blocks after the first `num_blocks` are ignored.
+    pub fn digest_variable_length<const MAX_BLOCKS: usize>(
+        chip: RIPEMD160Chip,
+        mut layouter: impl Layouter<F>,
+        data: &[[RIPEMD160Chip::BlockWord; BLOCK_SIZE]; MAX_BLOCKS],
+        num_blocks: Value<u64>,
+        byte_order: DigestByteOrder,
+    ) -> Result<RIPEMD160Digest<RIPEMD160Chip::BlockWord>, Error> {
+        let mut hasher = Self::new(chip, layouter.namespace(|| "init"))?;
+        let mut states = Vec::with_capacity(MAX_BLOCKS);
+        for b in data {
+            hasher.state = hasher.chip.compress(&mut layouter.namespace(|| "update"), &hasher.state, *b)?;
+            states.push(hasher.state.clone());
+        }
+        hasher
+            .chip
+            .select_digest(&mut layouter.namespace(|| "select"), &states, num_blocks, byte_order)
+            .map(RIPEMD160Digest)
+    }
+
+    /// Convenience function to compute hash of the first `len` bytes of an unpadded
+    /// message. The padding is constrained in the circuit, so the length can be a
+    /// witness. The bytes of `message` from `len` onwards are ignored, but `len` must
+    /// be padded to the same number of blocks as `message.len()`.
+    pub fn digest_unpadded(
+        chip: RIPEMD160Chip,
+        mut layouter: impl Layouter<F>,
+        message: &[Value<u8>],
+        len: Value<u64>,
+        byte_order: DigestByteOrder,
+    ) -> Result<RIPEMD160Digest<RIPEMD160Chip::BlockWord>, Error> {
+        let blocks = chip.pad_message(&mut layouter.namespace(|| "pad"), message, len)?;
+        let mut hasher = Self::new(chip, layouter.namespace(|| "init"))?;
+        hasher.update_assigned(layouter.namespace(|| "update"), &blocks)?;
+        hasher.finalize(layouter.namespace(|| "finalize"), byte_order)
+    }
+
+    /// Convenience function to compute hash of the data and constrain it to be equal to
+    /// the expected digest in the `instance` column starting at `start_row`. A verifier
+    /// is then convinced of the digest without access to the data.
+    pub fn digest_to_instance(
+        chip: RIPEMD160Chip,
+        mut layouter: impl Layouter<F>,
+        data: &Vec<[RIPEMD160Chip::BlockWord; BLOCK_SIZE]>,
+        instance: Column<Instance>,
+        start_row: usize,
     ) -> Result<RIPEMD160Digest<RIPEMD160Chip::BlockWord>, Error> {
         let mut hasher = Self::new(chip, layouter.namespace(|| "init"))?;
         hasher.update(layouter.namespace(|| "update"), data)?;
-        hasher.finalize(layouter.namespace(|| "finalize"))
+        hasher.finalize_to_instance(layouter.namespace(|| "finalize"), instance, start_row)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use halo2_proofs::{plonk::{Circuit, ConstraintSystem, self}, halo2curves::pasta::pallas, circuit::{SimpleFloorPlanner, Layouter}, dev::MockProver};
+    use halo2_proofs::{plonk::{Circuit, Column, ConstraintSystem, Instance, self}, halo2curves::pasta::pallas, circuit::{SimpleFloorPlanner, Layouter, Value}, dev::MockProver};
 
-    use crate::ripemd160::{table16::{Table16Config, Table16Chip, util::{convert_byte_slice_to_u32_slice, convert_byte_slice_to_blockword_slice}, BlockWord}, RIPEMD160, ref_impl::{ripemd160::hash, constants::DIGEST_SIZE}};
+    use crate::ripemd160::{table16::{Table16Config, Table16Chip, util::{convert_byte_slice_to_u32_slice, convert_byte_slice_to_blockword_slice}, BlockWord}, RIPEMD160, ref_impl::{ripemd160::hash, constants::DIGEST_SIZE}, DigestByteOrder};
     use crate::ripemd160::ref_impl::ripemd160::pad_message_bytes;
+    use halo2_proofs::halo2curves::bn256::Fr as BnScalar;
+    use crate::bitcoinvm_circuit::util::region_profile::print_region_summary;
+    use halo2_proofs::arithmetic::Field;
     use crate::ripemd160::ref_impl::constants::{BLOCK_SIZE, BLOCK_SIZE_BYTES};
 
 
@@ -120,7 +324,7 @@ mod tests {
         struct MyCircuit {}
 
         impl Circuit<pallas::Base> for MyCircuit {
-            type Config = Table16Config;
+            type Config = Table16Config<pallas::Base>;
             type FloorPlanner = SimpleFloorPlanner;
             
             fn without_witnesses(&self) -> Self {
@@ -144,7 +348,7 @@ mod tests {
                     .map(convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
                     .collect();
                 
-                let digest = RIPEMD160::digest(table16_chip, layouter, &data)?;
+                let digest = RIPEMD160::digest(table16_chip, layouter, &data, DigestByteOrder::Internal)?;
 
                 let output: [u32; DIGEST_SIZE] = convert_byte_slice_to_u32_slice(hash(input));
                 for (idx, digest_word) in digest.0.iter().enumerate() {
@@ -165,4 +369,561 @@ mod tests {
         };
         assert_eq!(prover.verify(), Ok(()));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn hash_abc_byte_orders() {
+        struct MyCircuit {
+            byte_order: DigestByteOrder,
+            expected: [u32; DIGEST_SIZE],
+        }
+
+        impl Circuit<pallas::Base> for MyCircuit {
+            type Config = Table16Config<pallas::Base>;
+            type FloorPlanner = SimpleFloorPlanner;
+            
+            fn without_witnesses(&self) -> Self {
+                MyCircuit { byte_order: self.byte_order, expected: self.expected }
+            }
+
+            fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+                Table16Chip::configure(meta)
+            }
+
+            fn synthesize(
+                &self, config: Self::Config,
+                mut layouter: impl Layouter<pallas::Base>,
+            ) -> Result<(), plonk::Error> {
+                let table16_chip = Table16Chip::construct(config.clone());
+                Table16Chip::load(config, &mut layouter)?;
+
+                let data: Vec<[BlockWord; BLOCK_SIZE]> = pad_message_bytes(b"abc".to_vec())
+                    .into_iter()
+                    .map(convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
+                    .collect();
+                
+                let digest = RIPEMD160::digest(table16_chip, layouter, &data, self.byte_order)?;
+                for (idx, digest_word) in digest.0.iter().enumerate() {
+                    digest_word.0.assert_if_known(|v| {
+                        *v == self.expected[idx]
+                    });
+                }
+
+                Ok(())
+            }
+        }
+
+        // RIPEMD-160("abc") as it appears in scripts and as it is displayed
+        for (byte_order, expected_hex) in [
+            (DigestByteOrder::Internal, "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc"),
+            (DigestByteOrder::Display, "fc0b5af187b0c6988e4a049b7a985de0f708b28e"),
+        ] {
+            let expected_bytes: [u8; 4*DIGEST_SIZE] = hex::decode(expected_hex).unwrap().try_into().unwrap();
+            let circuit = MyCircuit {
+                byte_order,
+                expected: convert_byte_slice_to_u32_slice(expected_bytes),
+            };
+
+            let prover = MockProver::<pallas::Base>::run(17, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+        assert_eq!(DigestByteOrder::default(), DigestByteOrder::Internal);
+    }
+
+    #[test]
+    fn digest_bytes() {
+        struct MyCircuit {}
+
+        impl Circuit<pallas::Base> for MyCircuit {
+            type Config = Table16Config<pallas::Base>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit {}
+            }
+
+            fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+                Table16Chip::configure(meta)
+            }
+
+            fn synthesize(
+                &self, config: Self::Config,
+                mut layouter: impl Layouter<pallas::Base>,
+            ) -> Result<(), plonk::Error> {
+                let table16_chip = Table16Chip::construct(config.clone());
+                Table16Chip::load(config, &mut layouter)?;
+
+                let data: Vec<[BlockWord; BLOCK_SIZE]> = pad_message_bytes(b"abc".to_vec())
+                    .into_iter()
+                    .map(convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
+                    .collect();
+
+                let digest = RIPEMD160::digest(table16_chip, layouter, &data, DigestByteOrder::Internal)?;
+
+                let expected = hash(b"abc".to_vec());
+                let expected_words: [u32; DIGEST_SIZE] = convert_byte_slice_to_u32_slice(expected);
+                for (word, expected_word) in digest.words().iter().zip(expected_words) {
+                    word.0.assert_if_known(|v| *v == expected_word);
+                }
+                digest.to_le_bytes().assert_if_known(|bytes| *bytes == expected);
+
+                Ok(())
+            }
+        }
+
+        let prover = MockProver::<pallas::Base>::run(17, &MyCircuit {}, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn hash_with_shared_spread_table() {
+        struct MyCircuit {}
+
+        impl Circuit<pallas::Base> for MyCircuit {
+            type Config = (Table16Config<pallas::Base>, Table16Config<pallas::Base>);
+            type FloorPlanner = SimpleFloorPlanner;
+            
+            fn without_witnesses(&self) -> Self {
+                MyCircuit {}
+            }
+
+            fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+                let config = Table16Chip::configure(meta);
+                let shared_config = Table16Chip::configure_with_spread_table(meta, config.spread_table());
+                (config, shared_config)
+            }
+
+            fn synthesize(
+                &self, config: Self::Config,
+                mut layouter: impl Layouter<pallas::Base>,
+            ) -> Result<(), plonk::Error> {
+                // The spread table is loaded once for both chips
+                Table16Chip::load(config.0.clone(), &mut layouter)?;
+
+                for (i, chip_config) in [config.0, config.1].into_iter().enumerate() {
+                    let table16_chip = Table16Chip::construct(chip_config);
+
+                    let input = vec![b'a' + i as u8; 3];
+                    let data: Vec<[BlockWord; BLOCK_SIZE]> = pad_message_bytes(input.clone())
+                        .into_iter()
+                        .map(convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
+                        .collect();
+
+                    let digest = RIPEMD160::digest(table16_chip, layouter.namespace(|| "digest"), &data, DigestByteOrder::Internal)?;
+
+                    let output: [u32; DIGEST_SIZE] = convert_byte_slice_to_u32_slice(hash(input));
+                    for (idx, digest_word) in digest.0.iter().enumerate() {
+                        digest_word.0.assert_if_known(|v| {
+                            *v == output[idx]
+                        });
+                    }
+                }
+
+                Ok(())
+            }
+        }
+
+        let circuit: MyCircuit = MyCircuit {};
+
+        let prover = match MockProver::<pallas::Base>::run(17, &circuit, vec![]) {
+            Ok(prover) => prover,
+            Err(e) => panic!("{:?}", e),
+        };
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn hash_abc_bn256() {
+        struct MyCircuit {}
+
+        impl Circuit<BnScalar> for MyCircuit {
+            type Config = Table16Config<BnScalar>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit {}
+            }
+
+            fn configure(meta: &mut ConstraintSystem<BnScalar>) -> Self::Config {
+                Table16Chip::configure(meta)
+            }
+
+            fn synthesize(
+                &self, config: Self::Config,
+                mut layouter: impl Layouter<BnScalar>,
+            ) -> Result<(), plonk::Error> {
+                let table16_chip = Table16Chip::construct(config.clone());
+                Table16Chip::load(config, &mut layouter)?;
+
+                let data: Vec<[BlockWord; BLOCK_SIZE]> = pad_message_bytes(b"abc".to_vec())
+                    .into_iter()
+                    .map(convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
+                    .collect();
+
+                let digest = RIPEMD160::digest(table16_chip, layouter, &data, DigestByteOrder::Internal)?;
+
+                let output: [u32; DIGEST_SIZE] = convert_byte_slice_to_u32_slice(hash(b"abc".to_vec()));
+                for (idx, digest_word) in digest.0.iter().enumerate() {
+                    digest_word.0.assert_if_known(|v| *v == output[idx]);
+                }
+
+                Ok(())
+            }
+        }
+
+        // The chip works over the scalar field of bn256 used by the BitcoinVM circuit
+        let circuit: MyCircuit = MyCircuit {};
+        let prover = MockProver::<BnScalar>::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Spread table not loaded")]
+    fn hash_without_loading_spread_table() {
+        struct MyCircuit {}
+
+        impl Circuit<pallas::Base> for MyCircuit {
+            type Config = Table16Config<pallas::Base>;
+            type FloorPlanner = SimpleFloorPlanner;
+            
+            fn without_witnesses(&self) -> Self {
+                MyCircuit {}
+            }
+
+            fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+                Table16Chip::configure(meta)
+            }
+
+            fn synthesize(
+                &self, config: Self::Config,
+                layouter: impl Layouter<pallas::Base>,
+            ) -> Result<(), plonk::Error> {
+                // Table16Chip::load is not called
+                let table16_chip = Table16Chip::construct(config);
+
+                let data: Vec<[BlockWord; BLOCK_SIZE]> = pad_message_bytes(b"abc".to_vec())
+                    .into_iter()
+                    .map(convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
+                    .collect();
+                
+                RIPEMD160::digest(table16_chip, layouter, &data, DigestByteOrder::Internal)?;
+                Ok(())
+            }
+        }
+
+        let circuit: MyCircuit = MyCircuit {};
+        let _ = MockProver::<pallas::Base>::run(17, &circuit, vec![]);
+    }
+
+    #[derive(Clone, Debug)]
+    struct PublicDigestConfig {
+        table16_config: Table16Config<pallas::Base>,
+        instance: Column<Instance>,
+    }
+
+    struct PublicDigestCircuit {
+        input: Vec<u8>,
+    }
+
+    impl Circuit<pallas::Base> for PublicDigestCircuit {
+        type Config = PublicDigestConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            PublicDigestCircuit { input: self.input.clone() }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+            PublicDigestConfig {
+                table16_config: Table16Chip::configure(meta),
+                instance,
+            }
+        }
+
+        fn synthesize(
+            &self, config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), plonk::Error> {
+            let table16_chip = Table16Chip::construct(config.table16_config.clone());
+            Table16Chip::load(config.table16_config, &mut layouter)?;
+
+            let data: Vec<[BlockWord; BLOCK_SIZE]> = pad_message_bytes(self.input.clone())
+                .into_iter()
+                .map(convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
+                .collect();
+
+            RIPEMD160::digest_to_instance(table16_chip, layouter, &data, config.instance, 0)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn hash_abc_public_digest() {
+        let input = b"abc".to_vec();
+        let circuit = PublicDigestCircuit { input: input.clone() };
+
+        let expected_digest: [u32; DIGEST_SIZE] = convert_byte_slice_to_u32_slice(hash(input));
+        let public_input: Vec<pallas::Base> = expected_digest
+            .iter()
+            .map(|w| pallas::Base::from(*w as u64))
+            .collect();
+
+        let prover = MockProver::<pallas::Base>::run(17, &circuit, vec![public_input.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // A wrong expected digest is rejected
+        let mut wrong_public_input = public_input;
+        wrong_public_input[0] += pallas::Base::one();
+        let prover = MockProver::<pallas::Base>::run(17, &circuit, vec![wrong_public_input]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn hash_abc_region_profile() {
+        let circuit = PublicDigestCircuit { input: b"abc".to_vec() };
+        let row_counts = print_region_summary(&circuit).unwrap();
+
+        let rows = |name: &str| row_counts
+            .iter()
+            .find(|(region, _)| region == name)
+            .map(|(_, rows)| *rows)
+            .unwrap_or_else(|| panic!("Region {} was not assigned", name));
+        assert!(rows("process message block") > 0);
+        // The 80 rounds on each line dominate the number of rows
+        let max_rows = row_counts.iter().map(|(_, rows)| *rows).max().unwrap();
+        assert_eq!(rows("compress"), max_rows);
+    }
+
+    struct UpdateBlocksCircuit {
+        input: Vec<u8>,
+        single_region: bool,
+    }
+
+    impl Circuit<pallas::Base> for UpdateBlocksCircuit {
+        type Config = Table16Config<pallas::Base>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            UpdateBlocksCircuit { input: self.input.clone(), single_region: self.single_region }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            Table16Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self, config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), plonk::Error> {
+            let table16_chip = Table16Chip::construct(config.clone());
+            Table16Chip::load(config, &mut layouter)?;
+
+            let data: Vec<[BlockWord; BLOCK_SIZE]> = pad_message_bytes(self.input.clone())
+                .into_iter()
+                .map(convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
+                .collect();
+
+            let mut hasher = RIPEMD160::new(table16_chip, layouter.namespace(|| "init"))?;
+            if self.single_region {
+                hasher.update_blocks(layouter.namespace(|| "update"), &data)?;
+            } else {
+                hasher.update(layouter.namespace(|| "update"), &data)?;
+            }
+            let digest = hasher.finalize(layouter.namespace(|| "finalize"), DigestByteOrder::Internal)?;
+
+            let output: [u32; DIGEST_SIZE] = convert_byte_slice_to_u32_slice(hash(self.input.clone()));
+            for (digest_word, expected) in digest.0.iter().zip(output) {
+                digest_word.0.assert_if_known(|v| *v == expected);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn update_blocks_in_single_region() {
+        // The longest message which is padded to 4 blocks
+        let input = vec![0xab; 4 * BLOCK_SIZE_BYTES - 9];
+        let per_block = UpdateBlocksCircuit { input: input.clone(), single_region: false };
+        let single_region = UpdateBlocksCircuit { input, single_region: true };
+
+        for circuit in [&per_block, &single_region] {
+            let prover = MockProver::<pallas::Base>::run(17, circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+
+        let block_rows = |circuit: &UpdateBlocksCircuit| -> usize {
+            print_region_summary(circuit)
+                .unwrap()
+                .iter()
+                .filter(|(name, _)| ["process message block", "compress", "compress blocks"].contains(&name.as_str()))
+                .map(|(_, rows)| *rows)
+                .sum()
+        };
+        let per_block_rows = block_rows(&per_block);
+        let single_region_rows = block_rows(&single_region);
+        assert!(single_region_rows < per_block_rows);
+    }
+
+    struct UnpaddedCircuit {
+        message: Vec<u8>,
+        len: u64,
+        expected: Option<[u32; DIGEST_SIZE]>,
+    }
+
+    impl Circuit<pallas::Base> for UnpaddedCircuit {
+        type Config = Table16Config<pallas::Base>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            UnpaddedCircuit { message: vec![0; self.message.len()], len: 0, expected: None }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            Table16Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self, config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), plonk::Error> {
+            let table16_chip = Table16Chip::construct(config.clone());
+            Table16Chip::load(config, &mut layouter)?;
+
+            let message: Vec<Value<u8>> = self.message.iter().map(|b| Value::known(*b)).collect();
+            let digest = RIPEMD160::digest_unpadded(
+                table16_chip,
+                layouter,
+                &message,
+                Value::known(self.len),
+                DigestByteOrder::Internal,
+            )?;
+
+            if let Some(expected) = self.expected {
+                for (idx, digest_word) in digest.0.iter().enumerate() {
+                    digest_word.0.assert_if_known(|v| *v == expected[idx]);
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn hash_unpadded_at_block_boundaries() {
+        // Messages ending just before and after the length field and the block boundary
+        for len in [55usize, 56, 63, 64] {
+            let message: Vec<u8> = (0..len as u8).map(|i| i.wrapping_mul(37)).collect();
+            let circuit = UnpaddedCircuit {
+                message: message.clone(),
+                len: len as u64,
+                expected: Some(convert_byte_slice_to_u32_slice(hash(message))),
+            };
+
+            let prover = MockProver::<pallas::Base>::run(17, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()), "message length {}", len);
+        }
+    }
+
+    #[test]
+    fn hash_unpadded_shorter_than_buffer() {
+        // The bytes after the witnessed length are ignored
+        let message: Vec<u8> = (0..60u8).collect();
+        let circuit = UnpaddedCircuit {
+            message: message.clone(),
+            len: 58,
+            expected: Some(convert_byte_slice_to_u32_slice(hash(message[..58].to_vec()))),
+        };
+
+        let prover = MockProver::<pallas::Base>::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn hash_unpadded_rejects_wrong_number_of_blocks() {
+        // A 56-byte message needs a second block for the length field
+        let circuit = UnpaddedCircuit { message: vec![0x61; 55], len: 56, expected: None };
+        let prover = MockProver::<pallas::Base>::run(17, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // A 50-byte message fits in one block, so two blocks are not its padding
+        let circuit = UnpaddedCircuit { message: vec![0x61; 60], len: 50, expected: None };
+        let prover = MockProver::<pallas::Base>::run(17, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    const MAX_BLOCKS: usize = 3;
+
+    struct VariableLengthCircuit {
+        message: Vec<u8>,
+        num_blocks: u64,
+        check_digest: bool,
+    }
+
+    impl Circuit<pallas::Base> for VariableLengthCircuit {
+        type Config = Table16Config<pallas::Base>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            VariableLengthCircuit { message: vec![], num_blocks: 0, check_digest: false }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            Table16Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self, config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), plonk::Error> {
+            let table16_chip = Table16Chip::construct(config.clone());
+            Table16Chip::load(config, &mut layouter)?;
+
+            // The padded message followed by zero blocks up to MAX_BLOCKS
+            let mut data = [[BlockWord::default(); BLOCK_SIZE]; MAX_BLOCKS];
+            for (block, padded) in data.iter_mut().zip(pad_message_bytes(self.message.clone())) {
+                *block = convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>(padded);
+            }
+
+            let digest = RIPEMD160::digest_variable_length(
+                table16_chip,
+                layouter,
+                &data,
+                Value::known(self.num_blocks),
+                DigestByteOrder::Internal,
+            )?;
+
+            if self.check_digest {
+                let output: [u32; DIGEST_SIZE] = convert_byte_slice_to_u32_slice(hash(self.message.clone()));
+                for (idx, digest_word) in digest.0.iter().enumerate() {
+                    digest_word.0.assert_if_known(|v| *v == output[idx]);
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn hash_variable_length() {
+        // Messages padded to 1, 2 and MAX_BLOCKS blocks in the same circuit
+        for (len, num_blocks) in [(3usize, 1u64), (62, 2), (130, MAX_BLOCKS as u64)] {
+            let message: Vec<u8> = (0..len).map(|i| (i * 7) as u8).collect();
+            assert_eq!(pad_message_bytes(message.clone()).len() as u64, num_blocks);
+            let circuit = VariableLengthCircuit { message, num_blocks, check_digest: true };
+
+            let prover = MockProver::<pallas::Base>::run(17, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()), "{} blocks", num_blocks);
+        }
+    }
+
+    #[test]
+    fn hash_variable_length_rejects_out_of_range_count() {
+        for num_blocks in [0, MAX_BLOCKS as u64 + 1] {
+            let circuit = VariableLengthCircuit { message: b"abc".to_vec(), num_blocks, check_digest: false };
+
+            let prover = MockProver::<pallas::Base>::run(17, &circuit, vec![]).unwrap();
+            assert!(prover.verify().is_err(), "{} blocks", num_blocks);
+        }
+    }
+}
@@ -114,3 +114,72 @@ pub const TEST_INPUT_HASH_A2Z: [u8; DIGEST_SIZE_BYTES] = [
     0x5B, 0x9D, 0x28, 0x65,
     0xB3, 0x70, 0x8D, 0xBC,
 ];
+
+/// Checks of the constant tables against their definitions in the RIPEMD-160
+/// specification, since a typo would give wrong hashes in both the reference
+/// implementation and the circuit.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The permutation ρ of the message words between phases of the left line
+    const RHO: [usize; ROUND_PHASE_SIZE] = [7, 4, 13, 1, 10, 6, 15, 3, 12, 0, 9, 5, 2, 14, 11, 8];
+
+    // Rotate amounts of each phase, indexed by the selected message word
+    const SHIFTS: [[u8; ROUND_PHASE_SIZE]; ROUNDS / ROUND_PHASE_SIZE] = [
+        [11, 14, 15, 12, 5, 8, 7, 9, 11, 13, 14, 15, 6, 7, 9, 8],
+        [12, 13, 11, 15, 6, 9, 9, 7, 12, 15, 11, 13, 7, 8, 7, 7],
+        [13, 15, 14, 11, 7, 7, 6, 8, 13, 14, 13, 12, 5, 5, 6, 9],
+        [14, 11, 12, 14, 8, 6, 5, 5, 15, 12, 15, 14, 9, 9, 8, 6],
+        [15, 12, 13, 13, 9, 5, 8, 6, 14, 11, 12, 11, 8, 6, 5, 5],
+    ];
+
+    #[test]
+    fn message_selection_is_a_permutation_per_phase() {
+        for table in [MSG_SEL_IDX_LEFT, MSG_SEL_IDX_RIGHT] {
+            for phase in table.chunks(ROUND_PHASE_SIZE) {
+                let mut seen = [false; ROUND_PHASE_SIZE];
+                for idx in phase {
+                    assert!(!seen[*idx], "word {} is selected twice in a phase", idx);
+                    seen[*idx] = true;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn message_selection_matches_spec() {
+        // The left line starts with the identity and the right line with π(i) = 9i + 5 mod 16,
+        // and each phase applies ρ to the previous one
+        let mut left: [usize; ROUND_PHASE_SIZE] = core::array::from_fn(|i| i);
+        let mut right: [usize; ROUND_PHASE_SIZE] = core::array::from_fn(|i| (9 * i + 5) % 16);
+        for phase in 0..ROUNDS / ROUND_PHASE_SIZE {
+            let rounds = phase * ROUND_PHASE_SIZE..(phase + 1) * ROUND_PHASE_SIZE;
+            assert_eq!(MSG_SEL_IDX_LEFT[rounds.clone()], left, "left phase {}", phase);
+            assert_eq!(MSG_SEL_IDX_RIGHT[rounds], right, "right phase {}", phase);
+            left = left.map(|i| RHO[i]);
+            right = right.map(|i| RHO[i]);
+        }
+    }
+
+    #[test]
+    fn rotate_amounts_match_spec() {
+        for round in 0..ROUNDS {
+            let shifts = &SHIFTS[round / ROUND_PHASE_SIZE];
+            for (amounts, msg_sel) in [(ROL_AMOUNT_LEFT, MSG_SEL_IDX_LEFT), (ROL_AMOUNT_RIGHT, MSG_SEL_IDX_RIGHT)] {
+                assert!((5..=15).contains(&amounts[round]), "round {}", round);
+                assert_eq!(amounts[round], shifts[msg_sel[round]], "round {}", round);
+            }
+        }
+    }
+
+    #[test]
+    fn round_constants_match_spec() {
+        // The integer parts of 2^30 times the square and cube roots of 2, 3, 5 and 7
+        let sqrt = [2f64, 3., 5., 7.].map(|x| (x.sqrt() * 2f64.powi(30)) as u32);
+        let cbrt = [2f64, 3., 5., 7.].map(|x| (x.cbrt() * 2f64.powi(30)) as u32);
+        assert_eq!(ROUND_CONSTANTS_LEFT, [0, sqrt[0], sqrt[1], sqrt[2], sqrt[3]]);
+        assert_eq!(ROUND_CONSTANTS_RIGHT, [cbrt[0], cbrt[1], cbrt[2], cbrt[3], 0]);
+        assert_eq!(INITIAL_VALUES, [0x6745_2301, 0xEFCD_AB89, 0x98BA_DCFE, 0x1032_5476, 0xC3D2_E1F0]);
+    }
+}
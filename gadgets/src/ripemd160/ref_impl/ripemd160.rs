@@ -278,4 +278,78 @@ mod tests {
             assert_eq!(u64::from_le_bytes(blocks[1][56..].try_into().expect("error")), (msg.len() << 3) as u64);
         }
     }
-}
\ No newline at end of file
+}
+/// Known-answer tests of the reference implementation, which all circuit tests compare against
+#[cfg(test)]
+mod known_answer_tests {
+    use std::convert::TryInto;
+
+    use bitcoin_hashes::{ripemd160, Hash};
+
+    use super::super::constants::{BLOCK_SIZE_BYTES, DIGEST_SIZE_BYTES};
+    use super::{hash, pad_message_bytes};
+
+    fn hex_digest(msg: &[u8]) -> String {
+        hex::encode(hash(msg.to_vec()))
+    }
+
+    // Test vectors from https://homes.esat.kuleuven.be/~bosselae/ripemd160.html
+    #[test]
+    fn official_test_vectors() {
+        let vectors: [(&[u8], &str); 8] = [
+            (b"", "9c1185a5c5e9fc54612808977ee8f548b2258d31"),
+            (b"a", "0bdc9d2d256b3ee9daae347be6f4dc835a467ffe"),
+            (b"abc", "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc"),
+            (b"message digest", "5d0689ef49d2fae572b881b123a85ffa21595f36"),
+            (b"abcdefghijklmnopqrstuvwxyz", "f71c27109c692c1b56bbdceb5b9d2865b3708dbc"),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "12a053384a9c0c88e405a06c27dcf49ada62eb2b",
+            ),
+            (
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                "b0e20b6e3116640286ed3a87a5713079b21f5189",
+            ),
+            (
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "9b752e45573d4b39f4dbd3323cab82bf63326bfb",
+            ),
+        ];
+        for (msg, expected) in vectors {
+            assert_eq!(hex_digest(msg), expected, "message {:?}", String::from_utf8_lossy(msg));
+        }
+    }
+
+    #[test]
+    fn million_a() {
+        assert_eq!(hex_digest(&vec![b'a'; 1_000_000]), "52783243c1697bdbe16d37f97f68f08325dc1528");
+    }
+
+    // A shortened version of the million a's case, covering every length around the
+    // first block boundaries
+    #[test]
+    fn matches_bitcoin_hashes() {
+        for len in 0..=3 * BLOCK_SIZE_BYTES {
+            let msg = vec![b'a'; len];
+            let expected: [u8; DIGEST_SIZE_BYTES] = ripemd160::Hash::hash(&msg).into_inner();
+            assert_eq!(hash(msg), expected, "length {}", len);
+        }
+    }
+
+    #[test]
+    fn padding_block_boundaries() {
+        // (message length, number of padded blocks)
+        for (len, num_blocks) in [(0, 1), (55, 1), (56, 2), (63, 2), (64, 2), (119, 2), (120, 3)] {
+            let msg: Vec<u8> = (0..len).map(|i| i as u8 + 1).collect();
+            let blocks = pad_message_bytes(msg.clone());
+            assert_eq!(blocks.len(), num_blocks, "length {}", len);
+
+            let padded = blocks.concat();
+            assert_eq!(padded[..len], msg[..]);
+            assert_eq!(padded[len], 0x80);
+            assert!(padded[len + 1..padded.len() - 8].iter().all(|byte| *byte == 0));
+            let len_bits = u64::from_le_bytes(padded[padded.len() - 8..].try_into().unwrap());
+            assert_eq!(len_bits, 8 * len as u64);
+        }
+    }
+}
@@ -6,24 +6,30 @@ use std::marker::PhantomData;
 
 //use super::Sha256Instructions;
 use halo2_proofs::{
+    arithmetic::FieldExt,
     circuit::{AssignedCell, Chip, Layouter, Region, Value},
-    plonk::{Advice, Any, Assigned, Column, ConstraintSystem, Error},
+    plonk::{Advice, Any, Assigned, Column, ConstraintSystem, Error, Instance},
 };
-use halo2_proofs::halo2curves::pasta::pallas;
 
 mod compression;
+mod digest_select;
 mod gates;
 mod message_schedule;
-mod spread_table;
+pub(crate) mod padding;
+pub(crate) mod spread_table;
 pub(crate) mod util;
 
 use gates::*;
 use spread_table::*;
+pub use spread_table::SpreadTableConfig;
 use message_schedule::*;
 use compression::*;
+use digest_select::DigestSelectConfig;
+use padding::{PaddingByteOrder, PaddingConfig};
+pub use padding::{num_padded_blocks, PaddedMessage};
 use util::*;
 use super::ref_impl::constants::*;
-use super::RIPEMD160Instructions;
+use super::{DigestByteOrder, RIPEMD160Instructions};
 
 #[derive(Clone, Copy, Debug, Default)]
 /// A word in a `Table16` message block.
@@ -66,10 +72,10 @@ impl<const LEN: usize> From<&Bits<LEN>> for [bool; LEN] {
     }
 }
 
-impl<const LEN: usize> From<&Bits<LEN>> for Assigned<pallas::Base> {
-    fn from(bits: &Bits<LEN>) -> Assigned<pallas::Base> {
+impl<F: FieldExt, const LEN: usize> From<&Bits<LEN>> for Assigned<F> {
+    fn from(bits: &Bits<LEN>) -> Assigned<F> {
         assert!(LEN <= 64);
-        pallas::Base::from(lebs2ip(&bits.0)).into()
+        F::from(lebs2ip(&bits.0)).into()
     }
 }
 
@@ -98,19 +104,19 @@ impl From<u32> for Bits<32> {
 }
 
 #[derive(Clone, Debug)]
-pub struct AssignedBits<const LEN: usize>(AssignedCell<Bits<LEN>, pallas::Base>);
+pub struct AssignedBits<F: FieldExt, const LEN: usize>(AssignedCell<Bits<LEN>, F>);
 
-impl<const LEN: usize> std::ops::Deref for AssignedBits<LEN> {
-    type Target = AssignedCell<Bits<LEN>, pallas::Base>;
+impl<F: FieldExt, const LEN: usize> std::ops::Deref for AssignedBits<F, LEN> {
+    type Target = AssignedCell<Bits<LEN>, F>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
-impl<const LEN: usize> AssignedBits<LEN> {
-    fn assign_bits<A, AR, T: TryInto<[bool; LEN]> + std::fmt::Debug + Clone>(
-        region: &mut Region<'_, pallas::Base>,
+impl<F: FieldExt, const LEN: usize> AssignedBits<F, LEN> {
+    pub(crate) fn assign_bits<A, AR, T: TryInto<[bool; LEN]> + std::fmt::Debug + Clone>(
+        region: &mut Region<'_, F>,
         annotation: A,
         column: impl Into<Column<Any>>,
         offset: usize,
@@ -142,13 +148,19 @@ impl<const LEN: usize> AssignedBits<LEN> {
     }
 }
 
-impl AssignedBits<16> {
-    fn value_u16(&self) -> Value<u16> {
+impl<F: FieldExt> AssignedBits<F, 8> {
+    pub(crate) fn value_u8(&self) -> Value<u8> {
+        self.value().map(|v| lebs2ip(&v.0) as u8)
+    }
+}
+
+impl<F: FieldExt> AssignedBits<F, 16> {
+    pub(crate) fn value_u16(&self) -> Value<u16> {
         self.value().map(|v| v.into())
     }
 
-    fn assign<A, AR>(
-        region: &mut Region<'_, pallas::Base>,
+    pub(crate) fn assign<A, AR>(
+        region: &mut Region<'_, F>,
         annotation: A,
         column: impl Into<Column<Any>>,
         offset: usize,
@@ -177,13 +189,13 @@ impl AssignedBits<16> {
     }
 }
 
-impl AssignedBits<32> {
-    fn value_u32(&self) -> Value<u32> {
+impl<F: FieldExt> AssignedBits<F, 32> {
+    pub(crate) fn value_u32(&self) -> Value<u32> {
         self.value().map(|v| v.into())
     }
 
-    fn assign<A, AR>(
-        region: &mut Region<'_, pallas::Base>,
+    pub(crate) fn assign<A, AR>(
+        region: &mut Region<'_, F>,
         annotation: A,
         column: impl Into<Column<Any>>,
         offset: usize,
@@ -216,21 +228,32 @@ pub const NUM_ADVICE_COLS: usize = 3;
 
 /// Configuration for a [`Table16Chip`].
 #[derive(Clone, Debug)]
-pub struct Table16Config {
+pub struct Table16Config<F: FieldExt> {
     lookup: SpreadTableConfig,
-    message_schedule: MessageScheduleConfig,
-    compression: CompressionConfig,
+    message_schedule: MessageScheduleConfig<F>,
+    // Assigns message words in the columns of the right line, next to the left line
+    right_message_schedule: MessageScheduleConfig<F>,
+    compression: CompressionConfig<F>,
+    padding: PaddingConfig<F>,
+    digest_select: DigestSelectConfig<F>,
+}
+
+impl<F: FieldExt> Table16Config<F> {
+    /// Returns the spread table configuration, to be shared with other chips
+    pub fn spread_table(&self) -> SpreadTableConfig {
+        self.lookup.clone()
+    }
 }
 
 /// A chip that implements RIPEMD-160 with a maximum lookup table size of $2^16$.
 #[derive(Clone, Debug)]
-pub struct Table16Chip {
-    config: Table16Config,
-    _marker: PhantomData<pallas::Base>,
+pub struct Table16Chip<F: FieldExt> {
+    config: Table16Config<F>,
+    _marker: PhantomData<F>,
 }
 
-impl Chip<pallas::Base> for Table16Chip {
-    type Config = Table16Config;
+impl<F: FieldExt> Chip<F> for Table16Chip<F> {
+    type Config = Table16Config<F>;
     type Loaded = ();
 
     fn config(&self) -> &Self::Config {
@@ -242,9 +265,9 @@ impl Chip<pallas::Base> for Table16Chip {
     }
 }
 
-impl Table16Chip {
+impl<F: FieldExt> Table16Chip<F> {
     /// Reconstructs this chip from the given config.
-    pub fn construct(config: <Self as Chip<pallas::Base>>::Config) -> Self {
+    pub fn construct(config: <Self as Chip<F>>::Config) -> Self {
         Self {
             config,
             _marker: PhantomData,
@@ -253,8 +276,24 @@ impl Table16Chip {
 
     /// Configures a circuit to include this chip.
     pub fn configure(
-        meta: &mut ConstraintSystem<pallas::Base>,
-    ) -> <Self as Chip<pallas::Base>>::Config {
+        meta: &mut ConstraintSystem<F>,
+    ) -> <Self as Chip<F>>::Config {
+        // - Three advice columns to interact with the lookup table.
+        let input_tag = meta.advice_column();
+        let input_dense = meta.advice_column();
+        let input_spread = meta.advice_column();
+
+        let lookup = SpreadTableChip::configure(meta, input_tag, input_dense, input_spread);
+
+        Self::configure_with_spread_table(meta, lookup)
+    }
+
+    /// Configures a circuit to include this chip, reusing a spread table configured by
+    /// another chip. The table only needs to be loaded once for all the chips sharing it.
+    pub fn configure_with_spread_table(
+        meta: &mut ConstraintSystem<F>,
+        lookup: SpreadTableConfig,
+    ) -> <Self as Chip<F>>::Config {
         // Columns required by this chip:
         let advice: [Column<Advice>; NUM_ADVICE_COLS]= [
             meta.advice_column(),
@@ -262,12 +301,6 @@ impl Table16Chip {
             meta.advice_column(),
         ];
 
-        // - Three advice columns to interact with the lookup table.
-        let input_tag = meta.advice_column();
-        let input_dense = meta.advice_column();
-        let input_spread = meta.advice_column();
-
-        let lookup = SpreadTableChip::configure(meta, input_tag, input_dense, input_spread);
         let lookup_inputs = lookup.input.clone();
 
         // Rename these here for ease of matching the gates to the specification.
@@ -285,46 +318,117 @@ impl Table16Chip {
 
         let s_decompose_word = meta.selector();
 
+        // A second group of columns for the right line of the compression function,
+        // which is assigned next to the left line
+        let right_input_tag = meta.advice_column();
+        let right_input_dense = meta.advice_column();
+        let right_input_spread = meta.advice_column();
+        let right_lookup_inputs = SpreadTableChip::configure_inputs(
+            meta,
+            &lookup,
+            right_input_tag,
+            right_input_dense,
+            right_input_spread,
+        );
+        let right_advice: [Column<Advice>; NUM_ADVICE_COLS] = [
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+        ];
+        for column in [right_lookup_inputs.dense, right_lookup_inputs.spread].iter().chain(right_advice.iter()) {
+            meta.enable_equality(*column);
+        }
+        let right_s_decompose_word = meta.selector();
+        let right_line =
+            CompressionConfig::configure(
+                meta,
+                right_lookup_inputs.clone(),
+                right_advice,
+                right_s_decompose_word
+            );
+        let right_message_schedule =
+            MessageScheduleConfig::configure(
+                meta,
+                right_lookup_inputs,
+                right_advice,
+                right_s_decompose_word
+            );
+
         let compression =
             CompressionConfig::configure(
                 meta,
                 lookup_inputs.clone(),
                 advice,
                 s_decompose_word
-            );
+            )
+            .with_right_line(right_line);
 
         let message_schedule =
             MessageScheduleConfig::configure(
                 meta,
-                lookup_inputs,
+                lookup_inputs.clone(),
                 advice,
                 s_decompose_word
             );
 
+        let padding = PaddingConfig::configure(meta, lookup_inputs, advice, PaddingByteOrder::LittleEndian);
+        let digest_select = DigestSelectConfig::configure(meta, advice);
+
         Table16Config {
             lookup,
             message_schedule,
+            right_message_schedule,
             compression,
+            padding,
+            digest_select,
         }
     }
 
+    /// Pads the first `len` bytes of `message` like [`RIPEMD160Instructions::pad_message`],
+    /// and also returns the cells of the padded bytes and of the message length.
+    pub fn pad_message_bytes(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        message: &[Value<u8>],
+        len: Value<u64>,
+    ) -> Result<PaddedMessage<F>, Error> {
+        self.config().lookup.assert_loaded();
+        self.config().padding.pad(layouter, message, len)
+    }
+
+    /// Converts the given state into the range checked bytes of its digest in the
+    /// [`DigestByteOrder::Internal`] byte order, i.e. the digest bytes as they appear in
+    /// scripts.
+    pub fn digest_bytes(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &State<F>,
+    ) -> Result<Vec<AssignedBits<F, 8>>, Error> {
+        let config = self.config();
+        config.lookup.assert_loaded();
+        let digest = config.compression.digest_cells(layouter, state.clone())?;
+        config.padding.word_bytes(layouter, &digest)
+    }
+
     /// Loads the lookup table required by this chip into the circuit.
     pub fn load(
-        config: Table16Config,
-        layouter: &mut impl Layouter<pallas::Base>,
+        config: Table16Config<F>,
+        layouter: &mut impl Layouter<F>,
     ) -> Result<(), Error> {
         SpreadTableChip::load(config.lookup, layouter)
     }
 }
 
-impl RIPEMD160Instructions<pallas::Base> for Table16Chip {
-    type State = State;
+impl<F: FieldExt> RIPEMD160Instructions<F> for Table16Chip<F> {
+    type State = State<F>;
     type BlockWord = BlockWord;
+    type AssignedWord = AssignedBits<F, 32>;
 
     fn initialization_vector(
         &self,
-        layouter: &mut impl Layouter<pallas::Base>,
-    ) -> Result<State, Error> {
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<Self::State, Error> {
+        self.config().lookup.assert_loaded();
         self.config().compression.initialize_with_iv(layouter, INITIAL_VALUES)
     }
 
@@ -332,41 +436,136 @@ impl RIPEMD160Instructions<pallas::Base> for Table16Chip {
     // message block and return the final state.
     fn compress(
         &self,
-        layouter: &mut impl Layouter<pallas::Base>,
+        layouter: &mut impl Layouter<F>,
         initialized_state: &Self::State,
         input: [Self::BlockWord; super::BLOCK_SIZE],
     ) -> Result<Self::State, Error> {
         let config = self.config();
+        config.lookup.assert_loaded();
         let (_, w_halves) = config.message_schedule.process(layouter, input)?;
         config
             .compression
             .compress(layouter, initialized_state.clone(), w_halves)
     }
 
+    fn compress_blocks(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &Self::State,
+        input: &[[Self::BlockWord; super::BLOCK_SIZE]],
+    ) -> Result<Self::State, Error> {
+        let config = self.config();
+        config.lookup.assert_loaded();
+        config.compression.compress_blocks_in_region(
+            layouter,
+            &config.right_message_schedule,
+            initialized_state.clone(),
+            input,
+        )
+    }
+
+    fn pad_message(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        message: &[Value<u8>],
+        len: Value<u64>,
+    ) -> Result<Vec<[Self::AssignedWord; super::BLOCK_SIZE]>, Error> {
+        self.config().lookup.assert_loaded();
+        self.config().padding.pad(layouter, message, len).map(|padded| padded.blocks)
+    }
+
+    fn pad_be_words(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        words: &[Self::AssignedWord],
+    ) -> Result<Vec<[Self::AssignedWord; super::BLOCK_SIZE]>, Error> {
+        self.config().lookup.assert_loaded();
+        self.config().padding.pad_be_words(layouter, words)
+    }
+
+    fn compress_assigned(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &Self::State,
+        input: &[Self::AssignedWord; super::BLOCK_SIZE],
+    ) -> Result<Self::State, Error> {
+        let config = self.config();
+        config.lookup.assert_loaded();
+        let (_, w_halves) = config.message_schedule.process_assigned(layouter, input)?;
+        config
+            .compression
+            .compress(layouter, initialized_state.clone(), w_halves)
+    }
+
     fn digest(
         &self,
-        layouter: &mut impl Layouter<pallas::Base>,
+        layouter: &mut impl Layouter<F>,
         state: &Self::State,
+        byte_order: DigestByteOrder,
     ) -> Result<[Self::BlockWord; super::DIGEST_SIZE], Error> {
         // Copy the dense forms of the state variable chunks down to this gate.
         // Reconstruct the 32-bit dense words.
-        self.config().compression.digest(layouter, state.clone())
+        let digest = self.config().compression.digest(layouter, state.clone())?;
+        Ok(reorder_digest(digest, byte_order))
+    }
+
+    fn select_digest(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        states: &[Self::State],
+        num_blocks: Value<u64>,
+        byte_order: DigestByteOrder,
+    ) -> Result<[Self::BlockWord; super::DIGEST_SIZE], Error> {
+        let config = self.config();
+        let digests = states
+            .iter()
+            .map(|state| config.compression.digest_cells(layouter, state.clone()))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let digest = config.digest_select.select(layouter, &digests, num_blocks)?;
+        Ok(reorder_digest(digest.map(|word| BlockWord(word.value_u32())), byte_order))
+    }
+
+    fn digest_to_instance(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &Self::State,
+        instance: Column<Instance>,
+        start_row: usize,
+    ) -> Result<[Self::BlockWord; super::DIGEST_SIZE], Error> {
+        let digest = self.config().compression.digest_cells(layouter, state.clone())?;
+        for (i, word) in digest.iter().enumerate() {
+            layouter.constrain_instance(word.cell(), instance, start_row + i)?;
+        }
+        Ok(digest.map(|word| BlockWord(word.value_u32())))
+    }
+}
+
+/// Returns the digest words of the state in the given byte order
+fn reorder_digest(digest: [BlockWord; DIGEST_SIZE], byte_order: DigestByteOrder) -> [BlockWord; DIGEST_SIZE] {
+    match byte_order {
+        DigestByteOrder::Internal => digest,
+        DigestByteOrder::Display => {
+            // Reversing the digest bytes reverses the word order and the bytes in each word
+            let mut reversed = digest.map(|word| BlockWord(word.0.map(u32::swap_bytes)));
+            reversed.reverse();
+            reversed
+        }
     }
 }
 
 /// Common assignment patterns used by Table16 regions.
-trait Table16Assignment {
+trait Table16Assignment<F: FieldExt> {
     fn assign_word_and_halves<A, AR>(
         &self,
         annotation: A,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         lookup: &SpreadInputs,
         a_3: Column<Advice>,
         a_4: Column<Advice>,
         a_5: Column<Advice>,
         word: Value<u32>,
         row: usize,
-    ) -> Result<(AssignedBits<32>, (SpreadVar<16, 32>, SpreadVar<16,32>)), Error> 
+    ) -> Result<(AssignedBits<F, 32>, (SpreadVar<F, 16, 32>, SpreadVar<F, 16, 32>)), Error> 
     where
         A: Fn() -> AR,
         AR: Into<String>,
@@ -384,7 +583,7 @@ trait Table16Assignment {
         let spread_w_hi = SpreadVar::with_lookup(region, &lookup, row + 1, spread_w_hi)?;
         spread_w_hi.dense.copy_advice(&annotation, region, a_4, row)?;
 
-        let w = AssignedBits::<32>::assign(
+        let w = AssignedBits::<F, 32>::assign(
             region,
             annotation,
             a_5,
@@ -6,12 +6,14 @@ use super::{
     AssignedBits, BlockWord, SpreadInputs, Table16Assignment, ROUNDS, DIGEST_SIZE, NUM_ADVICE_COLS,
 };
 use super::gates::Gate;
+use super::message_schedule::MessageScheduleConfig;
 use halo2_proofs::{
-    circuit::{Layouter, Value},
-    plonk::{Advice, Column, ConstraintSystem, Error, Selector},
+    arithmetic::FieldExt,
+    circuit::{Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Fixed, Selector},
     poly::Rotation,
 };
-use halo2_proofs::halo2curves::pasta::pallas;
+use std::marker::PhantomData;
 
 mod compression_gates;
 mod compression_util;
@@ -22,15 +24,15 @@ mod subregion_digest;
 // use compression_gates::CompressionGate;
 
 #[derive(Clone, Debug)]
-pub struct RoundWordDense(AssignedBits<16>, AssignedBits<16>);
+pub struct RoundWordDense<F: FieldExt>(AssignedBits<F, 16>, AssignedBits<F, 16>);
 
-impl From<(AssignedBits<16>, AssignedBits<16>)> for RoundWordDense {
-    fn from(halves: (AssignedBits<16>, AssignedBits<16>)) -> Self {
+impl<F: FieldExt> From<(AssignedBits<F, 16>, AssignedBits<F, 16>)> for RoundWordDense<F> {
+    fn from(halves: (AssignedBits<F, 16>, AssignedBits<F, 16>)) -> Self {
         Self(halves.0, halves.1)
     }
 }
 
-impl RoundWordDense {
+impl<F: FieldExt> RoundWordDense<F> {
     pub fn value(&self) -> Value<u32> {
         self.0
             .value_u16()
@@ -40,15 +42,15 @@ impl RoundWordDense {
 }
 
 #[derive(Clone, Debug)]
-pub struct RoundWordSpread(AssignedBits<32>, AssignedBits<32>);
+pub struct RoundWordSpread<F: FieldExt>(AssignedBits<F, 32>, AssignedBits<F, 32>);
 
-impl From<(AssignedBits<32>, AssignedBits<32>)> for RoundWordSpread {
-    fn from(halves: (AssignedBits<32>, AssignedBits<32>)) -> Self {
+impl<F: FieldExt> From<(AssignedBits<F, 32>, AssignedBits<F, 32>)> for RoundWordSpread<F> {
+    fn from(halves: (AssignedBits<F, 32>, AssignedBits<F, 32>)) -> Self {
         Self(halves.0, halves.1)
     }
 }
 
-impl RoundWordSpread {
+impl<F: FieldExt> RoundWordSpread<F> {
     pub fn value(&self) -> Value<u64> {
         self.0
             .value_u32()
@@ -59,13 +61,13 @@ impl RoundWordSpread {
 
 
 #[derive(Clone, Debug)]
-pub struct RoundWord {
-    dense_halves: RoundWordDense,
-    spread_halves: RoundWordSpread,
+pub struct RoundWord<F: FieldExt> {
+    dense_halves: RoundWordDense<F>,
+    spread_halves: RoundWordSpread<F>,
 }
 
-impl RoundWord {
-    pub fn new(dense_halves: RoundWordDense, spread_halves: RoundWordSpread) -> Self {
+impl<F: FieldExt> RoundWord<F> {
+    pub fn new(dense_halves: RoundWordDense<F>, spread_halves: RoundWordSpread<F>) -> Self {
         RoundWord {
             dense_halves,
             spread_halves,
@@ -75,23 +77,23 @@ impl RoundWord {
 
 /// The internal state for RIPEMD160
 #[derive(Clone, Debug)]
-pub struct State {
-    a: Option<StateWord>,
-    b: Option<StateWord>,
-    c: Option<StateWord>,
-    d: Option<StateWord>,
-    e: Option<StateWord>,
+pub struct State<F: FieldExt> {
+    a: Option<StateWord<F>>,
+    b: Option<StateWord<F>>,
+    c: Option<StateWord<F>>,
+    d: Option<StateWord<F>>,
+    e: Option<StateWord<F>>,
 }
 
-impl State {
+impl<F: FieldExt> State<F> {
     #[allow(clippy::many_single_char_names)]
     #[allow(clippy::too_many_arguments)]
     pub fn new(
-        a: StateWord,
-        b: StateWord,
-        c: StateWord,
-        d: StateWord,
-        e: StateWord,
+        a: StateWord<F>,
+        b: StateWord<F>,
+        c: StateWord<F>,
+        d: StateWord<F>,
+        e: StateWord<F>,
     ) -> Self {
         State {
             a: Some(a),
@@ -114,12 +116,12 @@ impl State {
 }
 
 #[derive(Clone, Debug)]
-pub enum StateWord {
-    A(RoundWordDense),
-    B(RoundWord),
-    C(RoundWord),
-    D(RoundWord),
-    E(RoundWordDense),
+pub enum StateWord<F: FieldExt> {
+    A(RoundWordDense<F>),
+    B(RoundWord<F>),
+    C(RoundWord<F>),
+    D(RoundWord<F>),
+    E(RoundWordDense<F>),
 }
 #[derive(Clone, Debug, PartialEq)]
 pub enum RoundSide {
@@ -128,7 +130,7 @@ pub enum RoundSide {
 }
 
 #[derive(Clone, Debug)]
-pub(super) struct CompressionConfig {
+pub(super) struct CompressionConfig<F: FieldExt> {
     lookup: SpreadInputs,
     advice: [Column<Advice>; NUM_ADVICE_COLS],
 
@@ -136,17 +138,24 @@ pub(super) struct CompressionConfig {
     s_f1: Selector,
     s_f2f4: Selector,
     s_f3f5: Selector,
-    s_rotate_left: [Selector; 11], // Rotate left with shifts from 5 to 15 (inclusive)
+    s_rotate_left: Selector, // Rotate left with shifts from 5 to 15 (inclusive)
+    // 2^shift, maximum tag of the chunk in the lookup, 2^(bits of x_lo), 2^(bits of x_hi)
+    // and whether the chunk in the lookup is the low one, on the rows of s_rotate_left
+    rotate_left_params: [Column<Fixed>; 5],
     s_sum_afxk: Selector,
     s_sum_re: Selector,
     s_sum_combine_ilr: Selector,
+    // The columns of the right line, which is then assigned on the same rows as the
+    // left line. Without them, the right rounds follow the left rounds.
+    right_line: Option<Box<CompressionConfig<F>>>,
+    _marker: PhantomData<F>,
 }
 
-impl Table16Assignment for CompressionConfig {}
+impl<F: FieldExt> Table16Assignment<F> for CompressionConfig<F> {}
 
-impl CompressionConfig {
+impl<F: FieldExt> CompressionConfig<F> {
     pub(super) fn configure(
-        meta: &mut ConstraintSystem<pallas::Base>,
+        meta: &mut ConstraintSystem<F>,
         lookup: SpreadInputs,
         advice: [Column<Advice>; NUM_ADVICE_COLS],
         s_decompose_word: Selector, 
@@ -154,18 +163,13 @@ impl CompressionConfig {
         let s_f1 = meta.selector();
         let s_f2f4 = meta.selector();
         let s_f3f5 = meta.selector();
-        let s_rotate_left = [
-            meta.selector(),
-            meta.selector(),
-            meta.selector(),
-            meta.selector(),
-            meta.selector(),
-            meta.selector(),
-            meta.selector(),
-            meta.selector(),
-            meta.selector(),
-            meta.selector(),
-            meta.selector(),
+        let s_rotate_left = meta.selector();
+        let rotate_left_params = [
+            meta.fixed_column(),
+            meta.fixed_column(),
+            meta.fixed_column(),
+            meta.fixed_column(),
+            meta.fixed_column(),
         ];
         let s_sum_afxk = meta.selector();
         let s_sum_re = meta.selector();
@@ -332,278 +336,38 @@ impl CompressionConfig {
             )
         });
 
-        meta.create_gate("rotate_left_5", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[0]);
-            let tag_b = meta.query_advice(a_0, Rotation::cur());
-            let b = meta.query_advice(a_1, Rotation::cur());
+        // One gate for all the shifts, with the shift selected by the fixed columns
+        // s_rotate_left | a_0   |   a_1  | a_3  |    a_4  |     a_5     |
+        //       1       | tag_t | t      | x_lo | word_lo | rol_word_lo |
+        //               |       | c      | x_hi | word_hi | rol_word_hi |
+        meta.create_gate("rotate_left", |meta| {
+            let s_rotate_left = meta.query_selector(s_rotate_left);
+            let pow_shift = meta.query_fixed(rotate_left_params[0], Rotation::cur());
+            let max_tag = meta.query_fixed(rotate_left_params[1], Rotation::cur());
+            let pow_x_lo = meta.query_fixed(rotate_left_params[2], Rotation::cur());
+            let pow_x_hi = meta.query_fixed(rotate_left_params[3], Rotation::cur());
+            let low_in_lookup = meta.query_fixed(rotate_left_params[4], Rotation::cur());
+            let tag_t = meta.query_advice(a_0, Rotation::cur());
+            let t = meta.query_advice(a_1, Rotation::cur());
             let c = meta.query_advice(a_1, Rotation::next());
-            let a_lo = meta.query_advice(a_3, Rotation::cur());
-            let a_hi = meta.query_advice(a_3, Rotation::next());
-            let word_lo = meta.query_advice(a_4, Rotation::cur());
-            let word_hi = meta.query_advice(a_4, Rotation::next());
-            let rol_word_lo = meta.query_advice(a_5, Rotation::cur());
-            let rol_word_hi = meta.query_advice(a_5, Rotation::next());
-            
-            CompressionGate::rotate_left_5_gate(
-                s_rotate_left,
-                a_lo,
-                a_hi,
-                b,
-                tag_b,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-        
-        meta.create_gate("rotate_left_6", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[1]);
-            let tag_b = meta.query_advice(a_0, Rotation::cur());
-            let b = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let a_lo = meta.query_advice(a_3, Rotation::cur());
-            let a_hi = meta.query_advice(a_3, Rotation::next());
-            let word_lo = meta.query_advice(a_4, Rotation::cur());
-            let word_hi = meta.query_advice(a_4, Rotation::next());
-            let rol_word_lo = meta.query_advice(a_5, Rotation::cur());
-            let rol_word_hi = meta.query_advice(a_5, Rotation::next());
-            
-            CompressionGate::rotate_left_6_gate(
-                s_rotate_left,
-                a_lo,
-                a_hi,
-                b,
-                tag_b,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-        
-        meta.create_gate("rotate_left_7", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[2]);
-            let tag_b = meta.query_advice(a_0, Rotation::cur());
-            let b = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let a_lo = meta.query_advice(a_3, Rotation::cur());
-            let a_hi = meta.query_advice(a_3, Rotation::next());
-            let word_lo = meta.query_advice(a_4, Rotation::cur());
-            let word_hi = meta.query_advice(a_4, Rotation::next());
-            let rol_word_lo = meta.query_advice(a_5, Rotation::cur());
-            let rol_word_hi = meta.query_advice(a_5, Rotation::next());
-            
-            CompressionGate::rotate_left_7_gate(
-                s_rotate_left,
-                a_lo,
-                a_hi,
-                b,
-                tag_b,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-        
-        meta.create_gate("rotate_left_8", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[3]);
-            let tag_b = meta.query_advice(a_0, Rotation::cur());
-            let b = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let a_lo = meta.query_advice(a_3, Rotation::cur());
-            let a_hi = meta.query_advice(a_3, Rotation::next());
-            let word_lo = meta.query_advice(a_4, Rotation::cur());
-            let word_hi = meta.query_advice(a_4, Rotation::next());
-            let rol_word_lo = meta.query_advice(a_5, Rotation::cur());
-            let rol_word_hi = meta.query_advice(a_5, Rotation::next());
-            
-            CompressionGate::rotate_left_8_gate(
-                s_rotate_left,
-                a_lo,
-                a_hi,
-                b,
-                tag_b,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-        
-        meta.create_gate("s_rotate_left_9", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[4]);
-            let tag_a = meta.query_advice(a_0, Rotation::cur());
-            let a = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let b_lo = meta.query_advice(a_3, Rotation::cur());
-            let b_hi = meta.query_advice(a_3, Rotation::next());
-            let word_lo = meta.query_advice(a_4, Rotation::cur());
-            let word_hi = meta.query_advice(a_4, Rotation::next());
-            let rol_word_lo = meta.query_advice(a_5, Rotation::cur());
-            let rol_word_hi = meta.query_advice(a_5, Rotation::next());
-            
-            CompressionGate::rotate_left_9_gate(
-                s_rotate_left,
-                a,
-                tag_a,
-                b_lo,
-                b_hi,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-
-        meta.create_gate("s_rotate_left_10", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[5]);
-            let tag_a = meta.query_advice(a_0, Rotation::cur());
-            let a = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let b_lo = meta.query_advice(a_3, Rotation::cur());
-            let b_hi = meta.query_advice(a_3, Rotation::next());
+            let x_lo = meta.query_advice(a_3, Rotation::cur());
+            let x_hi = meta.query_advice(a_3, Rotation::next());
             let word_lo = meta.query_advice(a_4, Rotation::cur());
             let word_hi = meta.query_advice(a_4, Rotation::next());
             let rol_word_lo = meta.query_advice(a_5, Rotation::cur());
             let rol_word_hi = meta.query_advice(a_5, Rotation::next());
-            
-            CompressionGate::rotate_left_10_gate(
-                s_rotate_left,
-                a,
-                tag_a,
-                b_lo,
-                b_hi,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
 
-        meta.create_gate("s_rotate_left_11", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[6]);
-            let tag_a = meta.query_advice(a_0, Rotation::cur());
-            let a = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let b_lo = meta.query_advice(a_3, Rotation::cur());
-            let b_hi = meta.query_advice(a_3, Rotation::next());
-            let word_lo = meta.query_advice(a_4, Rotation::cur());
-            let word_hi = meta.query_advice(a_4, Rotation::next());
-            let rol_word_lo = meta.query_advice(a_5, Rotation::cur());
-            let rol_word_hi = meta.query_advice(a_5, Rotation::next());
-            
-            CompressionGate::rotate_left_11_gate(
-                s_rotate_left,
-                a,
-                tag_a,
-                b_lo,
-                b_hi,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-
-        meta.create_gate("s_rotate_left_12", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[7]);
-            let tag_a = meta.query_advice(a_0, Rotation::cur());
-            let a = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let b_lo = meta.query_advice(a_3, Rotation::cur());
-            let b_hi = meta.query_advice(a_3, Rotation::next());
-            let word_lo = meta.query_advice(a_4, Rotation::cur());
-            let word_hi = meta.query_advice(a_4, Rotation::next());
-            let rol_word_lo = meta.query_advice(a_5, Rotation::cur());
-            let rol_word_hi = meta.query_advice(a_5, Rotation::next());
-            
-            CompressionGate::rotate_left_12_gate(
+            CompressionGate::rotate_left_gate(
                 s_rotate_left,
-                a,
-                tag_a,
-                b_lo,
-                b_hi,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-
-        meta.create_gate("s_rotate_left_13", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[8]);
-            let tag_a = meta.query_advice(a_0, Rotation::cur());
-            let a = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let b = meta.query_advice(a_3, Rotation::cur());
-            let word_lo = meta.query_advice(a_4, Rotation::cur());
-            let word_hi = meta.query_advice(a_4, Rotation::next());
-            let rol_word_lo = meta.query_advice(a_5, Rotation::cur());
-            let rol_word_hi = meta.query_advice(a_5, Rotation::next());
-            
-            CompressionGate::rotate_left_13_gate(
-                s_rotate_left,
-                a,
-                tag_a,
-                b,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-
-        meta.create_gate("s_rotate_left_14", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[9]);
-            let tag_a = meta.query_advice(a_0, Rotation::cur());
-            let a = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let b = meta.query_advice(a_3, Rotation::cur());
-            let word_lo = meta.query_advice(a_4, Rotation::cur());
-            let word_hi = meta.query_advice(a_4, Rotation::next());
-            let rol_word_lo = meta.query_advice(a_5, Rotation::cur());
-            let rol_word_hi = meta.query_advice(a_5, Rotation::next());
-            
-            CompressionGate::rotate_left_14_gate(
-                s_rotate_left,
-                a,
-                tag_a,
-                b,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-
-        meta.create_gate("s_rotate_left_15", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[10]);
-            let tag_a = meta.query_advice(a_0, Rotation::cur());
-            let a = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let b = meta.query_advice(a_3, Rotation::cur());
-            let word_lo = meta.query_advice(a_4, Rotation::cur());
-            let word_hi = meta.query_advice(a_4, Rotation::next());
-            let rol_word_lo = meta.query_advice(a_5, Rotation::cur());
-            let rol_word_hi = meta.query_advice(a_5, Rotation::next());
-            
-            CompressionGate::rotate_left_15_gate(
-                s_rotate_left,
-                a,
-                tag_a,
-                b,
+                pow_shift,
+                max_tag,
+                pow_x_lo,
+                pow_x_hi,
+                low_in_lookup,
+                t,
+                tag_t,
+                x_lo,
+                x_hi,
                 c,
                 word_lo,
                 word_hi,
@@ -700,19 +464,30 @@ impl CompressionConfig {
             s_f2f4,
             s_f3f5,
             s_rotate_left,
+            rotate_left_params,
             s_sum_afxk,
             s_sum_re,
             s_sum_combine_ilr,
+            right_line: None,
+            _marker: PhantomData,
         }
     }
+
+    /// Assigns the rounds of the right line in the columns of `right_line`, in parallel
+    /// with the left line. The lines are independent until they are combined, so this
+    /// roughly halves the rows of a compression.
+    pub(super) fn with_right_line(mut self, right_line: Self) -> Self {
+        self.right_line = Some(Box::new(right_line));
+        self
+    }
     
     /// Initialize compression with a constant Initialization Vector of 32-byte words.
     /// Returns an initialized state.
     pub(super) fn initialize_with_iv(
         &self,
-        layouter: &mut impl Layouter<pallas::Base>,
+        layouter: &mut impl Layouter<F>,
         init_state: [u32; DIGEST_SIZE],
-    ) -> Result<State, Error> {
+    ) -> Result<State<F>, Error> {
         let mut new_state = State::empty_state();
         layouter.assign_region(
             || "initialize_with_iv",
@@ -727,47 +502,141 @@ impl CompressionConfig {
     /// Given an initialized state and a message schedule, perform 80 compression rounds.
     pub(super) fn compress(
         &self,
-        layouter: &mut impl Layouter<pallas::Base>,
-        initialized_state: State,
-        w_halves: [(AssignedBits<16>, AssignedBits<16>); BLOCK_SIZE],
-    ) -> Result<State, Error> {
-        let mut left_state = State::empty_state();
-        let mut right_state = State::empty_state();
+        layouter: &mut impl Layouter<F>,
+        initialized_state: State<F>,
+        w_halves: [(AssignedBits<F, 16>, AssignedBits<F, 16>); BLOCK_SIZE],
+    ) -> Result<State<F>, Error> {
         let mut final_state = State::empty_state();
         layouter.assign_region(
             || "compress",
             |mut region| {
                 let mut row: usize = 0;
-                left_state = initialized_state.clone();
-                right_state = initialized_state.clone();
-                for idx in 0..ROUNDS {
-                    left_state = self.assign_round(&mut region, idx, left_state.clone(), w_halves.clone(), &mut row, RoundSide::Left)?;
-                    right_state = self.assign_round(&mut region, idx, right_state.clone(), w_halves.clone(), &mut row, RoundSide::Right)?;
+                let (left_state, right_state) =
+                    self.assign_rounds(&mut region, initialized_state.clone(), w_halves.clone(), &mut row)?;
+                final_state = self.assign_combine_ilr(&mut region, initialized_state.clone(), left_state, right_state, &mut row)?;
+                Ok(())
+            },
+        )?;
+        Ok(final_state)
+    }
+
+    /// Assigns the 80 rounds of both lines starting at `row`, and advances `row` past the
+    /// longer line. Returns the final states of the left and right lines.
+    fn assign_rounds(
+        &self,
+        region: &mut Region<'_, F>,
+        initialized_state: State<F>,
+        w_halves: [(AssignedBits<F, 16>, AssignedBits<F, 16>); BLOCK_SIZE],
+        row: &mut usize,
+    ) -> Result<(State<F>, State<F>), Error> {
+        let mut right_row = *row;
+        let mut left_state = initialized_state.clone();
+        let mut right_state = initialized_state;
+        for idx in 0..ROUNDS {
+            left_state = self.assign_round(region, idx, left_state, w_halves.clone(), row, RoundSide::Left)?;
+            right_state = match &self.right_line {
+                Some(right_line) => right_line.assign_round(region, idx, right_state, w_halves.clone(), &mut right_row, RoundSide::Right)?,
+                None => self.assign_round(region, idx, right_state, w_halves.clone(), row, RoundSide::Right)?,
+            };
+        }
+        *row = (*row).max(right_row);
+        Ok((left_state, right_state))
+    }
+
+    /// Given an initialized state, compress the message blocks in order within a single
+    /// region. The words of each block after the first are assigned by `message_schedule`,
+    /// which must use the columns of the right line, while the left line combines the
+    /// previous block. This takes fewer rows than a message schedule and a compression
+    /// region per block.
+    pub(super) fn compress_blocks_in_region(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        message_schedule: &MessageScheduleConfig<F>,
+        initialized_state: State<F>,
+        blocks: &[[BlockWord; BLOCK_SIZE]],
+    ) -> Result<State<F>, Error> {
+        if blocks.is_empty() {
+            return Ok(initialized_state);
+        }
+
+        let mut final_state = State::empty_state();
+        layouter.assign_region(
+            || "compress blocks",
+            |mut region| {
+                let mut row: usize = 0;
+                let mut state = initialized_state.clone();
+                let (_, mut w_halves) = message_schedule.assign_block(&mut region, &mut row, blocks[0], None)?;
+                for idx in 1..=blocks.len() {
+                    let (left_state, right_state) =
+                        self.assign_rounds(&mut region, state.clone(), w_halves.clone(), &mut row)?;
+
+                    let mut message_row = row;
+                    if let Some(block) = blocks.get(idx) {
+                        w_halves = message_schedule.assign_block(&mut region, &mut message_row, *block, None)?.1;
+                    }
+                    state = self.assign_combine_ilr(&mut region, state, left_state, right_state, &mut row)?;
+                    row = row.max(message_row);
                 }
-                final_state = self.assign_combine_ilr(&mut region, initialized_state.clone(), left_state.clone(), right_state.clone(), &mut row)?;
+                final_state = state;
                 Ok(())
             },
         )?;
         Ok(final_state)
     }
 
+    /// Given an initialized state and the message schedules of several blocks, compress
+    /// the blocks in order. The output state of each block is the initial state of the next.
+    pub(super) fn compress_blocks(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: State<F>,
+        blocks: &[[(AssignedBits<F, 16>, AssignedBits<F, 16>); BLOCK_SIZE]],
+    ) -> Result<State<F>, Error> {
+        let mut state = initialized_state;
+        for w_halves in blocks {
+            state = self.compress(layouter, state, w_halves.clone())?;
+        }
+        Ok(state)
+    }
+
 
     /// After the final round, convert the state into the final digest.
     pub(super) fn digest(
         &self,
-        layouter: &mut impl Layouter<pallas::Base>,
-        state: State,
+        layouter: &mut impl Layouter<F>,
+        state: State<F>,
     ) -> Result<[BlockWord; DIGEST_SIZE], Error> {
-        let mut digest = [BlockWord(Value::known(0)); DIGEST_SIZE];
+        let digest = self.digest_cells(layouter, state)?;
+        Ok(digest.map(|word| BlockWord(word.value_u32())))
+    }
+
+    /// After the final round, convert the state into the final digest.
+    /// Returns the assigned cells holding the 32-bit digest words.
+    pub(super) fn digest_cells(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: State<F>,
+    ) -> Result<[AssignedBits<F, 32>; DIGEST_SIZE], Error> {
         layouter.assign_region(
             || "digest",
-            |mut region| {
-                digest = self.assign_digest(&mut region, state.clone())?;
+            |mut region| self.assign_digest(&mut region, state.clone()),
+        )
+    }
 
-                Ok(())
-            },
-        )?;
-        Ok(digest)
+    /// After the final round, constrain the state words to equal the expected digest words.
+    /// The halves of each state word are checked against the expected word by the
+    /// s_decompose_word gate, without converting the state to BlockWords. Returns the assigned
+    /// cells holding the expected words, e.g. to copy them from a value pushed on the stack.
+    pub(super) fn constrain_digest_equals(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: State<F>,
+        expected: [Value<u32>; DIGEST_SIZE],
+    ) -> Result<[AssignedBits<F, 32>; DIGEST_SIZE], Error> {
+        layouter.assign_region(
+            || "digest equals",
+            |mut region| self.assign_digest_equals(&mut region, state.clone(), expected),
+        )
     }
  
 }
@@ -778,8 +647,11 @@ mod tests {
         BLOCK_SIZE_BYTES,
         BLOCK_SIZE,
         INITIAL_VALUES, DIGEST_SIZE,
+        MSG_SEL_IDX_LEFT, MSG_SEL_IDX_RIGHT, ROUNDS,
     };
-    use crate::ripemd160::ref_impl::ripemd160::{hash, pad_message_bytes};
+    use crate::ripemd160::ref_impl::ripemd160::{hash, pad_message_bytes, MessageBlock};
+    use crate::ripemd160::table16::compression::RoundSide;
+    use crate::ripemd160::table16::compression::subregion_main::select_message_word;
     use crate::ripemd160::table16::{AssignedBits, BlockWord};
     use crate::ripemd160::table16::compression::compression_util::match_state;
     use crate::ripemd160::table16::util::convert_byte_slice_to_u32_slice;
@@ -795,12 +667,181 @@ mod tests {
     };
     use halo2_proofs::halo2curves::pasta::pallas;
 
+    #[test]
+    fn test_compress_blocks() {
+        struct MyCircuit {}
+
+        impl Circuit<pallas::Base> for MyCircuit {
+            type Config = Table16Config<pallas::Base>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit {}
+            }
+
+            fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+                Table16Chip::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<pallas::Base>,
+            ) -> Result<(), Error> {
+                Table16Chip::load(config.clone(), &mut layouter)?;
+
+                // Test vector: 100 bytes which are padded to two blocks
+                let input_bytes = vec![b'a'; 100];
+                let padded_blocks = pad_message_bytes(input_bytes.clone());
+                assert_eq!(padded_blocks.len(), 2);
+                let output: [u32; DIGEST_SIZE] = convert_byte_slice_to_u32_slice(hash(input_bytes));
+
+                let mut blocks = vec![];
+                for block in padded_blocks {
+                    let input: [u32; BLOCK_SIZE] = convert_byte_slice_to_u32_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>(block);
+                    let (_, w_halves) = config.message_schedule.process(&mut layouter, input.map(|x| BlockWord(Value::known(x))))?;
+                    blocks.push(w_halves);
+                }
+
+                let initial_state = config.compression.initialize_with_iv(&mut layouter, INITIAL_VALUES)?;
+                let state = config.compression.compress_blocks(&mut layouter, initial_state, &blocks)?;
+
+                let digest = config.compression.digest(&mut layouter, state)?;
+                for (idx, digest_word) in digest.iter().enumerate() {
+                    digest_word.0.assert_if_known(|v| {
+                        *v == output[idx]
+                    });
+                }
+
+                Ok(())
+            }
+        }
+
+        let circuit: MyCircuit = MyCircuit {};
+
+        let prover = match MockProver::<pallas::Base>::run(17, &circuit, vec![]) {
+            Ok(prover) => prover,
+            Err(e) => panic!("{:?}", e),
+        };
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_message_word_selection() {
+        struct MyCircuit {}
+
+        impl Circuit<pallas::Base> for MyCircuit {
+            type Config = Table16Config<pallas::Base>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit {}
+            }
+
+            fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+                Table16Chip::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<pallas::Base>,
+            ) -> Result<(), Error> {
+                Table16Chip::load(config.clone(), &mut layouter)?;
+
+                // Message block with distinct words
+                let mut input_bytes = [0u8; BLOCK_SIZE_BYTES];
+                for (i, byte) in input_bytes.iter_mut().enumerate() {
+                    *byte = (7 * i + 1) as u8;
+                }
+                let input: [u32; BLOCK_SIZE] = convert_byte_slice_to_u32_slice(input_bytes);
+                let msg_block: MessageBlock = input_bytes.into();
+
+                let (_, w_halves) = config.message_schedule.process(&mut layouter, input.map(|x| BlockWord(Value::known(x))))?;
+
+                // The selected word must match the one used by the reference implementation
+                for round_idx in 0..ROUNDS {
+                    for (round_side, sel_idx) in [(RoundSide::Left, MSG_SEL_IDX_LEFT), (RoundSide::Right, MSG_SEL_IDX_RIGHT)] {
+                        let x = select_message_word(&w_halves, round_idx, &round_side);
+                        let expected = msg_block.get_word(sel_idx[round_idx]);
+                        x.0.value_u16()
+                            .zip(x.1.value_u16())
+                            .assert_if_known(|(lo, hi)| (*lo as u32) + ((*hi as u32) << 16) == expected);
+                    }
+                }
+
+                Ok(())
+            }
+        }
+
+        let circuit: MyCircuit = MyCircuit {};
+
+        let prover = match MockProver::<pallas::Base>::run(17, &circuit, vec![]) {
+            Ok(prover) => prover,
+            Err(e) => panic!("{:?}", e),
+        };
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_constrain_digest_equals() {
+        struct MyCircuit {
+            expected: [u32; DIGEST_SIZE],
+        }
+
+        impl Circuit<pallas::Base> for MyCircuit {
+            type Config = Table16Config<pallas::Base>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit { expected: [0; DIGEST_SIZE] }
+            }
+
+            fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+                Table16Chip::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<pallas::Base>,
+            ) -> Result<(), Error> {
+                Table16Chip::load(config.clone(), &mut layouter)?;
+
+                let input_bytes = b"abc";
+                let input: [u32; BLOCK_SIZE] = convert_byte_slice_to_u32_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>(pad_message_bytes(input_bytes.to_vec())[0]);
+                let (_, w_halves) = config.message_schedule.process(&mut layouter, input.map(|x| BlockWord(Value::known(x))))?;
+
+                let initial_state = config.compression.initialize_with_iv(&mut layouter, INITIAL_VALUES)?;
+                let state = config.compression.compress(&mut layouter, initial_state, w_halves)?;
+
+                config.compression.constrain_digest_equals(
+                    &mut layouter,
+                    state,
+                    self.expected.map(Value::known),
+                )?;
+                Ok(())
+            }
+        }
+
+        let output: [u32; DIGEST_SIZE] = convert_byte_slice_to_u32_slice(hash(b"abc".to_vec()));
+        let circuit = MyCircuit { expected: output };
+        let prover = MockProver::<pallas::Base>::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut wrong_output = output;
+        wrong_output[2] ^= 1;
+        let circuit = MyCircuit { expected: wrong_output };
+        let prover = MockProver::<pallas::Base>::run(17, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
     #[test]
     fn test_compression() {
         struct MyCircuit {}
 
         impl Circuit<pallas::Base> for MyCircuit {
-            type Config = Table16Config;
+            type Config = Table16Config<pallas::Base>;
             type FloorPlanner = SimpleFloorPlanner;
 
             fn without_witnesses(&self) -> Self {
@@ -841,32 +882,32 @@ mod tests {
 
                         let mut row: usize = 0;
                         config.compression.s_decompose_word.enable(&mut region, row)?;
-                        AssignedBits::<16>::assign(&mut region, || "expected a_lo", a_3, row, a.0.value_u16())?;
-                        AssignedBits::<16>::assign(&mut region, || "expected a_hi", a_4, row, a.1.value_u16())?;
-                        AssignedBits::<32>::assign(&mut region, || "actual a", a_5, row, Value::known(output[row]))?;
+                        AssignedBits::<pallas::Base, 16>::assign(&mut region, || "expected a_lo", a_3, row, a.0.value_u16())?;
+                        AssignedBits::<pallas::Base, 16>::assign(&mut region, || "expected a_hi", a_4, row, a.1.value_u16())?;
+                        AssignedBits::<pallas::Base, 32>::assign(&mut region, || "actual a", a_5, row, Value::known(output[row]))?;
 
                         row += 1;
                         config.compression.s_decompose_word.enable(&mut region, row)?;
-                        AssignedBits::<16>::assign(&mut region, || "expected b_lo", a_3, row, b.dense_halves.0.value_u16())?;
-                        AssignedBits::<16>::assign(&mut region, || "expected b_hi", a_4, row, b.dense_halves.1.value_u16())?;
-                        AssignedBits::<32>::assign(&mut region, || "actual b", a_5, row, Value::known(output[row]))?;
+                        AssignedBits::<pallas::Base, 16>::assign(&mut region, || "expected b_lo", a_3, row, b.dense_halves.0.value_u16())?;
+                        AssignedBits::<pallas::Base, 16>::assign(&mut region, || "expected b_hi", a_4, row, b.dense_halves.1.value_u16())?;
+                        AssignedBits::<pallas::Base, 32>::assign(&mut region, || "actual b", a_5, row, Value::known(output[row]))?;
 
                         row += 1;
                         config.compression.s_decompose_word.enable(&mut region, row)?;
-                        AssignedBits::<16>::assign(&mut region, || "expected c_lo", a_3, row, c.dense_halves.0.value_u16())?;
-                        AssignedBits::<16>::assign(&mut region, || "expected c_hi", a_4, row, c.dense_halves.1.value_u16())?;
-                        AssignedBits::<32>::assign(&mut region, || "actual c", a_5, row, Value::known(output[row]))?;
+                        AssignedBits::<pallas::Base, 16>::assign(&mut region, || "expected c_lo", a_3, row, c.dense_halves.0.value_u16())?;
+                        AssignedBits::<pallas::Base, 16>::assign(&mut region, || "expected c_hi", a_4, row, c.dense_halves.1.value_u16())?;
+                        AssignedBits::<pallas::Base, 32>::assign(&mut region, || "actual c", a_5, row, Value::known(output[row]))?;
 
                         row += 1;
                         config.compression.s_decompose_word.enable(&mut region, row)?;
-                        AssignedBits::<16>::assign(&mut region, || "expected d_lo", a_3, row, d.dense_halves.0.value_u16())?;
-                        AssignedBits::<16>::assign(&mut region, || "expected d_hi", a_4, row, d.dense_halves.1.value_u16())?;
-                        AssignedBits::<32>::assign(&mut region, || "actual d", a_5, row, Value::known(output[row]))?;
+                        AssignedBits::<pallas::Base, 16>::assign(&mut region, || "expected d_lo", a_3, row, d.dense_halves.0.value_u16())?;
+                        AssignedBits::<pallas::Base, 16>::assign(&mut region, || "expected d_hi", a_4, row, d.dense_halves.1.value_u16())?;
+                        AssignedBits::<pallas::Base, 32>::assign(&mut region, || "actual d", a_5, row, Value::known(output[row]))?;
 
                         row += 1;
-                        AssignedBits::<16>::assign(&mut region, || "expected e_lo", a_3, row, e.0.value_u16())?;
-                        AssignedBits::<16>::assign(&mut region, || "expected e_hi", a_4, row, e.1.value_u16())?;
-                        AssignedBits::<32>::assign(&mut region, || "actual e", a_5, row, Value::known(output[row]))?;
+                        AssignedBits::<pallas::Base, 16>::assign(&mut region, || "expected e_lo", a_3, row, e.0.value_u16())?;
+                        AssignedBits::<pallas::Base, 16>::assign(&mut region, || "expected e_hi", a_4, row, e.1.value_u16())?;
+                        AssignedBits::<pallas::Base, 32>::assign(&mut region, || "actual e", a_5, row, Value::known(output[row]))?;
 
                         Ok(())
                     }
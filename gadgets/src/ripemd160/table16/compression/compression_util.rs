@@ -9,14 +9,14 @@ use halo2_proofs::{
     circuit::{Region, Value},
     plonk::Error,
 };
-use halo2_proofs::halo2curves::pasta::pallas;
+use halo2_proofs::arithmetic::FieldExt;
 use std::convert::TryInto;
 
 
 
 
 
-impl CompressionConfig {
+impl<F: FieldExt> CompressionConfig<F> {
 
     // s_f1 | a_0 |   a_1    |       a_2       |    a_3      |    a_4      |    a_5      |
     //   1  |     | R_0_even | spread_R_0_even | spread_B_lo | spread_C_lo | spread_D_lo | 
@@ -26,12 +26,12 @@ impl CompressionConfig {
     // 
     pub(super) fn assign_f1(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         row: usize,
-        spread_halves_b: RoundWordSpread,
-        spread_halves_c: RoundWordSpread,
-        spread_halves_d: RoundWordSpread,
-    ) -> Result<(AssignedBits<16>, AssignedBits<16>), Error> {
+        spread_halves_b: RoundWordSpread<F>,
+        spread_halves_c: RoundWordSpread<F>,
+        spread_halves_d: RoundWordSpread<F>,
+    ) -> Result<(AssignedBits<F, 16>, AssignedBits<F, 16>), Error> {
         let a_3 = self.advice[0];
         let a_4 = self.advice[1];
         let a_5 = self.advice[2];
@@ -69,13 +69,13 @@ impl CompressionConfig {
 
     fn assign_f1_outputs(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         row: usize,
         r_0_even: Value<[bool; 16]>,
         r_0_odd: Value<[bool; 16]>,
         r_1_even: Value<[bool; 16]>,
         r_1_odd: Value<[bool; 16]>,
-    ) -> Result<(AssignedBits<16>, AssignedBits<16>), Error> {
+    ) -> Result<(AssignedBits<F, 16>, AssignedBits<F, 16>), Error> {
         let (even, _odd) = self.assign_spread_outputs(
             region,
             &self.lookup,
@@ -102,12 +102,12 @@ impl CompressionConfig {
     // Output is sum_lo, sum_hi
     pub(super) fn assign_f2(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         row: usize,
-        spread_halves_x: RoundWordSpread,
-        spread_halves_y: RoundWordSpread,
-        spread_halves_z: RoundWordSpread,
-    ) -> Result<(AssignedBits<16>, AssignedBits<16>), Error> {
+        spread_halves_x: RoundWordSpread<F>,
+        spread_halves_y: RoundWordSpread<F>,
+        spread_halves_z: RoundWordSpread<F>,
+    ) -> Result<(AssignedBits<F, 16>, AssignedBits<F, 16>), Error> {
         let a_3 = self.advice[0];
         let a_4 = self.advice[1];
         let a_5 = self.advice[2];
@@ -149,7 +149,7 @@ impl CompressionConfig {
             .value()
             .map(|spread_x_lo| negate_spread(spread_x_lo.0));
         // Assign spread_neg_x_lo
-        AssignedBits::<32>::assign_bits(
+        AssignedBits::<F, 32>::assign_bits(
             region,
             || "spread_neg_x_lo",
             a_5,
@@ -163,7 +163,7 @@ impl CompressionConfig {
             .value()
             .map(|spread_x_hi| negate_spread(spread_x_hi.0));
         // Assign spread_neg_x_hi
-        AssignedBits::<32>::assign_bits(
+        AssignedBits::<F, 32>::assign_bits(
             region,
             || "spread_neg_x_hi",
             a_5,
@@ -199,14 +199,14 @@ impl CompressionConfig {
         let sum_lo: Value<[bool; 16]> = sum.map(|w| w[..16].try_into().unwrap());
         let sum_hi: Value<[bool; 16]> = sum.map(|w| w[16..].try_into().unwrap());
 
-        let sum_lo = AssignedBits::<16>::assign_bits(region, || "sum_lo", a_3, row + 6, sum_lo)?;
-        let sum_hi = AssignedBits::<16>::assign_bits(region, || "sum_hi", a_3, row + 7, sum_hi)?;
+        let sum_lo = AssignedBits::<F, 16>::assign_bits(region, || "sum_lo", a_3, row + 6, sum_lo)?;
+        let sum_hi = AssignedBits::<F, 16>::assign_bits(region, || "sum_hi", a_3, row + 7, sum_hi)?;
 
         region.assign_advice(
             || "f2f4_carry",
             a_4,
             row + 6,
-            || carry.map(|value| pallas::Base::from(value as u64)),
+            || carry.map(|value| F::from(value as u64)),
         )?;
 
         Ok((sum_lo, sum_hi))
@@ -225,24 +225,24 @@ impl CompressionConfig {
     // Output is sum_lo, sum_hi
     pub(super) fn assign_f4(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         row: usize,
-        spread_halves_x: RoundWordSpread,
-        spread_halves_y: RoundWordSpread,
-        spread_halves_z: RoundWordSpread,
-    ) -> Result<(AssignedBits<16>, AssignedBits<16>), Error> {
+        spread_halves_x: RoundWordSpread<F>,
+        spread_halves_y: RoundWordSpread<F>,
+        spread_halves_z: RoundWordSpread<F>,
+    ) -> Result<(AssignedBits<F, 16>, AssignedBits<F, 16>), Error> {
         self.assign_f2(region, row, spread_halves_z, spread_halves_x, spread_halves_y)
     }
 
     fn assign_ch_outputs(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         row: usize,
         p_0_even: Value<[bool; 16]>,
         p_0_odd: Value<[bool; 16]>,
         p_1_even: Value<[bool; 16]>,
         p_1_odd: Value<[bool; 16]>,
-    ) -> Result<(AssignedBits<16>, AssignedBits<16>), Error> {
+    ) -> Result<(AssignedBits<F, 16>, AssignedBits<F, 16>), Error> {
         let (_even, odd) = self.assign_spread_outputs(
             region,
             &self.lookup,
@@ -271,12 +271,12 @@ impl CompressionConfig {
     // Output is in R_0_even, R_1_even
     pub(super) fn assign_f3(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         row: usize,
-        spread_halves_x: RoundWordSpread,
-        spread_halves_y: RoundWordSpread,
-        spread_halves_z: RoundWordSpread,
-    ) -> Result<(AssignedBits<16>, AssignedBits<16>), Error> {
+        spread_halves_x: RoundWordSpread<F>,
+        spread_halves_y: RoundWordSpread<F>,
+        spread_halves_z: RoundWordSpread<F>,
+    ) -> Result<(AssignedBits<F, 16>, AssignedBits<F, 16>), Error> {
         let a_3 = self.advice[0];
         let a_4 = self.advice[1];
         let a_5 = self.advice[2];
@@ -301,7 +301,7 @@ impl CompressionConfig {
             .value()
             .map(|spread_y_lo| negate_spread(spread_y_lo.0));
         // Assign spread_neg_y_lo
-        let assigned_neg_y_lo = AssignedBits::<32>::assign_bits(
+        let assigned_neg_y_lo = AssignedBits::<F, 32>::assign_bits(
             region,
             || "spread_neg_y_lo",
             a_3,
@@ -315,7 +315,7 @@ impl CompressionConfig {
             .value()
             .map(|spread_y_hi| negate_spread(spread_y_hi.0));
         // Assign spread_neg_y_hi
-       let assigned_neg_y_hi = AssignedBits::<32>::assign_bits(
+       let assigned_neg_y_hi = AssignedBits::<F, 32>::assign_bits(
             region,
             || "spread_neg_y_hi",
             a_3,
@@ -383,12 +383,12 @@ impl CompressionConfig {
     // f5(X, Y, Z) = X ^ (Y | !Z) = f3(Y, Z, X)
     pub(super) fn assign_f5(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         row: usize,
-        spread_halves_x: RoundWordSpread,
-        spread_halves_y: RoundWordSpread,
-        spread_halves_z: RoundWordSpread,
-    ) -> Result<(AssignedBits<16>, AssignedBits<16>), Error> {
+        spread_halves_x: RoundWordSpread<F>,
+        spread_halves_y: RoundWordSpread<F>,
+        spread_halves_z: RoundWordSpread<F>,
+    ) -> Result<(AssignedBits<F, 16>, AssignedBits<F, 16>), Error> {
         self.assign_f3(region, row, spread_halves_y, spread_halves_z, spread_halves_x)
     }
 
@@ -408,17 +408,42 @@ impl CompressionConfig {
     //               |     | c(16)    |     |      | word_hi | rol_word_hi | 
     pub(super) fn assign_rotate_left(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         row: usize,
-        word: RoundWordDense,
+        word: RoundWordDense<F>,
         shift: u8,
-    ) -> Result<RoundWordDense, Error> {
+    ) -> Result<RoundWordDense<F>, Error> {
         assert!(shift > 4 && shift < 16);
         let a_3 = self.advice[0];
         let a_4 = self.advice[1];
         let a_5 = self.advice[2];
         
-        self.s_rotate_left[shift as usize - 5].enable(region, row)?;
+        self.s_rotate_left.enable(region, row)?;
+
+        // Bits of the chunks x_lo and x_hi which are not in the lookup
+        let (x_lo_bits, x_hi_bits) = match shift {
+            5 => (2, 3),
+            6 => (3, 3),
+            7 => (3, 4),
+            8 => (4, 4),
+            9 => (3, 4),
+            10 => (3, 3),
+            11 => (2, 3),
+            12 => (2, 2),
+            13 => (3, 0),
+            14 => (2, 0),
+            _ => (1, 0),
+        };
+        let params = [
+            1u64 << shift,
+            (shift as i32 - 8).unsigned_abs() as u64,
+            1 << x_lo_bits,
+            1 << x_hi_bits,
+            (shift <= 8) as u64,
+        ];
+        for (column, param) in self.rotate_left_params.iter().zip(params) {
+            region.assign_fixed(|| "rotate_left param", *column, row, || Value::known(F::from(param)))?;
+        }
 
         // Assign and copy word_lo, word_hi
         word.0.copy_advice(|| "word_lo", region, a_4, row)?;
@@ -429,8 +454,8 @@ impl CompressionConfig {
         let rol_word_lo: Value<[bool; 16]> = rol_word.map(|q| q[..16].try_into().unwrap());
         let rol_word_hi: Value<[bool; 16]> = rol_word.map(|q| q[16..].try_into().unwrap());
         
-        let rol_word_lo = AssignedBits::<16>::assign_bits(region, || "rol_word_lo", a_5, row, rol_word_lo)?;
-        let rol_word_hi = AssignedBits::<16>::assign_bits(region, || "rol_word_hi", a_5, row + 1, rol_word_hi)?;
+        let rol_word_lo = AssignedBits::<F, 16>::assign_bits(region, || "rol_word_lo", a_5, row, rol_word_lo)?;
+        let rol_word_hi = AssignedBits::<F, 16>::assign_bits(region, || "rol_word_hi", a_5, row + 1, rol_word_hi)?;
 
         let word_hi = word.1.value_u16().map(|a| i2lebsp::<16>(a.into()));
         let c: Value<[bool; 16]>= word.0.value_u16().map(|a| i2lebsp(a.into()).try_into().unwrap());
@@ -442,8 +467,8 @@ impl CompressionConfig {
             let a_hi: Value<[bool; 3]> = word_hi.map(|q| q[13..].try_into().unwrap());
             self.assign_spread_word(region, &self.lookup, row, b, c)?;
 
-            AssignedBits::<2>::assign_bits(region, || "a_lo(2)", a_3, row, a_lo)?;
-            AssignedBits::<3>::assign_bits(region, || "a_hi(3)", a_3, row + 1, a_hi)?;
+            AssignedBits::<F, 2>::assign_bits(region, || "a_lo(2)", a_3, row, a_lo)?;
+            AssignedBits::<F, 3>::assign_bits(region, || "a_hi(3)", a_3, row + 1, a_hi)?;
         }
         else if shift == 6 {
             let b: Value<[bool; 10]> = word_hi.map(|q| q[..10].try_into().unwrap());
@@ -452,8 +477,8 @@ impl CompressionConfig {
             let a_hi: Value<[bool; 3]> = word_hi.map(|q| q[13..].try_into().unwrap());
             self.assign_spread_word(region, &self.lookup, row, b, c)?;
 
-            AssignedBits::<3>::assign_bits(region, || "a_lo(3)", a_3, row, a_lo)?;
-            AssignedBits::<3>::assign_bits(region, || "a_hi(3)", a_3, row + 1, a_hi)?;
+            AssignedBits::<F, 3>::assign_bits(region, || "a_lo(3)", a_3, row, a_lo)?;
+            AssignedBits::<F, 3>::assign_bits(region, || "a_hi(3)", a_3, row + 1, a_hi)?;
         }
         else if shift == 7 {
             let b: Value<[bool; 9]> = word_hi.map(|q| q[..9].try_into().unwrap());
@@ -462,8 +487,8 @@ impl CompressionConfig {
             let a_hi: Value<[bool; 4]> = word_hi.map(|q| q[12..].try_into().unwrap());
             self.assign_spread_word(region, &self.lookup, row, b, c)?;
 
-            AssignedBits::<3>::assign_bits(region, || "a_lo(3)", a_3, row, a_lo)?;
-            AssignedBits::<4>::assign_bits(region, || "a_hi(4)", a_3, row + 1, a_hi)?;
+            AssignedBits::<F, 3>::assign_bits(region, || "a_lo(3)", a_3, row, a_lo)?;
+            AssignedBits::<F, 4>::assign_bits(region, || "a_hi(4)", a_3, row + 1, a_hi)?;
         }
         else if shift == 8 {
             let b: Value<[bool; 8]> = word_hi.map(|q| q[..8].try_into().unwrap());
@@ -472,8 +497,8 @@ impl CompressionConfig {
             let a_hi: Value<[bool; 4]> = word_hi.map(|q| q[12..].try_into().unwrap());
             self.assign_spread_word(region, &self.lookup, row, b, c)?;
 
-            AssignedBits::<4>::assign_bits(region, || "a_lo(4)", a_3, row, a_lo)?;
-            AssignedBits::<4>::assign_bits(region, || "a_hi(4)", a_3, row + 1, a_hi)?;
+            AssignedBits::<F, 4>::assign_bits(region, || "a_lo(4)", a_3, row, a_lo)?;
+            AssignedBits::<F, 4>::assign_bits(region, || "a_hi(4)", a_3, row + 1, a_hi)?;
         }
         else if shift == 9 {
             let a: Value<[bool; 9]> = word_hi.map(|q| q[7..].try_into().unwrap());
@@ -482,8 +507,8 @@ impl CompressionConfig {
             let b_hi: Value<[bool; 4]> = word_hi.map(|q| q[3..7].try_into().unwrap());
             self.assign_spread_word(region, &self.lookup, row, a, c)?;
 
-            AssignedBits::<3>::assign_bits(region, || "b_lo(3)", a_3, row, b_lo)?;
-            AssignedBits::<4>::assign_bits(region, || "b_hi(4)", a_3, row + 1, b_hi)?;
+            AssignedBits::<F, 3>::assign_bits(region, || "b_lo(3)", a_3, row, b_lo)?;
+            AssignedBits::<F, 4>::assign_bits(region, || "b_hi(4)", a_3, row + 1, b_hi)?;
         }
         else if shift == 10 {
             let a: Value<[bool; 10]> = word_hi.map(|q| q[6..].try_into().unwrap());
@@ -492,8 +517,8 @@ impl CompressionConfig {
             let b_hi: Value<[bool; 3]> = word_hi.map(|q| q[3..6].try_into().unwrap());
             self.assign_spread_word(region, &self.lookup, row, a, c)?;
 
-            AssignedBits::<3>::assign_bits(region, || "b_lo(3)", a_3, row, b_lo)?;
-            AssignedBits::<3>::assign_bits(region, || "b_hi(3)", a_3, row + 1, b_hi)?;
+            AssignedBits::<F, 3>::assign_bits(region, || "b_lo(3)", a_3, row, b_lo)?;
+            AssignedBits::<F, 3>::assign_bits(region, || "b_hi(3)", a_3, row + 1, b_hi)?;
         }
         else if shift == 11 {
             let a: Value<[bool; 11]> = word_hi.map(|q| q[5..].try_into().unwrap());
@@ -502,8 +527,8 @@ impl CompressionConfig {
             let b_hi: Value<[bool; 3]> = word_hi.map(|q| q[2..5].try_into().unwrap());
             self.assign_spread_word(region, &self.lookup, row, a, c)?;
 
-            AssignedBits::<2>::assign_bits(region, || "b_lo(2)", a_3, row, b_lo)?;
-            AssignedBits::<3>::assign_bits(region, || "b_hi(3)", a_3, row + 1, b_hi)?;
+            AssignedBits::<F, 2>::assign_bits(region, || "b_lo(2)", a_3, row, b_lo)?;
+            AssignedBits::<F, 3>::assign_bits(region, || "b_hi(3)", a_3, row + 1, b_hi)?;
         }
         else if shift == 12 {
             let a: Value<[bool; 12]> = word_hi.map(|q| q[4..].try_into().unwrap());
@@ -512,8 +537,8 @@ impl CompressionConfig {
             let b_hi: Value<[bool; 2]> = word_hi.map(|q| q[2..4].try_into().unwrap());
             self.assign_spread_word(region, &self.lookup, row, a, c)?;
 
-            AssignedBits::<2>::assign_bits(region, || "b_lo(2)", a_3, row, b_lo)?;
-            AssignedBits::<2>::assign_bits(region, || "b_hi(2)", a_3, row + 1, b_hi)?;
+            AssignedBits::<F, 2>::assign_bits(region, || "b_lo(2)", a_3, row, b_lo)?;
+            AssignedBits::<F, 2>::assign_bits(region, || "b_hi(2)", a_3, row + 1, b_hi)?;
         }
         else if shift == 13 {
             let a: Value<[bool; 13]> = word_hi.map(|q| q[3..].try_into().unwrap());
@@ -521,7 +546,8 @@ impl CompressionConfig {
             let b: Value<[bool; 3]> = word_hi.map(|q| q[0..3].try_into().unwrap());
             self.assign_spread_word(region, &self.lookup, row, a, c)?;
 
-            AssignedBits::<3>::assign_bits(region, || "b(3)", a_3, row, b)?;
+            AssignedBits::<F, 3>::assign_bits(region, || "b(3)", a_3, row, b)?;
+            AssignedBits::<F, 1>::assign_bits(region, || "zero", a_3, row + 1, Value::known([false]))?;
         }
         else if shift == 14 {
             let a: Value<[bool; 14]> = word_hi.map(|q| q[2..].try_into().unwrap());
@@ -529,7 +555,8 @@ impl CompressionConfig {
             let b: Value<[bool; 2]> = word_hi.map(|q| q[0..2].try_into().unwrap());
             self.assign_spread_word(region, &self.lookup, row, a, c)?;
 
-            AssignedBits::<2>::assign_bits(region, || "b(2)", a_3, row, b)?;
+            AssignedBits::<F, 2>::assign_bits(region, || "b(2)", a_3, row, b)?;
+            AssignedBits::<F, 1>::assign_bits(region, || "zero", a_3, row + 1, Value::known([false]))?;
         }
         else {
             let a: Value<[bool; 15]> = word_hi.map(|q| q[1..].try_into().unwrap());
@@ -537,7 +564,8 @@ impl CompressionConfig {
             let b: Value<[bool; 1]> = word_hi.map(|q| q[0..1].try_into().unwrap());
             self.assign_spread_word(region, &self.lookup, row, a, c)?;
 
-            AssignedBits::<1>::assign_bits(region, || "b(1)", a_3, row, b)?;
+            AssignedBits::<F, 1>::assign_bits(region, || "b(1)", a_3, row, b)?;
+            AssignedBits::<F, 1>::assign_bits(region, || "zero", a_3, row + 1, Value::known([false]))?;
         };
 
         Ok(RoundWordDense(rol_word_lo, rol_word_hi))
@@ -550,13 +578,13 @@ impl CompressionConfig {
     //
     pub(super) fn assign_sum_afxk(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         row: usize,
-        a: RoundWordDense,
-        f: RoundWordDense,
-        x: RoundWordDense,
+        a: RoundWordDense<F>,
+        f: RoundWordDense<F>,
+        x: RoundWordDense<F>,
         k: u32,
-    ) -> Result<RoundWordDense, Error> {
+    ) -> Result<RoundWordDense<F>, Error> {
         let a_3 = self.advice[0];
         let a_4 = self.advice[1];
         let a_5 = self.advice[2];
@@ -578,8 +606,8 @@ impl CompressionConfig {
         let k: [bool; 32] = i2lebsp(k.into());
         let k_lo: [bool; 16] = k[..16].try_into().unwrap();
         let k_hi: [bool; 16] = k[16..].try_into().unwrap();
-        AssignedBits::<16>::assign_bits(region, || "k_lo", a_3, row + 2, Value::known(k_lo))?;
-        AssignedBits::<16>::assign_bits(region, || "k_hi", a_4, row + 2, Value::known(k_hi))?;
+        AssignedBits::<F, 16>::assign_bits(region, || "k_lo", a_3, row + 2, Value::known(k_lo))?;
+        AssignedBits::<F, 16>::assign_bits(region, || "k_hi", a_4, row + 2, Value::known(k_hi))?;
         
         let (sum, carry) = sum_with_carry(vec![
             (a.0.value_u16(), a.1.value_u16()),
@@ -595,7 +623,7 @@ impl CompressionConfig {
             || "sum_afxk_carry",
             a_5,
             row + 2,
-            || carry.map(|value| pallas::Base::from(value as u64)),
+            || carry.map(|value| F::from(value as u64)),
         )?;
 
         let sum: Value<[bool; 32]> = sum.map(|w| i2lebsp(w.into()));
@@ -613,11 +641,11 @@ impl CompressionConfig {
     //
     pub(super) fn assign_sum_re(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         row: usize,
-        rol: RoundWordDense,
-        e: RoundWordDense,
-    ) -> Result<RoundWord, Error> {
+        rol: RoundWordDense<F>,
+        e: RoundWordDense<F>,
+    ) -> Result<RoundWord<F>, Error> {
         let a_3 = self.advice[0];
         let a_4 = self.advice[1];
         let a_5 = self.advice[2];
@@ -640,7 +668,7 @@ impl CompressionConfig {
             || "sum_re_carry",
             a_5,
             row,
-            || carry.map(|value| pallas::Base::from(value as u64)),
+            || carry.map(|value| F::from(value as u64)),
         )?;
 
         let sum: Value<[bool; 32]> = sum.map(|w| i2lebsp(w.into()));
@@ -664,12 +692,12 @@ impl CompressionConfig {
     //
     pub(super) fn assign_sum_combine_ilr(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         row: usize,
-        init_state_word: RoundWordDense,
-        left_state_word: RoundWordDense,
-        right_state_word: RoundWordDense,
-    ) -> Result<RoundWord, Error> {
+        init_state_word: RoundWordDense<F>,
+        left_state_word: RoundWordDense<F>,
+        right_state_word: RoundWordDense<F>,
+    ) -> Result<RoundWord<F>, Error> {
         let a_3 = self.advice[0];
         let a_4 = self.advice[1];
         let a_5 = self.advice[2];
@@ -697,7 +725,7 @@ impl CompressionConfig {
             || "sum_combine_ilr_carry",
             a_3,
             row+2,
-            || carry.map(|value| pallas::Base::from(value as u64)),
+            || carry.map(|value| F::from(value as u64)),
         )?;
 
         let sum: Value<[bool; 32]> = sum.map(|w| i2lebsp(w.into()));
@@ -725,7 +753,7 @@ impl CompressionConfig {
     #[allow(clippy::type_complexity)]
     fn assign_spread_outputs(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         lookup: &SpreadInputs,
         row: usize,
         r_0_even: Value<[bool; 16]>,
@@ -734,8 +762,8 @@ impl CompressionConfig {
         r_1_odd: Value<[bool; 16]>,
     ) -> Result<
         (
-            (AssignedBits<16>, AssignedBits<16>),
-            (AssignedBits<16>, AssignedBits<16>),
+            (AssignedBits<F, 16>, AssignedBits<F, 16>),
+            (AssignedBits<F, 16>, AssignedBits<F, 16>),
         ),
         Error,
     > {
@@ -779,15 +807,15 @@ impl CompressionConfig {
     #[allow(clippy::type_complexity)]
     fn assign_spread_word(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         lookup: &SpreadInputs,
         row: usize,
         r_lo: Value<[bool; 16]>,
         r_hi: Value<[bool; 16]>,
     ) -> Result<
         (
-            (AssignedBits<16>, AssignedBits<16>),
-            (AssignedBits<32>, AssignedBits<32>),
+            (AssignedBits<F, 16>, AssignedBits<F, 16>),
+            (AssignedBits<F, 32>, AssignedBits<F, 32>),
         ),
             Error,
     > 
@@ -820,11 +848,11 @@ impl CompressionConfig {
     #[allow(clippy::type_complexity)]
     pub(super) fn assign_spread_dense_word(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         lookup: &SpreadInputs,
         row: usize,
-        word: RoundWordDense,
-    ) -> Result<RoundWord, Error> 
+        word: RoundWordDense<F>,
+    ) -> Result<RoundWord<F>, Error> 
     {
         // Lookup R_lo, R_hi
         let word_bits = word.value().map(|a| i2lebsp::<32>(a.into()));
@@ -852,10 +880,10 @@ impl CompressionConfig {
 
     pub(super) fn assign_decompose_word(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         row: usize,
-        word_lo: AssignedBits<16>,
-        word_hi: AssignedBits<16>,
+        word_lo: AssignedBits<F, 16>,
+        word_hi: AssignedBits<F, 16>,
         word: Value<u32>,
     ) -> Result<(), Error> {
         let a_3 = self.advice[0];
@@ -864,7 +892,7 @@ impl CompressionConfig {
 
         self.s_decompose_word.enable(region, row)?;
 
-        AssignedBits::<32>::assign(
+        AssignedBits::<F, 32>::assign(
             region,
             || "word(u32)",
             a_5,
@@ -880,42 +908,55 @@ impl CompressionConfig {
 
     pub(super) fn assign_decompose_word_dense(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         row: usize,
-        word: RoundWordDense,
-    ) -> Result<(), Error> {
+        word: RoundWordDense<F>,
+    ) -> Result<AssignedBits<F, 32>, Error> {
+        let word_value = word.value();
+        self.assign_decompose_word_equals(region, row, word, word_value)
+    }
+
+    // Assigns `expected` as the 32-bit word whose halves are copied from `word`. The
+    // s_decompose_word gate fails unless the halves recompose to `expected`.
+    pub(super) fn assign_decompose_word_equals(
+        &self,
+        region: &mut Region<'_, F>,
+        row: usize,
+        word: RoundWordDense<F>,
+        expected: Value<u32>,
+    ) -> Result<AssignedBits<F, 32>, Error> {
         let a_3 = self.advice[0];
         let a_4 = self.advice[1];
         let a_5 = self.advice[2];
 
         self.s_decompose_word.enable(region, row)?;
 
-        AssignedBits::<32>::assign(
+        let word_u32 = AssignedBits::<F, 32>::assign(
             region,
             || "word(u32)",
             a_5,
             row,
-            word.value(),
+            expected,
         )?;
 
         word.0.copy_advice(|| "word_lo", region, a_3, row)?;
         word.1.copy_advice(|| "word_hi", region, a_4, row)?;
 
-        Ok(())
+        Ok(word_u32)
     }
 
 
 }
 
 #[allow(clippy::many_single_char_names)]
-pub fn match_state(
-    state: State,
+pub fn match_state<F: FieldExt>(
+    state: State<F>,
 ) -> (
-    RoundWordDense,
-    RoundWord,
-    RoundWord,
-    RoundWord,
-    RoundWordDense,
+    RoundWordDense<F>,
+    RoundWord<F>,
+    RoundWord<F>,
+    RoundWord<F>,
+    RoundWordDense<F>,
 ) {
     let a = match state.a {
         Some(StateWord::A(a)) => a,
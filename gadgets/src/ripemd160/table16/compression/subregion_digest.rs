@@ -1,37 +1,55 @@
-use super::super::BlockWord;
+use super::super::AssignedBits;
 use super::{CompressionConfig, State, DIGEST_SIZE};
 use super::compression_util::*;
 use halo2_proofs::{
-    circuit::Region,
-    plonk::Error, halo2curves::pasta::pallas,
+    circuit::{Region, Value},
+    plonk::Error,
+    arithmetic::FieldExt,
 };
 
-impl CompressionConfig {
+impl<F: FieldExt> CompressionConfig<F> {
     #[allow(clippy::many_single_char_names)]
     pub fn assign_digest(
         &self,
-        region: &mut Region<'_, pallas::Base>,
-        state: State,
-    ) -> Result<[BlockWord; DIGEST_SIZE], Error> {
+        region: &mut Region<'_, F>,
+        state: State<F>,
+    ) -> Result<[AssignedBits<F, 32>; DIGEST_SIZE], Error> {
         let (a, b, c, d, e) = match_state(state);
 
         let mut row: usize = 0;
-        self.assign_decompose_word_dense(region, row, a.clone())?;
+        let a = self.assign_decompose_word_dense(region, row, a)?;
         row += 1;
-        self.assign_decompose_word_dense(region, row, b.clone().dense_halves)?;
+        let b = self.assign_decompose_word_dense(region, row, b.dense_halves)?;
         row += 1;
-        self.assign_decompose_word_dense(region, row, c.clone().dense_halves)?;
+        let c = self.assign_decompose_word_dense(region, row, c.dense_halves)?;
         row += 1;
-        self.assign_decompose_word_dense(region, row, d.clone().dense_halves)?;
+        let d = self.assign_decompose_word_dense(region, row, d.dense_halves)?;
         row += 1;
-        self.assign_decompose_word_dense(region, row, e.clone())?;
+        let e = self.assign_decompose_word_dense(region, row, e)?;
 
-        Ok([
-            BlockWord(a.value()),
-            BlockWord(b.dense_halves.value()),
-            BlockWord(c.dense_halves.value()),
-            BlockWord(d.dense_halves.value()),
-            BlockWord(e.value()),
-        ])
+        Ok([a, b, c, d, e])
     }
-}
\ No newline at end of file
+
+    #[allow(clippy::many_single_char_names)]
+    pub fn assign_digest_equals(
+        &self,
+        region: &mut Region<'_, F>,
+        state: State<F>,
+        expected: [Value<u32>; DIGEST_SIZE],
+    ) -> Result<[AssignedBits<F, 32>; DIGEST_SIZE], Error> {
+        let (a, b, c, d, e) = match_state(state);
+
+        let mut row: usize = 0;
+        let a = self.assign_decompose_word_equals(region, row, a, expected[0])?;
+        row += 1;
+        let b = self.assign_decompose_word_equals(region, row, b.dense_halves, expected[1])?;
+        row += 1;
+        let c = self.assign_decompose_word_equals(region, row, c.dense_halves, expected[2])?;
+        row += 1;
+        let d = self.assign_decompose_word_equals(region, row, d.dense_halves, expected[3])?;
+        row += 1;
+        let e = self.assign_decompose_word_equals(region, row, e, expected[4])?;
+
+        Ok([a, b, c, d, e])
+    }
+}
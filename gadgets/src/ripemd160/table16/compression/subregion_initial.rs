@@ -6,15 +6,15 @@ use halo2_proofs::{
     circuit::{Region, Value},
     plonk::Error,
 };
-use halo2_proofs::halo2curves::pasta::pallas;
+use halo2_proofs::arithmetic::FieldExt;
 
-impl CompressionConfig {
+impl<F: FieldExt> CompressionConfig<F> {
     #[allow(clippy::many_single_char_names)]
     pub fn initialize_iv(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         iv: [u32; DIGEST_SIZE],
-    ) -> Result<State, Error> {
+    ) -> Result<State<F>, Error> {
         let a_3 = self.advice[0];
         let a_4 = self.advice[1];
         let a_5 = self.advice[2];
@@ -3,19 +3,19 @@ use super::super::{AssignedBits, StateWord, ROUND_CONSTANTS_LEFT};
 use super::RoundSide::{self, Left, Right};
 use super::{compression_util::*, CompressionConfig, State, RoundWordDense};
 use halo2_proofs::{circuit::Region, plonk::Error};
-use halo2_proofs::halo2curves::pasta::pallas;
+use halo2_proofs::arithmetic::FieldExt;
 
-impl CompressionConfig {
+impl<F: FieldExt> CompressionConfig<F> {
     #[allow(clippy::many_single_char_names)]
     pub fn assign_round(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         round_idx: usize,
-        state: State,
-        message_word_halves: [(AssignedBits<16>, AssignedBits<16>); BLOCK_SIZE],
+        state: State<F>,
+        message_word_halves: [(AssignedBits<F, 16>, AssignedBits<F, 16>); BLOCK_SIZE],
         row: &mut usize,
         round_side: RoundSide,
-    ) -> Result<State, Error> {
+    ) -> Result<State<F>, Error> {
         let (a, b, c, d, e ) = match_state(state);
 
         let phase_idx = 1 + round_idx/ROUND_PHASE_SIZE;
@@ -83,18 +83,7 @@ impl CompressionConfig {
         };
 
         // A + f1(B,C,D) + X[r(idx)] + K(idx/16)
-        let x = if round_side == Left {
-            RoundWordDense(
-                message_word_halves[MSG_SEL_IDX_LEFT[round_idx]].clone().0,
-                message_word_halves[MSG_SEL_IDX_LEFT[round_idx]].clone().1,
-            )
-        }
-        else {
-            RoundWordDense(
-                message_word_halves[MSG_SEL_IDX_RIGHT[round_idx]].clone().0,
-                message_word_halves[MSG_SEL_IDX_RIGHT[round_idx]].clone().1,
-            )
-        };
+        let x = select_message_word(&message_word_halves, round_idx, &round_side);
         let sum_afxk = self.assign_sum_afxk(
             region,
             *row,
@@ -162,12 +151,12 @@ impl CompressionConfig {
     #[allow(clippy::many_single_char_names)]
     pub fn assign_combine_ilr(
         &self,
-        region: &mut Region<'_, pallas::Base>,
-        init_state: State,
-        left_state: State,
-        right_state: State,
+        region: &mut Region<'_, F>,
+        init_state: State<F>,
+        left_state: State<F>,
+        right_state: State<F>,
         row: &mut usize,
-    ) -> Result<State, Error> {
+    ) -> Result<State<F>, Error> {
         let (h0, h1, h2, h3, h4) = match_state(init_state);
         let (a_left, b_left, c_left, d_left, e_left) = match_state(left_state);
         let (a_right, b_right, c_right, d_right, e_right) = match_state(right_state);
@@ -191,4 +180,23 @@ impl CompressionConfig {
             StateWord::E(e.dense_halves),
         ))
     }
-}
\ No newline at end of file
+}
+
+/// Selects the message word X[r(idx)] used in a round, where r is the message
+/// word permutation of the left or right line
+pub(super) fn select_message_word<F: FieldExt>(
+    message_word_halves: &[(AssignedBits<F, 16>, AssignedBits<F, 16>); BLOCK_SIZE],
+    round_idx: usize,
+    round_side: &RoundSide,
+) -> RoundWordDense<F> {
+    let word_idx = if *round_side == Left {
+        MSG_SEL_IDX_LEFT[round_idx]
+    }
+    else {
+        MSG_SEL_IDX_RIGHT[round_idx]
+    };
+    RoundWordDense(
+        message_word_halves[word_idx].clone().0,
+        message_word_halves[word_idx].clone().1,
+    )
+}
@@ -0,0 +1,213 @@
+use std::convert::TryInto;
+
+use super::{AssignedBits, DIGEST_SIZE, NUM_ADVICE_COLS};
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+};
+use std::marker::PhantomData;
+
+/// Selects the digest after a witnessed number of blocks among the digests after each
+/// block of a fixed number of blocks.
+///
+/// Each candidate has a boolean selection flag and exactly one flag is set. The number
+/// of blocks is the position of the set flag, counted by summing the complement of the
+/// running sum of the flags. Each word of the selected digest is the running sum of
+/// the flags times the words of the candidates.
+//
+// | row                | a_3    | a_4                 | a_5                         |
+// |--------------------|--------|---------------------|-----------------------------|
+// | k                  | flag_k | flag_0 + .. + flag_k| number of blocks up to k    |
+// | n + k * 5 + w      | flag_k | digest_k[w]         | selected word w up to k     |
+//
+// where n is the number of candidates and the number of blocks up to k is
+// 1 + (1 - flag_0) + .. + (1 - flag_0 - .. - flag_{k-1}).
+#[derive(Clone, Debug)]
+pub(super) struct DigestSelectConfig<F: FieldExt> {
+    advice: [Column<Advice>; NUM_ADVICE_COLS],
+
+    /// Flag of the first candidate
+    s_count_first: Selector,
+    /// Flags of the other candidates
+    s_count_next: Selector,
+    /// Flag of the last candidate, where the running sums are complete
+    s_count_last: Selector,
+    /// Words of the first candidate
+    s_word_first: Selector,
+    /// Words of the other candidates
+    s_word_next: Selector,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> DigestSelectConfig<F> {
+    pub(super) fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advice: [Column<Advice>; NUM_ADVICE_COLS],
+    ) -> Self {
+        let s_count_first = meta.selector();
+        let s_count_next = meta.selector();
+        let s_count_last = meta.selector();
+        let s_word_first = meta.selector();
+        let s_word_next = meta.selector();
+
+        let a_3 = advice[0];
+        let a_4 = advice[1];
+        let a_5 = advice[2];
+
+        let one = || Expression::Constant(F::one());
+
+        meta.create_gate("select count first", |meta| {
+            let s_count_first = meta.query_selector(s_count_first);
+            let flag = meta.query_advice(a_3, Rotation::cur());
+            let flags = meta.query_advice(a_4, Rotation::cur());
+            let num_blocks = meta.query_advice(a_5, Rotation::cur());
+
+            vec![
+                ("flag is boolean", s_count_first.clone() * flag.clone() * (one() - flag.clone())),
+                ("flags", s_count_first.clone() * (flags - flag)),
+                ("num blocks", s_count_first * (num_blocks - one())),
+            ]
+        });
+
+        meta.create_gate("select count next", |meta| {
+            let s_count_next = meta.query_selector(s_count_next);
+            let flag = meta.query_advice(a_3, Rotation::cur());
+            let flags = meta.query_advice(a_4, Rotation::cur());
+            let flags_prev = meta.query_advice(a_4, Rotation::prev());
+            let num_blocks = meta.query_advice(a_5, Rotation::cur());
+            let num_blocks_prev = meta.query_advice(a_5, Rotation::prev());
+
+            vec![
+                ("flag is boolean", s_count_next.clone() * flag.clone() * (one() - flag.clone())),
+                ("flags", s_count_next.clone() * (flags - flags_prev.clone() - flag)),
+                ("num blocks", s_count_next * (num_blocks - num_blocks_prev - one() + flags_prev)),
+            ]
+        });
+
+        meta.create_gate("select count last", |meta| {
+            let s_count_last = meta.query_selector(s_count_last);
+            let flags = meta.query_advice(a_4, Rotation::cur());
+
+            // Exactly one of the boolean flags is set
+            vec![("one flag", s_count_last * (flags - one()))]
+        });
+
+        meta.create_gate("select word first", |meta| {
+            let s_word_first = meta.query_selector(s_word_first);
+            let flag = meta.query_advice(a_3, Rotation::cur());
+            let word = meta.query_advice(a_4, Rotation::cur());
+            let selected = meta.query_advice(a_5, Rotation::cur());
+
+            vec![("selected word", s_word_first * (selected - flag * word))]
+        });
+
+        meta.create_gate("select word next", |meta| {
+            let s_word_next = meta.query_selector(s_word_next);
+            let flag = meta.query_advice(a_3, Rotation::cur());
+            let word = meta.query_advice(a_4, Rotation::cur());
+            let selected = meta.query_advice(a_5, Rotation::cur());
+            // The same word of the previous candidate
+            let selected_prev = meta.query_advice(a_5, Rotation(-(DIGEST_SIZE as i32)));
+
+            vec![("selected word", s_word_next * (selected - selected_prev - flag * word))]
+        });
+
+        DigestSelectConfig {
+            advice,
+            s_count_first,
+            s_count_next,
+            s_count_last,
+            s_word_first,
+            s_word_next,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the digest at position `num_blocks - 1` of `digests`. The circuit is not
+    /// satisfied unless `num_blocks` is between 1 and `digests.len()`.
+    pub(super) fn select(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        digests: &[[AssignedBits<F, 32>; DIGEST_SIZE]],
+        num_blocks: Value<u64>,
+    ) -> Result<[AssignedBits<F, 32>; DIGEST_SIZE], Error> {
+        assert!(!digests.is_empty());
+
+        let a_3 = self.advice[0];
+        let a_4 = self.advice[1];
+        let a_5 = self.advice[2];
+
+        layouter.assign_region(
+            || "select digest",
+            |mut region| {
+                let mut flags = Vec::with_capacity(digests.len());
+                for k in 0..digests.len() {
+                    if k == 0 {
+                        self.s_count_first.enable(&mut region, k)?;
+                    } else {
+                        self.s_count_next.enable(&mut region, k)?;
+                    }
+                    if k == digests.len() - 1 {
+                        self.s_count_last.enable(&mut region, k)?;
+                    }
+
+                    let count = k as u64 + 1;
+                    flags.push(region.assign_advice(
+                        || "flag",
+                        a_3,
+                        k,
+                        || num_blocks.map(|n| F::from((n == count) as u64)),
+                    )?);
+                    region.assign_advice(
+                        || "flags",
+                        a_4,
+                        k,
+                        || num_blocks.map(|n| F::from((n <= count) as u64)),
+                    )?;
+                    region.assign_advice(
+                        || "num blocks",
+                        a_5,
+                        k,
+                        || num_blocks.map(|n| F::from(n.clamp(1, count))),
+                    )?;
+                }
+
+                let candidates: Value<Vec<Vec<u32>>> = digests
+                    .iter()
+                    .map(|digest| digest.iter().map(|word| word.value_u32()).collect())
+                    .collect();
+
+                let mut row = digests.len();
+                let mut selected = Vec::with_capacity(DIGEST_SIZE);
+                for (k, (digest, flag)) in digests.iter().zip(flags.iter()).enumerate() {
+                    selected.clear();
+                    for (w, word) in digest.iter().enumerate() {
+                        if k == 0 {
+                            self.s_word_first.enable(&mut region, row)?;
+                        } else {
+                            self.s_word_next.enable(&mut region, row)?;
+                        }
+
+                        flag.copy_advice(|| "flag", &mut region, a_3, row)?;
+                        word.copy_advice(|| "word", &mut region, a_4, row)?;
+                        // The word of the selected candidate once it is reached, zero before
+                        let value = candidates.as_ref().zip(num_blocks).map(|(candidates, n)| {
+                            let n = n as usize;
+                            if (1..=k + 1).contains(&n) {
+                                candidates[n - 1][w]
+                            } else {
+                                0
+                            }
+                        });
+                        selected.push(AssignedBits::<F, 32>::assign(&mut region, || "selected word", a_5, row, value)?);
+                        row += 1;
+                    }
+                }
+
+                Ok(selected.try_into().unwrap())
+            },
+        )
+    }
+}
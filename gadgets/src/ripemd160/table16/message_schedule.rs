@@ -8,19 +8,21 @@ use super::gates::Gate;
 use super::{AssignedBits, SpreadInputs, Table16Assignment, NUM_ADVICE_COLS, BlockWord};
 use super::BLOCK_SIZE;
 use halo2_proofs::{
-    circuit::Layouter,
+    arithmetic::FieldExt,
+    circuit::{Layouter, Region},
     plonk::{Advice, Column, ConstraintSystem, Error, Selector},
     poly::Rotation,
 };
-use halo2_proofs::halo2curves::pasta::pallas;
+use std::marker::PhantomData;
 
 mod schedule_util;
+use schedule_util::get_word_row;
 
 #[derive(Clone, Debug)]
-pub(super) struct MessageWord(AssignedBits<32>);
+pub(super) struct MessageWord<F: FieldExt>(AssignedBits<F, 32>);
 
-impl std::ops::Deref for MessageWord {
-    type Target = AssignedBits<32>;
+impl<F: FieldExt> std::ops::Deref for MessageWord<F> {
+    type Target = AssignedBits<F, 32>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -28,17 +30,18 @@ impl std::ops::Deref for MessageWord {
 }
 
 #[derive(Clone, Debug)]
-pub(super) struct MessageScheduleConfig {
+pub(super) struct MessageScheduleConfig<F: FieldExt> {
     lookup: SpreadInputs,
     advice: [Column<Advice>; NUM_ADVICE_COLS],
 
     /// Decomposition gate for X[0..16]
     s_decompose_word: Selector,
+    _marker: PhantomData<F>,
 }
 
-impl Table16Assignment for MessageScheduleConfig {}
+impl<F: FieldExt> Table16Assignment<F> for MessageScheduleConfig<F> {}
 
-impl MessageScheduleConfig {
+impl<F: FieldExt> MessageScheduleConfig<F> {
     /// Configures the message schedule.
     ///
     /// `advice` contains columns that the message schedule will only use for internal
@@ -46,7 +49,7 @@ impl MessageScheduleConfig {
     /// itself.
     #[allow(clippy::many_single_char_names)]
     pub(super) fn configure(
-        meta: &mut ConstraintSystem<pallas::Base>,
+        meta: &mut ConstraintSystem<F>,
         lookup: SpreadInputs,
         advice: [Column<Advice>; NUM_ADVICE_COLS],
         s_decompose_word: Selector,
@@ -70,41 +73,174 @@ impl MessageScheduleConfig {
             lookup,
             advice,
             s_decompose_word,
+            _marker: PhantomData,
         }
     }
 
     #[allow(clippy::type_complexity)]
     pub(super) fn process(
         &self,
-        layouter: &mut impl Layouter<pallas::Base>,
+        layouter: &mut impl Layouter<F>,
         input: [BlockWord; BLOCK_SIZE],
     ) -> Result<
         (
-            [MessageWord; BLOCK_SIZE],
-            [(AssignedBits<16>, AssignedBits<16>); BLOCK_SIZE],
+            [MessageWord<F>; BLOCK_SIZE],
+            [(AssignedBits<F, 16>, AssignedBits<F, 16>); BLOCK_SIZE],
         ),
         Error,
     > {
-        let mut w = Vec::<MessageWord>::with_capacity(BLOCK_SIZE);
-        let mut w_halves = Vec::<(AssignedBits<16>, AssignedBits<16>)>::with_capacity(BLOCK_SIZE);
+        self.process_inner(layouter, input, None)
+    }
+
+    /// Processes words which are already assigned in the circuit, e.g. by the padding
+    /// gadget, constraining the message words to be equal to them.
+    #[allow(clippy::type_complexity)]
+    pub(super) fn process_assigned(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        input: &[AssignedBits<F, 32>; BLOCK_SIZE],
+    ) -> Result<
+        (
+            [MessageWord<F>; BLOCK_SIZE],
+            [(AssignedBits<F, 16>, AssignedBits<F, 16>); BLOCK_SIZE],
+        ),
+        Error,
+    > {
+        let values = input.clone().map(|word| BlockWord(word.value_u32()));
+        self.process_inner(layouter, values, Some(input))
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn process_inner(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        input: [BlockWord; BLOCK_SIZE],
+        assigned_input: Option<&[AssignedBits<F, 32>; BLOCK_SIZE]>,
+    ) -> Result<
+        (
+            [MessageWord<F>; BLOCK_SIZE],
+            [(AssignedBits<F, 16>, AssignedBits<F, 16>); BLOCK_SIZE],
+        ),
+        Error,
+    > {
+        let mut w = None;
+        let mut w_halves = None;
 
         layouter.assign_region(
             || "process message block",
             |mut region| {
-                w = Vec::<MessageWord>::with_capacity(BLOCK_SIZE);
-                w_halves = Vec::<(AssignedBits<16>, AssignedBits<16>)>::with_capacity(BLOCK_SIZE);
-
-                // Assign X[0..16]
-                for (row, word) in input.iter().enumerate() {
-                    let (word, halves) = self.assign_msgblk_word_and_halves(&mut region, word.0, row)?;
-                    w.push(MessageWord(word));
-                    w_halves.push(halves);
-                }
-
+                let mut row = 0;
+                let (words, halves) = self.assign_block(&mut region, &mut row, input, assigned_input)?;
+                w = Some(words);
+                w_halves = Some(halves);
                 Ok(())
             },
         )?;
 
+        Ok((w.unwrap(), w_halves.unwrap()))
+    }
+
+    /// Assigns the words of a message block and their halves in `region` starting at
+    /// `row`, and advances `row` past the block. This lets a region hold several blocks.
+    #[allow(clippy::type_complexity)]
+    pub(super) fn assign_block(
+        &self,
+        region: &mut Region<'_, F>,
+        row: &mut usize,
+        input: [BlockWord; BLOCK_SIZE],
+        assigned_input: Option<&[AssignedBits<F, 32>; BLOCK_SIZE]>,
+    ) -> Result<
+        (
+            [MessageWord<F>; BLOCK_SIZE],
+            [(AssignedBits<F, 16>, AssignedBits<F, 16>); BLOCK_SIZE],
+        ),
+        Error,
+    > {
+        let mut w = Vec::<MessageWord<F>>::with_capacity(BLOCK_SIZE);
+        let mut w_halves = Vec::<(AssignedBits<F, 16>, AssignedBits<F, 16>)>::with_capacity(BLOCK_SIZE);
+
+        // Assign X[0..16]
+        for (idx, word) in input.iter().enumerate() {
+            let (word, halves) = self.assign_msgblk_word_and_halves(region, word.0, *row, idx)?;
+            if let Some(assigned_input) = assigned_input {
+                region.constrain_equal(word.cell(), assigned_input[idx].cell())?;
+            }
+            w.push(MessageWord(word));
+            w_halves.push(halves);
+        }
+        *row += get_word_row(BLOCK_SIZE);
+
         Ok((w.try_into().unwrap(), w_halves.try_into().unwrap()))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ripemd160::ref_impl::constants::{BLOCK_SIZE, BLOCK_SIZE_BYTES};
+    use crate::ripemd160::table16::BlockWord;
+    use crate::ripemd160::table16::util::convert_byte_slice_to_u32_slice;
+
+    use super::super::{Table16Chip, Table16Config};
+    use halo2_proofs::circuit::Value;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+    use halo2_proofs::halo2curves::pasta::pallas;
+
+    #[test]
+    fn test_message_schedule_process() {
+        struct MyCircuit {}
+
+        impl Circuit<pallas::Base> for MyCircuit {
+            type Config = Table16Config<pallas::Base>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit {}
+            }
+
+            fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+                Table16Chip::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<pallas::Base>,
+            ) -> Result<(), Error> {
+                Table16Chip::load(config.clone(), &mut layouter)?;
+
+                // Message block with distinct words
+                let mut input_bytes = [0u8; BLOCK_SIZE_BYTES];
+                for (i, byte) in input_bytes.iter_mut().enumerate() {
+                    *byte = (7 * i + 1) as u8;
+                }
+                let input: [u32; BLOCK_SIZE] = convert_byte_slice_to_u32_slice(input_bytes);
+
+                let (w, w_halves) = config.message_schedule.process(&mut layouter, input.map(|x| BlockWord(Value::known(x))))?;
+
+                // The message words are the input words without any expansion
+                for (idx, word) in w.iter().enumerate() {
+                    word.value_u32().assert_if_known(|v| *v == input[idx]);
+                }
+                for (idx, (lo, hi)) in w_halves.iter().enumerate() {
+                    lo.value_u16()
+                        .zip(hi.value_u16())
+                        .assert_if_known(|(lo, hi)| (*lo as u32) + ((*hi as u32) << 16) == input[idx]);
+                }
+
+                Ok(())
+            }
+        }
+
+        let circuit: MyCircuit = MyCircuit {};
+
+        let prover = match MockProver::<pallas::Base>::run(17, &circuit, vec![]) {
+            Ok(prover) => prover,
+            Err(e) => panic!("{:?}", e),
+        };
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
@@ -8,7 +8,7 @@ use halo2_proofs::{
     plonk::Error,
 };
 
-use halo2_proofs::halo2curves::pasta::pallas;
+use halo2_proofs::arithmetic::FieldExt;
 use super::super::message_schedule::BLOCK_SIZE;
 
 
@@ -21,20 +21,21 @@ pub fn get_word_row(word_idx: usize) -> usize {
     word_idx * DECOMPOSE_WORD_ROWS
 }
 
-impl MessageScheduleConfig {
-    // Assign a word and its hi and lo halves
+impl<F: FieldExt> MessageScheduleConfig<F> {
+    // Assign a word and its hi and lo halves, for a block starting at start_row
     pub fn assign_msgblk_word_and_halves(
         &self,
-        region: &mut Region<'_, pallas::Base>,
+        region: &mut Region<'_, F>,
         word: Value<u32>,
+        start_row: usize,
         word_idx: usize,
-    ) -> Result<(AssignedBits<32>, (AssignedBits<16>, AssignedBits<16>)), Error> {
+    ) -> Result<(AssignedBits<F, 32>, (AssignedBits<F, 16>, AssignedBits<F, 16>)), Error> {
         // Rename these here for ease of matching the gates to the specification.
         let a_3 = self.advice[0];
         let a_4 = self.advice[1];
         let a_5 = self.advice[2];
 
-        let row = get_word_row(word_idx);
+        let row = start_row + get_word_row(word_idx);
         self.s_decompose_word.enable(region, row)?;
 
         let (word, (spread_var_lo, spread_var_hi)) =
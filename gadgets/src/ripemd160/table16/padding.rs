@@ -0,0 +1,467 @@
+use std::convert::TryInto;
+
+use super::{AssignedBits, SpreadInputs, SpreadVar, SpreadWord, NUM_ADVICE_COLS};
+use super::util::i2lebsp;
+use crate::ripemd160::ref_impl::constants::{BLOCK_SIZE, BLOCK_SIZE_BYTES};
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector, VirtualCells},
+    poly::Rotation,
+};
+use std::marker::PhantomData;
+
+/// Number of bytes of the message length at the end of the last block
+pub const LENGTH_FIELD_BYTES: usize = 8;
+const PAD_BYTE: u64 = 0x80;
+
+/// Returns the number of blocks of a padded message of `len` bytes
+pub fn num_padded_blocks(len: usize) -> usize {
+    (len + LENGTH_FIELD_BYTES) / BLOCK_SIZE_BYTES + 1
+}
+
+/// Byte order of the words of the padded blocks and of the length field
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum PaddingByteOrder {
+    /// Little-endian, as in RIPEMD-160
+    LittleEndian,
+    /// Big-endian, as in SHA-1 and SHA-256
+    BigEndian,
+}
+
+impl PaddingByteOrder {
+    /// Weight of the byte at `index` in a number of `num_bytes` bytes
+    fn byte_weight<F: FieldExt>(&self, index: usize, num_bytes: usize) -> F {
+        let shift = match self {
+            PaddingByteOrder::LittleEndian => 8 * index,
+            PaddingByteOrder::BigEndian => 8 * (num_bytes - 1 - index),
+        };
+        F::from(1u64 << shift)
+    }
+
+    fn word_from_bytes(&self, bytes: &[u8]) -> u32 {
+        let bytes = bytes.try_into().unwrap();
+        match self {
+            PaddingByteOrder::LittleEndian => u32::from_le_bytes(bytes),
+            PaddingByteOrder::BigEndian => u32::from_be_bytes(bytes),
+        }
+    }
+
+    fn word_to_bytes(&self, word: u32) -> [u8; 4] {
+        match self {
+            PaddingByteOrder::LittleEndian => word.to_le_bytes(),
+            PaddingByteOrder::BigEndian => word.to_be_bytes(),
+        }
+    }
+}
+
+/// A message padded in the circuit, along with the cells of its bytes, e.g. to compute
+/// another commitment to the message than its digest
+#[derive(Clone, Debug)]
+pub struct PaddedMessage<F: FieldExt> {
+    /// Blocks of words of the padded message, in the byte order of the hash function
+    pub blocks: Vec<[AssignedBits<F, 32>; BLOCK_SIZE]>,
+    /// Range checked bytes of the padded message
+    pub bytes: Vec<AssignedBits<F, 8>>,
+    /// For each byte, 1 if it belongs to the message and 0 if it is padding. The flags are
+    /// 1 for the first `len` bytes and 0 for the other ones.
+    pub in_message: Vec<AssignedCell<F, F>>,
+    /// Length of the message in bytes
+    pub len: AssignedCell<F, F>,
+}
+
+/// Constrains the padding of a message with a witnessed length to whole blocks.
+///
+/// Every byte of the padded message is range checked with a lookup. A flag marks the
+/// bytes which belong to the message and can only switch from 1 to 0 once, and the
+/// sum of the flags is the message length. The first byte after the message is 0x80
+/// and the following bytes up to the 64-bit length field are zero. The message has to
+/// end in the last block, so the padding is the one of RIPEMD-160, SHA-1 and SHA-256
+/// and not longer. The words and the length field are little-endian for RIPEMD-160
+/// and big-endian for SHA-1 and SHA-256.
+///
+/// A message of fixed length can also be given as big-endian words which are already
+/// assigned, e.g. a SHA-256 digest. The words are then copied next to their bytes.
+//
+// | row   | a_0 | a_1    | a_2           | a_3        | a_4               | a_5          |
+// |-------|-----|--------|---------------|------------|-------------------|--------------|
+// | 0     |     |        |               | 1          | 0                 |              |
+// | 1 + i | 0   | byte_i | spread_byte_i | i < len    | min(i + 1, len)   | word_{i/4}   |
+//
+// The word column is only assigned on the rows of the first byte of each word, and
+// for a fixed length message on the rows of the second byte with the big-endian word.
+#[derive(Clone, Debug)]
+pub(crate) struct PaddingConfig<F: FieldExt> {
+    lookup: SpreadInputs,
+    advice: [Column<Advice>; NUM_ADVICE_COLS],
+    byte_order: PaddingByteOrder,
+
+    /// The row before the first byte
+    s_padding_start: Selector,
+    /// Bytes which are in the message for every valid length
+    s_message_byte: Selector,
+    /// Bytes before the length field
+    s_padding_byte: Selector,
+    /// Bytes of the length field
+    s_length_byte: Selector,
+    /// First byte of the length field
+    s_length: Selector,
+    /// First byte of each word
+    s_word: Selector,
+    /// Second byte of each big-endian word of a fixed length message
+    s_be_word: Selector,
+    /// Byte after a fixed length message
+    s_message_end: Selector,
+    /// Byte of a word which is decomposed outside a padded message
+    s_word_byte: Selector,
+    _marker: PhantomData<F>,
+}
+
+/// Constraints on every byte: the flag is boolean and switches off at most once,
+/// the length is the running sum of the flags and the byte fits in 8 bits
+fn byte_constraints<F: FieldExt>(
+    meta: &mut VirtualCells<'_, F>,
+    a_0: Column<Advice>,
+    a_3: Column<Advice>,
+    a_4: Column<Advice>,
+) -> Vec<(&'static str, Expression<F>)> {
+    let one = Expression::Constant(F::one());
+    let tag = meta.query_advice(a_0, Rotation::cur());
+    let in_message = meta.query_advice(a_3, Rotation::cur());
+    let in_message_prev = meta.query_advice(a_3, Rotation::prev());
+    let length_so_far = meta.query_advice(a_4, Rotation::cur());
+    let length_so_far_prev = meta.query_advice(a_4, Rotation::prev());
+    let is_first_padding_byte = in_message_prev - in_message.clone();
+
+    vec![
+        ("in message is boolean", in_message.clone() * (one.clone() - in_message.clone())),
+        ("padding starts once", is_first_padding_byte.clone() * (one - is_first_padding_byte)),
+        ("length so far", length_so_far - length_so_far_prev - in_message),
+        ("byte range check", tag),
+    ]
+}
+
+impl<F: FieldExt> PaddingConfig<F> {
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<F>,
+        lookup: SpreadInputs,
+        advice: [Column<Advice>; NUM_ADVICE_COLS],
+        byte_order: PaddingByteOrder,
+    ) -> Self {
+        let s_padding_start = meta.selector();
+        let s_message_byte = meta.selector();
+        let s_padding_byte = meta.selector();
+        let s_length_byte = meta.selector();
+        let s_length = meta.selector();
+        let s_word = meta.selector();
+        let s_be_word = meta.selector();
+        let s_message_end = meta.selector();
+        let s_word_byte = meta.selector();
+
+        let a_0 = lookup.tag;
+        let a_1 = lookup.dense;
+        let a_3 = advice[0];
+        let a_4 = advice[1];
+        let a_5 = advice[2];
+
+        let one = || Expression::Constant(F::one());
+
+        meta.create_gate("padding start", |meta| {
+            let s_padding_start = meta.query_selector(s_padding_start);
+            let in_message = meta.query_advice(a_3, Rotation::cur());
+            let length_so_far = meta.query_advice(a_4, Rotation::cur());
+
+            vec![
+                ("in message", s_padding_start.clone() * (one() - in_message)),
+                ("length so far", s_padding_start * length_so_far),
+            ]
+        });
+
+        meta.create_gate("padding byte", |meta| {
+            let s_padding_byte = meta.query_selector(s_padding_byte);
+            let byte = meta.query_advice(a_1, Rotation::cur());
+            let in_message = meta.query_advice(a_3, Rotation::cur());
+            let in_message_prev = meta.query_advice(a_3, Rotation::prev());
+            let is_first_padding_byte = in_message_prev - in_message.clone();
+
+            let mut constraints = byte_constraints(meta, a_0, a_3, a_4);
+            // Bytes after the message are 0x80 followed by zeros
+            constraints.push((
+                "padding",
+                (one() - in_message) * (byte - is_first_padding_byte * F::from(PAD_BYTE)),
+            ));
+            constraints
+                .into_iter()
+                .map(|(name, constraint)| (name, s_padding_byte.clone() * constraint))
+                .collect::<Vec<_>>()
+        });
+
+        meta.create_gate("message byte", |meta| {
+            let s_message_byte = meta.query_selector(s_message_byte);
+            let in_message = meta.query_advice(a_3, Rotation::cur());
+
+            vec![("message byte", s_message_byte * (one() - in_message))]
+        });
+
+        meta.create_gate("length byte", |meta| {
+            let s_length_byte = meta.query_selector(s_length_byte);
+            let in_message = meta.query_advice(a_3, Rotation::cur());
+            let in_message_prev = meta.query_advice(a_3, Rotation::prev());
+
+            let mut constraints = byte_constraints(meta, a_0, a_3, a_4);
+            // The message and the 0x80 byte end before the length field
+            constraints.push(("length field", in_message));
+            constraints.push(("padding before length field", in_message_prev));
+            constraints
+                .into_iter()
+                .map(|(name, constraint)| (name, s_length_byte.clone() * constraint))
+                .collect::<Vec<_>>()
+        });
+
+        meta.create_gate("length", |meta| {
+            let s_length = meta.query_selector(s_length);
+            let length = meta.query_advice(a_4, Rotation((LENGTH_FIELD_BYTES - 1) as i32));
+            let length_field = (0..LENGTH_FIELD_BYTES).fold(
+                Expression::Constant(F::zero()),
+                |acc, i| acc + meta.query_advice(a_1, Rotation(i as i32)) * byte_order.byte_weight::<F>(i, LENGTH_FIELD_BYTES),
+            );
+
+            // The length field holds the number of bits
+            vec![("length", s_length * (length_field - length * F::from(8u64)))]
+        });
+
+        meta.create_gate("word", |meta| {
+            let s_word = meta.query_selector(s_word);
+            let word = meta.query_advice(a_5, Rotation::cur());
+            let word_from_bytes = (0..4).fold(
+                Expression::Constant(F::zero()),
+                |acc, i| acc + meta.query_advice(a_1, Rotation(i as i32)) * byte_order.byte_weight::<F>(i, 4),
+            );
+
+            vec![("word", s_word * (word_from_bytes - word))]
+        });
+
+        meta.create_gate("big-endian word", |meta| {
+            let s_be_word = meta.query_selector(s_be_word);
+            let word = meta.query_advice(a_5, Rotation::cur());
+            let word_from_bytes = (0..4).fold(
+                Expression::Constant(F::zero()),
+                |acc, i| acc + meta.query_advice(a_1, Rotation(i - 1)) * F::from(1u64 << (8 * (3 - i))),
+            );
+
+            vec![("big-endian word", s_be_word * (word_from_bytes - word))]
+        });
+
+        meta.create_gate("message end", |meta| {
+            let s_message_end = meta.query_selector(s_message_end);
+            let in_message = meta.query_advice(a_3, Rotation::cur());
+
+            // The flags can only switch off once, so the message is not longer
+            vec![("message end", s_message_end * in_message)]
+        });
+
+        meta.create_gate("word byte", |meta| {
+            let s_word_byte = meta.query_selector(s_word_byte);
+            let tag = meta.query_advice(a_0, Rotation::cur());
+
+            vec![("byte range check", s_word_byte * tag)]
+        });
+
+        PaddingConfig {
+            lookup,
+            advice,
+            byte_order,
+            s_padding_start,
+            s_message_byte,
+            s_padding_byte,
+            s_length_byte,
+            s_length,
+            s_word,
+            s_be_word,
+            s_message_end,
+            s_word_byte,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Assigns the padded message and returns its blocks of little-endian words.
+    /// The number of blocks is that of a message of `message.len()` bytes. The
+    /// witnessed length `len` must give the same number of blocks, and the bytes
+    /// of `message` from `len` onwards are replaced by the padding.
+    pub(crate) fn pad(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        message: &[Value<u8>],
+        len: Value<u64>,
+    ) -> Result<PaddedMessage<F>, Error> {
+        self.assign_padding(layouter, message, len, None)
+    }
+
+    /// Assigns the padded message made of the big-endian bytes of `words` and returns
+    /// its blocks of words. The bytes are constrained to be those of the assigned words
+    /// and the message length is fixed.
+    pub(super) fn pad_be_words(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        words: &[AssignedBits<F, 32>],
+    ) -> Result<Vec<[AssignedBits<F, 32>; BLOCK_SIZE]>, Error> {
+        let message: Vec<Value<u8>> = words
+            .iter()
+            .flat_map(|word| {
+                let bytes = word.value_u32().map(u32::to_be_bytes);
+                (0..4).map(move |i| bytes.map(|bytes| bytes[i]))
+            })
+            .collect();
+        let len = Value::known(message.len() as u64);
+        self.assign_padding(layouter, &message, len, Some(words))
+            .map(|padded| padded.blocks)
+    }
+
+    /// Decomposes assigned words into their range checked bytes, in the byte order of the
+    /// padding, with the gates of the words of a padded message
+    pub(crate) fn word_bytes(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        words: &[AssignedBits<F, 32>],
+    ) -> Result<Vec<AssignedBits<F, 8>>, Error> {
+        let a_5 = self.advice[2];
+
+        layouter.assign_region(
+            || "word bytes",
+            |mut region| {
+                let mut bytes = Vec::with_capacity(4 * words.len());
+                for (i, word) in words.iter().enumerate() {
+                    self.s_word.enable(&mut region, 4 * i)?;
+                    word.copy_advice(|| "word", &mut region, a_5, 4 * i)?;
+                    let word_bytes = word.value_u32().map(|word| self.byte_order.word_to_bytes(word));
+                    for j in 0..4 {
+                        let row = 4 * i + j;
+                        self.s_word_byte.enable(&mut region, row)?;
+                        let spread_byte = word_bytes
+                            .map(|bytes| SpreadWord::<8, 16>::new(i2lebsp(bytes[j].into())));
+                        let byte = SpreadVar::with_lookup(&mut region, &self.lookup, row, spread_byte)?;
+                        bytes.push(byte.dense);
+                    }
+                }
+                Ok(bytes)
+            },
+        )
+    }
+
+    fn assign_padding(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        message: &[Value<u8>],
+        len: Value<u64>,
+        be_words: Option<&[AssignedBits<F, 32>]>,
+    ) -> Result<PaddedMessage<F>, Error> {
+        let num_bytes = num_padded_blocks(message.len()) * BLOCK_SIZE_BYTES;
+        let length_start = num_bytes - LENGTH_FIELD_BYTES;
+        // Shorter messages would be padded to fewer blocks
+        let min_len = match be_words {
+            Some(words) => 4 * words.len(),
+            None => length_start.saturating_sub(BLOCK_SIZE_BYTES),
+        };
+
+        let byte_order = self.byte_order;
+        let message: Value<Vec<u8>> = message.iter().copied().collect();
+        let padded = message.zip(len).map(|(message, len)| {
+            let len = len as usize;
+            (0..num_bytes)
+                .map(|i| {
+                    if i < len {
+                        message.get(i).copied().unwrap_or(0)
+                    } else if i == len {
+                        PAD_BYTE as u8
+                    } else if i >= length_start {
+                        let index = match byte_order {
+                            PaddingByteOrder::LittleEndian => i - length_start,
+                            PaddingByteOrder::BigEndian => num_bytes - 1 - i,
+                        };
+                        ((8 * len as u64) >> (8 * index)) as u8
+                    } else {
+                        0
+                    }
+                })
+                .collect::<Vec<u8>>()
+        });
+
+        let a_3 = self.advice[0];
+        let a_4 = self.advice[1];
+        let a_5 = self.advice[2];
+
+        layouter.assign_region(
+            || "pad message",
+            |mut region| {
+                self.s_padding_start.enable(&mut region, 0)?;
+                region.assign_advice(|| "in message", a_3, 0, || Value::known(F::one()))?;
+                region.assign_advice(|| "length so far", a_4, 0, || Value::known(F::zero()))?;
+
+                let mut blocks = Vec::with_capacity(num_bytes / BLOCK_SIZE_BYTES);
+                let mut words = Vec::with_capacity(BLOCK_SIZE);
+                let mut bytes = Vec::with_capacity(num_bytes);
+                let mut in_message = Vec::with_capacity(num_bytes);
+                let mut len_cell = None;
+                for i in 0..num_bytes {
+                    let row = i + 1;
+                    if i < length_start {
+                        self.s_padding_byte.enable(&mut region, row)?;
+                        if i < min_len {
+                            self.s_message_byte.enable(&mut region, row)?;
+                        }
+                    } else {
+                        self.s_length_byte.enable(&mut region, row)?;
+                    }
+                    if i == length_start {
+                        self.s_length.enable(&mut region, row)?;
+                    }
+                    if be_words.is_some() && i == min_len {
+                        self.s_message_end.enable(&mut region, row)?;
+                    }
+
+                    let byte = padded.as_ref().map(|padded| padded[i]);
+                    let spread_byte = byte.map(|byte| SpreadWord::<8, 16>::new(i2lebsp(byte.into())));
+                    let byte = SpreadVar::with_lookup(&mut region, &self.lookup, row, spread_byte)?;
+                    bytes.push(byte.dense);
+                    in_message.push(region.assign_advice(
+                        || "in message",
+                        a_3,
+                        row,
+                        || len.map(|len| F::from(((i as u64) < len) as u64)),
+                    )?);
+                    // The length so far after the last byte is the message length
+                    len_cell = Some(region.assign_advice(
+                        || "length so far",
+                        a_4,
+                        row,
+                        || len.map(|len| F::from(len.min(i as u64 + 1))),
+                    )?);
+
+                    if i % 4 == 0 {
+                        self.s_word.enable(&mut region, row)?;
+                        let word = padded
+                            .as_ref()
+                            .map(|padded| byte_order.word_from_bytes(&padded[i..i + 4]));
+                        words.push(AssignedBits::<F, 32>::assign(&mut region, || "word", a_5, row, word)?);
+                        if words.len() == BLOCK_SIZE {
+                            blocks.push(std::mem::take(&mut words).try_into().unwrap());
+                        }
+                    }
+                    if let Some(be_words) = be_words {
+                        if i % 4 == 1 && i < min_len {
+                            self.s_be_word.enable(&mut region, row)?;
+                            be_words[i / 4].copy_advice(|| "big-endian word", &mut region, a_5, row)?;
+                        }
+                    }
+                }
+
+                Ok(PaddedMessage {
+                    blocks,
+                    bytes,
+                    in_message,
+                    len: len_cell.expect("a padded message has at least one block"),
+                })
+            },
+        )
+    }
+}
@@ -8,9 +8,10 @@ use halo2_proofs::{
     plonk::{Advice, Column, ConstraintSystem, Error, TableColumn},
     poly::Rotation,
 };
-use halo2_proofs::halo2curves::pasta::pallas;
+use std::cell::Cell;
 use std::convert::TryInto;
 use std::marker::PhantomData;
+use std::rc::Rc;
 
 const BITS_8: usize = 1 << 8;
 const BITS_9: usize = 1 << 9;
@@ -23,7 +24,7 @@ const BITS_15: usize = 1 << 15;
 
 /// An input word into a lookup, containing (tag, dense, spread)
 #[derive(Copy, Clone, Debug)]
-pub(super) struct SpreadWord<const DENSE: usize, const SPREAD: usize> {
+pub(crate) struct SpreadWord<const DENSE: usize, const SPREAD: usize> {
     pub tag: u8,
     pub dense: [bool; DENSE],
     pub spread: [bool; SPREAD],
@@ -54,7 +55,7 @@ pub fn get_tag(input: u16) -> u8 {
 }
 
 impl<const DENSE: usize, const SPREAD: usize> SpreadWord<DENSE, SPREAD> {
-    pub(super) fn new(dense: [bool; DENSE]) -> Self {
+    pub(crate) fn new(dense: [bool; DENSE]) -> Self {
         assert!(DENSE <= 16);
         SpreadWord {
             tag: get_tag(lebs2ip(&dense) as u16),
@@ -63,7 +64,7 @@ impl<const DENSE: usize, const SPREAD: usize> SpreadWord<DENSE, SPREAD> {
         }
     }
 
-    pub(super) fn try_new<T: TryInto<[bool; DENSE]> + std::fmt::Debug>(dense: T) -> Self
+    pub(crate) fn try_new<T: TryInto<[bool; DENSE]> + std::fmt::Debug>(dense: T) -> Self
     where
         <T as TryInto<[bool; DENSE]>>::Error: std::fmt::Debug,
     {
@@ -79,15 +80,15 @@ impl<const DENSE: usize, const SPREAD: usize> SpreadWord<DENSE, SPREAD> {
 
 /// A variable stored in advice columns corresponding to a row of [`SpreadTableConfig`].
 #[derive(Clone, Debug)]
-pub(super) struct SpreadVar<const DENSE: usize, const SPREAD: usize> {
+pub(crate) struct SpreadVar<F: FieldExt, const DENSE: usize, const SPREAD: usize> {
     pub tag: Value<u8>,
-    pub dense: AssignedBits<DENSE>,
-    pub spread: AssignedBits<SPREAD>,
+    pub dense: AssignedBits<F, DENSE>,
+    pub spread: AssignedBits<F, SPREAD>,
 }
 
-impl<const DENSE: usize, const SPREAD: usize> SpreadVar<DENSE, SPREAD> {
-    pub(super) fn with_lookup(
-        region: &mut Region<'_, pallas::Base>,
+impl<F: FieldExt, const DENSE: usize, const SPREAD: usize> SpreadVar<F, DENSE, SPREAD> {
+    pub(crate) fn with_lookup(
+        region: &mut Region<'_, F>,
         cols: &SpreadInputs,
         row: usize,
         word: Value<SpreadWord<DENSE, SPREAD>>,
@@ -100,20 +101,20 @@ impl<const DENSE: usize, const SPREAD: usize> SpreadVar<DENSE, SPREAD> {
             || "tag",
             cols.tag,
             row,
-            || tag.map(|tag| pallas::Base::from(tag as u64)),
+            || tag.map(|tag| F::from(tag as u64)),
         )?;
 
         let dense =
-            AssignedBits::<DENSE>::assign_bits(region, || "dense", cols.dense, row, dense_val)?;
+            AssignedBits::<F, DENSE>::assign_bits(region, || "dense", cols.dense, row, dense_val)?;
 
         let spread =
-            AssignedBits::<SPREAD>::assign_bits(region, || "spread", cols.spread, row, spread_val)?;
+            AssignedBits::<F, SPREAD>::assign_bits(region, || "spread", cols.spread, row, spread_val)?;
 
         Ok(SpreadVar { tag, dense, spread })
     }
 
-    pub(super) fn without_lookup(
-        region: &mut Region<'_, pallas::Base>,
+    pub(crate) fn without_lookup(
+        region: &mut Region<'_, F>,
         dense_col: Column<Advice>,
         dense_row: usize,
         spread_col: Column<Advice>,
@@ -124,7 +125,7 @@ impl<const DENSE: usize, const SPREAD: usize> SpreadVar<DENSE, SPREAD> {
         let dense_val = word.map(|word| word.dense);
         let spread_val = word.map(|word| word.spread);
 
-        let dense = AssignedBits::<DENSE>::assign_bits(
+        let dense = AssignedBits::<F, DENSE>::assign_bits(
             region,
             || "dense",
             dense_col,
@@ -132,7 +133,7 @@ impl<const DENSE: usize, const SPREAD: usize> SpreadVar<DENSE, SPREAD> {
             dense_val,
         )?;
 
-        let spread = AssignedBits::<SPREAD>::assign_bits(
+        let spread = AssignedBits::<F, SPREAD>::assign_bits(
             region,
             || "spread",
             spread_col,
@@ -145,27 +146,30 @@ impl<const DENSE: usize, const SPREAD: usize> SpreadVar<DENSE, SPREAD> {
 }
 
 #[derive(Clone, Debug)]
-pub(super) struct SpreadInputs {
-    pub(super) tag: Column<Advice>,
-    pub(super) dense: Column<Advice>,
-    pub(super) spread: Column<Advice>,
+pub(crate) struct SpreadInputs {
+    pub(crate) tag: Column<Advice>,
+    pub(crate) dense: Column<Advice>,
+    pub(crate) spread: Column<Advice>,
 }
 
 #[derive(Clone, Debug)]
-pub(super) struct SpreadTable {
-    pub(super) tag: TableColumn,
-    pub(super) dense: TableColumn,
-    pub(super) spread: TableColumn,
+pub(crate) struct SpreadTable {
+    pub(crate) tag: TableColumn,
+    pub(crate) dense: TableColumn,
+    pub(crate) spread: TableColumn,
 }
 
+/// Configuration of the 2^16 row spread table and the advice columns looked up in it
 #[derive(Clone, Debug)]
-pub(super) struct SpreadTableConfig {
-    pub input: SpreadInputs,
-    pub table: SpreadTable,
+pub struct SpreadTableConfig {
+    pub(crate) input: SpreadInputs,
+    pub(crate) table: SpreadTable,
+    // Set once the table has been loaded. Shared between clones of the config
+    loaded: Rc<Cell<bool>>,
 }
 
 #[derive(Clone, Debug)]
-pub(super) struct SpreadTableChip<F: FieldExt> {
+pub(crate) struct SpreadTableChip<F: FieldExt> {
     config: SpreadTableConfig,
     _marker: PhantomData<F>,
 }
@@ -190,34 +194,56 @@ impl<F: FieldExt> SpreadTableChip<F> {
         input_dense: Column<Advice>,
         input_spread: Column<Advice>,
     ) -> <Self as Chip<F>>::Config {
-        let table_tag = meta.lookup_table_column();
-        let table_dense = meta.lookup_table_column();
-        let table_spread = meta.lookup_table_column();
+        let table = SpreadTable {
+            tag: meta.lookup_table_column(),
+            dense: meta.lookup_table_column(),
+            spread: meta.lookup_table_column(),
+        };
+
+        let input = SpreadInputs {
+            tag: input_tag,
+            dense: input_dense,
+            spread: input_spread,
+        };
+        Self::configure_lookup(meta, &input, &table);
 
+        SpreadTableConfig {
+            input,
+            table,
+            loaded: Rc::new(Cell::new(false)),
+        }
+    }
+
+    fn configure_lookup(meta: &mut ConstraintSystem<F>, input: &SpreadInputs, table: &SpreadTable) {
         meta.lookup("Bitlength lookup", |meta| {
-            let tag_cur = meta.query_advice(input_tag, Rotation::cur());
-            let dense_cur = meta.query_advice(input_dense, Rotation::cur());
-            let spread_cur = meta.query_advice(input_spread, Rotation::cur());
+            let tag_cur = meta.query_advice(input.tag, Rotation::cur());
+            let dense_cur = meta.query_advice(input.dense, Rotation::cur());
+            let spread_cur = meta.query_advice(input.spread, Rotation::cur());
 
             vec![
-                (tag_cur, table_tag),
-                (dense_cur, table_dense),
-                (spread_cur, table_spread),
+                (tag_cur, table.tag),
+                (dense_cur, table.dense),
+                (spread_cur, table.spread),
             ]
         });
+    }
 
-        SpreadTableConfig {
-            input: SpreadInputs {
-                tag: input_tag,
-                dense: input_dense,
-                spread: input_spread,
-            },
-            table: SpreadTable {
-                tag: table_tag,
-                dense: table_dense,
-                spread: table_spread,
-            },
-        }
+    /// Looks up another set of advice columns in the spread table of `config`, so that
+    /// spread words can be assigned on the same rows as those of the table inputs.
+    pub fn configure_inputs(
+        meta: &mut ConstraintSystem<F>,
+        config: &SpreadTableConfig,
+        input_tag: Column<Advice>,
+        input_dense: Column<Advice>,
+        input_spread: Column<Advice>,
+    ) -> SpreadInputs {
+        let input = SpreadInputs {
+            tag: input_tag,
+            dense: input_dense,
+            spread: input_spread,
+        };
+        Self::configure_lookup(meta, &input, &config.table);
+        input
     }
 
     pub fn load(
@@ -257,11 +283,22 @@ impl<F: FieldExt> SpreadTableChip<F> {
 
                 Ok(())
             },
-        )
+        )?;
+        config.loaded.set(true);
+        Ok(())
     }
 }
 
 impl SpreadTableConfig {
+    /// Panics if the spread table has not been loaded. Without the table, every
+    /// spread lookup fails at verification time with an opaque lookup error.
+    pub(crate) fn assert_loaded(&self) {
+        assert!(
+            self.loaded.get(),
+            "Spread table not loaded: call Table16Chip::load before assigning RIPEMD160 or SHA-256 regions"
+        );
+    }
+
     fn generate<F: FieldExt>() -> impl Iterator<Item = (F, F, F)> {
         (1..=(1 << 16)).scan(
             (F::zero(), F::zero(), F::zero()),
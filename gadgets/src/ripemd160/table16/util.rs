@@ -105,7 +105,8 @@ pub fn odd_bits<const LEN: usize, const HALF: usize>(bits: [bool; LEN]) -> [bool
 }
 
 /// Given a vector of words as vec![(lo: u16, hi: u16)], returns their sum: u32, along
-/// with a carry bit.
+/// with the carry. The sum is computed in a u64, so the carry is exact for any number
+/// of addends below 2^32 and is not limited to a single bit.
 pub fn sum_with_carry(words: Vec<(Value<u16>, Value<u16>)>) -> (Value<u32>, Value<u64>) {
     let words_lo: Value<Vec<u64>> = words.iter().map(|(lo, _)| lo.map(|lo| lo as u64)).collect();
     let words_hi: Value<Vec<u64>> = words.iter().map(|(_, hi)| hi.map(|hi| hi as u64)).collect();
@@ -147,3 +148,61 @@ pub fn convert_byte_slice_to_blockword_slice<const LEN_BYTES: usize, const LEN_W
         .try_into()
         .expect("Error during byte slice to blockword slice conversion")
 }
+
+
+#[cfg(test)]
+mod tests {
+    use crate::ripemd160::ref_impl::constants::{BLOCK_SIZE, BLOCK_SIZE_BYTES, DIGEST_SIZE, DIGEST_SIZE_BYTES};
+    use crate::ripemd160::ref_impl::ripemd160::hash;
+    use halo2_proofs::circuit::Value;
+    use super::{convert_byte_slice_to_u32_slice, convert_byte_slice_to_blockword_slice, sum_with_carry};
+
+    #[test]
+    fn test_byte_slice_to_u32_slice_round_trip() {
+        let mut bytes = [0u8; BLOCK_SIZE_BYTES];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let words: [u32; BLOCK_SIZE] = convert_byte_slice_to_u32_slice(bytes);
+
+        // RIPEMD160 words are little-endian
+        assert_eq!(words[0], 0x03020100);
+        assert_eq!(words[BLOCK_SIZE-1], 0x3f3e3d3c);
+
+        let round_trip_bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        assert_eq!(round_trip_bytes, bytes.to_vec());
+
+        // RIPEMD160("abc") = 8eb208f7e05d987a9b044a8e98c6b087f15a0bfc
+        let digest_bytes: [u8; DIGEST_SIZE_BYTES] = hash(b"abc".to_vec());
+        let digest_words: [u32; DIGEST_SIZE] = convert_byte_slice_to_u32_slice(digest_bytes);
+        assert_eq!(digest_words, [0xf708b28e, 0x7a985de0, 0x8e4a049b, 0x87b0c698, 0xfc0b5af1]);
+    }
+
+    #[test]
+    fn test_byte_slice_to_blockword_slice_round_trip() {
+        let mut bytes = [0u8; BLOCK_SIZE_BYTES];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = (0xff - i) as u8;
+        }
+        let words: [u32; BLOCK_SIZE] = convert_byte_slice_to_u32_slice(bytes);
+        let block_words = convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>(bytes);
+
+        for (i, (block_word, word)) in block_words.iter().zip(words.iter()).enumerate() {
+            block_word.0.assert_if_known(|v| v == word);
+            block_word.0.assert_if_known(|v| v.to_le_bytes()[..] == bytes[4*i..4*i+4]);
+        }
+        block_words[0].0.assert_if_known(|v| *v == 0xfcfdfeff);
+    }
+
+    #[test]
+    fn test_sum_with_carry_many_addends() {
+        for num_addends in [5u64, 8] {
+            let words = vec![(Value::known(u16::MAX), Value::known(u16::MAX)); num_addends as usize];
+            let (sum, carry) = sum_with_carry(words);
+
+            // num_addends * (2^32 - 1) = (num_addends - 1) * 2^32 + (2^32 - num_addends)
+            sum.assert_if_known(|sum| *sum == (num_addends as u32).wrapping_neg());
+            carry.assert_if_known(|carry| *carry == num_addends - 1);
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! The [SHA-1] hash function.
+//!
+//! SHA-1 is not collision resistant, but `OP_SHA1` is a consensus opcode of Bitcoin
+//! script, so the circuit reproduces it exactly.
+//!
+//! [SHA-1]: https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.180-4.pdf
+//!
+pub mod ref_impl;
+pub mod table16;
+use std::fmt;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter, Value},
+    plonk::Error,
+};
+
+use self::ref_impl::{BLOCK_SIZE, DIGEST_SIZE, DIGEST_SIZE_BYTES};
+
+/// The set of circuit instructions required to use the [`Sha1`] gadget.
+pub trait Sha1Instructions<F: FieldExt>: Chip<F> {
+    /// Variable representing the SHA-1 internal state.
+    type State: Clone + fmt::Debug;
+    /// Variable representing a 32-bit word of the input block to the SHA-1 compression
+    /// function.
+    type BlockWord: Copy + fmt::Debug + Default;
+
+    /// Places the SHA-1 IV in the circuit, returning the initial state variable.
+    fn initialization_vector(&self, layouter: &mut impl Layouter<F>) -> Result<Self::State, Error>;
+
+    /// Starting from the given initialized state, processes a block of input and returns the
+    /// final state.
+    fn compress(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &Self::State,
+        input: [Self::BlockWord; BLOCK_SIZE],
+    ) -> Result<Self::State, Error>;
+
+    /// Converts the given state into a message digest. The digest bytes are the
+    /// big-endian bytes of the words.
+    fn digest(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &Self::State,
+    ) -> Result<[Self::BlockWord; DIGEST_SIZE], Error>;
+}
+
+/// The output of a SHA-1 circuit invocation.
+#[derive(Debug)]
+pub struct Sha1Digest<BlockWord>([BlockWord; DIGEST_SIZE]);
+
+impl<BlockWord> Sha1Digest<BlockWord> {
+    /// Returns the words of the digest.
+    pub fn words(&self) -> &[BlockWord; DIGEST_SIZE] {
+        &self.0
+    }
+}
+
+impl Sha1Digest<table16::BlockWord> {
+    /// Returns the big-endian bytes of the digest words, i.e. the digest bytes.
+    pub fn to_be_bytes(&self) -> Value<[u8; DIGEST_SIZE_BYTES]> {
+        let words: Value<Vec<u32>> = self.0.iter().map(|word| word.0).collect();
+        words.map(|words| {
+            let mut bytes = [0u8; DIGEST_SIZE_BYTES];
+            for (chunk, word) in bytes.chunks_exact_mut(4).zip(words) {
+                chunk.copy_from_slice(&word.to_be_bytes());
+            }
+            bytes
+        })
+    }
+}
+
+/// A gadget that constrains a SHA-1 invocation. It supports input at a granularity of
+/// 32 bits.
+#[derive(Debug)]
+pub struct Sha1<F: FieldExt, CS: Sha1Instructions<F>> {
+    chip: CS,
+    state: CS::State,
+}
+
+impl<F: FieldExt, Sha1Chip: Sha1Instructions<F>> Sha1<F, Sha1Chip> {
+    /// Create a new hasher instance.
+    pub fn new(chip: Sha1Chip, mut layouter: impl Layouter<F>) -> Result<Self, Error> {
+        let state = chip.initialization_vector(&mut layouter)?;
+        Ok(Sha1 {
+            chip,
+            state,
+        })
+    }
+
+    /// Updating the internal state by consuming all message blocks
+    /// The input is assumed to be already padded to a multiple of 16 Blockwords
+    pub fn update(
+        &mut self,
+        mut layouter: impl Layouter<F>,
+        data: &Vec<[Sha1Chip::BlockWord; BLOCK_SIZE]>,
+    ) -> Result<(), Error> {
+        for b in data {
+            self.state = self.chip.compress(
+                &mut layouter,
+                &self.state,
+                *b,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Retrieve result and consume hasher instance.
+    pub fn finalize(
+        self,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<Sha1Digest<Sha1Chip::BlockWord>, Error> {
+        self.chip
+            .digest(&mut layouter, &self.state)
+            .map(Sha1Digest)
+    }
+
+    /// Convenience function to compute hash of the data.
+    pub fn digest(
+        chip: Sha1Chip,
+        mut layouter: impl Layouter<F>,
+        data: &Vec<[Sha1Chip::BlockWord; BLOCK_SIZE]>,
+    ) -> Result<Sha1Digest<Sha1Chip::BlockWord>, Error> {
+        let mut hasher = Self::new(chip, layouter.namespace(|| "init"))?;
+        hasher.update(layouter.namespace(|| "update"), data)?;
+        hasher.finalize(layouter.namespace(|| "finalize"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin_hashes::{sha1, Hash};
+    use halo2_proofs::{plonk::{Circuit, ConstraintSystem, self}, halo2curves::pasta::pallas, circuit::{SimpleFloorPlanner, Layouter}, dev::MockProver};
+    use halo2_proofs::arithmetic::FieldExt;
+    use halo2_proofs::halo2curves::bn256::Fr as BnScalar;
+
+    use crate::sha1::{Sha1, table16::{Table16Config, Table16Chip, BlockWord}};
+    use crate::sha1::ref_impl::{pad_message_bytes, convert_byte_slice_to_u32_slice, BLOCK_SIZE, BLOCK_SIZE_BYTES, DIGEST_SIZE_BYTES};
+    use crate::sha256::{self, Sha256};
+
+    fn message_blocks(input: Vec<u8>) -> Vec<[BlockWord; BLOCK_SIZE]> {
+        pad_message_bytes(input)
+            .into_iter()
+            .map(|block| convert_byte_slice_to_u32_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>(block).map(BlockWord::from))
+            .collect()
+    }
+
+    struct KnownVectorCircuit {
+        input: Vec<u8>,
+        expected: [u8; DIGEST_SIZE_BYTES],
+    }
+
+    impl<F: FieldExt> Circuit<F> for KnownVectorCircuit {
+        type Config = Table16Config<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            KnownVectorCircuit { input: self.input.clone(), expected: self.expected }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            Table16Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self, config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), plonk::Error> {
+            let table16_chip = Table16Chip::construct(config.clone());
+            Table16Chip::load(config, &mut layouter)?;
+
+            let digest = Sha1::digest(table16_chip, layouter, &message_blocks(self.input.clone()))?;
+            digest.to_be_bytes().assert_if_known(|bytes| *bytes == self.expected);
+
+            Ok(())
+        }
+    }
+
+    fn known_vectors() -> Vec<(Vec<u8>, &'static str)> {
+        vec![
+            (b"".to_vec(), "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+            (b"abc".to_vec(), "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            // Padded to two blocks
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq".to_vec(),
+                "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+            ),
+        ]
+    }
+
+    #[test]
+    fn hash_known_vectors() {
+        for (input, expected_hex) in known_vectors() {
+            let expected = hex::decode(expected_hex).unwrap().try_into().unwrap();
+            let circuit = KnownVectorCircuit { input, expected };
+
+            let prover = MockProver::<pallas::Base>::run(17, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn hash_known_vectors_bn256() {
+        // The chip works over the scalar field of bn256 used by the BitcoinVM circuit
+        for (input, expected_hex) in known_vectors() {
+            let expected = hex::decode(expected_hex).unwrap().try_into().unwrap();
+            let circuit = KnownVectorCircuit { input, expected };
+
+            let prover = MockProver::<BnScalar>::run(17, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn hash_with_sha256_spread_table() {
+        struct MyCircuit {}
+
+        impl Circuit<pallas::Base> for MyCircuit {
+            type Config = (sha256::table16::Table16Config<pallas::Base>, Table16Config<pallas::Base>);
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit {}
+            }
+
+            fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+                let sha256_config = sha256::table16::Table16Chip::configure(meta);
+                let sha1_config = Table16Chip::configure_with_spread_table(meta, sha256_config.spread_table());
+                (sha256_config, sha1_config)
+            }
+
+            fn synthesize(
+                &self, config: Self::Config,
+                mut layouter: impl Layouter<pallas::Base>,
+            ) -> Result<(), plonk::Error> {
+                // The spread table is loaded once for both chips
+                sha256::table16::Table16Chip::load(config.0.clone(), &mut layouter)?;
+
+                let input = vec![0xab; 100];
+                let sha1_digest = Sha1::digest(
+                    Table16Chip::construct(config.1),
+                    layouter.namespace(|| "sha1"),
+                    &message_blocks(input.clone()),
+                )?;
+                sha1_digest
+                    .to_be_bytes()
+                    .assert_if_known(|bytes| *bytes == sha1::Hash::hash(&input).into_inner());
+
+                Sha256::digest(
+                    sha256::table16::Table16Chip::construct(config.0),
+                    layouter.namespace(|| "sha256"),
+                    &message_blocks(input),
+                )?;
+
+                Ok(())
+            }
+        }
+
+        let circuit: MyCircuit = MyCircuit {};
+
+        let prover = MockProver::<pallas::Base>::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
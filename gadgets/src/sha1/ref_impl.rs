@@ -0,0 +1,115 @@
+//! A reference implementation of SHA-1, used to compute the witnesses of the
+//! [`Table16Chip`](super::table16::Table16Chip) and to check its outputs.
+use std::convert::TryInto;
+
+// SHA-1 pads messages like SHA-256 and reads the blocks as big-endian words
+pub use crate::sha256::ref_impl::{convert_byte_slice_to_u32_slice, pad_message_bytes, BLOCK_SIZE, BLOCK_SIZE_BYTES};
+
+pub const DIGEST_SIZE: usize = 5;
+pub const DIGEST_SIZE_BYTES: usize = DIGEST_SIZE*4;
+pub const ROUNDS: usize = 80;
+/// Number of consecutive rounds which share a round function and a round constant
+pub const ROUNDS_PER_STAGE: usize = 20;
+
+pub const INITIAL_VALUES: [u32; DIGEST_SIZE] = [
+    0x6745_2301,
+    0xefcd_ab89,
+    0x98ba_dcfe,
+    0x1032_5476,
+    0xc3d2_e1f0,
+];
+
+pub const ROUND_CONSTANTS: [u32; ROUNDS / ROUNDS_PER_STAGE] = [
+    0x5a82_7999,
+    0x6ed9_eba1,
+    0x8f1b_bcdc,
+    0xca62_c1d6,
+];
+
+pub fn ch(b: u32, c: u32, d: u32) -> u32 {
+    (b & c) ^ (!b & d)
+}
+
+pub fn parity(b: u32, c: u32, d: u32) -> u32 {
+    b ^ c ^ d
+}
+
+pub fn maj(b: u32, c: u32, d: u32) -> u32 {
+    (b & c) ^ (b & d) ^ (c & d)
+}
+
+/// Returns the round function of round `t`
+pub fn round_function(t: usize) -> fn(u32, u32, u32) -> u32 {
+    match t / ROUNDS_PER_STAGE {
+        0 => ch,
+        2 => maj,
+        _ => parity,
+    }
+}
+
+/// Returns the 80 words of the message schedule of a block
+pub fn message_schedule(block: [u32; BLOCK_SIZE]) -> [u32; ROUNDS] {
+    let mut w = [0u32; ROUNDS];
+    w[..BLOCK_SIZE].copy_from_slice(&block);
+    for t in BLOCK_SIZE..ROUNDS {
+        w[t] = (w[t-3] ^ w[t-8] ^ w[t-14] ^ w[t-16]).rotate_left(1);
+    }
+    w
+}
+
+#[allow(clippy::many_single_char_names)]
+pub fn compress(state: [u32; DIGEST_SIZE], block: [u32; BLOCK_SIZE]) -> [u32; DIGEST_SIZE] {
+    let w = message_schedule(block);
+    let [mut a, mut b, mut c, mut d, mut e] = state;
+    for (t, w) in w.iter().enumerate() {
+        let temp = a
+            .rotate_left(5)
+            .wrapping_add(round_function(t)(b, c, d))
+            .wrapping_add(e)
+            .wrapping_add(ROUND_CONSTANTS[t / ROUNDS_PER_STAGE])
+            .wrapping_add(*w);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
+    }
+
+    let mut next_state = state;
+    for (word, working_word) in next_state.iter_mut().zip([a, b, c, d, e]) {
+        *word = word.wrapping_add(working_word);
+    }
+    next_state
+}
+
+pub fn hash(msg_bytes: Vec<u8>) -> [u8; DIGEST_SIZE_BYTES] {
+    let mut state = INITIAL_VALUES;
+    for block in pad_message_bytes(msg_bytes) {
+        state = compress(state, convert_byte_slice_to_u32_slice(block));
+    }
+    state
+        .iter()
+        .flat_map(|word| word.to_be_bytes())
+        .collect::<Vec<u8>>()
+        .try_into()
+        .expect("Failed conversion")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hash;
+
+    #[test]
+    fn test_known_vectors() {
+        for (msg, expected_hex) in [
+            (b"".to_vec(), "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+            (b"abc".to_vec(), "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq".to_vec(),
+                "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+            ),
+        ] {
+            assert_eq!(hex::encode(hash(msg)), expected_hex);
+        }
+    }
+}
@@ -0,0 +1,210 @@
+/*
+A SHA-1 chip built from the gates of the SHA-256 Table16 chip. The XORs of the message schedule
+and the round functions are the even and odd bits of sums of spread words, and the rotations are
+gates of a single rotation. It shares the spread table of the RIPEMD-160 Table16 chip.
+*/
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error},
+};
+
+mod compression;
+mod message_schedule;
+
+pub use crate::ripemd160::table16::BlockWord;
+pub use crate::ripemd160::table16::SpreadTableConfig;
+pub use crate::ripemd160::table16::PaddedMessage;
+use crate::ripemd160::table16::AssignedBits;
+use crate::ripemd160::table16::padding::{PaddingByteOrder, PaddingConfig};
+use crate::ripemd160::table16::spread_table::{SpreadInputs, SpreadTableChip};
+use crate::sha256::table16::{RoundWord, NUM_ADVICE_COLS};
+use compression::CompressionConfig;
+use message_schedule::MessageScheduleConfig;
+use super::ref_impl::{DIGEST_SIZE, INITIAL_VALUES};
+use super::Sha1Instructions;
+
+/// The internal state for SHA-1
+#[derive(Clone, Debug)]
+pub struct State<F: FieldExt>([RoundWord<F>; DIGEST_SIZE]);
+
+/// Configuration for a [`Table16Chip`].
+#[derive(Clone, Debug)]
+pub struct Table16Config<F: FieldExt> {
+    lookup: SpreadTableConfig,
+    message_schedule: MessageScheduleConfig<F>,
+    compression: CompressionConfig<F>,
+    padding: PaddingConfig<F>,
+}
+
+impl<F: FieldExt> Table16Config<F> {
+    /// Returns the spread table configuration, to be shared with other chips
+    pub fn spread_table(&self) -> SpreadTableConfig {
+        self.lookup.clone()
+    }
+}
+
+/// A chip that implements SHA-1 with a maximum lookup table size of $2^16$.
+#[derive(Clone, Debug)]
+pub struct Table16Chip<F: FieldExt> {
+    config: Table16Config<F>,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> Chip<F> for Table16Chip<F> {
+    type Config = Table16Config<F>;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl<F: FieldExt> Table16Chip<F> {
+    /// Reconstructs this chip from the given config.
+    pub fn construct(config: <Self as Chip<F>>::Config) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Configures a circuit to include this chip.
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+    ) -> <Self as Chip<F>>::Config {
+        // - Three advice columns to interact with the lookup table.
+        let input_tag = meta.advice_column();
+        let input_dense = meta.advice_column();
+        let input_spread = meta.advice_column();
+
+        let lookup = SpreadTableChip::configure(meta, input_tag, input_dense, input_spread);
+
+        Self::configure_with_spread_table(meta, lookup)
+    }
+
+    /// Configures a circuit to include this chip, reusing a spread table configured by
+    /// another chip, e.g. the SHA-256 or the RIPEMD-160 chip. The table only needs to be
+    /// loaded once for all the chips sharing it.
+    pub fn configure_with_spread_table(
+        meta: &mut ConstraintSystem<F>,
+        lookup: SpreadTableConfig,
+    ) -> <Self as Chip<F>>::Config {
+        let advice: [Column<Advice>; NUM_ADVICE_COLS] = [
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+        ];
+
+        // Words and their spread halves are copied between the gates
+        for column in [lookup.input.dense, lookup.input.spread].iter().chain(advice.iter()) {
+            meta.enable_equality(*column);
+        }
+
+        let message_schedule = MessageScheduleConfig::configure(meta, lookup.input.clone(), advice);
+        let compression = CompressionConfig::configure(meta, lookup.input.clone(), advice);
+        let padding = PaddingConfig::configure(meta, lookup.input.clone(), advice, PaddingByteOrder::BigEndian);
+
+        Table16Config {
+            lookup,
+            message_schedule,
+            compression,
+            padding,
+        }
+    }
+
+    /// Pads the first `len` bytes of `message` in the circuit and returns the padded message
+    /// with the cells of its bytes and of its length. The number of blocks is fixed by
+    /// `message.len()`, so `len` has to be padded to the same number of blocks.
+    pub fn pad_message_bytes(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        message: &[Value<u8>],
+        len: Value<u64>,
+    ) -> Result<PaddedMessage<F>, Error> {
+        self.config().lookup.assert_loaded();
+        self.config().padding.pad(layouter, message, len)
+    }
+
+    /// Starting from the given initialized state, processes a block which is already
+    /// assigned in the circuit, e.g. by [`Table16Chip::pad_message_bytes`], and returns the
+    /// final state.
+    pub fn compress_assigned(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &State<F>,
+        input: &[AssignedBits<F, 32>; super::BLOCK_SIZE],
+    ) -> Result<State<F>, Error> {
+        let config = self.config();
+        config.lookup.assert_loaded();
+        let w = config.message_schedule.process_assigned(layouter, input)?;
+        config.compression.compress(layouter, initialized_state.clone(), w)
+    }
+
+    /// Converts the given state into the range checked bytes of its digest
+    pub fn digest_bytes(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &State<F>,
+    ) -> Result<Vec<AssignedBits<F, 8>>, Error> {
+        let config = self.config();
+        config.lookup.assert_loaded();
+        let digest = state.0.clone().map(|word| word.dense);
+        config.padding.word_bytes(layouter, &digest)
+    }
+
+    /// Loads the lookup table required by this chip into the circuit.
+    pub fn load(
+        config: Table16Config<F>,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<(), Error> {
+        SpreadTableChip::load(config.lookup, layouter)
+    }
+}
+
+impl<F: FieldExt> Sha1Instructions<F> for Table16Chip<F> {
+    type State = State<F>;
+    type BlockWord = BlockWord;
+
+    fn initialization_vector(
+        &self,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<State<F>, Error> {
+        self.config().lookup.assert_loaded();
+        self.config().compression.initialize_with_iv(layouter, INITIAL_VALUES)
+    }
+
+    fn compress(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &Self::State,
+        input: [Self::BlockWord; super::BLOCK_SIZE],
+    ) -> Result<Self::State, Error> {
+        let config = self.config();
+        config.lookup.assert_loaded();
+        let w = config.message_schedule.process(layouter, input)?;
+        config.compression.compress(layouter, initialized_state.clone(), w)
+    }
+
+    fn digest(
+        &self,
+        _layouter: &mut impl Layouter<F>,
+        state: &Self::State,
+    ) -> Result<[Self::BlockWord; super::DIGEST_SIZE], Error> {
+        // The state words are already range checked 32-bit words
+        Ok(state
+            .0
+            .iter()
+            .map(|word| BlockWord(word.dense.value_u32()))
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap())
+    }
+}
@@ -0,0 +1,241 @@
+use std::convert::TryInto;
+
+use super::{AssignedBits, SpreadInputs, State, NUM_ADVICE_COLS};
+use crate::sha1::ref_impl::{DIGEST_SIZE, ROUNDS, ROUNDS_PER_STAGE, ROUND_CONSTANTS};
+use crate::sha256::table16::gates::{
+    SigmaConfig, SigmaOp, SpreadBits, SpreadSumConfig, WordSumConfig, SIGMA_ROWS, SPREAD_SUM_ROWS,
+};
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::Layouter,
+    plonk::{Advice, Column, ConstraintSystem, Error},
+};
+
+#[derive(Clone, Debug)]
+pub(super) struct CompressionConfig<F: FieldExt> {
+    /// Words of the initialization vector, fixed to the constants
+    initial_word: WordSumConfig<F>,
+    /// ROTL^5(a)
+    rotate_left_5: SigmaConfig<F>,
+    /// c' = ROTL^30(b)
+    rotate_left_30: SigmaConfig<F>,
+    /// b & c, the first half of Ch(b, c, d)
+    ch: SpreadSumConfig<F>,
+    /// !b & d, the second half of Ch(b, c, d). The halves have no common bits,
+    /// so their sum is Ch(b, c, d).
+    ch_neg: SpreadSumConfig<F>,
+    /// Parity(b, c, d) = b ^ c ^ d
+    parity: SpreadSumConfig<F>,
+    /// Maj(b, c, d)
+    maj: SpreadSumConfig<F>,
+    /// a' = ROTL^5(a) + Ch(b, c, d) + e + K_t + W_t, for the first 20 rounds
+    new_a_ch: WordSumConfig<F>,
+    /// a' = ROTL^5(a) + f_t(b, c, d) + e + K_t + W_t, for the rounds with Parity or Maj
+    new_a: WordSumConfig<F>,
+    /// Sum of the state before and after the rounds
+    digest_word: WordSumConfig<F>,
+}
+
+impl<F: FieldExt> CompressionConfig<F> {
+    pub(super) fn configure(
+        meta: &mut ConstraintSystem<F>,
+        lookup: SpreadInputs,
+        advice: [Column<Advice>; NUM_ADVICE_COLS],
+    ) -> Self {
+        // The initialization vector and the round constants
+        let constants = meta.fixed_column();
+
+        let initial_word = WordSumConfig::configure(
+            meta,
+            "initial word",
+            lookup.clone(),
+            advice,
+            0,
+            Some(constants),
+        );
+        let rotate_left_5 = SigmaConfig::configure(
+            meta,
+            "rotate_left_5",
+            lookup.clone(),
+            advice,
+            [14, 13, 3, 2],
+            &[SigmaOp::RotateRight(27)],
+        );
+        let rotate_left_30 = SigmaConfig::configure(
+            meta,
+            "rotate_left_30",
+            lookup.clone(),
+            advice,
+            [2, 14, 14, 2],
+            &[SigmaOp::RotateRight(2)],
+        );
+        let ch = SpreadSumConfig::configure(meta, "ch", lookup.clone(), advice, 2, false, SpreadBits::Odd);
+        let ch_neg = SpreadSumConfig::configure(meta, "ch_neg", lookup.clone(), advice, 2, true, SpreadBits::Odd);
+        let parity = SpreadSumConfig::configure(meta, "parity", lookup.clone(), advice, 3, false, SpreadBits::Even);
+        let maj = SpreadSumConfig::configure(meta, "maj", lookup.clone(), advice, 3, false, SpreadBits::Odd);
+        let new_a_ch = WordSumConfig::configure(meta, "new a ch", lookup.clone(), advice, 5, Some(constants));
+        let new_a = WordSumConfig::configure(meta, "new a", lookup.clone(), advice, 4, Some(constants));
+        let digest_word = WordSumConfig::configure(meta, "digest word", lookup, advice, 2, None);
+
+        CompressionConfig {
+            initial_word,
+            rotate_left_5,
+            rotate_left_30,
+            ch,
+            ch_neg,
+            parity,
+            maj,
+            new_a_ch,
+            new_a,
+            digest_word,
+        }
+    }
+
+    pub(super) fn initialize_with_iv(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        iv: [u32; DIGEST_SIZE],
+    ) -> Result<State<F>, Error> {
+        layouter.assign_region(
+            || "initialize",
+            |mut region| {
+                let mut row = 0;
+                let mut state = Vec::with_capacity(DIGEST_SIZE);
+                for word in iv.iter() {
+                    state.push(self.initial_word.assign_sum(&mut region, row, &[], Some(*word))?);
+                    row += self.initial_word.rows();
+                }
+                Ok(State(state.try_into().unwrap()))
+            },
+        )
+    }
+
+    #[allow(clippy::many_single_char_names)]
+    pub(super) fn compress(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initial_state: State<F>,
+        w: [AssignedBits<F, 32>; ROUNDS],
+    ) -> Result<State<F>, Error> {
+        layouter.assign_region(
+            || "compress",
+            |mut region| {
+                let region = &mut region;
+                let mut row = 0;
+                let [mut a, mut b, mut c, mut d, mut e] = initial_state.0.clone();
+
+                for (t, w) in w.iter().enumerate() {
+                    let constant = Some(ROUND_CONSTANTS[t / ROUNDS_PER_STAGE]);
+                    let rotated_a = self.rotate_left_5.assign(region, row, &a.dense)?;
+                    row += SIGMA_ROWS;
+
+                    let new_a = if t < ROUNDS_PER_STAGE {
+                        let ch = self.ch.assign(region, row, &[&b, &c])?;
+                        row += SPREAD_SUM_ROWS;
+                        let ch_neg = self.ch_neg.assign(region, row, &[&b, &d])?;
+                        row += SPREAD_SUM_ROWS;
+                        let new_a = self.new_a_ch.assign_sum(
+                            region,
+                            row,
+                            &[&rotated_a.dense, &ch.dense, &ch_neg.dense, &e.dense, w],
+                            constant,
+                        )?;
+                        row += self.new_a_ch.rows();
+                        new_a
+                    } else {
+                        // Maj in the third stage and Parity in the second and the fourth
+                        let f = if t / ROUNDS_PER_STAGE == 2 { &self.maj } else { &self.parity };
+                        let f = f.assign(region, row, &[&b, &c, &d])?;
+                        row += SPREAD_SUM_ROWS;
+                        let new_a = self.new_a.assign_sum(
+                            region,
+                            row,
+                            &[&rotated_a.dense, &f.dense, &e.dense, w],
+                            constant,
+                        )?;
+                        row += self.new_a.rows();
+                        new_a
+                    };
+                    let new_c = self.rotate_left_30.assign(region, row, &b.dense)?;
+                    row += SIGMA_ROWS;
+
+                    e = d;
+                    d = c;
+                    c = new_c;
+                    b = a;
+                    a = new_a;
+                }
+
+                let mut state = Vec::with_capacity(DIGEST_SIZE);
+                for (initial, word) in initial_state.0.iter().zip([a, b, c, d, e].iter()) {
+                    state.push(self.digest_word.assign_sum(region, row, &[&initial.dense, &word.dense], None)?);
+                    row += self.digest_word.rows();
+                }
+                Ok(State(state.try_into().unwrap()))
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sha1::ref_impl::{compress, BLOCK_SIZE, INITIAL_VALUES};
+    use crate::sha1::table16::{BlockWord, Table16Chip, Table16Config};
+    use halo2_proofs::circuit::Value;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+    use halo2_proofs::halo2curves::pasta::pallas;
+
+    #[test]
+    fn test_compress_block() {
+        struct MyCircuit {}
+
+        impl Circuit<pallas::Base> for MyCircuit {
+            type Config = Table16Config<pallas::Base>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit {}
+            }
+
+            fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+                Table16Chip::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<pallas::Base>,
+            ) -> Result<(), Error> {
+                Table16Chip::load(config.clone(), &mut layouter)?;
+
+                let mut input = [0u32; BLOCK_SIZE];
+                for (i, word) in input.iter_mut().enumerate() {
+                    *word = !(i as u32).wrapping_mul(0x0101_0101);
+                }
+
+                let initial_state = config.compression.initialize_with_iv(&mut layouter, INITIAL_VALUES)?;
+                let w = config.message_schedule.process(&mut layouter, input.map(|x| BlockWord(Value::known(x))))?;
+                let state = config.compression.compress(&mut layouter, initial_state, w)?;
+
+                let expected = compress(INITIAL_VALUES, input);
+                for (word, expected) in state.0.iter().zip(expected.iter()) {
+                    word.dense.value_u32().assert_if_known(|v| v == expected);
+                }
+
+                Ok(())
+            }
+        }
+
+        let circuit: MyCircuit = MyCircuit {};
+
+        let prover = match MockProver::<pallas::Base>::run(17, &circuit, vec![]) {
+            Ok(prover) => prover,
+            Err(e) => panic!("{:?}", e),
+        };
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
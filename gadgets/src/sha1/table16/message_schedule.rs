@@ -0,0 +1,185 @@
+use std::convert::TryInto;
+
+use super::{AssignedBits, BlockWord, RoundWord, SpreadInputs, NUM_ADVICE_COLS};
+use crate::sha1::ref_impl::{BLOCK_SIZE, ROUNDS};
+use crate::sha256::table16::gates::{
+    SigmaConfig, SigmaOp, SpreadBits, SpreadSumConfig, WordSumConfig, SIGMA_ROWS, SPREAD_SUM_ROWS,
+};
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Layouter, Region},
+    plonk::{Advice, Column, ConstraintSystem, Error},
+};
+
+#[derive(Clone, Debug)]
+pub(super) struct MessageScheduleConfig<F: FieldExt> {
+    /// Range check of the words of the message block
+    message_word: WordSumConfig<F>,
+    /// W_{t-3} ^ W_{t-8} ^ W_{t-14}
+    xor_3: SpreadSumConfig<F>,
+    /// W_{t-3} ^ W_{t-8} ^ W_{t-14} ^ W_{t-16}
+    xor_4: SpreadSumConfig<F>,
+    /// W_t = ROTL^1(W_{t-3} ^ W_{t-8} ^ W_{t-14} ^ W_{t-16})
+    rotate_left_1: SigmaConfig<F>,
+}
+
+impl<F: FieldExt> MessageScheduleConfig<F> {
+    pub(super) fn configure(
+        meta: &mut ConstraintSystem<F>,
+        lookup: SpreadInputs,
+        advice: [Column<Advice>; NUM_ADVICE_COLS],
+    ) -> Self {
+        let message_word =
+            WordSumConfig::configure(meta, "message word", lookup.clone(), advice, 0, None);
+        // Four spread bits would not fit in the two bits of a spread sum, so the fourth
+        // word is XORed separately
+        let xor_3 = SpreadSumConfig::configure(meta, "xor_3", lookup.clone(), advice, 3, false, SpreadBits::Even);
+        let xor_4 = SpreadSumConfig::configure(meta, "xor_4", lookup.clone(), advice, 2, false, SpreadBits::Even);
+        let rotate_left_1 = SigmaConfig::configure(
+            meta,
+            "rotate_left_1",
+            lookup,
+            advice,
+            [15, 15, 1, 1],
+            &[SigmaOp::RotateRight(31)],
+        );
+
+        MessageScheduleConfig {
+            message_word,
+            xor_3,
+            xor_4,
+            rotate_left_1,
+        }
+    }
+
+    /// Returns the `ROUNDS` words of the message schedule of the block
+    pub(super) fn process(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        input: [BlockWord; BLOCK_SIZE],
+    ) -> Result<[AssignedBits<F, 32>; ROUNDS], Error> {
+        let w = layouter.assign_region(
+            || "process message block",
+            |mut region| {
+                let mut w = Vec::<RoundWord<F>>::with_capacity(ROUNDS);
+                let mut row = 0;
+
+                for word in input.iter() {
+                    w.push(self.message_word.assign_word(&mut region, row, word.0)?);
+                    row += self.message_word.rows();
+                }
+
+                self.assign_schedule(&mut region, row, w)
+            },
+        )?;
+
+        Ok(w.try_into().unwrap())
+    }
+
+    /// Returns the `ROUNDS` words of the message schedule of a block which is already
+    /// assigned, e.g. by the padding gadget. The words are copied into the range check of the
+    /// message words, which gives the spread halves the XORs need.
+    pub(super) fn process_assigned(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        input: &[AssignedBits<F, 32>; BLOCK_SIZE],
+    ) -> Result<[AssignedBits<F, 32>; ROUNDS], Error> {
+        let w = layouter.assign_region(
+            || "process assigned block",
+            |mut region| {
+                let mut w = Vec::<RoundWord<F>>::with_capacity(ROUNDS);
+                let mut row = 0;
+
+                for word in input.iter() {
+                    w.push(self.message_word.copy_word(&mut region, row, word)?);
+                    row += self.message_word.rows();
+                }
+
+                self.assign_schedule(&mut region, row, w)
+            },
+        )?;
+
+        Ok(w.try_into().unwrap())
+    }
+
+    // Extends the `BLOCK_SIZE` words of a block to the message schedule, starting at `row`
+    fn assign_schedule(
+        &self,
+        region: &mut Region<'_, F>,
+        mut row: usize,
+        mut w: Vec<RoundWord<F>>,
+    ) -> Result<Vec<AssignedBits<F, 32>>, Error> {
+        for t in BLOCK_SIZE..ROUNDS {
+            let xor_3 = self.xor_3.assign(region, row, &[&w[t - 3], &w[t - 8], &w[t - 14]])?;
+            row += SPREAD_SUM_ROWS;
+            let xor_4 = self.xor_4.assign(region, row, &[&xor_3, &w[t - 16]])?;
+            row += SPREAD_SUM_ROWS;
+            w.push(self.rotate_left_1.assign(region, row, &xor_4.dense)?);
+            row += SIGMA_ROWS;
+        }
+
+        Ok(w.into_iter().map(|word| word.dense).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sha1::ref_impl::{message_schedule, BLOCK_SIZE};
+    use crate::sha1::table16::{BlockWord, Table16Chip, Table16Config};
+    use halo2_proofs::circuit::Value;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+    use halo2_proofs::halo2curves::pasta::pallas;
+
+    #[test]
+    fn test_message_schedule_process() {
+        struct MyCircuit {}
+
+        impl Circuit<pallas::Base> for MyCircuit {
+            type Config = Table16Config<pallas::Base>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit {}
+            }
+
+            fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+                Table16Chip::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<pallas::Base>,
+            ) -> Result<(), Error> {
+                Table16Chip::load(config.clone(), &mut layouter)?;
+
+                // Message block with distinct words, including ones with the top bit set
+                let mut input = [0u32; BLOCK_SIZE];
+                for (i, word) in input.iter_mut().enumerate() {
+                    *word = (i as u32 + 1).wrapping_mul(0x9e37_79b9);
+                }
+
+                let w = config.message_schedule.process(&mut layouter, input.map(|x| BlockWord(Value::known(x))))?;
+
+                let expected = message_schedule(input);
+                for (word, expected) in w.iter().zip(expected.iter()) {
+                    word.value_u32().assert_if_known(|v| v == expected);
+                }
+
+                Ok(())
+            }
+        }
+
+        let circuit: MyCircuit = MyCircuit {};
+
+        let prover = match MockProver::<pallas::Base>::run(17, &circuit, vec![]) {
+            Ok(prover) => prover,
+            Err(e) => panic!("{:?}", e),
+        };
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
@@ -0,0 +1,414 @@
+//! The [SHA-256] hash function.
+//!
+//! [SHA-256]: https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.180-4.pdf
+//!
+pub mod ref_impl;
+pub mod table16;
+use std::fmt;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter, Value},
+    plonk::{Column, Error, Instance},
+};
+
+use self::ref_impl::{BLOCK_SIZE, DIGEST_SIZE, DIGEST_SIZE_BYTES};
+
+/// The set of circuit instructions required to use the [`Sha256`] gadget.
+pub trait Sha256Instructions<F: FieldExt>: Chip<F> {
+    /// Variable representing the SHA-256 internal state.
+    type State: Clone + fmt::Debug;
+    /// Variable representing a 32-bit word of the input block to the SHA-256 compression
+    /// function.
+    type BlockWord: Copy + fmt::Debug + Default;
+    /// Variable representing a 32-bit word of the digest which is assigned in the circuit.
+    type AssignedWord: Clone + fmt::Debug;
+
+    /// Places the SHA-256 IV in the circuit, returning the initial state variable.
+    fn initialization_vector(&self, layouter: &mut impl Layouter<F>) -> Result<Self::State, Error>;
+
+    /// Starting from the given initialized state, processes a block of input and returns the
+    /// final state.
+    fn compress(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &Self::State,
+        input: [Self::BlockWord; BLOCK_SIZE],
+    ) -> Result<Self::State, Error>;
+
+    /// Starting from the given initialized state, processes the padded block of a 32-byte
+    /// message whose words are the given assigned words, e.g. the digest of another
+    /// SHA-256 invocation, and returns the final state.
+    fn compress_digest(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &Self::State,
+        digest: &[Self::AssignedWord; DIGEST_SIZE],
+    ) -> Result<Self::State, Error>;
+
+    /// Converts the given state into a message digest. The digest bytes are the
+    /// big-endian bytes of the words.
+    fn digest(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &Self::State,
+    ) -> Result<[Self::BlockWord; DIGEST_SIZE], Error>;
+
+    /// Converts the given state into the assigned words of the message digest, so that
+    /// other gadgets can consume the digest with copy constraints.
+    fn digest_assigned(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &Self::State,
+    ) -> Result<[Self::AssignedWord; DIGEST_SIZE], Error>;
+
+    /// Converts the given state into a message digest and constrains the digest words
+    /// to be equal to the `DIGEST_SIZE` cells of `instance` starting at `start_row`.
+    fn digest_to_instance(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &Self::State,
+        instance: Column<Instance>,
+        start_row: usize,
+    ) -> Result<[Self::BlockWord; DIGEST_SIZE], Error>;
+}
+
+/// The output of a SHA-256 circuit invocation.
+#[derive(Debug)]
+pub struct Sha256Digest<BlockWord>([BlockWord; DIGEST_SIZE]);
+
+impl<BlockWord> Sha256Digest<BlockWord> {
+    /// Returns the words of the digest.
+    pub fn words(&self) -> &[BlockWord; DIGEST_SIZE] {
+        &self.0
+    }
+}
+
+impl Sha256Digest<table16::BlockWord> {
+    /// Returns the big-endian bytes of the digest words, i.e. the digest bytes.
+    pub fn to_be_bytes(&self) -> Value<[u8; DIGEST_SIZE_BYTES]> {
+        let words: Value<Vec<u32>> = self.0.iter().map(|word| word.0).collect();
+        words.map(|words| {
+            let mut bytes = [0u8; DIGEST_SIZE_BYTES];
+            for (chunk, word) in bytes.chunks_exact_mut(4).zip(words) {
+                chunk.copy_from_slice(&word.to_be_bytes());
+            }
+            bytes
+        })
+    }
+}
+
+/// A gadget that constrains a SHA-256 invocation. It supports input at a granularity of
+/// 32 bits.
+#[derive(Debug)]
+pub struct Sha256<F: FieldExt, CS: Sha256Instructions<F>> {
+    chip: CS,
+    state: CS::State,
+}
+
+impl<F: FieldExt, Sha256Chip: Sha256Instructions<F>> Sha256<F, Sha256Chip> {
+    /// Create a new hasher instance.
+    pub fn new(chip: Sha256Chip, mut layouter: impl Layouter<F>) -> Result<Self, Error> {
+        let state = chip.initialization_vector(&mut layouter)?;
+        Ok(Sha256 {
+            chip,
+            state,
+        })
+    }
+
+    /// Updating the internal state by consuming all message blocks
+    /// The input is assumed to be already padded to a multiple of 16 Blockwords
+    pub fn update(
+        &mut self,
+        mut layouter: impl Layouter<F>,
+        data: &Vec<[Sha256Chip::BlockWord; BLOCK_SIZE]>,
+    ) -> Result<(), Error> {
+        for b in data {
+            self.state = self.chip.compress(
+                &mut layouter,
+                &self.state,
+                *b,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Updating the internal state by consuming the padded block of a 32-byte message made
+    /// of the assigned words, e.g. the digest of another SHA-256 invocation. The message
+    /// padding assumes that the hasher has not consumed any other block.
+    pub fn update_digest(
+        &mut self,
+        mut layouter: impl Layouter<F>,
+        digest: &[Sha256Chip::AssignedWord; DIGEST_SIZE],
+    ) -> Result<(), Error> {
+        self.state = self.chip.compress_digest(&mut layouter, &self.state, digest)?;
+        Ok(())
+    }
+
+    /// Retrieve result and consume hasher instance.
+    pub fn finalize(
+        self,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<Sha256Digest<Sha256Chip::BlockWord>, Error> {
+        self.chip
+            .digest(&mut layouter, &self.state)
+            .map(Sha256Digest)
+    }
+
+    /// Retrieve the assigned digest words and consume hasher instance.
+    pub fn finalize_assigned(
+        self,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<[Sha256Chip::AssignedWord; DIGEST_SIZE], Error> {
+        self.chip.digest_assigned(&mut layouter, &self.state)
+    }
+
+    /// Retrieve result, constrain it to be equal to the expected digest in the
+    /// `instance` column starting at `start_row`, and consume hasher instance.
+    pub fn finalize_to_instance(
+        self,
+        mut layouter: impl Layouter<F>,
+        instance: Column<Instance>,
+        start_row: usize,
+    ) -> Result<Sha256Digest<Sha256Chip::BlockWord>, Error> {
+        self.chip
+            .digest_to_instance(&mut layouter, &self.state, instance, start_row)
+            .map(Sha256Digest)
+    }
+
+    /// Convenience function to compute hash of the data.
+    pub fn digest(
+        chip: Sha256Chip,
+        mut layouter: impl Layouter<F>,
+        data: &Vec<[Sha256Chip::BlockWord; BLOCK_SIZE]>,
+    ) -> Result<Sha256Digest<Sha256Chip::BlockWord>, Error> {
+        let mut hasher = Self::new(chip, layouter.namespace(|| "init"))?;
+        hasher.update(layouter.namespace(|| "update"), data)?;
+        hasher.finalize(layouter.namespace(|| "finalize"))
+    }
+
+    /// Convenience function to compute hash of the data and constrain it to be equal to
+    /// the expected digest in the `instance` column starting at `start_row`.
+    pub fn digest_to_instance(
+        chip: Sha256Chip,
+        mut layouter: impl Layouter<F>,
+        data: &Vec<[Sha256Chip::BlockWord; BLOCK_SIZE]>,
+        instance: Column<Instance>,
+        start_row: usize,
+    ) -> Result<Sha256Digest<Sha256Chip::BlockWord>, Error> {
+        let mut hasher = Self::new(chip, layouter.namespace(|| "init"))?;
+        hasher.update(layouter.namespace(|| "update"), data)?;
+        hasher.finalize_to_instance(layouter.namespace(|| "finalize"), instance, start_row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{plonk::{Circuit, Column, ConstraintSystem, Instance, self}, halo2curves::pasta::pallas, circuit::{SimpleFloorPlanner, Layouter}, dev::MockProver};
+    use halo2_proofs::arithmetic::{Field, FieldExt};
+    use halo2_proofs::halo2curves::bn256::Fr as BnScalar;
+
+    use crate::sha256::{Sha256, table16::{Table16Config, Table16Chip, BlockWord}};
+    use crate::sha256::ref_impl::{pad_message_bytes, convert_byte_slice_to_u32_slice, BLOCK_SIZE, BLOCK_SIZE_BYTES, DIGEST_SIZE};
+    use crate::ripemd160::{self, RIPEMD160, DigestByteOrder};
+    use crate::ripemd160::ref_impl::constants::BLOCK_SIZE_BYTES as RIPEMD160_BLOCK_SIZE_BYTES;
+
+    fn message_blocks(input: Vec<u8>) -> Vec<[BlockWord; BLOCK_SIZE]> {
+        pad_message_bytes(input)
+            .into_iter()
+            .map(|block| convert_byte_slice_to_u32_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>(block).map(BlockWord::from))
+            .collect()
+    }
+
+    fn expected_words(expected_hex: &str) -> [u32; DIGEST_SIZE] {
+        let expected_bytes: [u8; 4*DIGEST_SIZE] = hex::decode(expected_hex).unwrap().try_into().unwrap();
+        convert_byte_slice_to_u32_slice(expected_bytes)
+    }
+
+    struct KnownVectorCircuit {
+        input: Vec<u8>,
+        expected: [u32; DIGEST_SIZE],
+    }
+
+    impl<F: FieldExt> Circuit<F> for KnownVectorCircuit {
+        type Config = Table16Config<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            KnownVectorCircuit { input: self.input.clone(), expected: self.expected }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            Table16Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self, config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), plonk::Error> {
+            let table16_chip = Table16Chip::construct(config.clone());
+            Table16Chip::load(config, &mut layouter)?;
+
+            let digest = Sha256::digest(table16_chip, layouter, &message_blocks(self.input.clone()))?;
+            for (idx, digest_word) in digest.0.iter().enumerate() {
+                digest_word.0.assert_if_known(|v| {
+                    *v == self.expected[idx]
+                });
+            }
+
+            Ok(())
+        }
+    }
+
+    fn known_vectors() -> Vec<(Vec<u8>, &'static str)> {
+        vec![
+            (b"".to_vec(), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+            (b"abc".to_vec(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+            // Padded to two blocks
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq".to_vec(),
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            ),
+        ]
+    }
+
+    #[test]
+    fn hash_known_vectors() {
+        for (input, expected_hex) in known_vectors() {
+            let circuit = KnownVectorCircuit { input, expected: expected_words(expected_hex) };
+
+            let prover = MockProver::<pallas::Base>::run(17, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn hash_known_vectors_bn256() {
+        // The chip works over the scalar field of bn256 used by the BitcoinVM circuit
+        for (input, expected_hex) in known_vectors() {
+            let circuit = KnownVectorCircuit { input, expected: expected_words(expected_hex) };
+
+            let prover = MockProver::<BnScalar>::run(17, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn hash_with_ripemd160_spread_table() {
+        struct MyCircuit {}
+
+        impl Circuit<pallas::Base> for MyCircuit {
+            type Config = (ripemd160::table16::Table16Config<pallas::Base>, Table16Config<pallas::Base>);
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit {}
+            }
+
+            fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+                let ripemd160_config = ripemd160::table16::Table16Chip::configure(meta);
+                let sha256_config = Table16Chip::configure_with_spread_table(meta, ripemd160_config.spread_table());
+                (ripemd160_config, sha256_config)
+            }
+
+            fn synthesize(
+                &self, config: Self::Config,
+                mut layouter: impl Layouter<pallas::Base>,
+            ) -> Result<(), plonk::Error> {
+                // The spread table is loaded once for both chips
+                ripemd160::table16::Table16Chip::load(config.0.clone(), &mut layouter)?;
+
+                let sha256_digest = Sha256::digest(
+                    Table16Chip::construct(config.1),
+                    layouter.namespace(|| "sha256"),
+                    &message_blocks(b"abc".to_vec()),
+                )?;
+                let expected = expected_words("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+                for (idx, digest_word) in sha256_digest.0.iter().enumerate() {
+                    digest_word.0.assert_if_known(|v| *v == expected[idx]);
+                }
+
+                let ripemd160_data: Vec<[BlockWord; BLOCK_SIZE]> =
+                    ripemd160::ref_impl::ripemd160::pad_message_bytes(b"abc".to_vec())
+                        .into_iter()
+                        .map(ripemd160::table16::util::convert_byte_slice_to_blockword_slice::<RIPEMD160_BLOCK_SIZE_BYTES, BLOCK_SIZE>)
+                        .collect();
+                RIPEMD160::digest(
+                    ripemd160::table16::Table16Chip::construct(config.0),
+                    layouter.namespace(|| "ripemd160"),
+                    &ripemd160_data,
+                    DigestByteOrder::Internal,
+                )?;
+
+                Ok(())
+            }
+        }
+
+        let circuit: MyCircuit = MyCircuit {};
+
+        let prover = match MockProver::<pallas::Base>::run(17, &circuit, vec![]) {
+            Ok(prover) => prover,
+            Err(e) => panic!("{:?}", e),
+        };
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[derive(Clone, Debug)]
+    struct PublicDigestConfig {
+        table16_config: Table16Config<pallas::Base>,
+        instance: Column<Instance>,
+    }
+
+    struct PublicDigestCircuit {
+        input: Vec<u8>,
+    }
+
+    impl Circuit<pallas::Base> for PublicDigestCircuit {
+        type Config = PublicDigestConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            PublicDigestCircuit { input: self.input.clone() }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+            PublicDigestConfig {
+                table16_config: Table16Chip::configure(meta),
+                instance,
+            }
+        }
+
+        fn synthesize(
+            &self, config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), plonk::Error> {
+            let table16_chip = Table16Chip::construct(config.table16_config.clone());
+            Table16Chip::load(config.table16_config, &mut layouter)?;
+
+            Sha256::digest_to_instance(table16_chip, layouter, &message_blocks(self.input.clone()), config.instance, 0)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn hash_abc_public_digest() {
+        let circuit = PublicDigestCircuit { input: b"abc".to_vec() };
+
+        let public_input: Vec<pallas::Base> = expected_words("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+            .iter()
+            .map(|w| pallas::Base::from(*w as u64))
+            .collect();
+
+        let prover = MockProver::<pallas::Base>::run(17, &circuit, vec![public_input.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // A wrong expected digest is rejected
+        let mut wrong_public_input = public_input;
+        wrong_public_input[DIGEST_SIZE - 1] += pallas::Base::one();
+        let prover = MockProver::<pallas::Base>::run(17, &circuit, vec![wrong_public_input]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! A reference implementation of SHA-256, used to compute the witnesses of the
+//! [`Table16Chip`](super::table16::Table16Chip) and to check its outputs.
+use std::convert::TryInto;
+
+pub const DIGEST_SIZE: usize = 8;
+pub const DIGEST_SIZE_BYTES: usize = DIGEST_SIZE*4;
+pub const BLOCK_SIZE: usize = 16;
+pub const BLOCK_SIZE_BYTES: usize = BLOCK_SIZE*4;
+pub const ROUNDS: usize = 64;
+
+pub const INITIAL_VALUES: [u32; DIGEST_SIZE] = [
+    0x6a09_e667,
+    0xbb67_ae85,
+    0x3c6e_f372,
+    0xa54f_f53a,
+    0x510e_527f,
+    0x9b05_688c,
+    0x1f83_d9ab,
+    0x5be0_cd19,
+];
+
+pub const ROUND_CONSTANTS: [u32; ROUNDS] = [
+    0x428a_2f98, 0x7137_4491, 0xb5c0_fbcf, 0xe9b5_dba5, 0x3956_c25b, 0x59f1_11f1, 0x923f_82a4, 0xab1c_5ed5,
+    0xd807_aa98, 0x1283_5b01, 0x2431_85be, 0x550c_7dc3, 0x72be_5d74, 0x80de_b1fe, 0x9bdc_06a7, 0xc19b_f174,
+    0xe49b_69c1, 0xefbe_4786, 0x0fc1_9dc6, 0x240c_a1cc, 0x2de9_2c6f, 0x4a74_84aa, 0x5cb0_a9dc, 0x76f9_88da,
+    0x983e_5152, 0xa831_c66d, 0xb003_27c8, 0xbf59_7fc7, 0xc6e0_0bf3, 0xd5a7_9147, 0x06ca_6351, 0x1429_2967,
+    0x27b7_0a85, 0x2e1b_2138, 0x4d2c_6dfc, 0x5338_0d13, 0x650a_7354, 0x766a_0abb, 0x81c2_c92e, 0x9272_2c85,
+    0xa2bf_e8a1, 0xa81a_664b, 0xc24b_8b70, 0xc76c_51a3, 0xd192_e819, 0xd699_0624, 0xf40e_3585, 0x106a_a070,
+    0x19a4_c116, 0x1e37_6c08, 0x2748_774c, 0x34b0_bcb5, 0x391c_0cb3, 0x4ed8_aa4a, 0x5b9c_ca4f, 0x682e_6ff3,
+    0x748f_82ee, 0x78a5_636f, 0x84c8_7814, 0x8cc7_0208, 0x90be_fffa, 0xa450_6ceb, 0xbef9_a3f7, 0xc671_78f2,
+];
+
+pub fn sigma_0(x: u32) -> u32 {
+    x.rotate_right(7) ^ x.rotate_right(18) ^ (x >> 3)
+}
+
+pub fn sigma_1(x: u32) -> u32 {
+    x.rotate_right(17) ^ x.rotate_right(19) ^ (x >> 10)
+}
+
+pub fn big_sigma_0(x: u32) -> u32 {
+    x.rotate_right(2) ^ x.rotate_right(13) ^ x.rotate_right(22)
+}
+
+pub fn big_sigma_1(x: u32) -> u32 {
+    x.rotate_right(6) ^ x.rotate_right(11) ^ x.rotate_right(25)
+}
+
+pub fn ch(e: u32, f: u32, g: u32) -> u32 {
+    (e & f) ^ (!e & g)
+}
+
+pub fn maj(a: u32, b: u32, c: u32) -> u32 {
+    (a & b) ^ (a & c) ^ (b & c)
+}
+
+/// Pads the message to a multiple of the block size. Unlike RIPEMD-160, the
+/// message length is appended in big-endian byte order.
+pub fn pad_message_bytes(
+    msg_bytes: Vec<u8>,
+) -> Vec<[u8; BLOCK_SIZE_BYTES]> {
+    const PAD_BYTE: u8 = 0b1000_0000;
+    let msg_len_in_bits = (msg_bytes.len() << 3) as u64;
+    let mut padded_msg: Vec<u8> = msg_bytes;
+    padded_msg.push(PAD_BYTE);
+    while padded_msg.len() % BLOCK_SIZE_BYTES != BLOCK_SIZE_BYTES - 8 {
+        padded_msg.push(0);
+    }
+
+    padded_msg.extend(msg_len_in_bits.to_be_bytes());
+    assert!(padded_msg.len() % BLOCK_SIZE_BYTES == 0);
+
+    padded_msg
+        .chunks(BLOCK_SIZE_BYTES)
+        .map(|block| block.try_into().expect("Incorrect length"))
+        .collect()
+}
+
+/// Returns the 64 words of the message schedule of a block
+pub fn message_schedule(block: [u32; BLOCK_SIZE]) -> [u32; ROUNDS] {
+    let mut w = [0u32; ROUNDS];
+    w[..BLOCK_SIZE].copy_from_slice(&block);
+    for t in BLOCK_SIZE..ROUNDS {
+        w[t] = sigma_1(w[t-2])
+            .wrapping_add(w[t-7])
+            .wrapping_add(sigma_0(w[t-15]))
+            .wrapping_add(w[t-16]);
+    }
+    w
+}
+
+#[allow(clippy::many_single_char_names)]
+pub fn compress(state: [u32; DIGEST_SIZE], block: [u32; BLOCK_SIZE]) -> [u32; DIGEST_SIZE] {
+    let w = message_schedule(block);
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+    for (k, w) in ROUND_CONSTANTS.iter().zip(w.iter()) {
+        let t1 = h
+            .wrapping_add(big_sigma_1(e))
+            .wrapping_add(ch(e, f, g))
+            .wrapping_add(*k)
+            .wrapping_add(*w);
+        let t2 = big_sigma_0(a).wrapping_add(maj(a, b, c));
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+
+    let mut next_state = state;
+    for (word, working_word) in next_state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *word = word.wrapping_add(working_word);
+    }
+    next_state
+}
+
+pub fn hash(msg_bytes: Vec<u8>) -> [u8; DIGEST_SIZE_BYTES] {
+    let mut state = INITIAL_VALUES;
+    for block in pad_message_bytes(msg_bytes) {
+        state = compress(state, convert_byte_slice_to_u32_slice(block));
+    }
+    state
+        .iter()
+        .flat_map(|word| word.to_be_bytes())
+        .collect::<Vec<u8>>()
+        .try_into()
+        .expect("Failed conversion")
+}
+
+/// Converts bytes to the big-endian words used by SHA-256
+pub fn convert_byte_slice_to_u32_slice<const LEN_BYTES: usize, const LEN_U32: usize>(
+    b: [u8; LEN_BYTES]
+) -> [u32; LEN_U32] {
+    assert!(LEN_BYTES == 4*LEN_U32);
+    let v: Vec<u32> = b
+        .chunks(4)
+        .map(|word| u32::from_be_bytes(word.try_into().unwrap()))
+        .collect();
+    v.try_into().expect("Failed conversion")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hash;
+
+    #[test]
+    fn test_known_vectors() {
+        for (msg, expected_hex) in [
+            (b"".to_vec(), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+            (b"abc".to_vec(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq".to_vec(),
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            ),
+        ] {
+            assert_eq!(hex::encode(hash(msg)), expected_hex);
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use std::convert::TryInto;
+
+use super::gates::{SigmaConfig, SigmaOp, SpreadBits, SpreadSumConfig, WordSumConfig, SIGMA_ROWS, SPREAD_SUM_ROWS};
+use super::{AssignedBits, SpreadInputs, State, NUM_ADVICE_COLS};
+use crate::sha256::ref_impl::{DIGEST_SIZE, ROUNDS, ROUND_CONSTANTS};
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::Layouter,
+    plonk::{Advice, Column, ConstraintSystem, Error},
+};
+
+#[derive(Clone, Debug)]
+pub(super) struct CompressionConfig<F: FieldExt> {
+    /// Words of the initialization vector, fixed to the constants
+    initial_word: WordSumConfig<F>,
+    /// Σ0(a) = ROTR^2(a) ^ ROTR^13(a) ^ ROTR^22(a)
+    big_sigma_0: SigmaConfig<F>,
+    /// Σ1(e) = ROTR^6(e) ^ ROTR^11(e) ^ ROTR^25(e)
+    big_sigma_1: SigmaConfig<F>,
+    /// e & f, the first half of Ch(e, f, g)
+    ch: SpreadSumConfig<F>,
+    /// !e & g, the second half of Ch(e, f, g). The halves have no common bits,
+    /// so their sum is Ch(e, f, g).
+    ch_neg: SpreadSumConfig<F>,
+    /// Maj(a, b, c)
+    maj: SpreadSumConfig<F>,
+    /// e' = d + h + Σ1(e) + Ch(e, f, g) + K_t + W_t
+    new_e: WordSumConfig<F>,
+    /// a' = h + Σ1(e) + Ch(e, f, g) + K_t + W_t + Σ0(a) + Maj(a, b, c)
+    new_a: WordSumConfig<F>,
+    /// Sum of the state before and after the rounds
+    digest_word: WordSumConfig<F>,
+}
+
+impl<F: FieldExt> CompressionConfig<F> {
+    pub(super) fn configure(
+        meta: &mut ConstraintSystem<F>,
+        lookup: SpreadInputs,
+        advice: [Column<Advice>; NUM_ADVICE_COLS],
+    ) -> Self {
+        // The initialization vector and the round constants
+        let constants = meta.fixed_column();
+
+        let initial_word = WordSumConfig::configure(
+            meta,
+            "initial word",
+            lookup.clone(),
+            advice,
+            0,
+            Some(constants),
+        );
+        let big_sigma_0 = SigmaConfig::configure(
+            meta,
+            "big_sigma_0",
+            lookup.clone(),
+            advice,
+            [2, 11, 9, 10],
+            &[SigmaOp::RotateRight(2), SigmaOp::RotateRight(13), SigmaOp::RotateRight(22)],
+        );
+        let big_sigma_1 = SigmaConfig::configure(
+            meta,
+            "big_sigma_1",
+            lookup.clone(),
+            advice,
+            [6, 5, 14, 7],
+            &[SigmaOp::RotateRight(6), SigmaOp::RotateRight(11), SigmaOp::RotateRight(25)],
+        );
+        let ch = SpreadSumConfig::configure(meta, "ch", lookup.clone(), advice, 2, false, SpreadBits::Odd);
+        let ch_neg = SpreadSumConfig::configure(meta, "ch_neg", lookup.clone(), advice, 2, true, SpreadBits::Odd);
+        let maj = SpreadSumConfig::configure(meta, "maj", lookup.clone(), advice, 3, false, SpreadBits::Odd);
+        let new_e = WordSumConfig::configure(meta, "new e", lookup.clone(), advice, 6, Some(constants));
+        let new_a = WordSumConfig::configure(meta, "new a", lookup.clone(), advice, 7, Some(constants));
+        let digest_word = WordSumConfig::configure(meta, "digest word", lookup, advice, 2, None);
+
+        CompressionConfig {
+            initial_word,
+            big_sigma_0,
+            big_sigma_1,
+            ch,
+            ch_neg,
+            maj,
+            new_e,
+            new_a,
+            digest_word,
+        }
+    }
+
+    pub(super) fn initialize_with_iv(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        iv: [u32; DIGEST_SIZE],
+    ) -> Result<State<F>, Error> {
+        layouter.assign_region(
+            || "initialize",
+            |mut region| {
+                let mut row = 0;
+                let mut state = Vec::with_capacity(DIGEST_SIZE);
+                for word in iv.iter() {
+                    state.push(self.initial_word.assign_sum(&mut region, row, &[], Some(*word))?);
+                    row += self.initial_word.rows();
+                }
+                Ok(State(state.try_into().unwrap()))
+            },
+        )
+    }
+
+    #[allow(clippy::many_single_char_names)]
+    pub(super) fn compress(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initial_state: State<F>,
+        w: [AssignedBits<F, 32>; ROUNDS],
+    ) -> Result<State<F>, Error> {
+        layouter.assign_region(
+            || "compress",
+            |mut region| {
+                let region = &mut region;
+                let mut row = 0;
+                let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = initial_state.0.clone();
+
+                for (t, w) in w.iter().enumerate() {
+                    let sigma_1 = self.big_sigma_1.assign(region, row, &e.dense)?;
+                    row += SIGMA_ROWS;
+                    let ch = self.ch.assign(region, row, &[&e, &f])?;
+                    row += SPREAD_SUM_ROWS;
+                    let ch_neg = self.ch_neg.assign(region, row, &[&e, &g])?;
+                    row += SPREAD_SUM_ROWS;
+                    let sigma_0 = self.big_sigma_0.assign(region, row, &a.dense)?;
+                    row += SIGMA_ROWS;
+                    let maj = self.maj.assign(region, row, &[&a, &b, &c])?;
+                    row += SPREAD_SUM_ROWS;
+
+                    let new_e = self.new_e.assign_sum(
+                        region,
+                        row,
+                        &[&d.dense, &h.dense, &sigma_1.dense, &ch.dense, &ch_neg.dense, w],
+                        Some(ROUND_CONSTANTS[t]),
+                    )?;
+                    row += self.new_e.rows();
+                    let new_a = self.new_a.assign_sum(
+                        region,
+                        row,
+                        &[&h.dense, &sigma_1.dense, &ch.dense, &ch_neg.dense, w, &sigma_0.dense, &maj.dense],
+                        Some(ROUND_CONSTANTS[t]),
+                    )?;
+                    row += self.new_a.rows();
+
+                    h = g;
+                    g = f;
+                    f = e;
+                    e = new_e;
+                    d = c;
+                    c = b;
+                    b = a;
+                    a = new_a;
+                }
+
+                let mut state = Vec::with_capacity(DIGEST_SIZE);
+                for (initial, word) in initial_state.0.iter().zip([a, b, c, d, e, f, g, h].iter()) {
+                    state.push(self.digest_word.assign_sum(region, row, &[&initial.dense, &word.dense], None)?);
+                    row += self.digest_word.rows();
+                }
+                Ok(State(state.try_into().unwrap()))
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sha256::ref_impl::{compress, BLOCK_SIZE, INITIAL_VALUES};
+    use crate::sha256::table16::{BlockWord, Table16Chip, Table16Config};
+    use halo2_proofs::circuit::Value;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+    use halo2_proofs::halo2curves::pasta::pallas;
+
+    #[test]
+    fn test_compress_block() {
+        struct MyCircuit {}
+
+        impl Circuit<pallas::Base> for MyCircuit {
+            type Config = Table16Config<pallas::Base>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit {}
+            }
+
+            fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+                Table16Chip::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<pallas::Base>,
+            ) -> Result<(), Error> {
+                Table16Chip::load(config.clone(), &mut layouter)?;
+
+                let mut input = [0u32; BLOCK_SIZE];
+                for (i, word) in input.iter_mut().enumerate() {
+                    *word = !(i as u32).wrapping_mul(0x0101_0101);
+                }
+
+                let initial_state = config.compression.initialize_with_iv(&mut layouter, INITIAL_VALUES)?;
+                let w = config.message_schedule.process(&mut layouter, input.map(|x| BlockWord(Value::known(x))))?;
+                let state = config.compression.compress(&mut layouter, initial_state, w)?;
+
+                let expected = compress(INITIAL_VALUES, input);
+                for (word, expected) in state.0.iter().zip(expected.iter()) {
+                    word.dense.value_u32().assert_if_known(|v| v == expected);
+                }
+
+                Ok(())
+            }
+        }
+
+        let circuit: MyCircuit = MyCircuit {};
+
+        let prover = match MockProver::<pallas::Base>::run(17, &circuit, vec![]) {
+            Ok(prover) => prover,
+            Err(e) => panic!("{:?}", e),
+        };
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
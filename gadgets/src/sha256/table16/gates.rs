@@ -0,0 +1,552 @@
+//! Gates shared by the message schedule and the compression function. Each gate
+//! constrains a fixed block of rows starting at the row where its selector is enabled.
+//! The 16-bit chunks of all words are looked up in the spread table, which both
+//! range checks them and provides their spread forms. The gates are also used by the
+//! SHA-1 chip.
+use std::marker::PhantomData;
+
+use super::{AssignedBits, RoundWord, SpreadInputs, SpreadVar, SpreadWord, NUM_ADVICE_COLS};
+use crate::ripemd160::table16::util::i2lebsp;
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Selector},
+    poly::Rotation,
+};
+
+/// Spread form of the 32-bit word with all bits set
+const SPREAD_ALL_ONES: u64 = 0x5555_5555_5555_5555;
+
+/// Number of pieces a word is split into by a [`SigmaConfig`]
+pub(crate) const SIGMA_PIECES: usize = 4;
+/// Rows used by a [`SigmaConfig`]: two lookups per piece and four for the even
+/// and odd halves of the spread sum
+pub(crate) const SIGMA_ROWS: usize = 2 * SIGMA_PIECES + 4;
+/// Rows used by a [`SpreadSumConfig`]: four lookups for the even and odd halves
+pub(crate) const SPREAD_SUM_ROWS: usize = 4;
+
+fn spread_u32(word: u32) -> u64 {
+    (0..32).fold(0, |acc, i| acc | (((word as u64) >> i) & 1) << (2 * i))
+}
+
+fn even_bits_u64(spread: u64) -> u32 {
+    (0..32).fold(0, |acc, i| acc | (((spread >> (2 * i)) & 1) as u32) << i)
+}
+
+fn odd_bits_u64(spread: u64) -> u32 {
+    even_bits_u64(spread >> 1)
+}
+
+fn constant<F: FieldExt>(value: u64) -> Expression<F> {
+    Expression::Constant(F::from(value))
+}
+
+/// Assigns a 16-bit value with its tag and spread form in a row of the lookup columns
+fn assign_spread_u16<F: FieldExt>(
+    region: &mut Region<'_, F>,
+    lookup: &SpreadInputs,
+    row: usize,
+    value: Value<u16>,
+) -> Result<SpreadVar<F, 16, 32>, Error> {
+    let word = value.map(|value| SpreadWord::<16, 32>::new(i2lebsp(value.into())));
+    SpreadVar::with_lookup(region, lookup, row, word)
+}
+
+/// Assigns the low and high halves of a 32-bit value in two rows of the lookup columns
+fn assign_spread_halves<F: FieldExt>(
+    region: &mut Region<'_, F>,
+    lookup: &SpreadInputs,
+    row: usize,
+    value: Value<u32>,
+) -> Result<(SpreadVar<F, 16, 32>, SpreadVar<F, 16, 32>), Error> {
+    let lo = assign_spread_u16(region, lookup, row, value.map(|value| value as u16))?;
+    let hi = assign_spread_u16(region, lookup, row + 1, value.map(|value| (value >> 16) as u16))?;
+    Ok((lo, hi))
+}
+
+/// A rotation or shift of a word, up to three of which are XORed by a [`SigmaConfig`]
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum SigmaOp {
+    RotateRight(usize),
+    ShiftRight(usize),
+}
+
+impl SigmaOp {
+    fn amount(&self) -> usize {
+        match *self {
+            SigmaOp::RotateRight(n) | SigmaOp::ShiftRight(n) => n,
+        }
+    }
+
+    fn apply(&self, word: u32) -> u32 {
+        match *self {
+            SigmaOp::RotateRight(n) => word.rotate_right(n as u32),
+            SigmaOp::ShiftRight(n) => word >> n,
+        }
+    }
+
+    /// Position of the piece starting at bit `offset` after the operation, or
+    /// `None` if the piece is shifted out
+    fn position(&self, offset: usize) -> Option<usize> {
+        match *self {
+            SigmaOp::RotateRight(n) => Some((offset + 32 - n) % 32),
+            SigmaOp::ShiftRight(n) => offset.checked_sub(n),
+        }
+    }
+}
+
+fn piece_offsets(pieces: [usize; SIGMA_PIECES]) -> [usize; SIGMA_PIECES] {
+    let mut offset = 0;
+    pieces.map(|len| {
+        let start = offset;
+        offset += len;
+        start
+    })
+}
+
+/// Constrains the XOR of up to three rotations or shifts of a word, i.e. one of the
+/// functions σ0, σ1, Σ0 and Σ1 of SHA-256, or a single rotation.
+///
+/// The word is split into pieces at the rotation and shift amounts, so every
+/// operation moves whole pieces. The sum of the spread forms of the moved words
+/// has the XOR in its even bits.
+///
+/// | Row           | a_0, a_1, a_2 (lookup)        | a_3    |
+/// |---------------|-------------------------------|--------|
+/// | 2i            | piece i                       | word   |
+/// | 2i + 1        | piece i << (16 - len_i)       | output |
+/// | 8, 9          | even bits (lo, hi)            |        |
+/// | 10, 11        | odd bits (lo, hi)             |        |
+///
+/// A piece fits in `len_i` bits since the shifted piece fits in 16 bits.
+#[derive(Clone, Debug)]
+pub(crate) struct SigmaConfig<F: FieldExt> {
+    lookup: SpreadInputs,
+    advice: [Column<Advice>; NUM_ADVICE_COLS],
+    pieces: [usize; SIGMA_PIECES],
+    ops: Vec<SigmaOp>,
+    s_sigma: Selector,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> SigmaConfig<F> {
+    /// Configures the gate for the little-endian `pieces` of a word, which must
+    /// start at every rotation and shift amount of `ops`.
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<F>,
+        name: &'static str,
+        lookup: SpreadInputs,
+        advice: [Column<Advice>; NUM_ADVICE_COLS],
+        pieces: [usize; SIGMA_PIECES],
+        ops: &[SigmaOp],
+    ) -> Self {
+        let offsets = piece_offsets(pieces);
+        // Up to three spread bits are added at each position, which fits in two bits
+        assert!(!ops.is_empty() && ops.len() <= 3);
+        assert_eq!(pieces.iter().sum::<usize>(), 32);
+        assert!(pieces.iter().all(|len| *len > 0 && *len < 16));
+        for op in ops.iter() {
+            assert!(offsets.contains(&op.amount()), "{} does not move whole pieces", name);
+        }
+
+        let s_sigma = meta.selector();
+        let a_1 = lookup.dense;
+        let a_2 = lookup.spread;
+        let a_3 = advice[0];
+
+        meta.create_gate(name, |meta| {
+            let s_sigma = meta.query_selector(s_sigma);
+            let word = meta.query_advice(a_3, Rotation::cur());
+            let output = meta.query_advice(a_3, Rotation::next());
+
+            let mut constraints = vec![];
+            let mut word_from_pieces = constant(0);
+            let mut spread_sum = constant(0);
+            for (i, (len, offset)) in pieces.iter().zip(offsets.iter()).enumerate() {
+                let piece = meta.query_advice(a_1, Rotation((2 * i) as i32));
+                let spread_piece = meta.query_advice(a_2, Rotation((2 * i) as i32));
+                let shifted_piece = meta.query_advice(a_1, Rotation((2 * i + 1) as i32));
+
+                constraints.push((
+                    "piece range check",
+                    piece.clone() * F::from(1u64 << (16 - len)) - shifted_piece,
+                ));
+                word_from_pieces = word_from_pieces + piece * F::from(1u64 << offset);
+                for op in ops.iter() {
+                    if let Some(position) = op.position(*offset) {
+                        spread_sum = spread_sum + spread_piece.clone() * F::from(1u64 << (2 * position));
+                    }
+                }
+            }
+
+            let even_row = 2 * SIGMA_PIECES;
+            let even_lo = meta.query_advice(a_1, Rotation(even_row as i32));
+            let even_hi = meta.query_advice(a_1, Rotation(even_row as i32 + 1));
+            let spread_even_lo = meta.query_advice(a_2, Rotation(even_row as i32));
+            let spread_even_hi = meta.query_advice(a_2, Rotation(even_row as i32 + 1));
+            let spread_odd_lo = meta.query_advice(a_2, Rotation(even_row as i32 + 2));
+            let spread_odd_hi = meta.query_advice(a_2, Rotation(even_row as i32 + 3));
+
+            let spread_even = spread_even_lo + spread_even_hi * F::from(1u64 << 32);
+            let spread_odd = spread_odd_lo + spread_odd_hi * F::from(1u64 << 32);
+
+            constraints.push(("word from pieces", word_from_pieces - word));
+            constraints.push(("spread sum", spread_sum - (spread_even + spread_odd * F::from(2u64))));
+            constraints.push(("output", even_lo + even_hi * F::from(1u64 << 16) - output));
+
+            constraints
+                .into_iter()
+                .map(|(name, constraint)| (name, s_sigma.clone() * constraint))
+                .collect::<Vec<_>>()
+        });
+
+        SigmaConfig {
+            lookup,
+            advice,
+            pieces,
+            ops: ops.to_vec(),
+            s_sigma,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Assigns the gate starting at `row` and returns the output word
+    pub(crate) fn assign(
+        &self,
+        region: &mut Region<'_, F>,
+        row: usize,
+        word: &AssignedBits<F, 32>,
+    ) -> Result<RoundWord<F>, Error> {
+        let a_3 = self.advice[0];
+        self.s_sigma.enable(region, row)?;
+        word.copy_advice(|| "word", region, a_3, row)?;
+
+        let word = word.value_u32();
+        let offsets = piece_offsets(self.pieces);
+        for (i, (len, offset)) in self.pieces.iter().zip(offsets.iter()).enumerate() {
+            let piece = word.map(|word| ((word >> offset) & ((1 << len) - 1)) as u16);
+            assign_spread_u16(region, &self.lookup, row + 2 * i, piece)?;
+            assign_spread_u16(region, &self.lookup, row + 2 * i + 1, piece.map(|piece| piece << (16 - len)))?;
+        }
+
+        let spread_sum = word.map(|word| self.ops.iter().map(|op| spread_u32(op.apply(word))).sum::<u64>());
+        let even = spread_sum.map(even_bits_u64);
+        let (even_lo, even_hi) = assign_spread_halves(region, &self.lookup, row + 2 * SIGMA_PIECES, even)?;
+        assign_spread_halves(region, &self.lookup, row + 2 * SIGMA_PIECES + 2, spread_sum.map(odd_bits_u64))?;
+
+        let dense = AssignedBits::<F, 32>::assign(region, || "output", a_3, row + 1, even)?;
+        Ok(RoundWord {
+            dense,
+            spread_lo: even_lo.spread,
+            spread_hi: even_hi.spread,
+        })
+    }
+}
+
+/// Which bits of the spread sum of a [`SpreadSumConfig`] are its output
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SpreadBits {
+    /// The XOR of the words
+    Even,
+    /// The AND of two words or the majority of three
+    Odd,
+}
+
+/// Constrains the even or odd bits of the sum of the spread forms of two or three
+/// words. The even bits are the XOR of the words, and the odd bits are the AND of
+/// two words or the majority of three. With `negate_first` the spread form of the
+/// first word is replaced by that of its complement, so two words give the
+/// `!e & g` half of Ch.
+///
+/// | Row  | a_0, a_1, a_2 (lookup) | a_3         | a_4         | a_5    |
+/// |------|------------------------|-------------|-------------|--------|
+/// | 0    | even bits lo           | spread_lo 0 | spread_hi 0 | output |
+/// | 1    | even bits hi           | spread_lo 1 | spread_hi 1 |        |
+/// | 2    | odd bits lo            | spread_lo 2 | spread_hi 2 |        |
+/// | 3    | odd bits hi            |             |             |        |
+#[derive(Clone, Debug)]
+pub(crate) struct SpreadSumConfig<F: FieldExt> {
+    lookup: SpreadInputs,
+    advice: [Column<Advice>; NUM_ADVICE_COLS],
+    num_words: usize,
+    negate_first: bool,
+    output: SpreadBits,
+    s_spread_sum: Selector,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> SpreadSumConfig<F> {
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<F>,
+        name: &'static str,
+        lookup: SpreadInputs,
+        advice: [Column<Advice>; NUM_ADVICE_COLS],
+        num_words: usize,
+        negate_first: bool,
+        output_bits: SpreadBits,
+    ) -> Self {
+        assert!(num_words == 2 || num_words == 3);
+
+        let s_spread_sum = meta.selector();
+        let a_1 = lookup.dense;
+        let a_2 = lookup.spread;
+        let a_3 = advice[0];
+        let a_4 = advice[1];
+        let a_5 = advice[2];
+
+        meta.create_gate(name, |meta| {
+            let s_spread_sum = meta.query_selector(s_spread_sum);
+            let output = meta.query_advice(a_5, Rotation::cur());
+
+            let mut spread_sum = constant(0);
+            for i in 0..num_words {
+                let spread_lo = meta.query_advice(a_3, Rotation(i as i32));
+                let spread_hi = meta.query_advice(a_4, Rotation(i as i32));
+                let spread_word = spread_lo + spread_hi * F::from(1u64 << 32);
+                spread_sum = if i == 0 && negate_first {
+                    spread_sum + constant(SPREAD_ALL_ONES) - spread_word
+                } else {
+                    spread_sum + spread_word
+                };
+            }
+
+            let output_row = match output_bits {
+                SpreadBits::Even => 0,
+                SpreadBits::Odd => 2,
+            };
+            let output_lo = meta.query_advice(a_1, Rotation(output_row));
+            let output_hi = meta.query_advice(a_1, Rotation(output_row + 1));
+            let spread_even_lo = meta.query_advice(a_2, Rotation::cur());
+            let spread_even_hi = meta.query_advice(a_2, Rotation::next());
+            let spread_odd_lo = meta.query_advice(a_2, Rotation(2));
+            let spread_odd_hi = meta.query_advice(a_2, Rotation(3));
+
+            let spread_even = spread_even_lo + spread_even_hi * F::from(1u64 << 32);
+            let spread_odd = spread_odd_lo + spread_odd_hi * F::from(1u64 << 32);
+
+            vec![
+                ("spread sum", s_spread_sum.clone() * (spread_sum - (spread_even + spread_odd * F::from(2u64)))),
+                ("output", s_spread_sum * (output_lo + output_hi * F::from(1u64 << 16) - output)),
+            ]
+        });
+
+        SpreadSumConfig {
+            lookup,
+            advice,
+            num_words,
+            negate_first,
+            output: output_bits,
+            s_spread_sum,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Assigns the gate starting at `row` and returns the output word
+    pub(crate) fn assign(
+        &self,
+        region: &mut Region<'_, F>,
+        row: usize,
+        words: &[&RoundWord<F>],
+    ) -> Result<RoundWord<F>, Error> {
+        assert_eq!(words.len(), self.num_words);
+        let a_3 = self.advice[0];
+        let a_4 = self.advice[1];
+        let a_5 = self.advice[2];
+        self.s_spread_sum.enable(region, row)?;
+
+        for (i, word) in words.iter().enumerate() {
+            word.spread_lo.copy_advice(|| "spread_lo", region, a_3, row + i)?;
+            word.spread_hi.copy_advice(|| "spread_hi", region, a_4, row + i)?;
+        }
+
+        let negate_first = self.negate_first;
+        let values: Value<Vec<u32>> = words.iter().map(|word| word.dense.value_u32()).collect();
+        let spread_sum = values.map(|values| {
+            values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| if i == 0 && negate_first { spread_u32(!value) } else { spread_u32(*value) })
+                .sum::<u64>()
+        });
+        let even = spread_sum.map(even_bits_u64);
+        let odd = spread_sum.map(odd_bits_u64);
+        let even_halves = assign_spread_halves(region, &self.lookup, row, even)?;
+        let odd_halves = assign_spread_halves(region, &self.lookup, row + 2, odd)?;
+
+        let (output, (lo, hi)) = match self.output {
+            SpreadBits::Even => (even, even_halves),
+            SpreadBits::Odd => (odd, odd_halves),
+        };
+        let dense = AssignedBits::<F, 32>::assign(region, || "output", a_5, row, output)?;
+        Ok(RoundWord {
+            dense,
+            spread_lo: lo.spread,
+            spread_hi: hi.spread,
+        })
+    }
+}
+
+/// Constrains a 32-bit word to the sum modulo 2^32 of `num_addends` words and an
+/// optional constant, and looks up its halves. Without addends and a constant, it
+/// only range checks the word.
+///
+/// | Row | a_0, a_1, a_2 (lookup) | a_3       | a_4       | a_5       | fixed    |
+/// |-----|------------------------|-----------|-----------|-----------|----------|
+/// | 0   | word lo                | word      | addend 0  | addend 1  | constant |
+/// | 1   | word hi                | addend 2  | addend 3  | addend 4  |          |
+/// | 2   | carry                  | addend 5  | addend 6  | addend 7  |          |
+///
+/// The carry is looked up as a 16-bit value, which makes the word unique for fewer
+/// than 2^16 addends.
+#[derive(Clone, Debug)]
+pub(crate) struct WordSumConfig<F: FieldExt> {
+    lookup: SpreadInputs,
+    advice: [Column<Advice>; NUM_ADVICE_COLS],
+    num_addends: usize,
+    constant_column: Option<Column<Fixed>>,
+    s_word_sum: Selector,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> WordSumConfig<F> {
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<F>,
+        name: &'static str,
+        lookup: SpreadInputs,
+        advice: [Column<Advice>; NUM_ADVICE_COLS],
+        num_addends: usize,
+        constant_column: Option<Column<Fixed>>,
+    ) -> Self {
+        let s_word_sum = meta.selector();
+        let a_1 = lookup.dense;
+        let a_3 = advice[0];
+
+        meta.create_gate(name, |meta| {
+            let s_word_sum = meta.query_selector(s_word_sum);
+            let word = meta.query_advice(a_3, Rotation::cur());
+            let lo = meta.query_advice(a_1, Rotation::cur());
+            let hi = meta.query_advice(a_1, Rotation::next());
+
+            let mut constraints = vec![
+                ("word halves", s_word_sum.clone() * (lo + hi * F::from(1u64 << 16) - word.clone())),
+            ];
+            if num_addends > 0 || constant_column.is_some() {
+                let carry = meta.query_advice(a_1, Rotation(2));
+                let mut sum = match constant_column {
+                    Some(column) => meta.query_fixed(column, Rotation::cur()),
+                    None => constant(0),
+                };
+                for i in 0..num_addends {
+                    let (column, row) = Self::addend_position(advice, i);
+                    sum = sum + meta.query_advice(column, Rotation(row as i32));
+                }
+                constraints.push((
+                    "word sum",
+                    s_word_sum * (sum - word - carry * F::from(1u64 << 32)),
+                ));
+            }
+            constraints
+        });
+
+        WordSumConfig {
+            lookup,
+            advice,
+            num_addends,
+            constant_column,
+            s_word_sum,
+            _marker: PhantomData,
+        }
+    }
+
+    fn has_sum(&self) -> bool {
+        self.num_addends > 0 || self.constant_column.is_some()
+    }
+
+    /// Column and row offset of the `i`-th addend
+    fn addend_position(advice: [Column<Advice>; NUM_ADVICE_COLS], i: usize) -> (Column<Advice>, usize) {
+        (advice[(i + 1) % NUM_ADVICE_COLS], (i + 1) / NUM_ADVICE_COLS)
+    }
+
+    /// Number of rows used by the gate
+    pub(crate) fn rows(&self) -> usize {
+        let lookup_rows = if self.has_sum() { 3 } else { 2 };
+        lookup_rows.max(self.num_addends / NUM_ADVICE_COLS + 1)
+    }
+
+    /// Assigns a word which is only range checked, starting at `row`
+    pub(crate) fn assign_word(
+        &self,
+        region: &mut Region<'_, F>,
+        row: usize,
+        word: Value<u32>,
+    ) -> Result<RoundWord<F>, Error> {
+        assert!(!self.has_sum());
+        self.s_word_sum.enable(region, row)?;
+        self.assign_word_and_halves(region, row, word)
+    }
+
+    /// Copies a word which is already assigned, e.g. by the padding gadget, starting at
+    /// `row`, and returns it with the spread forms of its halves
+    pub(crate) fn copy_word(
+        &self,
+        region: &mut Region<'_, F>,
+        row: usize,
+        word: &AssignedBits<F, 32>,
+    ) -> Result<RoundWord<F>, Error> {
+        assert!(!self.has_sum());
+        self.s_word_sum.enable(region, row)?;
+        let (lo, hi) = assign_spread_halves(region, &self.lookup, row, word.value_u32())?;
+        word.copy_advice(|| "word", region, self.advice[0], row)?;
+        Ok(RoundWord {
+            dense: word.clone(),
+            spread_lo: lo.spread,
+            spread_hi: hi.spread,
+        })
+    }
+
+    /// Assigns the sum of the addends and the constant starting at `row`
+    pub(crate) fn assign_sum(
+        &self,
+        region: &mut Region<'_, F>,
+        row: usize,
+        addends: &[&AssignedBits<F, 32>],
+        constant: Option<u32>,
+    ) -> Result<RoundWord<F>, Error> {
+        assert_eq!(addends.len(), self.num_addends);
+        assert_eq!(constant.is_some(), self.constant_column.is_some());
+        self.s_word_sum.enable(region, row)?;
+
+        let mut sum = Value::known(constant.unwrap_or(0) as u64);
+        for (i, addend) in addends.iter().enumerate() {
+            let (column, offset) = Self::addend_position(self.advice, i);
+            addend.copy_advice(|| "addend", region, column, row + offset)?;
+            sum = sum.zip(addend.value_u32()).map(|(sum, addend)| sum + addend as u64);
+        }
+        if let (Some(column), Some(constant)) = (self.constant_column, constant) {
+            region.assign_fixed(
+                || "constant",
+                column,
+                row,
+                || Value::known(F::from(constant as u64)),
+            )?;
+        }
+
+        assign_spread_u16(region, &self.lookup, row + 2, sum.map(|sum| (sum >> 32) as u16))?;
+        self.assign_word_and_halves(region, row, sum.map(|sum| sum as u32))
+    }
+
+    fn assign_word_and_halves(
+        &self,
+        region: &mut Region<'_, F>,
+        row: usize,
+        word: Value<u32>,
+    ) -> Result<RoundWord<F>, Error> {
+        let (lo, hi) = assign_spread_halves(region, &self.lookup, row, word)?;
+        let dense = AssignedBits::<F, 32>::assign(region, || "word", self.advice[0], row, word)?;
+        Ok(RoundWord {
+            dense,
+            spread_lo: lo.spread,
+            spread_hi: hi.spread,
+        })
+    }
+}
@@ -0,0 +1,229 @@
+use std::convert::TryInto;
+
+use super::gates::{SigmaConfig, SigmaOp, WordSumConfig, SIGMA_ROWS};
+use super::{AssignedBits, BlockWord, SpreadInputs, NUM_ADVICE_COLS};
+use crate::sha256::ref_impl::{BLOCK_SIZE, DIGEST_SIZE, ROUNDS};
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Layouter, Region},
+    plonk::{Advice, Column, ConstraintSystem, Error},
+};
+
+#[derive(Clone, Debug)]
+pub(super) struct MessageScheduleConfig<F: FieldExt> {
+    /// Range check of the words of the message block
+    message_word: WordSumConfig<F>,
+    /// Words of the padding of a 32-byte message, fixed to the constants
+    padding_word: WordSumConfig<F>,
+    /// σ0(x) = ROTR^7(x) ^ ROTR^18(x) ^ SHR^3(x)
+    sigma_0: SigmaConfig<F>,
+    /// σ1(x) = ROTR^17(x) ^ ROTR^19(x) ^ SHR^10(x)
+    sigma_1: SigmaConfig<F>,
+    /// W_t = σ1(W_{t-2}) + W_{t-7} + σ0(W_{t-15}) + W_{t-16}
+    schedule_word: WordSumConfig<F>,
+}
+
+impl<F: FieldExt> MessageScheduleConfig<F> {
+    pub(super) fn configure(
+        meta: &mut ConstraintSystem<F>,
+        lookup: SpreadInputs,
+        advice: [Column<Advice>; NUM_ADVICE_COLS],
+    ) -> Self {
+        let message_word =
+            WordSumConfig::configure(meta, "message word", lookup.clone(), advice, 0, None);
+        let padding_constants = meta.fixed_column();
+        let padding_word = WordSumConfig::configure(
+            meta,
+            "padding word",
+            lookup.clone(),
+            advice,
+            0,
+            Some(padding_constants),
+        );
+        let sigma_0 = SigmaConfig::configure(
+            meta,
+            "sigma_0",
+            lookup.clone(),
+            advice,
+            [3, 4, 11, 14],
+            &[SigmaOp::RotateRight(7), SigmaOp::RotateRight(18), SigmaOp::ShiftRight(3)],
+        );
+        let sigma_1 = SigmaConfig::configure(
+            meta,
+            "sigma_1",
+            lookup.clone(),
+            advice,
+            [10, 7, 2, 13],
+            &[SigmaOp::RotateRight(17), SigmaOp::RotateRight(19), SigmaOp::ShiftRight(10)],
+        );
+        let schedule_word =
+            WordSumConfig::configure(meta, "schedule word", lookup, advice, 4, None);
+
+        MessageScheduleConfig {
+            message_word,
+            padding_word,
+            sigma_0,
+            sigma_1,
+            schedule_word,
+        }
+    }
+
+    /// Returns the `ROUNDS` words of the message schedule of the block
+    pub(super) fn process(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        input: [BlockWord; BLOCK_SIZE],
+    ) -> Result<[AssignedBits<F, 32>; ROUNDS], Error> {
+        let w = layouter.assign_region(
+            || "process message block",
+            |mut region| {
+                let mut w = Vec::<AssignedBits<F, 32>>::with_capacity(ROUNDS);
+                let mut row = 0;
+
+                for word in input.iter() {
+                    let word = self.message_word.assign_word(&mut region, row, word.0)?;
+                    row += self.message_word.rows();
+                    w.push(word.dense);
+                }
+
+                self.assign_schedule(&mut region, row, w)
+            },
+        )?;
+
+        Ok(w.try_into().unwrap())
+    }
+
+    /// Returns the `ROUNDS` words of the message schedule of a block which is already
+    /// assigned, e.g. by the padding gadget, whose words are range checked
+    pub(super) fn process_assigned(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        input: &[AssignedBits<F, 32>; BLOCK_SIZE],
+    ) -> Result<[AssignedBits<F, 32>; ROUNDS], Error> {
+        let w = layouter.assign_region(
+            || "process assigned block",
+            |mut region| self.assign_schedule(&mut region, 0, input.to_vec()),
+        )?;
+
+        Ok(w.try_into().unwrap())
+    }
+
+    /// Returns the `ROUNDS` words of the message schedule of the block which pads a 32-byte
+    /// message, e.g. the digest of another SHA-256 invocation. The message words are the
+    /// assigned words, which are already range checked, and the padding words are constants.
+    pub(super) fn process_digest(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        digest: &[AssignedBits<F, 32>; DIGEST_SIZE],
+    ) -> Result<[AssignedBits<F, 32>; ROUNDS], Error> {
+        // A one bit after the message and its bit length in the last word
+        let mut padding = [0u32; BLOCK_SIZE - DIGEST_SIZE];
+        padding[0] = 0x8000_0000;
+        padding[BLOCK_SIZE - DIGEST_SIZE - 1] = 32 * DIGEST_SIZE as u32;
+
+        let w = layouter.assign_region(
+            || "process digest block",
+            |mut region| {
+                let mut w = digest.to_vec();
+                let mut row = 0;
+
+                for word in padding {
+                    let word = self.padding_word.assign_sum(&mut region, row, &[], Some(word))?;
+                    row += self.padding_word.rows();
+                    w.push(word.dense);
+                }
+
+                self.assign_schedule(&mut region, row, w)
+            },
+        )?;
+
+        Ok(w.try_into().unwrap())
+    }
+
+    // Extends the `BLOCK_SIZE` words of a block to the message schedule, starting at `row`
+    fn assign_schedule(
+        &self,
+        region: &mut Region<'_, F>,
+        mut row: usize,
+        mut w: Vec<AssignedBits<F, 32>>,
+    ) -> Result<Vec<AssignedBits<F, 32>>, Error> {
+        for t in BLOCK_SIZE..ROUNDS {
+            let sigma_0 = self.sigma_0.assign(region, row, &w[t - 15])?;
+            row += SIGMA_ROWS;
+            let sigma_1 = self.sigma_1.assign(region, row, &w[t - 2])?;
+            row += SIGMA_ROWS;
+            let word = self.schedule_word.assign_sum(
+                region,
+                row,
+                &[&sigma_1.dense, &w[t - 7], &sigma_0.dense, &w[t - 16]],
+                None,
+            )?;
+            row += self.schedule_word.rows();
+            w.push(word.dense);
+        }
+
+        Ok(w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sha256::ref_impl::{message_schedule, BLOCK_SIZE};
+    use crate::sha256::table16::{BlockWord, Table16Chip, Table16Config};
+    use halo2_proofs::circuit::Value;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+    use halo2_proofs::halo2curves::pasta::pallas;
+
+    #[test]
+    fn test_message_schedule_process() {
+        struct MyCircuit {}
+
+        impl Circuit<pallas::Base> for MyCircuit {
+            type Config = Table16Config<pallas::Base>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit {}
+            }
+
+            fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+                Table16Chip::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<pallas::Base>,
+            ) -> Result<(), Error> {
+                Table16Chip::load(config.clone(), &mut layouter)?;
+
+                // Message block with distinct words, including ones with the top bit set
+                let mut input = [0u32; BLOCK_SIZE];
+                for (i, word) in input.iter_mut().enumerate() {
+                    *word = (i as u32 + 1).wrapping_mul(0x9e37_79b9);
+                }
+
+                let w = config.message_schedule.process(&mut layouter, input.map(|x| BlockWord(Value::known(x))))?;
+
+                let expected = message_schedule(input);
+                for (word, expected) in w.iter().zip(expected.iter()) {
+                    word.value_u32().assert_if_known(|v| v == expected);
+                }
+
+                Ok(())
+            }
+        }
+
+        let circuit: MyCircuit = MyCircuit {};
+
+        let prover = match MockProver::<pallas::Base>::run(17, &circuit, vec![]) {
+            Ok(prover) => prover,
+            Err(e) => panic!("{:?}", e),
+        };
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
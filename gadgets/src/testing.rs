@@ -0,0 +1,42 @@
+//! Helpers for running circuits in the MockProver in tests
+
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::dev::{MockProver, VerifyFailure};
+use halo2_proofs::plonk::Circuit;
+
+/// Runs the circuit in the MockProver and returns the constraint failures, if any.
+/// Panics if the circuit cannot be synthesized.
+pub(crate) fn run_and_verify<F: FieldExt, C: Circuit<F>>(
+    k: u32,
+    circuit: &C,
+    public_inputs: Vec<Vec<F>>,
+) -> Result<(), Vec<VerifyFailure>> {
+    let prover = match MockProver::run(k, circuit, public_inputs) {
+        Ok(prover) => prover,
+        Err(e) => panic!("{:?}", e),
+    };
+    prover.verify()
+}
+
+/// Asserts that the circuit is satisfied by the public inputs
+pub(crate) fn assert_proves<F: FieldExt, C: Circuit<F>>(
+    k: u32,
+    circuit: &C,
+    public_inputs: Vec<Vec<F>>,
+) {
+    if let Err(failures) = run_and_verify(k, circuit, public_inputs) {
+        panic!("Circuit was not satisfied: {:#?}", failures);
+    }
+}
+
+/// Asserts that the circuit is not satisfied by the public inputs and returns the failures
+pub(crate) fn assert_rejects<F: FieldExt, C: Circuit<F>>(
+    k: u32,
+    circuit: &C,
+    public_inputs: Vec<Vec<F>>,
+) -> Vec<VerifyFailure> {
+    match run_and_verify(k, circuit, public_inputs) {
+        Ok(()) => panic!("Circuit was unexpectedly satisfied"),
+        Err(failures) => failures,
+    }
+}